@@ -11,8 +11,8 @@ use iota_streams_app::{
 use iota_streams_app_channels::{
     api::tangle::{
         Address,
-        Author,
-        Subscriber,
+        AuthorBuilder,
+        SubscriberBuilder,
         Transport,
     },
     message,
@@ -23,14 +23,14 @@ use std::str::FromStr;
 
 fn example<T: Transport>(transport: &mut T, send_opt: T::SendOptions, recv_opt: T::RecvOptions) -> Fallible<()>
 where
-    T::SendOptions: Copy,
+    T::SendOptions: Clone,
     T::RecvOptions: Copy,
 {
-    let mut author = Author::new("AUTHOR9SEED", 2, true);
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").mss_height(2).build();
     println!("Channel address = {}", author.channel_address());
 
-    let mut subscriberA = Subscriber::new("SUBSCRIBERA9SEED", false);
-    let mut subscriberB = Subscriber::new("SUBSCRIBERB9SEED", true);
+    let mut subscriberA = SubscriberBuilder::new("SUBSCRIBERA9SEED").ntru(false).build();
+    let mut subscriberB = SubscriberBuilder::new("SUBSCRIBERB9SEED").build();
 
     let public_payload = Trytes(Tbits::from_str("PUBLICPAYLOAD").unwrap());
     let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
@@ -39,7 +39,7 @@ where
     let (announcement_address, announcement_tag) = {
         let msg = &author.announce()?;
         println!("  {}", msg.link.msgid);
-        transport.send_message_with_options(&msg, send_opt)?;
+        transport.send_message_with_options(&msg, send_opt.clone())?;
         (msg.link.appinst.to_string(), msg.link.msgid.to_string())
     };
     let announcement_link = Address::from_str(&announcement_address, &announcement_tag).unwrap();
@@ -66,7 +66,7 @@ where
     let signed_packet_link = {
         let msg = author.sign_packet(&announcement_link, &public_payload, &masked_payload)?;
         println!("  {}", msg.link.msgid);
-        transport.send_message_with_options(&msg, send_opt)?;
+        transport.send_message_with_options(&msg, send_opt.clone())?;
         msg.link.clone()
     };
     println!("  at {}", signed_packet_link.rel());
@@ -84,7 +84,7 @@ where
     let subscribeB_link = {
         let msg = subscriberB.subscribe(&announcement_link)?;
         println!("  {}", msg.link.msgid);
-        transport.send_message_with_options(&msg, send_opt)?;
+        transport.send_message_with_options(&msg, send_opt.clone())?;
         msg.link.clone()
     };
 
@@ -99,7 +99,7 @@ where
     let keyload_link = {
         let msg = author.share_keyload_for_everyone(&announcement_link)?;
         println!("  {}", msg.link.msgid);
-        transport.send_message_with_options(&msg, send_opt)?;
+        transport.send_message_with_options(&msg, send_opt.clone())?;
         msg.link
     };
 
@@ -116,7 +116,7 @@ where
     let tagged_packet_link = {
         let msg = author.tag_packet(&keyload_link, &public_payload, &masked_payload)?;
         println!("  {}", msg.link.msgid);
-        transport.send_message_with_options(&msg, send_opt)?;
+        transport.send_message_with_options(&msg, send_opt.clone())?;
         msg.link.clone()
     };
 
@@ -142,7 +142,7 @@ where
     let change_key_link = {
         let msg = author.change_key(&announcement_link)?;
         println!("  {}", msg.link.msgid);
-        transport.send_message_with_options(&msg, send_opt)?;
+        transport.send_message_with_options(&msg, send_opt.clone())?;
         msg.link
     };
 
@@ -157,7 +157,7 @@ where
     let unsubscribe_link = {
         let msg = subscriberB.unsubscribe(&subscribeB_link)?;
         println!("  {}", msg.link.msgid);
-        transport.send_message_with_options(&msg, send_opt)?;
+        transport.send_message_with_options(&msg, send_opt.clone())?;
         msg.link
     };
 
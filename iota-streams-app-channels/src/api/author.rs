@@ -4,16 +4,22 @@ use failure::{
     Fallible,
 };
 use std::{
-    cell::RefCell,
-    collections::{
-        HashMap,
-        HashSet,
+    cell::{
+        Cell,
+        RefCell,
     },
     fmt::Debug,
     str::FromStr,
 };
 
 use iota_streams_core::{
+    heap::HeapSize,
+    prelude::{
+        hash_map,
+        hash_set,
+        HashMap,
+        HashSet,
+    },
     prng,
     psk,
     sponge::spongos,
@@ -32,7 +38,10 @@ use iota_streams_core_mss::signature::mss;
 use iota_streams_core_ntru::key_encapsulation::ntru;
 
 use iota_streams_app::message::{
-    header::Header,
+    header::{
+        Header,
+        HeaderExt,
+    },
     *,
 };
 use iota_streams_protobuf3::types::*;
@@ -40,6 +49,12 @@ use iota_streams_protobuf3::types::*;
 use super::*;
 use crate::message::*;
 
+/// Size in trits of the counter used as PRNG nonce for keyload session keys.
+const KEYLOAD_COUNTER_SIZE: usize = 27;
+
+/// Number of session key commitments remembered for each NTRU recipient, older ones are forgotten.
+pub const KEM_HISTORY_SIZE: usize = 64;
+
 /// Generic Channel Author type parametrised by the type of links, link store and
 /// link generator.
 ///
@@ -72,14 +87,89 @@ where
     /// Own optional NTRU key pair.
     pub(crate) opt_ntru: Option<(ntru::PrivateKey<TW, F>, ntru::PublicKey<TW, F>)>,
 
-    /// Subscribers' pre-shared keys.
-    pub psks: psk::Psks<TW>,
+    /// Subscribers' pre-shared keys, ids are checked on insertion.
+    pub(crate) psks: psk::Psks<TW>,
 
     /// Subscribers' trusted NTRU public keys.
     pub ntru_pks: ntru::NtruPks<TW, F>,
 
+    /// Subscribers' access roles granted in keyloads.
+    pub roles: keyload::Roles<TW>,
+
+    /// Human-readable aliases of subscribers' NTRU public keys, eg. for logs.
+    pub(crate) aliases: HashMap<ntru::Pkid<TW>, String>,
+
+    /// Reveal keys of embargoed keyloads, they are kept secret until `RevealKey` message.
+    pub(crate) embargo_keys: psk::Psks<TW>,
+
+    /// NTRU public key ids of subscribers who requested a keyload in messages
+    /// handled with `handle_msg`. Author is to issue keyloads and clear the list.
+    pub keyload_requests: ntru::NtruPkids<TW>,
+
+    /// NTRU public key session keys of shared keyloads are escrowed to, eg. for audit access
+    /// in regulated deployments. Escrow is not published, the channel protocol is not affected.
+    pub escrow_pk: Option<ntru::PublicKey<TW, F>>,
+
+    /// Session keys escrowed to `escrow_pk` by keyload link, see `keyload::escrow_key`.
+    /// Author is to persist them and clear the list.
+    pub escrowed_keys: Vec<(Link, Tbits<TW>)>,
+
+    /// Counter of the last heartbeat, 0 if none has been published.
+    pub(crate) heartbeat_counter: usize,
+
+    /// Number of the last keyload epochs to keep in the link store. When set, links of
+    /// messages older than the last `keyload_epochs` keyloads are forgotten on rekey,
+    /// the announcement is kept. By default all links are kept.
+    pub keyload_epochs: Option<usize>,
+
+    /// Padding of keyload recipients hiding the number of subscribers, none by default.
+    pub recipient_padding: keyload::RecipientPadding,
+
+    /// Padding of masked payloads of signed and tagged packets, none by default.
+    pub payload_padding: payload_padding::PayloadPadding,
+
+    /// Application protocol version put in headers of new messages, 0 if not specified.
+    pub app_version: Trint3,
+
+    /// Check whether messages of the given application version can be handled,
+    /// incompatible messages are rejected. By default any version is accepted.
+    pub app_version_check: fn(Trint3) -> bool,
+
+    /// Maximum size in trytes of variable-length fields (eg. payloads) of handled messages,
+    /// unlimited if not set.
+    pub max_size: Option<usize>,
+
+    /// MAC size of lite packets announced in the channel, lite packets are not allowed if not set.
+    /// It must be set before the announcement.
+    pub lite_mac: Option<lite_packet::LiteMac>,
+
+    /// Access to the channel is granted with pre-shared keys only: own NTRU public key
+    /// is not announced, subscriptions are rejected and keyloads target pre-shared keys only.
+    /// It must be set before the announcement.
+    pub psk_only: bool,
+
+    /// Author set of a threshold channel, announcements and keyloads are signed with own
+    /// MSS key and `co_signers`. It must be set before the announcement.
+    pub(crate) author_set: Option<threshold_announce::AuthorSet<TW, P>>,
+
+    /// MSS private keys of other Author set members held by the Author.
+    pub(crate) co_signers: Vec<mss::PrivateKey<TW, P>>,
+
+    /// Check whether a session key and nonce may be encapsulated again for the NTRU recipient
+    /// with the given public key id, eg. after RNG failure. The same capsule is produced then,
+    /// linking keyloads together. Keyloads reusing them are refused unless the check returns true.
+    /// By default reuse is refused.
+    pub kem_reuse_check: fn(&ntru::Pkid<TW>) -> bool,
+
+    /// Commitments of the last `KEM_HISTORY_SIZE` session keys and nonces encapsulated
+    /// for each NTRU recipient, oldest first.
+    pub(crate) kem_history: HashMap<ntru::Pkid<TW>, Vec<NTrytes<TW>>>,
+
+    /// PRNG and counter generating session keys and nonces of keyloads, system RNG is used if not set.
+    pub(crate) keyload_prng: Option<(prng::Prng<TW, P::PrngG>, Cell<usize>)>,
+
     /// Link store.
-    store: RefCell<Store>,
+    pub(crate) store: RefCell<Store>,
 
     /// Link generator.
     pub(crate) link_gen: LinkGen,
@@ -129,6 +219,124 @@ where
 
             psks: HashMap::new(),
             ntru_pks: HashSet::new(),
+            roles: keyload::Roles::default(),
+            aliases: HashMap::new(),
+            embargo_keys: HashMap::new(),
+            keyload_requests: Vec::new(),
+            escrow_pk: None,
+            escrowed_keys: Vec::new(),
+            heartbeat_counter: 0,
+            keyload_epochs: None,
+            recipient_padding: keyload::RecipientPadding::None,
+            payload_padding: payload_padding::PayloadPadding::None,
+            app_version: Trint3(0),
+            app_version_check: accept_any_app_version,
+            max_size: None,
+            lite_mac: None,
+            psk_only: false,
+            author_set: None,
+            co_signers: Vec::new(),
+            kem_reuse_check: refuse_kem_reuse,
+            kem_history: HashMap::new(),
+            keyload_prng: None,
+
+            store: RefCell::new(store),
+            link_gen: link_gen,
+            appinst: appinst,
+        }
+    }
+
+    /// Create Author taking over the channel `appinst` with MSS private key `mss_sk`
+    /// handed over by the previous channel owner.
+    pub(crate) fn from_handover(
+        store: Store,
+        link_gen: LinkGen,
+        prng: prng::Prng<TW, P::PrngG>,
+        mss_sk: mss::PrivateKey<TW, P>,
+        opt_ntru: Option<(ntru::PrivateKey<TW, F>, ntru::PublicKey<TW, F>)>,
+        appinst: Link,
+    ) -> Self {
+        Self {
+            prng: prng,
+            default_mss_height: mss_sk.height(),
+            mss_sk: mss_sk,
+            opt_ntru: opt_ntru,
+
+            psks: HashMap::new(),
+            ntru_pks: HashSet::new(),
+            roles: keyload::Roles::default(),
+            aliases: HashMap::new(),
+            embargo_keys: HashMap::new(),
+            keyload_requests: Vec::new(),
+            escrow_pk: None,
+            escrowed_keys: Vec::new(),
+            heartbeat_counter: 0,
+            keyload_epochs: None,
+            recipient_padding: keyload::RecipientPadding::None,
+            payload_padding: payload_padding::PayloadPadding::None,
+            app_version: Trint3(0),
+            app_version_check: accept_any_app_version,
+            max_size: None,
+            lite_mac: None,
+            psk_only: false,
+            author_set: None,
+            co_signers: Vec::new(),
+            kem_reuse_check: refuse_kem_reuse,
+            kem_history: HashMap::new(),
+            keyload_prng: None,
+
+            store: RefCell::new(store),
+            link_gen: link_gen,
+            appinst: appinst,
+        }
+    }
+
+    /// Create Author of a new channel split off this one. MSS key pair is generated with
+    /// `nonce`, so the new channel has its own address. Own NTRU key pair, subscribers' keys,
+    /// roles, aliases and settings are carried over: subscribers can be given access to the new
+    /// channel without subscribing again. Author set is not carried over, keyload PRNG is reseeded
+    /// with `nonce` so the channels don't share session keys.
+    pub fn split<SplitStore>(
+        &self,
+        store: SplitStore,
+        mut link_gen: LinkGen,
+        nonce: &Tbits<TW>,
+    ) -> AuthorT<TW, F, P, Link, SplitStore, LinkGen> {
+        let mss_sk = mss::PrivateKey::<TW, P>::gen(&self.prng, nonce.slice(), self.default_mss_height);
+        let appinst = link_gen.link_from(mss_sk.public_key());
+
+        AuthorT {
+            prng: self.prng.clone(),
+            default_mss_height: self.default_mss_height,
+            mss_sk: mss_sk,
+            opt_ntru: self.opt_ntru.clone(),
+
+            psks: self.psks.clone(),
+            ntru_pks: self.ntru_pks.clone(),
+            roles: self.roles.clone(),
+            aliases: self.aliases.clone(),
+            embargo_keys: HashMap::new(),
+            keyload_requests: Vec::new(),
+            escrow_pk: self.escrow_pk.clone(),
+            escrowed_keys: Vec::new(),
+            heartbeat_counter: 0,
+            keyload_epochs: self.keyload_epochs,
+            recipient_padding: self.recipient_padding,
+            payload_padding: self.payload_padding,
+            app_version: self.app_version,
+            app_version_check: self.app_version_check,
+            max_size: self.max_size,
+            lite_mac: self.lite_mac,
+            psk_only: self.psk_only,
+            author_set: None,
+            co_signers: Vec::new(),
+            kem_reuse_check: self.kem_reuse_check,
+            kem_history: self.kem_history.clone(),
+            keyload_prng: self.keyload_prng.as_ref().map(|(prng, _)| {
+                let nonce = &Tbits::from_str("SPLIT").unwrap() + nonce;
+                let key = prng.gen_tbits(&nonce, prng::Prng::<TW, P::PrngG>::KEY_SIZE);
+                (prng::Prng::init(key), Cell::new(0))
+            }),
 
             store: RefCell::new(store),
             link_gen: link_gen,
@@ -136,15 +344,69 @@ where
         }
     }
 
+    /// Give a subscribed NTRU public key an alias replacing the previous one. Aliases are unique.
+    pub fn set_alias(&mut self, ntru_pkid: ntru::Pkid<TW>, alias: &str) -> Fallible<()> {
+        ensure!(!alias.is_empty(), "Alias is empty.");
+        ensure!(
+            self.ntru_pks.iter().any(|ntru_pk| ntru_pk.cmp_pkid(&ntru_pkid)),
+            "NTRU public key is not subscribed: {}.",
+            ntru_pkid
+        );
+        ensure!(
+            self.aliases.iter().all(|(pkid, a)| a != alias || *pkid == ntru_pkid),
+            "Alias is already taken: {}.",
+            alias
+        );
+        self.aliases.insert(ntru_pkid, alias.to_string());
+        Ok(())
+    }
+
+    pub fn remove_alias(&mut self, ntru_pkid: &ntru::Pkid<TW>) -> Option<String> {
+        self.aliases.remove(ntru_pkid)
+    }
+
+    pub fn alias(&self, ntru_pkid: &ntru::Pkid<TW>) -> Option<&str> {
+        self.aliases.get(ntru_pkid).map(|alias| alias.as_str())
+    }
+
+    /// Subscribed NTRU public key with the alias.
+    pub fn ntru_pk_by_alias(&self, alias: &str) -> Option<&ntru::PublicKey<TW, F>> {
+        let (ntru_pkid, _) = self.aliases.iter().find(|(_, a)| *a == alias)?;
+        self.ntru_pks.iter().find(|ntru_pk| ntru_pk.cmp_pkid(ntru_pkid))
+    }
+
+    /// Stored pre-shared key with the id.
+    pub fn psk(&self, pskid: &psk::PskId<TW>) -> Option<&psk::Psk<TW>> {
+        self.psks.get(pskid)
+    }
+
+    /// Ids of stored pre-shared keys.
+    pub fn psk_ids(&self) -> impl Iterator<Item = &psk::PskId<TW>> {
+        self.psks.keys()
+    }
+
     /// Prepare Announcement message.
     pub fn prepare_announcement<'a>(
         &'a mut self,
     ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, announce::ContentWrap<TW, F, P>>> {
+        ensure!(
+            self.author_set.is_none(),
+            "Channel governed by Author set must be announced with ThresholdAnnounce."
+        );
         // Create Header for the first message in the channel.
-        let header = self.link_gen.header_from(self.mss_sk.public_key(), announce::TYPE);
+        let header = self
+            .link_gen
+            .header_from(self.mss_sk.public_key(), announce::TYPE)
+            .with_app_version(self.app_version);
         let content = announce::ContentWrap {
             mss_sk: &self.mss_sk,
-            ntru_pk: self.opt_ntru.as_ref().map(|key_pair| &key_pair.1),
+            ntru_pk: if self.psk_only {
+                None
+            } else {
+                self.opt_ntru.as_ref().map(|key_pair| &key_pair.1)
+            },
+            link_version: self.link_gen.link_version(),
+            lite_mac: lite_packet::LiteMac::to_trint3(self.lite_mac),
         };
         Ok(PreparedMessage::new(self.store.borrow(), header, content))
     }
@@ -158,15 +420,97 @@ where
         wrapped.commit(self.store.borrow_mut(), info)
     }
 
+    /// Govern the channel by Author set of own MSS public key followed by `mss_pks`,
+    /// announcements and keyloads must be signed by at least `threshold` keys of the set.
+    /// `co_signers` are private keys of other members held by the Author, they sign along
+    /// with own key. It must be set before the announcement.
+    pub fn set_author_set(
+        &mut self,
+        threshold: usize,
+        mss_pks: Vec<mss::PublicKey<TW, P>>,
+        co_signers: Vec<mss::PrivateKey<TW, P>>,
+    ) -> Fallible<()> {
+        let mut set_pks = vec![self.mss_sk.public_key().clone()];
+        set_pks.extend(mss_pks);
+        let author_set = threshold_announce::AuthorSet::new(threshold, set_pks)?;
+        let mut indices = vec![0];
+        for co_signer in &co_signers {
+            match author_set.index_of(co_signer.public_key()) {
+                Some(i) if !indices.contains(&i) => indices.push(i),
+                Some(_) => bail!("Duplicate co-signer's MSS private key."),
+                None => bail!("Co-signer's MSS public key is not in Author set."),
+            }
+        }
+        ensure!(
+            threshold <= indices.len(),
+            "Not enough co-signers to meet threshold: {} of {}.",
+            indices.len(),
+            threshold
+        );
+        self.author_set = Some(author_set);
+        self.co_signers = co_signers;
+        Ok(())
+    }
+
+    /// Author set and its members signing with own key and co-signers.
+    fn threshold_signers(
+        &self,
+    ) -> Fallible<(&threshold_announce::AuthorSet<TW, P>, threshold_announce::Signers<'_, TW, P>)> {
+        match &self.author_set {
+            Some(author_set) => {
+                let mut signers = vec![(0, &self.mss_sk)];
+                for co_signer in &self.co_signers {
+                    // Co-signers have been checked to be in the set.
+                    signers.push((author_set.index_of(co_signer.public_key()).unwrap(), co_signer));
+                }
+                Ok((author_set, signers))
+            }
+            None => bail!("Channel is not governed by Author set."),
+        }
+    }
+
+    /// Prepare ThresholdAnnounce message.
+    pub fn prepare_threshold_announcement<'a>(
+        &'a mut self,
+    ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, threshold_announce::ContentWrap<'a, TW, F, P>>> {
+        let header = self
+            .link_gen
+            .header_from(self.mss_sk.public_key(), threshold_announce::TYPE)
+            .with_app_version(self.app_version);
+        let (author_set, signers) = self.threshold_signers()?;
+        let content = threshold_announce::ContentWrap {
+            author_set,
+            signers,
+            ntru_pk: if self.psk_only {
+                None
+            } else {
+                self.opt_ntru.as_ref().map(|key_pair| &key_pair.1)
+            },
+            link_version: self.link_gen.link_version(),
+            lite_mac: lite_packet::LiteMac::to_trint3(self.lite_mac),
+        };
+        Ok(PreparedMessage::new(self.store.borrow(), header, content))
+    }
+
+    /// Create ThresholdAnnounce message of a channel governed by Author set.
+    pub fn threshold_announce(
+        &mut self,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        let wrapped = self.prepare_threshold_announcement()?.wrap()?;
+        wrapped.commit(self.store.borrow_mut(), info)
+    }
+
     /// Prepare ChangeKey message: generate new MSS key pair.
     pub fn prepare_change_key<'a>(
         &'a mut self,
         link_to: &'a <Link as HasLink>::Rel,
     ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, change_key::ContentWrap<'a, TW, P, Link>>> {
+        ensure!(self.author_set.is_none(), "Author set members can't change keys.");
         let mss_nonce = self.mss_sk.nonce().clone();
         let mss_sk = mss::PrivateKey::gen(&self.prng, mss_nonce.slice(), self.default_mss_height);
 
-        let header = self.link_gen.header_from(link_to, change_key::TYPE);
+        let header = self.link_gen.header_from(link_to, change_key::TYPE).with_app_version(self.app_version);
 
         let content = change_key::ContentWrap::new(link_to, mss_sk, &self.mss_sk);
         Ok(PreparedMessage::new(self.store.borrow(), header, content))
@@ -191,6 +535,111 @@ where
         wrapped.commit(self.store.borrow_mut(), info)
     }
 
+    /// Prepare Handover message.
+    pub fn prepare_handover<'a>(
+        &'a mut self,
+        link_to: &'a <Link as HasLink>::Rel,
+        successor_mss_pk: &'a mss::PublicKey<TW, P>,
+        successor_sig: &Tbits<TW>,
+    ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, handover::ContentWrap<'a, TW, P, Link>>> {
+        ensure!(self.author_set.is_none(), "Channel governed by Author set can't be handed over.");
+        let acceptance = handover::acceptance::<TW, F, P, Link>(&self.appinst, successor_mss_pk)?;
+        ensure!(
+            successor_mss_pk.verify_tbits(&acceptance.0, successor_sig),
+            "Bad successor's acceptance signature."
+        );
+        let header = self.link_gen.header_from(link_to, handover::TYPE).with_app_version(self.app_version);
+        let content = handover::ContentWrap {
+            link: link_to,
+            mss_pk: successor_mss_pk,
+            mss_sig: NTrytes(successor_sig.clone()),
+            mss_linked_sk: &self.mss_sk,
+            _phantom: std::marker::PhantomData,
+        };
+        Ok(PreparedMessage::new(self.store.borrow(), header, content))
+    }
+
+    /// Hand the channel over to a successor owning MSS public key `successor_mss_pk`.
+    /// `successor_sig` is the successor's signature produced with `handover::sign_acceptance`.
+    /// Subscribers trust the successor's key after the message, the current MSS key pair
+    /// must not be used afterwards.
+    pub fn handover(
+        &mut self,
+        link_to: &<Link as HasLink>::Rel,
+        successor_mss_pk: &mss::PublicKey<TW, P>,
+        successor_sig: &Tbits<TW>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        let wrapped = self
+            .prepare_handover(link_to, successor_mss_pk, successor_sig)?
+            .wrap()?;
+        wrapped.commit(self.store.borrow_mut(), info)
+    }
+
+    /// Generate session keys and nonces of keyloads with `prng` instead of system RNG,
+    /// eg. for reproducible tests. `None` switches back to system RNG.
+    pub fn set_keyload_prng(&mut self, prng: Option<prng::Prng<TW, P::PrngG>>) {
+        self.keyload_prng = prng.map(|prng| (prng, Cell::new(0)));
+    }
+
+    /// Generate nonce and session key of a new keyload.
+    fn gen_session_key(&self) -> (NTrytes<TW>, NTrytes<TW>) {
+        let nonce_size = spongos::Spongos::<TW, F>::NONCE_SIZE;
+        let key_size = spongos::Spongos::<TW, F>::KEY_SIZE;
+        match &self.keyload_prng {
+            Some((prng, counter)) => {
+                let mut n = Tbits::<TW>::zero(KEYLOAD_COUNTER_SIZE);
+                n.slice_mut().put_usize(counter.get());
+                counter.set(counter.get() + 1);
+                let rnd = prng.gen_tbits(&n, nonce_size + key_size);
+                (
+                    NTrytes(Tbits::from_slice(rnd.slice().take(nonce_size))),
+                    NTrytes(Tbits::from_slice(rnd.slice().drop(nonce_size))),
+                )
+            }
+            None => (
+                NTrytes(prng::random_nonce(nonce_size)),
+                NTrytes(prng::random_key(key_size)),
+            ),
+        }
+    }
+
+    /// Check session key and nonce to be encapsulated for NTRU recipients `ntru_pks`,
+    /// reuse for a recipient is refused unless allowed by `kem_reuse_check`.
+    fn check_kem<'a, NtruPks>(&self, nonce: &NTrytes<TW>, key: &NTrytes<TW>, ntru_pks: NtruPks) -> Fallible<()>
+    where
+        NtruPks: Iterator<Item = ntru::INtruPk<'a, TW, F>>,
+        TW: 'a,
+        F: 'a,
+    {
+        let commitment = keyload::key_commitment::<TW, F>(nonce, key);
+        for ntru_pk in ntru_pks {
+            let pkid = ntru_pk.get_pkid();
+            if self.kem_history.get(&pkid).map_or(false, |used| used.contains(&commitment)) {
+                ensure!(
+                    (self.kem_reuse_check)(&pkid),
+                    "Session key and nonce were already encapsulated for NTRU public key id {}.",
+                    pkid
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Keyload has been wrapped, record session key and nonce encapsulated for NTRU recipients `ntru_pkids`.
+    fn record_kem(&mut self, nonce: &NTrytes<TW>, key: &NTrytes<TW>, ntru_pkids: Vec<ntru::Pkid<TW>>) {
+        let commitment = keyload::key_commitment::<TW, F>(nonce, key);
+        for pkid in ntru_pkids {
+            let used = self.kem_history.entry(pkid).or_default();
+            if !used.contains(&commitment) {
+                if used.len() == KEM_HISTORY_SIZE {
+                    used.remove(0);
+                }
+                used.push(commitment.clone());
+            }
+        }
+    }
+
     fn do_prepare_keyload<'a, Psks, NtruPks>(
         &'a self,
         header: Header<TW, Link>,
@@ -202,18 +651,37 @@ where
         Psks: Clone + ExactSizeIterator<Item = psk::IPsk<'a, TW>>,
         NtruPks: Clone + ExactSizeIterator<Item = ntru::INtruPk<'a, TW, F>>,
     {
-        let nonce = NTrytes(prng::random_nonce(spongos::Spongos::<TW, F>::NONCE_SIZE));
-        let key = NTrytes(prng::random_key(spongos::Spongos::<TW, F>::KEY_SIZE));
-        let content = keyload::ContentWrap {
+        let content = self.keyload_content(link_to, psks, ntru_pks)?;
+        Ok(PreparedMessage::new(self.store.borrow(), header, content))
+    }
+
+    fn keyload_content<'a, Psks, NtruPks>(
+        &'a self,
+        link_to: &'a <Link as HasLink>::Rel,
+        psks: Psks,
+        ntru_pks: NtruPks,
+    ) -> Fallible<keyload::ContentWrap<'a, TW, F, P::PrngG, Link, Psks, NtruPks>>
+    where
+        Psks: Clone + ExactSizeIterator<Item = psk::IPsk<'a, TW>>,
+        NtruPks: Clone + ExactSizeIterator<Item = ntru::INtruPk<'a, TW, F>>,
+    {
+        let (nonce, key) = self.gen_session_key();
+        self.check_kem(&nonce, &key, ntru_pks.clone())?;
+        let mut content = keyload::ContentWrap {
             link: link_to,
             nonce: nonce,
             key: key,
             psks: psks,
             prng: &self.prng,
             ntru_pks: ntru_pks,
+            roles: Some(&self.roles),
+            embargo: None,
+            dummy_psks: Vec::new(),
+            dummy_ntru_pks: Vec::new(),
             _phantom: std::marker::PhantomData,
         };
-        Ok(PreparedMessage::new(self.store.borrow(), header, content))
+        content.pad_recipients(self.recipient_padding)?;
+        Ok(content)
     }
 
     pub fn prepare_keyload<'a>(
@@ -239,7 +707,11 @@ where
             >,
         >,
     > {
-        let header = self.link_gen.header_from(link_to, keyload::TYPE);
+        ensure!(
+            !self.psk_only || ntru_pkids.is_empty(),
+            "Keyloads of PSK-only channel can't target NTRU public keys."
+        );
+        let header = self.link_gen.header_from(link_to, keyload::TYPE).with_app_version(self.app_version);
         let psks = psk::filter_psks(&self.psks, psk_ids);
         let ntru_pks = ntru::filter_ntru_pks(&self.ntru_pks, ntru_pkids);
         self.do_prepare_keyload(header, link_to, psks.into_iter(), ntru_pks.into_iter())
@@ -261,19 +733,97 @@ where
                 F,
                 P::PrngG,
                 Link,
-                std::collections::hash_map::Iter<psk::PskId<TW>, psk::Psk<TW>>,
-                std::collections::hash_set::Iter<ntru::PublicKey<TW, F>>,
+                hash_map::Iter<psk::PskId<TW>, psk::Psk<TW>>,
+                hash_set::Iter<ntru::PublicKey<TW, F>>,
             >,
         >,
     > {
-        let header = self.link_gen.header_from(link_to, keyload::TYPE);
+        let header = self.link_gen.header_from(link_to, keyload::TYPE).with_app_version(self.app_version);
         let ipsks = self.psks.iter();
         let intru_pks = self.ntru_pks.iter();
         self.do_prepare_keyload(header, link_to, ipsks, intru_pks)
     }
 
+    fn do_prepare_threshold_keyload<'a>(
+        &'a self,
+        header: Header<TW, Link>,
+        link_to: &'a <Link as HasLink>::Rel,
+        psks: Vec<psk::IPsk<'a, TW>>,
+        ntru_pks: Vec<ntru::INtruPk<'a, TW, F>>,
+    ) -> Fallible<
+        PreparedMessage<
+            'a,
+            TW,
+            F,
+            Link,
+            Store,
+            threshold_keyload::ContentWrap<
+                'a,
+                TW,
+                P,
+                keyload::ContentWrap<
+                    'a,
+                    TW,
+                    F,
+                    P::PrngG,
+                    Link,
+                    std::vec::IntoIter<psk::IPsk<'a, TW>>,
+                    std::vec::IntoIter<ntru::INtruPk<'a, TW, F>>,
+                >,
+            >,
+        >,
+    > {
+        ensure!(
+            !self.psk_only || ntru_pks.is_empty(),
+            "Keyloads of PSK-only channel can't target NTRU public keys."
+        );
+        let (_, signers) = self.threshold_signers()?;
+        let keyload = self.keyload_content(link_to, psks.into_iter(), ntru_pks.into_iter())?;
+        let content = threshold_keyload::ContentWrap { keyload, signers };
+        Ok(PreparedMessage::new(self.store.borrow(), header, content))
+    }
+
+    /// Prepare Keyload message of a channel governed by Author set, see `prepare_keyload`.
+    pub fn prepare_threshold_keyload<'a>(
+        &'a mut self,
+        link_to: &'a <Link as HasLink>::Rel,
+        psk_ids: &psk::PskIds<TW>,
+        ntru_pkids: &ntru::NtruPkids<TW>,
+    ) -> Fallible<
+        PreparedMessage<
+            'a,
+            TW,
+            F,
+            Link,
+            Store,
+            threshold_keyload::ContentWrap<
+                'a,
+                TW,
+                P,
+                keyload::ContentWrap<
+                    'a,
+                    TW,
+                    F,
+                    P::PrngG,
+                    Link,
+                    std::vec::IntoIter<psk::IPsk<'a, TW>>,
+                    std::vec::IntoIter<ntru::INtruPk<'a, TW, F>>,
+                >,
+            >,
+        >,
+    > {
+        let header = self
+            .link_gen
+            .header_from(link_to, threshold_keyload::TYPE)
+            .with_app_version(self.app_version);
+        let psks = psk::filter_psks(&self.psks, psk_ids);
+        let ntru_pks = ntru::filter_ntru_pks(&self.ntru_pks, ntru_pkids);
+        self.do_prepare_threshold_keyload(header, link_to, psks, ntru_pks)
+    }
+
     /// Create keyload message with a new session key shared with recipients
-    /// identified by pre-shared key IDs and by NTRU public key IDs.
+    /// identified by pre-shared key IDs and by NTRU public key IDs. Keyload of a channel
+    /// governed by Author set is signed by the set members.
     pub fn share_keyload(
         &mut self,
         link_to: &<Link as HasLink>::Rel,
@@ -281,7 +831,22 @@ where
         ntru_pkids: &ntru::NtruPkids<TW>,
         info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
     ) -> Fallible<TbinaryMessage<TW, F, Link>> {
-        let wrapped = self.prepare_keyload(link_to, psk_ids, ntru_pkids)?.wrap()?;
+        let (wrapped, nonce, key, ntru_pkids) = if self.author_set.is_some() {
+            let prepared = self.prepare_threshold_keyload(link_to, psk_ids, ntru_pkids)?;
+            let keyload = &prepared.content.keyload;
+            (prepared.wrap()?, keyload.nonce.clone(), keyload.key.clone(), keyload.ntru_pkids())
+        } else {
+            let prepared = self.prepare_keyload(link_to, psk_ids, ntru_pkids)?;
+            (
+                prepared.wrap()?,
+                prepared.content.nonce.clone(),
+                prepared.content.key.clone(),
+                prepared.content.ntru_pkids(),
+            )
+        };
+        self.escrow_session_key(&wrapped.message.link, &nonce, &key);
+        self.record_kem(&nonce, &key, ntru_pkids);
+        self.start_keyload_epoch();
         wrapped.commit(self.store.borrow_mut(), info)
     }
 
@@ -292,39 +857,419 @@ where
         link_to: &<Link as HasLink>::Rel,
         info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
     ) -> Fallible<TbinaryMessage<TW, F, Link>> {
-        let wrapped = self.prepare_keyload_for_everyone(link_to)?.wrap()?;
+        let (wrapped, nonce, key, ntru_pkids) = if self.author_set.is_some() {
+            let header = self
+                .link_gen
+                .header_from(link_to, threshold_keyload::TYPE)
+                .with_app_version(self.app_version);
+            let psks = self.psks.iter().collect();
+            let ntru_pks = self.ntru_pks.iter().collect();
+            let prepared = self.do_prepare_threshold_keyload(header, link_to, psks, ntru_pks)?;
+            let keyload = &prepared.content.keyload;
+            (prepared.wrap()?, keyload.nonce.clone(), keyload.key.clone(), keyload.ntru_pkids())
+        } else {
+            let prepared = self.prepare_keyload_for_everyone(link_to)?;
+            (
+                prepared.wrap()?,
+                prepared.content.nonce.clone(),
+                prepared.content.key.clone(),
+                prepared.content.ntru_pkids(),
+            )
+        };
+        self.escrow_session_key(&wrapped.message.link, &nonce, &key);
+        self.record_kem(&nonce, &key, ntru_pkids);
+        self.start_keyload_epoch();
         wrapped.commit(self.store.borrow_mut(), info)
     }
 
-    /// Prepare SignedPacket message.
-    pub fn prepare_signed_packet<'a>(
+    /// Escrow session key of keyload at `link` if escrow public key is set.
+    fn escrow_session_key(&mut self, link: &Link, nonce: &NTrytes<TW>, key: &NTrytes<TW>) {
+        if let Some(escrow_pk) = &self.escrow_pk {
+            let escrow = keyload::escrow_key(escrow_pk, &self.prng, nonce, key);
+            self.escrowed_keys.push((link.clone(), escrow));
+        }
+    }
+
+    /// Keyload has been wrapped, forget links from old keyload epochs.
+    fn start_keyload_epoch(&mut self) {
+        if let Some(keep) = self.keyload_epochs {
+            self.store.borrow_mut().start_epoch(keep);
+        }
+    }
+
+    /// Prepare embargoed Keyload message, the reveal key with id `reveal_key_id` is generated
+    /// unless it already exists.
+    pub fn prepare_keyload_embargoed<'a>(
         &'a mut self,
         link_to: &'a <Link as HasLink>::Rel,
-        public_payload: &'a Trytes<TW>,
-        masked_payload: &'a Trytes<TW>,
-    ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, signed_packet::ContentWrap<'a, TW, F, P, Link>>> {
-        let header = self.link_gen.header_from(link_to, signed_packet::TYPE);
-        let content = signed_packet::ContentWrap {
-            link: link_to,
-            public_payload: public_payload,
-            masked_payload: masked_payload,
-            mss_sk: &self.mss_sk,
-            _phantom: std::marker::PhantomData,
-        };
-        Ok(PreparedMessage::new(self.store.borrow(), header, content))
+        psk_ids: &psk::PskIds<TW>,
+        ntru_pkids: &ntru::NtruPkids<TW>,
+        reveal_key_id: &psk::PskId<TW>,
+    ) -> Fallible<
+        PreparedMessage<
+            'a,
+            TW,
+            F,
+            Link,
+            Store,
+            keyload::ContentWrap<
+                'a,
+                TW,
+                F,
+                P::PrngG,
+                Link,
+                std::vec::IntoIter<psk::IPsk<'a, TW>>,
+                std::vec::IntoIter<ntru::INtruPk<'a, TW, F>>,
+            >,
+        >,
+    > {
+        ensure!(self.author_set.is_none(), "Keyloads of channel governed by Author set can't be embargoed.");
+        ensure!(
+            reveal_key_id.size() == psk::PSKID_SIZE,
+            "Bad reveal key id size: {}.",
+            reveal_key_id.size()
+        );
+        if !self.embargo_keys.contains_key(reveal_key_id) {
+            let reveal_key = prng::random_key(psk::PSK_SIZE);
+            self.embargo_keys.insert(reveal_key_id.clone(), reveal_key);
+        }
+        ensure!(
+            !self.psk_only || ntru_pkids.is_empty(),
+            "Keyloads of PSK-only channel can't target NTRU public keys."
+        );
+        let header = self.link_gen.header_from(link_to, keyload::EMBARGOED_TYPE).with_app_version(self.app_version);
+        let psks = psk::filter_psks(&self.psks, psk_ids);
+        let ntru_pks = ntru::filter_ntru_pks(&self.ntru_pks, ntru_pkids);
+        let mut prepared = self.do_prepare_keyload(header, link_to, psks.into_iter(), ntru_pks.into_iter())?;
+        prepared.content.embargo = self.embargo_keys.get_key_value(reveal_key_id);
+        Ok(prepared)
     }
 
-    /// Create a signed message with public and masked payload.
-    pub fn sign_packet(
+    /// Create keyload message with a new session key shared with recipients identified
+    /// by pre-shared key IDs and by NTRU public key IDs. The recipients can unwrap
+    /// the keyload only after the reveal key is published with `reveal_key`.
+    pub fn share_keyload_embargoed(
         &mut self,
         link_to: &<Link as HasLink>::Rel,
-        public_payload: &Trytes<TW>,
-        masked_payload: &Trytes<TW>,
+        psk_ids: &psk::PskIds<TW>,
+        ntru_pkids: &ntru::NtruPkids<TW>,
+        reveal_key_id: &psk::PskId<TW>,
         info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
     ) -> Fallible<TbinaryMessage<TW, F, Link>> {
-        let wrapped = self
-            .prepare_signed_packet(link_to, public_payload, masked_payload)?
-            .wrap()?;
+        let (wrapped, nonce, key, ntru_pkids) = {
+            let prepared = self.prepare_keyload_embargoed(link_to, psk_ids, ntru_pkids, reveal_key_id)?;
+            (
+                prepared.wrap()?,
+                prepared.content.nonce.clone(),
+                prepared.content.key.clone(),
+                prepared.content.ntru_pkids(),
+            )
+        };
+        self.escrow_session_key(&wrapped.message.link, &nonce, &key);
+        self.record_kem(&nonce, &key, ntru_pkids);
+        self.start_keyload_epoch();
+        wrapped.commit(self.store.borrow_mut(), info)
+    }
+
+    /// Prepare RevealKey message.
+    pub fn prepare_reveal_key<'a>(
+        &'a mut self,
+        link_to: &'a <Link as HasLink>::Rel,
+        reveal_key_id: &psk::PskId<TW>,
+    ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, reveal_key::ContentWrap<'a, TW, F, P, Link>>> {
+        let reveal_key = match self.embargo_keys.get(reveal_key_id) {
+            Some(reveal_key) => reveal_key.clone(),
+            None => bail!("Reveal key not found: {}.", reveal_key_id),
+        };
+        let header = self.link_gen.header_from(link_to, reveal_key::TYPE).with_app_version(self.app_version);
+        let content = reveal_key::ContentWrap {
+            link: link_to,
+            reveal_key_id: NTrytes(reveal_key_id.clone()),
+            reveal_key: NTrytes(reveal_key),
+            mss_sk: &self.mss_sk,
+            _phantom: std::marker::PhantomData,
+        };
+        Ok(PreparedMessage::new(self.store.borrow(), header, content))
+    }
+
+    /// Publish reveal key with id `reveal_key_id` and lift embargo of the keyloads locked with it.
+    pub fn reveal_key(
+        &mut self,
+        link_to: &<Link as HasLink>::Rel,
+        reveal_key_id: &psk::PskId<TW>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        let wrapped = self.prepare_reveal_key(link_to, reveal_key_id)?.wrap()?;
+        wrapped.commit(self.store.borrow_mut(), info)
+    }
+
+    /// Prepare Rekey message with a new session key.
+    pub fn prepare_rekey<'a>(
+        &'a mut self,
+        link_to: &'a <Link as HasLink>::Rel,
+    ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, rekey::ContentWrap<'a, TW, F, Link>>> {
+        let header = self.link_gen.header_from(link_to, rekey::TYPE).with_app_version(self.app_version);
+        let (nonce, key) = self.gen_session_key();
+        let content = rekey::ContentWrap {
+            link: link_to,
+            nonce,
+            key,
+            _phantom: std::marker::PhantomData,
+        };
+        Ok(PreparedMessage::new(self.store.borrow(), header, content))
+    }
+
+    /// Refresh session key of keyload (or rekey) `link_to` for the same recipients.
+    /// Unlike a new keyload, the cost doesn't depend on the number of recipients.
+    pub fn rekey(
+        &mut self,
+        link_to: &<Link as HasLink>::Rel,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        let wrapped = self.prepare_rekey(link_to)?.wrap()?;
+        self.start_keyload_epoch();
+        wrapped.commit(self.store.borrow_mut(), info)
+    }
+
+    /// Prepare Heartbeat message, the heartbeat counter is incremented.
+    pub fn prepare_heartbeat<'a>(
+        &'a mut self,
+        link_to: &'a <Link as HasLink>::Rel,
+        timestamp: usize,
+    ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, heartbeat::ContentWrap<'a, TW, F, P, Link>>> {
+        self.heartbeat_counter += 1;
+        let header = self.link_gen.header_from(link_to, heartbeat::TYPE).with_app_version(self.app_version);
+        let content = heartbeat::ContentWrap {
+            link: link_to,
+            counter: Size(self.heartbeat_counter),
+            timestamp: Size(timestamp),
+            mss_sk: &self.mss_sk,
+            _phantom: std::marker::PhantomData,
+        };
+        Ok(PreparedMessage::new(self.store.borrow(), header, content))
+    }
+
+    /// Publish a heartbeat with `timestamp` in seconds since UNIX epoch.
+    pub fn heartbeat(
+        &mut self,
+        link_to: &<Link as HasLink>::Rel,
+        timestamp: usize,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        let wrapped = self.prepare_heartbeat(link_to, timestamp)?.wrap()?;
+        wrapped.commit(self.store.borrow_mut(), info)
+    }
+
+    /// Prepare CloseBranch message.
+    pub fn prepare_close_branch<'a>(
+        &'a mut self,
+        link_to: &'a <Link as HasLink>::Rel,
+    ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, close_branch::ContentWrap<'a, TW, F, P, Link>>> {
+        let header = self.link_gen.header_from(link_to, close_branch::TYPE).with_app_version(self.app_version);
+        let content = close_branch::ContentWrap {
+            link: link_to,
+            mss_sk: &self.mss_sk,
+            _phantom: std::marker::PhantomData,
+        };
+        Ok(PreparedMessage::new(self.store.borrow(), header, content))
+    }
+
+    /// Publish the last message of the branch `link_to` belongs to.
+    pub fn close_branch(
+        &mut self,
+        link_to: &<Link as HasLink>::Rel,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        let wrapped = self.prepare_close_branch(link_to)?.wrap()?;
+        wrapped.commit(self.store.borrow_mut(), info)
+    }
+
+    /// Derive MSS private key signing messages of a branch from the seed, keys derived
+    /// with different labels are independent of each other and of own MSS key.
+    pub fn derive_branch_key(&self, label: &Trytes<TW>) -> mss::PrivateKey<TW, P> {
+        let nonce = self.mss_sk.nonce() + &Tbits::from_str("BRANCHKEY").unwrap();
+        mss::PrivateKey::gen(&self.prng, (&nonce + &label.0).slice(), self.default_mss_height)
+    }
+
+    /// Prepare BranchKey message: derive branch MSS key pair with `label`.
+    pub fn prepare_branch_key<'a>(
+        &'a mut self,
+        link_to: &'a <Link as HasLink>::Rel,
+        label: &'a Trytes<TW>,
+    ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, branch_key::ContentWrap<'a, TW, P, Link>>> {
+        let mss_sk = self.derive_branch_key(label);
+        let header = self.link_gen.header_from(link_to, branch_key::TYPE).with_app_version(self.app_version);
+        let content = branch_key::ContentWrap::new(link_to, label, mss_sk, &self.mss_sk);
+        Ok(PreparedMessage::new(self.store.borrow(), header, content))
+    }
+
+    /// Bind MSS key pair derived with `label` to the branch `link_to` belongs to, the derived
+    /// private key is returned to sign packets of the branch with `sign_packet_with_key`.
+    pub fn branch_key(
+        &mut self,
+        link_to: &<Link as HasLink>::Rel,
+        label: &Trytes<TW>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<(TbinaryMessage<TW, F, Link>, mss::PrivateKey<TW, P>)> {
+        let (wrapped, mss_sk) = {
+            let prepared = self.prepare_branch_key(link_to, label)?;
+            let wrapped = prepared.wrap()?;
+            (wrapped, prepared.content.mss_sk)
+        };
+        Ok((wrapped.commit(self.store.borrow_mut(), info)?, mss_sk))
+    }
+
+    /// Prepare SignedPacket message.
+    pub fn prepare_signed_packet<'a>(
+        &'a mut self,
+        link_to: &'a <Link as HasLink>::Rel,
+        public_payload: &'a Trytes<TW>,
+        masked_payload: &'a Trytes<TW>,
+    ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, signed_packet::ContentWrap<'a, TW, F, P, Link>>> {
+        let header = self.link_gen.header_from(link_to, signed_packet::TYPE).with_app_version(self.app_version);
+        let content = signed_packet::ContentWrap {
+            link: link_to,
+            public_payload: public_payload,
+            masked_payload: masked_payload,
+            aad: None,
+            mss_sk: &self.mss_sk,
+            _phantom: std::marker::PhantomData,
+        };
+        Ok(PreparedMessage::new(self.store.borrow(), header, content))
+    }
+
+    /// Create a signed message with public and masked payload.
+    pub fn sign_packet(
+        &mut self,
+        link_to: &<Link as HasLink>::Rel,
+        public_payload: &Trytes<TW>,
+        masked_payload: &Trytes<TW>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        self.do_sign_packet(link_to, public_payload, masked_payload, None, None, info)
+    }
+
+    /// Create a signed message with additional authenticated data `aad` which is not transmitted.
+    pub fn sign_packet_with_aad(
+        &mut self,
+        link_to: &<Link as HasLink>::Rel,
+        public_payload: &Trytes<TW>,
+        masked_payload: &Trytes<TW>,
+        aad: &Trytes<TW>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        self.do_sign_packet(link_to, public_payload, masked_payload, Some(aad), None, info)
+    }
+
+    /// Create a signed message signed with branch key `mss_sk` instead of own MSS key,
+    /// see `branch_key`.
+    pub fn sign_packet_with_key(
+        &mut self,
+        link_to: &<Link as HasLink>::Rel,
+        public_payload: &Trytes<TW>,
+        masked_payload: &Trytes<TW>,
+        aad: Option<&Trytes<TW>>,
+        mss_sk: &mss::PrivateKey<TW, P>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        self.do_sign_packet(link_to, public_payload, masked_payload, aad, Some(mss_sk), info)
+    }
+
+    fn do_sign_packet(
+        &mut self,
+        link_to: &<Link as HasLink>::Rel,
+        public_payload: &Trytes<TW>,
+        masked_payload: &Trytes<TW>,
+        aad: Option<&Trytes<TW>>,
+        mss_sk: Option<&mss::PrivateKey<TW, P>>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        let padded = self.payload_padding.pad(masked_payload)?;
+        let wrapped = {
+            let masked_payload = padded.as_ref().unwrap_or(masked_payload);
+            let mut prepared = self.prepare_signed_packet(link_to, public_payload, masked_payload)?;
+            if padded.is_some() {
+                prepared = prepared.with_tlv_ext(&payload_padding::PaddedPayload);
+            }
+            prepared.content.aad = aad;
+            if let Some(mss_sk) = mss_sk {
+                prepared.content.mss_sk = mss_sk;
+            }
+            prepared.wrap()?
+        };
+        wrapped.commit(self.store.borrow_mut(), info)
+    }
+
+    /// Prepare StructuredPacket message.
+    pub fn prepare_structured_packet<'a>(
+        &'a mut self,
+        link_to: &'a <Link as HasLink>::Rel,
+        fields: &'a [structured_packet::Field<TW>],
+    ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, structured_packet::ContentWrap<'a, TW, F, P, Link>>> {
+        let mut content_fields = Vec::with_capacity(fields.len());
+        for field in fields {
+            let value = match &field.value {
+                Some(value) => value,
+                None => bail!("StructuredPacket field has no value."),
+            };
+            ensure!(value.0.size() % 3 == 0, "StructuredPacket field value is not trytes.");
+            let psk = match &field.audience {
+                structured_packet::Audience::Psk(pskid) => match self.psks.get(pskid) {
+                    Some(psk) => Some(psk),
+                    None => bail!("Pre-shared key {} not found.", pskid),
+                },
+                _ => None,
+            };
+            content_fields.push((value, field, psk));
+        }
+        let header = self
+            .link_gen
+            .header_from(link_to, structured_packet::TYPE)
+            .with_app_version(self.app_version);
+        let content = structured_packet::ContentWrap {
+            link: link_to,
+            fields: content_fields,
+            mss_sk: &self.mss_sk,
+            _phantom: std::marker::PhantomData,
+        };
+        Ok(PreparedMessage::new(self.store.borrow(), header, content))
+    }
+
+    /// Create a signed message with fields disclosed to different audiences.
+    pub fn sign_structured_packet(
+        &mut self,
+        link_to: &<Link as HasLink>::Rel,
+        fields: &[structured_packet::Field<TW>],
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        let wrapped = self.prepare_structured_packet(link_to, fields)?.wrap()?;
+        wrapped.commit(self.store.borrow_mut(), info)
+    }
+
+    /// Prepare Participants message.
+    pub fn prepare_participants<'a>(
+        &'a mut self,
+        link_to: &'a <Link as HasLink>::Rel,
+    ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, participants::ContentWrap<'a, TW, F, P, Link>>> {
+        let header = self.link_gen.header_from(link_to, participants::TYPE).with_app_version(self.app_version);
+        let content = participants::ContentWrap {
+            link: link_to,
+            ntru_pks: &self.ntru_pks,
+            roles: &self.roles,
+            mss_sk: &self.mss_sk,
+            _phantom: std::marker::PhantomData,
+        };
+        Ok(PreparedMessage::new(self.store.borrow(), header, content))
+    }
+
+    /// Publish a signed snapshot of subscribers' NTRU public keys and their roles.
+    /// It should be shared again whenever subscribers or roles change.
+    pub fn share_participants(
+        &mut self,
+        link_to: &<Link as HasLink>::Rel,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        let wrapped = self.prepare_participants(link_to)?.wrap()?;
         wrapped.commit(self.store.borrow_mut(), info)
     }
 
@@ -335,11 +1280,12 @@ where
         public_payload: &'a Trytes<TW>,
         masked_payload: &'a Trytes<TW>,
     ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, tagged_packet::ContentWrap<'a, TW, F, Link>>> {
-        let header = self.link_gen.header_from(link_to, tagged_packet::TYPE);
+        let header = self.link_gen.header_from(link_to, tagged_packet::TYPE).with_app_version(self.app_version);
         let content = tagged_packet::ContentWrap {
             link: link_to,
             public_payload: public_payload,
             masked_payload: masked_payload,
+            aad: None,
             _phantom: std::marker::PhantomData,
         };
         Ok(PreparedMessage::new(self.store.borrow(), header, content))
@@ -353,18 +1299,201 @@ where
         public_payload: &Trytes<TW>,
         masked_payload: &Trytes<TW>,
         info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        let padded = self.payload_padding.pad(masked_payload)?;
+        let wrapped = {
+            let masked_payload = padded.as_ref().unwrap_or(masked_payload);
+            let mut prepared = self.prepare_tagged_packet(link_to, public_payload, masked_payload)?;
+            if padded.is_some() {
+                prepared = prepared.with_tlv_ext(&payload_padding::PaddedPayload);
+            }
+            prepared.wrap()?
+        };
+        wrapped.commit(self.store.borrow_mut(), info)
+    }
+
+    /// Create a tagged message with additional authenticated data `aad` which is not transmitted.
+    pub fn tag_packet_with_aad(
+        &mut self,
+        link_to: &<Link as HasLink>::Rel,
+        public_payload: &Trytes<TW>,
+        masked_payload: &Trytes<TW>,
+        aad: &Trytes<TW>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        let padded = self.payload_padding.pad(masked_payload)?;
+        let wrapped = {
+            let masked_payload = padded.as_ref().unwrap_or(masked_payload);
+            let mut prepared = self.prepare_tagged_packet(link_to, public_payload, masked_payload)?;
+            if padded.is_some() {
+                prepared = prepared.with_tlv_ext(&payload_padding::PaddedPayload);
+            }
+            prepared.content.aad = Some(aad);
+            prepared.wrap()?
+        };
+        wrapped.commit(self.store.borrow_mut(), info)
+    }
+
+    /// Prepare MultiTaggedPacket message.
+    pub fn prepare_multi_tagged_packet<'a>(
+        &'a mut self,
+        link_to: &'a <Link as HasLink>::Rel,
+        public_payload: &'a Trytes<TW>,
+        masked_payload: &'a Trytes<TW>,
+        pskids: &[psk::PskId<TW>],
+    ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, multi_tagged_packet::ContentWrap<'a, TW, F, Link>>> {
+        ensure!(!pskids.is_empty(), "MultiTaggedPacket must be tagged for at least one group.");
+        let mut psks = Vec::with_capacity(pskids.len());
+        for pskid in pskids {
+            match self.psks.get_key_value(pskid) {
+                Some(psk) => psks.push(psk),
+                None => bail!("Pre-shared key {} not found.", pskid),
+            }
+        }
+        let header = self
+            .link_gen
+            .header_from(link_to, multi_tagged_packet::TYPE)
+            .with_app_version(self.app_version);
+        let content = multi_tagged_packet::ContentWrap {
+            link: link_to,
+            public_payload: public_payload,
+            masked_payload: masked_payload,
+            psks: psks,
+            _phantom: std::marker::PhantomData,
+        };
+        Ok(PreparedMessage::new(self.store.borrow(), header, content))
+    }
+
+    /// Create a tagged message with a MAC per recipient group identified by pre-shared key,
+    /// a group can verify the message but can't forge MACs of other groups.
+    pub fn multi_tag_packet(
+        &mut self,
+        link_to: &<Link as HasLink>::Rel,
+        public_payload: &Trytes<TW>,
+        masked_payload: &Trytes<TW>,
+        pskids: &[psk::PskId<TW>],
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
     ) -> Fallible<TbinaryMessage<TW, F, Link>> {
         let wrapped = self
-            .prepare_tagged_packet(link_to, public_payload, masked_payload)?
+            .prepare_multi_tagged_packet(link_to, public_payload, masked_payload, pskids)?
             .wrap()?;
         wrapped.commit(self.store.borrow_mut(), info)
     }
 
+    /// Prepare FramedPacket message.
+    pub fn prepare_framed_packet<'a>(
+        &'a mut self,
+        link_to: &'a <Link as HasLink>::Rel,
+        public_payload: &'a Trytes<TW>,
+        masked_payload: &Trytes<TW>,
+        frame_size: usize,
+    ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, framed_packet::ContentWrap<'a, TW, F, Link>>> {
+        ensure!(frame_size != 0, "Frame size must be positive.");
+        ensure!(masked_payload.0.size() % 3 == 0, "Masked payload is not trytes.");
+        let header = self.link_gen.header_from(link_to, framed_packet::TYPE).with_app_version(self.app_version);
+        let content = framed_packet::ContentWrap {
+            link: link_to,
+            public_payload: public_payload,
+            frames: framed_packet::split_frames(masked_payload, frame_size),
+            _phantom: std::marker::PhantomData,
+        };
+        Ok(PreparedMessage::new(self.store.borrow(), header, content))
+    }
+
+    /// Create a framed packet: as tagged packet, but masked payload is split into frames
+    /// of `frame_size` trytes, each frame is tagged separately.
+    pub fn frame_packet(
+        &mut self,
+        link_to: &<Link as HasLink>::Rel,
+        public_payload: &Trytes<TW>,
+        masked_payload: &Trytes<TW>,
+        frame_size: usize,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        let wrapped = self
+            .prepare_framed_packet(link_to, public_payload, masked_payload, frame_size)?
+            .wrap()?;
+        wrapped.commit(self.store.borrow_mut(), info)
+    }
+
+    /// Create a tagged packet with header extension carrying routing hints.
+    /// The extension is masked with the state of the message at `link_to`,
+    /// only those able to unwrap the tagged packet can read it.
+    pub fn tag_packet_with_ext(
+        &mut self,
+        link_to: &<Link as HasLink>::Rel,
+        public_payload: &Trytes<TW>,
+        masked_payload: &Trytes<TW>,
+        branch: &str,
+        schema: &str,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        let ext = HeaderExt::new(Link::from_base_rel(self.appinst.base(), link_to), branch, schema)?;
+        let padded = self.payload_padding.pad(masked_payload)?;
+        let wrapped = {
+            let masked_payload = padded.as_ref().unwrap_or(masked_payload);
+            let mut prepared = self
+                .prepare_tagged_packet(link_to, public_payload, masked_payload)?
+                .with_header_ext(ext);
+            if padded.is_some() {
+                prepared = prepared.with_tlv_ext(&payload_padding::PaddedPayload);
+            }
+            prepared.wrap()?
+        };
+        wrapped.commit(self.store.borrow_mut(), info)
+    }
+
+    fn ensure_lite_mac(&self) -> Fallible<lite_packet::LiteMac> {
+        match self.lite_mac {
+            Some(lite_mac) => Ok(lite_mac),
+            None => bail!("Lite packets are not allowed in the channel."),
+        }
+    }
+
+    /// Prepare LitePacket message.
+    pub fn prepare_lite_packet<'a>(
+        &'a mut self,
+        link_to: &'a <Link as HasLink>::Rel,
+        payload: &'a Trytes<TW>,
+    ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, lite_packet::ContentWrap<'a, TW, F, Link>>> {
+        let mac = self.ensure_lite_mac()?;
+        let header = self.link_gen.header_from(link_to, lite_packet::TYPE).with_app_version(self.app_version);
+        let content = lite_packet::ContentWrap {
+            link: link_to,
+            payload: payload,
+            mac: mac,
+            _phantom: std::marker::PhantomData,
+        };
+        Ok(PreparedMessage::new(self.store.borrow(), header, content))
+    }
+
+    /// Create a lite packet: masked payload with MAC truncated to the size announced in the channel.
+    /// As tagged packets, lite packets must be linked to a secret spongos state.
+    pub fn lite_packet(
+        &mut self,
+        link_to: &<Link as HasLink>::Rel,
+        payload: &Trytes<TW>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        let wrapped = self.prepare_lite_packet(link_to, payload)?.wrap()?;
+        wrapped.commit(self.store.borrow_mut(), info)
+    }
+
+    fn ensure_app_version<'a>(&self, preparsed: &PreparsedMessage<'a, TW, F, Link>) -> Fallible<()> {
+        ensure!(
+            (self.app_version_check)(preparsed.header.app_version),
+            "Incompatible application version: {}.",
+            preparsed.header.app_version
+        );
+        Ok(())
+    }
+
     fn ensure_appinst<'a>(&self, preparsed: &PreparsedMessage<'a, TW, F, Link>) -> Fallible<()> {
         ensure!(
             self.appinst.base() == preparsed.header.link.base(),
             "Message sent to another channel instance."
         );
+        self.ensure_app_version(preparsed)?;
         Ok(())
     }
 
@@ -411,7 +1540,13 @@ where
             for<'c> fn(&'c Self, &psk::PskId<TW>) -> Option<&'c psk::Psk<TW>>,
             for<'c> fn(&'c Self, &ntru::Pkid<TW>) -> Option<&'c ntru::PrivateKey<TW, F>>,
         >::new(self, Self::lookup_psk, Self::lookup_ntru_sk);
-        preparsed.unwrap(&*self.store.borrow(), content)
+        if preparsed.check_content_type(keyload::EMBARGOED_TYPE) {
+            preparsed
+                .with_max_size(self.max_size)
+                .unwrap(&*self.store.borrow(), content.with_reveal_keys(&self.embargo_keys))
+        } else {
+            preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
+        }
     }
 
     /// Try unwrapping session key from keyload using Subscriber's pre-shared key or NTRU private key (if any).
@@ -429,10 +1564,21 @@ where
     pub fn unwrap_tagged_packet<'a>(
         &self,
         preparsed: PreparsedMessage<'a, TW, F, Link>,
+    ) -> Fallible<UnwrappedMessage<TW, F, Link, tagged_packet::ContentUnwrap<TW, F, Link>>> {
+        self.unwrap_tagged_packet_with_aad(preparsed, None)
+    }
+
+    pub fn unwrap_tagged_packet_with_aad<'a>(
+        &self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        aad: Option<&Trytes<TW>>,
     ) -> Fallible<UnwrappedMessage<TW, F, Link, tagged_packet::ContentUnwrap<TW, F, Link>>> {
         self.ensure_appinst(&preparsed)?;
-        let content = tagged_packet::ContentUnwrap::new();
-        preparsed.unwrap(&*self.store.borrow(), content)
+        let mut content = tagged_packet::ContentUnwrap::new();
+        if let Some(aad) = aad {
+            content = content.with_aad(aad.clone());
+        }
+        preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
     }
 
     /// Get public payload, decrypt masked payload and verify MAC.
@@ -441,10 +1587,69 @@ where
         preparsed: PreparsedMessage<'a, TW, F, Link>,
         info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
     ) -> Fallible<(Trytes<TW>, Trytes<TW>)> {
+        self.handle_tagged_packet_with_aad(preparsed, None, info)
+    }
+
+    /// Get public payload, decrypt masked payload and verify MAC covering additional authenticated data `aad`.
+    pub fn handle_tagged_packet_with_aad<'a>(
+        &mut self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        aad: Option<&Trytes<TW>>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<(Trytes<TW>, Trytes<TW>)> {
+        let padded = preparsed.header.tlv_ext::<payload_padding::PaddedPayload>()?.is_some();
+        let content = self
+            .unwrap_tagged_packet_with_aad(preparsed, aad)?
+            .commit(self.store.borrow_mut(), info)?;
+        if padded {
+            Ok((content.public_payload, payload_padding::unpad(&content.masked_payload)?))
+        } else {
+            Ok((content.public_payload, content.masked_payload))
+        }
+    }
+
+    pub fn unwrap_framed_packet<'a>(
+        &self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+    ) -> Fallible<UnwrappedMessage<TW, F, Link, framed_packet::ContentUnwrap<TW, F, Link>>> {
+        self.ensure_appinst(&preparsed)?;
+        let content = framed_packet::ContentUnwrap::new();
+        preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
+    }
+
+    /// Get public payload, decrypt and reassemble frames of masked payload, verify tags and MAC.
+    /// A corrupted frame fails with `framed_packet::FrameCorrupted` error.
+    pub fn handle_framed_packet<'a>(
+        &mut self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<(Trytes<TW>, Trytes<TW>)> {
+        let content = self
+            .unwrap_framed_packet(preparsed)?
+            .commit(self.store.borrow_mut(), info)?;
+        let masked_payload = content.masked_payload();
+        Ok((content.public_payload, masked_payload))
+    }
+
+    pub fn unwrap_lite_packet<'a>(
+        &self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+    ) -> Fallible<UnwrappedMessage<TW, F, Link, lite_packet::ContentUnwrap<TW, F, Link>>> {
+        self.ensure_appinst(&preparsed)?;
+        let content = lite_packet::ContentUnwrap::new(self.ensure_lite_mac()?);
+        preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
+    }
+
+    /// Decrypt payload and verify truncated MAC.
+    pub fn handle_lite_packet<'a>(
+        &mut self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<Trytes<TW>> {
         let content = self
-            .unwrap_tagged_packet(preparsed)?
+            .unwrap_lite_packet(preparsed)?
             .commit(self.store.borrow_mut(), info)?;
-        Ok((content.public_payload, content.masked_payload))
+        Ok(content.payload)
     }
 
     pub fn unwrap_subscribe<'a>(
@@ -452,9 +1657,10 @@ where
         preparsed: PreparsedMessage<'a, TW, F, Link>,
     ) -> Fallible<UnwrappedMessage<TW, F, Link, subscribe::ContentUnwrap<TW, F, Link>>> {
         self.ensure_appinst(&preparsed)?;
+        ensure!(!self.psk_only, "Channel is PSK-only, subscriptions are not accepted.");
         if let Some((own_ntru_sk, _)) = &self.opt_ntru {
             let content = subscribe::ContentUnwrap::new(own_ntru_sk);
-            preparsed.unwrap(&*self.store.borrow(), content)
+            preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
         } else {
             bail!("Author doesn't have NTRU key pair.")
         }
@@ -466,14 +1672,56 @@ where
         preparsed: PreparsedMessage<'a, TW, F, Link>,
         info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
     ) -> Fallible<()> {
+        // Subscriber is trusted right away, see `Author::set_subscription_approval` to approve subscriptions first.
+        let subscriber_ntru_pk = self.handle_subscribe_request(preparsed, info)?;
+        self.ntru_pks.insert(subscriber_ntru_pk);
+        Ok(())
+    }
+
+    /// Get public payload, decrypt masked payload and verify MAC, return subscriber's NTRU
+    /// public key. The key is not added to the subscribers, eg. until the request is approved.
+    pub fn handle_subscribe_request<'a>(
+        &mut self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<ntru::PublicKey<TW, F>> {
         let content = self
             .unwrap_subscribe(preparsed)?
             .commit(self.store.borrow_mut(), info)?;
-        //TODO: trust content.subscriber_ntru_pk and add to the list of subscribers only if trusted.
-        let subscriber_ntru_pk = content.subscriber_ntru_pk;
-        self.ntru_pks.insert(subscriber_ntru_pk);
         // Unwrapped unsubscribe_key is not used explicitly.
-        Ok(())
+        Ok(content.subscriber_ntru_pk)
+    }
+
+    pub fn unwrap_keyload_request<'a>(
+        &self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+    ) -> Fallible<UnwrappedMessage<TW, F, Link, keyload_request::ContentUnwrap<TW, F, Link>>> {
+        self.ensure_appinst(&preparsed)?;
+        ensure!(!self.psk_only, "Channel is PSK-only, subscriptions are not accepted.");
+        if let Some((own_ntru_sk, _)) = &self.opt_ntru {
+            let content = keyload_request::ContentUnwrap::new(own_ntru_sk);
+            preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
+        } else {
+            bail!("Author doesn't have NTRU key pair.")
+        }
+    }
+
+    /// Decrypt requester's NTRU public key id and check the requester is subscribed.
+    pub fn handle_keyload_request<'a>(
+        &mut self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<ntru::Pkid<TW>> {
+        let content = self
+            .unwrap_keyload_request(preparsed)?
+            .commit(self.store.borrow_mut(), info)?;
+        let ntru_pkid = ntru::Pkid(content.subscriber_ntru_pkid.0);
+        ensure!(
+            self.ntru_pks.iter().any(|ntru_pk| ntru_pk.cmp_pkid(&ntru_pkid)),
+            "Keyload requester is not subscribed: {}.",
+            ntru_pkid
+        );
+        Ok(ntru_pkid)
     }
 
     pub fn unwrap_unsubscribe<'a>(
@@ -482,7 +1730,7 @@ where
     ) -> Fallible<UnwrappedMessage<TW, F, Link, unsubscribe::ContentUnwrap<TW, F, Link>>> {
         self.ensure_appinst(&preparsed)?;
         let content = unsubscribe::ContentUnwrap::new();
-        preparsed.unwrap(&*self.store.borrow(), content)
+        preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
     }
 
     /// Get public payload, decrypt masked payload and verify MAC.
@@ -509,14 +1757,64 @@ where
         if preparsed.check_content_type(tagged_packet::TYPE) {
             self.handle_tagged_packet(preparsed, info)?;
             Ok(())
+        } else if preparsed.check_content_type(lite_packet::TYPE) {
+            self.handle_lite_packet(preparsed, info)?;
+            Ok(())
+        } else if preparsed.check_content_type(framed_packet::TYPE) {
+            self.handle_framed_packet(preparsed, info)?;
+            Ok(())
+        } else if preparsed.check_content_type(keyload_request::TYPE) {
+            let ntru_pkid = self.handle_keyload_request(preparsed, info)?;
+            self.keyload_requests.push(ntru_pkid);
+            Ok(())
         } else if preparsed.check_content_type(announce::TYPE) {
             bail!("Can't handle announce message.")
+        } else if preparsed.check_content_type(branch_key::TYPE) {
+            bail!("Can't handle branch_key message.")
         } else if preparsed.check_content_type(change_key::TYPE) {
             bail!("Can't handle change_key message.")
+        } else if preparsed.check_content_type(close_branch::TYPE) {
+            bail!("Can't handle close_branch message.")
+        } else if preparsed.check_content_type(handover::TYPE) {
+            bail!("Can't handle handover message.")
+        } else if preparsed.check_content_type(heartbeat::TYPE) {
+            bail!("Can't handle heartbeat message.")
         } else if preparsed.check_content_type(signed_packet::TYPE) {
             bail!("Can't handle signed_packet message.")
+        } else if preparsed.check_content_type(structured_packet::TYPE) {
+            bail!("Can't handle structured_packet message.")
+        } else if preparsed.check_content_type(multi_tagged_packet::TYPE) {
+            bail!("Can't handle multi_tagged_packet message.")
         } else {
             bail!("Unsupported content type: '{}'.", preparsed.content_type())
         }
     }
 }
+
+impl<TW, F, P, Link, Store, LinkGen> HeapSize for AuthorT<TW, F, P, Link, Store, LinkGen>
+where
+    P: mss::Parameters<TW>,
+    Link: HeapSize,
+    Store: HeapSize,
+{
+    /// Heap owned by keys, subscribers and link store, link generator is not accounted.
+    fn heap_size(&self) -> usize {
+        self.prng.heap_size()
+            + self.mss_sk.heap_size()
+            + self.author_set.heap_size()
+            + self.co_signers.heap_size()
+            + self.opt_ntru.heap_size()
+            + self.psks.heap_size()
+            + self.ntru_pks.heap_size()
+            + self.roles.heap_size()
+            + self.aliases.heap_size()
+            + self.embargo_keys.heap_size()
+            + self.keyload_requests.heap_size()
+            + self.escrow_pk.heap_size()
+            + self.escrowed_keys.heap_size()
+            + self.kem_history.heap_size()
+            + self.keyload_prng.as_ref().map_or(0, |(prng, _)| prng.heap_size())
+            + self.store.borrow().heap_size()
+            + self.appinst.heap_size()
+    }
+}
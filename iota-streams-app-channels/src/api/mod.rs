@@ -1,3 +1,7 @@
+use failure::{
+    ensure,
+    Fallible,
+};
 use iota_streams_app::{
     message::{
         HasLink,
@@ -5,6 +9,7 @@ use iota_streams_app::{
     },
     transport::tangle::{
         DefaultTangleLinkGenerator,
+        MsgIdDerivation,
         TangleAddress,
     },
 };
@@ -19,6 +24,8 @@ use iota_streams_core::{
     },
 };
 use iota_streams_core_mss::signature::mss;
+use iota_streams_core_ntru::key_encapsulation::ntru;
+use iota_streams_protobuf3::types::Trint3;
 
 pub trait ChannelLinkGenerator<TW, P, Link>
 where
@@ -27,6 +34,11 @@ where
     Link: HasLink,
     Self: LinkGenerator<TW, Link, mss::PublicKey<TW, P>> + LinkGenerator<TW, Link, <Link as HasLink>::Rel>,
 {
+    /// Version of link derivation announced by the channel owner.
+    fn link_version(&self) -> Trint3;
+
+    /// Derive links as announced by the channel owner.
+    fn set_link_version(&mut self, link_version: Trint3) -> Fallible<()>;
 }
 impl<TW, F, P> ChannelLinkGenerator<TW, P, TangleAddress<TW>> for DefaultTangleLinkGenerator<TW, F>
 where
@@ -34,8 +46,34 @@ where
     F: PRP<TW> + Default,
     P: mss::Parameters<TW>,
 {
+    fn link_version(&self) -> Trint3 {
+        self.msgid_derivation().to_trint3()
+    }
+
+    fn set_link_version(&mut self, link_version: Trint3) -> Fallible<()> {
+        let msgid_derivation = MsgIdDerivation::from_trint3(link_version)?;
+        ensure!(
+            msgid_derivation != MsgIdDerivation::Custom || self.msgid_deriver().is_some(),
+            "Custom msgid derivation is announced, but no msgid deriver is set."
+        );
+        self.set_msgid_derivation(msgid_derivation);
+        Ok(())
+    }
+}
+
+/// Application version compatibility check accepting messages of any application version.
+pub fn accept_any_app_version(_app_version: Trint3) -> bool {
+    true
+}
+
+/// Check refusing reuse of session key and nonce for NTRU recipients, see `AuthorT::kem_reuse_check`.
+pub fn refuse_kem_reuse<TW>(_pkid: &ntru::Pkid<TW>) -> bool {
+    false
 }
 
+/// Author's key mismatch handler doing nothing, see `SubscriberT::on_author_key_mismatch`.
+pub fn ignore_author_key_mismatch(_err: &subscriber::AuthorKeyMismatch) {}
+
 /// Generic Channel Author API.
 pub mod author;
 
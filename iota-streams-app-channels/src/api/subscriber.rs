@@ -5,11 +5,18 @@ use failure::{
 };
 use std::{
     cell::RefCell,
-    fmt::Debug,
+    error,
+    fmt::{
+        self,
+        Debug,
+    },
+    hash,
     str::FromStr,
 };
 
 use iota_streams_core::{
+    heap::HeapSize,
+    prelude::HashMap,
     prng,
     psk,
     sponge::spongos,
@@ -28,14 +35,40 @@ use iota_streams_core_mss::signature::mss;
 use iota_streams_core_ntru::key_encapsulation::ntru;
 
 use iota_streams_app::message::{
-    header::Header,
+    header::{
+        Header,
+        HeaderExt,
+    },
     *,
 };
 use iota_streams_protobuf3::types::*;
 
-use super::*;
+use super::{
+    author::AuthorT,
+    *,
+};
 use crate::message::*;
 
+/// Error of handling a message signed with Author's MSS public key other than the trusted one,
+/// eg. a substituted announcement of the channel the Subscriber is registered to.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct AuthorKeyMismatch {
+    /// Content type of the message.
+    pub content_type: &'static str,
+}
+
+impl fmt::Display for AuthorKeyMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Message {} is signed with Author's MSS public key other than the trusted one.",
+            self.content_type
+        )
+    }
+}
+
+impl error::Error for AuthorKeyMismatch {}
+
 /// Generic Channel Subscriber type parametrised by the type of links, link store and
 /// link generator.
 ///
@@ -73,11 +106,63 @@ where
     //TODO: Store also Author's old MSS public keys?
     pub(crate) author_mss_pk: Option<mss::PublicKey<TW, P>>,
 
+    /// Author's MSS public key pinned by the first handled Announce message. Announce messages
+    /// of the same channel instance with another key are rejected.
+    pub(crate) announced_mss_pk: Option<mss::PublicKey<TW, P>>,
+
+    /// Author set announced in ThresholdAnnounce message, or nothing if the channel
+    /// is not governed by Author set.
+    pub(crate) author_set: Option<threshold_announce::AuthorSet<TW, P>>,
+
     /// Author's NTRU public key or nothing if Author has no NTRU key pair.
     pub(crate) author_ntru_pk: Option<ntru::PublicKey<TW, F>>,
 
+    /// MAC size of lite packets announced in the channel, or nothing if they are not allowed.
+    pub(crate) lite_mac: Option<lite_packet::LiteMac>,
+
+    /// Role granted by the last handled keyload or nothing if no keyload has been handled.
+    /// Write access is checked with the role of the branch, see `branch_role`.
+    pub(crate) role: Option<keyload::Role>,
+
+    /// The last Author's heartbeat handled, or nothing.
+    pub(crate) last_heartbeat: Option<heartbeat::Heartbeat>,
+
+    /// Reveal keys published by the Author in RevealKey messages.
+    pub(crate) reveal_keys: psk::Psks<TW>,
+
+    /// Session keys and granted roles of handled keyloads by keyload link. Handling a cached
+    /// keyload again (eg. when replaying the channel) skips decryption of recipients' keys.
+    pub(crate) session_keys: HashMap<Link, (NTrytes<TW>, keyload::Role)>,
+
+    /// Keyload links of handled and published packets, so that the role of a branch
+    /// is found for packets joined to other packets.
+    pub(crate) packet_keyloads: HashMap<Link, Link>,
+
+    /// Subscribers' NTRU public keys and roles listed in the last handled Participants message.
+    pub(crate) participants: Vec<(ntru::PublicKey<TW, F>, keyload::Role)>,
+
+    /// Own MSS private key generated when accepting channel handover, or nothing.
+    pub(crate) successor_mss_sk: Option<mss::PrivateKey<TW, P>>,
+
+    /// Application protocol version put in headers of new messages, 0 if not specified.
+    pub app_version: Trint3,
+
+    /// Check whether messages of the given application version can be handled,
+    /// incompatible messages are rejected. By default any version is accepted.
+    pub app_version_check: fn(Trint3) -> bool,
+
+    /// Called with the error before a message with mismatching Author's key is rejected.
+    pub on_author_key_mismatch: fn(&AuthorKeyMismatch),
+
+    /// Maximum size in trytes of variable-length fields (eg. payloads) of handled messages,
+    /// unlimited if not set.
+    pub max_size: Option<usize>,
+
+    /// Padding of masked payloads of tagged packets, none by default.
+    pub payload_padding: payload_padding::PayloadPadding,
+
     /// Link store.
-    store: RefCell<Store>,
+    pub(crate) store: RefCell<Store>,
 
     /// Link generator.
     pub(crate) link_gen: LinkGen,
@@ -88,7 +173,7 @@ where
     TW: RngTbitWord + IntTbitWord + StringTbitWord + SpongosTbitWord + trinary::TritWord,
     F: PRP<TW> + Clone + Default,
     P: mss::Parameters<TW>,
-    Link: HasLink + AbsorbExternalFallback<TW, F> + Default + Clone + Eq,
+    Link: HasLink + AbsorbExternalFallback<TW, F> + Default + Clone + Eq + hash::Hash,
     <Link as HasLink>::Base: Eq + Debug,
     <Link as HasLink>::Rel: Eq + Debug + Default + SkipFallback<TW, F>,
     Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
@@ -118,22 +203,92 @@ where
 
             appinst: None,
             author_mss_pk: None,
+            announced_mss_pk: None,
+            author_set: None,
             author_ntru_pk: None,
+            lite_mac: None,
+            role: None,
+            last_heartbeat: None,
+            reveal_keys: HashMap::new(),
+            session_keys: HashMap::new(),
+            packet_keyloads: HashMap::new(),
+            participants: Vec::new(),
+            successor_mss_sk: None,
+            app_version: Trint3(0),
+            app_version_check: accept_any_app_version,
+            on_author_key_mismatch: ignore_author_key_mismatch,
+            max_size: None,
+            payload_padding: payload_padding::PayloadPadding::None,
 
             store: RefCell::new(store),
             link_gen: link_gen,
         }
     }
 
+    fn ensure_app_version<'a>(&self, preparsed: &PreparsedMessage<'a, TW, F, Link>) -> Fallible<()> {
+        ensure!(
+            (self.app_version_check)(preparsed.header.app_version),
+            "Incompatible application version: {}.",
+            preparsed.header.app_version
+        );
+        Ok(())
+    }
+
     fn ensure_appinst<'a>(&self, preparsed: &PreparsedMessage<'a, TW, F, Link>) -> Fallible<()> {
         ensure!(self.appinst.is_some(), "Subscriber is not subscribed to a channel.");
         ensure!(
             self.appinst.as_ref().unwrap().base() == preparsed.header.link.base(),
             "Bad message application instance."
         );
+        self.ensure_app_version(preparsed)?;
+        Ok(())
+    }
+
+    fn ensure_author_mss_pk(
+        &self,
+        trusted_mss_pk: Option<&mss::PublicKey<TW, P>>,
+        mss_pk: &mss::PublicKey<TW, P>,
+        content_type: &'static str,
+    ) -> Fallible<()> {
+        if trusted_mss_pk.map_or(false, |trusted| trusted != mss_pk) {
+            let err = AuthorKeyMismatch { content_type };
+            (self.on_author_key_mismatch)(&err);
+            return Err(err.into());
+        }
+        Ok(())
+    }
+
+    /// Role granted in the branch of the message at `link_to`: by the keyload itself or by
+    /// the keyload the packet is joined to, directly or through other packets. Nothing if
+    /// the keyload of the branch has not been handled.
+    pub fn branch_role(&self, link_to: &<Link as HasLink>::Rel) -> Option<keyload::Role> {
+        let appinst = self.appinst.as_ref()?;
+        let link = Link::from_base_rel(appinst.base(), link_to);
+        let keyload = self.packet_keyloads.get(&link).unwrap_or(&link);
+        self.session_keys.get(keyload).map(|(_, role)| *role)
+    }
+
+    /// Publishing to the branch of `link_to` is allowed by the role granted in the branch.
+    /// Recipients don't check roles, see `keyload::Role`.
+    fn ensure_writer(&self, link_to: &<Link as HasLink>::Rel) -> Fallible<()> {
+        ensure!(
+            self.branch_role(link_to) != Some(keyload::Role::ReadOnly),
+            "Subscriber has read-only access to the branch."
+        );
         Ok(())
     }
 
+    /// Remember the keyload of the branch packet at `link` joined to `link_to` is in.
+    fn record_packet(&mut self, link: &Link, link_to: &<Link as HasLink>::Rel) {
+        if let Some(appinst) = &self.appinst {
+            let joined = Link::from_base_rel(appinst.base(), link_to);
+            let keyload = self.packet_keyloads.get(&joined).cloned().unwrap_or(joined);
+            if self.session_keys.contains_key(&keyload) {
+                self.packet_keyloads.insert(link.clone(), keyload);
+            }
+        }
+    }
+
     fn do_prepare_keyload<'a, Psks, NtruPks>(
         &'a self,
         header: Header<TW, Link>,
@@ -154,6 +309,10 @@ where
             psks: psks,
             prng: &self.prng,
             ntru_pks: ntru_pks,
+            roles: None,
+            embargo: None,
+            dummy_psks: Vec::new(),
+            dummy_ntru_pks: Vec::new(),
             _phantom: std::marker::PhantomData,
         };
         Ok(PreparedMessage::new(self.store.borrow(), header, content))
@@ -180,7 +339,8 @@ where
             >,
         >,
     > {
-        let header = self.link_gen.header_from(link_to, keyload::TYPE);
+        self.ensure_writer(link_to)?;
+        let header = self.link_gen.header_from(link_to, keyload::TYPE).with_app_version(self.app_version);
         self.do_prepare_keyload(
             header,
             link_to,
@@ -207,11 +367,13 @@ where
         public_payload: &'a Trytes<TW>,
         masked_payload: &'a Trytes<TW>,
     ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, tagged_packet::ContentWrap<'a, TW, F, Link>>> {
-        let header = self.link_gen.header_from(link_to, tagged_packet::TYPE);
+        self.ensure_writer(link_to)?;
+        let header = self.link_gen.header_from(link_to, tagged_packet::TYPE).with_app_version(self.app_version);
         let content = tagged_packet::ContentWrap {
             link: link_to,
             public_payload: public_payload,
             masked_payload: masked_payload,
+            aad: None,
             _phantom: std::marker::PhantomData,
         };
         Ok(PreparedMessage::new(self.store.borrow(), header, content))
@@ -225,11 +387,164 @@ where
         public_payload: &Trytes<TW>,
         masked_payload: &Trytes<TW>,
         info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        let padded = self.payload_padding.pad(masked_payload)?;
+        let wrapped = {
+            let masked_payload = padded.as_ref().unwrap_or(masked_payload);
+            let mut prepared = self.prepare_tagged_packet(link_to, public_payload, masked_payload)?;
+            if padded.is_some() {
+                prepared = prepared.with_tlv_ext(&payload_padding::PaddedPayload);
+            }
+            prepared.wrap()?
+        };
+        let msg = wrapped.commit(self.store.borrow_mut(), info)?;
+        self.record_packet(&msg.link, link_to);
+        Ok(msg)
+    }
+
+    /// Create a tagged message with additional authenticated data `aad` which is not transmitted.
+    pub fn tag_packet_with_aad(
+        &mut self,
+        link_to: &<Link as HasLink>::Rel,
+        public_payload: &Trytes<TW>,
+        masked_payload: &Trytes<TW>,
+        aad: &Trytes<TW>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        let padded = self.payload_padding.pad(masked_payload)?;
+        let wrapped = {
+            let masked_payload = padded.as_ref().unwrap_or(masked_payload);
+            let mut prepared = self.prepare_tagged_packet(link_to, public_payload, masked_payload)?;
+            if padded.is_some() {
+                prepared = prepared.with_tlv_ext(&payload_padding::PaddedPayload);
+            }
+            prepared.content.aad = Some(aad);
+            prepared.wrap()?
+        };
+        let msg = wrapped.commit(self.store.borrow_mut(), info)?;
+        self.record_packet(&msg.link, link_to);
+        Ok(msg)
+    }
+
+    /// Prepare FramedPacket message.
+    pub fn prepare_framed_packet<'a>(
+        &'a mut self,
+        link_to: &'a <Link as HasLink>::Rel,
+        public_payload: &'a Trytes<TW>,
+        masked_payload: &Trytes<TW>,
+        frame_size: usize,
+    ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, framed_packet::ContentWrap<'a, TW, F, Link>>> {
+        self.ensure_writer(link_to)?;
+        ensure!(frame_size != 0, "Frame size must be positive.");
+        ensure!(masked_payload.0.size() % 3 == 0, "Masked payload is not trytes.");
+        let header = self.link_gen.header_from(link_to, framed_packet::TYPE).with_app_version(self.app_version);
+        let content = framed_packet::ContentWrap {
+            link: link_to,
+            public_payload: public_payload,
+            frames: framed_packet::split_frames(masked_payload, frame_size),
+            _phantom: std::marker::PhantomData,
+        };
+        Ok(PreparedMessage::new(self.store.borrow(), header, content))
+    }
+
+    /// Create a framed packet: as tagged packet, but masked payload is split into frames
+    /// of `frame_size` trytes, each frame is tagged separately.
+    pub fn frame_packet(
+        &mut self,
+        link_to: &<Link as HasLink>::Rel,
+        public_payload: &Trytes<TW>,
+        masked_payload: &Trytes<TW>,
+        frame_size: usize,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
     ) -> Fallible<TbinaryMessage<TW, F, Link>> {
         let wrapped = self
-            .prepare_tagged_packet(link_to, public_payload, masked_payload)?
+            .prepare_framed_packet(link_to, public_payload, masked_payload, frame_size)?
             .wrap()?;
-        wrapped.commit(self.store.borrow_mut(), info)
+        let msg = wrapped.commit(self.store.borrow_mut(), info)?;
+        self.record_packet(&msg.link, link_to);
+        Ok(msg)
+    }
+
+    fn ensure_lite_mac(&self) -> Fallible<lite_packet::LiteMac> {
+        match self.lite_mac {
+            Some(lite_mac) => Ok(lite_mac),
+            None => bail!("Lite packets are not allowed in the channel."),
+        }
+    }
+
+    /// Prepare LitePacket message.
+    pub fn prepare_lite_packet<'a>(
+        &'a mut self,
+        link_to: &'a <Link as HasLink>::Rel,
+        payload: &'a Trytes<TW>,
+    ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, lite_packet::ContentWrap<'a, TW, F, Link>>> {
+        self.ensure_writer(link_to)?;
+        let mac = self.ensure_lite_mac()?;
+        let header = self.link_gen.header_from(link_to, lite_packet::TYPE).with_app_version(self.app_version);
+        let content = lite_packet::ContentWrap {
+            link: link_to,
+            payload: payload,
+            mac: mac,
+            _phantom: std::marker::PhantomData,
+        };
+        Ok(PreparedMessage::new(self.store.borrow(), header, content))
+    }
+
+    /// Create a lite packet: masked payload with MAC truncated to the size announced in the channel.
+    /// As tagged packets, lite packets must be linked to a secret spongos state.
+    pub fn lite_packet(
+        &mut self,
+        link_to: &<Link as HasLink>::Rel,
+        payload: &Trytes<TW>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        let wrapped = self.prepare_lite_packet(link_to, payload)?.wrap()?;
+        let msg = wrapped.commit(self.store.borrow_mut(), info)?;
+        self.record_packet(&msg.link, link_to);
+        Ok(msg)
+    }
+
+    /// Create a tagged packet with header extension carrying routing hints.
+    /// The extension is masked with the state of the message at `link_to`,
+    /// only those able to unwrap the tagged packet can read it.
+    pub fn tag_packet_with_ext(
+        &mut self,
+        link_to: &<Link as HasLink>::Rel,
+        public_payload: &Trytes<TW>,
+        masked_payload: &Trytes<TW>,
+        branch: &str,
+        schema: &str,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        let appinst = match &self.appinst {
+            Some(appinst) => appinst,
+            None => bail!("Subscriber is not subscribed to a channel."),
+        };
+        let ext = HeaderExt::new(Link::from_base_rel(appinst.base(), link_to), branch, schema)?;
+        let padded = self.payload_padding.pad(masked_payload)?;
+        let wrapped = {
+            let masked_payload = padded.as_ref().unwrap_or(masked_payload);
+            let mut prepared = self
+                .prepare_tagged_packet(link_to, public_payload, masked_payload)?
+                .with_header_ext(ext);
+            if padded.is_some() {
+                prepared = prepared.with_tlv_ext(&payload_padding::PaddedPayload);
+            }
+            prepared.wrap()?
+        };
+        let msg = wrapped.commit(self.store.borrow_mut(), info)?;
+        self.record_packet(&msg.link, link_to);
+        Ok(msg)
+    }
+
+    /// Unwrap header extension of a preparsed message, it requires the state
+    /// of the message the extension is masked with.
+    pub fn unwrap_header_ext<'a>(
+        &self,
+        preparsed: &PreparsedMessage<'a, TW, F, Link>,
+    ) -> Fallible<HeaderExt<TW, Link>> {
+        self.ensure_appinst(preparsed)?;
+        preparsed.unwrap_header_ext(&*self.store.borrow())
     }
 
     /// Prepare Subscribe message.
@@ -239,7 +554,7 @@ where
     ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, subscribe::ContentWrap<'a, TW, F, P::PrngG, Link>>> {
         if let Some(author_ntru_pk) = &self.author_ntru_pk {
             if let Some((_, own_ntru_pk)) = &self.opt_ntru {
-                let header = self.link_gen.header_from(link_to, subscribe::TYPE);
+                let header = self.link_gen.header_from(link_to, subscribe::TYPE).with_app_version(self.app_version);
                 let nonce = NTrytes(prng::random_nonce(spongos::Spongos::<TW, F>::NONCE_SIZE));
                 let unsubscribe_key = NTrytes(prng::random_key(spongos::Spongos::<TW, F>::KEY_SIZE));
                 let content = subscribe::ContentWrap {
@@ -266,7 +581,49 @@ where
         link_to: &<Link as HasLink>::Rel,
         info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
     ) -> Fallible<TbinaryMessage<TW, F, Link>> {
-        let wrapped = self.prepare_subscribe(link_to)?.wrap()?;
+        let wrapped = self.prepare_subscribe(link_to)?.wrap_fixed()?;
+        wrapped.commit(self.store.borrow_mut(), info)
+    }
+
+    /// Prepare KeyloadRequest message.
+    pub fn prepare_keyload_request<'a>(
+        &'a mut self,
+        link_to: &'a <Link as HasLink>::Rel,
+    ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, keyload_request::ContentWrap<'a, TW, F, P::PrngG, Link>>>
+    {
+        if let Some(author_ntru_pk) = &self.author_ntru_pk {
+            if let Some((_, own_ntru_pk)) = &self.opt_ntru {
+                let header = self
+                    .link_gen
+                    .header_from(link_to, keyload_request::TYPE)
+                    .with_app_version(self.app_version);
+                let nonce = NTrytes(prng::random_nonce(spongos::Spongos::<TW, F>::NONCE_SIZE));
+                let key = NTrytes(prng::random_key(spongos::Spongos::<TW, F>::KEY_SIZE));
+                let content = keyload_request::ContentWrap {
+                    link: link_to,
+                    nonce,
+                    key,
+                    subscriber_ntru_pkid: NTrytes(own_ntru_pk.get_pkid().0),
+                    author_ntru_pk: author_ntru_pk,
+                    prng: &self.prng,
+                    _phantom: std::marker::PhantomData,
+                };
+                Ok(PreparedMessage::new(self.store.borrow(), header, content))
+            } else {
+                bail!("Subscriber doesn't have own NTRU key pair.");
+            }
+        } else {
+            bail!("Subscriber doesn't have channel Author's NTRU public key.");
+        }
+    }
+
+    /// Ask channel Author for a new keyload, eg. when the current one can't be unwrapped.
+    pub fn request_keyload(
+        &mut self,
+        link_to: &<Link as HasLink>::Rel,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        let wrapped = self.prepare_keyload_request(link_to)?.wrap_fixed()?;
         wrapped.commit(self.store.borrow_mut(), info)
     }
 
@@ -275,7 +632,7 @@ where
         &'a mut self,
         link_to: &'a <Link as HasLink>::Rel,
     ) -> Fallible<PreparedMessage<'a, TW, F, Link, Store, unsubscribe::ContentWrap<'a, TW, F, Link>>> {
-        let header = self.link_gen.header_from(link_to, unsubscribe::TYPE);
+        let header = self.link_gen.header_from(link_to, unsubscribe::TYPE).with_app_version(self.app_version);
         let content = unsubscribe::ContentWrap {
             link: link_to,
             _phantom: std::marker::PhantomData,
@@ -289,7 +646,7 @@ where
         link_to: &<Link as HasLink>::Rel,
         info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
     ) -> Fallible<TbinaryMessage<TW, F, Link>> {
-        let wrapped = self.prepare_unsubscribe(link_to)?.wrap()?;
+        let wrapped = self.prepare_unsubscribe(link_to)?.wrap_fixed()?;
         wrapped.commit(self.store.borrow_mut(), info)
     }
 
@@ -305,9 +662,10 @@ where
                 appinst.base()
             );
         }
+        self.ensure_app_version(&preparsed)?;
 
         let content = announce::ContentUnwrap::<TW, F, P>::default();
-        preparsed.unwrap(&*self.store.borrow(), content)
+        preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
     }
 
     /// Bind Subscriber (or anonymously subscribe) to the channel announced
@@ -317,7 +675,9 @@ where
         preparsed: PreparsedMessage<'a, TW, F, Link>,
         info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
     ) -> Fallible<()> {
+        ensure!(self.author_set.is_none(), "Channel is governed by Author set.");
         let unwrapped = self.unwrap_announcement(preparsed)?;
+        self.ensure_author_mss_pk(self.announced_mss_pk.as_ref(), &unwrapped.content.mss_pk, announce::TYPE)?;
         let link = unwrapped.link.clone();
         let content = unwrapped.commit(self.store.borrow_mut(), info)?;
         //TODO: check commit after message is done / before joined
@@ -328,12 +688,81 @@ where
         //TODO: Verify appinst (address) == MSS public key.
         // At the moment the Author is free to choose any address, not tied to MSS PK.
 
+        self.bind(link, content.mss_pk, content.ntru_pk, content.link_version, content.lite_mac)
+    }
+
+    fn bind(
+        &mut self,
+        link: Link,
+        mss_pk: mss::PublicKey<TW, P>,
+        ntru_pk: Option<ntru::PublicKey<TW, F>>,
+        link_version: Trint3,
+        lite_mac: Trint3,
+    ) -> Fallible<()> {
+        self.link_gen.set_link_version(link_version)?;
+        self.lite_mac = lite_packet::LiteMac::from_trint3(lite_mac)?;
         self.appinst = Some(link);
-        self.author_mss_pk = Some(content.mss_pk);
-        self.author_ntru_pk = content.ntru_pk;
+        // Author's key may have been changed since the announcement.
+        if self.announced_mss_pk.is_none() {
+            self.announced_mss_pk = Some(mss_pk.clone());
+            self.author_mss_pk = Some(mss_pk);
+        }
+        self.author_ntru_pk = ntru_pk;
         Ok(())
     }
 
+    pub fn unwrap_threshold_announcement<'a>(
+        &self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+    ) -> Fallible<UnwrappedMessage<TW, F, Link, threshold_announce::ContentUnwrap<TW, F, P>>> {
+        if let Some(appinst) = &self.appinst {
+            ensure!(
+                appinst == &preparsed.header.link,
+                "Got ThresholdAnnounce with address {:?}, but already registered to a channel {:?}",
+                preparsed.header.link.base(),
+                appinst.base()
+            );
+        }
+        self.ensure_app_version(&preparsed)?;
+
+        let content = threshold_announce::ContentUnwrap::<TW, F, P>::default();
+        preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
+    }
+
+    /// Bind Subscriber to the channel governed by Author set announced in the message.
+    /// The announcement must be signed by at least threshold keys of the set.
+    pub fn handle_threshold_announcement<'a>(
+        &mut self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<()> {
+        let unwrapped = self.unwrap_threshold_announcement(preparsed)?;
+        let author_set = &unwrapped.content.author_set;
+        self.ensure_author_mss_pk(
+            self.announced_mss_pk.as_ref(),
+            &author_set.mss_pks[0],
+            threshold_announce::TYPE,
+        )?;
+        if let Some(pinned) = &self.author_set {
+            ensure!(
+                pinned.threshold == author_set.threshold && pinned.mss_pks == author_set.mss_pks,
+                "Author set differs from the announced one."
+            );
+        } else {
+            ensure!(self.announced_mss_pk.is_none(), "Channel is not governed by Author set.");
+        }
+        let link = unwrapped.link.clone();
+        let content = unwrapped.commit(self.store.borrow_mut(), info)?;
+        let mss_pk = content.author_set.mss_pks[0].clone();
+        self.author_set = Some(content.author_set);
+        self.bind(link, mss_pk, content.ntru_pk, content.link_version, content.lite_mac)
+    }
+
+    /// Author set of the channel, if it's governed by one.
+    pub fn author_set(&self) -> Option<&threshold_announce::AuthorSet<TW, P>> {
+        self.author_set.as_ref()
+    }
+
     pub fn unwrap_change_key<'a, 'b>(
         &'b self,
         preparsed: PreparsedMessage<'a, TW, F, Link>,
@@ -341,7 +770,7 @@ where
         self.ensure_appinst(&preparsed)?;
         let mss_linked_pk = self.author_mss_pk.as_ref().unwrap();
         let content = change_key::ContentUnwrap::new(mss_linked_pk);
-        preparsed.unwrap(&*self.store.borrow(), content)
+        preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
     }
 
     /// Verify new Author's MSS public key and update Author's MSS public key.
@@ -358,11 +787,110 @@ where
         Ok(())
     }
 
+    pub fn unwrap_branch_key<'a, 'b>(
+        &'b self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+    ) -> Fallible<UnwrappedMessage<TW, F, Link, branch_key::ContentUnwrap<'b, TW, P, Link>>> {
+        self.ensure_appinst(&preparsed)?;
+        ensure!(self.author_mss_pk.is_some(), "No Author's MSS public key found.");
+        let mss_linked_pk = self.author_mss_pk.as_ref().unwrap();
+        let content = branch_key::ContentUnwrap::new(mss_linked_pk);
+        preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
+    }
+
+    /// Verify branch MSS public key signed with Author's one, return its label and the key.
+    pub fn handle_branch_key<'a>(
+        &mut self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<(Trytes<TW>, mss::PublicKey<TW, P>)> {
+        let content = self
+            .unwrap_branch_key(preparsed)?
+            .commit(self.store.borrow_mut(), info)?;
+        Ok((content.label, content.mss_pk))
+    }
+
+    /// Generate own MSS key pair and accept channel handover: return MSS public key
+    /// and acceptance signature to be passed to the current channel Author.
+    pub fn accept_handover(
+        &mut self,
+        nonce: &Tbits<TW>,
+        mss_height: usize,
+    ) -> Fallible<(mss::PublicKey<TW, P>, Tbits<TW>)> {
+        ensure!(self.appinst.is_some(), "Subscriber is not subscribed to a channel.");
+        let mut mss_sk = mss::PrivateKey::<TW, P>::gen(&self.prng, nonce.slice(), mss_height);
+        let sig = handover::sign_acceptance::<TW, F, P, Link>(self.appinst.as_ref().unwrap(), &mut mss_sk)?;
+        let mss_pk = mss_sk.public_key().clone();
+        self.successor_mss_sk = Some(mss_sk);
+        Ok((mss_pk, sig))
+    }
+
+    pub fn unwrap_handover<'a, 'b>(
+        &'b self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+    ) -> Fallible<UnwrappedMessage<TW, F, Link, handover::ContentUnwrap<'b, TW, P, Link>>> {
+        self.ensure_appinst(&preparsed)?;
+        let mss_linked_pk = self.author_mss_pk.as_ref().unwrap();
+        let content = handover::ContentUnwrap::new(self.appinst.as_ref().unwrap(), mss_linked_pk);
+        preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
+    }
+
+    /// Verify successor's MSS public key and trust it as the channel Author's one.
+    pub fn handle_handover<'a>(
+        &mut self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<()> {
+        ensure!(self.author_mss_pk.is_some(), "No Author's MSS public key found.");
+        let content = self
+            .unwrap_handover(preparsed)?
+            .commit(self.store.borrow_mut(), info)?;
+        self.author_mss_pk = Some(content.mss_pk);
+        Ok(())
+    }
+
+    /// Turn Subscriber into channel Author once Handover message to own MSS public key
+    /// has been handled. Link store and own keys are kept, the new Author
+    /// doesn't know pre-shared keys of other subscribers. NTRU public keys and roles
+    /// are taken from the last handled Participants message, if any.
+    pub fn into_author(self) -> Fallible<AuthorT<TW, F, P, Link, Store, LinkGen>> {
+        ensure!(self.appinst.is_some(), "Subscriber is not subscribed to a channel.");
+        match self.successor_mss_sk {
+            Some(mss_sk) => {
+                ensure!(
+                    self.author_mss_pk.as_ref() == Some(mss_sk.public_key()),
+                    "Channel has not been handed over to the Subscriber."
+                );
+                let own_ntru_pk = self.opt_ntru.as_ref().map(|(_, pk)| pk.clone());
+                let mut author = AuthorT::from_handover(
+                    self.store.into_inner(),
+                    self.link_gen,
+                    self.prng,
+                    mss_sk,
+                    self.opt_ntru,
+                    self.appinst.unwrap(),
+                );
+                author.lite_mac = self.lite_mac;
+                author.max_size = self.max_size;
+                author.payload_padding = self.payload_padding;
+                author.heartbeat_counter = self.last_heartbeat.map_or(0, |heartbeat| heartbeat.counter);
+                for (ntru_pk, role) in self.participants {
+                    if Some(&ntru_pk) != own_ntru_pk.as_ref() {
+                        author.roles.set_ntru_role(ntru_pk.get_pkid(), role);
+                        author.ntru_pks.insert(ntru_pk);
+                    }
+                }
+                Ok(author)
+            }
+            None => bail!("Subscriber has not accepted channel handover."),
+        }
+    }
+
     fn lookup_psk<'b>(&'b self, pskid: &psk::PskId<TW>) -> Option<&'b psk::Psk<TW>> {
         self.opt_psk.as_ref().map_or(
             None,
             |(own_pskid, own_psk)| {
-                if pskid == own_pskid {
+                if pskid.ct_eq(own_pskid) {
                     Some(own_psk)
                 } else {
                     None
@@ -401,7 +929,57 @@ where
         >,
     > {
         self.ensure_appinst(&preparsed)?;
-        let content = keyload::ContentUnwrap::<
+        let mut content = keyload::ContentUnwrap::<
+            'b,
+            TW,
+            F,
+            Link,
+            Self,
+            for<'c> fn(&'c Self, &psk::PskId<TW>) -> Option<&'c psk::Psk<TW>>,
+            for<'c> fn(&'c Self, &ntru::Pkid<TW>) -> Option<&'c ntru::PrivateKey<TW, F>>,
+        >::new(self, Self::lookup_psk, Self::lookup_ntru_sk);
+        if let Some((key, role)) = self.session_keys.get(&preparsed.header.link) {
+            content = content.with_cached_key(key.clone(), *role);
+        }
+        if preparsed.check_content_type(keyload::EMBARGOED_TYPE) {
+            preparsed
+                .with_max_size(self.max_size)
+                .unwrap(&*self.store.borrow(), content.with_reveal_keys(&self.reveal_keys))
+        } else {
+            preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
+        }
+    }
+
+    pub fn unwrap_threshold_keyload<'a, 'b>(
+        &'b self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+    ) -> Fallible<
+        UnwrappedMessage<
+            TW,
+            F,
+            Link,
+            threshold_keyload::ContentUnwrap<
+                'b,
+                TW,
+                P,
+                keyload::ContentUnwrap<
+                    'b,
+                    TW,
+                    F,
+                    Link,
+                    Self,
+                    for<'c> fn(&'c Self, &psk::PskId<TW>) -> Option<&'c psk::Psk<TW>>,
+                    for<'c> fn(&'c Self, &ntru::Pkid<TW>) -> Option<&'c ntru::PrivateKey<TW, F>>,
+                >,
+            >,
+        >,
+    > {
+        self.ensure_appinst(&preparsed)?;
+        let author_set = match &self.author_set {
+            Some(author_set) => author_set,
+            None => bail!("Channel is not governed by Author set."),
+        };
+        let mut keyload = keyload::ContentUnwrap::<
             'b,
             TW,
             F,
@@ -410,32 +988,112 @@ where
             for<'c> fn(&'c Self, &psk::PskId<TW>) -> Option<&'c psk::Psk<TW>>,
             for<'c> fn(&'c Self, &ntru::Pkid<TW>) -> Option<&'c ntru::PrivateKey<TW, F>>,
         >::new(self, Self::lookup_psk, Self::lookup_ntru_sk);
-        preparsed.unwrap(&*self.store.borrow(), content)
+        if let Some((key, role)) = self.session_keys.get(&preparsed.header.link) {
+            keyload = keyload.with_cached_key(key.clone(), *role);
+        }
+        let content = threshold_keyload::ContentUnwrap::new(keyload, author_set);
+        preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
     }
 
     /// Try unwrapping session key from keyload using Subscriber's pre-shared key or NTRU private key (if any).
+    /// Embargoed keyload can be unwrapped only after its reveal key has been handled.
+    /// Keyload of a channel governed by Author set must be signed by at least threshold keys of the set.
     pub fn handle_keyload<'a>(
         &mut self,
         preparsed: PreparsedMessage<'a, TW, F, Link>,
         info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
     ) -> Fallible<()> {
-        let _content = self.unwrap_keyload(preparsed)?.commit(self.store.borrow_mut(), info)?;
-        // Unwrapped nonce and key in content are not used explicitly.
-        // The resulting spongos state is joined into a protected message state.
+        let (link, key, role) = if preparsed.check_content_type(threshold_keyload::TYPE) {
+            let unwrapped = self.unwrap_threshold_keyload(preparsed)?;
+            let link = unwrapped.link.clone();
+            let content = unwrapped.commit(self.store.borrow_mut(), info)?;
+            (link, content.keyload.key, content.keyload.role)
+        } else {
+            let unwrapped = self.unwrap_keyload(preparsed)?;
+            let link = unwrapped.link.clone();
+            let content = unwrapped.commit(self.store.borrow_mut(), info)?;
+            // Unwrapped nonce is not used explicitly.
+            // The resulting spongos state is joined into a protected message state.
+            (link, content.key, content.role)
+        };
+        self.session_keys.insert(link, (key, role));
+        self.role = Some(role);
         Ok(())
     }
 
+    /// Cache session key of keyload at `link` escrowed to own NTRU public key, see
+    /// `keyload::escrow_key`. The keyload can then be handled, the role granted is read-only.
+    pub fn import_escrowed_key(&mut self, link: Link, escrow: &Tbits<TW>) -> Fallible<()> {
+        let key = match &self.opt_ntru {
+            Some((ntru_sk, _)) => keyload::open_escrowed_key(ntru_sk, escrow),
+            None => bail!("Subscriber has no NTRU key pair to open escrow."),
+        };
+        match key {
+            Some(key) => {
+                self.session_keys.insert(link, (key, keyload::Role::ReadOnly));
+                Ok(())
+            }
+            None => bail!("Escrowed session key can't be opened."),
+        }
+    }
+
+    /// Links of keyloads and rekeys with cached session keys.
+    pub fn session_key_links(&self) -> impl Iterator<Item = &Link> {
+        self.session_keys.keys()
+    }
+
+    /// Forget session key of keyload at `link` together with the keyload state in the link
+    /// store, so that messages linked to the keyload can't be unwrapped anymore (eg. for
+    /// forward secrecy). States of messages already handled are kept. Returns whether
+    /// the key was cached.
+    pub fn forget_session_key(&mut self, link: &Link) -> bool {
+        self.store.borrow_mut().erase(link.rel());
+        self.packet_keyloads.retain(|_, keyload| keyload != link);
+        self.session_keys.remove(link).is_some()
+    }
+
+    /// Forget all cached session keys and states of their keyloads, see `forget_session_key`.
+    pub fn purge_session_keys(&mut self) {
+        let mut store = self.store.borrow_mut();
+        for link in self.session_keys.keys() {
+            store.erase(link.rel());
+        }
+        self.session_keys.clear();
+        self.packet_keyloads.clear();
+    }
+
+    /// Role granted by the last handled keyload, see `branch_role` for the role in a branch.
+    pub fn role(&self) -> Option<keyload::Role> {
+        self.role
+    }
+
+    /// Own NTRU public key, if any.
+    pub fn ntru_public_key(&self) -> Option<&ntru::PublicKey<TW, F>> {
+        self.opt_ntru.as_ref().map(|(_, pk)| pk)
+    }
+
     pub fn unwrap_signed_packet<'a>(
         &self,
         preparsed: PreparsedMessage<'a, TW, F, Link>,
+    ) -> Fallible<UnwrappedMessage<TW, F, Link, signed_packet::ContentUnwrap<TW, F, P, Link>>> {
+        self.unwrap_signed_packet_with_aad(preparsed, None)
+    }
+
+    pub fn unwrap_signed_packet_with_aad<'a>(
+        &self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        aad: Option<&Trytes<TW>>,
     ) -> Fallible<UnwrappedMessage<TW, F, Link, signed_packet::ContentUnwrap<TW, F, P, Link>>> {
         self.ensure_appinst(&preparsed)?;
         ensure!(
             self.author_mss_pk.is_some(),
             "No Author's MSS public key found, can't verify signature."
         );
-        let content = signed_packet::ContentUnwrap::new();
-        preparsed.unwrap(&*self.store.borrow(), content)
+        let mut content = signed_packet::ContentUnwrap::new();
+        if let Some(aad) = aad {
+            content = content.with_aad(aad.clone());
+        }
+        preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
     }
 
     /// Verify new Author's MSS public key and update Author's MSS public key.
@@ -443,27 +1101,260 @@ where
         &mut self,
         preparsed: PreparsedMessage<'a, TW, F, Link>,
         info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<(Trytes<TW>, Trytes<TW>)> {
+        self.handle_signed_packet_with_aad(preparsed, None, info)
+    }
+
+    /// Verify signature covering additional authenticated data `aad`, get public payload and decrypt masked payload.
+    pub fn handle_signed_packet_with_aad<'a>(
+        &mut self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        aad: Option<&Trytes<TW>>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
     ) -> Fallible<(Trytes<TW>, Trytes<TW>)> {
         ensure!(self.author_mss_pk.is_some(), "No Author's MSS public key found.");
+        let author_mss_pk = self.author_mss_pk.clone();
+        self.handle_signed_packet_with_key(preparsed, aad, author_mss_pk.as_ref().unwrap(), info)
+    }
+
+    /// Verify signature with branch MSS public key `mss_pk` bound with BranchKey message
+    /// instead of Author's one.
+    pub fn handle_signed_packet_with_key<'a>(
+        &mut self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        aad: Option<&Trytes<TW>>,
+        mss_pk: &mss::PublicKey<TW, P>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<(Trytes<TW>, Trytes<TW>)> {
+        let padded = preparsed.header.tlv_ext::<payload_padding::PaddedPayload>()?.is_some();
+        let unwrapped = self.unwrap_signed_packet_with_aad(preparsed, aad)?;
+        self.ensure_author_mss_pk(Some(mss_pk), &unwrapped.content.mss_pk, signed_packet::TYPE)?;
+        let content = unwrapped.commit(self.store.borrow_mut(), info)?;
+        if padded {
+            Ok((content.public_payload, payload_padding::unpad(&content.masked_payload)?))
+        } else {
+            Ok((content.public_payload, content.masked_payload))
+        }
+    }
+
+    pub fn unwrap_structured_packet<'a, 'b>(
+        &'b self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+    ) -> Fallible<UnwrappedMessage<TW, F, Link, structured_packet::ContentUnwrap<'b, TW, F, P, Link>>> {
+        self.ensure_appinst(&preparsed)?;
+        ensure!(
+            self.author_mss_pk.is_some(),
+            "No Author's MSS public key found, can't verify signature."
+        );
+        let opt_psk = self.opt_psk.as_ref().map(|(pskid, psk)| (pskid, psk));
+        let content = structured_packet::ContentUnwrap::new(opt_psk);
+        preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
+    }
+
+    /// Verify Author's signature and return fields, values of fields for other audiences are omitted.
+    pub fn handle_structured_packet<'a>(
+        &mut self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<Vec<structured_packet::Field<TW>>> {
+        let unwrapped = self.unwrap_structured_packet(preparsed)?;
+        self.ensure_author_mss_pk(self.author_mss_pk.as_ref(), &unwrapped.content.mss_pk, structured_packet::TYPE)?;
+        let content = unwrapped.commit(self.store.borrow_mut(), info)?;
+        Ok(content.fields)
+    }
+
+    pub fn unwrap_multi_tagged_packet<'a, 'b>(
+        &'b self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+    ) -> Fallible<UnwrappedMessage<TW, F, Link, multi_tagged_packet::ContentUnwrap<'b, TW, F, Link>>> {
+        self.ensure_appinst(&preparsed)?;
+        ensure!(self.opt_psk.is_some(), "No own pre-shared key found, can't verify MAC.");
+        let opt_psk = self.opt_psk.as_ref().map(|(pskid, psk)| (pskid, psk));
+        let content = multi_tagged_packet::ContentUnwrap::new(opt_psk);
+        preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
+    }
+
+    /// Get public payload, decrypt masked payload and verify MAC of own pre-shared key group.
+    pub fn handle_multi_tagged_packet<'a>(
+        &mut self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<(Trytes<TW>, Trytes<TW>)> {
         let content = self
-            .unwrap_signed_packet(preparsed)?
+            .unwrap_multi_tagged_packet(preparsed)?
             .commit(self.store.borrow_mut(), info)?;
+        Ok((content.public_payload, content.masked_payload))
+    }
+
+    pub fn unwrap_participants<'a>(
+        &self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+    ) -> Fallible<UnwrappedMessage<TW, F, Link, participants::ContentUnwrap<TW, F, P, Link>>> {
+        self.ensure_appinst(&preparsed)?;
         ensure!(
-            self.author_mss_pk
-                .as_ref()
-                .map_or(false, |mss_pk| *mss_pk == content.mss_pk),
-            "Bad signed packet signature."
+            self.author_mss_pk.is_some(),
+            "No Author's MSS public key found, can't verify signature."
         );
-        Ok((content.public_payload, content.masked_payload))
+        let content = participants::ContentUnwrap::new();
+        preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
+    }
+
+    /// Verify Author's signature and replace the known participants with the listed ones.
+    pub fn handle_participants<'a>(
+        &mut self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<()> {
+        let unwrapped = self.unwrap_participants(preparsed)?;
+        self.ensure_author_mss_pk(self.author_mss_pk.as_ref(), &unwrapped.content.mss_pk, participants::TYPE)?;
+        let content = unwrapped.commit(self.store.borrow_mut(), info)?;
+        self.participants = content.ntru_pks;
+        Ok(())
+    }
+
+    /// Subscribers' NTRU public keys and roles listed in the last handled Participants message.
+    pub fn participants(&self) -> &[(ntru::PublicKey<TW, F>, keyload::Role)] {
+        &self.participants
+    }
+
+    pub fn unwrap_reveal_key<'a>(
+        &self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+    ) -> Fallible<UnwrappedMessage<TW, F, Link, reveal_key::ContentUnwrap<TW, F, P, Link>>> {
+        self.ensure_appinst(&preparsed)?;
+        ensure!(
+            self.author_mss_pk.is_some(),
+            "No Author's MSS public key found, can't verify signature."
+        );
+        let content = reveal_key::ContentUnwrap::new();
+        preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
+    }
+
+    /// Verify Author's signature and remember the reveal key, return its id.
+    /// Embargoed keyloads locked with the key can be handled afterwards.
+    pub fn handle_reveal_key<'a>(
+        &mut self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<psk::PskId<TW>> {
+        let unwrapped = self.unwrap_reveal_key(preparsed)?;
+        self.ensure_author_mss_pk(self.author_mss_pk.as_ref(), &unwrapped.content.mss_pk, reveal_key::TYPE)?;
+        let content = unwrapped.commit(self.store.borrow_mut(), info)?;
+        self.reveal_keys
+            .insert(content.reveal_key_id.0.clone(), content.reveal_key.0);
+        Ok(content.reveal_key_id.0)
+    }
+
+    pub fn unwrap_rekey<'a>(
+        &self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+    ) -> Fallible<UnwrappedMessage<TW, F, Link, rekey::ContentUnwrap<TW, F, Link>>> {
+        self.ensure_appinst(&preparsed)?;
+        let content = rekey::ContentUnwrap::new();
+        preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
+    }
+
+    /// Unwrap the refreshed session key, the rekey can only be handled after the keyload
+    /// (or rekey) it is linked to. The role granted by the linked keyload is kept.
+    pub fn handle_rekey<'a>(
+        &mut self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<()> {
+        let unwrapped = self.unwrap_rekey(preparsed)?;
+        let role = match self.session_keys.iter().find(|(l, _)| *l.rel() == unwrapped.content.link) {
+            Some((_, (_, role))) => *role,
+            None => bail!("Rekey is not linked to a handled keyload."),
+        };
+        let link = unwrapped.link.clone();
+        let content = unwrapped.commit(self.store.borrow_mut(), info)?;
+        self.session_keys.insert(link, (content.key, role));
+        self.role = Some(role);
+        Ok(())
+    }
+
+    pub fn unwrap_heartbeat<'a>(
+        &self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+    ) -> Fallible<UnwrappedMessage<TW, F, Link, heartbeat::ContentUnwrap<TW, F, P, Link>>> {
+        self.ensure_appinst(&preparsed)?;
+        ensure!(
+            self.author_mss_pk.is_some(),
+            "No Author's MSS public key found, can't verify signature."
+        );
+        let content = heartbeat::ContentUnwrap::new();
+        preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
+    }
+
+    /// Verify Author's signature and remember the heartbeat. Heartbeats not newer than
+    /// the last handled one are rejected, so that they can't be replayed.
+    pub fn handle_heartbeat<'a>(
+        &mut self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<heartbeat::Heartbeat> {
+        let unwrapped = self.unwrap_heartbeat(preparsed)?;
+        self.ensure_author_mss_pk(self.author_mss_pk.as_ref(), &unwrapped.content.mss_pk, heartbeat::TYPE)?;
+        let content = unwrapped.commit(self.store.borrow_mut(), info)?;
+        let heartbeat = heartbeat::Heartbeat {
+            counter: content.counter.0,
+            timestamp: content.timestamp.0,
+        };
+        if let Some(last) = self.last_heartbeat {
+            ensure!(
+                last.counter < heartbeat.counter,
+                "Stale heartbeat: {}, the last one is {}.",
+                heartbeat.counter,
+                last.counter
+            );
+        }
+        self.last_heartbeat = Some(heartbeat);
+        Ok(heartbeat)
+    }
+
+    pub fn unwrap_close_branch<'a>(
+        &self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+    ) -> Fallible<UnwrappedMessage<TW, F, Link, close_branch::ContentUnwrap<TW, F, P, Link>>> {
+        self.ensure_appinst(&preparsed)?;
+        ensure!(
+            self.author_mss_pk.is_some(),
+            "No Author's MSS public key found, can't verify signature."
+        );
+        let content = close_branch::ContentUnwrap::new();
+        preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
+    }
+
+    /// Verify Author's signature, return link to the message in the closed branch.
+    pub fn handle_close_branch<'a>(
+        &mut self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<<Link as HasLink>::Rel> {
+        let unwrapped = self.unwrap_close_branch(preparsed)?;
+        self.ensure_author_mss_pk(self.author_mss_pk.as_ref(), &unwrapped.content.mss_pk, close_branch::TYPE)?;
+        let content = unwrapped.commit(self.store.borrow_mut(), info)?;
+        Ok(content.link)
     }
 
     pub fn unwrap_tagged_packet<'a>(
         &self,
         preparsed: PreparsedMessage<'a, TW, F, Link>,
+    ) -> Fallible<UnwrappedMessage<TW, F, Link, tagged_packet::ContentUnwrap<TW, F, Link>>> {
+        self.unwrap_tagged_packet_with_aad(preparsed, None)
+    }
+
+    pub fn unwrap_tagged_packet_with_aad<'a>(
+        &self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        aad: Option<&Trytes<TW>>,
     ) -> Fallible<UnwrappedMessage<TW, F, Link, tagged_packet::ContentUnwrap<TW, F, Link>>> {
         self.ensure_appinst(&preparsed)?;
-        let content = tagged_packet::ContentUnwrap::new();
-        preparsed.unwrap(&*self.store.borrow(), content)
+        let mut content = tagged_packet::ContentUnwrap::new();
+        if let Some(aad) = aad {
+            content = content.with_aad(aad.clone());
+        }
+        preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
     }
 
     /// Get public payload, decrypt masked payload and verify MAC.
@@ -472,10 +1363,102 @@ where
         preparsed: PreparsedMessage<'a, TW, F, Link>,
         info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
     ) -> Fallible<(Trytes<TW>, Trytes<TW>)> {
+        self.handle_tagged_packet_with_aad(preparsed, None, info)
+    }
+
+    /// Get public payload, decrypt masked payload and verify MAC covering additional authenticated data `aad`.
+    pub fn handle_tagged_packet_with_aad<'a>(
+        &mut self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        aad: Option<&Trytes<TW>>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<(Trytes<TW>, Trytes<TW>)> {
+        let padded = preparsed.header.tlv_ext::<payload_padding::PaddedPayload>()?.is_some();
+        let link = preparsed.header.link.clone();
         let content = self
-            .unwrap_tagged_packet(preparsed)?
+            .unwrap_tagged_packet_with_aad(preparsed, aad)?
             .commit(self.store.borrow_mut(), info)?;
-        Ok((content.public_payload, content.masked_payload))
+        self.record_packet(&link, &content.link);
+        if padded {
+            Ok((content.public_payload, payload_padding::unpad(&content.masked_payload)?))
+        } else {
+            Ok((content.public_payload, content.masked_payload))
+        }
+    }
+
+    /// Get public payload, pass masked payload decrypted in chunks of `chunk_size` trytes to
+    /// `on_chunk` and verify MAC. Chunks are passed before the MAC is verified, they must be
+    /// discarded if it fails. Padded payloads can't be streamed.
+    pub fn handle_tagged_packet_streaming<'a>(
+        &mut self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        aad: Option<&Trytes<TW>>,
+        chunk_size: usize,
+        on_chunk: &mut dyn FnMut(&Trytes<TW>) -> Fallible<()>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<Trytes<TW>> {
+        self.ensure_appinst(&preparsed)?;
+        ensure!(
+            preparsed.header.tlv_ext::<payload_padding::PaddedPayload>()?.is_none(),
+            "Padded payloads can't be streamed."
+        );
+        let mut content = tagged_packet::StreamContentUnwrap::<TW, F, Link>::new(chunk_size, on_chunk);
+        if let Some(aad) = aad {
+            content = content.with_aad(aad.clone());
+        }
+        let link = preparsed.header.link.clone();
+        let unwrapped = preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)?;
+        let content = unwrapped.commit(self.store.borrow_mut(), info)?;
+        self.record_packet(&link, &content.link);
+        Ok(content.public_payload)
+    }
+
+    pub fn unwrap_framed_packet<'a>(
+        &self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+    ) -> Fallible<UnwrappedMessage<TW, F, Link, framed_packet::ContentUnwrap<TW, F, Link>>> {
+        self.ensure_appinst(&preparsed)?;
+        let content = framed_packet::ContentUnwrap::new();
+        preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
+    }
+
+    /// Get public payload, decrypt and reassemble frames of masked payload, verify tags and MAC.
+    /// A corrupted frame fails with `framed_packet::FrameCorrupted` error.
+    pub fn handle_framed_packet<'a>(
+        &mut self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<(Trytes<TW>, Trytes<TW>)> {
+        let link = preparsed.header.link.clone();
+        let content = self
+            .unwrap_framed_packet(preparsed)?
+            .commit(self.store.borrow_mut(), info)?;
+        self.record_packet(&link, &content.link);
+        let masked_payload = content.masked_payload();
+        Ok((content.public_payload, masked_payload))
+    }
+
+    pub fn unwrap_lite_packet<'a>(
+        &self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+    ) -> Fallible<UnwrappedMessage<TW, F, Link, lite_packet::ContentUnwrap<TW, F, Link>>> {
+        self.ensure_appinst(&preparsed)?;
+        let content = lite_packet::ContentUnwrap::new(self.ensure_lite_mac()?);
+        preparsed.with_max_size(self.max_size).unwrap(&*self.store.borrow(), content)
+    }
+
+    /// Decrypt payload and verify truncated MAC.
+    pub fn handle_lite_packet<'a>(
+        &mut self,
+        preparsed: PreparsedMessage<'a, TW, F, Link>,
+        info: <Store as LinkStore<TW, F, <Link as HasLink>::Rel>>::Info,
+    ) -> Fallible<Trytes<TW>> {
+        let link = preparsed.header.link.clone();
+        let content = self
+            .unwrap_lite_packet(preparsed)?
+            .commit(self.store.borrow_mut(), info)?;
+        self.record_packet(&link, &content.link);
+        Ok(content.payload)
     }
 
     /*
@@ -519,3 +1502,28 @@ where
        }
     */
 }
+
+impl<TW, F, P, Link, Store, LinkGen> HeapSize for SubscriberT<TW, F, P, Link, Store, LinkGen>
+where
+    P: mss::Parameters<TW>,
+    Link: HeapSize,
+    Store: HeapSize,
+{
+    /// Heap owned by keys, participants and link store, link generator is not accounted.
+    fn heap_size(&self) -> usize {
+        self.prng.heap_size()
+            + self.opt_psk.heap_size()
+            + self.opt_ntru.heap_size()
+            + self.appinst.heap_size()
+            + self.author_mss_pk.heap_size()
+            + self.announced_mss_pk.heap_size()
+            + self.author_set.heap_size()
+            + self.author_ntru_pk.heap_size()
+            + self.reveal_keys.heap_size()
+            + self.session_keys.heap_size()
+            + self.packet_keyloads.heap_size()
+            + self.participants.heap_size()
+            + self.successor_mss_sk.heap_size()
+            + self.store.borrow().heap_size()
+    }
+}
@@ -0,0 +1,188 @@
+//! Portable channel archives for offline audit.
+//!
+//! An archive is a self-contained sequence of channel messages, oldest first. Each entry
+//! keeps the message link, the wrapped message body (header + content) as is and the
+//! index of the archived message it is joined to. An archive can be handed over without
+//! transport access and replayed with the usual Subscriber API over `to_transport`.
+//!
+//! Binary layout, integers are big-endian:
+//!
+//! ```text
+//! archive = magic[15] version:u8 count:u32 entry*count
+//! entry   = link[66] prev:u32 body_size:u32 body[(body_size + 4) / 5]
+//! ```
+//!
+//! `link` and `body` trits are packed 5 per byte (see `TangleAddress::to_packed`),
+//! `body_size` is in trits and `prev` is the index of the joined entry plus one,
+//! or 0 for Announce and for a first entry joined to a message outside the archive.
+
+use failure::{
+    bail,
+    ensure,
+    Fallible,
+};
+use std::{
+    collections::HashSet,
+    convert::TryInto,
+};
+
+use super::*;
+use iota_streams_app::{
+    message::HasLink as _,
+    transport::Transport as _,
+};
+
+/// Archive header.
+const ARCHIVE_MAGIC: &[u8; 15] = b"STREAMS9ARCHIVE";
+
+/// Archive format version.
+const ARCHIVE_VERSION: u8 = 1;
+
+/// Archived message together with its ordering metadata.
+pub struct ArchiveEntry {
+    /// Wrapped message.
+    pub msg: Message,
+    /// Index of the archived message `msg` is joined to.
+    pub prev: Option<usize>,
+}
+
+/// Sequence of messages of a single channel, oldest first.
+pub struct StreamsArchive {
+    entries: Vec<ArchiveEntry>,
+}
+
+/// Link to the message `msg` is joined to or nothing for Announce message.
+fn joined_link(msg: &Message) -> Fallible<Option<Address>> {
+    let preparsed = msg.parse_header()?;
+    if is_announcement(&preparsed) {
+        Ok(None)
+    } else {
+        Ok(Some(Address::new(msg.link.base().clone(), preparsed.peek_link()?)))
+    }
+}
+
+impl StreamsArchive {
+    /// Create archive from channel messages ordered oldest first.
+    pub fn from_msgs(msgs: Vec<Message>) -> Fallible<Self> {
+        let mut entries: Vec<ArchiveEntry> = Vec::with_capacity(msgs.len());
+        for msg in msgs {
+            let prev = match joined_link(&msg)? {
+                Some(link) => entries.iter().position(|e| e.msg.link == link),
+                None => None,
+            };
+            entries.push(ArchiveEntry { msg, prev });
+        }
+        let archive = Self { entries };
+        verify_archive(&archive)?;
+        Ok(archive)
+    }
+
+    /// Archived messages with ordering metadata, oldest first.
+    pub fn entries(&self) -> &[ArchiveEntry] {
+        &self.entries
+    }
+
+    /// Address of the archived channel.
+    pub fn channel_address(&self) -> Option<&ChannelAddress> {
+        self.entries.first().map(|e| e.msg.link.base())
+    }
+
+    /// Transport holding archived messages, Subscriber can handle them offline.
+    pub fn to_transport(&self) -> BucketTransport {
+        let mut transport = BucketTransport::new();
+        for e in &self.entries {
+            // Sending to `BucketTransport` doesn't fail.
+            transport.send_message(&e.msg).unwrap();
+        }
+        transport
+    }
+
+    /// Encode archive into bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(ARCHIVE_MAGIC);
+        bytes.push(ARCHIVE_VERSION);
+        bytes.extend_from_slice(&(self.entries.len() as u32).to_be_bytes());
+        for e in &self.entries {
+            bytes.extend(e.msg.link.to_packed());
+            bytes.extend_from_slice(&(e.prev.map_or(0, |i| i + 1) as u32).to_be_bytes());
+            bytes.extend_from_slice(&(e.msg.body.size() as u32).to_be_bytes());
+            pack_trits(&e.msg.body, &mut bytes);
+        }
+        bytes
+    }
+
+    /// Decode archive from bytes, it's not verified.
+    fn from_bytes(bytes: &[u8]) -> Fallible<Self> {
+        let mut r = Reader(bytes);
+        ensure!(r.take(ARCHIVE_MAGIC.len())? == ARCHIVE_MAGIC, "Bad archive header.");
+        let version = r.take(1)?[0];
+        ensure!(version == ARCHIVE_VERSION, "Archive version not supported: {}.", version);
+        let count = r.u32()?;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let link = Address::from_packed(r.take(PACKED_LINK_SIZE)?)?;
+            let prev = match r.u32()? {
+                0 => None,
+                i => Some(i - 1),
+            };
+            let body_size = r.u32()?;
+            let body = unpack_trits(r.take(packed_len(body_size))?, body_size)?;
+            entries.push(ArchiveEntry {
+                msg: Message::new(link, body),
+                prev,
+            });
+        }
+        ensure!(r.0.is_empty(), "Trailing bytes in archive: {}.", r.0.len());
+        Ok(Self { entries })
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Fallible<&'a [u8]> {
+        ensure!(self.0.len() >= n, "Archive is truncated.");
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Fallible<usize> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into()?) as usize)
+    }
+}
+
+/// Check archive integrity: all messages belong to the same channel, headers can be
+/// parsed and ordering metadata matches the links messages are joined to.
+///
+/// Messages are not unwrapped, ie. signatures are checked when the archive is replayed.
+pub fn verify_archive(archive: &StreamsArchive) -> Fallible<()> {
+    let appinst = match archive.channel_address() {
+        Some(appinst) => appinst,
+        None => bail!("Archive is empty."),
+    };
+    let mut msgids = HashSet::new();
+    for (i, e) in archive.entries.iter().enumerate() {
+        ensure!(e.msg.link.base() == appinst, "Archived message {} is from another channel.", i);
+        ensure!(msgids.insert(e.msg.link.rel().clone()), "Archived message {} is duplicated.", i);
+        match (joined_link(&e.msg)?, e.prev) {
+            (None, None) => ensure!(i == 0, "Announce must be the first archived message."),
+            (Some(link), Some(prev)) => ensure!(
+                prev < i && archive.entries[prev].msg.link == link,
+                "Archived message {} is out of order.",
+                i
+            ),
+            (Some(_), None) => ensure!(i == 0, "Archived message {} is joined to a missing message.", i),
+            (None, Some(_)) => bail!("Archived Announce can't be joined to a message."),
+        }
+    }
+    Ok(())
+}
+
+/// Decode and verify archive produced by `StreamsArchive::to_bytes`.
+pub fn read_archive(bytes: &[u8]) -> Fallible<StreamsArchive> {
+    let archive = StreamsArchive::from_bytes(bytes)?;
+    verify_archive(&archive)?;
+    Ok(archive)
+}
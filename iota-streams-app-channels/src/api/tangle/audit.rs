@@ -0,0 +1,246 @@
+//! End-to-end verification of a full set of channel messages for third-party audit.
+//!
+//! Messages are replayed offline from the announcement in the order they are joined. Each
+//! message is checked to belong to the channel, to be joined to an audited message, to have
+//! its link sequenced from the joined message and to be authentic. Messages are unwrapped
+//! with a Subscriber: a keyless auditor authenticates messages of public branches only,
+//! branches started by keyloads are authenticated by an auditor holding a recipient key.
+
+use failure::{
+    bail,
+    Fallible,
+};
+use std::{
+    collections::{
+        HashMap,
+        HashSet,
+    },
+    fmt,
+};
+
+use super::{
+    manager::{
+        unwrap,
+        Received,
+    },
+    *,
+};
+use crate::message::{
+    keyload,
+    rekey,
+    threshold_keyload,
+};
+use iota_streams_app::message::HasLink as _;
+use iota_streams_protobuf3::types::LinkStore;
+
+/// Seed of the keyless auditor used by `audit_channel`.
+const AUDITOR_SEED: &str = "STREAMS9AUDITOR";
+
+/// Problem found by an audit.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum AuditIssue {
+    /// There is no announcement in the audited messages.
+    MissingAnnouncement,
+    /// Message is from another channel.
+    ForeignChannel(Address),
+    /// Message header can't be parsed.
+    Malformed(Address, String),
+    /// Message is joined to a message missing from the audited messages.
+    Gap { link: Address, joined: Address },
+    /// Different messages are published at the same link.
+    Fork(Address),
+    /// Message link is not generated from the joined message.
+    BadSequencing(Address),
+    /// Message can't be authenticated although the message it is joined to is.
+    Invalid(Address, String),
+}
+
+impl fmt::Display for AuditIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditIssue::MissingAnnouncement => write!(f, "missing announcement"),
+            AuditIssue::ForeignChannel(link) => write!(f, "foreign message {}", link),
+            AuditIssue::Malformed(link, err) => write!(f, "malformed message {}: {}", link, err),
+            AuditIssue::Gap { link, joined } => write!(f, "gap: message {} is joined to missing {}", link, joined),
+            AuditIssue::Fork(link) => write!(f, "fork at {}", link),
+            AuditIssue::BadSequencing(link) => write!(f, "bad sequencing of message {}", link),
+            AuditIssue::Invalid(link, err) => write!(f, "invalid message {}: {}", link, err),
+        }
+    }
+}
+
+/// Result of an audit.
+#[derive(Clone, Default, Debug)]
+pub struct AuditReport {
+    /// Authenticated messages in replay order, starting with the announcement.
+    pub verified: Vec<Address>,
+    /// Messages not authenticated for lack of keys, messages not meant for subscribers
+    /// (eg. Subscribe) and messages joined to them.
+    pub unverified: Vec<Address>,
+    pub issues: Vec<AuditIssue>,
+}
+
+impl AuditReport {
+    /// No issues are found.
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for AuditReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "verified: {}, unverified: {}, issues: {}",
+            self.verified.len(),
+            self.unverified.len(),
+            self.issues.len()
+        )?;
+        for issue in &self.issues {
+            writeln!(f, "{}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+/// Audit channel messages with a keyless auditor, see `audit_channel_with`.
+pub fn audit_channel(msgs: &[Message], max_gap: usize) -> Fallible<AuditReport> {
+    audit_channel_with(&mut SubscriberBuilder::new(AUDITOR_SEED).build(), msgs, max_gap)
+}
+
+/// Audit channel messages given in any order with an unregistered `auditor`. Message links
+/// are checked against publisher counters up to the number of messages plus `max_gap`.
+/// Keyloads the auditor is not a recipient of are reported unverified, not invalid.
+pub fn audit_channel_with<S>(auditor: &mut Subscriber<S>, msgs: &[Message], max_gap: usize) -> Fallible<AuditReport>
+where
+    S: LinkStore<DefaultTW, DefaultF, MsgId<DefaultTW>, Info = MsgInfo>,
+{
+    let mut report = AuditReport::default();
+
+    // Identical copies (eg. reattachments) are audited once.
+    let mut by_link: HashMap<&Address, &Message> = HashMap::new();
+    let mut msgs: Vec<&Message> = msgs
+        .iter()
+        .filter(|msg| match by_link.get(&msg.link) {
+            Some(other) => {
+                if other.body != msg.body && !report.issues.contains(&AuditIssue::Fork(msg.link.clone())) {
+                    report.issues.push(AuditIssue::Fork(msg.link.clone()));
+                }
+                false
+            }
+            None => {
+                by_link.insert(&msg.link, msg);
+                true
+            }
+        })
+        .collect();
+
+    let announcement = match msgs
+        .iter()
+        .position(|msg| msg.parse_header().map_or(false, |preparsed| is_announcement(&preparsed)))
+    {
+        Some(i) => msgs.remove(i),
+        None => {
+            report.issues.push(AuditIssue::MissingAnnouncement);
+            return Ok(report);
+        }
+    };
+    if let Err(err) = auditor.unwrap_announcement(announcement.parse_header()?) {
+        report
+            .issues
+            .push(AuditIssue::Invalid(announcement.link.clone(), err.to_string()));
+        return Ok(report);
+    }
+    report.verified.push(announcement.link.clone());
+    let link_gen = auditor.fetch_state()?.0;
+    let max_counter = msgs.len() + max_gap;
+
+    let mut pending = Vec::new();
+    for msg in msgs {
+        if msg.link.base() != announcement.link.base() {
+            report.issues.push(AuditIssue::ForeignChannel(msg.link.clone()));
+            continue;
+        }
+        let joined = match msg.parse_header().and_then(|preparsed| {
+            if is_announcement(&preparsed) {
+                bail!("Repeated announcement.");
+            }
+            Ok(Address::new(msg.link.base().clone(), preparsed.peek_link()?))
+        }) {
+            Ok(joined) => joined,
+            Err(err) => {
+                report.issues.push(AuditIssue::Malformed(msg.link.clone(), err.to_string()));
+                continue;
+            }
+        };
+        if !(0..=max_counter).any(|counter| link_gen.link_at(&joined.msgid, counter) == msg.link) {
+            report.issues.push(AuditIssue::BadSequencing(msg.link.clone()));
+        }
+        pending.push((msg, joined));
+    }
+
+    // Authentication status of replayed messages.
+    let mut replayed = HashMap::new();
+    replayed.insert(announcement.link.msgid.clone(), true);
+    loop {
+        let (ready, rest): (Vec<_>, Vec<_>) = pending
+            .into_iter()
+            .partition(|(_, joined)| replayed.contains_key(&joined.msgid));
+        pending = rest;
+        if ready.is_empty() {
+            break;
+        }
+        for (msg, joined) in ready {
+            let verified = replayed[&joined.msgid] && authenticate(auditor, msg, &mut report);
+            if verified {
+                report.verified.push(msg.link.clone());
+            } else if !report.issues.iter().any(|issue| is_invalid(issue, &msg.link)) {
+                report.unverified.push(msg.link.clone());
+            }
+            replayed.insert(msg.link.msgid.clone(), verified);
+        }
+    }
+
+    // Messages left are joined to missing messages or to their descendants.
+    let left: HashSet<_> = pending.iter().map(|(msg, _)| msg.link.msgid.clone()).collect();
+    for (msg, joined) in pending {
+        if left.contains(&joined.msgid) {
+            report.unverified.push(msg.link.clone());
+        } else {
+            report.issues.push(AuditIssue::Gap {
+                link: msg.link.clone(),
+                joined,
+            });
+        }
+    }
+    Ok(report)
+}
+
+/// Unwrap the message, report it invalid unless it is a keyload for other recipients.
+fn authenticate<S>(auditor: &mut Subscriber<S>, msg: &Message, report: &mut AuditReport) -> bool
+where
+    S: LinkStore<DefaultTW, DefaultF, MsgId<DefaultTW>, Info = MsgInfo>,
+{
+    match unwrap(auditor, msg) {
+        Ok(Received::Other(_)) => false,
+        Ok(_) => true,
+        Err(err) => {
+            let is_keyload = msg.parse_header().map_or(false, |preparsed| {
+                preparsed.check_content_type(keyload::TYPE)
+                    || preparsed.check_content_type(threshold_keyload::TYPE)
+                    || preparsed.check_content_type(rekey::TYPE)
+            });
+            if !is_keyload {
+                report.issues.push(AuditIssue::Invalid(msg.link.clone(), err.to_string()));
+            }
+            false
+        }
+    }
+}
+
+fn is_invalid(issue: &AuditIssue, link: &Address) -> bool {
+    match issue {
+        AuditIssue::Invalid(invalid, _) => invalid == link,
+        _ => false,
+    }
+}
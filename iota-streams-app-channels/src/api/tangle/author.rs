@@ -1,64 +1,592 @@
 //! Customize Author with default implementation for use over the Tangle.
 
-use failure::Fallible;
+use failure::{
+    ensure,
+    Fallible,
+};
 use std::str::FromStr;
 
-use super::*;
-use crate::api::author::AuthorT;
+use super::{
+    branch::BranchKeys,
+    cover::Cover,
+    pending::PendingSubscriptions,
+    rate::RateGuard,
+    report::Tracker,
+    *,
+};
+use crate::{
+    api::author::AuthorT,
+    message::{
+        change_key,
+        handover,
+        keyload::{
+            self,
+            RecipientPadding,
+            Role,
+        },
+        lite_packet::LiteMac,
+        rekey,
+        tagged_packet,
+        threshold_keyload,
+    },
+};
 use iota_streams_app::message::HasLink as _;
 
 use iota_streams_core::{
+    heap::HeapSize,
     prng,
-    tbits::Tbits,
+    tbits::{
+        trinary,
+        Tbits,
+    },
+};
+use iota_streams_protobuf3::types::{
+    LinkStore,
+    size_trytes,
+    Trint3,
 };
 
-type AuthorImp = AuthorT<DefaultTW, DefaultF, DefaultP, Address, Store, LinkGen>;
+type AuthorImp<S> = AuthorT<DefaultTW, DefaultF, DefaultP, Address, S, LinkGen>;
+
+/// Author type parametrised by link store.
+pub struct Author<S = Store> {
+    pub(super) imp: AuthorImp<S>,
+    pub(super) rate: RateGuard,
+    pub(super) tracker: Tracker,
+    pub(super) cover: Cover,
+    pub(super) branch_keys: BranchKeys<MssPrivateKey>,
+    pub(super) pending: PendingSubscriptions,
+    pub(super) time_provider: SharedTimeProvider,
+}
+
+/// MSS key nonce of a channel with index `channel_idx`.
+/// Channel with index 0 uses the plain nonce, others have the index trytes appended.
+fn channel_nonce(channel_idx: usize) -> Tbits<DefaultTW> {
+    let nonce = Tbits::from_str("TANGLEAUTHOR").unwrap();
+    if channel_idx == 0 {
+        return nonce;
+    }
 
-/// Author type.
-pub struct Author {
-    imp: AuthorImp,
+    let mut idx = Tbits::zero(3 * size_trytes(channel_idx));
+    {
+        let mut slice = idx.slice_mut();
+        let mut n = channel_idx;
+        while !slice.is_empty() {
+            let (r, q) = trinary::mods3_usize(n);
+            slice.advance(3).put3(r);
+            n = q;
+        }
+    }
+    &nonce + &idx
 }
 
 impl Author {
     /// Create a new Author instance, generate new MSS keypair and optionally NTRU keypair.
+    #[deprecated(note = "Use `AuthorBuilder` instead.")]
     pub fn new(seed: &str, mss_height: usize, with_ntru: bool) -> Self {
-        let nonce = Tbits::from_str("TANGLEAUTHOR").unwrap();
+        Self::gen(Store::default(), seed, 0, mss_height, with_ntru, MsgIdDerivation::default(), None)
+    }
+
+    /// Create a new Author instance for the channel with index `channel_idx`.
+    /// The same seed can run multiple channels, each index derives its own MSS keypair
+    /// and hence its own channel address; `new` is the same as index 0.
+    #[deprecated(note = "Use `AuthorBuilder::channel_index` instead.")]
+    pub fn new_at_index(seed: &str, channel_idx: usize, mss_height: usize, with_ntru: bool) -> Self {
+        Self::gen(Store::default(), seed, channel_idx, mss_height, with_ntru, MsgIdDerivation::default(), None)
+    }
+
+    /// Create a new Author instance deriving msgids with `msgid_derivation`.
+    /// The derivation is announced, `MsgIdDerivation::Troika` keeps links stable
+    /// across changes of the channel PRP.
+    #[deprecated(note = "Use `AuthorBuilder::msgid_derivation` instead.")]
+    pub fn new_with_msgid_derivation(
+        seed: &str,
+        channel_idx: usize,
+        mss_height: usize,
+        with_ntru: bool,
+        msgid_derivation: MsgIdDerivation,
+    ) -> Self {
+        Self::gen(Store::default(), seed, channel_idx, mss_height, with_ntru, msgid_derivation, None)
+    }
+
+    /// Addresses of channels with indices `0..n` created from `seed`, eg. to recover them.
+    /// Each address requires MSS keypair generation and thus is as slow as creating Author.
+    pub fn channel_addresses(seed: &str, mss_height: usize, n: usize) -> Vec<ChannelAddress> {
+        (0..n)
+            .map(|channel_idx| {
+                Self::gen(Store::default(), seed, channel_idx, mss_height, false, MsgIdDerivation::default(), None)
+                    .channel_address()
+                    .clone()
+            })
+            .collect()
+    }
+
+    /// Short display id of a link, it must not collide with other links in the store.
+    pub fn short_link_id(&self, link: &Address) -> Fallible<String> {
+        link.msgid.to_short_id_checked(self.imp.store.borrow().links())
+    }
+
+    /// Resolve short display id against links in the store.
+    pub fn resolve_short_link_id(&self, short_id: &str) -> Fallible<Address> {
+        let msgid = MsgId::resolve_short_id(short_id, self.imp.store.borrow().links())?;
+        Ok(Address::new(self.channel_address().clone(), msgid))
+    }
+}
+
+impl<S> Author<S>
+where
+    S: LinkStore<DefaultTW, DefaultF, MsgId<DefaultTW>, Info = MsgInfo>,
+{
+    pub(super) fn gen(
+        store: S,
+        seed: &str,
+        channel_idx: usize,
+        mss_height: usize,
+        with_ntru: bool,
+        msgid_derivation: MsgIdDerivation,
+        msgid_deriver: Option<SharedMsgIdDeriver>,
+    ) -> Self {
+        let nonce = channel_nonce(channel_idx);
+        let mut link_gen = LinkGen::default();
+        link_gen.set_msgid_derivation(msgid_derivation);
+        link_gen.set_msgid_deriver(msgid_deriver);
         Self {
             imp: AuthorT::gen(
-                Store::default(),
-                LinkGen::default(),
+                store,
+                link_gen,
                 prng::dbg_init_str(seed),
                 &nonce,
                 mss_height,
                 with_ntru,
             ),
+            rate: RateGuard::default(),
+            tracker: Tracker::default(),
+            cover: Cover::default(),
+            branch_keys: BranchKeys::default(),
+            pending: PendingSubscriptions::default(),
+            time_provider: system_time_provider(),
         }
     }
 
+    /// Keep only links of messages from the last `keep` keyload epochs, older links are
+    /// forgotten on rekey. `None` keeps all links.
+    pub fn set_keyload_epochs(&mut self, keep: Option<usize>) {
+        self.imp.keyload_epochs = keep;
+    }
+
+    /// Pad keyload recipients with dummy ones, so that the number of subscribers is hidden.
+    pub fn set_recipient_padding(&mut self, padding: RecipientPadding) {
+        self.imp.recipient_padding = padding;
+    }
+
+    /// Pad masked payloads of signed and tagged packets to size classes, recipients strip
+    /// the padding.
+    pub fn set_payload_padding(&mut self, padding: PayloadPadding) {
+        self.imp.payload_padding = padding;
+    }
+
+    /// MAC size of lite packets, `None` disallows them. It's announced, so it must be set
+    /// before the announcement.
+    pub fn set_lite_mac(&mut self, lite_mac: Option<LiteMac>) {
+        self.imp.lite_mac = lite_mac;
+    }
+
+    /// Application protocol version put in headers of new messages, 0 means not specified.
+    pub fn set_app_version(&mut self, app_version: Trint3) {
+        self.imp.app_version = app_version;
+    }
+
+    /// Reject messages whose application version `check` returns false for.
+    pub fn set_app_version_check(&mut self, check: fn(Trint3) -> bool) {
+        self.imp.app_version_check = check;
+    }
+
+    /// Allow reuse of a keyload session key and nonce for the NTRU recipient `check` returns true for,
+    /// eg. to log it instead of refusing the keyload.
+    pub fn set_kem_reuse_check(&mut self, check: fn(&NtruPkid) -> bool) {
+        self.imp.kem_reuse_check = check;
+    }
+
+    /// Escrow session keys of shared keyloads to NTRU public key `escrow_pk`, `None` stops escrow.
+    /// Escrowed keys are not published, take them with `take_escrowed_keys` and persist.
+    pub fn set_escrow_pk(&mut self, escrow_pk: Option<NtruPublicKey>) {
+        self.imp.escrow_pk = escrow_pk;
+    }
+
+    /// Session keys escrowed since the last call by keyload link, the holder of the escrow
+    /// private key imports them with `Subscriber::import_escrowed_key`.
+    pub fn take_escrowed_keys(&mut self) -> Vec<(Address, Tbits<DefaultTW>)> {
+        std::mem::replace(&mut self.imp.escrowed_keys, Vec::new())
+    }
+
+    /// Generate keyload session keys and nonces with a PRNG seeded with `seed` instead of system RNG,
+    /// eg. for reproducible tests. `None` switches back to system RNG.
+    pub fn set_keyload_seed(&mut self, seed: Option<&str>) {
+        self.imp.set_keyload_prng(seed.map(prng::dbg_init_str));
+    }
+
+    /// Reject handled messages with variable-length fields larger than `max_size` trytes,
+    /// `None` removes the limit. Parse headers of untrusted messages with
+    /// `parse_header_with_max_size` to limit header fields as well.
+    pub fn set_max_size(&mut self, max_size: Option<usize>) {
+        self.imp.max_size = max_size;
+    }
+
+    /// Flag packets exceeding `policy`, `None` disables the check.
+    pub fn set_rate_policy(&mut self, policy: Option<RatePolicy>) {
+        self.rate.policy = policy;
+    }
+
+    /// Clock of heartbeats, rate policy, cover traffic and reports, system time by default.
+    pub fn set_time_provider(&mut self, time_provider: SharedTimeProvider) {
+        self.time_provider = time_provider;
+    }
+
+    /// Links of handled packets exceeding the rate policy, in order of arrival.
+    pub fn rate_violations(&self) -> &[Address] {
+        &self.rate.violations
+    }
+
+    /// Forget flagged packets, eg. once the application has acted on them.
+    pub fn clear_rate_violations(&mut self) {
+        self.rate.violations.clear();
+    }
+
     /// Channel app instance.
     pub fn channel_address(&self) -> &ChannelAddress {
         &self.imp.appinst.appinst
     }
 
+    /// Compact base64url representation of a link, eg. for QR codes.
+    pub fn compact_link(&self, link: &Address) -> String {
+        link.to_compact()
+    }
+
+    /// Commitment tag of a message published or handled before, it is the same for
+    /// the publisher and recipients. Links forgotten by the store have no tag.
+    pub fn message_tag(&self, link: &Address) -> Fallible<MessageTag> {
+        let (spongos, _) = self.imp.store.borrow().lookup(link.rel())?;
+        Ok(message::message_tag(&spongos))
+    }
+
+    /// Tag the message at `link` in the link store with `label`, eg. "latest-config",
+    /// to find it later with `find_by_label`. Tagging another message moves the label.
+    pub fn tag_link(&mut self, link: &Address, label: &str) -> Fallible<()> {
+        ensure!(link.base() == self.channel_address(), "Link is not in the channel.");
+        self.imp.store.borrow_mut().tag_link(link.rel(), label)
+    }
+
+    /// Link to the message tagged with `label`.
+    pub fn find_by_label(&self, label: &str) -> Option<Address> {
+        let msgid = self.imp.store.borrow().find_by_label(label)?;
+        Some(Address::new(self.channel_address().clone(), msgid))
+    }
+
+    /// Fetch up to `count` channel messages preceding the message at `link`, the nearest first.
+    pub fn fetch_prev_msgs<T: Transport>(&self, transport: &mut T, link: &Address, count: usize) -> Fallible<Vec<Message>>
+    where
+        T::RecvOptions: Default,
+    {
+        ensure!(link.base() == self.channel_address(), "Link is not in the channel.");
+        fetch_prev_msgs(transport, link, count)
+    }
+
+    /// Archive the message at `link` together with up to `count` preceding messages,
+    /// see `fetch_prev_msgs`. The archive can be verified and read offline.
+    pub fn archive<T: Transport>(&self, transport: &mut T, link: &Address, count: usize) -> Fallible<StreamsArchive>
+    where
+        T::RecvOptions: Default,
+    {
+        let mut msgs = self.fetch_prev_msgs(transport, link, count)?;
+        msgs.reverse();
+        msgs.push(transport.recv_message(link)?);
+        StreamsArchive::from_msgs(msgs)
+    }
+
+    /// Confirmation status of a message sent at `link`, unconfirmed messages can be reattached
+    /// with `ConfirmTransport::reattach`.
+    pub fn confirmation_status<T: ConfirmTransport>(
+        &self,
+        transport: &mut T,
+        link: &Address,
+    ) -> Fallible<ConfirmationStatus> {
+        ensure!(link.base() == self.channel_address(), "Link is not in the channel.");
+        transport.confirmation_status(link)
+    }
+
+    /// Recover link generator state lost since the last backup, eg. after a crash, by probing
+    /// `transport` for own messages published after the current state (see `repair` module).
+    /// Keys changed since the backup can't be recovered, repair fails if ChangeKey or Handover
+    /// message is found. Links of found messages are returned, they are not added to the link store.
+    pub fn repair_sequencing<T: Transport>(&mut self, transport: &mut T, max_gap: usize) -> Fallible<Vec<Address>>
+    where
+        T::RecvOptions: Default,
+    {
+        let appinst = self.imp.appinst.msgid.clone();
+        let entries = self.imp.store.borrow().entries()?;
+        let known: Vec<_> = entries.into_iter().map(|(link, _, _)| link).chain(Some(appinst)).collect();
+        let (counter, found) = repair::probe_sequencing(transport, &self.imp.link_gen, known, max_gap);
+        for msg in &found {
+            let preparsed = msg.parse_header()?;
+            ensure!(
+                !preparsed.check_content_type(change_key::TYPE) && !preparsed.check_content_type(handover::TYPE),
+                "Keys were changed since the backup, Author can't be repaired."
+            );
+        }
+        self.imp.link_gen.set_counter(counter);
+        Ok(found.into_iter().map(|msg| msg.link).collect())
+    }
+
+    /// Split the branch at `branch_link` off into a new channel with fresh MSS keys and its own
+    /// address. Author of the new channel is returned with its announcement and a keyload for
+    /// all current subscribers linked to it. Subscribers keep access: registered to the new
+    /// channel with the same keys, they unwrap the keyload without subscribing again.
+    /// This channel is not changed, eg. sign a packet with the new announcement link
+    /// in the branch to point subscribers to the new channel.
+    pub fn export_branch(&self, branch_link: &Address) -> Fallible<(Author, Message, Message)> {
+        ensure!(branch_link.base() == self.channel_address(), "Link is not in the channel.");
+        ensure!(
+            self.imp.store.borrow().lookup(branch_link.rel()).is_ok(),
+            "Branch link is not in the link store."
+        );
+        let nonce = &Tbits::from_str("TANGLEBRANCH").unwrap() + branch_link.msgid.tbits();
+        let mut link_gen = LinkGen::default();
+        link_gen.set_msgid_derivation(self.imp.link_gen.msgid_derivation());
+        link_gen.set_msgid_deriver(self.imp.link_gen.msgid_deriver().cloned());
+        let mut rate = RateGuard::default();
+        rate.policy = self.rate.policy;
+        let mut pending = PendingSubscriptions::default();
+        pending.approval = self.pending.approval;
+
+        let mut author = Author {
+            imp: self.imp.split(Store::default(), link_gen, &nonce),
+            rate,
+            tracker: Tracker::default(),
+            cover: Cover::default(),
+            branch_keys: BranchKeys::default(),
+            pending,
+            time_provider: self.time_provider.clone(),
+        };
+        let announcement = author.announce()?;
+        let keyload = author.share_keyload_for_everyone(&announcement.link)?;
+        Ok((author, announcement, keyload))
+    }
+
+    /// Summary of subscribers and branches of the channel.
+    pub fn report(&self) -> ChannelReport {
+        let now = self.time_provider.now();
+        ChannelReport {
+            subscribers: self.imp.ntru_pks.len(),
+            psks: self.imp.psks.len(),
+            pending_keyload_requests: self.imp.keyload_requests.len(),
+            pending_subscriptions: self.pending.list(now).len(),
+            links: self.imp.store.borrow().entries().map_or(0, |entries| entries.len()),
+            branches: self.tracker.branches(self.channel_address(), now),
+            last_keyload_age: self.tracker.last_keyload_age(now),
+        }
+    }
+
+    /// Record published message for `report`.
+    fn track(&mut self, link_to: &Address, msg: Fallible<Message>) -> Fallible<Message> {
+        let msg = msg?;
+        let preparsed = msg.parse_header()?;
+        let is_keyload = preparsed.check_content_type(keyload::TYPE)
+            || preparsed.check_content_type(threshold_keyload::TYPE)
+            || preparsed.check_content_type(rekey::TYPE);
+        let now = self.time_provider.now();
+        self.tracker.record(&link_to.msgid, &msg.link.msgid, is_keyload, now);
+        if is_keyload && self.imp.keyload_epochs.is_some() {
+            // Links of old keyload epochs have been forgotten by the store.
+            let store = self.imp.store.borrow();
+            self.tracker.retain(|msgid| store.lookup(msgid).is_ok());
+        }
+        self.cover.published(now);
+        Ok(msg)
+    }
+
+    /// Track a signed packet, it's signed with the key of the branch it's joined to, if any.
+    fn track_branch(&mut self, link_to: &Address, msg: Fallible<Message>) -> Fallible<Message> {
+        let msg = self.track(link_to, msg)?;
+        self.branch_keys.add(&link_to.msgid, &msg.link.msgid);
+        Ok(msg)
+    }
+
     /// Announce creation of a new Channel.
     pub fn announce(&mut self) -> Fallible<Message> {
-        self.imp.announce(MsgInfo::Announce)
+        if self.imp.author_set.is_some() {
+            self.imp.threshold_announce(MsgInfo::Announce)
+        } else {
+            self.imp.announce(MsgInfo::Announce)
+        }
+    }
+
+    /// Announce creation of a new Channel with access granted by pre-shared keys only.
+    /// Subscriptions are rejected and keyloads target pre-shared keys only, so subscribers
+    /// need no NTRU key pair, see `SubscriberBuilder::ntru`.
+    pub fn announce_psk_only(&mut self) -> Fallible<Message> {
+        self.imp.psk_only = true;
+        self.announce()
+    }
+
+    /// Govern the channel by Author set of own MSS public key followed by `mss_pks`: the
+    /// announcement and keyloads are signed by own key and `co_signers`, subscribers require
+    /// at least `threshold` valid signatures. It must be set before the announcement.
+    pub fn set_author_set(
+        &mut self,
+        threshold: usize,
+        mss_pks: Vec<MssPublicKey>,
+        co_signers: Vec<MssPrivateKey>,
+    ) -> Fallible<()> {
+        self.imp.set_author_set(threshold, mss_pks, co_signers)
     }
 
     /// Change keys, attach message to `link_to`.
     pub fn change_key(&mut self, link_to: &Address) -> Fallible<Message> {
-        self.imp.change_key(link_to.rel(), MsgInfo::ChangeKey)
+        self.tracker.ensure_open(&link_to.msgid)?;
+        let msg = self.imp.change_key(link_to.rel(), MsgInfo::ChangeKey);
+        self.track(link_to, msg)
+    }
+
+    /// Hand the channel over to a successor, attach message to `link_to`.
+    /// Successor's MSS public key and acceptance signature are obtained with
+    /// `Subscriber::accept_handover`.
+    pub fn handover(
+        &mut self,
+        link_to: &Address,
+        successor_mss_pk: &MssPublicKey,
+        successor_sig: &Tbits<DefaultTW>,
+    ) -> Fallible<Message> {
+        self.tracker.ensure_open(&link_to.msgid)?;
+        let msg = self.imp.handover(link_to.rel(), successor_mss_pk, successor_sig, MsgInfo::Handover);
+        self.track(link_to, msg)
     }
 
     /// Create a new keyload for a list of subscribers.
     pub fn share_keyload(&mut self, link_to: &Address, psk_ids: &PskIds, ntru_pkids: &NtruPkids) -> Fallible<Message> {
-        self.imp
-            .share_keyload(link_to.rel(), psk_ids, ntru_pkids, MsgInfo::Keyload)
+        self.tracker.ensure_open(&link_to.msgid)?;
+        let msg = self.imp.share_keyload(link_to.rel(), psk_ids, ntru_pkids, MsgInfo::Keyload);
+        self.track(link_to, msg)
+    }
+
+    /// Create a new embargoed keyload for a list of subscribers, it can be unwrapped
+    /// only after the reveal key with id `reveal_key_id` is published with `reveal_key`.
+    pub fn share_keyload_embargoed(
+        &mut self,
+        link_to: &Address,
+        psk_ids: &PskIds,
+        ntru_pkids: &NtruPkids,
+        reveal_key_id: &PskId,
+    ) -> Fallible<Message> {
+        self.tracker.ensure_open(&link_to.msgid)?;
+        let msg = self.imp.share_keyload_embargoed(link_to.rel(), psk_ids, ntru_pkids, reveal_key_id, MsgInfo::Keyload);
+        self.track(link_to, msg)
+    }
+
+    /// Publish reveal key with id `reveal_key_id`, attach message to `link_to`.
+    pub fn reveal_key(&mut self, link_to: &Address, reveal_key_id: &PskId) -> Fallible<Message> {
+        self.tracker.ensure_open(&link_to.msgid)?;
+        let msg = self.imp.reveal_key(link_to.rel(), reveal_key_id, MsgInfo::RevealKey);
+        self.track(link_to, msg)
+    }
+
+    /// Refresh session key of keyload (or rekey) `link_to` for the same recipients without
+    /// encapsulating it to each of them again.
+    pub fn rekey(&mut self, link_to: &Address) -> Fallible<Message> {
+        self.tracker.ensure_open(&link_to.msgid)?;
+        let msg = self.imp.rekey(link_to.rel(), MsgInfo::Rekey);
+        self.track(link_to, msg)
+    }
+
+    /// Publish a heartbeat timestamped with the current time, attach message to `link_to`.
+    /// Each heartbeat is signed, so it takes up an MSS private key.
+    pub fn heartbeat(&mut self, link_to: &Address) -> Fallible<Message> {
+        self.tracker.ensure_open(&link_to.msgid)?;
+        let timestamp = self.time_provider.now().as_secs() as usize;
+        let msg = self.imp.heartbeat(link_to.rel(), timestamp, MsgInfo::Heartbeat);
+        self.track(link_to, msg)
+    }
+
+    /// Close the branch of `link_to` with a signed marker message attached to `link_to`.
+    /// Subsequent messages attached to the branch are refused, subscribers stop following it.
+    pub fn close_branch(&mut self, link_to: &Address) -> Fallible<Message> {
+        self.tracker.ensure_open(&link_to.msgid)?;
+        let msg = self.imp.close_branch(link_to.rel(), MsgInfo::CloseBranch);
+        let msg = self.track(link_to, msg)?;
+        self.tracker.close(&link_to.msgid);
+        Ok(msg)
+    }
+
+    /// Whether the branch of `link` is closed.
+    pub fn is_branch_closed(&self, link: &Address) -> bool {
+        self.tracker.is_closed(&link.msgid)
+    }
+
+    /// Forget states of messages of the closed branch of `link` to reclaim memory,
+    /// return the number of messages forgotten. Messages attached to the branch,
+    /// eg. tagged packets of subscribers, can't be unwrapped afterwards.
+    pub fn prune_branch(&mut self, link: &Address) -> Fallible<usize> {
+        ensure!(self.tracker.is_closed(&link.msgid), "Branch of message {} is not closed.", link);
+        let branch = self.tracker.branch(&link.msgid);
+        let msgids = self.tracker.messages(&branch);
+        let mut store = self.imp.store.borrow_mut();
+        for msgid in &msgids {
+            store.erase(msgid);
+        }
+        self.tracker.forget(&branch);
+        Ok(msgids.len())
+    }
+
+    /// Store a pre-shared key, its id is derived with `psk::pskid_from_psk`.
+    pub fn add_psk(&mut self, psk: Psk) -> PskId {
+        psk::add_psk::<DefaultTW, DefaultF>(&mut self.imp.psks, psk)
+    }
+
+    /// Store a pre-shared key, `psk_id` must be derived from it with `psk::pskid_from_psk`.
+    pub fn insert_psk(&mut self, psk_id: PskId, psk: Psk) -> Fallible<()> {
+        ensure!(
+            psk::insert_psk::<DefaultTW, DefaultF>(&mut self.imp.psks, psk_id, psk).is_ok(),
+            "Pre-shared key id is not derived from the key."
+        );
+        Ok(())
+    }
+
+    /// Ids of stored pre-shared keys.
+    pub fn psk_ids(&self) -> Vec<PskId> {
+        self.imp.psk_ids().cloned().collect()
+    }
+
+    /// Set role granted in subsequent keyloads to the subscriber with the pre-shared key id.
+    pub fn set_psk_role(&mut self, psk_id: PskId, role: Role) {
+        self.imp.roles.set_psk_role(psk_id, role)
+    }
+
+    /// Set role granted in subsequent keyloads to the subscriber with the NTRU public key id.
+    pub fn set_ntru_role(&mut self, ntru_pkid: NtruPkid, role: Role) {
+        self.imp.roles.set_ntru_role(ntru_pkid, role)
+    }
+
+    /// Give the subscriber with the NTRU public key id a unique alias, eg. for logs.
+    pub fn set_alias(&mut self, ntru_pkid: NtruPkid, alias: &str) -> Fallible<()> {
+        self.imp.set_alias(ntru_pkid, alias)
+    }
+
+    pub fn remove_alias(&mut self, ntru_pkid: &NtruPkid) -> Option<String> {
+        self.imp.remove_alias(ntru_pkid)
+    }
+
+    pub fn alias(&self, ntru_pkid: &NtruPkid) -> Option<&str> {
+        self.imp.alias(ntru_pkid)
+    }
+
+    pub fn ntru_pk_by_alias(&self, alias: &str) -> Option<&NtruPublicKey> {
+        self.imp.ntru_pk_by_alias(alias)
     }
 
     /// Create keyload for all subscribed subscribers.
     pub fn share_keyload_for_everyone(&mut self, link_to: &Address) -> Fallible<Message> {
-        self.imp.share_keyload_for_everyone(link_to.rel(), MsgInfo::Keyload)
+        self.tracker.ensure_open(&link_to.msgid)?;
+        let msg = self.imp.share_keyload_for_everyone(link_to.rel(), MsgInfo::Keyload);
+        self.track(link_to, msg)
     }
 
     /// Create a signed packet.
@@ -68,8 +596,93 @@ impl Author {
         public_payload: &Trytes,
         masked_payload: &Trytes,
     ) -> Fallible<Message> {
-        self.imp
-            .sign_packet(link_to.rel(), public_payload, masked_payload, MsgInfo::SignedPacket)
+        self.tracker.ensure_open(&link_to.msgid)?;
+        let msg = match self.branch_keys.get(&link_to.msgid) {
+            Some(mss_sk) => self.imp.sign_packet_with_key(
+                link_to.rel(),
+                public_payload,
+                masked_payload,
+                None,
+                mss_sk,
+                MsgInfo::SignedPacket,
+            ),
+            None => self.imp.sign_packet(link_to.rel(), public_payload, masked_payload, MsgInfo::SignedPacket),
+        };
+        self.track_branch(link_to, msg)
+    }
+
+    /// Bind MSS key derived from the seed with `label` to the branch `link_to` belongs to,
+    /// eg. a keyload. Signed packets joined to `link_to`, to the binding message or to signed
+    /// packets of the branch are signed with the branch key, so its compromise doesn't expose
+    /// the channel key.
+    ///
+    /// A label can be bound once: a derived key starts signing with its first one-time key,
+    /// so binding it again would reuse them. The binding message is tagged in the link store
+    /// (see `tag_link`), so a label stays bound after the Author is restored with the store,
+    /// as long as the store keeps the binding message.
+    pub fn bind_branch_key(&mut self, link_to: &Address, label: &Trytes) -> Fallible<Message> {
+        let tag = branch_key_tag(label);
+        ensure!(
+            !self.branch_keys.is_bound(label) && self.imp.store.borrow().find_by_label(&tag).is_none(),
+            "Branch key {} is bound already.",
+            label
+        );
+        self.tracker.ensure_open(&link_to.msgid)?;
+        let (msg, mss_sk) = self.imp.branch_key(link_to.rel(), label, MsgInfo::BranchKey)?;
+        let msg = self.track(link_to, Ok(msg))?;
+        self.branch_keys.bind(label.clone(), mss_sk, &[&link_to.msgid, &msg.link.msgid]);
+        self.imp.store.borrow_mut().tag_link(&msg.link.msgid, &tag)?;
+        Ok(msg)
+    }
+
+    /// MSS public key of the branch key bound with `label`, see `bind_branch_key`.
+    pub fn branch_public_key(&self, label: &Trytes) -> Option<MssPublicKey> {
+        self.branch_keys.by_label(label).map(|mss_sk| mss_sk.public_key().clone())
+    }
+
+    /// Create a signed packet with additional authenticated data, `aad` is not sent
+    /// and recipients must provide it to unwrap the packet.
+    pub fn sign_packet_with_aad(
+        &mut self,
+        link_to: &Address,
+        public_payload: &Trytes,
+        masked_payload: &Trytes,
+        aad: &Trytes,
+    ) -> Fallible<Message> {
+        self.tracker.ensure_open(&link_to.msgid)?;
+        let msg = match self.branch_keys.get(&link_to.msgid) {
+            Some(mss_sk) => self.imp.sign_packet_with_key(
+                link_to.rel(),
+                public_payload,
+                masked_payload,
+                Some(aad),
+                mss_sk,
+                MsgInfo::SignedPacket,
+            ),
+            None => self.imp.sign_packet_with_aad(
+                link_to.rel(),
+                public_payload,
+                masked_payload,
+                aad,
+                MsgInfo::SignedPacket,
+            ),
+        };
+        self.track_branch(link_to, msg)
+    }
+
+    /// Create a signed structured packet, each field is disclosed to its own audience.
+    pub fn sign_structured_packet(&mut self, link_to: &Address, fields: &[Field]) -> Fallible<Message> {
+        self.tracker.ensure_open(&link_to.msgid)?;
+        let msg = self.imp.sign_structured_packet(link_to.rel(), fields, MsgInfo::StructuredPacket);
+        self.track(link_to, msg)
+    }
+
+    /// Publish a signed list of subscribers' NTRU public keys and roles, attach message to `link_to`.
+    /// Link it to a keyload so that only the keyload recipients can read it.
+    pub fn share_participants(&mut self, link_to: &Address) -> Fallible<Message> {
+        self.tracker.ensure_open(&link_to.msgid)?;
+        let msg = self.imp.share_participants(link_to.rel(), MsgInfo::Participants);
+        self.track(link_to, msg)
     }
 
     /// Create a tagged packet.
@@ -79,18 +692,219 @@ impl Author {
         public_payload: &Trytes,
         masked_payload: &Trytes,
     ) -> Fallible<Message> {
-        self.imp
-            .tag_packet(link_to.rel(), public_payload, masked_payload, MsgInfo::TaggedPacket)
+        self.tracker.ensure_open(&link_to.msgid)?;
+        let msg = self.imp.tag_packet(link_to.rel(), public_payload, masked_payload, MsgInfo::TaggedPacket);
+        self.track(link_to, msg)
+    }
+
+    /// Create a tagged packet with additional authenticated data, `aad` is not sent
+    /// and recipients must provide it to unwrap the packet.
+    pub fn tag_packet_with_aad(
+        &mut self,
+        link_to: &Address,
+        public_payload: &Trytes,
+        masked_payload: &Trytes,
+        aad: &Trytes,
+    ) -> Fallible<Message> {
+        self.tracker.ensure_open(&link_to.msgid)?;
+        let msg = self.imp.tag_packet_with_aad(
+            link_to.rel(),
+            public_payload,
+            masked_payload,
+            aad,
+            MsgInfo::TaggedPacket,
+        );
+        self.track(link_to, msg)
+    }
+
+    /// Create a tagged packet with a MAC per recipient group, groups are identified by `psk_ids`.
+    pub fn multi_tag_packet(
+        &mut self,
+        link_to: &Address,
+        public_payload: &Trytes,
+        masked_payload: &Trytes,
+        psk_ids: &PskIds,
+    ) -> Fallible<Message> {
+        self.tracker.ensure_open(&link_to.msgid)?;
+        let msg = self.imp.multi_tag_packet(
+            link_to.rel(),
+            public_payload,
+            masked_payload,
+            psk_ids,
+            MsgInfo::MultiTaggedPacket,
+        );
+        self.track(link_to, msg)
+    }
+
+    /// Create a padding packet with masked payload of `payload_size` trytes.
+    /// It can't be told from tagged packets without the session key.
+    pub fn pad(&mut self, link_to: &Address, payload_size: usize) -> Fallible<Message> {
+        let masked_payload = tagged_packet::padding_payload(payload_size)?;
+        self.tag_packet(link_to, &Trytes::default(), &masked_payload)
+    }
+
+    /// Publish padding packets according to `schedule`, `None` disables cover traffic.
+    pub fn set_cover_traffic(&mut self, schedule: Option<CoverTraffic>) {
+        self.cover.schedule = schedule;
+    }
+
+    /// Create a padding packet if no message has been published for the cover traffic interval.
+    /// It's to be called periodically, more often than the interval.
+    pub fn poll_cover_traffic(&mut self, link_to: &Address) -> Fallible<Option<Message>> {
+        match self.cover.due(self.time_provider.now()) {
+            Some(payload_size) => self.pad(link_to, payload_size).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Create a framed packet, masked payload is split into tagged frames of `frame_size` trytes.
+    pub fn frame_packet(
+        &mut self,
+        link_to: &Address,
+        public_payload: &Trytes,
+        masked_payload: &Trytes,
+        frame_size: usize,
+    ) -> Fallible<Message> {
+        self.tracker.ensure_open(&link_to.msgid)?;
+        let msg = self.imp.frame_packet(
+            link_to.rel(),
+            public_payload,
+            masked_payload,
+            frame_size,
+            MsgInfo::FramedPacket,
+        );
+        self.track(link_to, msg)
+    }
+
+    /// Create a lite packet with MAC truncated to the announced size.
+    pub fn lite_packet(&mut self, link_to: &Address, payload: &Trytes) -> Fallible<Message> {
+        self.tracker.ensure_open(&link_to.msgid)?;
+        let msg = self.imp.lite_packet(link_to.rel(), payload, MsgInfo::LitePacket);
+        self.track(link_to, msg)
+    }
+
+    /// Create a tagged packet with masked branch label and schema id in header extension.
+    pub fn tag_packet_with_ext(
+        &mut self,
+        link_to: &Address,
+        public_payload: &Trytes,
+        masked_payload: &Trytes,
+        branch: &str,
+        schema: &str,
+    ) -> Fallible<Message> {
+        self.tracker.ensure_open(&link_to.msgid)?;
+        let msg = self.imp.tag_packet_with_ext(
+            link_to.rel(),
+            public_payload,
+            masked_payload,
+            branch,
+            schema,
+            MsgInfo::TaggedPacket,
+        );
+        self.track(link_to, msg)
     }
 
     /// Unwrap tagged packet.
     pub fn unwrap_tagged_packet<'a>(&mut self, preparsed: Preparsed<'a>) -> Fallible<(Trytes, Trytes)> {
-        self.imp.handle_tagged_packet(preparsed, MsgInfo::TaggedPacket)
+        self.do_unwrap_tagged_packet(preparsed, None)
+    }
+
+    /// Unwrap tagged packet created with additional authenticated data `aad`.
+    pub fn unwrap_tagged_packet_with_aad<'a>(
+        &mut self,
+        preparsed: Preparsed<'a>,
+        aad: &Trytes,
+    ) -> Fallible<(Trytes, Trytes)> {
+        self.do_unwrap_tagged_packet(preparsed, Some(aad))
+    }
+
+    fn do_unwrap_tagged_packet<'a>(
+        &mut self,
+        preparsed: Preparsed<'a>,
+        aad: Option<&Trytes>,
+    ) -> Fallible<(Trytes, Trytes)> {
+        let joined = RateGuard::joined_link(&preparsed)?;
+        let link = preparsed.header.link.clone();
+        let payloads = self
+            .imp
+            .handle_tagged_packet_with_aad(preparsed, aad, MsgInfo::TaggedPacket)?;
+        let now = self.time_provider.now();
+        self.tracker.record(&joined.msgid, &link.msgid, false, now);
+        self.rate.record(joined, &link, now);
+        Ok(payloads)
     }
 
-    /// Subscribe a new subscriber.
+    /// Unwrap framed packet, a corrupted frame fails with `FrameCorrupted` error.
+    pub fn unwrap_framed_packet<'a>(&mut self, preparsed: Preparsed<'a>) -> Fallible<(Trytes, Trytes)> {
+        let joined = RateGuard::joined_link(&preparsed)?;
+        let link = preparsed.header.link.clone();
+        let payloads = self.imp.handle_framed_packet(preparsed, MsgInfo::FramedPacket)?;
+        let now = self.time_provider.now();
+        self.tracker.record(&joined.msgid, &link.msgid, false, now);
+        self.rate.record(joined, &link, now);
+        Ok(payloads)
+    }
+
+    /// Unwrap lite packet.
+    pub fn unwrap_lite_packet<'a>(&mut self, preparsed: Preparsed<'a>) -> Fallible<Trytes> {
+        let joined = RateGuard::joined_link(&preparsed)?;
+        let link = preparsed.header.link.clone();
+        let payload = self.imp.handle_lite_packet(preparsed, MsgInfo::LitePacket)?;
+        let now = self.time_provider.now();
+        self.tracker.record(&joined.msgid, &link.msgid, false, now);
+        self.rate.record(joined, &link, now);
+        Ok(payload)
+    }
+
+    /// Subscribe a new subscriber. With subscription approval the request is queued instead,
+    /// see `pending_subscriptions`, it's refused while the queue is full.
     pub fn unwrap_subscribe<'a>(&mut self, preparsed: Preparsed<'a>) -> Fallible<()> {
-        self.imp.handle_subscribe(preparsed, MsgInfo::Subscribe)
+        if self.pending.approval.is_none() {
+            return self.imp.handle_subscribe(preparsed, MsgInfo::Subscribe);
+        }
+        // Refused requests are not committed, so they can be handled again later.
+        let link = preparsed.header.link.clone();
+        let now = self.time_provider.now();
+        let unwrapped = self.imp.unwrap_subscribe(preparsed)?;
+        let ntru_pk = unwrapped.content.subscriber_ntru_pk.clone();
+        let subscribed = self.imp.ntru_pks.contains(&ntru_pk);
+        if !subscribed {
+            self.pending.ensure_room(&ntru_pk, now)?;
+        }
+        unwrapped.commit(self.imp.store.borrow_mut(), MsgInfo::Subscribe)?;
+        if !subscribed {
+            self.pending.push(link, ntru_pk, now)?;
+        }
+        Ok(())
+    }
+
+    /// Queue handled subscriptions for approval, requests expire after `approval.expiry`.
+    /// `None` subscribes subscribers right away, requests pending approval are kept.
+    pub fn set_subscription_approval(&mut self, approval: Option<SubscriptionApproval>) {
+        self.pending.approval = approval;
+    }
+
+    /// Subscription requests pending approval, oldest first. Expired requests are not listed.
+    pub fn pending_subscriptions(&self) -> Vec<PendingSubscription> {
+        self.pending.list(self.time_provider.now())
+    }
+
+    /// Subscribe the subscriber with pending request of `ntru_pkid`.
+    pub fn approve_subscription(&mut self, ntru_pkid: &NtruPkid) -> Fallible<()> {
+        let ntru_pk = self.pending.take(ntru_pkid, self.time_provider.now())?;
+        self.imp.ntru_pks.insert(ntru_pk);
+        Ok(())
+    }
+
+    /// Drop pending request of `ntru_pkid`.
+    pub fn deny_subscription(&mut self, ntru_pkid: &NtruPkid) -> Fallible<()> {
+        self.pending.take(ntru_pkid, self.time_provider.now())?;
+        Ok(())
+    }
+
+    /// Handle keyload request, return NTRU public key id of the subscribed requester.
+    pub fn unwrap_keyload_request<'a>(&mut self, preparsed: Preparsed<'a>) -> Fallible<NtruPkid> {
+        self.imp.handle_keyload_request(preparsed, MsgInfo::KeyloadRequest)
     }
 
     /// Unsubscribe a subscriber
@@ -98,3 +912,15 @@ impl Author {
         self.imp.handle_unsubscribe(preparsed, MsgInfo::Unsubscribe)
     }
 }
+
+/// Link store label of the message binding branch key `label`.
+fn branch_key_tag(label: &Trytes) -> String {
+    format!("branch-key:{}", label)
+}
+
+impl<S: HeapSize> HeapSize for Author<S> {
+    /// Heap owned by keys, subscribers and link store, see `TracingAlloc` for exact usage.
+    fn heap_size(&self) -> usize {
+        self.imp.heap_size() + self.tracker.heap_size() + self.branch_keys.heap_size()
+    }
+}
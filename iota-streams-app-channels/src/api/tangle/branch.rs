@@ -0,0 +1,107 @@
+//! Following chosen branches of a channel.
+//!
+//! A branch is the tree of messages joined, directly or not, to the branch message, eg.
+//! a keyload shared with a group of devices. Msgid of a message is derived from the msgid
+//! it is joined to and the publisher's counter (see `DefaultTangleLinkGenerator`), so the
+//! next messages of a branch are found by probing links joined to the branch messages only,
+//! messages of other branches are never fetched.
+
+use std::collections::HashMap;
+
+use super::*;
+
+#[derive(Default)]
+pub(super) struct Branches {
+    /// Messages of followed branches and the branch each one belongs to, by msgid.
+    pub(super) followed: HashMap<MsgId<DefaultTW>, MsgId<DefaultTW>>,
+    /// Counter of the last fetched message.
+    pub(super) counter: usize,
+}
+
+impl Branches {
+    pub(super) fn follow(&mut self, branch: &MsgId<DefaultTW>) {
+        self.followed.insert(branch.clone(), branch.clone());
+    }
+
+    pub(super) fn unfollow(&mut self, branch: &MsgId<DefaultTW>) -> bool {
+        let n = self.followed.len();
+        self.followed.retain(|_, b| b != branch);
+        n != self.followed.len()
+    }
+
+    /// Stop following the branch of message `msgid`, returns whether it was followed.
+    pub(super) fn close(&mut self, msgid: &MsgId<DefaultTW>) -> bool {
+        match self.followed.get(msgid).cloned() {
+            Some(branch) => self.unfollow(&branch),
+            None => false,
+        }
+    }
+
+    /// Add fetched message joined to `joined` to the branch of `joined`.
+    pub(super) fn add(&mut self, joined: &MsgId<DefaultTW>, msgid: &MsgId<DefaultTW>) {
+        if let Some(branch) = self.followed.get(joined).cloned() {
+            self.followed.insert(msgid.clone(), branch);
+        }
+    }
+}
+
+impl HeapSize for Branches {
+    fn heap_size(&self) -> usize {
+        self.followed.heap_size()
+    }
+}
+
+/// Keys signing messages of branches, see `branch_key` message. A key is bound to the branch
+/// message and the branch is followed by adding messages joined to it.
+pub(super) struct BranchKeys<K> {
+    /// Keys with their labels in order of binding.
+    keys: Vec<(Trytes, K)>,
+    /// Index of the key of a message's branch, by msgid.
+    links: HashMap<MsgId<DefaultTW>, usize>,
+}
+
+impl<K> Default for BranchKeys<K> {
+    fn default() -> Self {
+        Self {
+            keys: Vec::new(),
+            links: HashMap::new(),
+        }
+    }
+}
+
+impl<K> BranchKeys<K> {
+    pub(super) fn is_bound(&self, label: &Trytes) -> bool {
+        self.keys.iter().any(|(l, _)| l == label)
+    }
+
+    /// Bind `key` to the branch `msgids` belong to.
+    pub(super) fn bind(&mut self, label: Trytes, key: K, msgids: &[&MsgId<DefaultTW>]) {
+        for msgid in msgids {
+            self.links.insert((*msgid).clone(), self.keys.len());
+        }
+        self.keys.push((label, key));
+    }
+
+    /// Key bound with `label`.
+    pub(super) fn by_label(&self, label: &Trytes) -> Option<&K> {
+        self.keys.iter().find(|(l, _)| l == label).map(|(_, key)| key)
+    }
+
+    /// Key of the branch of message `msgid`.
+    pub(super) fn get(&self, msgid: &MsgId<DefaultTW>) -> Option<&K> {
+        self.links.get(msgid).map(|i| &self.keys[*i].1)
+    }
+
+    /// Add message joined to `joined` to the branch of `joined`.
+    pub(super) fn add(&mut self, joined: &MsgId<DefaultTW>, msgid: &MsgId<DefaultTW>) {
+        if let Some(i) = self.links.get(joined).cloned() {
+            self.links.insert(msgid.clone(), i);
+        }
+    }
+}
+
+impl<K: HeapSize> HeapSize for BranchKeys<K> {
+    fn heap_size(&self) -> usize {
+        self.keys.heap_size() + self.links.heap_size()
+    }
+}
@@ -0,0 +1,334 @@
+//! Builders for Author and Subscriber with named settings and defaults.
+
+use super::*;
+use crate::{
+    api::accept_any_app_version,
+    message::{
+        keyload::RecipientPadding,
+        lite_packet::LiteMac,
+    },
+};
+use iota_streams_protobuf3::types::{
+    LinkStore,
+    Trint3,
+};
+
+/// Default height of Author's MSS Merkle tree, the key pair can sign `2^3` messages.
+pub const DEFAULT_MSS_HEIGHT: usize = 3;
+
+/// Author builder.
+///
+/// ```ignore
+/// let author = AuthorBuilder::new("AUTHOR9SEED").mss_height(2).channel_index(1).build();
+/// ```
+#[derive(Clone)]
+pub struct AuthorBuilder {
+    seed: String,
+    channel_idx: usize,
+    mss_height: usize,
+    with_ntru: bool,
+    msgid_derivation: MsgIdDerivation,
+    msgid_deriver: Option<SharedMsgIdDeriver>,
+    keyload_epochs: Option<usize>,
+    recipient_padding: RecipientPadding,
+    payload_padding: PayloadPadding,
+    app_version: Trint3,
+    app_version_check: fn(Trint3) -> bool,
+    max_size: Option<usize>,
+    lite_mac: Option<LiteMac>,
+    rate_policy: Option<RatePolicy>,
+    subscription_approval: Option<SubscriptionApproval>,
+    time_provider: SharedTimeProvider,
+}
+
+impl AuthorBuilder {
+    /// Builder with default settings: channel index 0, MSS height `DEFAULT_MSS_HEIGHT`,
+    /// NTRU keypair generated, default msgid derivation, all links kept, keyload recipients
+    /// and payloads not padded, lite packets not allowed and application version not specified,
+    /// messages of any application version are accepted and their size is not limited.
+    pub fn new(seed: &str) -> Self {
+        Self {
+            seed: seed.to_string(),
+            channel_idx: 0,
+            mss_height: DEFAULT_MSS_HEIGHT,
+            with_ntru: true,
+            msgid_derivation: MsgIdDerivation::default(),
+            msgid_deriver: None,
+            keyload_epochs: None,
+            recipient_padding: RecipientPadding::None,
+            payload_padding: PayloadPadding::None,
+            app_version: Trint3(0),
+            app_version_check: accept_any_app_version,
+            max_size: None,
+            lite_mac: None,
+            rate_policy: None,
+            subscription_approval: None,
+            time_provider: system_time_provider(),
+        }
+    }
+
+    /// Index of the channel run with the seed, each index has its own channel address.
+    pub fn channel_index(mut self, channel_idx: usize) -> Self {
+        self.channel_idx = channel_idx;
+        self
+    }
+
+    /// Height of MSS Merkle tree, Author can sign `2^mss_height` messages.
+    pub fn mss_height(mut self, mss_height: usize) -> Self {
+        self.mss_height = mss_height;
+        self
+    }
+
+    /// Whether to generate NTRU keypair, it's needed to accept subscriptions.
+    pub fn ntru(mut self, with_ntru: bool) -> Self {
+        self.with_ntru = with_ntru;
+        self
+    }
+
+    /// Msgid derivation announced in the channel.
+    pub fn msgid_derivation(mut self, msgid_derivation: MsgIdDerivation) -> Self {
+        self.msgid_derivation = msgid_derivation;
+        self
+    }
+
+    /// Derive msgids with a custom deriver, eg. incorporating a tenant identifier. The derivation
+    /// is announced as `MsgIdDerivation::Custom`, Subscribers must be built with the same deriver.
+    pub fn msgid_deriver(mut self, msgid_deriver: SharedMsgIdDeriver) -> Self {
+        self.msgid_derivation = MsgIdDerivation::Custom;
+        self.msgid_deriver = Some(msgid_deriver);
+        self
+    }
+
+    /// Keep only links of messages from the last `keep` keyload epochs, so that long-running
+    /// Author doesn't grow the link store. Messages linked to forgotten links can't be handled.
+    pub fn keyload_epochs(mut self, keep: usize) -> Self {
+        self.keyload_epochs = Some(keep);
+        self
+    }
+
+    /// Pad keyload recipients with dummy ones, so that the number of subscribers
+    /// can't be inferred from keyloads.
+    pub fn recipient_padding(mut self, padding: RecipientPadding) -> Self {
+        self.recipient_padding = padding;
+        self
+    }
+
+    /// Pad masked payloads of signed and tagged packets to size classes, so that message sizes
+    /// leak less about the content.
+    pub fn payload_padding(mut self, padding: PayloadPadding) -> Self {
+        self.payload_padding = padding;
+        self
+    }
+
+    /// Application protocol version put in headers of new messages.
+    pub fn app_version(mut self, app_version: Trint3) -> Self {
+        self.app_version = app_version;
+        self
+    }
+
+    /// Compatibility check of application version of handled messages.
+    pub fn app_version_check(mut self, check: fn(Trint3) -> bool) -> Self {
+        self.app_version_check = check;
+        self
+    }
+
+    /// Limit size of variable-length fields of handled messages to `max_size` trytes.
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Allow lite packets with MAC truncated to `lite_mac` in the channel.
+    pub fn lite_mac(mut self, lite_mac: LiteMac) -> Self {
+        self.lite_mac = Some(lite_mac);
+        self
+    }
+
+    /// Flag handled packets exceeding the rate policy.
+    pub fn rate_policy(mut self, policy: RatePolicy) -> Self {
+        self.rate_policy = Some(policy);
+        self
+    }
+
+    /// Queue handled subscriptions for approval instead of subscribing right away.
+    pub fn subscription_approval(mut self, approval: SubscriptionApproval) -> Self {
+        self.subscription_approval = Some(approval);
+        self
+    }
+
+    /// Clock of time-dependent features, eg. a `ManualTimeProvider` in tests. System time by default.
+    pub fn time_provider(mut self, time_provider: SharedTimeProvider) -> Self {
+        self.time_provider = time_provider;
+        self
+    }
+
+    /// Create Author, it generates MSS keypair and may take a while for large MSS height.
+    pub fn build(&self) -> Author {
+        self.build_with_store(Store::default())
+    }
+
+    /// Create Author with a custom link store, eg. `EncryptedStore` keeping spongos states sealed.
+    pub fn build_with_store<S>(&self, store: S) -> Author<S>
+    where
+        S: LinkStore<DefaultTW, DefaultF, MsgId<DefaultTW>, Info = MsgInfo>,
+    {
+        let mut author = Author::gen(
+            store,
+            &self.seed,
+            self.channel_idx,
+            self.mss_height,
+            self.with_ntru,
+            self.msgid_derivation,
+            self.msgid_deriver.clone(),
+        );
+        author.set_keyload_epochs(self.keyload_epochs);
+        author.set_recipient_padding(self.recipient_padding);
+        author.set_payload_padding(self.payload_padding);
+        author.set_app_version(self.app_version);
+        author.set_app_version_check(self.app_version_check);
+        author.set_max_size(self.max_size);
+        author.set_lite_mac(self.lite_mac);
+        author.set_rate_policy(self.rate_policy);
+        author.set_subscription_approval(self.subscription_approval);
+        author.set_time_provider(self.time_provider.clone());
+        author
+    }
+}
+
+/// Subscriber builder.
+///
+/// ```ignore
+/// let subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").ntru(false).build();
+/// ```
+#[derive(Clone)]
+pub struct SubscriberBuilder {
+    seed: String,
+    with_ntru: bool,
+    msgid_deriver: Option<SharedMsgIdDeriver>,
+    payload_padding: PayloadPadding,
+    app_version: Trint3,
+    app_version_check: fn(Trint3) -> bool,
+    max_size: Option<usize>,
+    rate_policy: Option<RatePolicy>,
+    psk: Option<(PskId, Psk)>,
+    strict_sequencing: Option<usize>,
+    time_provider: SharedTimeProvider,
+}
+
+impl SubscriberBuilder {
+    /// Builder with default settings: NTRU keypair generated, payloads not padded, application
+    /// version not specified, messages of any application version accepted and their size not limited.
+    pub fn new(seed: &str) -> Self {
+        Self {
+            seed: seed.to_string(),
+            with_ntru: true,
+            msgid_deriver: None,
+            payload_padding: PayloadPadding::None,
+            app_version: Trint3(0),
+            app_version_check: accept_any_app_version,
+            max_size: None,
+            rate_policy: None,
+            psk: None,
+            strict_sequencing: None,
+            time_provider: system_time_provider(),
+        }
+    }
+
+    /// Whether to generate NTRU keypair, it's needed to subscribe and receive keyloads.
+    pub fn ntru(mut self, with_ntru: bool) -> Self {
+        self.with_ntru = with_ntru;
+        self
+    }
+
+    /// Custom msgid deriver of channels announced with `MsgIdDerivation::Custom`.
+    pub fn msgid_deriver(mut self, msgid_deriver: SharedMsgIdDeriver) -> Self {
+        self.msgid_deriver = Some(msgid_deriver);
+        self
+    }
+
+    /// Pad masked payloads of tagged packets to size classes.
+    pub fn payload_padding(mut self, padding: PayloadPadding) -> Self {
+        self.payload_padding = padding;
+        self
+    }
+
+    /// Application protocol version put in headers of new messages.
+    pub fn app_version(mut self, app_version: Trint3) -> Self {
+        self.app_version = app_version;
+        self
+    }
+
+    /// Compatibility check of application version of handled messages.
+    pub fn app_version_check(mut self, check: fn(Trint3) -> bool) -> Self {
+        self.app_version_check = check;
+        self
+    }
+
+    /// Limit size of variable-length fields of handled messages to `max_size` trytes.
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Flag handled packets exceeding the rate policy.
+    pub fn rate_policy(mut self, policy: RatePolicy) -> Self {
+        self.rate_policy = Some(policy);
+        self
+    }
+
+    /// Pre-shared key provisioned to the Subscriber, eg. at manufacture. Keyloads for the key
+    /// can be unwrapped without subscription. Its id is derived with `psk::pskid_from_psk`
+    /// like in `Author::add_psk`.
+    pub fn psk(mut self, psk: Psk) -> Self {
+        let psk_id = psk::pskid_from_psk::<DefaultTW, DefaultF>(&psk);
+        self.psk = Some((psk_id, psk));
+        self
+    }
+
+    /// Reject signed messages of the Author not sequenced after the last handled one,
+    /// the next message is looked for within `window` counters.
+    pub fn strict_sequencing(mut self, window: usize) -> Self {
+        self.strict_sequencing = Some(window);
+        self
+    }
+
+    /// Clock of time-dependent features, eg. a `ManualTimeProvider` in tests. System time by default.
+    pub fn time_provider(mut self, time_provider: SharedTimeProvider) -> Self {
+        self.time_provider = time_provider;
+        self
+    }
+
+    /// Create Subscriber.
+    pub fn build(&self) -> Subscriber {
+        self.setup(Subscriber::gen(Store::default(), &self.seed, self.with_ntru))
+    }
+
+    /// Create lightweight Subscriber keeping only the latest spongos state per message type.
+    pub fn build_lightweight(&self) -> Subscriber<LightStore> {
+        self.setup(Subscriber::gen(LightStore::default(), &self.seed, self.with_ntru))
+    }
+
+    /// Create Subscriber with a custom link store, eg. `FileStore` or a store backed by a database.
+    pub fn build_with_store<S>(&self, store: S) -> Subscriber<S>
+    where
+        S: LinkStore<DefaultTW, DefaultF, MsgId<DefaultTW>, Info = MsgInfo>,
+    {
+        self.setup(Subscriber::gen(store, &self.seed, self.with_ntru))
+    }
+
+    fn setup<S>(&self, mut subscriber: Subscriber<S>) -> Subscriber<S>
+    where
+        S: LinkStore<DefaultTW, DefaultF, MsgId<DefaultTW>, Info = MsgInfo>,
+    {
+        subscriber.set_msgid_deriver(self.msgid_deriver.clone());
+        subscriber.set_payload_padding(self.payload_padding);
+        subscriber.set_app_version(self.app_version);
+        subscriber.set_app_version_check(self.app_version_check);
+        subscriber.set_max_size(self.max_size);
+        subscriber.set_rate_policy(self.rate_policy);
+        subscriber.set_strict_sequencing(self.strict_sequencing);
+        subscriber.set_time_provider(self.time_provider.clone());
+        subscriber.imp.opt_psk = self.psk.clone();
+        subscriber
+    }
+}
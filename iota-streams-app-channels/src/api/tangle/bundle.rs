@@ -0,0 +1,131 @@
+//! Announcement bundles for onboarding devices with a single QR code scan.
+//!
+//! A bundle carries the link to the Announce message and optionally the id of the pre-shared
+//! key the device is expected to be provisioned with. Author's MSS public key is the channel
+//! address, so the bundle also pins the key: the announcement fetched from the transport
+//! is accepted only if it's signed with the key.
+//!
+//! Binary layout:
+//!
+//! ```text
+//! bundle = version:u8 flags:u8 link[66] pskid[17]?
+//! ```
+//!
+//! `link` and `pskid` trits are packed 5 per byte (see `TangleAddress::to_packed`),
+//! bit 0 of `flags` is set if `pskid` is present. The base45 text form of a bundle takes
+//! 102 or 128 characters and fits QR alphanumeric mode.
+
+use failure::{
+    bail,
+    ensure,
+    Fallible,
+};
+
+use super::*;
+use iota_streams_core::psk::PSKID_SIZE;
+
+/// Bundle format version.
+const BUNDLE_VERSION: u8 = 1;
+
+/// `flags` bit signifying presence of PSK id.
+const FLAG_PSKID: u8 = 1;
+
+/// Everything needed to join a channel.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AnnouncementBundle {
+    /// Link to the Announce message.
+    pub link: Address,
+    /// Id of the pre-shared key the Subscriber must hold.
+    pub psk_id: Option<PskId>,
+}
+
+impl AnnouncementBundle {
+    /// Bundle of the channel `announcement`, `psk_id` tells Subscriber which pre-shared key to use.
+    pub fn new(announcement: &Message, psk_id: Option<PskId>) -> Fallible<Self> {
+        let preparsed = announcement.parse_header()?;
+        ensure!(
+            is_announcement(&preparsed),
+            "Not an announcement: '{}'.",
+            preparsed.content_type()
+        );
+        if let Some(psk_id) = &psk_id {
+            ensure!(psk_id.size() == PSKID_SIZE, "Bad PSK id size: {}.", psk_id.size());
+        }
+        Ok(Self {
+            link: announcement.link.clone(),
+            psk_id,
+        })
+    }
+
+    /// Serialize bundle into bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![BUNDLE_VERSION, 0];
+        bytes.extend(self.link.to_packed());
+        if let Some(psk_id) = &self.psk_id {
+            bytes[1] |= FLAG_PSKID;
+            pack_trits(psk_id, &mut bytes);
+        }
+        bytes
+    }
+
+    /// Deserialize bundle produced by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Fallible<Self> {
+        ensure!(bytes.len() >= 2 + PACKED_LINK_SIZE, "Bundle is too short: {}.", bytes.len());
+        ensure!(bytes[0] == BUNDLE_VERSION, "Unsupported bundle version: {}.", bytes[0]);
+        let link = Address::from_packed(&bytes[2..2 + PACKED_LINK_SIZE])?;
+        let rest = &bytes[2 + PACKED_LINK_SIZE..];
+        let psk_id = match bytes[1] {
+            0 => {
+                ensure!(rest.is_empty(), "Trailing bundle bytes: {}.", rest.len());
+                None
+            }
+            FLAG_PSKID => Some(unpack_trits(rest, PSKID_SIZE)?),
+            flags => bail!("Bad bundle flags: {}.", flags),
+        };
+        Ok(Self { link, psk_id })
+    }
+
+    /// Encode bundle into base45 text, eg. for QR alphanumeric mode.
+    pub fn to_base45(&self) -> String {
+        Base45.encode(&self.to_bytes())
+    }
+
+    /// Decode bundle from base45 text produced by `to_base45`.
+    pub fn from_base45(s: &str) -> Fallible<Self> {
+        Self::from_bytes(&Base45.decode(s)?)
+    }
+}
+
+impl Subscriber {
+    /// Create Subscriber and register it to the channel of `bundle`. The announcement is
+    /// fetched from `transport` and must be signed with the MSS key pinned by the bundle.
+    pub fn from_bundle<T: Transport>(
+        builder: &SubscriberBuilder,
+        bundle: &AnnouncementBundle,
+        transport: &mut T,
+    ) -> Fallible<Self>
+    where
+        T::RecvOptions: Default,
+    {
+        let mut subscriber = builder.build();
+        if let Some(psk_id) = &bundle.psk_id {
+            match &subscriber.imp.opt_psk {
+                Some((own_psk_id, _)) if own_psk_id == psk_id => {}
+                _ => bail!("Subscriber has no pre-shared key with id {}.", psk_id),
+            }
+        }
+
+        let msg = transport.recv_message(&bundle.link)?;
+        let preparsed = msg.parse_header()?;
+        ensure!(
+            is_announcement(&preparsed),
+            "Bundle link is not an announcement: '{}'.",
+            preparsed.content_type()
+        );
+        subscriber.unwrap_announcement(preparsed)?;
+        match subscriber.author_mss_public_key() {
+            Some(mss_pk) if mss_pk.tbits() == bundle.link.appinst.tbits() => Ok(subscriber),
+            _ => bail!("Announcement is not signed with the channel MSS key."),
+        }
+    }
+}
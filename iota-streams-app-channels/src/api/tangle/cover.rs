@@ -0,0 +1,38 @@
+//! Cover traffic hiding activity patterns of a channel.
+//!
+//! Padding packets are published whenever no message has been published for the schedule
+//! interval, so observers see messages at least that often whatever the actual activity.
+//! See `tagged_packet::padding_payload` for the padding packet format.
+
+use std::time::Duration;
+
+/// Cover traffic schedule.
+#[derive(Copy, Clone, Debug)]
+pub struct CoverTraffic {
+    /// Maximum time between published messages.
+    pub interval: Duration,
+    /// Size of masked payload of padding packets in trytes, it should match the size of real packets.
+    pub payload_size: usize,
+}
+
+#[derive(Default)]
+pub(super) struct Cover {
+    pub(super) schedule: Option<CoverTraffic>,
+    /// Time of the last published message since UNIX epoch.
+    last: Option<Duration>,
+}
+
+impl Cover {
+    /// Padding packet is due at `now` according to the schedule.
+    pub(super) fn due(&self, now: Duration) -> Option<usize> {
+        let schedule = self.schedule?;
+        match self.last {
+            Some(last) if now.saturating_sub(last) < schedule.interval => None,
+            _ => Some(schedule.payload_size),
+        }
+    }
+
+    pub(super) fn published(&mut self, now: Duration) {
+        self.last = Some(now);
+    }
+}
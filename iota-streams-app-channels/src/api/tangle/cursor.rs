@@ -0,0 +1,92 @@
+//! Read positions for resuming consumption of a channel in another process.
+//!
+//! Messages are fetched in order of the publishing counter (see `Subscriber::fetch_next_msgs`),
+//! so the read position is the counter of the last fetched message together with messages of
+//! followed branches. Stateless workers restore the position with `Subscriber::resume_from`
+//! and fetch messages published after it. States of the messages are not part of the cursor,
+//! workers share them through the link store, eg. `FileStore`.
+//!
+//! Binary layout:
+//!
+//! ```text
+//! cursor = version:u8 channel[66] counter:u64 n:u32 (msgid[17] branch[17]){n}
+//! ```
+//!
+//! Integers are big-endian, `channel` is the packed link to the announcement and msgids are
+//! packed 5 trits per byte (see `TangleAddress::to_packed`).
+
+use failure::{
+    ensure,
+    Fallible,
+};
+use std::convert::TryInto as _;
+
+use super::*;
+
+/// Cursor format version.
+const CURSOR_VERSION: u8 = 1;
+
+/// Read position of a Subscriber, see `Subscriber::cursor`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Cursor {
+    /// Link to the announcement of the channel.
+    pub channel: Address,
+    /// Counter of the last fetched message.
+    pub counter: usize,
+    /// Messages of followed branches with the branch each one belongs to.
+    pub followed: Vec<(MsgId<DefaultTW>, MsgId<DefaultTW>)>,
+}
+
+impl Cursor {
+    /// Serialize cursor into bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![CURSOR_VERSION];
+        bytes.extend(self.channel.to_packed());
+        bytes.extend(&(self.counter as u64).to_be_bytes());
+        bytes.extend(&(self.followed.len() as u32).to_be_bytes());
+        for (msgid, branch) in &self.followed {
+            bytes.extend(msgid.to_packed());
+            bytes.extend(branch.to_packed());
+        }
+        bytes
+    }
+
+    /// Deserialize cursor produced by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Fallible<Self> {
+        const HEADER_SIZE: usize = 1 + PACKED_LINK_SIZE + 8 + 4;
+        ensure!(bytes.len() >= HEADER_SIZE, "Cursor is too short: {}.", bytes.len());
+        ensure!(bytes[0] == CURSOR_VERSION, "Unsupported cursor version: {}.", bytes[0]);
+        let channel = Address::from_packed(&bytes[1..1 + PACKED_LINK_SIZE])?;
+        let (counter, n) = bytes[1 + PACKED_LINK_SIZE..HEADER_SIZE].split_at(8);
+        let counter = u64::from_be_bytes(counter.try_into()?) as usize;
+        let n = u32::from_be_bytes(n.try_into()?) as usize;
+        let rest = &bytes[HEADER_SIZE..];
+        ensure!(
+            rest.len() == n * 2 * PACKED_MSGID_SIZE,
+            "Bad cursor length: {}.",
+            bytes.len()
+        );
+        let followed = rest
+            .chunks(2 * PACKED_MSGID_SIZE)
+            .map(|pair| {
+                let (msgid, branch) = pair.split_at(PACKED_MSGID_SIZE);
+                Ok((MsgId::from_packed(msgid)?, MsgId::from_packed(branch)?))
+            })
+            .collect::<Fallible<_>>()?;
+        Ok(Self {
+            channel,
+            counter,
+            followed,
+        })
+    }
+
+    /// Encode cursor into a compact base64url string.
+    pub fn to_compact(&self) -> String {
+        encode_base64url(&self.to_bytes())
+    }
+
+    /// Decode a string produced by `to_compact`.
+    pub fn from_compact(s: &str) -> Fallible<Self> {
+        Self::from_bytes(&decode_base64url(s)?)
+    }
+}
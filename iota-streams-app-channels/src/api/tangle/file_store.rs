@@ -0,0 +1,532 @@
+//! Link store persisted in a file.
+//!
+//! Links are kept in memory (see `Store`) and each update, erase or label is appended to
+//! a journal file, so the store survives restarts. Several processes may share the file: appended
+//! records are visible to other processes once they `reload` the store. `reload` compacts the file
+//! if it has records of erased or overwritten links, records appended by other processes meanwhile
+//! are lost, so processes should reload while others don't update the store.
+//!
+//! Spongos states of messages linked to keyloads contain session key material. The file opened
+//! with `FileStore::open` keeps them in plaintext and must be kept secret, the file opened with
+//! `FileStore::open_sealed` keeps them sealed with a key (see `EncryptedStore`). Epochs are not
+//! persisted, links loaded from the file are never forgotten by `start_epoch`.
+//!
+//! The file starts with the journal format version. Files written by older crate versions are
+//! migrated to the current version when loaded, files of newer versions are rejected. Processes
+//! sharing a file must use the same version.
+//!
+//! `save_encrypted_store` and `load_encrypted_store` persist `EncryptedStore` in the format
+//! of sealed journals.
+
+use failure::{
+    bail,
+    ensure,
+    format_err,
+    Fallible,
+};
+use std::{
+    fs,
+    io::Write as _,
+    path::{
+        Path,
+        PathBuf,
+    },
+    str::FromStr,
+};
+
+use super::*;
+use iota_streams_core::{
+    heap::HeapSize,
+    prng,
+    sponge::{
+        prp::inner::Inner,
+        spongos::Spongos,
+    },
+    tbits::Tbits,
+};
+use iota_streams_protobuf3::types::{
+    LinkStore,
+    SealBinding,
+    SealedState,
+};
+
+/// Message types with their journal names, in order of their codes in version 0 journals.
+const MSG_INFOS: [(MsgInfo, &str); 19] = [
+    (MsgInfo::Announce, "Announce"),
+    (MsgInfo::ChangeKey, "ChangeKey"),
+    (MsgInfo::FramedPacket, "FramedPacket"),
+    (MsgInfo::Handover, "Handover"),
+    (MsgInfo::Heartbeat, "Heartbeat"),
+    (MsgInfo::Keyload, "Keyload"),
+    (MsgInfo::KeyloadRequest, "KeyloadRequest"),
+    (MsgInfo::LitePacket, "LitePacket"),
+    (MsgInfo::Participants, "Participants"),
+    (MsgInfo::RevealKey, "RevealKey"),
+    (MsgInfo::SignedPacket, "SignedPacket"),
+    (MsgInfo::StructuredPacket, "StructuredPacket"),
+    (MsgInfo::TaggedPacket, "TaggedPacket"),
+    (MsgInfo::Subscribe, "Subscribe"),
+    (MsgInfo::Unsubscribe, "Unsubscribe"),
+    (MsgInfo::MultiTaggedPacket, "MultiTaggedPacket"),
+    (MsgInfo::CloseBranch, "CloseBranch"),
+    (MsgInfo::BranchKey, "BranchKey"),
+    (MsgInfo::Rekey, "Rekey"),
+];
+
+fn info_name(info: MsgInfo) -> &'static str {
+    MSG_INFOS.iter().find(|(i, _)| *i == info).unwrap().1
+}
+
+fn info_by_name(name: &str) -> Option<MsgInfo> {
+    MSG_INFOS.iter().find(|(_, n)| *n == name).map(|(i, _)| *i)
+}
+
+/// Message types are bound to sealed spongos states by their code.
+impl SealBinding<DefaultTW> for MsgInfo {
+    fn binding(&self) -> Tbits<DefaultTW> {
+        let code = MSG_INFOS.iter().position(|(i, _)| i == self).unwrap();
+        code.binding()
+    }
+}
+
+/// Plaintext journal format version written by this crate version.
+const JOURNAL_VERSION: usize = 1;
+
+/// The first line of plaintext journals is the header followed by the version, version 0
+/// journals have no header.
+const JOURNAL_HEADER: &str = "#streams-link-store";
+
+/// Migrations of journal records to the next version, indexed by the version migrated from.
+const MIGRATIONS: [fn(&str) -> Fallible<String>; JOURNAL_VERSION] = [migrate_v0];
+
+/// Version 0 journals identify message types by code, version 1 journals by name.
+fn migrate_v0(records: &str) -> Fallible<String> {
+    let mut migrated = String::new();
+    for (n, line) in records.lines().enumerate() {
+        match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["+", msgid, code, inner] => {
+                let (_, name) = code
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|code| MSG_INFOS.get(code))
+                    .ok_or_else(|| format_err!("Bad message type at line {}.", n + 1))?;
+                migrated.push_str(&format!("+ {} {} {}\n", msgid, name, inner));
+            }
+            _ => {
+                migrated.push_str(line);
+                migrated.push('\n');
+            }
+        }
+    }
+    Ok(migrated)
+}
+
+/// The first line of sealed journals is the header followed by the version.
+const SEALED_HEADER: &str = "#streams-sealed-link-store";
+
+/// Sealed journal format version written by this crate version.
+const SEALED_VERSION: usize = 1;
+
+/// Link store kept in memory by `FileStore`, it defines the format of journal records.
+pub trait JournalStore: LinkStore<DefaultTW, DefaultF, MsgId<DefaultTW>, Info = MsgInfo> + Sized {
+    /// The first line of the journal, it's followed by the format version.
+    const HEADER: &'static str;
+
+    /// Journal format version written by this crate version.
+    const VERSION: usize;
+
+    /// Journaled state of a link.
+    type Record;
+
+    /// Migrate journal records of an older `version` to the current version.
+    fn migrate(version: usize, records: &str) -> Fallible<String>;
+
+    /// Empty store of the same kind.
+    fn cleared(&self) -> Self;
+
+    /// Record of the link update, the store is not updated.
+    fn record(
+        &mut self,
+        link: &MsgId<DefaultTW>,
+        spongos: Spongos<DefaultTW, DefaultF>,
+        info: MsgInfo,
+    ) -> Self::Record;
+
+    /// Update the link with the journaled state.
+    fn insert(&mut self, link: MsgId<DefaultTW>, record: Self::Record) -> Fallible<()>;
+
+    /// Records of all links.
+    fn records(&self) -> Fallible<Vec<(MsgId<DefaultTW>, Self::Record)>>;
+
+    fn format_record(link: &MsgId<DefaultTW>, record: &Self::Record) -> String;
+
+    /// Parse fields of the update record at line `n`.
+    fn parse_record(fields: &[&str], n: usize) -> Fallible<(MsgId<DefaultTW>, Self::Record)>;
+}
+
+impl JournalStore for Store {
+    const HEADER: &'static str = JOURNAL_HEADER;
+    const VERSION: usize = JOURNAL_VERSION;
+    type Record = (Spongos<DefaultTW, DefaultF>, MsgInfo);
+
+    fn migrate(version: usize, records: &str) -> Fallible<String> {
+        let mut records = records.to_string();
+        for migration in &MIGRATIONS[version..] {
+            records = migration(&records)?;
+        }
+        Ok(records)
+    }
+
+    fn cleared(&self) -> Self {
+        Store::default()
+    }
+
+    fn record(
+        &mut self,
+        _link: &MsgId<DefaultTW>,
+        spongos: Spongos<DefaultTW, DefaultF>,
+        info: MsgInfo,
+    ) -> Self::Record {
+        (spongos, info)
+    }
+
+    fn insert(&mut self, link: MsgId<DefaultTW>, (spongos, info): Self::Record) -> Fallible<()> {
+        self.update(&link, spongos, info)
+    }
+
+    fn records(&self) -> Fallible<Vec<(MsgId<DefaultTW>, Self::Record)>> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .map(|(link, spongos, info)| (link, (spongos, info)))
+            .collect())
+    }
+
+    fn format_record(link: &MsgId<DefaultTW>, (spongos, info): &Self::Record) -> String {
+        let inner = spongos.to_inner();
+        format!("+ {} {} {}\n", link, info_name(*info), inner.as_ref())
+    }
+
+    fn parse_record(fields: &[&str], n: usize) -> Fallible<(MsgId<DefaultTW>, Self::Record)> {
+        match fields {
+            [msgid, name, inner] => {
+                let msgid = MsgId::from_str(msgid).map_err(|_| format_err!("Bad msgid at line {}.", n))?;
+                let info = info_by_name(name).ok_or_else(|| format_err!("Bad message type at line {}.", n))?;
+                let inner = Tbits::from_str(inner).map_err(|_| format_err!("Bad spongos state at line {}.", n))?;
+                ensure!(
+                    inner.size() == Inner::<DefaultTW, DefaultF>::default().inner.size(),
+                    "Bad spongos state size at line {}.",
+                    n
+                );
+                Ok((msgid, (Spongos::from_inner(Inner::from(inner)), info)))
+            }
+            _ => bail!("Bad link store record at line {}.", n),
+        }
+    }
+}
+
+/// Sealed records carry the nonce, the sealed spongos state and the MAC, not the epoch.
+impl JournalStore for EncryptedStore {
+    const HEADER: &'static str = SEALED_HEADER;
+    const VERSION: usize = SEALED_VERSION;
+    type Record = SealedState<DefaultTW, MsgInfo>;
+
+    fn migrate(_version: usize, _records: &str) -> Fallible<String> {
+        bail!("Link store file is not sealed.")
+    }
+
+    fn cleared(&self) -> Self {
+        let mut store = self.clone();
+        store.clear();
+        store
+    }
+
+    fn record(
+        &mut self,
+        link: &MsgId<DefaultTW>,
+        spongos: Spongos<DefaultTW, DefaultF>,
+        info: MsgInfo,
+    ) -> Self::Record {
+        self.seal(link, spongos, info)
+    }
+
+    fn insert(&mut self, link: MsgId<DefaultTW>, record: Self::Record) -> Fallible<()> {
+        self.insert_sealed(link, record)
+    }
+
+    fn records(&self) -> Fallible<Vec<(MsgId<DefaultTW>, Self::Record)>> {
+        Ok(self
+            .sealed_states()
+            .map(|(link, state)| (link.clone(), state.clone()))
+            .collect())
+    }
+
+    fn format_record(link: &MsgId<DefaultTW>, state: &Self::Record) -> String {
+        format!(
+            "+ {} {} {} {} {}\n",
+            link,
+            info_name(state.info),
+            state.nonce,
+            state.sealed,
+            state.mac
+        )
+    }
+
+    fn parse_record(fields: &[&str], n: usize) -> Fallible<(MsgId<DefaultTW>, Self::Record)> {
+        match fields {
+            [msgid, name, nonce, sealed, mac] => {
+                let msgid = MsgId::from_str(msgid).map_err(|_| format_err!("Bad msgid at line {}.", n))?;
+                let info = info_by_name(name).ok_or_else(|| format_err!("Bad message type at line {}.", n))?;
+                let nonce = Tbits::from_str(nonce).map_err(|_| format_err!("Bad nonce at line {}.", n))?;
+                let sealed = Tbits::from_str(sealed).map_err(|_| format_err!("Bad sealed state at line {}.", n))?;
+                let mac = Tbits::from_str(mac).map_err(|_| format_err!("Bad MAC at line {}.", n))?;
+                let state = SealedState {
+                    nonce,
+                    sealed,
+                    mac,
+                    info,
+                    epoch: 0,
+                };
+                Ok((msgid, state))
+            }
+            _ => bail!("Bad sealed link store record at line {}.", n),
+        }
+    }
+}
+
+/// Split the journal into its version and records.
+fn parse_version<S: JournalStore>(journal: &str) -> Fallible<(usize, &str)> {
+    if !journal.starts_with(S::HEADER) {
+        return Ok((0, journal));
+    }
+    let (header, records) = journal.split_at(journal.find('\n').map_or(journal.len(), |i| i + 1));
+    let version = header[S::HEADER.len()..]
+        .trim()
+        .parse::<usize>()
+        .map_err(|_| format_err!("Bad link store file header: '{}'.", header.trim()))?;
+    ensure!(
+        version <= S::VERSION,
+        "Link store file version {} is newer than supported version {}.",
+        version,
+        S::VERSION
+    );
+    Ok((version, records))
+}
+
+/// Journal records of all links and labels of `store`.
+fn journal<S: JournalStore>(store: &S) -> Fallible<String> {
+    let mut journal = String::new();
+    for (link, record) in store.records()? {
+        journal.push_str(&S::format_record(&link, &record));
+    }
+    for (label, link) in store.labels() {
+        journal.push_str(&format!("= {} {}\n", label, link));
+    }
+    Ok(journal)
+}
+
+/// Replace the file at `path` with the current version header and `records`.
+fn write_journal<S: JournalStore>(path: &Path, records: &str) -> Fallible<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, format!("{} {}\n{}", S::HEADER, S::VERSION, records))?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Link store appending updates to a journal file.
+pub struct FileStore<S = Store> {
+    path: PathBuf,
+    store: S,
+}
+
+impl FileStore {
+    /// Open the store at `path` keeping spongos states in plaintext, the file is created
+    /// if it doesn't exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Fallible<Self> {
+        Self::open_with(path, Store::default())
+    }
+
+    /// Iterate over all links in the store.
+    pub fn links(&self) -> impl Iterator<Item = &MsgId<DefaultTW>> {
+        self.store.links()
+    }
+}
+
+impl FileStore<EncryptedStore> {
+    /// Open the store at `path` keeping spongos states sealed with `key` of `Spongos::KEY_SIZE`
+    /// tbits, the file is created if it doesn't exist. Files sealed with another key are rejected.
+    /// Nonces are derived from a random one, so processes sharing the file don't reuse them.
+    pub fn open_sealed<P: AsRef<Path>>(path: P, key: Tbits<DefaultTW>) -> Fallible<Self> {
+        let nonce = prng::random_nonce(Spongos::<DefaultTW, DefaultF>::NONCE_SIZE);
+        Self::open_with(path, EncryptedStore::new(key, nonce)?)
+    }
+
+    /// Iterate over all links in the store.
+    pub fn links(&self) -> impl Iterator<Item = &MsgId<DefaultTW>> {
+        self.store.links()
+    }
+}
+
+impl<S: JournalStore> FileStore<S> {
+    fn open_with<P: AsRef<Path>>(path: P, store: S) -> Fallible<Self> {
+        let mut store = Self {
+            path: path.as_ref().to_path_buf(),
+            store: store,
+        };
+        if !store.path.exists() {
+            store.write(&String::new())?;
+        }
+        store.reload()?;
+        Ok(store)
+    }
+
+    /// Replace links in memory with the ones in the file, eg. to pick up updates
+    /// of other processes. The file is migrated if it was written by an older version
+    /// and compacted if it has records of erased or overwritten links.
+    pub fn reload(&mut self) -> Fallible<()> {
+        let journal = fs::read_to_string(&self.path)?;
+        let (version, records) = parse_version::<S>(&journal)?;
+        let records = if version < S::VERSION {
+            S::migrate(version, records)?
+        } else {
+            records.to_string()
+        };
+
+        let mut store = self.store.cleared();
+        let mut count = 0;
+        // The header is the first line.
+        for (n, line) in records.lines().enumerate().map(|(n, line)| (n + 2, line)) {
+            let record: Vec<&str> = line.split_whitespace().collect();
+            match record.as_slice() {
+                ["+", fields @ ..] => {
+                    let (msgid, record) = S::parse_record(fields, n)?;
+                    store
+                        .insert(msgid, record)
+                        .map_err(|e| format_err!("Bad link store record at line {}: {}", n, e))?;
+                }
+                ["-", msgid] => {
+                    let msgid = MsgId::from_str(msgid).map_err(|_| format_err!("Bad msgid at line {}.", n))?;
+                    store.erase(&msgid);
+                }
+                ["=", label, msgid] => {
+                    let msgid = MsgId::from_str(msgid).map_err(|_| format_err!("Bad msgid at line {}.", n))?;
+                    store.tag_link(&msgid, label)?;
+                }
+                [] => continue,
+                _ => bail!("Bad link store record at line {}.", n),
+            }
+            count += 1;
+        }
+        self.store = store;
+
+        // Migrated records are written once they are loaded, in the same order.
+        let live = self.store.records()?.len() + self.store.labels().len();
+        if live < count {
+            self.compact()?;
+        } else if version < S::VERSION {
+            self.write(&records)?;
+        }
+        Ok(())
+    }
+
+    /// Rewrite the file with the links in memory only, dropping records of erased
+    /// and overwritten links.
+    pub fn compact(&mut self) -> Fallible<()> {
+        self.write(&journal(&self.store)?)
+    }
+
+    /// Replace the file with the current version header and `records`.
+    fn write(&self, records: &str) -> Fallible<()> {
+        write_journal::<S>(&self.path, records)
+    }
+
+    fn append(&self, record: &str) -> Fallible<()> {
+        let mut file = fs::OpenOptions::new().append(true).open(&self.path)?;
+        file.write_all(record.as_bytes())?;
+        Ok(())
+    }
+}
+
+impl<S: HeapSize> HeapSize for FileStore<S> {
+    fn heap_size(&self) -> usize {
+        self.path.capacity() + self.store.heap_size()
+    }
+}
+
+impl<S: JournalStore> LinkStore<DefaultTW, DefaultF, MsgId<DefaultTW>> for FileStore<S> {
+    type Info = MsgInfo;
+
+    fn lookup(&self, link: &MsgId<DefaultTW>) -> Fallible<(Spongos<DefaultTW, DefaultF>, MsgInfo)> {
+        self.store.lookup(link)
+    }
+
+    /// Append the update to the file, the store is not updated if it fails.
+    fn update(
+        &mut self,
+        link: &MsgId<DefaultTW>,
+        spongos: Spongos<DefaultTW, DefaultF>,
+        info: MsgInfo,
+    ) -> Fallible<()> {
+        let record = self.store.record(link, spongos, info);
+        self.append(&S::format_record(link, &record))?;
+        self.store.insert(link.clone(), record)
+    }
+
+    /// Erase the link, it reappears after `reload` if the file can't be written.
+    fn erase(&mut self, link: &MsgId<DefaultTW>) {
+        let _ = self.append(&format!("- {}\n", link));
+        self.store.erase(link);
+    }
+
+    fn start_epoch(&mut self, keep: usize) {
+        let links: Vec<_> = self
+            .store
+            .records()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(link, _)| link)
+            .collect();
+        self.store.start_epoch(keep);
+        for link in links {
+            if self.store.lookup(&link).is_err() {
+                let _ = self.append(&format!("- {}\n", link));
+            }
+        }
+    }
+
+    fn entries(&self) -> Fallible<Vec<(MsgId<DefaultTW>, Spongos<DefaultTW, DefaultF>, MsgInfo)>> {
+        self.store.entries()
+    }
+
+    /// Labels are journaled, so they must not contain whitespace.
+    fn tag_link(&mut self, link: &MsgId<DefaultTW>, label: &str) -> Fallible<()> {
+        ensure!(
+            !label.is_empty() && !label.contains(char::is_whitespace),
+            "Bad label: '{}'.",
+            label
+        );
+        ensure!(self.store.lookup(link).is_ok(), "Link not found.");
+        self.append(&format!("= {} {}\n", label, link))?;
+        self.store.tag_link(link, label)
+    }
+
+    fn find_by_label(&self, label: &str) -> Option<MsgId<DefaultTW>> {
+        self.store.find_by_label(label)
+    }
+
+    fn labels(&self) -> Vec<(String, MsgId<DefaultTW>)> {
+        self.store.labels()
+    }
+}
+
+/// Save `store` to `path` in the format of sealed journals, spongos states stay sealed.
+/// The file is replaced atomically.
+pub fn save_encrypted_store<P: AsRef<Path>>(store: &EncryptedStore, path: P) -> Fallible<()> {
+    write_journal::<EncryptedStore>(path.as_ref(), &journal(store)?)
+}
+
+/// Load store saved with `save_encrypted_store`, records not sealed with `key` are rejected.
+pub fn load_encrypted_store<P: AsRef<Path>>(key: Tbits<DefaultTW>, path: P) -> Fallible<EncryptedStore> {
+    ensure!(path.as_ref().exists(), "Link store file not found.");
+    Ok(FileStore::open_sealed(path, key)?.store)
+}
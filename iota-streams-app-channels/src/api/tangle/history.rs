@@ -0,0 +1,39 @@
+//! Paging through channel history backwards.
+
+use failure::Fallible;
+
+use super::*;
+use iota_streams_app::message::HasLink as _;
+
+/// Link to the message `msg` is joined to or nothing for Announce message.
+fn prev_link(msg: &Message) -> Fallible<Option<Address>> {
+    let preparsed = msg.parse_header()?;
+    if is_announcement(&preparsed) {
+        Ok(None)
+    } else {
+        Ok(Some(Address::new(msg.link.base().clone(), preparsed.peek_link()?)))
+    }
+}
+
+/// Fetch up to `count` messages preceding the message at `link`, the nearest first.
+///
+/// Each message (except for Announce) is joined to a previous message, the link to it
+/// is not masked and can be read without unwrapping the message. The walk stops at
+/// the Announce message. Fetched messages are not unwrapped and must be handled
+/// in reverse order, ie. starting from the oldest one.
+pub fn fetch_prev_msgs<T: Transport>(transport: &mut T, link: &Address, count: usize) -> Fallible<Vec<Message>>
+where
+    T::RecvOptions: Default,
+{
+    let mut msgs = Vec::with_capacity(count);
+    let mut next_link = prev_link(&transport.recv_message(link)?)?;
+    while let Some(link) = next_link {
+        if msgs.len() == count {
+            break;
+        }
+        let msg = transport.recv_message(&link)?;
+        next_link = prev_link(&msg)?;
+        msgs.push(msg);
+    }
+    Ok(msgs)
+}
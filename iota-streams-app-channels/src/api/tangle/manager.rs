@@ -0,0 +1,231 @@
+//! Subscriptions to many channels, eg. a gateway consuming device channels.
+//!
+//! `ChannelManager` keeps a Subscriber per channel keyed by channel address. `sync` fetches
+//! new messages of every channel over a single transport, unwraps them with the channel's
+//! Subscriber and passes them to the channel's handler:
+//!
+//! ```ignore
+//! let mut manager = ChannelManager::new(8);
+//! manager.insert(subscriber, |link, received| {
+//!     if let Received::SignedPacket(public, _) = received {
+//!         println!("{}: {}", link, public);
+//!     }
+//! })?;
+//! let errors = manager.sync(&mut transport);
+//! ```
+//!
+//! A background thread syncs a manager shared via `Shared` handle, see `shared` module.
+
+use failure::{
+    bail,
+    Error,
+    Fallible,
+};
+use std::collections::HashMap;
+
+use super::*;
+use crate::message::{
+    branch_key,
+    change_key,
+    close_branch,
+    framed_packet,
+    handover,
+    heartbeat,
+    keyload,
+    lite_packet,
+    multi_tagged_packet,
+    participants,
+    rekey,
+    reveal_key,
+    signed_packet,
+    structured_packet,
+    tagged_packet,
+    threshold_keyload,
+};
+use iota_streams_protobuf3::types::LinkStore;
+
+/// Message of a managed channel unwrapped by its Subscriber.
+pub enum Received {
+    Keyload,
+    Rekey,
+    ChangeKey,
+    Handover,
+    RevealKey(PskId),
+    Heartbeat(Heartbeat),
+    /// Branch of the linked message is closed.
+    BranchClosed(Address),
+    /// Branch key with the label is bound, see `Author::bind_branch_key`.
+    BranchKey(Trytes),
+    Participants,
+    SignedPacket(Trytes, Trytes),
+    TaggedPacket(Trytes, Trytes),
+    MultiTaggedPacket(Trytes, Trytes),
+    FramedPacket(Trytes, Trytes),
+    StructuredPacket(Vec<Field>),
+    LitePacket(Trytes),
+    /// Message not meant for subscribers, eg. subscribe message of another subscriber,
+    /// only its content type is reported.
+    Other(String),
+}
+
+/// Handler of messages of a managed channel.
+pub type Handler = Box<dyn FnMut(&Address, Received) + Send>;
+
+struct Channel<S> {
+    subscriber: Subscriber<S>,
+    handler: Handler,
+}
+
+/// Subscribers to many channels synced over a shared transport.
+pub struct ChannelManager<S = Store> {
+    channels: HashMap<ChannelAddress, Channel<S>>,
+    max_gap: usize,
+}
+
+impl<S> ChannelManager<S>
+where
+    S: LinkStore<DefaultTW, DefaultF, MsgId<DefaultTW>, Info = MsgInfo>,
+{
+    /// Create a manager, `max_gap` is passed to `Subscriber::fetch_next_msgs`.
+    pub fn new(max_gap: usize) -> Self {
+        Self {
+            channels: HashMap::new(),
+            max_gap,
+        }
+    }
+
+    /// Manage channel of a registered `subscriber`, its messages are passed to `handler`.
+    /// A Subscriber already managed for the channel is replaced and returned.
+    pub fn insert<H>(&mut self, subscriber: Subscriber<S>, handler: H) -> Fallible<Option<Subscriber<S>>>
+    where
+        H: FnMut(&Address, Received) + Send + 'static,
+    {
+        let appinst = match subscriber.channel_address() {
+            Some(appinst) => appinst.clone(),
+            None => bail!("Subscriber is not registered to a channel."),
+        };
+        let channel = Channel {
+            subscriber,
+            handler: Box::new(handler),
+        };
+        Ok(self.channels.insert(appinst, channel).map(|c| c.subscriber))
+    }
+
+    /// Stop managing the channel and return its Subscriber.
+    pub fn remove(&mut self, appinst: &ChannelAddress) -> Option<Subscriber<S>> {
+        self.channels.remove(appinst).map(|c| c.subscriber)
+    }
+
+    pub fn get(&self, appinst: &ChannelAddress) -> Option<&Subscriber<S>> {
+        self.channels.get(appinst).map(|c| &c.subscriber)
+    }
+
+    /// Subscriber of the channel, eg. to send a subscribe message.
+    pub fn get_mut(&mut self, appinst: &ChannelAddress) -> Option<&mut Subscriber<S>> {
+        self.channels.get_mut(appinst).map(|c| &mut c.subscriber)
+    }
+
+    /// Addresses of managed channels.
+    pub fn channels(&self) -> impl Iterator<Item = &ChannelAddress> {
+        self.channels.keys()
+    }
+
+    pub fn len(&self) -> usize {
+        self.channels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.channels.is_empty()
+    }
+
+    /// Fetch and dispatch new messages of all channels. A failed fetch or unwrap doesn't stop
+    /// syncing of other channels or messages, the errors are returned with channel address.
+    pub fn sync<T: Transport>(&mut self, transport: &mut T) -> Vec<(ChannelAddress, Error)>
+    where
+        T::RecvOptions: Default,
+    {
+        let mut errors = Vec::new();
+        for (appinst, channel) in self.channels.iter_mut() {
+            match channel.subscriber.fetch_next_msgs(transport, self.max_gap) {
+                Ok(msgs) => {
+                    for msg in msgs {
+                        match unwrap(&mut channel.subscriber, &msg) {
+                            Ok(received) => (channel.handler)(&msg.link, received),
+                            Err(err) => errors.push((appinst.clone(), err)),
+                        }
+                    }
+                }
+                Err(err) => errors.push((appinst.clone(), err)),
+            }
+        }
+        errors
+    }
+
+    /// Sync a single channel, it stops at the first failed message. Number of fetched
+    /// messages is returned.
+    pub fn sync_channel<T: Transport>(&mut self, transport: &mut T, appinst: &ChannelAddress) -> Fallible<usize>
+    where
+        T::RecvOptions: Default,
+    {
+        let channel = match self.channels.get_mut(appinst) {
+            Some(channel) => channel,
+            None => bail!("Channel is not managed."),
+        };
+        let msgs = channel.subscriber.fetch_next_msgs(transport, self.max_gap)?;
+        for msg in &msgs {
+            let received = unwrap(&mut channel.subscriber, msg)?;
+            (channel.handler)(&msg.link, received);
+        }
+        Ok(msgs.len())
+    }
+}
+
+/// Unwrap message of the Subscriber's channel according to its content type.
+pub(super) fn unwrap<S>(subscriber: &mut Subscriber<S>, msg: &Message) -> Fallible<Received>
+where
+    S: LinkStore<DefaultTW, DefaultF, MsgId<DefaultTW>, Info = MsgInfo>,
+{
+    let preparsed = msg.parse_header()?;
+    Ok(if preparsed.check_content_type(keyload::TYPE) || preparsed.check_content_type(threshold_keyload::TYPE) {
+        subscriber.unwrap_keyload(preparsed)?;
+        Received::Keyload
+    } else if preparsed.check_content_type(rekey::TYPE) {
+        subscriber.unwrap_rekey(preparsed)?;
+        Received::Rekey
+    } else if preparsed.check_content_type(change_key::TYPE) {
+        subscriber.unwrap_change_key(preparsed)?;
+        Received::ChangeKey
+    } else if preparsed.check_content_type(handover::TYPE) {
+        subscriber.unwrap_handover(preparsed)?;
+        Received::Handover
+    } else if preparsed.check_content_type(reveal_key::TYPE) {
+        Received::RevealKey(subscriber.unwrap_reveal_key(preparsed)?)
+    } else if preparsed.check_content_type(heartbeat::TYPE) {
+        Received::Heartbeat(subscriber.unwrap_heartbeat(preparsed)?)
+    } else if preparsed.check_content_type(close_branch::TYPE) {
+        Received::BranchClosed(subscriber.unwrap_close_branch(preparsed)?)
+    } else if preparsed.check_content_type(branch_key::TYPE) {
+        Received::BranchKey(subscriber.unwrap_branch_key(preparsed)?.0)
+    } else if preparsed.check_content_type(participants::TYPE) {
+        subscriber.unwrap_participants(preparsed)?;
+        Received::Participants
+    } else if preparsed.check_content_type(signed_packet::TYPE) {
+        let (public, masked) = subscriber.unwrap_signed_packet(preparsed)?;
+        Received::SignedPacket(public, masked)
+    } else if preparsed.check_content_type(tagged_packet::TYPE) {
+        let (public, masked) = subscriber.unwrap_tagged_packet(preparsed)?;
+        Received::TaggedPacket(public, masked)
+    } else if preparsed.check_content_type(multi_tagged_packet::TYPE) {
+        let (public, masked) = subscriber.unwrap_multi_tagged_packet(preparsed)?;
+        Received::MultiTaggedPacket(public, masked)
+    } else if preparsed.check_content_type(framed_packet::TYPE) {
+        let (public, masked) = subscriber.unwrap_framed_packet(preparsed)?;
+        Received::FramedPacket(public, masked)
+    } else if preparsed.check_content_type(structured_packet::TYPE) {
+        Received::StructuredPacket(subscriber.unwrap_structured_packet(preparsed)?)
+    } else if preparsed.check_content_type(lite_packet::TYPE) {
+        Received::LitePacket(subscriber.unwrap_lite_packet(preparsed)?)
+    } else {
+        Received::Other(preparsed.content_type())
+    })
+}
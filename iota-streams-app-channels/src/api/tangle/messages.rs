@@ -0,0 +1,76 @@
+//! Iterator over new messages of a channel.
+//!
+//! `Subscriber::messages` polls the transport for messages following the last fetched one
+//! (see `Subscriber::fetch_next_msgs`), unwraps them and yields them in order, so that
+//! standard combinators can be used instead of fetch loops:
+//!
+//! ```ignore
+//! let payloads: Vec<_> = subscriber
+//!     .messages(&mut transport, 8)
+//!     .filter_map(|msg| match msg {
+//!         Ok((_, Received::SignedPacket(public, _))) => Some(public),
+//!         _ => None,
+//!     })
+//!     .collect();
+//! ```
+//!
+//! The iterator ends when no new message is found or fetching fails. A new iterator
+//! continues where the previous one stopped.
+
+use failure::Fallible;
+use std::collections::VecDeque;
+
+use super::{
+    manager::unwrap,
+    *,
+};
+use iota_streams_protobuf3::types::LinkStore;
+
+/// Messages of the Subscriber's channel unwrapped as they are fetched.
+pub struct Messages<'a, S, T> {
+    subscriber: &'a mut Subscriber<S>,
+    transport: &'a mut T,
+    max_gap: usize,
+    fetched: VecDeque<Message>,
+    done: bool,
+}
+
+impl<'a, S, T> Messages<'a, S, T> {
+    pub(super) fn new(subscriber: &'a mut Subscriber<S>, transport: &'a mut T, max_gap: usize) -> Self {
+        Self {
+            subscriber,
+            transport,
+            max_gap,
+            fetched: VecDeque::new(),
+            done: false,
+        }
+    }
+}
+
+impl<'a, S, T> Iterator for Messages<'a, S, T>
+where
+    S: LinkStore<DefaultTW, DefaultF, MsgId<DefaultTW>, Info = MsgInfo>,
+    T: Transport,
+    T::RecvOptions: Default,
+{
+    /// Link of the message and its content, a failed unwrap doesn't end the iteration.
+    type Item = Fallible<(Address, Received)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // The next batch is fetched once the previous one is handled.
+        if self.fetched.is_empty() && !self.done {
+            match self.subscriber.fetch_next_msgs(self.transport, self.max_gap) {
+                Ok(msgs) => {
+                    self.done = msgs.is_empty();
+                    self.fetched.extend(msgs);
+                }
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+        let msg = self.fetched.pop_front()?;
+        Some(unwrap(self.subscriber, &msg).map(|received| (msg.link, received)))
+    }
+}
@@ -0,0 +1,78 @@
+//! Redundant publishing of the same payload to several channels, eg. geographic mirrors.
+//!
+//! Each mirror is a channel (a distinct Author) or a branch of a channel (the same Author linked
+//! to a different message). `Mirror` keeps the last link of every mirror and chains the next
+//! packet to it:
+//!
+//! ```ignore
+//! let mut mirror = Mirror::new().with(&mut author_eu, keyload_eu).with(&mut author_us, keyload_us);
+//! for msg in mirror.tag_packet(&public, &masked)? {
+//!     transport.send_message(&msg)?;
+//! }
+//! ```
+//!
+//! Payloads are borrowed by every mirror, not copied. Signatures can't be shared though:
+//! each message is signed with the key of its channel over its own spongos state.
+
+use failure::{
+    format_err,
+    Fallible,
+};
+
+use super::*;
+
+/// Publisher of the same packets to several mirror channels.
+#[derive(Default)]
+pub struct Mirror<'a> {
+    targets: Vec<(&'a mut Author, Address)>,
+}
+
+impl<'a> Mirror<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a mirror, its first packet is published by `author` and attached to `link_to`.
+    pub fn with(mut self, author: &'a mut Author, link_to: Address) -> Self {
+        self.targets.push((author, link_to));
+        self
+    }
+
+    /// Number of mirrors.
+    pub fn len(&self) -> usize {
+        self.targets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    /// Links the next packets are attached to, in the order mirrors were added.
+    pub fn links(&self) -> impl Iterator<Item = &Address> {
+        self.targets.iter().map(|(_, link)| link)
+    }
+
+    /// Create a signed packet for every mirror, messages are returned in the order mirrors were added.
+    pub fn sign_packet(&mut self, public_payload: &Trytes, masked_payload: &Trytes) -> Fallible<Vec<Message>> {
+        self.publish(|author, link_to| author.sign_packet(link_to, public_payload, masked_payload))
+    }
+
+    /// Create a tagged packet for every mirror, messages are returned in the order mirrors were added.
+    pub fn tag_packet(&mut self, public_payload: &Trytes, masked_payload: &Trytes) -> Fallible<Vec<Message>> {
+        self.publish(|author, link_to| author.tag_packet(link_to, public_payload, masked_payload))
+    }
+
+    /// Links are advanced only if all mirrors succeed, otherwise messages already created
+    /// for the preceding mirrors are dropped.
+    fn publish(&mut self, mut f: impl FnMut(&mut Author, &Address) -> Fallible<Message>) -> Fallible<Vec<Message>> {
+        let mut msgs = Vec::with_capacity(self.targets.len());
+        for (i, (author, link_to)) in self.targets.iter_mut().enumerate() {
+            let msg = f(author, link_to).map_err(|e| format_err!("Mirror {}: {}", i, e))?;
+            msgs.push(msg);
+        }
+        for ((_, link_to), msg) in self.targets.iter_mut().zip(msgs.iter()) {
+            *link_to = msg.link.clone();
+        }
+        Ok(msgs)
+    }
+}
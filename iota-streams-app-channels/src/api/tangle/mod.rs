@@ -1,5 +1,11 @@
 //! Default parameters for Author and Subscriber types.
 
+use crate::message::{
+    announce,
+    structured_packet,
+    threshold_announce,
+};
+use std::sync::Arc;
 use iota_streams_app::{
     message,
     transport::{
@@ -8,6 +14,7 @@ use iota_streams_app::{
     },
 };
 use iota_streams_core::{
+    heap::HeapSize,
     psk,
     sponge::prp::troika::Troika,
     tbits::trinary::Trit,
@@ -16,7 +23,11 @@ use iota_streams_core_mss::signature::mss;
 use iota_streams_core_ntru::key_encapsulation::ntru;
 use iota_streams_protobuf3::{
     types as pb3_types,
-    types::DefaultLinkStore,
+    types::{
+        DefaultLinkStore,
+        EncryptedLinkStore,
+        LatestLinkStore,
+    },
 };
 
 /// Default tbit word encoding.
@@ -30,13 +41,26 @@ pub type DefaultP = mss::troika::ParametersMtTraversal<DefaultTW>;
 
 /// Default Tbit & PSK & MSS & NTRU types.
 pub type Trytes = pb3_types::Trytes<DefaultTW>;
+pub type PskId = psk::PskId<DefaultTW>;
 pub type PskIds = psk::PskIds<DefaultTW>;
+pub type Psk = psk::Psk<DefaultTW>;
 pub type MssPublicKey = mss::PublicKey<DefaultTW, DefaultP>;
 pub type MssPrivateKey = mss::PrivateKey<DefaultTW, DefaultP>;
 pub type NtruPublicKey = ntru::PublicKey<DefaultTW, DefaultF>;
 pub type NtruPrivateKey = ntru::PrivateKey<DefaultTW, DefaultF>;
+pub type NtruPkid = ntru::Pkid<DefaultTW>;
 pub type NtruPkids = ntru::NtruPkids<DefaultTW>;
 
+/// Structured packet field and its audience.
+pub type Field = structured_packet::Field<DefaultTW>;
+pub type Audience = structured_packet::Audience<DefaultTW>;
+
+/// Author MSS public keys governing a channel.
+pub type AuthorSet = threshold_announce::AuthorSet<DefaultTW, DefaultP>;
+
+/// Counter and timestamp of Author's heartbeat.
+pub use crate::message::heartbeat::Heartbeat;
+
 /// Link type.
 pub type Address = TangleAddress<DefaultTW>;
 /// Channel address.
@@ -47,38 +71,213 @@ pub type Message = message::TbinaryMessage<DefaultTW, DefaultF, Address>;
 /// Message type with parsed header.
 pub type Preparsed<'a> = message::PreparsedMessage<'a, DefaultTW, DefaultF, Address>;
 
+/// Commitment to a message independent of its link.
+pub type MessageTag = message::MessageTag<DefaultTW>;
+
+/// Header extension with routing hints.
+pub type HeaderExt = message::header::HeaderExt<DefaultTW, Address>;
+
 /// Link Generator specifies algorithm for generating new message addressed.
 pub type LinkGen = DefaultTangleLinkGenerator<DefaultTW, DefaultF>;
 
+/// Custom msgid deriver shared by link generators, see `MsgIdDerivation::Custom`.
+pub type SharedMsgIdDeriver = Arc<dyn MsgIdDeriver<DefaultTW>>;
+
 /// Test Transport.
 pub type BucketTransport = transport::BucketTransport<DefaultTW, DefaultF, Address>;
 
+/// Transport sending messages as length-delimited frames over a byte stream.
+pub type FramedTransport<S> = transport::tangle::Framed<DefaultTW, DefaultF, S>;
+
 pub trait Transport: transport::Transport<DefaultTW, DefaultF, Address> {}
 
 impl<T> Transport for T where T: transport::Transport<DefaultTW, DefaultF, Address> {}
 
+/// Transport tracking confirmation of sent messages.
+pub trait ConfirmTransport: transport::ConfirmTransport<DefaultTW, DefaultF, Address> {}
+
+impl<T> ConfirmTransport for T where T: transport::ConfirmTransport<DefaultTW, DefaultF, Address> {}
+
+/// Transport reporting attachment time of messages.
+pub trait TimestampTransport: transport::TimestampTransport<DefaultTW, DefaultF, Address> {}
+
+impl<T> TimestampTransport for T where T: transport::TimestampTransport<DefaultTW, DefaultF, Address> {}
+
+pub use transport::{
+    send_with_backpressure,
+    send_with_backpressure_using,
+    Backpressure,
+    ConfirmOptions,
+    ConfirmationStatus,
+    EncryptionLayer,
+    Layer,
+    Layered,
+    LoggingLayer,
+    MetricsLayer,
+    NodeHealth,
+    RateLimitedTransport,
+    TransportExt,
+    TransportMetrics,
+};
+
 /// Message associated info, just message type indicator.
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
 pub enum MsgInfo {
     Announce,
+    BranchKey,
     ChangeKey,
+    CloseBranch,
+    FramedPacket,
+    Handover,
+    Heartbeat,
     Keyload,
+    KeyloadRequest,
+    LitePacket,
+    MultiTaggedPacket,
+    Participants,
+    Rekey,
+    RevealKey,
     SignedPacket,
+    StructuredPacket,
     TaggedPacket,
     Subscribe,
     Unsubscribe,
 }
 
+impl HeapSize for MsgInfo {
+    fn heap_size(&self) -> usize {
+        0
+    }
+}
+
+/// Whether the message is a channel announcement, either Announce or ThresholdAnnounce.
+fn is_announcement(preparsed: &Preparsed) -> bool {
+    preparsed.check_content_type(announce::TYPE) || preparsed.check_content_type(threshold_announce::TYPE)
+}
+
 /// Link Store.
 pub type Store = DefaultLinkStore<DefaultTW, DefaultF, MsgId<DefaultTW>, MsgInfo>;
 
+/// Link Store keeping spongos states sealed, it can be passed to `AuthorBuilder::build_with_store`
+/// and `SubscriberBuilder::build_with_store` instead of `Store`.
+pub type EncryptedStore = EncryptedLinkStore<DefaultTW, DefaultF, MsgId<DefaultTW>, MsgInfo>;
+
+/// Link Store keeping only the latest spongos state per message type, used by lightweight Subscriber.
+pub type LightStore = LatestLinkStore<DefaultTW, DefaultF, MsgId<DefaultTW>, MsgInfo>;
+
+mod archive;
+mod audit;
 mod author;
+mod branch;
+mod builder;
+mod bundle;
+mod cover;
+mod cursor;
+mod file_store;
+mod history;
+mod manager;
+mod messages;
+mod mirror;
+mod monitor;
+mod offline;
+mod pending;
+mod rate;
+mod repair;
+mod report;
+mod sequencing;
+mod shared;
 mod subscriber;
 
 /// Tangle-specific Channel Author type.
 pub use author::Author;
 /// Tangle-specific Channel Subscriber type.
 pub use subscriber::Subscriber;
+pub use crate::api::subscriber::AuthorKeyMismatch;
+/// Author and Subscriber builders.
+pub use builder::{
+    AuthorBuilder,
+    SubscriberBuilder,
+    DEFAULT_MSS_HEIGHT,
+};
+/// Announcement bundles for onboarding.
+pub use bundle::AnnouncementBundle;
+/// Cover traffic.
+pub use cover::CoverTraffic;
+/// Read positions for resuming consumption.
+pub use cursor::Cursor;
+pub use crate::message::tagged_packet::is_padding;
+/// Size classes of masked payloads.
+pub use crate::message::payload_padding::PayloadPadding;
+/// Link store persisted in a file.
+pub use file_store::{
+    load_encrypted_store,
+    save_encrypted_store,
+    FileStore,
+    JournalStore,
+};
+/// Channel history paging.
+pub use history::fetch_prev_msgs;
+/// Thread-safe handles.
+pub use shared::{
+    Shared,
+    SharedAuthor,
+    SharedSubscriber,
+};
+/// Wrapped messages as bytes for delivery outside of transport.
+pub use offline::{
+    msg_from_bytes,
+    msg_to_bytes,
+};
+/// Subscriptions to many channels.
+pub use manager::{
+    ChannelManager,
+    Handler,
+    Received,
+};
+/// Iterator over new messages of a channel.
+pub use messages::Messages;
+/// Publishing to mirror channels.
+pub use mirror::Mirror;
+/// Header-only monitoring.
+pub use monitor::{
+    Activity,
+    ChannelActivity,
+};
+/// Rate policy for packets.
+pub use rate::RatePolicy;
+/// Subscriptions pending approval.
+pub use pending::{
+    PendingSubscription,
+    DEFAULT_MAX_PENDING,
+    SubscriptionApproval,
+};
+/// Clock of heartbeats, rate policies, cover traffic and reports.
+pub use iota_streams_app::time::{
+    system_time_provider,
+    ManualTimeProvider,
+    SharedTimeProvider,
+    SystemTimeProvider,
+    TimeProvider,
+};
+/// Channel health reports.
+pub use report::{
+    BranchReport,
+    ChannelReport,
+};
+/// Portable channel archives.
+pub use archive::{
+    read_archive,
+    verify_archive,
+    ArchiveEntry,
+    StreamsArchive,
+};
+/// Third-party audit of channel messages.
+pub use audit::{
+    audit_channel,
+    audit_channel_with,
+    AuditIssue,
+    AuditReport,
+};
 
 #[cfg(test)]
 mod test;
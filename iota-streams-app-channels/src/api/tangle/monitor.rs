@@ -0,0 +1,67 @@
+//! Header-only monitoring of channel activity.
+//!
+//! Monitoring agents track which messages are published to a channel and how the publishing
+//! counter progresses without unwrapping any content, so they need no keys. Next messages are
+//! found by probing links joined to the announcement or to monitored messages (see `repair`
+//! module), only headers of found messages are parsed. Transports deliver whole messages,
+//! payloads are dropped unread.
+//!
+//! Monitoring keeps its own read position, it doesn't affect `Subscriber::fetch_next_msgs`.
+
+use std::collections::{
+    HashMap,
+    HashSet,
+};
+
+use super::*;
+
+/// Message seen by a monitor.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Activity {
+    pub link: Address,
+    /// Link to the message it is joined to.
+    pub joined: Address,
+    pub content_type: String,
+    /// Publisher counter the link of the message is generated with.
+    pub counter: usize,
+}
+
+/// Summary of monitored messages, see `Subscriber::channel_activity`.
+#[derive(Clone, Default, Debug)]
+pub struct ChannelActivity {
+    /// Number of messages by content type.
+    pub messages: HashMap<String, usize>,
+    /// Link to the last monitored message.
+    pub last: Option<Address>,
+    /// Counter of the last monitored message.
+    pub counter: usize,
+}
+
+impl ChannelActivity {
+    /// Total number of monitored messages.
+    pub fn total(&self) -> usize {
+        self.messages.values().sum()
+    }
+}
+
+#[derive(Default)]
+pub(super) struct Monitor {
+    /// Monitored messages, the next messages may be joined to them.
+    pub(super) known: HashSet<MsgId<DefaultTW>>,
+    pub(super) activity: ChannelActivity,
+}
+
+impl Monitor {
+    pub(super) fn record(&mut self, activity: &Activity) {
+        self.known.insert(activity.link.msgid.clone());
+        *self.activity.messages.entry(activity.content_type.clone()).or_insert(0) += 1;
+        self.activity.last = Some(activity.link.clone());
+        self.activity.counter = activity.counter;
+    }
+}
+
+impl HeapSize for Monitor {
+    fn heap_size(&self) -> usize {
+        self.known.heap_size() + self.activity.messages.heap_size() + self.activity.last.heap_size()
+    }
+}
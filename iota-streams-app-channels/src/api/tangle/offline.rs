@@ -0,0 +1,136 @@
+//! Wrapped messages as bytes for delivery pipelines other than `Transport`.
+//!
+//! `send_*_offline` methods wrap a message and return its link together with the
+//! message bytes, nothing is sent. `receive_*_from_bytes` methods unwrap messages
+//! delivered this way. The link is to be delivered along with the bytes, eg. packed
+//! with `Address::to_packed`.
+//!
+//! Binary layout, integers are big-endian:
+//!
+//! ```text
+//! msg = body_size:u32 body[(body_size + 4) / 5]
+//! ```
+//!
+//! `body` trits (header + content) are packed 5 per byte, `body_size` is in trits.
+
+use failure::{
+    ensure,
+    Fallible,
+};
+use std::convert::TryInto;
+
+use super::*;
+use iota_streams_protobuf3::types::LinkStore;
+
+/// Message body as bytes.
+pub fn msg_to_bytes(msg: &Message) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(4 + packed_len(msg.body.size()));
+    bytes.extend(&(msg.body.size() as u32).to_be_bytes());
+    pack_trits(&msg.body, &mut bytes);
+    bytes
+}
+
+/// Message with link `link` and body bytes produced by `msg_to_bytes`.
+pub fn msg_from_bytes(link: &Address, bytes: &[u8]) -> Fallible<Message> {
+    ensure!(bytes.len() >= 4, "Bad message bytes length: {}.", bytes.len());
+    let body_size = u32::from_be_bytes(bytes[..4].try_into().unwrap()) as usize;
+    let body = unpack_trits(&bytes[4..], body_size)?;
+    Ok(Message::new(link.clone(), body))
+}
+
+fn offline(msg: Fallible<Message>) -> Fallible<(Address, Vec<u8>)> {
+    let msg = msg?;
+    let bytes = msg_to_bytes(&msg);
+    Ok((msg.link, bytes))
+}
+
+impl<S> Author<S>
+where
+    S: LinkStore<DefaultTW, DefaultF, MsgId<DefaultTW>, Info = MsgInfo>,
+{
+    /// Announce creation of a new Channel without sending the announcement.
+    pub fn send_announcement_offline(&mut self) -> Fallible<(Address, Vec<u8>)> {
+        offline(self.announce())
+    }
+
+    /// Create a new keyload for all subscribers without sending it.
+    pub fn send_keyload_for_everyone_offline(&mut self, link_to: &Address) -> Fallible<(Address, Vec<u8>)> {
+        offline(self.share_keyload_for_everyone(link_to))
+    }
+
+    /// Create a signed packet without sending it.
+    pub fn send_signed_packet_offline(
+        &mut self,
+        link_to: &Address,
+        public_payload: &Trytes,
+        masked_payload: &Trytes,
+    ) -> Fallible<(Address, Vec<u8>)> {
+        offline(self.sign_packet(link_to, public_payload, masked_payload))
+    }
+
+    /// Create a tagged packet without sending it.
+    pub fn send_tagged_packet_offline(
+        &mut self,
+        link_to: &Address,
+        public_payload: &Trytes,
+        masked_payload: &Trytes,
+    ) -> Fallible<(Address, Vec<u8>)> {
+        offline(self.tag_packet(link_to, public_payload, masked_payload))
+    }
+
+    /// Unwrap subscribe message delivered as bytes.
+    pub fn receive_subscribe_from_bytes(&mut self, link: &Address, bytes: &[u8]) -> Fallible<()> {
+        let msg = msg_from_bytes(link, bytes)?;
+        self.unwrap_subscribe(msg.parse_header()?)
+    }
+
+    /// Unwrap tagged packet delivered as bytes.
+    pub fn receive_tagged_packet_from_bytes(&mut self, link: &Address, bytes: &[u8]) -> Fallible<(Trytes, Trytes)> {
+        let msg = msg_from_bytes(link, bytes)?;
+        self.unwrap_tagged_packet(msg.parse_header()?)
+    }
+}
+
+impl<S> Subscriber<S>
+where
+    S: LinkStore<DefaultTW, DefaultF, MsgId<DefaultTW>, Info = MsgInfo>,
+{
+    /// Create a subscribe message without sending it.
+    pub fn send_subscribe_offline(&mut self, link_to: &Address) -> Fallible<(Address, Vec<u8>)> {
+        offline(self.subscribe(link_to))
+    }
+
+    /// Create a tagged packet without sending it.
+    pub fn send_tagged_packet_offline(
+        &mut self,
+        link_to: &Address,
+        public_payload: &Trytes,
+        masked_payload: &Trytes,
+    ) -> Fallible<(Address, Vec<u8>)> {
+        offline(self.tag_packet(link_to, public_payload, masked_payload))
+    }
+
+    /// Unwrap announcement delivered as bytes.
+    pub fn receive_announcement_from_bytes(&mut self, link: &Address, bytes: &[u8]) -> Fallible<()> {
+        let msg = msg_from_bytes(link, bytes)?;
+        self.unwrap_announcement(msg.parse_header()?)
+    }
+
+    /// Unwrap keyload delivered as bytes.
+    pub fn receive_keyload_from_bytes(&mut self, link: &Address, bytes: &[u8]) -> Fallible<()> {
+        let msg = msg_from_bytes(link, bytes)?;
+        self.unwrap_keyload(msg.parse_header()?)
+    }
+
+    /// Unwrap signed packet delivered as bytes.
+    pub fn receive_signed_packet_from_bytes(&mut self, link: &Address, bytes: &[u8]) -> Fallible<(Trytes, Trytes)> {
+        let msg = msg_from_bytes(link, bytes)?;
+        self.unwrap_signed_packet(msg.parse_header()?)
+    }
+
+    /// Unwrap tagged packet delivered as bytes.
+    pub fn receive_tagged_packet_from_bytes(&mut self, link: &Address, bytes: &[u8]) -> Fallible<(Trytes, Trytes)> {
+        let msg = msg_from_bytes(link, bytes)?;
+        self.unwrap_tagged_packet(msg.parse_header()?)
+    }
+}
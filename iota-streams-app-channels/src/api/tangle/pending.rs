@@ -0,0 +1,125 @@
+//! Subscriptions pending approval.
+//!
+//! With subscription approval, NTRU public keys of handled Subscribe messages are queued
+//! instead of being trusted right away. The application lists pending requests and approves
+//! or denies them, eg. after checking the subscriber out of band. Requests not decided
+//! within the expiry window are dropped, the subscriber has to subscribe again. Requests
+//! are refused while the queue is full.
+
+use failure::{
+    ensure,
+    format_err,
+    Fallible,
+};
+use std::time::Duration;
+
+use super::*;
+
+/// Default maximum number of pending requests.
+pub const DEFAULT_MAX_PENDING: usize = 256;
+
+/// Manual approval of subscriptions.
+#[derive(Copy, Clone, Debug)]
+pub struct SubscriptionApproval {
+    /// Time pending requests are kept for.
+    pub expiry: Duration,
+    /// Maximum number of pending requests.
+    pub max_pending: usize,
+}
+
+impl SubscriptionApproval {
+    pub fn new(expiry: Duration) -> Self {
+        Self {
+            expiry,
+            max_pending: DEFAULT_MAX_PENDING,
+        }
+    }
+
+    pub fn with_max_pending(mut self, max_pending: usize) -> Self {
+        self.max_pending = max_pending;
+        self
+    }
+}
+
+/// Subscription request waiting for approval.
+#[derive(Clone)]
+pub struct PendingSubscription {
+    /// Link to the Subscribe message.
+    pub link: Address,
+    /// Subscriber's NTRU public key id.
+    pub ntru_pkid: NtruPkid,
+    /// Time the request was handled since UNIX epoch.
+    pub received: Duration,
+    ntru_pk: NtruPublicKey,
+}
+
+#[derive(Default)]
+pub(super) struct PendingSubscriptions {
+    pub(super) approval: Option<SubscriptionApproval>,
+    /// Pending requests, oldest first.
+    requests: Vec<PendingSubscription>,
+}
+
+impl PendingSubscriptions {
+    /// Check the request with `ntru_pk` can be queued at `now`: either it replaces a pending
+    /// request or the queue is not full.
+    pub(super) fn ensure_room(&mut self, ntru_pk: &NtruPublicKey, now: Duration) -> Fallible<()> {
+        self.expire(now);
+        if self.requests.iter().any(|request| request.ntru_pk == *ntru_pk) {
+            return Ok(());
+        }
+        let max_pending = self.approval.map_or(usize::MAX, |approval| approval.max_pending);
+        ensure!(
+            self.requests.len() < max_pending,
+            "Too many pending subscriptions, at most {} are kept.",
+            max_pending
+        );
+        Ok(())
+    }
+
+    /// Queue request from Subscribe message at `link` handled at `now`, a pending request
+    /// with the same key is replaced.
+    pub(super) fn push(&mut self, link: Address, ntru_pk: NtruPublicKey, now: Duration) -> Fallible<()> {
+        self.ensure_room(&ntru_pk, now)?;
+        self.requests.retain(|request| request.ntru_pk != ntru_pk);
+        self.requests.push(PendingSubscription {
+            link,
+            ntru_pkid: ntru_pk.get_pkid(),
+            received: now,
+            ntru_pk,
+        });
+        Ok(())
+    }
+
+    /// Requests not expired at `now`.
+    pub(super) fn list(&self, now: Duration) -> Vec<PendingSubscription> {
+        self.requests
+            .iter()
+            .filter(|request| !self.is_expired(request, now))
+            .cloned()
+            .collect()
+    }
+
+    /// Remove request of the subscriber with `ntru_pkid` and return its key.
+    pub(super) fn take(&mut self, ntru_pkid: &NtruPkid, now: Duration) -> Fallible<NtruPublicKey> {
+        self.expire(now);
+        let pos = self
+            .requests
+            .iter()
+            .position(|request| request.ntru_pk.cmp_pkid(ntru_pkid))
+            .ok_or_else(|| format_err!("No pending subscription of {}.", ntru_pkid))?;
+        Ok(self.requests.remove(pos).ntru_pk)
+    }
+
+    fn is_expired(&self, request: &PendingSubscription, now: Duration) -> bool {
+        self.approval
+            .map_or(false, |approval| now.saturating_sub(request.received) >= approval.expiry)
+    }
+
+    fn expire(&mut self, now: Duration) {
+        let approval = self.approval;
+        self.requests.retain(|request| {
+            approval.map_or(true, |approval| now.saturating_sub(request.received) < approval.expiry)
+        });
+    }
+}
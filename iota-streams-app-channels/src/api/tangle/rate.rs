@@ -0,0 +1,65 @@
+//! Rate policies protecting shared branches from flooding.
+//!
+//! Packets are not sequenced and tagged packets don't identify their writer. A writer
+//! publishes to a branch by joining packets to the branch message (eg. the keyload
+//! shared with the writers), so packets are counted per joined link. Packets exceeding
+//! the policy are still unwrapped, their links are flagged for the application to act on.
+//! Arrival time is the time the packet is unwrapped, Streams messages carry no timestamp.
+
+use failure::Fallible;
+use std::{
+    collections::{
+        HashMap,
+        VecDeque,
+    },
+    time::Duration,
+};
+
+use super::*;
+use iota_streams_app::message::HasLink as _;
+
+/// Maximum number of packets joined to the same message within a time window.
+#[derive(Copy, Clone, Debug)]
+pub struct RatePolicy {
+    pub max_msgs: usize,
+    pub window: Duration,
+}
+
+impl RatePolicy {
+    pub fn new(max_msgs: usize, window: Duration) -> Self {
+        Self { max_msgs, window }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct RateGuard {
+    pub(super) policy: Option<RatePolicy>,
+    /// Arrival times of packets within the window per joined link.
+    arrivals: HashMap<Address, VecDeque<Duration>>,
+    /// Links of packets exceeding the policy.
+    pub(super) violations: Vec<Address>,
+}
+
+impl RateGuard {
+    /// Link to the message the packet is joined to.
+    pub(super) fn joined_link<'a>(preparsed: &Preparsed<'a>) -> Fallible<Address> {
+        Ok(Address::new(
+            preparsed.header.link.base().clone(),
+            preparsed.peek_link()?,
+        ))
+    }
+
+    /// Count packet `link` joined to `joined` arrived at `now`, flag it if the policy is exceeded.
+    pub(super) fn record(&mut self, joined: Address, link: &Address, now: Duration) {
+        if let Some(policy) = self.policy {
+            let arrivals = self.arrivals.entry(joined).or_insert_with(VecDeque::new);
+            while arrivals.front().map_or(false, |t| now.saturating_sub(*t) >= policy.window) {
+                arrivals.pop_front();
+            }
+            arrivals.push_back(now);
+            if arrivals.len() > policy.max_msgs {
+                self.violations.push(link.clone());
+            }
+        }
+    }
+}
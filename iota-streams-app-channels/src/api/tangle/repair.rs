@@ -0,0 +1,134 @@
+//! Recovering link generator state from the transport.
+//!
+//! Msgid of a new message is derived from the channel address, the msgid it is joined to
+//! and the number of links generated so far (see `DefaultTangleLinkGenerator`). If user
+//! state is restored from an outdated backup, eg. after a crash, the counter is behind
+//! and new messages would be published at links that are already taken.
+//!
+//! Repair probes the transport for messages at the links following the counter, joined
+//! to any known message. A found message is taken as known as the next message may be
+//! joined to it. Probing stops after `max_gap` consecutive counters with no message,
+//! ie. links generated for messages that were never published are tolerated up to the gap.
+//!
+//! Longer gaps, eg. lost messages, are skipped by probing links at exponentially growing
+//! distance past the counter, see `probe_gap`.
+
+use std::collections::HashSet;
+
+use super::*;
+
+/// Messages found following the counter of `link_gen` and the counter of the last one.
+pub(super) fn probe_sequencing<T: Transport>(
+    transport: &mut T,
+    link_gen: &LinkGen,
+    known: impl IntoIterator<Item = MsgId<DefaultTW>>,
+    max_gap: usize,
+) -> (usize, Vec<Message>)
+where
+    T::RecvOptions: Default,
+{
+    let (counter, found) = probe_sequencing_by(link_gen, known, max_gap, |link| recv(transport, link).map(Some));
+    (counter, found.into_iter().filter_map(|f| f.msg).collect())
+}
+
+/// Message found by probing.
+pub(super) struct Found {
+    /// Msgid the message is joined to.
+    pub(super) joined: MsgId<DefaultTW>,
+    pub(super) link: Address,
+    /// Counter the link is generated with.
+    pub(super) counter: usize,
+    /// The message unless it was skipped by `probe_link`.
+    pub(super) msg: Option<Message>,
+}
+
+/// Probe links with `probe_link`, it returns `None` if there is no message at the link and
+/// `Some(None)` if there is a message to be skipped.
+pub(super) fn probe_sequencing_by(
+    link_gen: &LinkGen,
+    known: impl IntoIterator<Item = MsgId<DefaultTW>>,
+    max_gap: usize,
+    mut probe_link: impl FnMut(&Address) -> Option<Option<Message>>,
+) -> (usize, Vec<Found>) {
+    let mut known = dedup(known);
+    let mut counter = link_gen.counter();
+    let mut found = Vec::new();
+    let mut gap = 0;
+    while gap < max_gap {
+        let c = counter + gap + 1;
+        match probe(&mut probe_link, link_gen, &known, c) {
+            Some(f) => {
+                counter = c;
+                gap = 0;
+                known.push(f.link.msgid.clone());
+                found.push(f);
+            }
+            None => gap += 1,
+        }
+    }
+    (counter, found)
+}
+
+/// Number of counters to skip past the counter of `link_gen` to get to the next found message.
+/// Distances 1, 2, 4, ... up to `max_skip` are probed, then the found message is followed back
+/// to the first of consecutive messages, the lost messages are usually consecutive too.
+pub(super) fn probe_gap<T: Transport>(
+    transport: &mut T,
+    link_gen: &LinkGen,
+    known: impl IntoIterator<Item = MsgId<DefaultTW>>,
+    max_skip: usize,
+) -> Option<usize>
+where
+    T::RecvOptions: Default,
+{
+    let known = dedup(known);
+    let counter = link_gen.counter();
+    let mut missed = 0;
+    let mut distance = 1;
+    while distance <= max_skip {
+        let mut probe_link = |link: &Address| recv(transport, link).map(Some);
+        if probe(&mut probe_link, link_gen, &known, counter + distance).is_some() {
+            let mut first = distance;
+            while first > missed + 1 && probe(&mut probe_link, link_gen, &known, counter + first - 1).is_some() {
+                first -= 1;
+            }
+            return Some(first - 1);
+        }
+        missed = distance;
+        distance *= 2;
+    }
+    None
+}
+
+fn dedup(known: impl IntoIterator<Item = MsgId<DefaultTW>>) -> Vec<MsgId<DefaultTW>> {
+    known.into_iter().collect::<HashSet<_>>().into_iter().collect()
+}
+
+pub(super) fn recv<T: Transport>(transport: &mut T, link: &Address) -> Option<Message>
+where
+    T::RecvOptions: Default,
+{
+    // Transport errors are taken as missing messages.
+    transport.recv_messages(link).ok()?.pop()
+}
+
+/// Message at counter `c` joined to any of `known` msgids.
+fn probe(
+    probe_link: &mut impl FnMut(&Address) -> Option<Option<Message>>,
+    link_gen: &LinkGen,
+    known: &[MsgId<DefaultTW>],
+    c: usize,
+) -> Option<Found> {
+    for msgid in known {
+        let link = link_gen.link_at(msgid, c);
+        if let Some(msg) = probe_link(&link) {
+            return Some(Found {
+                joined: msgid.clone(),
+                link,
+                counter: c,
+                msg,
+            });
+        }
+    }
+    None
+}
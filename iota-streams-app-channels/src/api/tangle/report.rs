@@ -0,0 +1,201 @@
+//! Channel health reports for monitoring.
+//!
+//! Branches of a channel start at keyloads, messages joined to the announcement make up
+//! the main branch. Any other message belongs to the branch of the message it is joined to.
+//! Only messages published or unwrapped by the Author are accounted. Closed branches
+//! accept no more messages. Messages are tracked as long as the link store keeps them,
+//! branches keep their message counts.
+//!
+//! Reports derive `Serialize` and `Deserialize` with the `serde` feature, links are
+//! serialized in compact form (see `Address::to_compact`).
+
+use failure::{
+    ensure,
+    Fallible,
+};
+use std::{
+    collections::{
+        HashMap,
+        HashSet,
+    },
+    fmt,
+    time::Duration,
+};
+
+use super::*;
+
+/// Branch summary.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BranchReport {
+    /// Link to the keyload starting the branch or to the announcement for the main branch.
+    #[cfg_attr(feature = "serde", serde(with = "compact_address"))]
+    pub link: Address,
+    /// Number of messages in the branch, the keyload itself is not counted.
+    pub messages: usize,
+    /// Time since the keyload was published, nothing for the main branch.
+    pub keyload_age: Option<Duration>,
+}
+
+/// Channel summary, see `Author::report`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChannelReport {
+    /// Number of subscribed NTRU public keys.
+    pub subscribers: usize,
+    /// Number of pre-shared keys.
+    pub psks: usize,
+    /// Number of keyload requests not served yet.
+    pub pending_keyload_requests: usize,
+    /// Number of subscription requests pending approval, see `Author::pending_subscriptions`.
+    pub pending_subscriptions: usize,
+    /// Number of links in the link store.
+    pub links: usize,
+    /// Branches in order of creation.
+    pub branches: Vec<BranchReport>,
+    /// Time since the last keyload was published.
+    pub last_keyload_age: Option<Duration>,
+}
+
+impl fmt::Display for ChannelReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "subscribers: {}, psks: {}, pending keyload requests: {}, pending subscriptions: {}, links: {}",
+            self.subscribers, self.psks, self.pending_keyload_requests, self.pending_subscriptions, self.links
+        )?;
+        for branch in &self.branches {
+            write!(f, "branch {}: {} messages", branch.link, branch.messages)?;
+            if let Some(age) = branch.keyload_age {
+                write!(f, ", keyload age {}s", age.as_secs())?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+mod compact_address {
+    use serde::{
+        de::Error as _,
+        Deserialize,
+        Deserializer,
+        Serializer,
+    };
+
+    use super::Address;
+
+    pub fn serialize<S: Serializer>(link: &Address, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&link.to_compact())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Address, D::Error> {
+        let compact = String::deserialize(deserializer)?;
+        Address::from_compact(&compact).map_err(D::Error::custom)
+    }
+}
+
+/// Branches of published and unwrapped messages.
+#[derive(Default)]
+pub(super) struct Tracker {
+    /// Branch of each message kept by the link store, by msgid.
+    branch_of: HashMap<MsgId<DefaultTW>, MsgId<DefaultTW>>,
+    /// Branches and their message counts in order of creation, with keyload publishing time.
+    branches: Vec<(MsgId<DefaultTW>, usize, Option<Duration>)>,
+    /// Closed branches.
+    closed: HashSet<MsgId<DefaultTW>>,
+}
+
+impl Tracker {
+    /// Record message `link` joined to `joined` at `now`, keyloads start new branches.
+    pub(super) fn record(
+        &mut self,
+        joined: &MsgId<DefaultTW>,
+        link: &MsgId<DefaultTW>,
+        is_keyload: bool,
+        now: Duration,
+    ) {
+        if is_keyload {
+            self.branches.push((link.clone(), 0, Some(now)));
+            self.branch_of.insert(link.clone(), link.clone());
+            return;
+        }
+        let branch = self.branch(joined);
+        match self.branches.iter_mut().find(|(b, _, _)| *b == branch) {
+            Some((_, messages, _)) => *messages += 1,
+            None => self.branches.push((branch.clone(), 1, None)),
+        }
+        self.branch_of.insert(link.clone(), branch);
+    }
+
+    /// Branch of message `msgid`. Messages joined to unknown messages, eg. the announcement,
+    /// are in the main branch.
+    pub(super) fn branch(&self, msgid: &MsgId<DefaultTW>) -> MsgId<DefaultTW> {
+        self.branch_of.get(msgid).cloned().unwrap_or_else(|| msgid.clone())
+    }
+
+    /// Close the branch of message `msgid`, return the branch.
+    pub(super) fn close(&mut self, msgid: &MsgId<DefaultTW>) -> MsgId<DefaultTW> {
+        let branch = self.branch(msgid);
+        self.closed.insert(branch.clone());
+        branch
+    }
+
+    pub(super) fn is_closed(&self, msgid: &MsgId<DefaultTW>) -> bool {
+        self.closed.contains(&self.branch(msgid))
+    }
+
+    /// Messages can't be joined to `msgid` if its branch is closed.
+    pub(super) fn ensure_open(&self, msgid: &MsgId<DefaultTW>) -> Fallible<()> {
+        ensure!(!self.is_closed(msgid), "Branch of message {} is closed.", msgid);
+        Ok(())
+    }
+
+    /// Stop tracking messages of `branch`, eg. once they are erased from the link store.
+    /// The branch stays closed and keeps its message count.
+    pub(super) fn forget(&mut self, branch: &MsgId<DefaultTW>) {
+        self.branch_of.retain(|_, b| b != branch);
+    }
+
+    /// Stop tracking messages not kept by `keep`, eg. forgotten by the link store.
+    pub(super) fn retain<K: Fn(&MsgId<DefaultTW>) -> bool>(&mut self, keep: K) {
+        self.branch_of.retain(|msgid, _| keep(msgid));
+    }
+
+    /// Recorded messages of `branch`.
+    pub(super) fn messages(&self, branch: &MsgId<DefaultTW>) -> Vec<MsgId<DefaultTW>> {
+        self.branch_of
+            .iter()
+            .filter(|(_, b)| *b == branch)
+            .map(|(msgid, _)| msgid.clone())
+            .collect()
+    }
+
+    pub(super) fn branches(&self, appinst: &ChannelAddress, now: Duration) -> Vec<BranchReport> {
+        self.branches
+            .iter()
+            .map(|(link, messages, published)| BranchReport {
+                link: Address::new(appinst.clone(), link.clone()),
+                messages: *messages,
+                keyload_age: published.map(|t| now.saturating_sub(t)),
+            })
+            .collect()
+    }
+
+    pub(super) fn last_keyload_age(&self, now: Duration) -> Option<Duration> {
+        self.branches
+            .iter()
+            .rev()
+            .find_map(|(_, _, published)| published.map(|t| now.saturating_sub(t)))
+    }
+}
+
+impl HeapSize for Tracker {
+    fn heap_size(&self) -> usize {
+        self.branch_of.heap_size()
+            + self.branches.capacity() * std::mem::size_of::<(MsgId<DefaultTW>, usize, Option<Duration>)>()
+            + self.branches.iter().map(|(link, _, _)| link.heap_size()).sum::<usize>()
+            + self.closed.heap_size()
+    }
+}
@@ -0,0 +1,52 @@
+//! Strict sequencing of Author's messages.
+//!
+//! Msgid of a message is derived from the msgid it is joined to and the publisher's counter
+//! (see `DefaultTangleLinkGenerator`), so the counter is recovered from the link. With strict
+//! sequencing a Subscriber rejects signed messages of the Author whose counter is not greater
+//! than that of the last handled one, eg. a replayed key change rolling the Author's key back.
+//! Unsigned messages (keyloads, tagged packets) may be published by subscribers and don't
+//! identify their publisher, they are not sequenced.
+
+use failure::{
+    bail,
+    Fallible,
+};
+
+use super::*;
+
+#[derive(Default)]
+pub(super) struct Sequencing {
+    /// Number of counters following the last one the next message is looked for within,
+    /// `None` if sequencing is not enforced.
+    pub(super) window: Option<usize>,
+    /// Counter of the last handled message.
+    pub(super) last: Option<usize>,
+}
+
+impl Sequencing {
+    /// Counter of message at `link` joined to `joined`, it must follow the last handled one.
+    /// `None` is returned if sequencing is not enforced.
+    pub(super) fn check(
+        &self,
+        link_gen: &LinkGen,
+        joined: &MsgId<DefaultTW>,
+        link: &Address,
+    ) -> Fallible<Option<usize>> {
+        let window = match self.window {
+            Some(window) => window,
+            None => return Ok(None),
+        };
+        let first = self.last.map_or(0, |last| last + 1);
+        match (first..first + window).find(|counter| link_gen.link_at(joined, *counter) == *link) {
+            Some(counter) => Ok(Some(counter)),
+            None => bail!("Message {} is not sequenced after the last handled message of the Author.", link),
+        }
+    }
+
+    /// Message with `counter` returned by `check` has been handled.
+    pub(super) fn advance(&mut self, counter: Option<usize>) {
+        if counter.is_some() {
+            self.last = counter;
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Thread-safe handles to Author and Subscriber.
+//!
+//! `Author` and `Subscriber` are `Send` but not `Sync`: the link store is kept in a `RefCell`
+//! and every operation updates the user state (MSS keys, link generator, link store). A
+//! multi-threaded server shares a user through `Shared` handle and locks it per request:
+//!
+//! ```ignore
+//! let author = SharedAuthor::new(AuthorBuilder::new("AUTHOR9SEED").build());
+//! let handle = author.clone();
+//! thread::spawn(move || handle.lock()?.sign_packet(&link, &public, &masked));
+//! ```
+//!
+//! Messages should be sent while the lock is held if their order matters.
+
+use failure::{
+    format_err,
+    Fallible,
+};
+use std::sync::{
+    Arc,
+    Mutex,
+    MutexGuard,
+};
+
+use super::*;
+
+/// Cloneable handle sharing a user between threads.
+pub struct Shared<U> {
+    inner: Arc<Mutex<U>>,
+}
+
+impl<U> Clone for Shared<U> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<U: Send> Shared<U> {
+    pub fn new(user: U) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(user)),
+        }
+    }
+
+    /// Lock the user for exclusive access. It fails if another thread panicked while holding
+    /// the lock: the user state may be inconsistent, eg. an MSS key may have been used.
+    pub fn lock(&self) -> Fallible<MutexGuard<'_, U>> {
+        self.inner.lock().map_err(|_| format_err!("User state is poisoned by a panicked thread."))
+    }
+
+    /// Take the user back if this is the last handle.
+    pub fn try_unwrap(self) -> Fallible<U> {
+        match Arc::try_unwrap(self.inner) {
+            Ok(m) => m.into_inner().map_err(|_| format_err!("User state is poisoned by a panicked thread.")),
+            Err(_) => Err(format_err!("User is still shared.")),
+        }
+    }
+}
+
+/// Author shared between threads.
+pub type SharedAuthor = Shared<Author>;
+
+/// Subscriber shared between threads.
+pub type SharedSubscriber = Shared<Subscriber>;
@@ -1,39 +1,139 @@
 //! Customize Subscriber with default parameters for use over the Tangle.
 
-use failure::Fallible;
-use std::str::FromStr;
+use failure::{
+    bail,
+    ensure,
+    Fallible,
+};
+use std::{
+    str::FromStr,
+    time::Duration,
+};
 
-use super::*;
-use crate::api::subscriber::SubscriberT;
+use super::{
+    branch::{
+        BranchKeys,
+        Branches,
+    },
+    cover::Cover,
+    pending::PendingSubscriptions,
+    monitor::Monitor,
+    rate::RateGuard,
+    report::Tracker,
+    sequencing::Sequencing,
+    *,
+};
+use crate::{
+    api::subscriber::SubscriberT,
+    message::{
+        keyload::Role,
+        tagged_packet,
+        threshold_announce,
+    },
+};
 use iota_streams_app::message::HasLink as _;
 
 use iota_streams_core::{
+    heap::HeapSize,
     prng,
     tbits::Tbits,
 };
+use iota_streams_protobuf3::types::{
+    LinkStore,
+    Trint3,
+};
 
-type SubscriberImp = SubscriberT<DefaultTW, DefaultF, DefaultP, Address, Store, LinkGen>;
+type SubscriberImp<S> = SubscriberT<DefaultTW, DefaultF, DefaultP, Address, S, LinkGen>;
 
-/// Subscriber type.
-pub struct Subscriber {
-    imp: SubscriberImp,
+/// Subscriber type parametrised by link store.
+pub struct Subscriber<S = Store> {
+    pub(super) imp: SubscriberImp<S>,
+    rate: RateGuard,
+    branches: Branches,
+    branch_keys: BranchKeys<MssPublicKey>,
+    monitor: Monitor,
+    sequencing: Sequencing,
+    time_provider: SharedTimeProvider,
 }
 
 impl Subscriber {
     /// Create a new Subscriber instance, optionally generate NTRU keypair.
+    #[deprecated(note = "Use `SubscriberBuilder` instead.")]
     pub fn new(seed: &str, with_ntru: bool) -> Self {
+        Self::gen(Store::default(), seed, with_ntru)
+    }
+
+    /// Short display id of a link, it must not collide with other links in the store.
+    pub fn short_link_id(&self, link: &Address) -> Fallible<String> {
+        link.msgid.to_short_id_checked(self.imp.store.borrow().links())
+    }
+
+    /// Resolve short display id against links in the store.
+    pub fn resolve_short_link_id(&self, short_id: &str) -> Fallible<Address> {
+        ensure!(self.is_registered(), "Subscriber is not registered to a channel.");
+        let msgid = MsgId::resolve_short_id(short_id, self.imp.store.borrow().links())?;
+        Ok(Address::new(self.channel_address().unwrap().clone(), msgid))
+    }
+
+    /// Recover link generator state lost since the last backup, eg. after a crash, by probing
+    /// `transport` for own messages published after the current state (see `repair` module).
+    /// Links of found messages are returned, they are not added to the link store.
+    pub fn repair_sequencing<T: Transport>(&mut self, transport: &mut T, max_gap: usize) -> Fallible<Vec<Address>>
+    where
+        T::RecvOptions: Default,
+    {
+        ensure!(self.is_registered(), "Subscriber is not registered to a channel.");
+        let appinst = self.imp.appinst.as_ref().map(|link| link.msgid.clone());
+        let known: Vec<_> = self.imp.store.borrow().links().cloned().chain(appinst).collect();
+        let (counter, found) = repair::probe_sequencing(transport, &self.imp.link_gen, known, max_gap);
+        self.imp.link_gen.set_counter(counter);
+        Ok(found.into_iter().map(|msg| msg.link).collect())
+    }
+}
+
+impl Subscriber<LightStore> {
+    /// Create a lightweight Subscriber instance for memory-constrained devices.
+    ///
+    /// Only the latest spongos state per message type is kept, so memory usage doesn't
+    /// grow with the channel length. Messages must be handled in order: a message linked
+    /// to an older message than the latest one of its type (eg. a tagged packet linked to
+    /// a replaced keyload) can't be unwrapped.
+    #[deprecated(note = "Use `SubscriberBuilder::build_lightweight` instead.")]
+    pub fn new_lightweight(seed: &str, with_ntru: bool) -> Self {
+        Self::gen(LightStore::default(), seed, with_ntru)
+    }
+}
+
+impl<S> Subscriber<S>
+where
+    S: LinkStore<DefaultTW, DefaultF, MsgId<DefaultTW>, Info = MsgInfo>,
+{
+    pub(super) fn gen(store: S, seed: &str, with_ntru: bool) -> Self {
         let nonce = Tbits::from_str("TANGLESUBSCRIBER").unwrap();
         Self {
-            imp: SubscriberT::gen(
-                Store::default(),
-                LinkGen::default(),
-                prng::dbg_init_str(seed),
-                &nonce,
-                with_ntru,
-            ),
+            imp: SubscriberT::gen(store, LinkGen::default(), prng::dbg_init_str(seed), &nonce, with_ntru),
+            rate: RateGuard::default(),
+            branches: Branches::default(),
+            branch_keys: BranchKeys::default(),
+            monitor: Monitor::default(),
+            sequencing: Sequencing::default(),
+            time_provider: system_time_provider(),
         }
     }
 
+    /// Take over the channel once Handover to own MSS public key has been handled.
+    pub fn into_author(self) -> Fallible<Author<S>> {
+        Ok(Author {
+            imp: self.imp.into_author()?,
+            rate: self.rate,
+            tracker: Tracker::default(),
+            cover: Cover::default(),
+            branch_keys: BranchKeys::default(),
+            pending: PendingSubscriptions::default(),
+            time_provider: self.time_provider,
+        })
+    }
+
     /// Ie. has Announce message been handled?
     pub fn is_registered(&self) -> bool {
         self.imp.appinst.is_some()
@@ -43,7 +143,12 @@ impl Subscriber {
     pub fn unregister(&mut self) {
         self.imp.appinst = None;
         self.imp.author_mss_pk = None;
+        self.imp.announced_mss_pk = None;
+        self.imp.author_set = None;
         self.imp.author_ntru_pk = None;
+        self.imp.lite_mac = None;
+        self.imp.last_heartbeat = None;
+        self.imp.role = None;
     }
 
     /// Return Channel app instance.
@@ -51,16 +156,380 @@ impl Subscriber {
         self.imp.appinst.as_ref().map(|tangle_address| &tangle_address.appinst)
     }
 
+    /// Tag the message at `link` in the link store with `label`, eg. "latest-config",
+    /// to find it later with `find_by_label`. Tagging another message moves the label.
+    pub fn tag_link(&mut self, link: &Address, label: &str) -> Fallible<()> {
+        ensure!(
+            Some(link.base()) == self.channel_address(),
+            "Link is not in the channel."
+        );
+        self.imp.store.borrow_mut().tag_link(link.rel(), label)
+    }
+
+    /// Link to the message tagged with `label`.
+    pub fn find_by_label(&self, label: &str) -> Option<Address> {
+        let msgid = self.imp.store.borrow().find_by_label(label)?;
+        Some(Address::new(self.channel_address()?.clone(), msgid))
+    }
+
     /// Return Author's MSS public key.
     pub fn author_mss_public_key(&self) -> &Option<MssPublicKey> {
         &self.imp.author_mss_pk
     }
 
+    /// Return Author set governing the channel, if any.
+    pub fn author_set(&self) -> Option<&AuthorSet> {
+        self.imp.author_set()
+    }
+
     /// Return Author's NTRU public key.
     pub fn author_ntru_public_key(&self) -> &Option<NtruPublicKey> {
         &self.imp.author_ntru_pk
     }
 
+    /// Return msgid derivation announced in the channel.
+    pub fn msgid_derivation(&self) -> MsgIdDerivation {
+        self.imp.link_gen.msgid_derivation()
+    }
+
+    /// Return own NTRU public key.
+    pub fn ntru_public_key(&self) -> Option<&NtruPublicKey> {
+        self.imp.ntru_public_key()
+    }
+
+    /// Return role granted by the last handled keyload.
+    pub fn role(&self) -> Option<Role> {
+        self.imp.role()
+    }
+
+    /// Role granted in the branch of the message at `link`, it limits publishing to the branch.
+    pub fn branch_role(&self, link: &Address) -> Option<Role> {
+        self.imp.branch_role(link.rel())
+    }
+
+    /// Application protocol version put in headers of new messages, 0 means not specified.
+    pub fn set_app_version(&mut self, app_version: Trint3) {
+        self.imp.app_version = app_version;
+    }
+
+    /// Reject messages whose application version `check` returns false for.
+    pub fn set_app_version_check(&mut self, check: fn(Trint3) -> bool) {
+        self.imp.app_version_check = check;
+    }
+
+    /// Call `handler` with the error before rejecting a message signed with Author's key
+    /// other than the trusted one, eg. a substituted announcement.
+    pub fn set_author_key_mismatch_handler(&mut self, handler: fn(&AuthorKeyMismatch)) {
+        self.imp.on_author_key_mismatch = handler;
+    }
+
+    /// Reject handled messages with variable-length fields larger than `max_size` trytes,
+    /// `None` removes the limit. Parse headers of untrusted messages with
+    /// `parse_header_with_max_size` to limit header fields as well.
+    pub fn set_max_size(&mut self, max_size: Option<usize>) {
+        self.imp.max_size = max_size;
+    }
+
+    /// Custom msgid deriver, it must be set before handling announcement of a channel
+    /// with `MsgIdDerivation::Custom`.
+    pub fn set_msgid_deriver(&mut self, msgid_deriver: Option<SharedMsgIdDeriver>) {
+        self.imp.link_gen.set_msgid_deriver(msgid_deriver);
+    }
+
+    /// Pad masked payloads of tagged packets to size classes, recipients strip the padding.
+    pub fn set_payload_padding(&mut self, padding: PayloadPadding) {
+        self.imp.payload_padding = padding;
+    }
+
+    /// Flag packets exceeding `policy`, `None` disables the check.
+    pub fn set_rate_policy(&mut self, policy: Option<RatePolicy>) {
+        self.rate.policy = policy;
+    }
+
+    /// Clock of heartbeat liveness and rate policy, system time by default.
+    pub fn set_time_provider(&mut self, time_provider: SharedTimeProvider) {
+        self.time_provider = time_provider;
+    }
+
+    /// Links of handled packets exceeding the rate policy, in order of arrival.
+    pub fn rate_violations(&self) -> &[Address] {
+        &self.rate.violations
+    }
+
+    /// Forget flagged packets, eg. once the application has acted on them.
+    pub fn clear_rate_violations(&mut self) {
+        self.rate.violations.clear();
+    }
+
+    /// Reject signed messages of the Author not sequenced after the last handled one (eg.
+    /// replayed), see `sequencing` module. The next message is looked for within `window`
+    /// counters following the last one, `None` disables the check.
+    pub fn set_strict_sequencing(&mut self, window: Option<usize>) {
+        self.sequencing.window = window;
+    }
+
+    /// Counter of the last handled message of the Author under strict sequencing.
+    pub fn last_sequenced(&self) -> Option<usize> {
+        self.sequencing.last
+    }
+
+    /// Accept messages sequenced after `counter` again, eg. to reprocess handled messages
+    /// intentionally. `None` accepts messages from the start of the channel.
+    pub fn rewind_sequencing(&mut self, counter: Option<usize>) {
+        self.sequencing.last = counter;
+    }
+
+    /// Counter of the message under strict sequencing, see `Sequencing::check`.
+    fn sequence<'a>(&self, preparsed: &Preparsed<'a>) -> Fallible<Option<usize>> {
+        if self.sequencing.window.is_none() {
+            return Ok(None);
+        }
+        let link_gen = self.channel_link_gen()?;
+        self.sequencing.check(&link_gen, &preparsed.peek_link()?, &preparsed.header.link)
+    }
+
+    /// Compact base64url representation of a link, eg. for QR codes.
+    pub fn compact_link(&self, link: &Address) -> String {
+        link.to_compact()
+    }
+
+    /// Commitment tag of a message published or handled before, it is the same for
+    /// the publisher and recipients. Links forgotten by the store have no tag.
+    pub fn message_tag(&self, link: &Address) -> Fallible<MessageTag> {
+        let (spongos, _) = self.imp.store.borrow().lookup(link.rel())?;
+        Ok(message::message_tag(&spongos))
+    }
+
+    /// Fetch up to `count` channel messages preceding the message at `link`, the nearest first.
+    /// Late subscribers can page through the channel history instead of syncing all of it.
+    pub fn fetch_prev_msgs<T: Transport>(&self, transport: &mut T, link: &Address, count: usize) -> Fallible<Vec<Message>>
+    where
+        T::RecvOptions: Default,
+    {
+        ensure!(
+            self.channel_address() == Some(link.base()),
+            "Link is not in the channel the Subscriber is registered to."
+        );
+        fetch_prev_msgs(transport, link, count)
+    }
+
+    /// Archive the message at `link` together with up to `count` preceding messages,
+    /// see `fetch_prev_msgs`. The archive can be verified and read offline.
+    pub fn archive<T: Transport>(&self, transport: &mut T, link: &Address, count: usize) -> Fallible<StreamsArchive>
+    where
+        T::RecvOptions: Default,
+    {
+        let mut msgs = self.fetch_prev_msgs(transport, link, count)?;
+        msgs.reverse();
+        msgs.push(transport.recv_message(link)?);
+        StreamsArchive::from_msgs(msgs)
+    }
+
+    /// Follow the branch at `link`: once any branch is followed, `fetch_next_msgs` fetches
+    /// messages of followed branches only.
+    pub fn follow_branch(&mut self, link: &Address) -> Fallible<()> {
+        ensure!(
+            self.channel_address() == Some(link.base()),
+            "Link is not in the channel the Subscriber is registered to."
+        );
+        self.branches.follow(&link.msgid);
+        Ok(())
+    }
+
+    /// Stop following the branch at `link`, returns whether it was followed.
+    pub fn unfollow_branch(&mut self, link: &Address) -> bool {
+        self.branches.unfollow(&link.msgid)
+    }
+
+    /// Fetch messages published after the last fetched one, joined to known messages
+    /// or to messages of followed branches if any is followed. Up to `max_gap` consecutive
+    /// links with no message (eg. messages of other branches) are probed before giving up.
+    /// Fetched messages are not unwrapped and must be handled in order.
+    pub fn fetch_next_msgs<T: Transport>(&mut self, transport: &mut T, max_gap: usize) -> Fallible<Vec<Message>>
+    where
+        T::RecvOptions: Default,
+    {
+        let (link_gen, known) = self.fetch_state()?;
+        let (counter, found) = repair::probe_sequencing(transport, &link_gen, known, max_gap);
+        self.branches.counter = counter;
+        for msg in &found {
+            self.branches.add(&msg.parse_header()?.peek_link()?, &msg.link.msgid);
+        }
+        Ok(found)
+    }
+
+    /// Iterate over new messages fetched with `fetch_next_msgs` and unwrapped in order,
+    /// see `messages` module.
+    pub fn messages<'a, T: Transport>(&'a mut self, transport: &'a mut T, max_gap: usize) -> Messages<'a, S, T>
+    where
+        T::RecvOptions: Default,
+    {
+        Messages::new(self, transport, max_gap)
+    }
+
+    /// Fetch messages like `fetch_next_msgs` but download only messages attached at or after
+    /// `since`, in milliseconds since UNIX epoch. Older messages are probed by timestamp only
+    /// and are skipped, newer messages joined to them are found but can't be unwrapped unless
+    /// the state they are joined to is known, eg. from a keyload fetched separately.
+    pub fn fetch_msgs_since<T: TimestampTransport>(
+        &mut self,
+        transport: &mut T,
+        max_gap: usize,
+        since: i64,
+    ) -> Fallible<Vec<Message>>
+    where
+        T::RecvOptions: Default,
+    {
+        let (link_gen, known) = self.fetch_state()?;
+        let (counter, found) = repair::probe_sequencing_by(&link_gen, known, max_gap, |link| {
+            // Transport errors are taken as missing messages.
+            let timestamps = transport.attachment_timestamps(link).ok()?;
+            if timestamps.is_empty() {
+                None
+            } else if timestamps.iter().all(|timestamp| *timestamp < since) {
+                Some(None)
+            } else {
+                Some(transport.recv_messages_since(link, since).ok()?.pop())
+            }
+        });
+        self.branches.counter = counter;
+        let mut msgs = Vec::new();
+        for f in found {
+            self.branches.add(&f.joined, &f.link.msgid);
+            msgs.extend(f.msg);
+        }
+        Ok(msgs)
+    }
+
+    /// Skip over lost messages when `fetch_next_msgs` stalls. Links are probed at distance
+    /// 1, 2, 4, ... up to `max_skip` past the last fetched message. If a message is found,
+    /// the next fetch resumes from it and the number of skipped links is returned. Only
+    /// messages joined to known messages can be found, messages joined to a lost message
+    /// are lost too.
+    pub fn skip_lost_msgs<T: Transport>(&mut self, transport: &mut T, max_skip: usize) -> Fallible<Option<usize>>
+    where
+        T::RecvOptions: Default,
+    {
+        let (link_gen, known) = self.fetch_state()?;
+        let skipped = repair::probe_gap(transport, &link_gen, known, max_skip);
+        if let Some(skipped) = skipped {
+            self.branches.counter += skipped;
+        }
+        Ok(skipped)
+    }
+
+    /// Fetch messages published after the last monitored one and parse their headers only,
+    /// see `monitor` module. Messages joined to the announcement or to monitored messages
+    /// are found, up to `max_gap` consecutive links with no message are probed.
+    pub fn monitor_next_msgs<T: Transport>(&mut self, transport: &mut T, max_gap: usize) -> Fallible<Vec<Activity>>
+    where
+        T::RecvOptions: Default,
+    {
+        let (mut link_gen, _) = self.fetch_state()?;
+        link_gen.set_counter(self.monitor.activity.counter);
+        let announcement = self.imp.appinst.clone().unwrap();
+        let known = self.monitor.known.iter().cloned().chain(Some(announcement.msgid.clone()));
+        let (_, found) = repair::probe_sequencing_by(&link_gen, known, max_gap, |link| {
+            repair::recv(transport, link).map(Some)
+        });
+        let mut monitored = Vec::new();
+        for f in found {
+            if let Some(msg) = f.msg {
+                let activity = Activity {
+                    content_type: msg.parse_header()?.content_type(),
+                    link: f.link,
+                    joined: Address::new(announcement.appinst.clone(), f.joined),
+                    counter: f.counter,
+                };
+                self.monitor.record(&activity);
+                monitored.push(activity);
+            }
+        }
+        Ok(monitored)
+    }
+
+    /// Summary of messages monitored with `monitor_next_msgs`.
+    pub fn channel_activity(&self) -> &ChannelActivity {
+        &self.monitor.activity
+    }
+
+    /// Read position of the Subscriber, see `cursor` module.
+    pub fn cursor(&self) -> Fallible<Cursor> {
+        let channel = match &self.imp.appinst {
+            Some(appinst) => appinst.clone(),
+            None => bail!("Subscriber is not registered to a channel."),
+        };
+        let mut followed: Vec<_> = self
+            .branches
+            .followed
+            .iter()
+            .map(|(msgid, branch)| (msgid.clone(), branch.clone()))
+            .collect();
+        followed.sort_by(|a, b| a.0.to_packed().cmp(&b.0.to_packed()));
+        Ok(Cursor {
+            channel,
+            counter: self.branches.counter,
+            followed,
+        })
+    }
+
+    /// Resume fetching from the read position `cursor` of a Subscriber registered to the same
+    /// channel, messages fetched before it are skipped.
+    pub fn resume_from(&mut self, cursor: &Cursor) -> Fallible<()> {
+        ensure!(
+            self.imp.appinst.as_ref() == Some(&cursor.channel),
+            "Cursor is not in the channel the Subscriber is registered to."
+        );
+        self.branches.counter = cursor.counter;
+        self.branches.followed = cursor.followed.iter().cloned().collect();
+        Ok(())
+    }
+
+    /// Link generator at the last fetched message and msgids the next messages may be joined to.
+    pub(super) fn fetch_state(&self) -> Fallible<(LinkGen, Vec<MsgId<DefaultTW>>)> {
+        let mut link_gen = self.channel_link_gen()?;
+        let appinst = self.imp.appinst.clone().unwrap();
+        let known: Vec<_> = if self.branches.followed.is_empty() {
+            let entries = self.imp.store.borrow().entries()?;
+            entries
+                .into_iter()
+                .map(|(msgid, _, _)| msgid)
+                .chain(Some(appinst.msgid.clone()))
+                .collect()
+        } else {
+            self.branches.followed.keys().cloned().collect()
+        };
+        link_gen.set_counter(self.branches.counter);
+        Ok((link_gen, known))
+    }
+
+    /// Link generator of the channel the Subscriber is registered to.
+    fn channel_link_gen(&self) -> Fallible<LinkGen> {
+        let appinst = match &self.imp.appinst {
+            Some(appinst) => appinst,
+            None => bail!("Subscriber is not registered to a channel."),
+        };
+        let mut link_gen = LinkGen::default();
+        link_gen.reset_appinst(appinst.appinst.clone());
+        link_gen.set_msgid_derivation(self.msgid_derivation());
+        link_gen.set_msgid_deriver(self.imp.link_gen.msgid_deriver().cloned());
+        Ok(link_gen)
+    }
+
+    /// Confirmation status of a message sent at `link`, unconfirmed messages can be reattached
+    /// with `ConfirmTransport::reattach`.
+    pub fn confirmation_status<T: ConfirmTransport>(
+        &self,
+        transport: &mut T,
+        link: &Address,
+    ) -> Fallible<ConfirmationStatus> {
+        ensure!(
+            self.channel_address() == Some(link.base()),
+            "Link is not in the channel the Subscriber is registered to."
+        );
+        transport.confirmation_status(link)
+    }
+
     /// Create tagged packet.
     pub fn tag_packet(
         &mut self,
@@ -72,30 +541,160 @@ impl Subscriber {
             .tag_packet(link_to.rel(), public_payload, masked_payload, MsgInfo::TaggedPacket)
     }
 
+    /// Create tagged packet with additional authenticated data, `aad` is not sent
+    /// and recipients must provide it to unwrap the packet.
+    pub fn tag_packet_with_aad(
+        &mut self,
+        link_to: &Address,
+        public_payload: &Trytes,
+        masked_payload: &Trytes,
+        aad: &Trytes,
+    ) -> Fallible<Message> {
+        self.imp
+            .tag_packet_with_aad(link_to.rel(), public_payload, masked_payload, aad, MsgInfo::TaggedPacket)
+    }
+
+    /// Create a padding packet with masked payload of `payload_size` trytes.
+    /// It can't be told from tagged packets without the session key.
+    pub fn pad(&mut self, link_to: &Address, payload_size: usize) -> Fallible<Message> {
+        let masked_payload = tagged_packet::padding_payload(payload_size)?;
+        self.tag_packet(link_to, &Trytes::default(), &masked_payload)
+    }
+
+    /// Create a framed packet, masked payload is split into tagged frames of `frame_size` trytes.
+    pub fn frame_packet(
+        &mut self,
+        link_to: &Address,
+        public_payload: &Trytes,
+        masked_payload: &Trytes,
+        frame_size: usize,
+    ) -> Fallible<Message> {
+        self.imp.frame_packet(
+            link_to.rel(),
+            public_payload,
+            masked_payload,
+            frame_size,
+            MsgInfo::FramedPacket,
+        )
+    }
+
+    /// Create a lite packet with MAC truncated to the size announced in the channel.
+    pub fn lite_packet(&mut self, link_to: &Address, payload: &Trytes) -> Fallible<Message> {
+        self.imp.lite_packet(link_to.rel(), payload, MsgInfo::LitePacket)
+    }
+
+    /// Create a tagged packet with masked branch label and schema id in header extension.
+    pub fn tag_packet_with_ext(
+        &mut self,
+        link_to: &Address,
+        public_payload: &Trytes,
+        masked_payload: &Trytes,
+        branch: &str,
+        schema: &str,
+    ) -> Fallible<Message> {
+        self.imp.tag_packet_with_ext(
+            link_to.rel(),
+            public_payload,
+            masked_payload,
+            branch,
+            schema,
+            MsgInfo::TaggedPacket,
+        )
+    }
+
+    /// Unwrap header extension, it's readable only by keyload recipients.
+    pub fn unwrap_header_ext<'a>(&self, preparsed: &Preparsed<'a>) -> Fallible<HeaderExt> {
+        self.imp.unwrap_header_ext(preparsed)
+    }
+
     /// Subscribe to a Channel app instance.
     pub fn subscribe(&mut self, link_to: &Address) -> Fallible<Message> {
         //TODO: remove link_to
         self.imp.subscribe(link_to.rel(), MsgInfo::Subscribe)
     }
 
+    /// Ask Author for a new keyload, attach message to `link_to`.
+    pub fn request_keyload(&mut self, link_to: &Address) -> Fallible<Message> {
+        self.imp.request_keyload(link_to.rel(), MsgInfo::KeyloadRequest)
+    }
+
     /// Unsubscribe from the Channel app instance.
     pub fn unsubscribe(&mut self, link_to: &Address) -> Fallible<Message> {
         //TODO: lookup link_to Subscribe message.
         self.imp.unsubscribe(link_to.rel(), MsgInfo::Unsubscribe)
     }
 
-    /// Handle Channel app instance announcement.
+    /// Handle Channel app instance announcement, either Announce or ThresholdAnnounce.
     pub fn unwrap_announcement<'a>(&mut self, preparsed: Preparsed<'a>) -> Fallible<()> {
-        self.imp.handle_announcement(preparsed, MsgInfo::Announce)?;
+        if preparsed.check_content_type(threshold_announce::TYPE) {
+            self.imp.handle_threshold_announcement(preparsed, MsgInfo::Announce)?;
+        } else {
+            self.imp.handle_announcement(preparsed, MsgInfo::Announce)?;
+        }
         self.imp
             .link_gen
             .reset_appinst(self.imp.appinst.as_ref().unwrap().base().clone());
         Ok(())
     }
 
+    /// Fetch and handle the announcement of the channel with address `author_pk` (ie. MSS public
+    /// key of channel owner), so that announcement links need not be shared out of band.
+    /// The link is derived locally, `index` is the number of messages published in the channel
+    /// before the announcement: 0 for a channel announced first. Returns the announcement link.
+    pub fn receive_announcement_by_pk<T: Transport>(
+        &mut self,
+        transport: &mut T,
+        author_pk: &ChannelAddress,
+        index: usize,
+    ) -> Fallible<Address>
+    where
+        T::RecvOptions: Default,
+    {
+        let mut link_gen = LinkGen::default();
+        link_gen.reset_appinst(author_pk.clone());
+        link_gen.set_msgid_deriver(self.imp.link_gen.msgid_deriver().cloned());
+        // Msgid derivation is announced, so all known ones are tried.
+        let mut msgid_derivations = vec![MsgIdDerivation::ChannelPrp, MsgIdDerivation::Troika];
+        if link_gen.msgid_deriver().is_some() {
+            msgid_derivations.push(MsgIdDerivation::Custom);
+        }
+        for msgid_derivation in msgid_derivations {
+            link_gen.set_msgid_derivation(msgid_derivation);
+            // The first link of a channel is its address, it's not published.
+            let link = link_gen.link_at(&MsgId::default(), index + 2);
+            if let Ok(msg) = transport.recv_message(&link) {
+                self.unwrap_announcement(msg.parse_header()?)?;
+                if self.imp.author_mss_pk.as_ref().map(|pk| pk.tbits()) != Some(author_pk.tbits()) {
+                    self.unregister();
+                    bail!("Announcement is not signed with the channel address key.");
+                }
+                return Ok(link);
+            }
+        }
+        bail!("Announcement of channel {} not found at index {}.", author_pk, index)
+    }
+
     /// Handle key change.
     pub fn unwrap_change_key<'a>(&mut self, preparsed: Preparsed<'a>) -> Fallible<()> {
+        let counter = self.sequence(&preparsed)?;
         self.imp.handle_change_key(preparsed, MsgInfo::ChangeKey)?;
+        self.sequencing.advance(counter);
+        Ok(())
+    }
+
+    /// Generate own MSS keypair and accept channel handover. The returned MSS public key
+    /// and acceptance signature are passed to the channel Author.
+    pub fn accept_handover(&mut self, mss_height: usize) -> Fallible<(MssPublicKey, Tbits<DefaultTW>)> {
+        let nonce = Tbits::from_str("TANGLESUCCESSOR").unwrap();
+        self.imp.accept_handover(&nonce, mss_height)
+    }
+
+    /// Handle channel handover. The successor publishes with its own counter, so strict
+    /// sequencing starts over.
+    pub fn unwrap_handover<'a>(&mut self, preparsed: Preparsed<'a>) -> Fallible<()> {
+        self.sequence(&preparsed)?;
+        self.imp.handle_handover(preparsed, MsgInfo::Handover)?;
+        self.sequencing.last = None;
         Ok(())
     }
 
@@ -105,13 +704,230 @@ impl Subscriber {
         Ok(())
     }
 
+    /// Import session key of keyload at `link` escrowed to own NTRU public key, the keyload
+    /// and messages linked to it can be unwrapped then with read-only role.
+    pub fn import_escrowed_key(&mut self, link: &Address, escrow: &Tbits<DefaultTW>) -> Fallible<()> {
+        self.imp.import_escrowed_key(link.clone(), escrow)
+    }
+
+    /// Links of handled keyloads and rekeys with cached session keys.
+    pub fn session_keys(&self) -> Vec<Address> {
+        self.imp.session_key_links().cloned().collect()
+    }
+
+    /// Forget session key of keyload at `link`, messages linked to the keyload can't be
+    /// unwrapped afterwards. Returns whether the key was cached.
+    pub fn forget_session_key(&mut self, link: &Address) -> bool {
+        self.imp.forget_session_key(link)
+    }
+
+    /// Forget all cached session keys.
+    pub fn purge_session_keys(&mut self) {
+        self.imp.purge_session_keys()
+    }
+
+    /// Handle reveal key, return its id. Embargoed keyloads locked with the key
+    /// can be unwrapped afterwards.
+    pub fn unwrap_reveal_key<'a>(&mut self, preparsed: Preparsed<'a>) -> Fallible<PskId> {
+        let counter = self.sequence(&preparsed)?;
+        let reveal_key_id = self.imp.handle_reveal_key(preparsed, MsgInfo::RevealKey)?;
+        self.sequencing.advance(counter);
+        Ok(reveal_key_id)
+    }
+
+    /// Handle rekey of a handled keyload (or rekey).
+    pub fn unwrap_rekey<'a>(&mut self, preparsed: Preparsed<'a>) -> Fallible<()> {
+        self.imp.handle_rekey(preparsed, MsgInfo::Rekey)
+    }
+
+    /// Handle Author's heartbeat, stale or replayed heartbeats are rejected.
+    pub fn unwrap_heartbeat<'a>(&mut self, preparsed: Preparsed<'a>) -> Fallible<Heartbeat> {
+        let counter = self.sequence(&preparsed)?;
+        let heartbeat = self.imp.handle_heartbeat(preparsed, MsgInfo::Heartbeat)?;
+        self.sequencing.advance(counter);
+        Ok(heartbeat)
+    }
+
+    /// Handle Author's branch closing marker, return link to the message it's attached to.
+    /// The closed branch is not followed anymore.
+    pub fn unwrap_close_branch<'a>(&mut self, preparsed: Preparsed<'a>) -> Fallible<Address> {
+        let appinst = preparsed.header.link.base().clone();
+        let counter = self.sequence(&preparsed)?;
+        let msgid = self.imp.handle_close_branch(preparsed, MsgInfo::CloseBranch)?;
+        self.sequencing.advance(counter);
+        self.branches.close(&msgid);
+        Ok(Address::new(appinst, msgid))
+    }
+
+    /// The last handled Author's heartbeat.
+    pub fn last_heartbeat(&self) -> Option<Heartbeat> {
+        self.imp.last_heartbeat
+    }
+
+    /// Whether the last handled heartbeat was published within `window` from now.
+    /// Author's and Subscriber's clocks are assumed to be in sync.
+    pub fn author_is_live(&self, window: Duration) -> bool {
+        let now = self.time_provider.now().as_secs() as usize;
+        self.imp
+            .last_heartbeat
+            .map_or(false, |heartbeat| now.saturating_sub(heartbeat.timestamp) <= window.as_secs() as usize)
+    }
+
+    /// Handle participants snapshot published by the Author.
+    pub fn unwrap_participants<'a>(&mut self, preparsed: Preparsed<'a>) -> Fallible<()> {
+        let counter = self.sequence(&preparsed)?;
+        self.imp.handle_participants(preparsed, MsgInfo::Participants)?;
+        self.sequencing.advance(counter);
+        Ok(())
+    }
+
+    /// Subscribers' NTRU public keys and roles listed in the last handled participants snapshot.
+    pub fn participants(&self) -> &[(NtruPublicKey, Role)] {
+        self.imp.participants()
+    }
+
     /// Unwrap and verify signed packet.
     pub fn unwrap_signed_packet<'a>(&mut self, preparsed: Preparsed<'a>) -> Fallible<(Trytes, Trytes)> {
-        self.imp.handle_signed_packet(preparsed, MsgInfo::SignedPacket)
+        self.do_unwrap_signed_packet(preparsed, None)
+    }
+
+    /// Unwrap and verify signed packet created with additional authenticated data `aad`.
+    pub fn unwrap_signed_packet_with_aad<'a>(
+        &mut self,
+        preparsed: Preparsed<'a>,
+        aad: &Trytes,
+    ) -> Fallible<(Trytes, Trytes)> {
+        self.do_unwrap_signed_packet(preparsed, Some(aad))
+    }
+
+    fn do_unwrap_signed_packet<'a>(
+        &mut self,
+        preparsed: Preparsed<'a>,
+        aad: Option<&Trytes>,
+    ) -> Fallible<(Trytes, Trytes)> {
+        let joined = RateGuard::joined_link(&preparsed)?;
+        let link = preparsed.header.link.clone();
+        let counter = self.sequence(&preparsed)?;
+        let payloads = match self.branch_keys.get(&joined.msgid) {
+            Some(mss_pk) => self
+                .imp
+                .handle_signed_packet_with_key(preparsed, aad, mss_pk, MsgInfo::SignedPacket)?,
+            None => self
+                .imp
+                .handle_signed_packet_with_aad(preparsed, aad, MsgInfo::SignedPacket)?,
+        };
+        self.sequencing.advance(counter);
+        self.branch_keys.add(&joined.msgid, &link.msgid);
+        self.rate.record(joined, &link, self.time_provider.now());
+        Ok(payloads)
+    }
+
+    /// Unwrap branch key bound by the Author, signed packets of the branch are verified with it.
+    /// Label and the key are returned.
+    pub fn unwrap_branch_key<'a>(&mut self, preparsed: Preparsed<'a>) -> Fallible<(Trytes, MssPublicKey)> {
+        let joined = preparsed.peek_link()?;
+        let link = preparsed.header.link.clone();
+        let counter = self.sequence(&preparsed)?;
+        let (label, mss_pk) = self.imp.handle_branch_key(preparsed, MsgInfo::BranchKey)?;
+        self.sequencing.advance(counter);
+        self.branch_keys.bind(label.clone(), mss_pk.clone(), &[&joined, &link.msgid]);
+        Ok((label, mss_pk))
+    }
+
+    /// Unwrap and verify structured packet, values of fields not disclosed to the Subscriber are omitted.
+    pub fn unwrap_structured_packet<'a>(&mut self, preparsed: Preparsed<'a>) -> Fallible<Vec<Field>> {
+        let joined = RateGuard::joined_link(&preparsed)?;
+        let link = preparsed.header.link.clone();
+        let counter = self.sequence(&preparsed)?;
+        let fields = self.imp.handle_structured_packet(preparsed, MsgInfo::StructuredPacket)?;
+        self.sequencing.advance(counter);
+        self.rate.record(joined, &link, self.time_provider.now());
+        Ok(fields)
     }
 
     /// Unwrap and verify tagged packet.
     pub fn unwrap_tagged_packet<'a>(&mut self, preparsed: Preparsed<'a>) -> Fallible<(Trytes, Trytes)> {
-        self.imp.handle_tagged_packet(preparsed, MsgInfo::TaggedPacket)
+        self.do_unwrap_tagged_packet(preparsed, None)
+    }
+
+    /// Unwrap and verify tagged packet created with additional authenticated data `aad`.
+    pub fn unwrap_tagged_packet_with_aad<'a>(
+        &mut self,
+        preparsed: Preparsed<'a>,
+        aad: &Trytes,
+    ) -> Fallible<(Trytes, Trytes)> {
+        self.do_unwrap_tagged_packet(preparsed, Some(aad))
+    }
+
+    fn do_unwrap_tagged_packet<'a>(
+        &mut self,
+        preparsed: Preparsed<'a>,
+        aad: Option<&Trytes>,
+    ) -> Fallible<(Trytes, Trytes)> {
+        let joined = RateGuard::joined_link(&preparsed)?;
+        let link = preparsed.header.link.clone();
+        let payloads = self
+            .imp
+            .handle_tagged_packet_with_aad(preparsed, aad, MsgInfo::TaggedPacket)?;
+        self.rate.record(joined, &link, self.time_provider.now());
+        Ok(payloads)
+    }
+
+    /// Unwrap tagged packet passing masked payload to `on_chunk` in chunks of `chunk_size`
+    /// trytes as they are decrypted, eg. to write a large payload to storage without buffering
+    /// it. Public payload is returned once the MAC is verified, chunks already passed must be
+    /// discarded if unwrapping fails.
+    pub fn unwrap_tagged_packet_streaming<'a>(
+        &mut self,
+        preparsed: Preparsed<'a>,
+        chunk_size: usize,
+        mut on_chunk: impl FnMut(&Trytes) -> Fallible<()>,
+    ) -> Fallible<Trytes> {
+        let joined = RateGuard::joined_link(&preparsed)?;
+        let link = preparsed.header.link.clone();
+        let public_payload = self.imp.handle_tagged_packet_streaming(
+            preparsed,
+            None,
+            chunk_size,
+            &mut on_chunk,
+            MsgInfo::TaggedPacket,
+        )?;
+        self.rate.record(joined, &link, self.time_provider.now());
+        Ok(public_payload)
+    }
+
+    /// Unwrap multi-tagged packet and verify MAC of own pre-shared key group.
+    pub fn unwrap_multi_tagged_packet<'a>(&mut self, preparsed: Preparsed<'a>) -> Fallible<(Trytes, Trytes)> {
+        let joined = RateGuard::joined_link(&preparsed)?;
+        let link = preparsed.header.link.clone();
+        let payloads = self
+            .imp
+            .handle_multi_tagged_packet(preparsed, MsgInfo::MultiTaggedPacket)?;
+        self.rate.record(joined, &link, self.time_provider.now());
+        Ok(payloads)
+    }
+
+    /// Unwrap framed packet, a corrupted frame fails with `FrameCorrupted` error.
+    pub fn unwrap_framed_packet<'a>(&mut self, preparsed: Preparsed<'a>) -> Fallible<(Trytes, Trytes)> {
+        let joined = RateGuard::joined_link(&preparsed)?;
+        let link = preparsed.header.link.clone();
+        let payloads = self.imp.handle_framed_packet(preparsed, MsgInfo::FramedPacket)?;
+        self.rate.record(joined, &link, self.time_provider.now());
+        Ok(payloads)
+    }
+
+    /// Unwrap lite packet.
+    pub fn unwrap_lite_packet<'a>(&mut self, preparsed: Preparsed<'a>) -> Fallible<Trytes> {
+        let joined = RateGuard::joined_link(&preparsed)?;
+        let link = preparsed.header.link.clone();
+        let payload = self.imp.handle_lite_packet(preparsed, MsgInfo::LitePacket)?;
+        self.rate.record(joined, &link, self.time_provider.now());
+        Ok(payload)
+    }
+}
+
+impl<S: HeapSize> HeapSize for Subscriber<S> {
+    fn heap_size(&self) -> usize {
+        self.imp.heap_size() + self.branches.heap_size() + self.branch_keys.heap_size() + self.monitor.heap_size()
     }
 }
@@ -1,33 +1,135 @@
 #![allow(non_snake_case)]
 use crate::{
-    api::tangle::{
-        Address,
-        Author,
-        BucketTransport,
-        Subscriber,
-        Transport,
+    api::{
+        author::KEM_HISTORY_SIZE,
+        refuse_kem_reuse,
+        tangle::{
+            Activity,
+            Address,
+            AnnouncementBundle,
+            audit_channel,
+            audit_channel_with,
+            AuditIssue,
+            Author,
+            Audience,
+            AuthorBuilder,
+            AuthorKeyMismatch,
+            BucketTransport,
+            ChannelAddress,
+            ChannelManager,
+            CoverTraffic,
+            Cursor,
+            DefaultF,
+            DefaultTW,
+            EncryptedStore,
+            Field,
+            FileStore,
+            load_encrypted_store,
+            is_padding,
+            LinkGen,
+            ManualTimeProvider,
+            Message,
+            Mirror,
+            MsgInfo,
+            PayloadPadding,
+            RatePolicy,
+            SubscriptionApproval,
+            Received,
+            SharedAuthor,
+            SharedMsgIdDeriver,
+            read_archive,
+            save_encrypted_store,
+            Store,
+            StreamsArchive,
+            Subscriber,
+            SubscriberBuilder,
+            Transport,
+        },
+    },
+    message::{
+        self,
+        keyload::{
+            RecipientPadding,
+            Role,
+        },
+        framed_packet::{
+            FrameCorrupted,
+            FRAME_TAG_SIZE,
+        },
+        lite_packet::LiteMac,
     },
-    message,
 };
 use failure::{
+    bail,
     ensure,
     Fallible,
 };
-use iota_streams_app::message::HasLink;
-use iota_streams_core::tbits::Tbits;
-use iota_streams_protobuf3::types::Trytes;
-use std::str::FromStr;
+use iota_streams_app::{
+    message::{
+        HasLink,
+        LinkGenerator,
+        STREAMS_5_VER,
+        header::TlvExtension,
+        inspect,
+        migrate_links,
+    },
+    transport::{
+        tangle::{
+            MsgId,
+            MsgIdDerivation,
+            MsgIdDeriver,
+            MSGID_SIZE,
+        },
+        Transport as _,
+    },
+};
+use iota_streams_core::{
+    heap::HeapSize,
+    prng,
+    sponge::spongos::Spongos,
+    tbits::Tbits,
+};
+use iota_streams_protobuf3::{
+    command::{
+        unwrap::SizeLimitExceeded,
+        wrap,
+        Absorb,
+        Commit,
+        Squeeze,
+    },
+    io,
+    types::{
+        External,
+        LinkStore,
+        NTrytes,
+        Size,
+        Trint3,
+        Trytes,
+    },
+};
+use std::{
+    str::FromStr,
+    sync::{
+        atomic::{
+            AtomicUsize,
+            Ordering,
+        },
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
 
 fn example<T: Transport>(transport: &mut T) -> Fallible<()>
 where
     T::SendOptions: Default,
     T::RecvOptions: Default,
 {
-    let mut author = Author::new("AUTHOR9SEED", 2, true);
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").mss_height(2).build();
     println!("Channel address = {}", author.channel_address());
 
-    let mut subscriberA = Subscriber::new("SUBSCRIBERA9SEED", false);
-    let mut subscriberB = Subscriber::new("SUBSCRIBERB9SEED", true);
+    let mut subscriberA = SubscriberBuilder::new("SUBSCRIBERA9SEED").ntru(false).build();
+    let mut subscriberB = SubscriberBuilder::new("SUBSCRIBERB9SEED").build();
 
     let public_payload = Trytes(Tbits::from_str("PUBLICPAYLOAD").unwrap());
     let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
@@ -40,6 +142,7 @@ where
         (msg.link.appinst.to_string(), msg.link.msgid.to_string())
     };
     let announcement_link = Address::from_str(&announcement_address, &announcement_tag).unwrap();
+    ensure!(Address::from_compact(&author.compact_link(&announcement_link))? == announcement_link);
 
     {
         let msg = transport.recv_message(&announcement_link)?;
@@ -107,6 +210,9 @@ where
         let resultA = subscriberA.unwrap_keyload(preparsed.clone());
         ensure!(resultA.is_err());
         subscriberB.unwrap_keyload(preparsed)?;
+        ensure!(subscriberB.role() == Some(Role::ReadWrite));
+        let short_id = author.short_link_id(&keyload_link)?;
+        ensure!(author.resolve_short_link_id(&short_id)? == keyload_link);
     }
 
     println!("tag packet");
@@ -135,6 +241,77 @@ where
         subscriberB.unwrap_keyload(preparsed)?;
     }
 
+    println!("share read-only keyload");
+    let read_only_keyload_link = {
+        let ntru_pkid = subscriberB.ntru_public_key().unwrap().get_pkid();
+        author.set_ntru_role(ntru_pkid, Role::ReadOnly);
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        println!("  {}", msg);
+        transport.send_message(&msg)?;
+        msg.link
+    };
+
+    {
+        let msg = transport.recv_message(&read_only_keyload_link)?;
+        let preparsed = msg.parse_header()?;
+        ensure!(preparsed.check_content_type(message::keyload::TYPE));
+        subscriberB.unwrap_keyload(preparsed)?;
+        ensure!(subscriberB.role() == Some(Role::ReadOnly));
+        let result = subscriberB.tag_packet(&read_only_keyload_link, &public_payload, &masked_payload);
+        ensure!(result.is_err());
+    }
+
+    println!("request keyload");
+    let keyload_request_link = {
+        let msg = subscriberB.request_keyload(&announcement_link)?;
+        println!("  {}", msg);
+        transport.send_message(&msg)?;
+        msg.link
+    };
+
+    let requested_keyload_link = {
+        let msg = transport.recv_message(&keyload_request_link)?;
+        let preparsed = msg.parse_header()?;
+        ensure!(preparsed.check_content_type(message::keyload_request::TYPE));
+        let ntru_pkid = author.unwrap_keyload_request(preparsed)?;
+        ensure!(subscriberB.ntru_public_key().unwrap().cmp_pkid(&ntru_pkid));
+        author.set_ntru_role(subscriberB.ntru_public_key().unwrap().get_pkid(), Role::ReadWrite);
+        let msg = author.share_keyload(&announcement_link, &Vec::new(), &vec![ntru_pkid])?;
+        println!("  {}", msg);
+        transport.send_message(&msg)?;
+        msg.link
+    };
+
+    {
+        let msg = transport.recv_message(&requested_keyload_link)?;
+        let preparsed = msg.parse_header()?;
+        subscriberB.unwrap_keyload(preparsed)?;
+        ensure!(subscriberB.role() == Some(Role::ReadWrite));
+
+        // Roles are kept per branch, the read-only branch stays read-only.
+        ensure!(subscriberB.branch_role(&read_only_keyload_link) == Some(Role::ReadOnly));
+        ensure!(subscriberB.branch_role(&requested_keyload_link) == Some(Role::ReadWrite));
+        ensure!(subscriberB.tag_packet(&read_only_keyload_link, &public_payload, &masked_payload).is_err());
+        let msg = author.tag_packet(&read_only_keyload_link, &public_payload, &masked_payload)?;
+        subscriberB.unwrap_tagged_packet(msg.parse_header()?)?;
+        ensure!(subscriberB.branch_role(&msg.link) == Some(Role::ReadOnly));
+        ensure!(subscriberB.tag_packet(&msg.link, &public_payload, &masked_payload).is_err());
+        let msg = subscriberB.tag_packet(&requested_keyload_link, &public_payload, &masked_payload)?;
+        ensure!(subscriberB.branch_role(&msg.link) == Some(Role::ReadWrite));
+    }
+
+    {
+        let msgs = subscriberB.fetch_prev_msgs(transport, &read_only_keyload_link, 3)?;
+        ensure!(msgs.len() == 1);
+        ensure!(msgs[0].link == announcement_link);
+        let msgs = author.fetch_prev_msgs(transport, &tagged_packet_link, 1)?;
+        ensure!(msgs.len() == 1);
+        ensure!(msgs[0].link == keyload_link);
+        let msgs = author.fetch_prev_msgs(transport, &tagged_packet_link, 5)?;
+        ensure!(msgs.len() == 2);
+        ensure!(msgs[1].link == announcement_link);
+    }
+
     println!("change key");
     let change_key_link = {
         let msg = author.change_key(&announcement_link)?;
@@ -173,3 +350,3675 @@ fn run_basic_scenario() {
     let mut transport = BucketTransport::new();
     assert!(dbg!(example(&mut transport)).is_ok());
 }
+
+#[test]
+fn run_channel_index() {
+    let addresses = Author::channel_addresses("AUTHOR9SEED", 1, 3);
+    assert_eq!(addresses.len(), 3);
+    let builder = AuthorBuilder::new("AUTHOR9SEED").mss_height(1).ntru(false);
+    assert!(&addresses[0] == builder.build().channel_address());
+    assert!(&addresses[2] == builder.channel_index(2).build().channel_address());
+    assert!(addresses[0] != addresses[1] && addresses[1] != addresses[2] && addresses[0] != addresses[2]);
+}
+
+#[test]
+fn run_msgid_derivation() {
+    let mut author = AuthorBuilder::new("AUTHOR9SEED")
+        .mss_height(1)
+        .msgid_derivation(MsgIdDerivation::Troika)
+        .build();
+    let announcement = author.announce().unwrap();
+    let default_announcement = AuthorBuilder::new("AUTHOR9SEED")
+        .mss_height(1)
+        .ntru(false)
+        .build()
+        .announce()
+        .unwrap();
+    assert!(announcement.link.appinst == default_announcement.link.appinst);
+
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+    subscriber
+        .unwrap_announcement(default_announcement.parse_header().unwrap())
+        .unwrap();
+    assert_eq!(subscriber.msgid_derivation(), MsgIdDerivation::ChannelPrp);
+    subscriber.unregister();
+    subscriber
+        .unwrap_announcement(announcement.parse_header().unwrap())
+        .unwrap();
+    assert_eq!(subscriber.msgid_derivation(), MsgIdDerivation::Troika);
+    let subscribe_link = subscriber.subscribe(&announcement.link).unwrap().link;
+
+    let mut link_gen = LinkGen::default();
+    link_gen.reset_appinst(announcement.link.appinst.clone());
+    link_gen.set_msgid_derivation(MsgIdDerivation::Troika);
+    assert!(subscribe_link == link_gen.link_from(&announcement.link.msgid));
+}
+
+fn handover<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").mss_height(1).ntru(false).build();
+    let mut successor = SubscriberBuilder::new("SUCCESSOR9SEED").ntru(false).build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").ntru(false).build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&announcement_link)?;
+        successor.unwrap_announcement(msg.parse_header()?)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+    }
+
+    let (successor_mss_pk, successor_sig) = successor.accept_handover(1)?;
+    let handover_link = {
+        let msg = author.handover(&announcement_link, &successor_mss_pk, &successor_sig)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&handover_link)?;
+        let preparsed = msg.parse_header()?;
+        ensure!(preparsed.check_content_type(message::handover::TYPE));
+        subscriber.unwrap_handover(preparsed)?;
+        successor.unwrap_handover(msg.parse_header()?)?;
+    }
+    ensure!(subscriber.author_mss_public_key().as_ref() == Some(&successor_mss_pk));
+
+    let public_payload = Trytes(Tbits::from_str("PUBLICPAYLOAD").unwrap());
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    let mut new_author = successor.into_author()?;
+    ensure!(new_author.channel_address() == author.channel_address());
+    {
+        let msg = new_author.sign_packet(&handover_link, &public_payload, &masked_payload)?;
+        let (unwrapped_public, unwrapped_masked) = subscriber.unwrap_signed_packet(msg.parse_header()?)?;
+        ensure!(public_payload == unwrapped_public);
+        ensure!(masked_payload == unwrapped_masked);
+    }
+    {
+        let msg = author.sign_packet(&handover_link, &public_payload, &masked_payload)?;
+        ensure!(subscriber.unwrap_signed_packet(msg.parse_header()?).is_err());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn run_handover() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(handover(&mut transport)).is_ok());
+}
+
+fn embargoed_keyload<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").mss_height(1).build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let subscribe_link = {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        let msg = subscriber.subscribe(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&subscribe_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+    }
+
+    let reveal_key_id = Tbits::from_str("PRESSRELEASE999999999999999").unwrap();
+    let ntru_pkids = vec![subscriber.ntru_public_key().unwrap().get_pkid()];
+    let keyload_link = {
+        let msg = author.share_keyload_embargoed(&announcement_link, &Vec::new(), &ntru_pkids, &reveal_key_id)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let public_payload = Trytes(Tbits::from_str("PUBLICPAYLOAD").unwrap());
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    let tagged_packet_link = {
+        let msg = author.tag_packet(&keyload_link, &public_payload, &masked_payload)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+
+    {
+        let msg = transport.recv_message(&keyload_link)?;
+        let preparsed = msg.parse_header()?;
+        ensure!(preparsed.check_content_type(message::keyload::EMBARGOED_TYPE));
+        ensure!(subscriber.unwrap_keyload(preparsed).is_err());
+    }
+
+    let reveal_key_link = {
+        let msg = author.reveal_key(&announcement_link, &reveal_key_id)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&reveal_key_link)?;
+        let preparsed = msg.parse_header()?;
+        ensure!(preparsed.check_content_type(message::reveal_key::TYPE));
+        ensure!(subscriber.unwrap_reveal_key(preparsed)? == reveal_key_id);
+    }
+
+    {
+        let msg = transport.recv_message(&keyload_link)?;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+        let msg = transport.recv_message(&tagged_packet_link)?;
+        let (unwrapped_public, unwrapped_masked) = subscriber.unwrap_tagged_packet(msg.parse_header()?)?;
+        ensure!(public_payload == unwrapped_public);
+        ensure!(masked_payload == unwrapped_masked);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn run_embargoed_keyload() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(embargoed_keyload(&mut transport)).is_ok());
+}
+
+fn header_ext<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").mss_height(1).build();
+    let mut subscriberA = SubscriberBuilder::new("SUBSCRIBERA9SEED").build();
+    let mut subscriberB = SubscriberBuilder::new("SUBSCRIBERB9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let subscribe_link = {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriberA.unwrap_announcement(msg.parse_header()?)?;
+        subscriberB.unwrap_announcement(msg.parse_header()?)?;
+        let msg = subscriberA.subscribe(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&subscribe_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+    }
+    let keyload_link = {
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let public_payload = Trytes(Tbits::from_str("PUBLICPAYLOAD").unwrap());
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    ensure!(author
+        .tag_packet_with_ext(&keyload_link, &public_payload, &masked_payload, "branch a", "SCHEMA9VA")
+        .is_err());
+    let tagged_packet_link = {
+        let msg = author.tag_packet_with_ext(&keyload_link, &public_payload, &masked_payload, "BRANCHA", "SCHEMA9VA")?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+
+    {
+        let msg = transport.recv_message(&keyload_link)?;
+        subscriberA.unwrap_keyload(msg.parse_header()?)?;
+        ensure!(subscriberB.unwrap_keyload(msg.parse_header()?).is_err());
+    }
+    {
+        let msg = transport.recv_message(&tagged_packet_link)?;
+        let preparsed = msg.parse_header()?;
+        ensure!(preparsed.header.version == STREAMS_5_VER);
+        let ext = subscriberA.unwrap_header_ext(&preparsed)?;
+        ensure!(ext.branch == Trytes(Tbits::from_str("BRANCHA").unwrap()));
+        ensure!(ext.schema == Trytes(Tbits::from_str("SCHEMA9VA").unwrap()));
+        let (unwrapped_public, unwrapped_masked) = subscriberA.unwrap_tagged_packet(preparsed)?;
+        ensure!(public_payload == unwrapped_public);
+        ensure!(masked_payload == unwrapped_masked);
+    }
+    {
+        // Non-recipient can't read the extension, but can still follow the sequence.
+        let msg = transport.recv_message(&tagged_packet_link)?;
+        let preparsed = msg.parse_header()?;
+        ensure!(subscriberB.unwrap_header_ext(&preparsed).is_err());
+        ensure!(&preparsed.peek_link()? == keyload_link.rel());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn run_header_ext() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(header_ext(&mut transport)).is_ok());
+}
+
+fn lightweight_subscriber<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").mss_height(2).build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build_lightweight();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let subscribe_link = {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        let msg = subscriber.subscribe(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&subscribe_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+    }
+
+    let public_payload = Trytes(Tbits::from_str("PUBLICPAYLOAD").unwrap());
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    let keyload_link1 = {
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let tagged_packet_link1 = {
+        let msg = author.tag_packet(&keyload_link1, &public_payload, &masked_payload)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let keyload_link2 = {
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let tagged_packet_link2 = {
+        let msg = author.tag_packet(&keyload_link2, &public_payload, &masked_payload)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+
+    {
+        // Messages handled in order can be unwrapped.
+        let msg = transport.recv_message(&keyload_link1)?;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+        let msg = transport.recv_message(&tagged_packet_link1)?;
+        let (unwrapped_public, unwrapped_masked) = subscriber.unwrap_tagged_packet(msg.parse_header()?)?;
+        ensure!(public_payload == unwrapped_public);
+        ensure!(masked_payload == unwrapped_masked);
+
+        let msg = transport.recv_message(&keyload_link2)?;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+        let msg = transport.recv_message(&tagged_packet_link2)?;
+        subscriber.unwrap_tagged_packet(msg.parse_header()?)?;
+    }
+    {
+        // The first keyload has been evicted.
+        let msg = transport.recv_message(&tagged_packet_link1)?;
+        ensure!(subscriber.unwrap_tagged_packet(msg.parse_header()?).is_err());
+        // Announce is still kept.
+        let msg = author.sign_packet(&announcement_link, &public_payload, &masked_payload)?;
+        subscriber.unwrap_signed_packet(msg.parse_header()?)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn run_lightweight_subscriber() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(lightweight_subscriber(&mut transport)).is_ok());
+}
+
+fn keyload_epochs<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").mss_height(2).keyload_epochs(1).build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let subscribe_link = {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        let msg = subscriber.subscribe(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&subscribe_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+    }
+
+    let public_payload = Trytes(Tbits::from_str("PUBLICPAYLOAD").unwrap());
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    let keyload_link1 = {
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let tagged_packet_link1 = {
+        let msg = transport.recv_message(&keyload_link1)?;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+        let msg = subscriber.tag_packet(&keyload_link1, &public_payload, &masked_payload)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let keyload_link2 = {
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let tagged_packet_link2 = {
+        let msg = transport.recv_message(&keyload_link2)?;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+        let msg = subscriber.tag_packet(&keyload_link2, &public_payload, &masked_payload)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+
+    {
+        // The first keyload epoch has been forgotten by Author.
+        let msg = transport.recv_message(&tagged_packet_link1)?;
+        ensure!(author.unwrap_tagged_packet(msg.parse_header()?).is_err());
+        let msg = transport.recv_message(&tagged_packet_link2)?;
+        let (unwrapped_public, unwrapped_masked) = author.unwrap_tagged_packet(msg.parse_header()?)?;
+        ensure!(public_payload == unwrapped_public);
+        ensure!(masked_payload == unwrapped_masked);
+    }
+    {
+        // Announce is kept.
+        let msg = author.sign_packet(&announcement_link, &public_payload, &masked_payload)?;
+        subscriber.unwrap_signed_packet(msg.parse_header()?)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn run_keyload_epochs() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(keyload_epochs(&mut transport)).is_ok());
+}
+
+fn app_version<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED")
+        .mss_height(1)
+        .app_version(Trint3(2))
+        .app_version_check(|v| v.0 >= 2)
+        .build();
+    let mut subscriberA = SubscriberBuilder::new("SUBSCRIBERA9SEED")
+        .app_version(Trint3(2))
+        .app_version_check(|v| v.0 >= 2)
+        .build();
+    let mut subscriberB = SubscriberBuilder::new("SUBSCRIBERB9SEED")
+        .app_version_check(|v| v == Trint3(1))
+        .build();
+    let mut subscriberC = SubscriberBuilder::new("SUBSCRIBERC9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&announcement_link)?;
+        let preparsed = msg.parse_header()?;
+        ensure!(preparsed.header.version == STREAMS_5_VER);
+        ensure!(preparsed.header.app_version == Trint3(2));
+        subscriberA.unwrap_announcement(msg.parse_header()?)?;
+        ensure!(subscriberB.unwrap_announcement(msg.parse_header()?).is_err());
+        subscriberC.unwrap_announcement(msg.parse_header()?)?;
+    }
+    let subscribe_link = {
+        let msg = subscriberA.subscribe(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        // Messages without application version are rejected by Author.
+        let msg = subscriberC.subscribe(&announcement_link)?;
+        ensure!(msg.parse_header()?.header.app_version == Trint3(0));
+        ensure!(author.unwrap_subscribe(msg.parse_header()?).is_err());
+        let msg = transport.recv_message(&subscribe_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+    }
+    let keyload_link = {
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let public_payload = Trytes(Tbits::from_str("PUBLICPAYLOAD").unwrap());
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    let tagged_packet_link = {
+        let msg = author.tag_packet_with_ext(&keyload_link, &public_payload, &masked_payload, "BRANCHA", "SCHEMA9VA")?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+
+    {
+        let msg = transport.recv_message(&keyload_link)?;
+        subscriberA.unwrap_keyload(msg.parse_header()?)?;
+    }
+    {
+        // Application version and header extension go together.
+        let msg = transport.recv_message(&tagged_packet_link)?;
+        let preparsed = msg.parse_header()?;
+        ensure!(preparsed.header.version == STREAMS_5_VER);
+        let ext = subscriberA.unwrap_header_ext(&preparsed)?;
+        ensure!(ext.branch == Trytes(Tbits::from_str("BRANCHA").unwrap()));
+        let (unwrapped_public, unwrapped_masked) = subscriberA.unwrap_tagged_packet(preparsed)?;
+        ensure!(public_payload == unwrapped_public);
+        ensure!(masked_payload == unwrapped_masked);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn run_app_version() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(app_version(&mut transport)).is_ok());
+}
+
+fn key_commitment<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").mss_height(1).build();
+    let mut subscriberA = SubscriberBuilder::new("SUBSCRIBERA9SEED").ntru(false).build();
+    let mut subscriberB = SubscriberBuilder::new("SUBSCRIBERB9SEED").ntru(false).build();
+
+    let pskid = Tbits::cycle_str(81, "PSKID");
+    let psk = Tbits::cycle_str(243, "PSK");
+    let stale_psk = Tbits::cycle_str(243, "STALEPSK");
+    author.imp.psks.insert(pskid.clone(), psk.clone());
+    subscriberA.imp.opt_psk = Some((pskid.clone(), psk));
+    // Subscriber B missed PSK update.
+    subscriberB.imp.opt_psk = Some((pskid.clone(), stale_psk));
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriberA.unwrap_announcement(msg.parse_header()?)?;
+        subscriberB.unwrap_announcement(msg.parse_header()?)?;
+    }
+    let keyload_link = {
+        let msg = author.share_keyload(&announcement_link, &vec![pskid], &vec![])?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&keyload_link)?;
+        subscriberA.unwrap_keyload(msg.parse_header()?)?;
+        match subscriberB.unwrap_keyload(msg.parse_header()?) {
+            Ok(_) => bail!("Keyload unwrapped with stale PSK."),
+            Err(e) => ensure!(e.to_string().contains("key commitment"), "Unexpected error: {}", e),
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn run_key_commitment() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(key_commitment(&mut transport)).is_ok());
+}
+
+fn archive<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").mss_height(2).build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let subscribe_link = {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        let msg = subscriber.subscribe(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&subscribe_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+    }
+    let keyload_link = {
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let public_payload = Trytes(Tbits::from_str("PUBLICPAYLOAD").unwrap());
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    let signed_packet_link = {
+        let msg = author.sign_packet(&keyload_link, &public_payload, &masked_payload)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+
+    let bytes = author.archive(transport, &signed_packet_link, 10)?.to_bytes();
+    ensure!(read_archive(&bytes[..bytes.len() - 1]).is_err());
+
+    // Auditor holding the subscriber's keys replays the archive offline.
+    let archive = read_archive(&bytes)?;
+    ensure!(archive.entries().len() == 3);
+    ensure!(archive.entries()[2].prev == Some(1));
+    let mut auditor = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+    let mut archive_transport = archive.to_transport();
+    {
+        let msg = archive_transport.recv_message(&announcement_link)?;
+        auditor.unwrap_announcement(msg.parse_header()?)?;
+        let msg = archive_transport.recv_message(&keyload_link)?;
+        auditor.unwrap_keyload(msg.parse_header()?)?;
+        let msg = archive_transport.recv_message(&signed_packet_link)?;
+        let (unwrapped_public, unwrapped_masked) = auditor.unwrap_signed_packet(msg.parse_header()?)?;
+        ensure!(public_payload == unwrapped_public);
+        ensure!(masked_payload == unwrapped_masked);
+    }
+
+    // Messages must be ordered oldest first.
+    let mut msgs: Vec<_> = archive.entries().iter().map(|e| e.msg.clone()).collect();
+    msgs.reverse();
+    ensure!(StreamsArchive::from_msgs(msgs).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn run_archive() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(archive(&mut transport)).is_ok());
+}
+
+fn rate_policy<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED")
+        .mss_height(1)
+        .rate_policy(RatePolicy::new(2, Duration::from_secs(600)))
+        .build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let subscribe_link = {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        let msg = subscriber.subscribe(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&subscribe_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+    }
+    let keyload_link = {
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&keyload_link)?;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+    }
+
+    // Subscriber floods the branch with packets joined to the keyload.
+    let mut tagged_packet_links = Vec::new();
+    for payload in &["PACKETA", "PACKETB", "PACKETC"] {
+        let public_payload = Trytes(Tbits::from_str(payload).unwrap());
+        let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+        let msg = subscriber.tag_packet(&keyload_link, &public_payload, &masked_payload)?;
+        transport.send_message(&msg)?;
+        tagged_packet_links.push(msg.link);
+    }
+    for link in &tagged_packet_links {
+        let msg = transport.recv_message(link)?;
+        // Violating packets are unwrapped and flagged.
+        author.unwrap_tagged_packet(msg.parse_header()?)?;
+    }
+    ensure!(author.rate_violations() == &tagged_packet_links[2..]);
+
+    {
+        // Packets joined to another message are counted separately.
+        let public_payload = Trytes(Tbits::from_str("PACKETD").unwrap());
+        let msg = subscriber.tag_packet(&tagged_packet_links[0], &public_payload, &public_payload)?;
+        author.unwrap_tagged_packet(msg.parse_header()?)?;
+        ensure!(author.rate_violations().len() == 1);
+    }
+    author.clear_rate_violations();
+    ensure!(author.rate_violations().is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn run_rate_policy() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(rate_policy(&mut transport)).is_ok());
+}
+
+fn shared_author<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let author = SharedAuthor::new(AuthorBuilder::new("AUTHOR9SEED").mss_height(3).build());
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+
+    let announcement_link = {
+        let msg = author.lock()?.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+    }
+
+    let handlers: Vec<_> = ["PACKETA", "PACKETB", "PACKETC", "PACKETD"]
+        .iter()
+        .map(|payload| {
+            let author = author.clone();
+            let link = announcement_link.clone();
+            thread::spawn(move || {
+                let public_payload = Trytes(Tbits::from_str(payload).unwrap());
+                author.lock()?.sign_packet(&link, &public_payload, &Trytes(Tbits::zero(0)))
+            })
+        })
+        .collect();
+    let mut links = Vec::new();
+    for handler in handlers {
+        let msg = handler.join().unwrap()?;
+        transport.send_message(&msg)?;
+        links.push(msg.link);
+    }
+
+    for link in &links {
+        let msg = transport.recv_message(link)?;
+        subscriber.unwrap_signed_packet(msg.parse_header()?)?;
+    }
+    ensure!(author.try_unwrap().is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn run_shared_author() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(shared_author(&mut transport)).is_ok());
+}
+
+fn repair_sequencing<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").mss_height(3).build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let mut signed_packet_links = Vec::new();
+    let mut link_to = announcement_link.clone();
+    for payload in &["PACKETA", "PACKETB"] {
+        let public_payload = Trytes(Tbits::from_str(payload).unwrap());
+        let msg = author.sign_packet(&link_to, &public_payload, &Trytes(Tbits::zero(0)))?;
+        transport.send_message(&msg)?;
+        link_to = msg.link.clone();
+        signed_packet_links.push(msg.link);
+    }
+
+    // Author state restored from a backup taken right after the announcement.
+    let mut restored = AuthorBuilder::new("AUTHOR9SEED").mss_height(3).build();
+    ensure!(restored.announce()?.link == announcement_link);
+    ensure!(restored.repair_sequencing(transport, 2)? == signed_packet_links);
+    ensure!(restored.repair_sequencing(transport, 2)?.is_empty());
+
+    let public_payload = Trytes(Tbits::from_str("PACKETC").unwrap());
+    let msg = restored.sign_packet(&announcement_link, &public_payload, &Trytes(Tbits::zero(0)))?;
+    ensure!(!signed_packet_links.contains(&msg.link));
+    transport.send_message(&msg)?;
+
+    {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+    }
+    // Subscriber hasn't published anything.
+    ensure!(subscriber.repair_sequencing(transport, 2)?.is_empty());
+    let msg = transport.recv_message(&msg.link)?;
+    subscriber.unwrap_signed_packet(msg.parse_header()?)?;
+
+    Ok(())
+}
+
+#[test]
+fn run_repair_sequencing() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(repair_sequencing(&mut transport)).is_ok());
+}
+
+fn recipient_padding<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED")
+        .mss_height(1)
+        .recipient_padding(RecipientPadding::Bucket(2))
+        .build();
+    let mut subscriberA = SubscriberBuilder::new("SUBSCRIBERA9SEED").build();
+    let mut subscriberB = SubscriberBuilder::new("SUBSCRIBERB9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriberA.unwrap_announcement(msg.parse_header()?)?;
+        subscriberB.unwrap_announcement(msg.parse_header()?)?;
+    }
+
+    {
+        let msg = subscriberA.subscribe(&announcement_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+    }
+    let keyloadA = author.share_keyload_for_everyone(&announcement_link)?;
+    {
+        let msg = subscriberB.subscribe(&announcement_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+    }
+    let keyloadAB = author.share_keyload_for_everyone(&announcement_link)?;
+
+    // One and two recipients are padded to the same bucket.
+    ensure!(keyloadA.body.size() == keyloadAB.body.size());
+    subscriberA.unwrap_keyload(keyloadA.parse_header()?)?;
+    subscriberA.unwrap_keyload(keyloadAB.parse_header()?)?;
+    subscriberB.unwrap_keyload(keyloadAB.parse_header()?)?;
+    ensure!(subscriberB.unwrap_keyload(keyloadA.parse_header()?).is_err());
+
+    author.set_recipient_padding(RecipientPadding::Fixed(1));
+    ensure!(author.share_keyload_for_everyone(&announcement_link).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn run_recipient_padding() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(recipient_padding(&mut transport)).is_ok());
+}
+
+fn participants<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").mss_height(2).build();
+    let mut successor = SubscriberBuilder::new("SUCCESSOR9SEED").build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    for user in &mut [&mut successor, &mut subscriber] {
+        let msg = transport.recv_message(&announcement_link)?;
+        user.unwrap_announcement(msg.parse_header()?)?;
+        let msg = user.subscribe(&announcement_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+    }
+    let subscriber_pk = subscriber.ntru_public_key().unwrap().clone();
+    author.set_ntru_role(subscriber_pk.get_pkid(), Role::ReadOnly);
+
+    let keyload_link = {
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let participants_link = {
+        let msg = author.share_participants(&keyload_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&keyload_link)?;
+        successor.unwrap_keyload(msg.parse_header()?)?;
+        let msg = transport.recv_message(&participants_link)?;
+        let preparsed = msg.parse_header()?;
+        ensure!(preparsed.check_content_type(message::participants::TYPE));
+        successor.unwrap_participants(preparsed)?;
+    }
+    ensure!(successor.participants().len() == 2);
+    ensure!(successor
+        .participants()
+        .iter()
+        .any(|(ntru_pk, role)| *ntru_pk == subscriber_pk && *role == Role::ReadOnly));
+
+    // Successor Author bootstraps the list of subscribers from the snapshot.
+    let (successor_mss_pk, successor_sig) = successor.accept_handover(1)?;
+    let handover_link = {
+        let msg = author.handover(&announcement_link, &successor_mss_pk, &successor_sig)?;
+        subscriber.unwrap_handover(msg.parse_header()?)?;
+        successor.unwrap_handover(msg.parse_header()?)?;
+        msg.link
+    };
+    let mut new_author = successor.into_author()?;
+    {
+        let msg = new_author.share_keyload_for_everyone(&handover_link)?;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+    }
+    ensure!(subscriber.role() == Some(Role::ReadOnly));
+
+    Ok(())
+}
+
+#[test]
+fn run_participants() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(participants(&mut transport)).is_ok());
+}
+
+fn export_branch<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").mss_height(2).build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        let msg = subscriber.subscribe(&announcement_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+    }
+    let public_payload = Trytes(Tbits::from_str("PUBLICPAYLOAD").unwrap());
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    let branch_link = author.sign_packet(&announcement_link, &public_payload, &masked_payload)?.link;
+
+    let (mut branch_author, announcement, keyload) = author.export_branch(&branch_link)?;
+    ensure!(branch_author.channel_address() != author.channel_address());
+    ensure!(announcement.link.base() == branch_author.channel_address());
+
+    // Subscriber registers to the new channel with the same keys.
+    let mut branch_subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+    branch_subscriber.unwrap_announcement(announcement.parse_header()?)?;
+    branch_subscriber.unwrap_keyload(keyload.parse_header()?)?;
+    {
+        let msg = branch_author.tag_packet(&keyload.link, &public_payload, &masked_payload)?;
+        let (unwrapped_public, unwrapped_masked) = branch_subscriber.unwrap_tagged_packet(msg.parse_header()?)?;
+        ensure!(public_payload == unwrapped_public);
+        ensure!(masked_payload == unwrapped_masked);
+    }
+
+    ensure!(author.export_branch(&keyload.link).is_err());
+
+    // Channels split with keyload PRNG set don't share session keys.
+    author.set_keyload_seed(Some("KEYLOAD9SEED"));
+    let (mut branch_author, announcement, _) = author.export_branch(&branch_link)?;
+    let keys: Vec<_> = (0..2)
+        .map(|_| author.imp.prepare_keyload_for_everyone(&announcement_link.msgid).map(|p| p.content.key.clone()))
+        .collect::<Fallible<_>>()?;
+    let branch_key = branch_author.imp.prepare_keyload_for_everyone(&announcement.link.msgid)?.content.key.clone();
+    ensure!(!keys.contains(&branch_key));
+    Ok(())
+}
+
+#[test]
+fn run_export_branch() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(export_branch(&mut transport)).is_ok());
+}
+
+fn heap_size<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").mss_height(2).build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+    let author_size = author.heap_size();
+    let subscriber_size = subscriber.heap_size();
+    ensure!(0 < author_size && 0 < subscriber_size);
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        let msg = subscriber.subscribe(&announcement_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+    }
+
+    // Subscriber's NTRU key and links are accounted.
+    ensure!(author_size < author.heap_size());
+    ensure!(subscriber_size < subscriber.heap_size());
+    Ok(())
+}
+
+#[test]
+fn run_heap_size() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(heap_size(&mut transport)).is_ok());
+}
+
+fn mirror<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut authorA = AuthorBuilder::new("AUTHORA9SEED").mss_height(2).build();
+    let mut authorB = AuthorBuilder::new("AUTHORB9SEED").mss_height(2).build();
+    let mut subscriberA = SubscriberBuilder::new("SUBSCRIBERA9SEED").build();
+    let mut subscriberB = SubscriberBuilder::new("SUBSCRIBERB9SEED").build();
+
+    let announcementA = authorA.announce()?;
+    subscriberA.unwrap_announcement(announcementA.parse_header()?)?;
+    let announcementB = authorB.announce()?;
+    subscriberB.unwrap_announcement(announcementB.parse_header()?)?;
+
+    let public_payload = Trytes(Tbits::from_str("PUBLICPAYLOAD").unwrap());
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    let mut mirror = Mirror::new()
+        .with(&mut authorA, announcementA.link.clone())
+        .with(&mut authorB, announcementB.link.clone());
+    ensure!(mirror.len() == 2);
+
+    // Packets are chained in each mirror.
+    for _ in 0..2 {
+        let msgs = mirror.sign_packet(&public_payload, &masked_payload)?;
+        ensure!(msgs.len() == 2);
+        ensure!(mirror.links().eq(msgs.iter().map(|msg| &msg.link)));
+        for msg in &msgs {
+            transport.send_message(msg)?;
+        }
+        for (subscriber, msg) in [&mut subscriberA, &mut subscriberB].iter_mut().zip(msgs.iter()) {
+            let msg = transport.recv_message(&msg.link)?;
+            let (unwrapped_public, unwrapped_masked) = subscriber.unwrap_signed_packet(msg.parse_header()?)?;
+            ensure!(public_payload == unwrapped_public);
+            ensure!(masked_payload == unwrapped_masked);
+        }
+    }
+
+    // Author B can't link to a message of channel A, no link is advanced.
+    let linkA = mirror.links().next().unwrap().clone();
+    let mut mirror = Mirror::new()
+        .with(&mut authorA, linkA.clone())
+        .with(&mut authorB, linkA.clone());
+    ensure!(mirror.sign_packet(&public_payload, &masked_payload).is_err());
+    ensure!(mirror.links().all(|link| *link == linkA));
+    Ok(())
+}
+
+#[test]
+fn run_mirror() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(mirror(&mut transport)).is_ok());
+}
+
+fn announcement_bundle<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").mss_height(2).build();
+    let psk = Tbits::cycle_str(243, "PSK");
+    let pskid = author.add_psk(psk.clone());
+    ensure!(author.psk_ids() == vec![pskid.clone()]);
+    ensure!(author.insert_psk(Tbits::cycle_str(81, "PSKID"), psk.clone()).is_err());
+    ensure!(author.psk_ids().len() == 1);
+
+    let announcement = author.announce()?;
+    transport.send_message(&announcement)?;
+    let announcement_link = announcement.link.clone();
+    let keyload_link = {
+        let msg = author.share_keyload(&announcement_link, &vec![pskid.clone()], &vec![])?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+
+    let bundle = AnnouncementBundle::new(&announcement, Some(pskid.clone()))?;
+    let text = bundle.to_base45();
+    ensure!(text.len() == 128);
+    let bundle = AnnouncementBundle::from_base45(&text)?;
+    ensure!(bundle.link == announcement_link);
+
+    let builder = SubscriberBuilder::new("SUBSCRIBER9SEED").ntru(false);
+    ensure!(Subscriber::from_bundle(&builder, &bundle, transport).is_err());
+    let mut subscriber = Subscriber::from_bundle(&builder.psk(psk), &bundle, transport)?;
+    {
+        let msg = transport.recv_message(&keyload_link)?;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+    }
+
+    let bundle = AnnouncementBundle::new(&announcement, None)?;
+    ensure!(bundle.to_base45().len() == 102);
+    ensure!(AnnouncementBundle::from_bytes(&bundle.to_bytes())? == bundle);
+    Ok(())
+}
+
+#[test]
+fn run_announcement_bundle() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(announcement_bundle(&mut transport)).is_ok());
+}
+
+fn structured_packet<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").mss_height(2).build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+    let psk = Tbits::cycle_str(243, "AUDITORPSK");
+    let pskid = author.add_psk(psk.clone());
+    let mut auditor = SubscriberBuilder::new("AUDITOR9SEED").ntru(false).psk(psk).build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        auditor.unwrap_announcement(msg.parse_header()?)?;
+        msg.link
+    };
+    {
+        let msg = subscriber.subscribe(&announcement_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+    }
+    let keyload_link = {
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    for user in &mut [&mut subscriber, &mut auditor] {
+        let msg = transport.recv_message(&keyload_link)?;
+        user.unwrap_keyload(msg.parse_header()?)?;
+    }
+
+    let trytes = |s: &str| Trytes(Tbits::from_str(s).unwrap());
+    let fields = vec![
+        Field::new(trytes("DEVICE"), Audience::Public, trytes("SENSOR9A")),
+        Field::new(trytes("READING"), Audience::Subscribers, trytes("TWENTYONE")),
+        Field::new(trytes("CALIBRATION"), Audience::Psk(pskid.clone()), trytes("OFFSET9THREE")),
+    ];
+    let packet_link = {
+        let msg = author.sign_structured_packet(&keyload_link, &fields)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+
+    let msg = transport.recv_message(&packet_link)?;
+    let unwrapped = subscriber.unwrap_structured_packet(msg.parse_header()?)?;
+    ensure!(unwrapped.len() == 3);
+    ensure!(unwrapped[0].value == fields[0].value && unwrapped[1].value == fields[1].value);
+    ensure!(unwrapped[2].audience == fields[2].audience && unwrapped[2].value.is_none());
+
+    let unwrapped = auditor.unwrap_structured_packet(msg.parse_header()?)?;
+    ensure!(unwrapped.iter().zip(fields.iter()).all(|(u, f)| u.name == f.name && u.value == f.value));
+
+    // Fields of unknown pre-shared keys can't be published.
+    let unknown = vec![Field::new(trytes("X"), Audience::Psk(Tbits::cycle_str(81, "UNKNOWN")), trytes("X"))];
+    ensure!(author.sign_structured_packet(&keyload_link, &unknown).is_err());
+    Ok(())
+}
+
+#[test]
+fn run_structured_packet() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(structured_packet(&mut transport)).is_ok());
+}
+
+fn lite_packet<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").lite_mac(LiteMac::Bytes8).build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        msg.link
+    };
+    {
+        let msg = subscriber.subscribe(&announcement_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+    }
+    let keyload_link = {
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+        msg.link
+    };
+
+    let payload = Trytes(Tbits::from_str("TEMPERATURE9TWENTYONE").unwrap());
+    let lite_link = {
+        let msg = subscriber.lite_packet(&keyload_link, &payload)?;
+        let tagged = subscriber.tag_packet(&keyload_link, &Trytes::default(), &payload)?;
+        ensure!(msg.body.size() < tagged.body.size());
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&lite_link)?;
+        ensure!(author.unwrap_lite_packet(msg.parse_header()?)? == payload);
+    }
+    {
+        let msg = author.lite_packet(&lite_link, &payload)?;
+        ensure!(subscriber.unwrap_lite_packet(msg.parse_header()?)? == payload);
+    }
+
+    // Lite packets are not allowed unless announced.
+    let mut other = AuthorBuilder::new("OTHER9AUTHOR9SEED").build();
+    let other_announcement_link = other.announce()?.link;
+    ensure!(other.lite_packet(&other_announcement_link, &payload).is_err());
+    Ok(())
+}
+
+#[test]
+fn run_lite_packet() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(lite_packet(&mut transport)).is_ok());
+}
+
+fn heartbeat<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        msg.link
+    };
+    ensure!(subscriber.last_heartbeat().is_none());
+    ensure!(!subscriber.author_is_live(Duration::from_secs(60)));
+
+    let first_link = {
+        let msg = author.heartbeat(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let second_link = {
+        let msg = author.heartbeat(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&second_link)?;
+        let heartbeat = subscriber.unwrap_heartbeat(msg.parse_header()?)?;
+        ensure!(heartbeat.counter == 2);
+        ensure!(subscriber.last_heartbeat() == Some(heartbeat));
+        ensure!(subscriber.author_is_live(Duration::from_secs(60)));
+    }
+
+    // Older heartbeats are rejected.
+    {
+        let msg = transport.recv_message(&first_link)?;
+        ensure!(subscriber.unwrap_heartbeat(msg.parse_header()?).is_err());
+        ensure!(subscriber.last_heartbeat().map(|heartbeat| heartbeat.counter) == Some(2));
+    }
+
+    // Author is not live if the last heartbeat is outside the window.
+    {
+        let msg = author.imp.heartbeat(announcement_link.rel(), 0, MsgInfo::Heartbeat)?;
+        subscriber.unwrap_heartbeat(msg.parse_header()?)?;
+        ensure!(!subscriber.author_is_live(Duration::from_secs(60)));
+    }
+    Ok(())
+}
+
+#[test]
+fn run_heartbeat() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(heartbeat(&mut transport)).is_ok());
+}
+
+fn close_branch<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+    let payload = Trytes(Tbits::from_str("PAYLOAD").unwrap());
+
+    let announcement = author.announce()?;
+    transport.send_message(&announcement)?;
+    subscriber.unwrap_announcement(announcement.parse_header()?)?;
+    let subscribe = subscriber.subscribe(&announcement.link)?;
+    author.unwrap_subscribe(subscribe.parse_header()?)?;
+
+    let keyload = author.share_keyload_for_everyone(&announcement.link)?;
+    transport.send_message(&keyload)?;
+    for msg in subscriber.messages(transport, 5) {
+        msg?;
+    }
+    subscriber.follow_branch(&keyload.link)?;
+
+    let packet = author.sign_packet(&keyload.link, &payload, &payload)?;
+    transport.send_message(&packet)?;
+    let marker = author.close_branch(&packet.link)?;
+    transport.send_message(&marker)?;
+    ensure!(author.is_branch_closed(&keyload.link));
+    ensure!(author.sign_packet(&keyload.link, &payload, &payload).is_err());
+    ensure!(author.tag_packet(&marker.link, &payload, &payload).is_err());
+    ensure!(author.close_branch(&packet.link).is_err());
+
+    // Other branches are open.
+    let next_keyload = author.share_keyload_for_everyone(&announcement.link)?;
+    ensure!(!author.is_branch_closed(&next_keyload.link));
+    author.sign_packet(&next_keyload.link, &payload, &payload)?;
+
+    let mut received = Vec::new();
+    for msg in subscriber.messages(transport, 5) {
+        match msg? {
+            (_, Received::SignedPacket(public, _)) if public == payload => {}
+            (link, Received::BranchClosed(closed)) if link == marker.link => received.push(closed),
+            _ => bail!("Unexpected message."),
+        }
+    }
+    ensure!(received == vec![packet.link.clone()]);
+    ensure!(!subscriber.unfollow_branch(&keyload.link));
+
+    ensure!(author.prune_branch(&next_keyload.link).is_err());
+    ensure!(author.prune_branch(&packet.link)? == 3);
+    Ok(())
+}
+
+#[test]
+fn run_close_branch() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(close_branch(&mut transport)).is_ok());
+}
+
+fn message_tags<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+    let payload = Trytes(Tbits::from_str("PAYLOAD").unwrap());
+
+    let announcement = author.announce()?;
+    transport.send_message(&announcement)?;
+    subscriber.unwrap_announcement(transport.recv_message(&announcement.link)?.parse_header()?)?;
+
+    // Tag of the wrapped message is kept in the link store.
+    let wrapped = author
+        .imp
+        .prepare_signed_packet(announcement.link.rel(), &payload, &payload)?
+        .wrap()?;
+    let tag = wrapped.tag();
+    let packet = wrapped.commit(author.imp.store.borrow_mut(), MsgInfo::SignedPacket)?;
+    transport.send_message(&packet)?;
+    ensure!(author.message_tag(&packet.link)? == tag);
+
+    // Recipients get the same tag regardless of the link.
+    let msg = transport.recv_message(&packet.link)?;
+    let unwrapped = subscriber.imp.unwrap_signed_packet(msg.parse_header()?)?;
+    ensure!(unwrapped.tag() == tag);
+    unwrapped.commit(subscriber.imp.store.borrow_mut(), MsgInfo::SignedPacket)?;
+    ensure!(subscriber.message_tag(&packet.link)? == tag);
+
+    ensure!(author.message_tag(&announcement.link)? != tag);
+    let other = author.sign_packet(&announcement.link, &payload, &payload)?;
+    ensure!(author.message_tag(&other.link)? != tag);
+    ensure!(subscriber.message_tag(&other.link).is_err());
+    Ok(())
+}
+
+#[test]
+fn run_message_tags() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(message_tags(&mut transport)).is_ok());
+}
+
+fn max_size<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").max_size(20).build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        msg.link
+    };
+
+    let small = Trytes(Tbits::from_str("SMALL9PAYLOAD").unwrap());
+    let large = Trytes(Tbits::from_str("PAYLOAD9LARGER9THAN9LIMIT").unwrap());
+    let small_link = {
+        let msg = author.sign_packet(&announcement_link, &small, &Trytes::default())?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let large_link = {
+        let msg = author.sign_packet(&announcement_link, &large, &Trytes::default())?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+
+    {
+        let msg = transport.recv_message(&small_link)?;
+        let (public_payload, _) = subscriber.unwrap_signed_packet(msg.parse_header()?)?;
+        ensure!(public_payload == small);
+    }
+    {
+        let msg = transport.recv_message(&large_link)?;
+        let err = match subscriber.unwrap_signed_packet(msg.parse_header()?) {
+            Ok(_) => bail!("Oversized payload accepted."),
+            Err(err) => err,
+        };
+        ensure!(err.downcast_ref::<SizeLimitExceeded>() == Some(&SizeLimitExceeded { size: 25, max_size: 20 }));
+    }
+
+    // The limit can be lifted.
+    subscriber.set_max_size(None);
+    {
+        let msg = transport.recv_message(&large_link)?;
+        let (public_payload, _) = subscriber.unwrap_signed_packet(msg.parse_header()?)?;
+        ensure!(public_payload == large);
+    }
+    Ok(())
+}
+
+#[test]
+fn run_max_size() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(max_size(&mut transport)).is_ok());
+}
+
+fn announcement_by_pk<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let mut other = AuthorBuilder::new("OTHER9AUTHOR9SEED")
+        .msgid_derivation(MsgIdDerivation::Troika)
+        .build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    ensure!(subscriber
+        .receive_announcement_by_pk(transport, author.channel_address(), 1)
+        .is_err());
+    ensure!(!subscriber.is_registered());
+    let link = subscriber.receive_announcement_by_pk(transport, author.channel_address(), 0)?;
+    ensure!(link == announcement_link);
+    ensure!(subscriber.channel_address() == Some(author.channel_address()));
+
+    // Announced after another message.
+    let other_announcement_link = {
+        let first_link = other.announce()?.link;
+        other.sign_packet(&first_link, &Trytes::default(), &Trytes::default())?;
+        let msg = other.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    subscriber.unregister();
+    let link = subscriber.receive_announcement_by_pk(transport, other.channel_address(), 2)?;
+    ensure!(link == other_announcement_link);
+    ensure!(subscriber.msgid_derivation() == MsgIdDerivation::Troika);
+    Ok(())
+}
+
+#[test]
+fn run_announcement_by_pk() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(announcement_by_pk(&mut transport)).is_ok());
+}
+
+fn inspect_header<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").app_version(Trint3(5)).build();
+    let announcement_link = author.announce()?.link;
+    let public_payload = Trytes(Tbits::from_str("PUBLICPAYLOAD").unwrap());
+    let link = {
+        let msg = author.sign_packet(&announcement_link, &public_payload, &Trytes::default())?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+
+    // No channel state is needed.
+    let msg = transport.recv_message(&link)?;
+    let info = inspect(&msg)?;
+    ensure!(info.link == link);
+    ensure!(info.version == STREAMS_5_VER);
+    ensure!(info.content_type == message::signed_packet::TYPE);
+    ensure!(info.app_version == Trint3(5));
+    ensure!(info.ext_size == 0);
+    ensure!(info.content_size > public_payload.0.size());
+    Ok(())
+}
+
+#[test]
+fn run_inspect_header() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(inspect_header(&mut transport)).is_ok());
+}
+
+fn psk_only<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").ntru(false).build();
+    let mut ntru_subscriber = SubscriberBuilder::new("NTRU9SUBSCRIBER9SEED").build();
+
+    let pskid = Tbits::cycle_str(81, "PSKID");
+    let psk = Tbits::cycle_str(243, "PSK");
+    author.imp.psks.insert(pskid.clone(), psk.clone());
+    subscriber.imp.opt_psk = Some((pskid, psk));
+
+    let announcement_link = {
+        let msg = author.announce_psk_only()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        ntru_subscriber.unwrap_announcement(msg.parse_header()?)?;
+        ensure!(ntru_subscriber.author_ntru_public_key().is_none());
+    }
+    ensure!(ntru_subscriber.subscribe(&announcement_link).is_err());
+    let ntru_pkid = ntru_subscriber.ntru_public_key().unwrap().get_pkid();
+    ensure!(author
+        .share_keyload(&announcement_link, &vec![], &vec![ntru_pkid])
+        .is_err());
+
+    let keyload_link = {
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&keyload_link)?;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+    }
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    {
+        let msg = author.tag_packet(&keyload_link, &Trytes::default(), &masked_payload)?;
+        let (_, unwrapped_masked) = subscriber.unwrap_tagged_packet(msg.parse_header()?)?;
+        ensure!(unwrapped_masked == masked_payload);
+    }
+    Ok(())
+}
+
+#[test]
+fn run_psk_only() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(psk_only(&mut transport)).is_ok());
+}
+
+fn session_keys<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").ntru(false).build();
+
+    let pskid = Tbits::cycle_str(81, "PSKID");
+    let psk = Tbits::cycle_str(243, "PSK");
+    author.imp.psks.insert(pskid.clone(), psk.clone());
+    subscriber.imp.opt_psk = Some((pskid, psk));
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+    }
+
+    let keyload_link = {
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&keyload_link)?;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+        ensure!(subscriber.session_keys() == vec![keyload_link.clone()]);
+
+        // Cached session key is used even when the pre-shared key is gone.
+        subscriber.imp.opt_psk = None;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+    }
+
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    let msg = author.tag_packet(&keyload_link, &Trytes::default(), &masked_payload)?;
+    {
+        let (_, unwrapped_masked) = subscriber.unwrap_tagged_packet(msg.parse_header()?)?;
+        ensure!(unwrapped_masked == masked_payload);
+    }
+
+    ensure!(subscriber.forget_session_key(&keyload_link));
+    ensure!(!subscriber.forget_session_key(&keyload_link));
+    ensure!(subscriber.session_keys().is_empty());
+    ensure!(subscriber.unwrap_tagged_packet(msg.parse_header()?).is_err());
+    Ok(())
+}
+
+#[test]
+fn run_session_keys() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(session_keys(&mut transport)).is_ok());
+}
+
+fn framed_packet<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        msg.link
+    };
+    {
+        let msg = subscriber.subscribe(&announcement_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+    }
+    let keyload_link = {
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+        msg.link
+    };
+
+    let public_payload = Trytes(Tbits::from_str("PUBLICPAYLOAD").unwrap());
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOADSPLITINTOFRAMES").unwrap());
+    ensure!(author
+        .frame_packet(&keyload_link, &public_payload, &masked_payload, 0)
+        .is_err());
+    let mut msg = author.frame_packet(&keyload_link, &public_payload, &masked_payload, 9)?;
+    transport.send_message(&msg)?;
+    {
+        let msg = transport.recv_message(&msg.link)?;
+        let (unwrapped_public, unwrapped_masked) = subscriber.unwrap_framed_packet(msg.parse_header()?)?;
+        ensure!(unwrapped_public == public_payload);
+        ensure!(unwrapped_masked == masked_payload);
+    }
+
+    // Corrupt the last trit of the last frame (1 tryte), it's followed by the frame tag and the MAC.
+    let pos = msg.body.size() - 243 - FRAME_TAG_SIZE - 1;
+    let t = msg.body.slice().drop(pos).take(1).get_isize();
+    msg.body.slice_mut().drop(pos).take(1).put_isize(if t == 1 { -1 } else { t + 1 });
+    match subscriber.unwrap_framed_packet(msg.parse_header()?) {
+        Err(err) => ensure!(err.downcast_ref::<FrameCorrupted>() == Some(&FrameCorrupted { index: 3 })),
+        Ok(_) => bail!("Corrupted frame is not detected."),
+    }
+    Ok(())
+}
+
+#[test]
+fn run_framed_packet() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(framed_packet(&mut transport)).is_ok());
+}
+
+fn file_store<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let path = std::env::temp_dir().join(format!("streams-file-store-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let builder = SubscriberBuilder::new("SUBSCRIBER9SEED");
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let keyload_link = {
+        let mut subscriber = builder.build_with_store(FileStore::open(&path)?);
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        let msg = subscriber.subscribe(&announcement_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+        ensure!(subscriber.tag_link(&msg.link, "latest keyload").is_err());
+        subscriber.tag_link(&msg.link, "latest-keyload")?;
+        msg.link
+    };
+
+    // Keyload state and labels survive restart, the keyload is not handled again.
+    let mut store = FileStore::open(&path)?;
+    ensure!(store.links().count() == 3);
+    store.compact()?;
+    let mut subscriber = builder.build_with_store(store);
+    {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+    }
+    ensure!(subscriber.find_by_label("latest-keyload") == Some(keyload_link.clone()));
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    {
+        let msg = author.tag_packet(&keyload_link, &Trytes::default(), &masked_payload)?;
+        let (_, unwrapped_masked) = subscriber.unwrap_tagged_packet(msg.parse_header()?)?;
+        ensure!(unwrapped_masked == masked_payload);
+    }
+    ensure!(FileStore::open(&path)?.links().count() == 4);
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn run_file_store() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(file_store(&mut transport)).is_ok());
+}
+
+fn sealed_file_store<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let path = std::env::temp_dir().join(format!("streams-sealed-file-store-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let key = prng::random_key(Spongos::<DefaultTW, DefaultF>::KEY_SIZE);
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let builder = SubscriberBuilder::new("SUBSCRIBER9SEED");
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let (keyload_link, keyload_state) = {
+        let mut subscriber = builder.build_with_store(FileStore::open_sealed(&path, key.clone())?);
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        let msg = subscriber.subscribe(&announcement_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+        subscriber.tag_link(&msg.link, "keyload")?;
+        subscriber.tag_link(&msg.link, "latest-keyload")?;
+        subscriber.tag_link(&announcement_link, "latest-keyload")?;
+        let (spongos, _) = subscriber.imp.store.borrow().lookup(&msg.link.msgid)?;
+        (msg.link, spongos.to_inner().as_ref().to_string())
+    };
+
+    // Spongos states are not written in plaintext.
+    let journal = std::fs::read_to_string(&path)?;
+    ensure!(!journal.contains(&keyload_state), "Keyload spongos state is not sealed.");
+    ensure!(FileStore::open(&path).is_err());
+    let other_key = prng::random_key(Spongos::<DefaultTW, DefaultF>::KEY_SIZE);
+    ensure!(FileStore::open_sealed(&path, other_key).is_err());
+
+    // Reload drops the overwritten label record.
+    let store = FileStore::open_sealed(&path, key)?;
+    ensure!(store.links().count() == 3);
+    ensure!(std::fs::read_to_string(&path)?.lines().count() == journal.lines().count() - 1);
+    let mut subscriber = builder.build_with_store(store);
+    {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+    }
+    ensure!(subscriber.find_by_label("keyload") == Some(keyload_link.clone()));
+    ensure!(subscriber.find_by_label("latest-keyload") == Some(announcement_link.clone()));
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    {
+        let msg = author.tag_packet(&keyload_link, &Trytes::default(), &masked_payload)?;
+        let (_, unwrapped_masked) = subscriber.unwrap_tagged_packet(msg.parse_header()?)?;
+        ensure!(unwrapped_masked == masked_payload);
+    }
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn run_sealed_file_store() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(sealed_file_store(&mut transport)).is_ok());
+}
+
+fn encrypted_store<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let path = std::env::temp_dir().join(format!("streams-encrypted-store-{}", std::process::id()));
+    let key = prng::random_key(Spongos::<DefaultTW, DefaultF>::KEY_SIZE);
+    let nonce = prng::random_nonce(Spongos::<DefaultTW, DefaultF>::NONCE_SIZE);
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build_with_store(EncryptedStore::new(key.clone(), nonce)?);
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let keyload_link = {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        let msg = subscriber.subscribe(&announcement_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+        author.tag_link(&msg.link, "latest-keyload")?;
+        msg.link
+    };
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    {
+        let msg = author.tag_packet(&keyload_link, &Trytes::default(), &masked_payload)?;
+        let (_, unwrapped_masked) = subscriber.unwrap_tagged_packet(msg.parse_header()?)?;
+        ensure!(unwrapped_masked == masked_payload);
+    }
+
+    // Sealed states and labels are saved and loaded back with the key only.
+    save_encrypted_store(&*author.imp.store.borrow(), &path)?;
+    let store = load_encrypted_store(key, &path)?;
+    ensure!(store.links().count() == author.imp.store.borrow().links().count());
+    ensure!(store.find_by_label("latest-keyload") == Some(keyload_link.msgid.clone()));
+    ensure!(store.lookup(&keyload_link.msgid)?.1 == MsgInfo::Keyload);
+    let other_key = prng::random_key(Spongos::<DefaultTW, DefaultF>::KEY_SIZE);
+    ensure!(load_encrypted_store(other_key, &path).is_err());
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn run_encrypted_store() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(encrypted_store(&mut transport)).is_ok());
+}
+
+fn migrate_link_store<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let builder = SubscriberBuilder::new("SUBSCRIBER9SEED");
+    let mut subscriber = builder.build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        msg.link
+    };
+    let keyload_link = {
+        let msg = subscriber.subscribe(&announcement_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+        msg.link
+    };
+
+    // Plaintext store is migrated into a sealed one, the keyload state is kept.
+    let key = prng::random_key(Spongos::<DefaultTW, DefaultF>::KEY_SIZE);
+    let nonce = prng::random_nonce(Spongos::<DefaultTW, DefaultF>::NONCE_SIZE);
+    let mut store = EncryptedStore::new(key, nonce)?;
+    {
+        let from = subscriber.imp.store.borrow();
+        let links: Vec<_> = from.links().cloned().collect();
+        migrate_links(&links, &*from, &mut store)?;
+    }
+    ensure!(store.links().count() == 3);
+    let mut migrated = builder.build_with_store(store);
+    {
+        let msg = transport.recv_message(&announcement_link)?;
+        migrated.unwrap_announcement(msg.parse_header()?)?;
+    }
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    {
+        let msg = author.tag_packet(&keyload_link, &Trytes::default(), &masked_payload)?;
+        let (_, unwrapped_masked) = migrated.unwrap_tagged_packet(msg.parse_header()?)?;
+        ensure!(unwrapped_masked == masked_payload);
+    }
+    Ok(())
+}
+
+#[test]
+fn run_migrate_link_store() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(migrate_link_store(&mut transport)).is_ok());
+}
+
+/// Link store files written by previous versions: Subscriber's store with the announcement,
+/// a signed packet linked to it and the packet labeled "latest-packet".
+const FILE_STORE_FIXTURES: [&str; 2] = [
+    include_str!("fixtures/file_store_v0.txt"),
+    include_str!("fixtures/file_store_v1.txt"),
+];
+
+fn file_store_versions<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let path = std::env::temp_dir().join(format!("streams-file-store-versions-{}", std::process::id()));
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let announcement = author.announce()?;
+    let public_payload = Trytes(Tbits::from_str("PUBLIC").unwrap());
+    let masked_payload = Trytes(Tbits::from_str("MASKED").unwrap());
+    let packet = author.sign_packet(&announcement.link, &public_payload, &masked_payload)?;
+    let next_packet = author.sign_packet(&packet.link, &public_payload, &masked_payload)?;
+    transport.send_message(&next_packet)?;
+
+    for fixture in FILE_STORE_FIXTURES.iter() {
+        std::fs::write(&path, fixture)?;
+        let store = FileStore::open(&path)?;
+        // Older files are migrated to the current version.
+        ensure!(std::fs::read_to_string(&path)? == FILE_STORE_FIXTURES[1]);
+        ensure!(store.links().count() == 2);
+        ensure!(store.find_by_label("latest-packet") == Some(packet.link.msgid.clone()));
+
+        // The packet state is loaded, the next packet can be unwrapped.
+        let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build_with_store(store);
+        subscriber.unwrap_announcement(announcement.parse_header()?)?;
+        let msg = transport.recv_message(&next_packet.link)?;
+        let (_, unwrapped_masked) = subscriber.unwrap_signed_packet(msg.parse_header()?)?;
+        ensure!(unwrapped_masked == masked_payload);
+    }
+
+    // Files of newer versions are rejected.
+    std::fs::write(&path, "#streams-link-store 2\n")?;
+    ensure!(FileStore::open(&path).is_err());
+    std::fs::write(&path, "#streams-link-store X\n")?;
+    ensure!(FileStore::open(&path).is_err());
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn run_file_store_versions() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(file_store_versions(&mut transport)).is_ok());
+}
+
+fn follow_branch<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+    let mut send = |msg: Message| -> Fallible<Address> {
+        transport.send_message(&msg)?;
+        Ok(msg.link)
+    };
+
+    let announcement_link = send(author.announce()?)?;
+    let branch_a = send(author.share_keyload_for_everyone(&announcement_link)?)?;
+    let branch_b = send(author.share_keyload_for_everyone(&announcement_link)?)?;
+    let payload = Trytes(Tbits::from_str("PAYLOAD").unwrap());
+    let a1 = send(author.tag_packet(&branch_a, &payload, &payload)?)?;
+    send(author.tag_packet(&branch_b, &payload, &payload)?)?;
+    send(author.tag_packet(&branch_b, &payload, &payload)?)?;
+    let a2 = send(author.tag_packet(&a1, &payload, &payload)?)?;
+
+    {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+    }
+    subscriber.follow_branch(&branch_a)?;
+    let links: Vec<_> = subscriber
+        .fetch_next_msgs(transport, 5)?
+        .into_iter()
+        .map(|msg| msg.link)
+        .collect();
+    ensure!(links == vec![a1, a2.clone()]);
+
+    // Fetching resumes after the last fetched message.
+    transport.send_message(&author.tag_packet(&branch_b, &payload, &payload)?)?;
+    let a3 = author.tag_packet(&a2, &payload, &payload)?;
+    transport.send_message(&a3)?;
+    let msgs = subscriber.fetch_next_msgs(transport, 5)?;
+    ensure!(msgs.len() == 1 && msgs[0].link == a3.link);
+
+    ensure!(subscriber.unfollow_branch(&branch_a));
+    ensure!(!subscriber.unfollow_branch(&branch_a));
+    ensure!(!subscriber.unfollow_branch(&branch_b));
+    Ok(())
+}
+
+#[test]
+fn run_follow_branch() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(follow_branch(&mut transport)).is_ok());
+}
+
+fn channel_report<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+    let payload = Trytes(Tbits::from_str("PAYLOAD").unwrap());
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        msg.link
+    };
+    {
+        let msg = subscriber.subscribe(&announcement_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+    }
+    author.sign_packet(&announcement_link, &payload, &payload)?;
+    let keyload_link = {
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let packet_link = author.tag_packet(&keyload_link, &payload, &payload)?.link;
+    author.tag_packet(&packet_link, &payload, &payload)?;
+    {
+        let msg = transport.recv_message(&keyload_link)?;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+        let msg = subscriber.tag_packet(&keyload_link, &payload, &payload)?;
+        author.unwrap_tagged_packet(msg.parse_header()?)?;
+    }
+
+    let report = author.report();
+    ensure!(report.subscribers == 1 && report.pending_keyload_requests == 0);
+    ensure!(report.branches.len() == 2);
+    ensure!(report.branches[0].link == announcement_link && report.branches[0].messages == 1);
+    ensure!(report.branches[0].keyload_age.is_none());
+    ensure!(report.branches[1].link == keyload_link && report.branches[1].messages == 3);
+    ensure!(report.branches[1].keyload_age.is_some() && report.last_keyload_age.is_some());
+    ensure!(report.pending_subscriptions == 0);
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_string(&report)?;
+        let parsed: super::ChannelReport = serde_json::from_str(&json)?;
+        ensure!(parsed.branches[1].link == keyload_link && parsed.branches[1].messages == 3);
+    }
+
+    // Messages forgotten by the store are not tracked, branches keep their counts.
+    author.set_keyload_epochs(Some(1));
+    let keyload_link = author.share_keyload_for_everyone(&announcement_link)?.link;
+    author.tag_packet(&keyload_link, &payload, &payload)?;
+    ensure!(author.tracker.messages(&keyload_link.msgid).len() == 2);
+    author.share_keyload_for_everyone(&announcement_link)?;
+    ensure!(author.tracker.messages(&keyload_link.msgid).is_empty());
+    ensure!(author.report().branches[2].messages == 1);
+    Ok(())
+}
+
+#[test]
+fn run_channel_report() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(channel_report(&mut transport)).is_ok());
+}
+
+fn kem_reuse<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+    let subscriber_pk = subscriber.ntru_public_key().unwrap().clone();
+    let mut authors = Vec::new();
+    for _ in 0..2 {
+        let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+        author.imp.ntru_pks.insert(subscriber_pk.clone());
+        author.set_keyload_seed(Some("KEYLOAD9SEED"));
+        authors.push(author);
+    }
+
+    let announcement_link = {
+        let msg = authors[0].announce()?;
+        authors[1].announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+    }
+
+    // Keyloads are reproducible with the same seed.
+    let keyload_link = {
+        let msg = authors[0].share_keyload_for_everyone(&announcement_link)?;
+        let same = authors[1].share_keyload_for_everyone(&announcement_link)?;
+        ensure!(msg.body == same.body);
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&keyload_link)?;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+    }
+
+    // Restarted PRNG repeats the session key and nonce.
+    let author = &mut authors[0];
+    author.set_keyload_seed(Some("KEYLOAD9SEED"));
+    ensure!(author.share_keyload_for_everyone(&announcement_link).is_err());
+    author.set_kem_reuse_check(|_| true);
+    author.set_keyload_seed(Some("KEYLOAD9SEED"));
+    ensure!(author.share_keyload_for_everyone(&announcement_link).is_ok());
+
+    // Fresh session keys are encapsulated with system RNG.
+    author.set_kem_reuse_check(refuse_kem_reuse);
+    author.set_keyload_seed(None);
+    author.share_keyload_for_everyone(&announcement_link)?;
+
+    // Keyloads prepared but not wrapped are not recorded.
+    let author = &mut authors[1];
+    author.set_keyload_seed(Some("OTHER9KEYLOAD9SEED"));
+    author.imp.prepare_keyload_for_everyone(&announcement_link.msgid)?;
+    author.set_keyload_seed(Some("OTHER9KEYLOAD9SEED"));
+    author.share_keyload_for_everyone(&announcement_link)?;
+
+    // History is capped per recipient, the oldest session key is forgotten.
+    author.set_keyload_seed(None);
+    for _ in 0..KEM_HISTORY_SIZE {
+        author.share_keyload_for_everyone(&announcement_link)?;
+    }
+    ensure!(author.imp.kem_history[&subscriber_pk.get_pkid()].len() == KEM_HISTORY_SIZE);
+    author.set_keyload_seed(Some("OTHER9KEYLOAD9SEED"));
+    author.share_keyload_for_everyone(&announcement_link)?;
+    Ok(())
+}
+
+#[test]
+fn run_kem_reuse() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(kem_reuse(&mut transport)).is_ok());
+}
+
+fn cover_traffic<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+    author.imp.ntru_pks.insert(subscriber.ntru_public_key().unwrap().clone());
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+    }
+    let keyload_link = {
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&keyload_link)?;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+    }
+
+    // Nothing is due right after the keyload.
+    author.set_cover_traffic(Some(CoverTraffic {
+        interval: Duration::from_secs(3600),
+        payload_size: 81,
+    }));
+    ensure!(author.poll_cover_traffic(&keyload_link)?.is_none());
+
+    author.set_cover_traffic(Some(CoverTraffic {
+        interval: Duration::from_secs(0),
+        payload_size: 81,
+    }));
+    let msg = author.poll_cover_traffic(&keyload_link)?.unwrap();
+    {
+        let (public, masked) = subscriber.unwrap_tagged_packet(msg.parse_header()?)?;
+        ensure!(public.0.size() == 0);
+        ensure!(masked.0.size() == 3 * 81);
+        ensure!(is_padding(&masked));
+    }
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    let msg = author.tag_packet(&keyload_link, &Trytes::default(), &masked_payload)?;
+    {
+        let (_, masked) = subscriber.unwrap_tagged_packet(msg.parse_header()?)?;
+        ensure!(!is_padding(&masked));
+    }
+
+    let msg = subscriber.pad(&keyload_link, 30)?;
+    {
+        let (_, masked) = author.unwrap_tagged_packet(msg.parse_header()?)?;
+        ensure!(is_padding(&masked));
+    }
+    ensure!(subscriber.pad(&keyload_link, 5).is_err());
+    Ok(())
+}
+
+#[test]
+fn run_cover_traffic() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(cover_traffic(&mut transport)).is_ok());
+}
+
+fn offline_wrap() -> Fallible<()> {
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+
+    // Messages are moved as bytes, no transport is involved.
+    let (announcement_link, bytes) = author.send_announcement_offline()?;
+    subscriber.receive_announcement_from_bytes(&announcement_link, &bytes)?;
+
+    let (subscribe_link, bytes) = subscriber.send_subscribe_offline(&announcement_link)?;
+    author.receive_subscribe_from_bytes(&subscribe_link, &bytes)?;
+
+    let (keyload_link, bytes) = author.send_keyload_for_everyone_offline(&announcement_link)?;
+    subscriber.receive_keyload_from_bytes(&keyload_link, &bytes)?;
+
+    let public_payload = Trytes(Tbits::from_str("PUBLICPAYLOAD").unwrap());
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    let (signed_link, bytes) = author.send_signed_packet_offline(&keyload_link, &public_payload, &masked_payload)?;
+    let (public, masked) = subscriber.receive_signed_packet_from_bytes(&signed_link, &bytes)?;
+    ensure!(public == public_payload && masked == masked_payload);
+
+    let (tagged_link, bytes) = subscriber.send_tagged_packet_offline(&keyload_link, &public_payload, &masked_payload)?;
+    let (public, masked) = author.receive_tagged_packet_from_bytes(&tagged_link, &bytes)?;
+    ensure!(public == public_payload && masked == masked_payload);
+
+    // Bytes of one message don't unwrap under the link of another.
+    let (_, bytes) = author.send_tagged_packet_offline(&keyload_link, &public_payload, &masked_payload)?;
+    ensure!(subscriber.receive_tagged_packet_from_bytes(&tagged_link, &bytes).is_err());
+    ensure!(subscriber.receive_tagged_packet_from_bytes(&tagged_link, &bytes[..3]).is_err());
+    Ok(())
+}
+
+#[test]
+fn run_offline_wrap() {
+    assert!(dbg!(offline_wrap()).is_ok());
+}
+
+fn aad<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    // With a wrong AAD masked payload size is garbage, limit it.
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").max_size(100).build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").max_size(100).build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+    }
+    {
+        let msg = subscriber.subscribe(&announcement_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+    }
+    let keyload_link = {
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&keyload_link)?;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+    }
+
+    let public_payload = Trytes(Tbits::from_str("PUBLICPAYLOAD").unwrap());
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    let aad = Trytes(Tbits::from_str("RECORD9ID").unwrap());
+    let wrong_aad = Trytes(Tbits::from_str("RECORD9IE").unwrap());
+
+    // AAD is not sent, signed packet only unwraps with the same AAD.
+    let signed_link = {
+        let msg = author.sign_packet_with_aad(&keyload_link, &public_payload, &masked_payload, &aad)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&signed_link)?;
+        ensure!(subscriber.unwrap_signed_packet(msg.parse_header()?).is_err());
+        ensure!(subscriber.unwrap_signed_packet_with_aad(msg.parse_header()?, &wrong_aad).is_err());
+        let (public, masked) = subscriber.unwrap_signed_packet_with_aad(msg.parse_header()?, &aad)?;
+        ensure!(public == public_payload && masked == masked_payload);
+    }
+
+    let tagged_link = {
+        let msg = subscriber.tag_packet_with_aad(&keyload_link, &public_payload, &masked_payload, &aad)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&tagged_link)?;
+        ensure!(author.unwrap_tagged_packet(msg.parse_header()?).is_err());
+        ensure!(author.unwrap_tagged_packet_with_aad(msg.parse_header()?, &wrong_aad).is_err());
+        let (public, masked) = author.unwrap_tagged_packet_with_aad(msg.parse_header()?, &aad)?;
+        ensure!(public == public_payload && masked == masked_payload);
+    }
+
+    // Tagged packet without AAD doesn't unwrap with one.
+    let tagged_link = {
+        let msg = author.tag_packet(&keyload_link, &public_payload, &masked_payload)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&tagged_link)?;
+        ensure!(subscriber.unwrap_tagged_packet_with_aad(msg.parse_header()?, &aad).is_err());
+        subscriber.unwrap_tagged_packet(msg.parse_header()?)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn run_aad() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(aad(&mut transport)).is_ok());
+}
+
+static AUTHOR_KEY_MISMATCHES: AtomicUsize = AtomicUsize::new(0);
+
+fn announcement_pinning<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let mut forger = AuthorBuilder::new("FORGER9SEED").build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+    subscriber.set_author_key_mismatch_handler(|_| {
+        AUTHOR_KEY_MISMATCHES.fetch_add(1, Ordering::SeqCst);
+    });
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+    }
+
+    // Forged announcement of the same channel signed with another key.
+    let forged = {
+        let mut prepared = forger.imp.prepare_announcement()?;
+        prepared.header.link = announcement_link.clone();
+        prepared.wrap()?.message
+    };
+    match subscriber.unwrap_announcement(forged.parse_header()?) {
+        Ok(_) => bail!("Forged announcement is handled."),
+        Err(err) => ensure!(
+            err.downcast_ref::<AuthorKeyMismatch>()
+                == Some(&AuthorKeyMismatch {
+                    content_type: message::announce::TYPE
+                })
+        ),
+    }
+    ensure!(AUTHOR_KEY_MISMATCHES.load(Ordering::SeqCst) == 1);
+    ensure!(subscriber.author_mss_public_key().as_ref() == Some(author.imp.mss_sk.public_key()));
+
+    // Author's key change survives handling the announcement again.
+    let change_key_link = {
+        let msg = author.change_key(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&change_key_link)?;
+        subscriber.unwrap_change_key(msg.parse_header()?)?;
+    }
+    {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+    }
+    ensure!(subscriber.author_mss_public_key().as_ref() == Some(author.imp.mss_sk.public_key()));
+    ensure!(AUTHOR_KEY_MISMATCHES.load(Ordering::SeqCst) == 1);
+    Ok(())
+}
+
+#[test]
+fn run_announcement_pinning() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(announcement_pinning(&mut transport)).is_ok());
+}
+
+fn multi_tagged_packet<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let mut subscribers = Vec::new();
+    let mut pskids = Vec::new();
+    for group in &["GROUPA", "GROUPB", "GROUPC"] {
+        let pskid = Tbits::cycle_str(81, &format!("{}9ID", group));
+        let psk = Tbits::cycle_str(243, group);
+        author.imp.psks.insert(pskid.clone(), psk.clone());
+        let mut subscriber = SubscriberBuilder::new(&format!("{}9SEED", group)).ntru(false).build();
+        subscriber.imp.opt_psk = Some((pskid.clone(), psk));
+        subscribers.push(subscriber);
+        pskids.push(pskid);
+    }
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let keyload_link = {
+        let msg = author.share_keyload(&announcement_link, &pskids, &vec![])?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    for subscriber in &mut subscribers {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        let msg = transport.recv_message(&keyload_link)?;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+    }
+
+    // Groups A and B verify their own MACs, group C is not tagged.
+    let public_payload = Trytes(Tbits::from_str("PUBLICPAYLOAD").unwrap());
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    let packet_link = {
+        let msg = author.multi_tag_packet(&keyload_link, &public_payload, &masked_payload, &pskids[..2].to_vec())?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let msg = transport.recv_message(&packet_link)?;
+    for subscriber in &mut subscribers[..2] {
+        let (public, masked) = subscriber.unwrap_multi_tagged_packet(msg.parse_header()?)?;
+        ensure!(public == public_payload && masked == masked_payload);
+    }
+    ensure!(subscribers[2].unwrap_multi_tagged_packet(msg.parse_header()?).is_err());
+
+    ensure!(author
+        .multi_tag_packet(&keyload_link, &public_payload, &masked_payload, &vec![])
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn run_multi_tagged_packet() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(multi_tagged_packet(&mut transport)).is_ok());
+}
+
+fn fixed_size<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let mut subscriberA = SubscriberBuilder::new("SUBSCRIBERA9SEED").build();
+    let mut subscriberB = SubscriberBuilder::new("SUBSCRIBERB9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let msg = transport.recv_message(&announcement_link)?;
+    subscriberA.unwrap_announcement(msg.parse_header()?)?;
+    subscriberB.unwrap_announcement(msg.parse_header()?)?;
+
+    // Size of subscribe content is cached by the first subscriber and reused by the second.
+    for subscriber in [&mut subscriberA, &mut subscriberB].iter_mut() {
+        let size = subscriber.imp.prepare_subscribe(announcement_link.rel())?.wrap()?.message.body.size();
+        let msg = subscriber.subscribe(&announcement_link)?;
+        ensure!(msg.body.size() == size, "Fixed size mismatch: {} != {}.", msg.body.size(), size);
+        author.unwrap_subscribe(msg.parse_header()?)?;
+    }
+    ensure!(author.imp.ntru_pks.len() == 2);
+    Ok(())
+}
+
+#[test]
+fn run_fixed_size() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(fixed_size(&mut transport)).is_ok());
+}
+
+fn channel_manager<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut authorA = AuthorBuilder::new("AUTHORA9SEED").build();
+    let mut authorB = AuthorBuilder::new("AUTHORB9SEED").build();
+    let mut manager = ChannelManager::new(5);
+    let (sender, receiver) = std::sync::mpsc::channel();
+
+    let mut last_links = Vec::new();
+    for author in [&mut authorA, &mut authorB].iter_mut() {
+        let announcement = author.announce()?;
+        transport.send_message(&announcement)?;
+        let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+        subscriber.unwrap_announcement(announcement.parse_header()?)?;
+        let sender = sender.clone();
+        manager.insert(subscriber, move |link, received| {
+            if let Received::SignedPacket(public, _) = received {
+                sender.send((link.clone(), public)).unwrap();
+            }
+        })?;
+        last_links.push(announcement.link);
+    }
+    ensure!(manager.len() == 2);
+
+    let payload = Trytes(Tbits::from_str("PAYLOAD").unwrap());
+    for round in 0..2 {
+        let mut sent = Vec::new();
+        for (author, link) in [&mut authorA, &mut authorB].iter_mut().zip(last_links.iter_mut()) {
+            let msg = author.sign_packet(link, &payload, &payload)?;
+            transport.send_message(&msg)?;
+            *link = msg.link.clone();
+            sent.push(msg.link);
+        }
+        let errors = manager.sync(transport);
+        ensure!(errors.is_empty(), "Round {}: sync errors: {}.", round, errors.len());
+        let mut received: Vec<_> = receiver
+            .try_iter()
+            .map(|(link, public)| {
+                assert!(public == payload);
+                link
+            })
+            .collect();
+        received.sort_by_key(|link| sent.iter().position(|l| l == link));
+        ensure!(received == sent, "Round {}: unexpected messages.", round);
+    }
+
+    ensure!(manager.remove(authorA.channel_address()).is_some());
+    ensure!(manager.get(authorA.channel_address()).is_none());
+    ensure!(manager.len() == 1);
+    Ok(())
+}
+
+#[test]
+fn run_channel_manager() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(channel_manager(&mut transport)).is_ok());
+}
+
+fn skip_lost_msgs<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        msg.link
+    };
+    // The first 5 packets are lost.
+    let payload = Trytes(Tbits::from_str("PAYLOAD").unwrap());
+    let mut sent = Vec::new();
+    for i in 0..10 {
+        let msg = author.sign_packet(&announcement_link, &payload, &payload)?;
+        if i >= 5 {
+            transport.send_message(&msg)?;
+            sent.push(msg.link);
+        }
+    }
+
+    ensure!(subscriber.fetch_next_msgs(transport, 2)?.is_empty());
+    ensure!(subscriber.skip_lost_msgs(transport, 2)?.is_none());
+    ensure!(subscriber.skip_lost_msgs(transport, 16)?.is_some());
+    let links: Vec<_> = subscriber
+        .fetch_next_msgs(transport, 2)?
+        .into_iter()
+        .map(|msg| msg.link)
+        .collect();
+    ensure!(links == sent);
+    Ok(())
+}
+
+#[test]
+fn run_skip_lost_msgs() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(skip_lost_msgs(&mut transport)).is_ok());
+}
+
+fn fetch_msgs_since(transport: &mut BucketTransport) -> Fallible<()> {
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message_at(&msg, 1000);
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        msg.link
+    };
+    let payload = Trytes(Tbits::from_str("PAYLOAD").unwrap());
+    let mut links = Vec::new();
+    for timestamp in [2000, 3000, 4000].iter() {
+        let msg = author.sign_packet(&announcement_link, &payload, &payload)?;
+        transport.send_message_at(&msg, *timestamp);
+        links.push(msg.link);
+    }
+
+    let msgs = subscriber.fetch_msgs_since(transport, 5, 3000)?;
+    ensure!(msgs.iter().map(|msg| &msg.link).eq(links[1..].iter()));
+    for msg in msgs {
+        subscriber.unwrap_signed_packet(msg.parse_header()?)?;
+    }
+
+    // Fetching resumes after the last probed message.
+    let msg = author.sign_packet(&announcement_link, &payload, &payload)?;
+    transport.send_message_at(&msg, 5000);
+    let msgs = subscriber.fetch_msgs_since(transport, 5, 3000)?;
+    ensure!(msgs.len() == 1 && msgs[0].link == msg.link);
+    Ok(())
+}
+
+#[test]
+fn run_fetch_msgs_since() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(fetch_msgs_since(&mut transport)).is_ok());
+}
+
+/// Routing hint carried in header as TLV extension.
+struct Route(String);
+
+impl TlvExtension<DefaultTW> for Route {
+    const TLV_TYPE: usize = 1;
+    fn to_value(&self) -> Trytes<DefaultTW> {
+        Trytes(Tbits::from_str(&self.0).unwrap())
+    }
+    fn from_value(value: &Trytes<DefaultTW>) -> Fallible<Self> {
+        Ok(Route(value.to_string()))
+    }
+}
+
+fn header_tlv<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        msg.link
+    };
+    let payload = Trytes(Tbits::from_str("PAYLOAD").unwrap());
+    let unknown = Trytes(Tbits::from_str("UNKNOWN9TLV").unwrap());
+    let msg = {
+        let mut prepared = author
+            .imp
+            .prepare_signed_packet(announcement_link.rel(), &payload, &payload)?
+            .with_tlv_ext(&Route("ROUTE9A".to_string()));
+        prepared.header = prepared.header.with_tlv(99, unknown.clone());
+        prepared.wrap()?.message
+    };
+
+    let preparsed = msg.parse_header()?;
+    ensure!(preparsed.header.version == STREAMS_5_VER);
+    ensure!(preparsed.header.tlv_ext::<Route>()?.map(|r| r.0) == Some("ROUTE9A".to_string()));
+    ensure!(preparsed.header.tlv(99) == Some(&unknown));
+    ensure!(preparsed.header.tlv(2).is_none());
+    subscriber.unwrap_signed_packet(preparsed)?;
+    Ok(())
+}
+
+#[test]
+fn run_header_tlv() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(header_tlv(&mut transport)).is_ok());
+}
+
+fn threshold_announce<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let co_signer_a = AuthorBuilder::new("COSIGNER9A").mss_height(3).build().imp.mss_sk;
+    let co_signer_b = AuthorBuilder::new("COSIGNER9B").mss_height(3).build().imp.mss_sk;
+    let mss_pks = vec![co_signer_a.public_key().clone(), co_signer_b.public_key().clone()];
+
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    ensure!(author.set_author_set(2, mss_pks.clone(), Vec::new()).is_err());
+    author.set_author_set(2, mss_pks.clone(), vec![co_signer_a])?;
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        let preparsed = msg.parse_header()?;
+        ensure!(preparsed.check_content_type(message::threshold_announce::TYPE));
+        subscriber.unwrap_announcement(preparsed)?;
+        msg.link
+    };
+    ensure!(subscriber.author_set().map(|set| (set.threshold, set.mss_pks.len())) == Some((2, 3)));
+    ensure!(author.change_key(&announcement_link).is_err());
+
+    let subscribe_link = {
+        let msg = subscriber.subscribe(&announcement_link)?;
+        transport.send_message(&msg)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+        msg.link
+    };
+    let keyload_link = {
+        let msg = author.share_keyload_for_everyone(&subscribe_link)?;
+        transport.send_message(&msg)?;
+        let preparsed = msg.parse_header()?;
+        ensure!(preparsed.check_content_type(message::threshold_keyload::TYPE));
+        subscriber.unwrap_keyload(preparsed)?;
+        msg.link
+    };
+    let payload = Trytes(Tbits::from_str("PAYLOAD").unwrap());
+    let msg = author.tag_packet(&keyload_link, &payload, &payload)?;
+    ensure!(subscriber.unwrap_tagged_packet(msg.parse_header()?)? == (payload.clone(), payload));
+
+    // Announcement claiming a threshold it doesn't meet is rejected.
+    let mut forger = AuthorBuilder::new("FORGER9SEED").build();
+    forger.set_author_set(2, mss_pks, vec![co_signer_b])?;
+    forger.imp.author_set.as_mut().unwrap().threshold = 3;
+    let msg = forger.announce()?;
+    let mut other = SubscriberBuilder::new("OTHER9SUBSCRIBER").build();
+    ensure!(other.unwrap_announcement(msg.parse_header()?).is_err());
+    ensure!(!other.is_registered());
+    Ok(())
+}
+
+#[test]
+fn run_threshold_announce() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(threshold_announce(&mut transport)).is_ok());
+}
+
+fn aliases<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+    let ntru_pkid = subscriber.ntru_public_key().unwrap().get_pkid();
+    ensure!(author.set_alias(ntru_pkid.clone(), "sensor-1").is_err());
+
+    let announcement = author.announce()?;
+    subscriber.unwrap_announcement(announcement.parse_header()?)?;
+    let msg = subscriber.subscribe(&announcement.link)?;
+    transport.send_message(&msg)?;
+    author.unwrap_subscribe(transport.recv_message(&msg.link)?.parse_header()?)?;
+
+    author.set_alias(ntru_pkid.clone(), "sensor-1")?;
+    ensure!(author.alias(&ntru_pkid) == Some("sensor-1"));
+    ensure!(author.ntru_pk_by_alias("sensor-1").map_or(false, |ntru_pk| ntru_pk.cmp_pkid(&ntru_pkid)));
+    author.set_alias(ntru_pkid.clone(), "sensor-2")?;
+    ensure!(author.ntru_pk_by_alias("sensor-1").is_none());
+    ensure!(author.set_alias(ntru_pkid.clone(), "").is_err());
+
+    let (exported, _, _) = author.export_branch(&announcement.link)?;
+    ensure!(exported.alias(&ntru_pkid) == Some("sensor-2"));
+    ensure!(author.remove_alias(&ntru_pkid).as_deref() == Some("sensor-2"));
+    ensure!(author.alias(&ntru_pkid).is_none());
+    Ok(())
+}
+
+#[test]
+fn run_aliases() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(aliases(&mut transport)).is_ok());
+}
+
+fn messages_iter<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+    ensure!(subscriber.messages(transport, 5).next().map_or(false, |msg| msg.is_err()));
+
+    let announcement = author.announce()?;
+    transport.send_message(&announcement)?;
+    subscriber.unwrap_announcement(announcement.parse_header()?)?;
+
+    let payload = Trytes(Tbits::from_str("PAYLOAD").unwrap());
+    let mut link = announcement.link;
+    let mut sent = Vec::new();
+    for _ in 0..3 {
+        let msg = author.sign_packet(&link, &payload, &payload)?;
+        transport.send_message(&msg)?;
+        link = msg.link;
+        sent.push(link.clone());
+    }
+
+    let mut received = Vec::new();
+    for msg in subscriber.messages(transport, 5) {
+        match msg? {
+            (link, Received::SignedPacket(public, _)) if public == payload => received.push(link),
+            _ => bail!("Unexpected message."),
+        }
+    }
+    ensure!(received == sent);
+    ensure!(subscriber.messages(transport, 5).next().is_none());
+
+    let msg = author.sign_packet(&link, &payload, &payload)?;
+    transport.send_message(&msg)?;
+    let links: Vec<_> = subscriber
+        .messages(transport, 5)
+        .map(|msg| msg.map(|(link, _)| link))
+        .collect::<Fallible<_>>()?;
+    ensure!(links == vec![msg.link]);
+    Ok(())
+}
+
+#[test]
+fn run_messages_iter() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(messages_iter(&mut transport)).is_ok());
+}
+
+fn payload_padding<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED")
+        .mss_height(2)
+        .payload_padding(PayloadPadding::Multiple(27))
+        .build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED")
+        .payload_padding(PayloadPadding::Pow2)
+        .build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        msg.link
+    };
+    {
+        let msg = subscriber.subscribe(&announcement_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+    }
+    let keyload_link = {
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        subscriber.unwrap_keyload(msg.parse_header()?)?;
+        msg.link
+    };
+
+    let public = Trytes(Tbits::from_str("PUBLIC").unwrap());
+    let short = Trytes(Tbits::from_str("A").unwrap());
+    let long = Trytes(Tbits::from_str("MASKED9PAYLOAD9A99").unwrap());
+
+    // Payloads of the same size class make messages of the same size.
+    let msg_short = author.sign_packet(&keyload_link, &public, &short)?;
+    let msg_long = author.sign_packet(&keyload_link, &public, &long)?;
+    ensure!(msg_short.body.size() == msg_long.body.size());
+    ensure!(subscriber.unwrap_signed_packet(msg_short.parse_header()?)?.1 == short);
+    ensure!(subscriber.unwrap_signed_packet(msg_long.parse_header()?)?.1 == long);
+
+    let msg = subscriber.tag_packet(&keyload_link, &public, &long)?;
+    ensure!(author.unwrap_tagged_packet(msg.parse_header()?)? == (public.clone(), long.clone()));
+
+    // Unpadded packets are handled as before.
+    author.set_payload_padding(PayloadPadding::None);
+    let msg = author.tag_packet(&keyload_link, &public, &long)?;
+    ensure!(msg.parse_header()?.header.tlvs.is_empty());
+    ensure!(subscriber.unwrap_tagged_packet(msg.parse_header()?)?.1 == long);
+    Ok(())
+}
+
+#[test]
+fn run_payload_padding() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(payload_padding(&mut transport)).is_ok());
+}
+
+fn key_escrow<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+    let mut auditor = SubscriberBuilder::new("AUDITOR9SEED").build();
+    author.set_escrow_pk(auditor.ntru_public_key().cloned());
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        auditor.unwrap_announcement(msg.parse_header()?)?;
+        msg.link
+    };
+    {
+        let msg = subscriber.subscribe(&announcement_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+    }
+    let keyload = author.share_keyload_for_everyone(&announcement_link)?;
+    let payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    let packet = author.sign_packet(&keyload.link, &payload, &payload)?;
+    subscriber.unwrap_keyload(keyload.parse_header()?)?;
+    ensure!(subscriber.unwrap_signed_packet(packet.parse_header()?)?.1 == payload);
+
+    // Auditor isn't a keyload recipient, it unwraps the keyload with escrowed session key.
+    ensure!(auditor.unwrap_keyload(keyload.parse_header()?).is_err());
+    let escrowed = author.take_escrowed_keys();
+    ensure!(escrowed.len() == 1 && escrowed[0].0 == keyload.link);
+    ensure!(author.take_escrowed_keys().is_empty());
+    ensure!(subscriber.import_escrowed_key(&keyload.link, &escrowed[0].1).is_err());
+    auditor.import_escrowed_key(&keyload.link, &escrowed[0].1)?;
+    auditor.unwrap_keyload(keyload.parse_header()?)?;
+    ensure!(auditor.role() == Some(Role::ReadOnly));
+    ensure!(auditor.unwrap_signed_packet(packet.parse_header()?)?.1 == payload);
+
+    author.set_escrow_pk(None);
+    author.share_keyload_for_everyone(&announcement_link)?;
+    ensure!(author.take_escrowed_keys().is_empty());
+    Ok(())
+}
+
+#[test]
+fn run_key_escrow() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(key_escrow(&mut transport)).is_ok());
+}
+
+fn header_malleability<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED")
+        .app_version(Trint3(5))
+        .payload_padding(PayloadPadding::Pow2)
+        .build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        msg.link
+    };
+    {
+        let msg = subscriber.subscribe(&announcement_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+    }
+    let keyload = author.share_keyload_for_everyone(&announcement_link)?;
+    subscriber.unwrap_keyload(keyload.parse_header()?)?;
+
+    // Header has application version, TLV extension and extension block.
+    let public_payload = Trytes(Tbits::from_str("PUBLICPAYLOAD").unwrap());
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    let msg = author.tag_packet_with_ext(&keyload.link, &public_payload, &masked_payload, "BRANCHA", "SCHEMA9VA")?;
+    let info = inspect(&msg)?;
+    ensure!(info.version == STREAMS_5_VER);
+    ensure!(info.ext_size != 0);
+
+    // Any single trit flip in the header fails unwrap.
+    let header_size = msg.body.size() - info.content_size;
+    for pos in 0..header_size {
+        let mut tampered = msg.clone();
+        let t = tampered.body.slice().drop(pos).take(1).get_isize();
+        tampered.body.slice_mut().drop(pos).take(1).put_isize(if t == 1 { -1 } else { t + 1 });
+        let unwrapped = tampered
+            .parse_header()
+            .and_then(|preparsed| subscriber.unwrap_tagged_packet(preparsed));
+        ensure!(unwrapped.is_err(), "Header trit {} flip is not detected.", pos);
+    }
+
+    // Headers of versions 2 to 4 don't commit the extension block, they are rejected.
+    for version in 2..=4 {
+        let mut downgraded = msg.clone();
+        downgraded.body.slice_mut().take(3).put_isize(version);
+        ensure!(downgraded.parse_header().is_err(), "Version {} header is accepted.", version);
+    }
+
+    let (unwrapped_public, unwrapped_masked) = subscriber.unwrap_tagged_packet(msg.parse_header()?)?;
+    ensure!(unwrapped_public == public_payload);
+    ensure!(unwrapped_masked == masked_payload);
+    Ok(())
+}
+
+#[test]
+fn run_header_malleability() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(header_malleability(&mut transport)).is_ok());
+}
+
+/// Msgids of a tenant's channels, the tenant id is absorbed along with the usual inputs.
+struct TenantMsgIdDeriver {
+    tenant: Trytes<DefaultTW>,
+}
+
+impl MsgIdDeriver<DefaultTW> for TenantMsgIdDeriver {
+    fn derive_msgid(
+        &self,
+        appinst: &ChannelAddress,
+        msgid: &MsgId<DefaultTW>,
+        counter: usize,
+    ) -> Fallible<NTrytes<DefaultTW>> {
+        let mut id = NTrytes(Tbits::zero(MSGID_SIZE));
+        wrap::Context::<DefaultTW, DefaultF, io::NoOStream>::new(io::NoOStream)
+            .absorb(External(&self.tenant))?
+            .absorb(External(&NTrytes(appinst.tbits().clone())))?
+            .absorb(External(&NTrytes(msgid.tbits().clone())))?
+            .absorb(External(Size(counter)))?
+            .commit()?
+            .squeeze(External(&mut id))?;
+        Ok(id)
+    }
+}
+
+fn tenant_deriver(tenant: &str) -> SharedMsgIdDeriver {
+    Arc::new(TenantMsgIdDeriver {
+        tenant: Trytes(Tbits::from_str(tenant).unwrap()),
+    })
+}
+
+fn msgid_deriver<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED")
+        .msgid_deriver(tenant_deriver("TENANTA"))
+        .build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED")
+        .msgid_deriver(tenant_deriver("TENANTA"))
+        .build();
+
+    let announcement = author.announce()?;
+    transport.send_message(&announcement)?;
+    let default_announcement = AuthorBuilder::new("AUTHOR9SEED").build().announce()?;
+    ensure!(announcement.link.appinst == default_announcement.link.appinst);
+    ensure!(announcement.link.msgid != default_announcement.link.msgid);
+
+    // Custom derivation is announced, it can't be followed without the deriver.
+    let mut other = SubscriberBuilder::new("OTHER9SEED").build();
+    ensure!(other.unwrap_announcement(announcement.parse_header()?).is_err());
+
+    subscriber.unwrap_announcement(announcement.parse_header()?)?;
+    ensure!(subscriber.msgid_derivation() == MsgIdDerivation::Custom);
+    let subscribe = subscriber.subscribe(&announcement.link)?;
+    author.unwrap_subscribe(subscribe.parse_header()?)?;
+    let mut link_gen = LinkGen::default();
+    link_gen.reset_appinst(announcement.link.appinst.clone());
+    link_gen.set_msgid_derivation(MsgIdDerivation::Custom);
+    link_gen.set_msgid_deriver(Some(tenant_deriver("TENANTA")));
+    ensure!(subscribe.link == link_gen.link_at(&announcement.link.msgid, 1));
+    link_gen.set_msgid_deriver(Some(tenant_deriver("TENANTB")));
+    ensure!(subscribe.link != link_gen.link_at(&announcement.link.msgid, 1));
+
+    let payload = Trytes(Tbits::from_str("PUBLICPAYLOAD").unwrap());
+    transport.send_message(&author.sign_packet(&announcement.link, &payload, &Trytes::default())?)?;
+    let msgs = subscriber.fetch_next_msgs(transport, 5)?;
+    ensure!(msgs.len() == 1);
+    ensure!(subscriber.unwrap_signed_packet(msgs[0].parse_header()?)?.0 == payload);
+    Ok(())
+}
+
+#[test]
+fn run_msgid_deriver() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(msgid_deriver(&mut transport)).is_ok());
+}
+
+fn cursor<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let announcement = author.announce()?;
+    transport.send_message(&announcement)?;
+    let payload = Trytes(Tbits::from_str("PAYLOAD").unwrap());
+    let sign_packets = |author: &mut Author, transport: &mut T, n: usize| -> Fallible<Vec<Address>> {
+        let mut links = Vec::new();
+        for _ in 0..n {
+            let msg = author.sign_packet(&announcement.link, &payload, &payload)?;
+            transport.send_message(&msg)?;
+            links.push(msg.link);
+        }
+        Ok(links)
+    };
+
+    let mut worker = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+    ensure!(worker.cursor().is_err());
+    worker.unwrap_announcement(announcement.parse_header()?)?;
+    worker.follow_branch(&announcement.link)?;
+    sign_packets(&mut author, transport, 2)?;
+    ensure!(worker.messages(transport, 5).count() == 2);
+    let cursor = worker.cursor()?;
+    ensure!(cursor.followed.len() == 3);
+    let compact = cursor.to_compact();
+    ensure!(Cursor::from_compact(&compact)? == cursor);
+    ensure!(Cursor::from_bytes(&cursor.to_bytes()[1..]).is_err());
+
+    // Another worker resumes from the cursor and fetches new messages only.
+    let sent = sign_packets(&mut author, transport, 2)?;
+    let mut worker = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+    worker.unwrap_announcement(announcement.parse_header()?)?;
+    worker.resume_from(&Cursor::from_compact(&compact)?)?;
+    let received: Vec<_> = worker
+        .messages(transport, 5)
+        .map(|msg| msg.map(|(link, _)| link))
+        .collect::<Fallible<_>>()?;
+    ensure!(received == sent);
+
+    let mut other = AuthorBuilder::new("OTHER9SEED").build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+    subscriber.unwrap_announcement(other.announce()?.parse_header()?)?;
+    ensure!(subscriber.resume_from(&cursor).is_err());
+    Ok(())
+}
+
+#[test]
+fn run_cursor() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(cursor(&mut transport)).is_ok());
+}
+
+fn monitor<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let announcement = author.announce()?;
+    transport.send_message(&announcement)?;
+    let mut monitor = SubscriberBuilder::new("MONITOR9SEED").ntru(false).build();
+    ensure!(monitor.monitor_next_msgs(transport, 5).is_err());
+    monitor.unwrap_announcement(announcement.parse_header()?)?;
+
+    let keyload = author.share_keyload_for_everyone(&announcement.link)?;
+    transport.send_message(&keyload)?;
+    let payload = Trytes(Tbits::from_str("PAYLOAD").unwrap());
+    let packet = author.sign_packet(&keyload.link, &payload, &payload)?;
+    transport.send_message(&packet)?;
+
+    // Content is not unwrapped, the monitor has no keys.
+    let monitored = monitor.monitor_next_msgs(transport, 5)?;
+    ensure!(
+        monitored
+            == vec![
+                Activity {
+                    link: keyload.link.clone(),
+                    joined: announcement.link.clone(),
+                    content_type: message::keyload::TYPE.to_string(),
+                    counter: 3,
+                },
+                Activity {
+                    link: packet.link.clone(),
+                    joined: keyload.link.clone(),
+                    content_type: message::signed_packet::TYPE.to_string(),
+                    counter: 4,
+                },
+            ]
+    );
+    ensure!(monitor.monitor_next_msgs(transport, 5)?.is_empty());
+
+    let packet = author.sign_packet(&announcement.link, &payload, &payload)?;
+    transport.send_message(&packet)?;
+    ensure!(monitor.monitor_next_msgs(transport, 5)?.len() == 1);
+    let activity = monitor.channel_activity();
+    ensure!(activity.total() == 3);
+    ensure!(activity.messages[message::signed_packet::TYPE] == 2);
+    ensure!(activity.last == Some(packet.link) && activity.counter == 5);
+
+    // Monitoring doesn't move the read position of fetching.
+    ensure!(monitor.fetch_next_msgs(transport, 5)?.len() == 3);
+    Ok(())
+}
+
+#[test]
+fn run_monitor() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(monitor(&mut transport)).is_ok());
+}
+
+fn branch_key<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+    let mut late = SubscriberBuilder::new("LATE9SEED").build();
+    let announcement = author.announce()?;
+    subscriber.unwrap_announcement(announcement.parse_header()?)?;
+    late.unwrap_announcement(announcement.parse_header()?)?;
+    author.unwrap_subscribe(subscriber.subscribe(&announcement.link)?.parse_header()?)?;
+    author.unwrap_subscribe(late.subscribe(&announcement.link)?.parse_header()?)?;
+    let keyload = author.share_keyload_for_everyone(&announcement.link)?;
+    subscriber.unwrap_keyload(keyload.parse_header()?)?;
+    late.unwrap_keyload(keyload.parse_header()?)?;
+
+    let label = Trytes(Tbits::from_str("DEVICEA").unwrap());
+    let binding = author.bind_branch_key(&keyload.link, &label)?;
+    transport.send_message(&binding)?;
+    ensure!(author.bind_branch_key(&keyload.link, &label).is_err());
+    let (bound_label, branch_pk) = subscriber.unwrap_branch_key(binding.parse_header()?)?;
+    ensure!(bound_label == label);
+    ensure!(Some(&branch_pk) == author.branch_public_key(&label).as_ref());
+    ensure!(author.branch_public_key(&Trytes(Tbits::from_str("DEVICEB").unwrap())).is_none());
+    ensure!(Some(&branch_pk) != subscriber.author_mss_public_key().as_ref());
+
+    // Packets of the branch are signed with the branch key.
+    let payload = Trytes(Tbits::from_str("PAYLOAD").unwrap());
+    let packet = author.sign_packet(&keyload.link, &payload, &payload)?;
+    ensure!(subscriber.unwrap_signed_packet(packet.parse_header()?)?.0 == payload);
+    let next = author.sign_packet(&packet.link, &payload, &payload)?;
+    ensure!(subscriber.unwrap_signed_packet(next.parse_header()?)?.0 == payload);
+    let err = late.unwrap_signed_packet(packet.parse_header()?).unwrap_err();
+    ensure!(err.downcast_ref::<AuthorKeyMismatch>().is_some());
+
+    // Other branches are signed with the channel key.
+    let main = author.sign_packet(&announcement.link, &payload, &payload)?;
+    ensure!(subscriber.unwrap_signed_packet(main.parse_header()?)?.0 == payload);
+
+    // Author restored with the link store doesn't bind the label again, one-time keys would be reused.
+    let mut restored = {
+        let from = author.imp.store.borrow();
+        let mut store = Store::default();
+        for (link, spongos, info) in from.entries()? {
+            store.update(&link, spongos, info)?;
+        }
+        for (tag, link) in from.labels() {
+            store.tag_link(&link, &tag)?;
+        }
+        AuthorBuilder::new("AUTHOR9SEED").build_with_store(store)
+    };
+    ensure!(restored.bind_branch_key(&keyload.link, &label).is_err());
+    ensure!(restored.branch_public_key(&label).is_none());
+    Ok(())
+}
+
+#[test]
+fn run_branch_key() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(branch_key(&mut transport)).is_ok());
+}
+
+fn audit<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").mss_height(2).build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+    let public_payload = Trytes(Tbits::from_str("PUBLICPAYLOAD").unwrap());
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+
+    let announcement = author.announce()?;
+    let public_packet = author.sign_packet(&announcement.link, &public_payload, &masked_payload)?;
+    subscriber.unwrap_announcement(announcement.parse_header()?)?;
+    let subscribe = subscriber.subscribe(&announcement.link)?;
+    transport.send_message(&subscribe)?;
+    author.unwrap_subscribe(transport.recv_message(&subscribe.link)?.parse_header()?)?;
+    let keyload = author.share_keyload_for_everyone(&announcement.link)?;
+    let packet = author.sign_packet(&keyload.link, &public_payload, &masked_payload)?;
+    let msgs = vec![
+        packet.clone(),
+        announcement.clone(),
+        public_packet.clone(),
+        subscribe.clone(),
+        keyload.clone(),
+    ];
+
+    // Keyless auditor authenticates the public branch only.
+    let report = audit_channel(&msgs, 10)?;
+    ensure!(report.is_clean(), "{}", report);
+    ensure!(report.verified == vec![announcement.link.clone(), public_packet.link.clone()]);
+    ensure!(report.unverified.len() == 3);
+
+    // Auditor holding the subscriber's keys authenticates the keyload branch too.
+    let mut auditor = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+    let report = audit_channel_with(&mut auditor, &msgs, 10)?;
+    ensure!(report.is_clean(), "{}", report);
+    ensure!(report.verified.len() == 4);
+    ensure!(report.unverified == vec![subscribe.link.clone()]);
+
+    // Corrupt the signature of the public packet and publish another copy at its link.
+    let mut forged = public_packet.clone();
+    let pos = forged.body.size() - 1;
+    let t = forged.body.slice().drop(pos).take(1).get_isize();
+    forged.body.slice_mut().drop(pos).take(1).put_isize(if t == 1 { -1 } else { t + 1 });
+    let report = audit_channel(&[announcement.clone(), forged.clone(), packet.clone()], 10)?;
+    ensure!(report.issues.len() == 2, "{}", report);
+    match &report.issues[0] {
+        AuditIssue::Invalid(link, _) => ensure!(*link == public_packet.link),
+        issue => bail!("Unexpected issue: {}.", issue),
+    }
+    ensure!(
+        report.issues[1]
+            == AuditIssue::Gap {
+                link: packet.link.clone(),
+                joined: keyload.link.clone(),
+            }
+    );
+    let report = audit_channel(&[announcement, public_packet.clone(), forged, public_packet.clone()], 10)?;
+    ensure!(report.issues == vec![AuditIssue::Fork(public_packet.link)], "{}", report);
+    Ok(())
+}
+
+#[test]
+fn run_audit() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(audit(&mut transport)).is_ok());
+}
+
+fn rekey<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").mss_height(1).build();
+    let mut subscriberA = SubscriberBuilder::new("SUBSCRIBERA9SEED").build();
+    let mut subscriberB = SubscriberBuilder::new("SUBSCRIBERB9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let subscribe_link = {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriberA.unwrap_announcement(msg.parse_header()?)?;
+        subscriberB.unwrap_announcement(msg.parse_header()?)?;
+        let msg = subscriberA.subscribe(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&subscribe_link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+    }
+
+    let keyload_link = {
+        let msg = author.share_keyload_for_everyone(&announcement_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    // Rekeys are chained, each one is joined to the previous one.
+    let rekey_link = {
+        let msg = author.rekey(&keyload_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let rekey2_link = {
+        let msg = author.rekey(&rekey_link)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    let public_payload = Trytes(Tbits::from_str("PUBLICPAYLOAD").unwrap());
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    let signed_packet_link = {
+        let msg = author.sign_packet(&rekey2_link, &public_payload, &masked_payload)?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+
+    {
+        let msg = transport.recv_message(&keyload_link)?;
+        subscriberA.unwrap_keyload(msg.parse_header()?)?;
+        for link in &[&rekey_link, &rekey2_link] {
+            let msg = transport.recv_message(link)?;
+            let preparsed = msg.parse_header()?;
+            ensure!(preparsed.check_content_type(message::rekey::TYPE));
+            subscriberA.unwrap_rekey(preparsed)?;
+        }
+        ensure!(subscriberA.session_keys().contains(&rekey2_link));
+        let msg = transport.recv_message(&signed_packet_link)?;
+        let (unwrapped_public, unwrapped_masked) = subscriberA.unwrap_signed_packet(msg.parse_header()?)?;
+        ensure!(public_payload == unwrapped_public);
+        ensure!(masked_payload == unwrapped_masked);
+    }
+
+    {
+        let msg = transport.recv_message(&keyload_link)?;
+        ensure!(subscriberB.unwrap_keyload(msg.parse_header()?).is_err());
+        let msg = transport.recv_message(&rekey_link)?;
+        ensure!(subscriberB.unwrap_rekey(msg.parse_header()?).is_err());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn run_rekey() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(rekey(&mut transport)).is_ok());
+}
+
+fn strict_sequencing<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").mss_height(2).build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").strict_sequencing(10).build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+    }
+
+    let public_payload = Trytes(Tbits::from_str("PUBLICPAYLOAD").unwrap());
+    let masked_payload = Trytes(Tbits::from_str("MASKEDPAYLOAD").unwrap());
+    let mut packets = Vec::new();
+    for _ in 0..2 {
+        let msg = author.sign_packet(&announcement_link, &public_payload, &masked_payload)?;
+        transport.send_message(&msg)?;
+        packets.push(msg);
+    }
+
+    for msg in &packets {
+        subscriber.unwrap_signed_packet(msg.parse_header()?)?;
+    }
+    let last = subscriber.last_sequenced();
+    ensure!(last.is_some());
+
+    // Replayed packet is rejected.
+    ensure!(subscriber.unwrap_signed_packet(packets[0].parse_header()?).is_err());
+    ensure!(subscriber.last_sequenced() == last);
+
+    // Packets are reprocessed intentionally.
+    subscriber.rewind_sequencing(None);
+    for msg in &packets {
+        subscriber.unwrap_signed_packet(msg.parse_header()?)?;
+    }
+    ensure!(subscriber.last_sequenced() == last);
+
+    // Tagged packets are not sequenced.
+    let msg = author.tag_packet(&announcement_link, &public_payload, &masked_payload)?;
+    subscriber.unwrap_tagged_packet(msg.parse_header()?)?;
+    subscriber.unwrap_tagged_packet(msg.parse_header()?)?;
+
+    Ok(())
+}
+
+#[test]
+fn run_strict_sequencing() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(strict_sequencing(&mut transport)).is_ok());
+}
+
+fn streaming_unwrap<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let mut author = AuthorBuilder::new("AUTHOR9SEED").mss_height(1).build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+
+    let announcement_link = {
+        let msg = author.announce()?;
+        transport.send_message(&msg)?;
+        msg.link
+    };
+    {
+        let msg = transport.recv_message(&announcement_link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+    }
+
+    let public_payload = Trytes(Tbits::from_str("FIRMWARE9VERSION9TWO").unwrap());
+    let masked_payload = Trytes(Tbits::from_str(&"FIRMWARE9IMAGE".repeat(50)).unwrap());
+    let msg = author.tag_packet(&announcement_link, &public_payload, &masked_payload)?;
+    transport.send_message(&msg)?;
+
+    let mut chunks = Vec::new();
+    let unwrapped_public = subscriber.unwrap_tagged_packet_streaming(msg.parse_header()?, 64, |chunk| {
+        chunks.push(chunk.0.clone());
+        Ok(())
+    })?;
+    ensure!(public_payload == unwrapped_public);
+    ensure!(chunks.len() == 11 && chunks.iter().all(|chunk| chunk.size() <= 3 * 64));
+    let chunks: Vec<_> = chunks.iter().map(|chunk| chunk.slice()).collect();
+    ensure!(Tbits::from_slices(&chunks) == masked_payload.0);
+
+    // Corrupted MAC is reported after all chunks are passed.
+    let mut corrupted = msg.clone();
+    let pos = corrupted.body.size() - 1;
+    let t = corrupted.body.slice().drop(pos).take(1).get_isize();
+    corrupted.body.slice_mut().drop(pos).take(1).put_isize(if t == 1 { -1 } else { t + 1 });
+    let mut n = 0;
+    let result = subscriber.unwrap_tagged_packet_streaming(corrupted.parse_header()?, 64, |_| {
+        n += 1;
+        Ok(())
+    });
+    ensure!(result.is_err() && n == 11);
+
+    // Callback errors abort unwrapping.
+    let result = subscriber.unwrap_tagged_packet_streaming(msg.parse_header()?, 64, |_| bail!("Flash is full."));
+    ensure!(result.is_err());
+    Ok(())
+}
+
+#[test]
+fn run_streaming_unwrap() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(streaming_unwrap(&mut transport)).is_ok());
+}
+
+fn time_provider<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let clock = ManualTimeProvider::new(Duration::from_secs(1_000_000));
+    let mut author = AuthorBuilder::new("AUTHOR9SEED")
+        .mss_height(2)
+        .time_provider(Arc::new(clock.clone()))
+        .build();
+    let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED")
+        .ntru(false)
+        .rate_policy(RatePolicy::new(1, Duration::from_secs(60)))
+        .time_provider(Arc::new(clock.clone()))
+        .build();
+
+    let announcement = author.announce()?;
+    transport.send_message(&announcement)?;
+    let msg = transport.recv_message(&announcement.link)?;
+    subscriber.unwrap_announcement(msg.parse_header()?)?;
+
+    // Heartbeats are timestamped and checked with the clock.
+    let msg = author.heartbeat(&announcement.link)?;
+    let heartbeat = subscriber.unwrap_heartbeat(msg.parse_header()?)?;
+    ensure!(heartbeat.timestamp == 1_000_000);
+    ensure!(subscriber.author_is_live(Duration::from_secs(60)));
+    clock.advance(Duration::from_secs(61));
+    ensure!(!subscriber.author_is_live(Duration::from_secs(60)));
+
+    // Keyload age is measured with the clock.
+    author.share_keyload_for_everyone(&announcement.link)?;
+    clock.advance(Duration::from_secs(30));
+    ensure!(author.report().last_keyload_age == Some(Duration::from_secs(30)));
+
+    // Rate window is measured with the clock.
+    let payload = Trytes(Tbits::from_str("PAYLOAD").unwrap());
+    for _ in 0..2 {
+        let msg = author.tag_packet(&announcement.link, &payload, &Trytes::default())?;
+        subscriber.unwrap_tagged_packet(msg.parse_header()?)?;
+    }
+    ensure!(subscriber.rate_violations().len() == 1);
+    clock.advance(Duration::from_secs(60));
+    let msg = author.tag_packet(&announcement.link, &payload, &Trytes::default())?;
+    subscriber.unwrap_tagged_packet(msg.parse_header()?)?;
+    ensure!(subscriber.rate_violations().len() == 1);
+
+    // Cover traffic is due once the interval has passed on the clock.
+    author.set_cover_traffic(Some(CoverTraffic {
+        interval: Duration::from_secs(60),
+        payload_size: 27,
+    }));
+    ensure!(author.poll_cover_traffic(&announcement.link)?.is_none());
+    clock.advance(Duration::from_secs(60));
+    ensure!(author.poll_cover_traffic(&announcement.link)?.is_some());
+    Ok(())
+}
+
+#[test]
+fn run_time_provider() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(time_provider(&mut transport)).is_ok());
+}
+
+fn subscription_approval<T: Transport>(transport: &mut T) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let clock = ManualTimeProvider::default();
+    let mut author = AuthorBuilder::new("AUTHOR9SEED")
+        .mss_height(1)
+        .subscription_approval(SubscriptionApproval::new(Duration::from_secs(60)))
+        .time_provider(Arc::new(clock.clone()))
+        .build();
+    let mut subscriberA = SubscriberBuilder::new("SUBSCRIBERA9SEED").build();
+    let mut subscriberB = SubscriberBuilder::new("SUBSCRIBERB9SEED").build();
+    let mut subscriberC = SubscriberBuilder::new("SUBSCRIBERC9SEED").build();
+
+    let announcement = author.announce()?;
+    transport.send_message(&announcement)?;
+    for subscriber in [&mut subscriberA, &mut subscriberB, &mut subscriberC].iter_mut() {
+        let msg = transport.recv_message(&announcement.link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+        let msg = subscriber.subscribe(&announcement.link)?;
+        author.unwrap_subscribe(msg.parse_header()?)?;
+        clock.advance(Duration::from_secs(10));
+    }
+
+    // Requests are queued, not subscribed.
+    let pkidA = subscriberA.ntru_public_key().unwrap().get_pkid();
+    let pkidB = subscriberB.ntru_public_key().unwrap().get_pkid();
+    let pkidC = subscriberC.ntru_public_key().unwrap().get_pkid();
+    let pending = author.pending_subscriptions();
+    ensure!(pending.len() == 3 && pending[0].ntru_pkid == pkidA && pending[2].received == Duration::from_secs(20));
+    ensure!(author.report().subscribers == 0);
+
+    author.approve_subscription(&pkidA)?;
+    author.deny_subscription(&pkidB)?;
+    ensure!(author.approve_subscription(&pkidB).is_err());
+    ensure!(author.pending_subscriptions().len() == 1);
+
+    let keyload = author.share_keyload_for_everyone(&announcement.link)?;
+    subscriberA.unwrap_keyload(keyload.parse_header()?)?;
+    ensure!(subscriberB.unwrap_keyload(keyload.parse_header()?).is_err());
+
+    // Undecided requests expire.
+    clock.advance(Duration::from_secs(60));
+    ensure!(author.pending_subscriptions().is_empty());
+    ensure!(author.approve_subscription(&pkidC).is_err());
+    ensure!(author.report().subscribers == 1);
+
+    // Requests are refused while the queue is full, a repeated request replaces the pending one.
+    author.set_subscription_approval(Some(SubscriptionApproval::new(Duration::from_secs(60)).with_max_pending(1)));
+    let msg = subscriberB.subscribe(&announcement.link)?;
+    author.unwrap_subscribe(msg.parse_header()?)?;
+    let mut subscriberD = SubscriberBuilder::new("SUBSCRIBERD9SEED").build();
+    subscriberD.unwrap_announcement(announcement.parse_header()?)?;
+    let refused = subscriberD.subscribe(&announcement.link)?;
+    let tag = author.message_tag(&refused.link).ok();
+    ensure!(author.unwrap_subscribe(refused.parse_header()?).is_err());
+    ensure!(author.message_tag(&refused.link).ok() == tag);
+    let msg = subscriberB.subscribe(&announcement.link)?;
+    author.unwrap_subscribe(msg.parse_header()?)?;
+    let pending = author.pending_subscriptions();
+    ensure!(pending.len() == 1 && pending[0].ntru_pkid == pkidB);
+
+    // Refused request is not committed and is handled once there is room.
+    author.deny_subscription(&pkidB)?;
+    author.unwrap_subscribe(refused.parse_header()?)?;
+    ensure!(author.pending_subscriptions()[0].ntru_pkid == subscriberD.ntru_public_key().unwrap().get_pkid());
+    Ok(())
+}
+
+#[test]
+fn run_subscription_approval() {
+    let mut transport = BucketTransport::new();
+    assert!(dbg!(subscription_approval(&mut transport)).is_ok());
+}
@@ -9,6 +9,8 @@
 //!         null empty = 0;
 //!         tryte ntrupk[3072] = 1;
 //!     }
+//!     absorb tryte link_version;
+//!     absorb tryte lite_mac;
 //!     commit;
 //!     squeeze external tryte tag[78];
 //!     mssig(tag) sig;
@@ -23,6 +25,12 @@
 //!
 //! * `ntrupk` -- channel owner's NTRU public key.
 //!
+//! * `link_version` -- version of link derivation used in the channel, eg. for Tangle:
+//! 0 -- msgids are derived with the channel PRP, 1 -- msgids are derived with Troika.
+//!
+//! * `lite_mac` -- MAC size of `LitePacket` messages in the channel: 0 -- lite packets are
+//! not allowed, 1, 2, 3 -- MAC is truncated to 8, 12, 16 bytes of security respectively.
+//!
 //! * `tag` -- hash-value to be signed.
 //!
 //! * `sig` -- signature of `tag` field produced with the MSS private key corresponding to `msspk`.
@@ -59,6 +67,8 @@ pub const TYPE: &str = "STREAMS9CHANNEL9ANNOUNCE";
 pub struct ContentWrap<'a, TW, F, P: mss::Parameters<TW>> {
     pub(crate) mss_sk: &'a mss::PrivateKey<TW, P>,
     pub(crate) ntru_pk: Option<&'a ntru::PublicKey<TW, F>>,
+    pub(crate) link_version: Trint3,
+    pub(crate) lite_mac: Trint3,
 }
 
 impl<'a, TW, F, P: mss::Parameters<TW>, Store> message::ContentWrap<TW, F, Store> for ContentWrap<'a, TW, F, P>
@@ -77,7 +87,9 @@ where
             oneof = Trint3(0);
             ctx.absorb(&oneof)?;
         }
-        ctx.mssig(self.mss_sk, MssHashSig)?;
+        ctx.absorb(&self.link_version)?
+            .absorb(&self.lite_mac)?
+            .mssig(self.mss_sk, MssHashSig)?;
         Ok(ctx)
     }
 
@@ -95,7 +107,9 @@ where
             oneof = Trint3(0);
             ctx.absorb(&oneof)?;
         }
-        ctx.mssig(self.mss_sk, MssHashSig)?;
+        ctx.absorb(&self.link_version)?
+            .absorb(&self.lite_mac)?
+            .mssig(self.mss_sk, MssHashSig)?;
         Ok(ctx)
     }
 }
@@ -103,6 +117,8 @@ where
 pub struct ContentUnwrap<TW, F, P> {
     pub(crate) mss_pk: mss::PublicKey<TW, P>,
     pub(crate) ntru_pk: Option<ntru::PublicKey<TW, F>>,
+    pub(crate) link_version: Trint3,
+    pub(crate) lite_mac: Trint3,
 }
 
 impl<TW, F, P> Default for ContentUnwrap<TW, F, P>
@@ -114,6 +130,8 @@ where
         Self {
             mss_pk: mss::PublicKey::<TW, P>::default(),
             ntru_pk: None,
+            link_version: Trint3(0),
+            lite_mac: Trint3(0),
         }
     }
 }
@@ -141,7 +159,9 @@ where
             }
             _ => bail!("Announce: bad oneof: {:?}", oneof),
         };
-        ctx.mssig(&self.mss_pk, MssHashSig)?;
+        ctx.absorb(&mut self.link_version)?
+            .absorb(&mut self.lite_mac)?
+            .mssig(&self.mss_pk, MssHashSig)?;
         Ok(ctx)
     }
 }
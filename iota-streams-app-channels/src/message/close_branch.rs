@@ -0,0 +1,123 @@
+//! `CloseBranch` message content. The message is the last one published by channel owner
+//! in a branch, Author refuses to publish to a closed branch and subscribers stop following it.
+//! The message is linked to the keyload starting the branch or to any message in it.
+//!
+//! ```pb3
+//! message CloseBranch {
+//!     join link msgid;
+//!     commit;
+//!     squeeze external tryte hash[78];
+//!     mssig(hash) sig;
+//! }
+//! ```
+//!
+//! # Fields
+//!
+//! * `msgid` -- link to a message in the branch being closed.
+//!
+//! * `hash` -- hash value to be signed.
+//!
+//! * `sig` -- message signature generated with one of channel owner's private key.
+
+use failure::Fallible;
+use iota_streams_app::message::{
+    self,
+    HasLink,
+};
+use iota_streams_core::{
+    sponge::prp::PRP,
+    tbits::{
+        trinary,
+        word::{
+            BasicTbitWord,
+            IntTbitWord,
+            SpongosTbitWord,
+        },
+    },
+};
+use iota_streams_core_mss::signature::mss;
+use iota_streams_protobuf3::{
+    command::*,
+    io,
+    types::*,
+};
+
+/// Type of `CloseBranch` message content.
+pub const TYPE: &str = "STREAMS9CHANNEL9CLOSEBRANCH";
+
+pub struct ContentWrap<'a, TW, F, P, Link>
+where
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: 'a,
+{
+    pub(crate) link: &'a <Link as HasLink>::Rel,
+    pub(crate) mss_sk: &'a mss::PrivateKey<TW, P>,
+    pub(crate) _phantom: std::marker::PhantomData<(F, Link)>,
+}
+
+impl<'a, TW, F, P, Link, Store> message::ContentWrap<TW, F, Store> for ContentWrap<'a, TW, F, P, Link>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: 'a + Eq + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn sizeof<'c>(&self, ctx: &'c mut sizeof::Context<TW, F>) -> Fallible<&'c mut sizeof::Context<TW, F>> {
+        let store = EmptyLinkStore::<TW, F, <Link as HasLink>::Rel, ()>::default();
+        ctx.join(&store, self.link)?.mssig(self.mss_sk, MssHashSig)?;
+        Ok(ctx)
+    }
+
+    fn wrap<'c, OS: io::OStream<TW>>(
+        &self,
+        store: &Store,
+        ctx: &'c mut wrap::Context<TW, F, OS>,
+    ) -> Fallible<&'c mut wrap::Context<TW, F, OS>> {
+        ctx.join(store, self.link)?.mssig(self.mss_sk, MssHashSig)?;
+        Ok(ctx)
+    }
+}
+
+pub struct ContentUnwrap<TW, F, P, Link: HasLink> {
+    pub(crate) link: <Link as HasLink>::Rel,
+    pub(crate) mss_pk: mss::PublicKey<TW, P>,
+    pub(crate) _phantom: std::marker::PhantomData<(F, Link)>,
+}
+
+impl<TW, F, P, Link> ContentUnwrap<TW, F, P, Link>
+where
+    TW: BasicTbitWord,
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+{
+    pub fn new() -> Self {
+        Self {
+            link: <<Link as HasLink>::Rel as Default>::default(),
+            mss_pk: mss::PublicKey::<TW, P>::default(),
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<TW, F, P, Link, Store> message::ContentUnwrap<TW, F, Store> for ContentUnwrap<TW, F, P, Link>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn unwrap<'c, IS: io::IStream<TW>>(
+        &mut self,
+        store: &Store,
+        ctx: &'c mut unwrap::Context<TW, F, IS>,
+    ) -> Fallible<&'c mut unwrap::Context<TW, F, IS>> {
+        ctx.join(store, &mut self.link)?.mssig(&mut self.mss_pk, MssHashSig)?;
+        Ok(ctx)
+    }
+}
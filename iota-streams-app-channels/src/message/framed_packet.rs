@@ -0,0 +1,211 @@
+//! `FramedPacket` message content. It's a variant of `TaggedPacket` for large payloads:
+//! the masked payload is split into frames and each frame is followed by its own tag,
+//! so a corrupted frame is detected as soon as it's unmasked and can be identified
+//! (eg. to request retransmission of the frame only) instead of failing the final MAC.
+//!
+//! ```pb3
+//! message FramedPacket {
+//!     join link msgid;
+//!     absorb trytes public_payload;
+//!     skip repeated {
+//!         mask trytes frame;
+//!         commit;
+//!         squeeze tryte tag[27];
+//!     }
+//!     commit;
+//!     squeeze tryte mac[81];
+//! }
+//! ```
+//!
+//! # Fields
+//!
+//! * `msgid` -- link to the base message.
+//!
+//! * `public_payload` -- public part of payload.
+//!
+//! * `frame` -- masked part of payload, all frames but the last one have the same size.
+//!
+//! * `tag` -- MAC of the message up to and including the frame.
+//!
+//! * `mac` -- MAC of the message.
+//!
+
+use failure::Fallible;
+use std::{
+    error,
+    fmt,
+};
+
+use iota_streams_app::message::{
+    self,
+    HasLink,
+};
+use iota_streams_core::{
+    sponge::{
+        prp::PRP,
+        spongos,
+    },
+    tbits::{
+        trinary,
+        word::{
+            BasicTbitWord,
+            SpongosTbitWord,
+        },
+        Tbits,
+    },
+};
+use iota_streams_protobuf3::{
+    command::*,
+    io,
+    types::*,
+};
+
+/// Type of `FramedPacket` message content.
+pub const TYPE: &str = "STREAMS9CHANNEL9FRAMEDPACKET";
+
+/// Size of frame tag in trits.
+pub const FRAME_TAG_SIZE: usize = 81;
+
+/// Error of unwrapping a frame with bad tag.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct FrameCorrupted {
+    /// Index of the first corrupted frame.
+    pub index: usize,
+}
+
+impl fmt::Display for FrameCorrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Integrity is violated, bad tag of frame {}.", self.index)
+    }
+}
+
+impl error::Error for FrameCorrupted {}
+
+/// Split payload into frames of `frame_size` trytes, the last frame may be shorter.
+/// Empty payload has no frames.
+pub fn split_frames<TW>(payload: &Trytes<TW>, frame_size: usize) -> Vec<Trytes<TW>>
+where
+    TW: BasicTbitWord,
+{
+    let mut frames = Vec::new();
+    let mut s = payload.0.slice();
+    while !s.is_empty() {
+        frames.push(Trytes(Tbits::from_slice(s.take_min(3 * frame_size))));
+        s = s.drop_min(3 * frame_size);
+    }
+    frames
+}
+
+pub struct ContentWrap<'a, TW, F, Link>
+where
+    Link: HasLink,
+    <Link as HasLink>::Rel: 'a,
+{
+    pub(crate) link: &'a <Link as HasLink>::Rel,
+    pub(crate) public_payload: &'a Trytes<TW>,
+    pub(crate) frames: Vec<Trytes<TW>>,
+    pub(crate) _phantom: std::marker::PhantomData<(F, Link)>,
+}
+
+impl<'a, TW, F, Link, Store> message::ContentWrap<TW, F, Store> for ContentWrap<'a, TW, F, Link>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: 'a + Eq + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn sizeof<'c>(&self, ctx: &'c mut sizeof::Context<TW, F>) -> Fallible<&'c mut sizeof::Context<TW, F>> {
+        let store = EmptyLinkStore::<TW, F, <Link as HasLink>::Rel, ()>::default();
+        let mac = Mac(spongos::Spongos::<TW, F>::MAC_SIZE);
+        ctx.join(&store, self.link)?
+            .absorb(self.public_payload)?
+            .skip(Size(self.frames.len()))?
+            .repeated(self.frames.iter(), |ctx, frame| {
+                ctx.mask(frame)?.commit()?.squeeze(&Mac(FRAME_TAG_SIZE))
+            })?
+            .commit()?
+            .squeeze(&mac)?;
+        Ok(ctx)
+    }
+
+    fn wrap<'c, OS: io::OStream<TW>>(
+        &self,
+        store: &Store,
+        ctx: &'c mut wrap::Context<TW, F, OS>,
+    ) -> Fallible<&'c mut wrap::Context<TW, F, OS>> {
+        let mac = Mac(spongos::Spongos::<TW, F>::MAC_SIZE);
+        ctx.join(store, self.link)?
+            .absorb(self.public_payload)?
+            .skip(Size(self.frames.len()))?
+            .repeated(self.frames.iter(), |ctx, frame| {
+                ctx.mask(frame)?.commit()?.squeeze(&Mac(FRAME_TAG_SIZE))
+            })?
+            .commit()?
+            .squeeze(&mac)?;
+        Ok(ctx)
+    }
+}
+
+pub struct ContentUnwrap<TW, F, Link: HasLink> {
+    pub(crate) link: <Link as HasLink>::Rel,
+    pub(crate) public_payload: Trytes<TW>,
+    pub(crate) frames: Vec<Trytes<TW>>,
+    pub(crate) _phantom: std::marker::PhantomData<(F, Link)>,
+}
+
+impl<TW, F, Link> ContentUnwrap<TW, F, Link>
+where
+    TW: BasicTbitWord,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+{
+    pub fn new() -> Self {
+        Self {
+            link: <<Link as HasLink>::Rel as Default>::default(),
+            public_payload: Trytes::<TW>::default(),
+            frames: Vec::new(),
+            _phantom: std::marker::PhantomData,
+        }
+    }
+
+    /// Masked payload reassembled from frames.
+    pub fn masked_payload(&self) -> Trytes<TW> {
+        let slices: Vec<_> = self.frames.iter().map(|frame| frame.0.slice()).collect();
+        Trytes(Tbits::from_slices(&slices))
+    }
+}
+
+impl<TW, F, Link, Store> message::ContentUnwrap<TW, F, Store> for ContentUnwrap<TW, F, Link>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn unwrap<'c, IS: io::IStream<TW>>(
+        &mut self,
+        store: &Store,
+        ctx: &'c mut unwrap::Context<TW, F, IS>,
+    ) -> Fallible<&'c mut unwrap::Context<TW, F, IS>> {
+        let mac = Mac(spongos::Spongos::<TW, F>::MAC_SIZE);
+        let mut repeated = Size(0);
+        ctx.join(store, &mut self.link)?
+            .absorb(&mut self.public_payload)?
+            .skip(&mut repeated)?
+            .repeated(repeated, |ctx| {
+                let mut frame = Trytes::default();
+                ctx.mask(&mut frame)?.commit()?;
+                let index = self.frames.len();
+                if ctx.squeeze(&Mac(FRAME_TAG_SIZE)).is_err() {
+                    return Err(FrameCorrupted { index }.into());
+                }
+                self.frames.push(frame);
+                Ok(ctx)
+            })?
+            .commit()?
+            .squeeze(&mac)?;
+        Ok(ctx)
+    }
+}
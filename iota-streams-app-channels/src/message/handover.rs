@@ -0,0 +1,214 @@
+//! `Handover` message content. This message is published by channel author in order
+//! to hand over the channel to a successor owning a new MSS key pair, eg. when the
+//! author's device is replaced. The message is linked to either `Announce`, `ChangeKey`
+//! or `Handover` message.
+//!
+//! Successor accepts the channel beforehand by signing `acceptance` hash with its MSS
+//! private key and passing the signature and its MSS public key to the author.
+//!
+//! ```pb3
+//! message Handover {
+//!     join link msgid;
+//!     absorb tryte msspk[81];
+//!     mssig(acceptance) sig_with_msspk;
+//!     commit;
+//!     squeeze external tryte hash[78];
+//!     mssig(hash) sig_with_linked_msspk;
+//! }
+//! message Acceptance {
+//!     absorb external tryte appinst[81];
+//!     absorb external tryte zero_msgid[27];
+//!     absorb external tryte msspk[81];
+//!     commit;
+//!     squeeze external tryte acceptance[78];
+//! }
+//! ```
+//!
+//! # Fields:
+//!
+//! * `msgid` -- link to the message containing a trusted MSS public key.
+//!
+//! * `msspk` -- successor's MSS public key.
+//!
+//! * `acceptance` -- hash of channel application instance and successor's MSS public key.
+//!
+//! * `sig_with_msspk` -- successor's signature of `acceptance`, it's produced independently
+//! of the message and is not absorbed.
+//!
+//! * `hash` -- message hash value to be signed.
+//!
+//! * `sig_with_linked_msspk` -- signature generated with the MSS private key
+//! corresponding to the *trusted* public key contained in the linked message.
+//!
+
+use failure::{
+    ensure,
+    Fallible,
+};
+use iota_streams_app::message::{
+    self,
+    HasLink,
+};
+use iota_streams_core::{
+    sponge::prp::PRP,
+    tbits::{
+        trinary,
+        word::{
+            BasicTbitWord,
+            IntTbitWord,
+            SpongosTbitWord,
+        },
+        Tbits,
+    },
+};
+use iota_streams_core_mss::signature::mss;
+use iota_streams_protobuf3::{
+    command::*,
+    io,
+    types::*,
+};
+
+/// Type of `Handover` message content.
+pub const TYPE: &str = "STREAMS9CHANNEL9HANDOVER";
+
+/// Hash of channel application instance and successor's MSS public key signed by successor.
+/// Only the base part of `appinst` link is bound, the relative part is set to default.
+pub fn acceptance<TW, F, P, Link>(appinst: &Link, mss_pk: &mss::PublicKey<TW, P>) -> Fallible<NTrytes<TW>>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW> + Default,
+    P: mss::Parameters<TW>,
+    Link: HasLink + AbsorbExternalFallback<TW, F>,
+    <Link as HasLink>::Rel: Default,
+{
+    let channel = Link::from_base_rel(appinst.base(), &<Link as HasLink>::Rel::default());
+    let mut hash = External(NTrytes::zero(P::HASH_SIZE));
+    wrap::Context::<TW, F, io::NoOStream>::new(io::NoOStream)
+        .absorb(External(Fallback(&channel)))?
+        .absorb(External(&NTrytes(mss_pk.tbits().clone())))?
+        .commit()?
+        .squeeze(&mut hash)?;
+    Ok(hash.0)
+}
+
+/// Successor's signature of `acceptance`, it's passed to the channel author.
+pub fn sign_acceptance<TW, F, P, Link>(appinst: &Link, mss_sk: &mut mss::PrivateKey<TW, P>) -> Fallible<Tbits<TW>>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW> + Default,
+    P: mss::Parameters<TW>,
+    Link: HasLink + AbsorbExternalFallback<TW, F>,
+    <Link as HasLink>::Rel: Default,
+{
+    ensure!(
+        mss_sk.private_keys_left() > 0,
+        "All WOTS private keys in MSS Merkle tree have been exhausted, nothing to sign hash with."
+    );
+    let hash = acceptance::<TW, F, P, Link>(appinst, mss_sk.public_key())?;
+    let sig = mss_sk.sign_tbits(&hash.0);
+    mss_sk.next();
+    Ok(sig)
+}
+
+pub struct ContentWrap<'a, TW, P, Link>
+where
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+{
+    pub(crate) link: &'a <Link as HasLink>::Rel,
+    pub(crate) mss_pk: &'a mss::PublicKey<TW, P>,
+    pub(crate) mss_sig: NTrytes<TW>,
+    pub(crate) mss_linked_sk: &'a mss::PrivateKey<TW, P>,
+    pub(crate) _phantom: std::marker::PhantomData<Link>,
+}
+
+impl<'a, TW, F, P, Link, Store> message::ContentWrap<TW, F, Store> for ContentWrap<'a, TW, P, Link>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: 'a + Eq + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn sizeof<'c>(&self, ctx: &'c mut sizeof::Context<TW, F>) -> Fallible<&'c mut sizeof::Context<TW, F>> {
+        // Store has no impact on wrapped size
+        let store = EmptyLinkStore::<TW, F, <Link as HasLink>::Rel, ()>::default();
+        let hash = External(Mac(P::HASH_SIZE));
+        ctx.join(&store, self.link)?
+            .absorb(self.mss_pk)?
+            .skip(&self.mss_sig)?
+            .commit()?
+            .squeeze(&hash)?
+            .mssig(self.mss_linked_sk, &hash)?;
+        Ok(ctx)
+    }
+
+    fn wrap<'c, OS: io::OStream<TW>>(
+        &self,
+        store: &Store,
+        ctx: &'c mut wrap::Context<TW, F, OS>,
+    ) -> Fallible<&'c mut wrap::Context<TW, F, OS>> {
+        let mut hash = External(NTrytes::zero(P::HASH_SIZE));
+        // Successor's signature is already computed, it's not absorbed just as `mssig` output.
+        ctx.join(store, self.link)?
+            .absorb(self.mss_pk)?
+            .skip(&self.mss_sig)?
+            .commit()?
+            .squeeze(&mut hash)?
+            .mssig(self.mss_linked_sk, &hash)?;
+        Ok(ctx)
+    }
+}
+
+pub struct ContentUnwrap<'a, TW, P, Link>
+where
+    Link: HasLink,
+{
+    pub(crate) link: <Link as HasLink>::Rel,
+    pub(crate) mss_pk: mss::PublicKey<TW, P>,
+    pub(crate) mss_linked_pk: &'a mss::PublicKey<TW, P>,
+    appinst: &'a Link,
+}
+
+impl<'a, TW, P, Link> ContentUnwrap<'a, TW, P, Link>
+where
+    TW: BasicTbitWord,
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: 'a + Default,
+{
+    pub fn new(appinst: &'a Link, mss_linked_pk: &'a mss::PublicKey<TW, P>) -> Self {
+        Self {
+            link: <<Link as HasLink>::Rel as Default>::default(),
+            mss_pk: mss::PublicKey::<TW, P>::default(),
+            mss_linked_pk: mss_linked_pk,
+            appinst: appinst,
+        }
+    }
+}
+
+impl<'a, TW, F, P, Link, Store> message::ContentUnwrap<TW, F, Store> for ContentUnwrap<'a, TW, P, Link>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW> + Default,
+    P: mss::Parameters<TW>,
+    Link: HasLink + AbsorbExternalFallback<TW, F>,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn unwrap<'c, IS: io::IStream<TW>>(
+        &mut self,
+        store: &Store,
+        ctx: &'c mut unwrap::Context<TW, F, IS>,
+    ) -> Fallible<&'c mut unwrap::Context<TW, F, IS>> {
+        let mut hash = External(NTrytes::zero(P::HASH_SIZE));
+        ctx.join(store, &mut self.link)?.absorb(&mut self.mss_pk)?;
+        let acceptance = External(acceptance::<TW, F, P, Link>(self.appinst, &self.mss_pk)?);
+        ctx.mssig(&self.mss_pk, &acceptance)?
+            .commit()?
+            .squeeze(&mut hash)?
+            .mssig(self.mss_linked_pk, &hash)?;
+        Ok(ctx)
+    }
+}
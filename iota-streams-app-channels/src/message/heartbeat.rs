@@ -0,0 +1,155 @@
+//! `Heartbeat` message content. The message is published periodically by channel owner,
+//! so that subscribers can tell a live channel from a dead one. The message may be linked
+//! to any other message in the channel, usually to `Announce`.
+//!
+//! ```pb3
+//! message Heartbeat {
+//!     join link msgid;
+//!     absorb size counter;
+//!     absorb size timestamp;
+//!     commit;
+//!     squeeze external tryte hash[78];
+//!     mssig(hash) sig;
+//! }
+//! ```
+//!
+//! # Fields
+//!
+//! * `msgid` -- link to the base message.
+//!
+//! * `counter` -- heartbeat number, it's incremented with each heartbeat.
+//!
+//! * `timestamp` -- time the heartbeat was published, in seconds since UNIX epoch.
+//!
+//! * `hash` -- hash value to be signed.
+//!
+//! * `sig` -- message signature generated with one of channel owner's private key.
+//!
+//! Note, each heartbeat takes up an MSS private key, Author's MSS height must be chosen
+//! accordingly.
+
+use failure::Fallible;
+use iota_streams_app::message::{
+    self,
+    HasLink,
+};
+use iota_streams_core::{
+    sponge::prp::PRP,
+    tbits::{
+        trinary,
+        word::{
+            BasicTbitWord,
+            IntTbitWord,
+            SpongosTbitWord,
+        },
+    },
+};
+use iota_streams_core_mss::signature::mss;
+use iota_streams_protobuf3::{
+    command::*,
+    io,
+    types::*,
+};
+
+/// Type of `Heartbeat` message content.
+pub const TYPE: &str = "STREAMS9CHANNEL9HEARTBEAT";
+
+/// Counter and timestamp of a heartbeat.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Heartbeat {
+    pub counter: usize,
+    /// Seconds since UNIX epoch.
+    pub timestamp: usize,
+}
+
+pub struct ContentWrap<'a, TW, F, P, Link>
+where
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: 'a,
+{
+    pub(crate) link: &'a <Link as HasLink>::Rel,
+    pub(crate) counter: Size,
+    pub(crate) timestamp: Size,
+    pub(crate) mss_sk: &'a mss::PrivateKey<TW, P>,
+    pub(crate) _phantom: std::marker::PhantomData<(F, Link)>,
+}
+
+impl<'a, TW, F, P, Link, Store> message::ContentWrap<TW, F, Store> for ContentWrap<'a, TW, F, P, Link>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: 'a + Eq + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn sizeof<'c>(&self, ctx: &'c mut sizeof::Context<TW, F>) -> Fallible<&'c mut sizeof::Context<TW, F>> {
+        let store = EmptyLinkStore::<TW, F, <Link as HasLink>::Rel, ()>::default();
+        ctx.join(&store, self.link)?
+            .absorb(self.counter)?
+            .absorb(self.timestamp)?
+            .mssig(self.mss_sk, MssHashSig)?;
+        Ok(ctx)
+    }
+
+    fn wrap<'c, OS: io::OStream<TW>>(
+        &self,
+        store: &Store,
+        ctx: &'c mut wrap::Context<TW, F, OS>,
+    ) -> Fallible<&'c mut wrap::Context<TW, F, OS>> {
+        ctx.join(store, self.link)?
+            .absorb(self.counter)?
+            .absorb(self.timestamp)?
+            .mssig(self.mss_sk, MssHashSig)?;
+        Ok(ctx)
+    }
+}
+
+pub struct ContentUnwrap<TW, F, P, Link: HasLink> {
+    pub(crate) link: <Link as HasLink>::Rel,
+    pub(crate) counter: Size,
+    pub(crate) timestamp: Size,
+    pub(crate) mss_pk: mss::PublicKey<TW, P>,
+    pub(crate) _phantom: std::marker::PhantomData<(F, Link)>,
+}
+
+impl<TW, F, P, Link> ContentUnwrap<TW, F, P, Link>
+where
+    TW: BasicTbitWord,
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+{
+    pub fn new() -> Self {
+        Self {
+            link: <<Link as HasLink>::Rel as Default>::default(),
+            counter: Size(0),
+            timestamp: Size(0),
+            mss_pk: mss::PublicKey::<TW, P>::default(),
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<TW, F, P, Link, Store> message::ContentUnwrap<TW, F, Store> for ContentUnwrap<TW, F, P, Link>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn unwrap<'c, IS: io::IStream<TW>>(
+        &mut self,
+        store: &Store,
+        ctx: &'c mut unwrap::Context<TW, F, IS>,
+    ) -> Fallible<&'c mut unwrap::Context<TW, F, IS>> {
+        ctx.join(store, &mut self.link)?
+            .absorb(&mut self.counter)?
+            .absorb(&mut self.timestamp)?
+            .mssig(&mut self.mss_pk, MssHashSig)?;
+        Ok(ctx)
+    }
+}
@@ -1,23 +1,33 @@
 //! `Keyload` message content. This message contains key information for the set of recipients.
 //! Recipients are identified either by pre-shared keys or by NTRU public key identifiers.
 //!
+//! Embargoed keyload additionally locks the session key with a reveal key published later
+//! in `RevealKey` message, it has `EMBARGOED_TYPE` content type and the `embargo` fields.
+//!
 //! ```pb3
 //! message Keyload {
 //!     join link msgid;
 //!     absorb tryte nonce[27];
+//!     absorb tryte key_commitment[27];
 //!     skip repeated {
 //!         fork;
 //!         mask tryte id[27];
 //!         absorb external tryte psk[81];
 //!         commit;
 //!         mask(key) tryte ekey[81];
+//!         mask tryte role;
 //!     }
 //!     skip repeated {
 //!         fork;
 //!         mask tryte id[27];
 //!         ntrukem(key) tryte ekey[3072];
+//!         mask tryte role;
 //!     }
 //!     absorb external tryte key[81];
+//!     embargo {
+//!         absorb tryte reveal_key_id[27];
+//!         absorb external tryte reveal_key[81];
+//!     }
 //!     commit;
 //! }
 //! fork {
@@ -33,14 +43,25 @@
 //! * `nonce` -- A nonce to be used with the key encapsulated in the keyload.
 //! A unique nonce allows for session keys to be reused.
 //!
+//! * `key_commitment` -- Hash of `nonce` and `key`, see `key_commitment` function.
+//! A recipient checks the key obtained from `ekey` against it, so that a key
+//! encrypted for a different session (eg. with a stale PSK) is reported as such
+//! rather than surfacing later as a corrupted message.
+//!
 //! * `id` -- Key (PSK or NTRU public key) identifier.
 //!
 //! * `psk` -- Pre-shared key known to the author and to a legit recipient.
 //!
 //! * `ekey` -- Masked session key; session key is either encrypted with spongos or with NTRU.
 //!
+//! * `role` -- Recipient's access role: 0 -- read-only, 1 -- read-write.
+//!
 //! * `key` -- Session key; a legit recipient gets it from `ekey`.
 //!
+//! * `reveal_key_id` -- Identifier of the reveal key, present in embargoed keyload only.
+//!
+//! * `reveal_key` -- Reveal key; it's kept secret by the author until `RevealKey` message.
+//!
 //! * `sig` -- Optional signature; allows to authenticate keyload.
 //!
 //! Notes:
@@ -50,13 +71,28 @@
 //!     and identities of the latter keyload will be protected with the key from the former.
 //! 2) Keyload is not authenticated (signed). It can later be implicitly authenticated
 //!     via `SignedPacket`.
+//! 3) Roles are enforced by recipients themselves. All recipients share the same session
+//!     key, so a MAC of `TaggedPacket` can't prove that it was produced by a writer.
+//! 4) The number of recipients is visible in `repeated` sizes and in the message size.
+//!     It can be hidden with `RecipientPadding`: the sections are filled up with dummy
+//!     recipients having random keys, their entries are indistinguishable from real ones.
+
+use failure::{
+    bail,
+    ensure,
+    Fallible,
+};
+use std::{
+    collections::HashSet,
+    hash,
+};
 
-use failure::Fallible;
 use iota_streams_app::message::{
     self,
     HasLink,
 };
 use iota_streams_core::{
+    heap::HeapSize,
     prng,
     psk,
     sponge::{
@@ -68,8 +104,10 @@ use iota_streams_core::{
         word::{
             BasicTbitWord,
             IntTbitWord,
+            RngTbitWord,
             SpongosTbitWord,
         },
+        Tbits,
     },
 };
 use iota_streams_core_ntru::key_encapsulation::ntru;
@@ -82,6 +120,203 @@ use iota_streams_protobuf3::{
 /// Type of `Keyload` message content.
 pub const TYPE: &str = "STREAMS9CHANNEL9KEYLOAD";
 
+/// Type of embargoed `Keyload` message content.
+pub const EMBARGOED_TYPE: &str = "STREAMS9CHANNEL9EMBARGOEDKEYLOAD";
+
+/// Size of session key commitment in trits.
+pub const KEY_COMMITMENT_SIZE: usize = 81;
+
+/// Session key commitment: hash of the keyload nonce and the session key.
+pub fn key_commitment<TW, F>(nonce: &NTrytes<TW>, key: &NTrytes<TW>) -> NTrytes<TW>
+where
+    TW: SpongosTbitWord,
+    F: PRP<TW> + Default,
+{
+    let mut s = spongos::Spongos::<TW, F>::init();
+    s.absorb(nonce.0.slice());
+    s.absorb(key.0.slice());
+    s.commit();
+    NTrytes(s.squeeze_tbits(KEY_COMMITMENT_SIZE))
+}
+
+/// Session key escrowed to NTRU public key `escrow_pk`: the keyload nonce followed by NTRU
+/// capsule of the key. Escrow is kept apart from keyload, recipients don't see it.
+pub fn escrow_key<TW, F, G>(
+    escrow_pk: &ntru::PublicKey<TW, F>,
+    prng: &prng::Prng<TW, G>,
+    nonce: &NTrytes<TW>,
+    key: &NTrytes<TW>,
+) -> Tbits<TW>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW> + Default,
+    G: PRP<TW> + Clone + Default,
+{
+    let mut s = spongos::Spongos::<TW, F>::init();
+    s.absorb(nonce.0.slice());
+    s.commit();
+    let mut capsule = Tbits::zero(ntru::EKEY_SIZE);
+    escrow_pk.encrypt_with_spongos(&mut s, prng, nonce.0.slice(), key.0.slice(), capsule.slice_mut());
+    let mut escrow = nonce.0.clone();
+    escrow += &capsule;
+    escrow
+}
+
+/// Decapsulate escrowed session key with NTRU private key `escrow_sk`, `None` is returned
+/// if the escrow is malformed or was made for another key.
+pub fn open_escrowed_key<TW, F>(escrow_sk: &ntru::PrivateKey<TW, F>, escrow: &Tbits<TW>) -> Option<NTrytes<TW>>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW> + Default,
+{
+    let nonce_size = spongos::Spongos::<TW, F>::NONCE_SIZE;
+    if escrow.size() != nonce_size + ntru::EKEY_SIZE {
+        return None;
+    }
+    let mut s = spongos::Spongos::<TW, F>::init();
+    s.absorb(escrow.slice().take(nonce_size));
+    s.commit();
+    let mut key = NTrytes::zero(spongos::Spongos::<TW, F>::KEY_SIZE);
+    if escrow_sk.decrypt_with_spongos(&mut s, escrow.slice().drop(nonce_size), key.0.slice_mut()) {
+        Some(key)
+    } else {
+        None
+    }
+}
+
+/// Access role of a keyload recipient.
+///
+/// Write access is enforced by the sender only: the session key is shared by all recipients,
+/// so a recipient can't tell packets of read-write recipients from those of read-only ones.
+/// A modified client of a read-only recipient can still publish packets to the branch.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Role {
+    /// Recipient can only unwrap messages linked to the keyload.
+    ReadOnly,
+    /// Recipient can also publish messages linked to the keyload.
+    ReadWrite,
+}
+
+impl Role {
+    pub(crate) fn to_trint3(self) -> Trint3 {
+        match self {
+            Role::ReadOnly => Trint3(0),
+            Role::ReadWrite => Trint3(1),
+        }
+    }
+
+    pub(crate) fn from_trint3(role: Trint3) -> Fallible<Self> {
+        match role {
+            Trint3(0) => Ok(Role::ReadOnly),
+            Trint3(1) => Ok(Role::ReadWrite),
+            _ => bail!("Keyload: bad role: {}", role),
+        }
+    }
+}
+
+impl HeapSize for Role {
+    fn heap_size(&self) -> usize {
+        0
+    }
+}
+
+/// Read-only keyload recipients, all the other recipients get `Role::ReadWrite`.
+#[derive(Clone)]
+pub struct Roles<TW> {
+    pub read_only_pskids: HashSet<psk::PskId<TW>>,
+    pub read_only_ntru_pkids: HashSet<ntru::Pkid<TW>>,
+}
+
+impl<TW> HeapSize for Roles<TW> {
+    fn heap_size(&self) -> usize {
+        self.read_only_pskids.heap_size() + self.read_only_ntru_pkids.heap_size()
+    }
+}
+
+impl<TW> Default for Roles<TW> {
+    fn default() -> Self {
+        Self {
+            read_only_pskids: HashSet::new(),
+            read_only_ntru_pkids: HashSet::new(),
+        }
+    }
+}
+
+impl<TW> Roles<TW>
+where
+    TW: BasicTbitWord,
+    TW::Tbit: hash::Hash,
+{
+    /// Role of a recipient identified by pre-shared key identifier.
+    pub fn psk_role(&self, pskid: &psk::PskId<TW>) -> Role {
+        if self.read_only_pskids.contains(pskid) {
+            Role::ReadOnly
+        } else {
+            Role::ReadWrite
+        }
+    }
+
+    /// Role of a recipient identified by NTRU public key.
+    pub fn ntru_role<F>(&self, ntru_pk: &ntru::PublicKey<TW, F>) -> Role {
+        if self.read_only_ntru_pkids.contains(&ntru_pk.get_pkid()) {
+            Role::ReadOnly
+        } else {
+            Role::ReadWrite
+        }
+    }
+
+    /// Set role of a recipient identified by pre-shared key identifier.
+    pub fn set_psk_role(&mut self, pskid: psk::PskId<TW>, role: Role) {
+        match role {
+            Role::ReadOnly => self.read_only_pskids.insert(pskid),
+            Role::ReadWrite => self.read_only_pskids.remove(&pskid),
+        };
+    }
+
+    /// Set role of a recipient identified by NTRU public key identifier.
+    pub fn set_ntru_role(&mut self, ntru_pkid: ntru::Pkid<TW>, role: Role) {
+        match role {
+            Role::ReadOnly => self.read_only_ntru_pkids.insert(ntru_pkid),
+            Role::ReadWrite => self.read_only_ntru_pkids.remove(&ntru_pkid),
+        };
+    }
+}
+
+/// Padding of keyload recipient sections with dummy recipients, PSK and NTRU sections
+/// are padded separately.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum RecipientPadding {
+    /// No dummy recipients.
+    None,
+    /// Pad each section to exactly `n` recipients, keyload fails if there are more recipients.
+    Fixed(usize),
+    /// Pad each section to a multiple of `n` recipients.
+    Bucket(usize),
+}
+
+impl Default for RecipientPadding {
+    fn default() -> Self {
+        RecipientPadding::None
+    }
+}
+
+impl RecipientPadding {
+    /// Number of entries in a section with `n` recipients.
+    pub fn padded_len(self, n: usize) -> Fallible<usize> {
+        match self {
+            RecipientPadding::None => Ok(n),
+            RecipientPadding::Fixed(m) => {
+                ensure!(n <= m, "Keyload has more recipients than padding allows: {} > {}.", n, m);
+                Ok(m)
+            }
+            RecipientPadding::Bucket(m) => {
+                ensure!(m != 0, "Keyload padding bucket size must not be zero.");
+                Ok((n + m - 1) / m * m)
+            }
+        }
+    }
+}
+
 pub struct ContentWrap<'a, TW, F, G, Link: HasLink, Psks, NtruPks> {
     pub(crate) link: &'a <Link as HasLink>::Rel,
     pub nonce: NTrytes<TW>,
@@ -89,14 +324,70 @@ pub struct ContentWrap<'a, TW, F, G, Link: HasLink, Psks, NtruPks> {
     pub(crate) psks: Psks,
     pub(crate) prng: &'a prng::Prng<TW, G>,
     pub(crate) ntru_pks: NtruPks,
+    /// Recipients' roles, everyone is a writer if not set.
+    pub(crate) roles: Option<&'a Roles<TW>>,
+    /// Reveal key id and reveal key for embargoed keyload.
+    pub(crate) embargo: Option<psk::IPsk<'a, TW>>,
+    /// Dummy recipients padding PSK section.
+    pub(crate) dummy_psks: Vec<(psk::PskId<TW>, psk::Psk<TW>)>,
+    /// Dummy recipients padding NTRU section.
+    pub(crate) dummy_ntru_pks: Vec<ntru::PublicKey<TW, F>>,
     pub(crate) _phantom: std::marker::PhantomData<(F, Link)>,
 }
 
+impl<'a, TW, F, G, Link: HasLink, Psks, NtruPks> ContentWrap<'a, TW, F, G, Link, Psks, NtruPks>
+where
+    TW: BasicTbitWord,
+    TW::Tbit: hash::Hash,
+{
+    fn psk_role(&self, pskid: &psk::PskId<TW>) -> Trint3 {
+        self.roles.map_or(Role::ReadWrite, |roles| roles.psk_role(pskid)).to_trint3()
+    }
+
+    fn ntru_role(&self, ntru_pk: &ntru::PublicKey<TW, F>) -> Trint3 {
+        self.roles.map_or(Role::ReadWrite, |roles| roles.ntru_role(ntru_pk)).to_trint3()
+    }
+
+    /// Public key ids of NTRU recipients, dummy recipients excluded.
+    pub(crate) fn ntru_pkids(&self) -> Vec<ntru::Pkid<TW>>
+    where
+        NtruPks: Clone + Iterator<Item = ntru::INtruPk<'a, TW, F>>,
+        F: 'a,
+    {
+        self.ntru_pks.clone().map(|ntru_pk| ntru_pk.get_pkid()).collect()
+    }
+}
+
+impl<'a, TW, F, G, Link: HasLink, Psks, NtruPks> ContentWrap<'a, TW, F, G, Link, Psks, NtruPks>
+where
+    TW: RngTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    G: PRP<TW> + Default,
+    Psks: ExactSizeIterator,
+    NtruPks: ExactSizeIterator,
+{
+    /// Add dummy recipients with random PSKs and ephemeral NTRU keys according to `padding`.
+    pub(crate) fn pad_recipients(&mut self, padding: RecipientPadding) -> Fallible<()> {
+        let n = padding.padded_len(self.psks.len())? - self.psks.len();
+        self.dummy_psks = (0..n)
+            .map(|_| (prng::random_key(psk::PSKID_SIZE), prng::random_key(psk::PSK_SIZE)))
+            .collect();
+        let n = padding.padded_len(self.ntru_pks.len())? - self.ntru_pks.len();
+        self.dummy_ntru_pks = (0..n)
+            .map(|_| {
+                let nonce = prng::random_nonce(spongos::Spongos::<TW, F>::NONCE_SIZE);
+                ntru::gen_keypair::<TW, F, G>(self.prng, nonce.slice()).1
+            })
+            .collect();
+        Ok(())
+    }
+}
+
 impl<'a, TW, F, G, Link, Store, Psks, NtruPks> message::ContentWrap<TW, F, Store>
     for ContentWrap<'a, TW, F, G, Link, Psks, NtruPks>
 where
     TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
-    F: 'a + PRP<TW> + Clone, // weird 'a constraint, but compiler requires it somehow?!
+    F: 'a + PRP<TW> + Clone + Default, // weird 'a constraint, but compiler requires it somehow?!
     G: PRP<TW> + Clone + Default,
     Link: HasLink,
     <Link as HasLink>::Rel: 'a + Eq + SkipFallback<TW, F>,
@@ -107,25 +398,46 @@ where
 {
     fn sizeof<'c>(&self, ctx: &'c mut sizeof::Context<TW, F>) -> Fallible<&'c mut sizeof::Context<TW, F>> {
         let store = EmptyLinkStore::<TW, F, <Link as HasLink>::Rel, ()>::default();
-        let repeated_psks = Size(self.psks.len());
-        let repeated_ntru_pks = Size(self.ntru_pks.len());
+        let repeated_psks = Size(self.psks.len() + self.dummy_psks.len());
+        let repeated_ntru_pks = Size(self.ntru_pks.len() + self.dummy_ntru_pks.len());
+        // Items are reborrowed with the lifetime of dummies.
+        let psks = self
+            .psks
+            .clone()
+            .map(|(pskid, psk)| -> psk::IPsk<TW> { (pskid, psk) })
+            .chain(self.dummy_psks.iter().map(|(pskid, psk)| (pskid, psk)));
+        let ntru_pks = self
+            .ntru_pks
+            .clone()
+            .map(|ntru_pk| -> ntru::INtruPk<TW, F> { ntru_pk })
+            .chain(self.dummy_ntru_pks.iter());
         ctx.join(&store, self.link)?
             .absorb(&self.nonce)?
+            .absorb(&key_commitment::<TW, F>(&self.nonce, &self.key))?
             .skip(repeated_psks)?
-            .repeated(self.psks.clone(), |ctx, (pskid, psk)| {
+            .repeated(psks, |ctx, (pskid, psk)| {
                 ctx.fork(|ctx| {
                     ctx.mask(&NTrytes(pskid.clone()))?
                         .absorb(External(&NTrytes(psk.clone())))?
                         .commit()?
-                        .mask(&self.key)
+                        .mask(&self.key)?
+                        .mask(self.psk_role(pskid))
                 })
             })?
             .skip(repeated_ntru_pks)?
-            .repeated(self.ntru_pks.clone(), |ctx, ntru_pk| {
-                ctx.fork(|ctx| ctx.mask(&NTrytes(ntru_pk.get_pkid().0))?.ntrukem(ntru_pk, &self.key))
+            .repeated(ntru_pks, |ctx, ntru_pk| {
+                ctx.fork(|ctx| {
+                    ctx.mask(&NTrytes(ntru_pk.get_pkid().0))?
+                        .ntrukem(ntru_pk, &self.key)?
+                        .mask(self.ntru_role(ntru_pk))
+                })
             })?
-            .absorb(External(&self.key))?
-            .commit()?;
+            .absorb(External(&self.key))?;
+        if let Some((reveal_key_id, reveal_key)) = self.embargo {
+            ctx.absorb(&NTrytes(reveal_key_id.clone()))?
+                .absorb(External(&NTrytes(reveal_key.clone())))?;
+        }
+        ctx.commit()?;
         Ok(ctx)
     }
 
@@ -134,28 +446,46 @@ where
         store: &Store,
         ctx: &'c mut wrap::Context<TW, F, OS>,
     ) -> Fallible<&'c mut wrap::Context<TW, F, OS>> {
-        let repeated_psks = Size(self.psks.len());
-        let repeated_ntru_pks = Size(self.ntru_pks.len());
+        let repeated_psks = Size(self.psks.len() + self.dummy_psks.len());
+        let repeated_ntru_pks = Size(self.ntru_pks.len() + self.dummy_ntru_pks.len());
+        // Items are reborrowed with the lifetime of dummies.
+        let psks = self
+            .psks
+            .clone()
+            .map(|(pskid, psk)| -> psk::IPsk<TW> { (pskid, psk) })
+            .chain(self.dummy_psks.iter().map(|(pskid, psk)| (pskid, psk)));
+        let ntru_pks = self
+            .ntru_pks
+            .clone()
+            .map(|ntru_pk| -> ntru::INtruPk<TW, F> { ntru_pk })
+            .chain(self.dummy_ntru_pks.iter());
         ctx.join(store, self.link)?
             .absorb(&self.nonce)?
+            .absorb(&key_commitment::<TW, F>(&self.nonce, &self.key))?
             .skip(repeated_psks)?
-            .repeated(self.psks.clone().into_iter(), |ctx, (pskid, psk)| {
+            .repeated(psks, |ctx, (pskid, psk)| {
                 ctx.fork(|ctx| {
                     ctx.mask(&NTrytes(pskid.clone()))?
                         .absorb(External(&NTrytes(psk.clone())))?
                         .commit()?
-                        .mask(&self.key)
+                        .mask(&self.key)?
+                        .mask(&self.psk_role(pskid))
                 })
             })?
             .skip(repeated_ntru_pks)?
-            .repeated(self.ntru_pks.clone().into_iter(), |ctx, ntru_pk| {
+            .repeated(ntru_pks, |ctx, ntru_pk| {
                 ctx.fork(|ctx| {
                     ctx.mask(&NTrytes(ntru_pk.get_pkid().0))?
-                        .ntrukem((ntru_pk, self.prng, &self.nonce.0), &self.key)
+                        .ntrukem((ntru_pk, self.prng, &self.nonce.0), &self.key)?
+                        .mask(&self.ntru_role(ntru_pk))
                 })
             })?
-            .absorb(External(&self.key))?
-            .commit()?;
+            .absorb(External(&self.key))?;
+        if let Some((reveal_key_id, reveal_key)) = self.embargo {
+            ctx.absorb(&NTrytes(reveal_key_id.clone()))?
+                .absorb(External(&NTrytes(reveal_key.clone())))?;
+        }
+        ctx.commit()?;
         Ok(ctx)
     }
 }
@@ -165,10 +495,17 @@ where
 pub struct ContentUnwrap<'a, TW, F, Link: HasLink, LookupArg: 'a, LookupPsk, LookupNtruSk> {
     pub link: <Link as HasLink>::Rel,
     pub nonce: NTrytes<TW>,
+    pub key_commitment: NTrytes<TW>,
     pub(crate) lookup_arg: &'a LookupArg,
     pub(crate) lookup_psk: LookupPsk,
     pub(crate) lookup_ntru_sk: LookupNtruSk,
     pub key: NTrytes<TW>,
+    pub role: Role,
+    /// Revealed keys, set for embargoed keyload only.
+    pub(crate) reveal_keys: Option<&'a psk::Psks<TW>>,
+    pub reveal_key_id: NTrytes<TW>,
+    /// Session key and role cached from a previous unwrap of the keyload.
+    pub(crate) cached_key: Option<(NTrytes<TW>, Role)>,
     _phantom: std::marker::PhantomData<(F, Link)>,
 }
 
@@ -187,20 +524,38 @@ where
         Self {
             link: <<Link as HasLink>::Rel as Default>::default(),
             nonce: NTrytes::zero(spongos::Spongos::<TW, F>::NONCE_SIZE),
+            key_commitment: NTrytes::zero(KEY_COMMITMENT_SIZE),
             lookup_arg,
             lookup_psk,
             lookup_ntru_sk,
             key: NTrytes::zero(spongos::Spongos::<TW, F>::KEY_SIZE),
+            role: Role::ReadOnly,
+            reveal_keys: None,
+            reveal_key_id: NTrytes::zero(psk::PSKID_SIZE),
+            cached_key: None,
             _phantom: std::marker::PhantomData,
         }
     }
+
+    /// Unwrap embargoed keyload, the reveal key is looked up in `reveal_keys`.
+    pub fn with_reveal_keys(mut self, reveal_keys: &'a psk::Psks<TW>) -> Self {
+        self.reveal_keys = Some(reveal_keys);
+        self
+    }
+
+    /// Use session key cached from a previous unwrap of the keyload, recipients' encrypted
+    /// keys are skipped rather than decrypted. The key is still checked against the commitment.
+    pub fn with_cached_key(mut self, key: NTrytes<TW>, role: Role) -> Self {
+        self.cached_key = Some((key, role));
+        self
+    }
 }
 
 impl<'a, TW, F, Link, Store, LookupArg, LookupPsk, LookupNtruSk> message::ContentUnwrap<TW, F, Store>
     for ContentUnwrap<'a, TW, F, Link, LookupArg, LookupPsk, LookupNtruSk>
 where
     TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
-    F: PRP<TW> + Clone,
+    F: PRP<TW> + Clone + Default,
     Link: HasLink,
     <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
     Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
@@ -217,10 +572,17 @@ where
         let mut repeated_ntru_pks = Size(0);
         let mut pskid = NTrytes::zero(psk::PSKID_SIZE);
         let mut ntru_pkid = NTrytes::zero(ntru::PKID_SIZE);
+        let mut role = Trint3(0);
         let mut key_found = false;
+        if let Some((key, cached_role)) = self.cached_key.take() {
+            self.key = key;
+            role = cached_role.to_trint3();
+            key_found = true;
+        }
 
         ctx.join(store, &mut self.link)?
             .absorb(&mut self.nonce)?
+            .absorb(&mut self.key_commitment)?
             .skip(&mut repeated_psks)?
             .repeated(repeated_psks, |ctx| {
                 if !key_found {
@@ -229,18 +591,19 @@ where
                         if let Some(psk) = (self.lookup_psk)(self.lookup_arg, &pskid.0) {
                             ctx.absorb(External(&NTrytes(psk.clone())))? //TODO: Get rid off clone()
                                 .commit()?
-                                .mask(&mut self.key)?;
+                                .mask(&mut self.key)?
+                                .mask(&mut role)?;
                             key_found = true;
                             Ok(ctx)
                         } else {
                             // Just drop the rest of the forked message so not to waste Spongos operations
-                            let n = Size(0 + 0 + spongos::Spongos::<TW, F>::KEY_SIZE);
+                            let n = Size(0 + 0 + spongos::Spongos::<TW, F>::KEY_SIZE + 3);
                             ctx.drop(n)
                         }
                     })
                 } else {
                     // Drop entire fork.
-                    let n = Size(psk::PSKID_SIZE + 0 + 0 + spongos::Spongos::<TW, F>::KEY_SIZE);
+                    let n = Size(psk::PSKID_SIZE + 0 + 0 + spongos::Spongos::<TW, F>::KEY_SIZE + 3);
                     ctx.drop(n)
                 }
             })?
@@ -250,24 +613,37 @@ where
                     ctx.fork(|ctx| {
                         ctx.mask(&mut ntru_pkid)?;
                         if let Some(ntru_sk) = (self.lookup_ntru_sk)(self.lookup_arg, ntru_pkid.0.as_ref()) {
-                            ctx.ntrukem(ntru_sk, &mut self.key)?;
+                            ctx.ntrukem(ntru_sk, &mut self.key)?.mask(&mut role)?;
                             key_found = true;
                             Ok(ctx)
                         } else {
                             // Just drop the rest of the forked message so not to waste Spongos operations
-                            let n = Size(ntru::EKEY_SIZE);
+                            let n = Size(ntru::EKEY_SIZE + 3);
                             ctx.drop(n)
                         }
                     })
                 } else {
                     // Drop entire fork.
-                    let n = Size(ntru::PKID_SIZE + ntru::EKEY_SIZE);
+                    let n = Size(ntru::PKID_SIZE + ntru::EKEY_SIZE + 3);
                     ctx.drop(n)
                 }
             })?
             .guard(key_found, "Key not found")?
-            .absorb(External(&self.key))?
-            .commit()?;
+            .guard(
+                key_commitment::<TW, F>(&self.nonce, &self.key).ct_eq(&self.key_commitment),
+                "Key doesn't match key commitment, it was encrypted for a different session",
+            )?
+            .absorb(External(&self.key))?;
+        // Role is garbage if the key is wrong, check it after the key commitment.
+        self.role = Role::from_trint3(role)?;
+        if let Some(reveal_keys) = self.reveal_keys {
+            ctx.absorb(&mut self.reveal_key_id)?;
+            match reveal_keys.get(&self.reveal_key_id.0) {
+                Some(reveal_key) => ctx.absorb(External(&NTrytes(reveal_key.clone())))?,
+                None => bail!("Keyload is embargoed, reveal key not found."),
+            };
+        }
+        ctx.commit()?;
         Ok(ctx)
     }
 }
@@ -0,0 +1,159 @@
+//! `KeyloadRequest` message content. This message is published by a subscriber who can't
+//! unwrap the current keyload and asks channel owner for a new one. It contains subscriber's
+//! NTRU public key identifier encrypted with a session key which in turn is encapsulated for
+//! channel owner using owner's NTRU public key.
+//!
+//! Channel owner decides whether the requester is approved (eg. it has subscribed earlier)
+//! and can publish a new keyload for the requester.
+//!
+//! ```pb3
+//! message KeyloadRequest {
+//!     join link msgid;
+//!     ntrukem(key) tryte ekey[3072];
+//!     commit;
+//!     mask tryte ntrupkid[27];
+//!     commit;
+//!     squeeze tryte mac[27];
+//! }
+//! ```
+//!
+//! # Fields:
+//!
+//! * `msgid` -- link to a message the subscriber could unwrap, usually `Announce`.
+//!
+//! * `ekey` -- session key encapsulated with channel owner's NTRU public key.
+//!
+//! * `ntrupkid` -- subscriber's NTRU public key identifier.
+//!
+//! * `mac` -- authentication tag.
+//!
+//! Note, the request is not signed, anyone knowing subscriber's NTRU public key identifier
+//! can request a keyload for the subscriber. Keyload is still encrypted for the subscriber only.
+
+use failure::Fallible;
+use iota_streams_app::message::{
+    self,
+    HasLink,
+};
+use iota_streams_core::{
+    prng,
+    sponge::{
+        prp::PRP,
+        spongos,
+    },
+    tbits::{
+        trinary,
+        word::{
+            BasicTbitWord,
+            SpongosTbitWord,
+        },
+    },
+};
+use iota_streams_core_ntru::key_encapsulation::ntru;
+use iota_streams_protobuf3::{
+    command::*,
+    io,
+    types::*,
+};
+
+/// Type of `KeyloadRequest` message content.
+pub const TYPE: &str = "STREAMS9CHANNEL9KEYLOADREQUEST";
+
+pub struct ContentWrap<'a, TW, F, G, Link: HasLink> {
+    pub(crate) link: &'a <Link as HasLink>::Rel,
+    pub nonce: NTrytes<TW>,
+    pub key: NTrytes<TW>,
+    pub(crate) subscriber_ntru_pkid: NTrytes<TW>,
+    pub(crate) author_ntru_pk: &'a ntru::PublicKey<TW, F>,
+    pub(crate) prng: &'a prng::Prng<TW, G>,
+    pub(crate) _phantom: std::marker::PhantomData<Link>,
+}
+
+impl<'a, TW, F, G, Link, Store> message::ContentWrap<TW, F, Store> for ContentWrap<'a, TW, F, G, Link>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    G: PRP<TW> + Clone + Default,
+    Link: HasLink,
+    <Link as HasLink>::Rel: 'a + Eq + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn sizeof<'c>(&self, ctx: &'c mut sizeof::Context<TW, F>) -> Fallible<&'c mut sizeof::Context<TW, F>> {
+        let store = EmptyLinkStore::<TW, F, <Link as HasLink>::Rel, ()>::default();
+        let mac = Mac(spongos::Spongos::<TW, F>::MAC_SIZE);
+        ctx.join(&store, self.link)?
+            .ntrukem(self.author_ntru_pk, &self.key)?
+            .commit()?
+            .mask(&self.subscriber_ntru_pkid)?
+            .commit()?
+            .squeeze(&mac)?;
+        Ok(ctx)
+    }
+
+    fn wrap<'c, OS: io::OStream<TW>>(
+        &self,
+        store: &Store,
+        ctx: &'c mut wrap::Context<TW, F, OS>,
+    ) -> Fallible<&'c mut wrap::Context<TW, F, OS>> {
+        let mac = Mac(spongos::Spongos::<TW, F>::MAC_SIZE);
+        ctx.join(store, self.link)?
+            .ntrukem((self.author_ntru_pk, self.prng, &self.nonce.0), &self.key)?
+            .commit()?
+            .mask(&self.subscriber_ntru_pkid)?
+            .commit()?
+            .squeeze(&mac)?;
+        Ok(ctx)
+    }
+}
+
+impl<'a, TW, F, G, Link: HasLink> message::FixedSize for ContentWrap<'a, TW, F, G, Link> {}
+
+pub struct ContentUnwrap<'a, TW, F, Link: HasLink> {
+    pub link: <Link as HasLink>::Rel,
+    pub key: NTrytes<TW>,
+    pub subscriber_ntru_pkid: NTrytes<TW>,
+    author_ntru_sk: &'a ntru::PrivateKey<TW, F>,
+    _phantom: std::marker::PhantomData<Link>,
+}
+
+impl<'a, TW, F, Link> ContentUnwrap<'a, TW, F, Link>
+where
+    TW: BasicTbitWord,
+    F: PRP<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+{
+    pub fn new(author_ntru_sk: &'a ntru::PrivateKey<TW, F>) -> Self {
+        Self {
+            link: <<Link as HasLink>::Rel as Default>::default(),
+            key: NTrytes::<TW>::zero(spongos::Spongos::<TW, F>::KEY_SIZE),
+            subscriber_ntru_pkid: NTrytes::<TW>::zero(ntru::PKID_SIZE),
+            author_ntru_sk,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<'a, TW, F, Link, Store> message::ContentUnwrap<TW, F, Store> for ContentUnwrap<'a, TW, F, Link>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn unwrap<'c, IS: io::IStream<TW>>(
+        &mut self,
+        store: &Store,
+        ctx: &'c mut unwrap::Context<TW, F, IS>,
+    ) -> Fallible<&'c mut unwrap::Context<TW, F, IS>> {
+        let mac = Mac(spongos::Spongos::<TW, F>::MAC_SIZE);
+        ctx.join(store, &mut self.link)?
+            .ntrukem(self.author_ntru_sk, &mut self.key)?
+            .commit()?
+            .mask(&mut self.subscriber_ntru_pkid)?
+            .commit()?
+            .squeeze(&mac)?;
+        Ok(ctx)
+    }
+}
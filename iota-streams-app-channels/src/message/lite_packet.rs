@@ -0,0 +1,177 @@
+//! `LitePacket` message content. It's a lightweight variant of `TaggedPacket` for channels
+//! where every trit counts, eg. a single branch of sensor readings. There's only masked
+//! payload and the MAC is truncated to the size announced by channel owner in `Announce`
+//! message, so that all parties agree on it. As tagged packets, lite packets must be
+//! linked to a secret spongos state and can be published by channel owner or by a recipient.
+//!
+//! ```pb3
+//! message LitePacket {
+//!     join link msgid;
+//!     mask trytes payload;
+//!     commit;
+//!     squeeze tryte mac[n];
+//! }
+//! ```
+//!
+//! # Fields
+//!
+//! * `msgid` -- link to the base message.
+//!
+//! * `payload` -- masked payload.
+//!
+//! * `mac` -- truncated MAC of the message, `n` is determined by `LiteMac`.
+//!
+
+use failure::{
+    bail,
+    Fallible,
+};
+use iota_streams_app::message::{
+    self,
+    HasLink,
+};
+use iota_streams_core::{
+    sponge::prp::PRP,
+    tbits::{
+        trinary,
+        word::{
+            BasicTbitWord,
+            SpongosTbitWord,
+        },
+    },
+};
+use iota_streams_protobuf3::{
+    command::*,
+    io,
+    types::*,
+};
+
+/// Type of `LitePacket` message content.
+pub const TYPE: &str = "STREAMS9CHANNEL9LITEPACKET";
+
+/// Size of lite packet MAC, named after the number of bytes of security it provides at least.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LiteMac {
+    /// 14 trytes.
+    Bytes8,
+    /// 21 trytes.
+    Bytes12,
+    /// 27 trytes, the size of `TaggedPacket` MAC.
+    Bytes16,
+}
+
+impl LiteMac {
+    /// MAC size in trits.
+    pub fn size(self) -> usize {
+        match self {
+            LiteMac::Bytes8 => 42,
+            LiteMac::Bytes12 => 63,
+            LiteMac::Bytes16 => 81,
+        }
+    }
+
+    /// Encode optional MAC size as announced: 0 -- lite packets are not allowed.
+    pub(crate) fn to_trint3(lite_mac: Option<Self>) -> Trint3 {
+        match lite_mac {
+            None => Trint3(0),
+            Some(LiteMac::Bytes8) => Trint3(1),
+            Some(LiteMac::Bytes12) => Trint3(2),
+            Some(LiteMac::Bytes16) => Trint3(3),
+        }
+    }
+
+    pub(crate) fn from_trint3(lite_mac: Trint3) -> Fallible<Option<Self>> {
+        match lite_mac {
+            Trint3(0) => Ok(None),
+            Trint3(1) => Ok(Some(LiteMac::Bytes8)),
+            Trint3(2) => Ok(Some(LiteMac::Bytes12)),
+            Trint3(3) => Ok(Some(LiteMac::Bytes16)),
+            _ => bail!("Bad lite packet MAC size: {}", lite_mac),
+        }
+    }
+}
+
+pub struct ContentWrap<'a, TW, F, Link>
+where
+    Link: HasLink,
+    <Link as HasLink>::Rel: 'a,
+{
+    pub(crate) link: &'a <Link as HasLink>::Rel,
+    pub(crate) payload: &'a Trytes<TW>,
+    pub(crate) mac: LiteMac,
+    pub(crate) _phantom: std::marker::PhantomData<(F, Link)>,
+}
+
+impl<'a, TW, F, Link, Store> message::ContentWrap<TW, F, Store> for ContentWrap<'a, TW, F, Link>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: 'a + Eq + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn sizeof<'c>(&self, ctx: &'c mut sizeof::Context<TW, F>) -> Fallible<&'c mut sizeof::Context<TW, F>> {
+        let store = EmptyLinkStore::<TW, F, <Link as HasLink>::Rel, ()>::default();
+        ctx.join(&store, self.link)?
+            .mask(self.payload)?
+            .commit()?
+            .squeeze(&Mac(self.mac.size()))?;
+        Ok(ctx)
+    }
+
+    fn wrap<'c, OS: io::OStream<TW>>(
+        &self,
+        store: &Store,
+        ctx: &'c mut wrap::Context<TW, F, OS>,
+    ) -> Fallible<&'c mut wrap::Context<TW, F, OS>> {
+        ctx.join(store, self.link)?
+            .mask(self.payload)?
+            .commit()?
+            .squeeze(&Mac(self.mac.size()))?;
+        Ok(ctx)
+    }
+}
+
+pub struct ContentUnwrap<TW, F, Link: HasLink> {
+    pub(crate) link: <Link as HasLink>::Rel,
+    pub(crate) payload: Trytes<TW>,
+    pub(crate) mac: LiteMac,
+    pub(crate) _phantom: std::marker::PhantomData<(F, Link)>,
+}
+
+impl<TW, F, Link> ContentUnwrap<TW, F, Link>
+where
+    TW: BasicTbitWord,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+{
+    pub fn new(mac: LiteMac) -> Self {
+        Self {
+            link: <<Link as HasLink>::Rel as Default>::default(),
+            payload: Trytes::<TW>::default(),
+            mac,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<TW, F, Link, Store> message::ContentUnwrap<TW, F, Store> for ContentUnwrap<TW, F, Link>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn unwrap<'c, IS: io::IStream<TW>>(
+        &mut self,
+        store: &Store,
+        ctx: &'c mut unwrap::Context<TW, F, IS>,
+    ) -> Fallible<&'c mut unwrap::Context<TW, F, IS>> {
+        ctx.join(store, &mut self.link)?
+            .mask(&mut self.payload)?
+            .commit()?
+            .squeeze(&Mac(self.mac.size()))?;
+        Ok(ctx)
+    }
+}
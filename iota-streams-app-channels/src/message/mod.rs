@@ -3,18 +3,63 @@
 /// Announce message.
 pub mod announce;
 
+/// BranchKey message.
+pub mod branch_key;
+
 /// ChangeKey message.
 pub mod change_key;
 
+/// CloseBranch message.
+pub mod close_branch;
+
+/// FramedPacket message.
+pub mod framed_packet;
+
+/// Handover message.
+pub mod handover;
+
+/// Heartbeat message.
+pub mod heartbeat;
+
 /// Keyload message.
 pub mod keyload;
 
+/// KeyloadRequest message.
+pub mod keyload_request;
+
+/// LitePacket message.
+pub mod lite_packet;
+
+/// MultiTaggedPacket message.
+pub mod multi_tagged_packet;
+
+/// Participants message.
+pub mod participants;
+
+/// Padding of masked payloads.
+pub mod payload_padding;
+
+/// Rekey message.
+pub mod rekey;
+
+/// RevealKey message.
+pub mod reveal_key;
+
 /// SignedPacket message.
 pub mod signed_packet;
 
+/// StructuredPacket message.
+pub mod structured_packet;
+
 /// TaggedPacket message.
 pub mod tagged_packet;
 
+/// ThresholdAnnounce message.
+pub mod threshold_announce;
+
+/// ThresholdKeyload message.
+pub mod threshold_keyload;
+
 /// Subscribe message.
 pub mod subscribe;
 
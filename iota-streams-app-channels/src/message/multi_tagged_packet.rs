@@ -0,0 +1,196 @@
+//! `MultiTaggedPacket` message content. The message may be linked to any other message
+//! in the channel. It contains both plain and masked payloads as `TaggedPacket`, but
+//! instead of a single MAC it carries a MAC per recipient group. Each group is identified
+//! by a pre-shared key, its MAC is keyed with the key. A group can verify the message
+//! without being able to forge MACs of other groups. The message is published by
+//! channel owner.
+//!
+//! ```pb3
+//! message MultiTaggedPacket {
+//!     join link msgid;
+//!     absorb trytes public_payload;
+//!     mask trytes masked_payload;
+//!     commit;
+//!     skip repeated {
+//!         absorb tryte pskid[27];
+//!         fork {
+//!             absorb external tryte psk[81];
+//!             commit;
+//!             squeeze tryte mac[81];
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! # Fields
+//!
+//! * `msgid` -- link to the base message.
+//!
+//! * `public_payload` -- public part of payload.
+//!
+//! * `masked_payload` -- masked part of payload.
+//!
+//! * `pskid` -- identifier of pre-shared key of the recipient group.
+//!
+//! * `psk` -- pre-shared key of the recipient group.
+//!
+//! * `mac` -- MAC of the message for the recipient group.
+//!
+//! Note, the payloads are masked with the joined spongos state, ie. all recipients of
+//! the linked keyload can read them, MACs only tell who can verify the message.
+//!
+
+use failure::{
+    ensure,
+    Fallible,
+};
+use iota_streams_app::message::{
+    self,
+    HasLink,
+};
+use iota_streams_core::{
+    psk,
+    sponge::{
+        prp::PRP,
+        spongos,
+    },
+    tbits::{
+        trinary,
+        word::{
+            BasicTbitWord,
+            SpongosTbitWord,
+        },
+    },
+};
+use iota_streams_protobuf3::{
+    command::*,
+    io,
+    types::*,
+};
+
+/// Type of `MultiTaggedPacket` message content.
+pub const TYPE: &str = "STREAMS9CHANNEL9MULTITAGGEDPACKET";
+
+pub struct ContentWrap<'a, TW, F, Link>
+where
+    Link: HasLink,
+    <Link as HasLink>::Rel: 'a,
+{
+    pub(crate) link: &'a <Link as HasLink>::Rel,
+    pub(crate) public_payload: &'a Trytes<TW>,
+    pub(crate) masked_payload: &'a Trytes<TW>,
+    pub(crate) psks: Vec<psk::IPsk<'a, TW>>,
+    pub(crate) _phantom: std::marker::PhantomData<(F, Link)>,
+}
+
+impl<'a, TW, F, Link, Store> message::ContentWrap<TW, F, Store> for ContentWrap<'a, TW, F, Link>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW> + Clone,
+    Link: HasLink,
+    <Link as HasLink>::Rel: 'a + Eq + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn sizeof<'c>(&self, ctx: &'c mut sizeof::Context<TW, F>) -> Fallible<&'c mut sizeof::Context<TW, F>> {
+        let store = EmptyLinkStore::<TW, F, <Link as HasLink>::Rel, ()>::default();
+        let mac = Mac(spongos::Spongos::<TW, F>::MAC_SIZE);
+        ctx.join(&store, self.link)?
+            .absorb(self.public_payload)?
+            .mask(self.masked_payload)?
+            .commit()?
+            .skip(Size(self.psks.len()))?
+            .repeated(self.psks.iter(), |ctx, (pskid, psk)| {
+                ctx.absorb(&NTrytes((*pskid).clone()))?
+                    .fork(|ctx| ctx.absorb(External(&NTrytes((*psk).clone())))?.commit()?.squeeze(&mac))
+            })?;
+        Ok(ctx)
+    }
+
+    fn wrap<'c, OS: io::OStream<TW>>(
+        &self,
+        store: &Store,
+        ctx: &'c mut wrap::Context<TW, F, OS>,
+    ) -> Fallible<&'c mut wrap::Context<TW, F, OS>> {
+        let mac = Mac(spongos::Spongos::<TW, F>::MAC_SIZE);
+        ctx.join(store, self.link)?
+            .absorb(self.public_payload)?
+            .mask(self.masked_payload)?
+            .commit()?
+            .skip(Size(self.psks.len()))?
+            .repeated(self.psks.iter(), |ctx, (pskid, psk)| {
+                ctx.absorb(&NTrytes((*pskid).clone()))?
+                    .fork(|ctx| ctx.absorb(External(&NTrytes((*psk).clone())))?.commit()?.squeeze(&mac))
+            })?;
+        Ok(ctx)
+    }
+}
+
+pub struct ContentUnwrap<'a, TW, F, Link: HasLink> {
+    pub(crate) link: <Link as HasLink>::Rel,
+    pub(crate) public_payload: Trytes<TW>,
+    pub(crate) masked_payload: Trytes<TW>,
+    /// Own pre-shared key, the MAC of its group is verified.
+    pub(crate) opt_psk: Option<psk::IPsk<'a, TW>>,
+    /// Identifiers of pre-shared keys of the groups the message is tagged for.
+    pub(crate) pskids: Vec<psk::PskId<TW>>,
+    pub(crate) _phantom: std::marker::PhantomData<(F, Link)>,
+}
+
+impl<'a, TW, F, Link> ContentUnwrap<'a, TW, F, Link>
+where
+    TW: BasicTbitWord,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+{
+    pub fn new(opt_psk: Option<psk::IPsk<'a, TW>>) -> Self {
+        Self {
+            link: <<Link as HasLink>::Rel as Default>::default(),
+            public_payload: Trytes::<TW>::default(),
+            masked_payload: Trytes::<TW>::default(),
+            opt_psk,
+            pskids: Vec::new(),
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<'a, TW, F, Link, Store> message::ContentUnwrap<TW, F, Store> for ContentUnwrap<'a, TW, F, Link>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW> + Clone,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn unwrap<'c, IS: io::IStream<TW>>(
+        &mut self,
+        store: &Store,
+        ctx: &'c mut unwrap::Context<TW, F, IS>,
+    ) -> Fallible<&'c mut unwrap::Context<TW, F, IS>> {
+        let mac = Mac(spongos::Spongos::<TW, F>::MAC_SIZE);
+        let mut repeated = Size(0);
+        let mut verified = false;
+        ctx.join(store, &mut self.link)?
+            .absorb(&mut self.public_payload)?
+            .mask(&mut self.masked_payload)?
+            .commit()?
+            .skip(&mut repeated)?
+            .repeated(repeated, |ctx| {
+                let mut pskid = NTrytes::zero(psk::PSKID_SIZE);
+                ctx.absorb(&mut pskid)?;
+                match self.opt_psk {
+                    Some((own_pskid, psk)) if *own_pskid == pskid.0 => {
+                        ctx.fork(|ctx| ctx.absorb(External(&NTrytes(psk.clone())))?.commit()?.squeeze(&mac))?;
+                        verified = true;
+                    }
+                    _ => {
+                        ctx.drop(Size(mac.0))?;
+                    }
+                }
+                self.pskids.push(pskid.0);
+                Ok(ctx)
+            })?;
+        ensure!(verified, "MultiTaggedPacket: no MAC for own pre-shared key.");
+        Ok(ctx)
+    }
+}
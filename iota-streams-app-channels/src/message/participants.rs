@@ -0,0 +1,166 @@
+//! `Participants` message content. The message is published by channel owner and lists
+//! NTRU public keys of the current subscribers together with their keyload roles.
+//! A late joiner, eg. a successor Author or a read replica, can bootstrap its list
+//! of subscribers from the latest snapshot instead of replaying every `Subscribe` message.
+//! The message may be linked to any other message in the channel, usually to `Keyload`
+//! so that the list is only readable by the keyload recipients.
+//!
+//! ```pb3
+//! message Participants {
+//!     join link msgid;
+//!     skip repeated {
+//!         mask tryte ntrupk[3072];
+//!         mask tryte role;
+//!     }
+//!     commit;
+//!     squeeze external tryte hash[78];
+//!     mssig(hash) sig;
+//! }
+//! ```
+//!
+//! # Fields
+//!
+//! * `msgid` -- link to the base message.
+//!
+//! * `ntrupk` -- subscriber's NTRU public key.
+//!
+//! * `role` -- subscriber's access role: 0 -- read-only, 1 -- read-write.
+//!
+//! * `hash` -- hash value to be signed.
+//!
+//! * `sig` -- message signature generated with one of channel owner's private key.
+//!
+//! Note, subscribers identified by pre-shared keys are not listed as the keys are secret.
+
+use failure::Fallible;
+use iota_streams_app::message::{
+    self,
+    HasLink,
+};
+use iota_streams_core::{
+    sponge::prp::PRP,
+    tbits::{
+        trinary,
+        word::{
+            BasicTbitWord,
+            IntTbitWord,
+            SpongosTbitWord,
+        },
+    },
+};
+use iota_streams_core_mss::signature::mss;
+use iota_streams_core_ntru::key_encapsulation::ntru;
+use iota_streams_protobuf3::{
+    command::*,
+    io,
+    types::*,
+};
+
+use super::keyload::{
+    Role,
+    Roles,
+};
+
+/// Type of `Participants` message content.
+pub const TYPE: &str = "STREAMS9CHANNEL9PARTICIPANTS";
+
+pub struct ContentWrap<'a, TW, F, P, Link>
+where
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: 'a,
+{
+    pub(crate) link: &'a <Link as HasLink>::Rel,
+    pub(crate) ntru_pks: &'a ntru::NtruPks<TW, F>,
+    pub(crate) roles: &'a Roles<TW>,
+    pub(crate) mss_sk: &'a mss::PrivateKey<TW, P>,
+    pub(crate) _phantom: std::marker::PhantomData<Link>,
+}
+
+impl<'a, TW, F, P, Link, Store> message::ContentWrap<TW, F, Store> for ContentWrap<'a, TW, F, P, Link>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: 'a + Eq + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn sizeof<'c>(&self, ctx: &'c mut sizeof::Context<TW, F>) -> Fallible<&'c mut sizeof::Context<TW, F>> {
+        let store = EmptyLinkStore::<TW, F, <Link as HasLink>::Rel, ()>::default();
+        ctx.join(&store, self.link)?
+            .skip(Size(self.ntru_pks.len()))?
+            .repeated(self.ntru_pks.iter(), |ctx, ntru_pk| {
+                ctx.mask(ntru_pk)?.mask(self.roles.ntru_role(ntru_pk).to_trint3())
+            })?
+            .mssig(self.mss_sk, MssHashSig)?;
+        Ok(ctx)
+    }
+
+    fn wrap<'c, OS: io::OStream<TW>>(
+        &self,
+        store: &Store,
+        ctx: &'c mut wrap::Context<TW, F, OS>,
+    ) -> Fallible<&'c mut wrap::Context<TW, F, OS>> {
+        ctx.join(store, self.link)?
+            .skip(Size(self.ntru_pks.len()))?
+            .repeated(self.ntru_pks.iter(), |ctx, ntru_pk| {
+                ctx.mask(ntru_pk)?.mask(&self.roles.ntru_role(ntru_pk).to_trint3())
+            })?
+            .mssig(self.mss_sk, MssHashSig)?;
+        Ok(ctx)
+    }
+}
+
+pub struct ContentUnwrap<TW, F, P, Link: HasLink> {
+    pub(crate) link: <Link as HasLink>::Rel,
+    pub(crate) ntru_pks: Vec<(ntru::PublicKey<TW, F>, Role)>,
+    pub(crate) mss_pk: mss::PublicKey<TW, P>,
+    pub(crate) _phantom: std::marker::PhantomData<Link>,
+}
+
+impl<TW, F, P, Link> ContentUnwrap<TW, F, P, Link>
+where
+    TW: BasicTbitWord,
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+{
+    pub fn new() -> Self {
+        Self {
+            link: <<Link as HasLink>::Rel as Default>::default(),
+            ntru_pks: Vec::new(),
+            mss_pk: mss::PublicKey::<TW, P>::default(),
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<TW, F, P, Link, Store> message::ContentUnwrap<TW, F, Store> for ContentUnwrap<TW, F, P, Link>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn unwrap<'c, IS: io::IStream<TW>>(
+        &mut self,
+        store: &Store,
+        ctx: &'c mut unwrap::Context<TW, F, IS>,
+    ) -> Fallible<&'c mut unwrap::Context<TW, F, IS>> {
+        let mut repeated = Size(0);
+        ctx.join(store, &mut self.link)?
+            .skip(&mut repeated)?
+            .repeated(repeated, |ctx| {
+                let mut ntru_pk = ntru::PublicKey::<TW, F>::default();
+                let mut role = Trint3(0);
+                ctx.mask(&mut ntru_pk)?.mask(&mut role)?;
+                self.ntru_pks.push((ntru_pk, Role::from_trint3(role)?));
+                Ok(ctx)
+            })?
+            .mssig(&mut self.mss_pk, MssHashSig)?;
+        Ok(ctx)
+    }
+}
@@ -0,0 +1,97 @@
+//! Padding of masked payloads of `SignedPacket` and `TaggedPacket` messages to size classes,
+//! so that message sizes leak less about the content.
+//!
+//! Padded payload is the payload followed by marker tryte `A` and filler trytes `9` up to
+//! the size class. The padding is masked along with the payload and stripped on unwrap,
+//! padded messages carry `PaddedPayload` header TLV extension.
+
+use failure::{
+    ensure,
+    format_err,
+    Fallible,
+};
+use std::str::FromStr;
+
+use iota_streams_app::message::header::TlvExtension;
+use iota_streams_core::tbits::{
+    word::StringTbitWord,
+    Tbits,
+};
+use iota_streams_protobuf3::types::Trytes;
+
+/// Size classes of masked payloads.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PayloadPadding {
+    /// Payloads are not padded.
+    None,
+    /// Pad to the next power of 2 trytes.
+    Pow2,
+    /// Pad to a multiple of `n` trytes.
+    Multiple(usize),
+}
+
+impl Default for PayloadPadding {
+    fn default() -> Self {
+        PayloadPadding::None
+    }
+}
+
+impl PayloadPadding {
+    /// Size in trytes of padded payload of `n` trytes, including the marker.
+    pub fn padded_len(self, n: usize) -> Fallible<usize> {
+        match self {
+            PayloadPadding::None => Ok(n),
+            PayloadPadding::Pow2 => Ok((n + 1).next_power_of_two()),
+            PayloadPadding::Multiple(m) => {
+                ensure!(m != 0, "Payload padding size must not be zero.");
+                Ok((n + 1 + m - 1) / m * m)
+            }
+        }
+    }
+
+    /// Padded `payload`, `None` if payloads are not padded.
+    pub fn pad<TW>(self, payload: &Trytes<TW>) -> Fallible<Option<Trytes<TW>>>
+    where
+        TW: StringTbitWord,
+    {
+        if self == PayloadPadding::None {
+            return Ok(None);
+        }
+        let mut s = (payload.0).to_string();
+        let padded_len = self.padded_len(s.len())?;
+        s.push('A');
+        s.extend(std::iter::repeat('9').take(padded_len - s.len()));
+        Ok(Some(Trytes(
+            Tbits::from_str(&s).map_err(|_| format_err!("Bad payload trytes."))?,
+        )))
+    }
+}
+
+/// Strip padding off a padded `payload`.
+pub fn unpad<TW>(payload: &Trytes<TW>) -> Fallible<Trytes<TW>>
+where
+    TW: StringTbitWord,
+{
+    let s = (payload.0).to_string();
+    let s = s.trim_end_matches('9');
+    ensure!(s.ends_with('A'), "Bad payload padding.");
+    Ok(Trytes(
+        Tbits::from_str(&s[..s.len() - 1]).map_err(|_| format_err!("Bad payload trytes."))?,
+    ))
+}
+
+/// Header TLV extension marking padded masked payload.
+pub struct PaddedPayload;
+
+impl<TW> TlvExtension<TW> for PaddedPayload
+where
+    TW: StringTbitWord,
+{
+    const TLV_TYPE: usize = 2;
+    fn to_value(&self) -> Trytes<TW> {
+        Trytes::default()
+    }
+    fn from_value(_value: &Trytes<TW>) -> Fallible<Self> {
+        Ok(PaddedPayload)
+    }
+}
@@ -0,0 +1,142 @@
+//! `Rekey` message content. The message refreshes the session key of a keyload without
+//! encapsulating it to each recipient again: the new key is masked with the spongos state
+//! of the joined keyload (or rekey), so only the recipients of the keyload can unwrap it.
+//! Rekeying the same recipient set therefore takes constant time regardless of its size.
+//!
+//! ```pb3
+//! message Rekey {
+//!     join link msgid;
+//!     absorb tryte nonce[27];
+//!     mask tryte key[81];
+//!     absorb external tryte key[81];
+//!     commit;
+//! }
+//! ```
+//!
+//! # Fields
+//!
+//! * `msgid` -- link to the keyload or rekey with the session key to be refreshed.
+//!
+//! * `nonce` -- A nonce to be used with the new session key.
+//!
+//! * `key` -- New session key.
+//!
+//! Note, recipients removed from the keyload can still unwrap its rekeys, a new keyload
+//! must be published to change the recipient set.
+
+use failure::Fallible;
+use iota_streams_app::message::{
+    self,
+    HasLink,
+};
+use iota_streams_core::{
+    sponge::{
+        prp::PRP,
+        spongos,
+    },
+    tbits::{
+        trinary,
+        word::{
+            BasicTbitWord,
+            IntTbitWord,
+            SpongosTbitWord,
+        },
+    },
+};
+use iota_streams_protobuf3::{
+    command::*,
+    io,
+    types::*,
+};
+
+/// Type of `Rekey` message content.
+pub const TYPE: &str = "STREAMS9CHANNEL9REKEY";
+
+pub struct ContentWrap<'a, TW, F, Link>
+where
+    Link: HasLink,
+    <Link as HasLink>::Rel: 'a,
+{
+    pub(crate) link: &'a <Link as HasLink>::Rel,
+    pub(crate) nonce: NTrytes<TW>,
+    pub(crate) key: NTrytes<TW>,
+    pub(crate) _phantom: std::marker::PhantomData<(F, Link)>,
+}
+
+impl<'a, TW, F, Link, Store> message::ContentWrap<TW, F, Store> for ContentWrap<'a, TW, F, Link>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: 'a + Eq + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn sizeof<'c>(&self, ctx: &'c mut sizeof::Context<TW, F>) -> Fallible<&'c mut sizeof::Context<TW, F>> {
+        let store = EmptyLinkStore::<TW, F, <Link as HasLink>::Rel, ()>::default();
+        ctx.join(&store, self.link)?
+            .absorb(&self.nonce)?
+            .mask(&self.key)?
+            .absorb(External(&self.key))?
+            .commit()?;
+        Ok(ctx)
+    }
+
+    fn wrap<'c, OS: io::OStream<TW>>(
+        &self,
+        store: &Store,
+        ctx: &'c mut wrap::Context<TW, F, OS>,
+    ) -> Fallible<&'c mut wrap::Context<TW, F, OS>> {
+        ctx.join(store, self.link)?
+            .absorb(&self.nonce)?
+            .mask(&self.key)?
+            .absorb(External(&self.key))?
+            .commit()?;
+        Ok(ctx)
+    }
+}
+
+pub struct ContentUnwrap<TW, F, Link: HasLink> {
+    pub(crate) link: <Link as HasLink>::Rel,
+    pub(crate) nonce: NTrytes<TW>,
+    pub(crate) key: NTrytes<TW>,
+    pub(crate) _phantom: std::marker::PhantomData<(F, Link)>,
+}
+
+impl<TW, F, Link> ContentUnwrap<TW, F, Link>
+where
+    TW: BasicTbitWord,
+    F: PRP<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+{
+    pub fn new() -> Self {
+        Self {
+            link: <<Link as HasLink>::Rel as Default>::default(),
+            nonce: NTrytes::zero(spongos::Spongos::<TW, F>::NONCE_SIZE),
+            key: NTrytes::zero(spongos::Spongos::<TW, F>::KEY_SIZE),
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<TW, F, Link, Store> message::ContentUnwrap<TW, F, Store> for ContentUnwrap<TW, F, Link>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn unwrap<'c, IS: io::IStream<TW>>(
+        &mut self,
+        store: &Store,
+        ctx: &'c mut unwrap::Context<TW, F, IS>,
+    ) -> Fallible<&'c mut unwrap::Context<TW, F, IS>> {
+        ctx.join(store, &mut self.link)?
+            .absorb(&mut self.nonce)?
+            .mask(&mut self.key)?
+            .absorb(External(&self.key))?
+            .commit()?;
+        Ok(ctx)
+    }
+}
@@ -0,0 +1,149 @@
+//! `RevealKey` message content. The message is published by channel owner in order to lift
+//! an embargo: it reveals the key embargoed keyloads were additionally locked with.
+//! Recipients of an embargoed keyload can unwrap it only after the reveal key is known.
+//! The message may be linked to any other message in the channel, usually to `Announce`.
+//!
+//! ```pb3
+//! message RevealKey {
+//!     join link msgid;
+//!     absorb tryte reveal_key_id[27];
+//!     absorb tryte reveal_key[81];
+//!     commit;
+//!     squeeze external tryte hash[78];
+//!     mssig(hash) sig;
+//! }
+//! ```
+//!
+//! # Fields
+//!
+//! * `msgid` -- link to the base message.
+//!
+//! * `reveal_key_id` -- identifier of the reveal key used in embargoed keyloads.
+//!
+//! * `reveal_key` -- the reveal key.
+//!
+//! * `hash` -- hash value to be signed.
+//!
+//! * `sig` -- message signature generated with one of channel owner's private key.
+//!
+//! Note, the reveal key is not masked when the message is linked to a public message.
+//! Only the recipients of embargoed keyloads can unwrap them once the key is revealed.
+
+use failure::Fallible;
+use iota_streams_app::message::{
+    self,
+    HasLink,
+};
+use iota_streams_core::{
+    psk,
+    sponge::prp::PRP,
+    tbits::{
+        trinary,
+        word::{
+            BasicTbitWord,
+            IntTbitWord,
+            SpongosTbitWord,
+        },
+    },
+};
+use iota_streams_core_mss::signature::mss;
+use iota_streams_protobuf3::{
+    command::*,
+    io,
+    types::*,
+};
+
+/// Type of `RevealKey` message content.
+pub const TYPE: &str = "STREAMS9CHANNEL9REVEALKEY";
+
+pub struct ContentWrap<'a, TW, F, P, Link>
+where
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: 'a,
+{
+    pub(crate) link: &'a <Link as HasLink>::Rel,
+    pub(crate) reveal_key_id: NTrytes<TW>,
+    pub(crate) reveal_key: NTrytes<TW>,
+    pub(crate) mss_sk: &'a mss::PrivateKey<TW, P>,
+    pub(crate) _phantom: std::marker::PhantomData<(F, Link)>,
+}
+
+impl<'a, TW, F, P, Link, Store> message::ContentWrap<TW, F, Store> for ContentWrap<'a, TW, F, P, Link>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: 'a + Eq + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn sizeof<'c>(&self, ctx: &'c mut sizeof::Context<TW, F>) -> Fallible<&'c mut sizeof::Context<TW, F>> {
+        let store = EmptyLinkStore::<TW, F, <Link as HasLink>::Rel, ()>::default();
+        ctx.join(&store, self.link)?
+            .absorb(&self.reveal_key_id)?
+            .absorb(&self.reveal_key)?
+            .mssig(self.mss_sk, MssHashSig)?;
+        Ok(ctx)
+    }
+
+    fn wrap<'c, OS: io::OStream<TW>>(
+        &self,
+        store: &Store,
+        ctx: &'c mut wrap::Context<TW, F, OS>,
+    ) -> Fallible<&'c mut wrap::Context<TW, F, OS>> {
+        ctx.join(store, self.link)?
+            .absorb(&self.reveal_key_id)?
+            .absorb(&self.reveal_key)?
+            .mssig(self.mss_sk, MssHashSig)?;
+        Ok(ctx)
+    }
+}
+
+pub struct ContentUnwrap<TW, F, P, Link: HasLink> {
+    pub(crate) link: <Link as HasLink>::Rel,
+    pub(crate) reveal_key_id: NTrytes<TW>,
+    pub(crate) reveal_key: NTrytes<TW>,
+    pub(crate) mss_pk: mss::PublicKey<TW, P>,
+    pub(crate) _phantom: std::marker::PhantomData<(F, Link)>,
+}
+
+impl<TW, F, P, Link> ContentUnwrap<TW, F, P, Link>
+where
+    TW: BasicTbitWord,
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+{
+    pub fn new() -> Self {
+        Self {
+            link: <<Link as HasLink>::Rel as Default>::default(),
+            reveal_key_id: NTrytes::zero(psk::PSKID_SIZE),
+            reveal_key: NTrytes::zero(psk::PSK_SIZE),
+            mss_pk: mss::PublicKey::<TW, P>::default(),
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<TW, F, P, Link, Store> message::ContentUnwrap<TW, F, Store> for ContentUnwrap<TW, F, P, Link>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn unwrap<'c, IS: io::IStream<TW>>(
+        &mut self,
+        store: &Store,
+        ctx: &'c mut unwrap::Context<TW, F, IS>,
+    ) -> Fallible<&'c mut unwrap::Context<TW, F, IS>> {
+        ctx.join(store, &mut self.link)?
+            .absorb(&mut self.reveal_key_id)?
+            .absorb(&mut self.reveal_key)?
+            .mssig(&mut self.mss_pk, MssHashSig)?;
+        Ok(ctx)
+    }
+}
@@ -6,6 +6,7 @@
 //! ```pb3
 //! message SignedPacket {
 //!     join link msgid;
+//!     absorb external trytes aad;
 //!     absorb trytes public_payload;
 //!     mask trytes masked_payload;
 //!     commit;
@@ -18,6 +19,9 @@
 //!
 //! * `msgid` -- link to the base message.
 //!
+//! * `aad` -- optional additional authenticated data, it is not transmitted and must
+//! be provided by recipients. When absent it is not absorbed.
+//!
 //! * `public_payload` -- public part of payload.
 //!
 //! * `masked_payload` -- masked part of payload.
@@ -62,6 +66,7 @@ where
     pub(crate) link: &'a <Link as HasLink>::Rel,
     pub(crate) public_payload: &'a Trytes<TW>,
     pub(crate) masked_payload: &'a Trytes<TW>,
+    pub(crate) aad: Option<&'a Trytes<TW>>,
     pub(crate) mss_sk: &'a mss::PrivateKey<TW, P>,
     pub(crate) _phantom: std::marker::PhantomData<(F, Link)>,
 }
@@ -77,8 +82,11 @@ where
 {
     fn sizeof<'c>(&self, ctx: &'c mut sizeof::Context<TW, F>) -> Fallible<&'c mut sizeof::Context<TW, F>> {
         let store = EmptyLinkStore::<TW, F, <Link as HasLink>::Rel, ()>::default();
-        ctx.join(&store, self.link)?
-            .absorb(self.public_payload)?
+        ctx.join(&store, self.link)?;
+        if let Some(aad) = self.aad {
+            ctx.absorb(External(aad))?;
+        }
+        ctx.absorb(self.public_payload)?
             .mask(self.masked_payload)?
             .mssig(self.mss_sk, MssHashSig)?;
         //TODO: Is both public and masked payloads are ok? Leave public only or masked only?
@@ -90,8 +98,11 @@ where
         store: &Store,
         ctx: &'c mut wrap::Context<TW, F, OS>,
     ) -> Fallible<&'c mut wrap::Context<TW, F, OS>> {
-        ctx.join(store, self.link)?
-            .absorb(self.public_payload)?
+        ctx.join(store, self.link)?;
+        if let Some(aad) = self.aad {
+            ctx.absorb(External(aad))?;
+        }
+        ctx.absorb(self.public_payload)?
             .mask(self.masked_payload)?
             .mssig(self.mss_sk, MssHashSig)?;
         Ok(ctx)
@@ -102,6 +113,7 @@ pub struct ContentUnwrap<TW, F, P, Link: HasLink> {
     pub(crate) link: <Link as HasLink>::Rel,
     pub(crate) public_payload: Trytes<TW>,
     pub(crate) masked_payload: Trytes<TW>,
+    pub(crate) aad: Option<Trytes<TW>>,
     pub(crate) mss_pk: mss::PublicKey<TW, P>,
     pub(crate) _phantom: std::marker::PhantomData<(F, Link)>,
 }
@@ -118,10 +130,17 @@ where
             link: <<Link as HasLink>::Rel as Default>::default(),
             public_payload: Trytes::<TW>::default(),
             masked_payload: Trytes::<TW>::default(),
+            aad: None,
             mss_pk: mss::PublicKey::<TW, P>::default(),
             _phantom: std::marker::PhantomData,
         }
     }
+
+    /// Additional authenticated data the message was wrapped with.
+    pub fn with_aad(mut self, aad: Trytes<TW>) -> Self {
+        self.aad = Some(aad);
+        self
+    }
 }
 
 impl<TW, F, P, Link, Store> message::ContentUnwrap<TW, F, Store> for ContentUnwrap<TW, F, P, Link>
@@ -138,8 +157,11 @@ where
         store: &Store,
         ctx: &'c mut unwrap::Context<TW, F, IS>,
     ) -> Fallible<&'c mut unwrap::Context<TW, F, IS>> {
-        ctx.join(store, &mut self.link)?
-            .absorb(&mut self.public_payload)?
+        ctx.join(store, &mut self.link)?;
+        if let Some(aad) = &self.aad {
+            ctx.absorb(External(aad))?;
+        }
+        ctx.absorb(&mut self.public_payload)?
             .mask(&mut self.masked_payload)?
             .mssig(&mut self.mss_pk, MssHashSig)?;
         Ok(ctx)
@@ -0,0 +1,320 @@
+//! `StructuredPacket` message content. The message may be linked to any other message
+//! in the channel. Its payload is a list of named fields, each field is disclosed to its
+//! own audience: public fields are encoded in plain, subscribers' fields are masked with
+//! the joined spongos state (ie. they are secret if the message is linked to a keyload)
+//! and pre-shared key fields (eg. for auditors) are masked with a key derived from the state
+//! and the pre-shared key. The message can only be signed and published by channel owner.
+//!
+//! ```pb3
+//! message StructuredPacket {
+//!     join link msgid;
+//!     skip repeated {
+//!         absorb trytes name;
+//!         absorb oneof audience {
+//!             null public = 0;
+//!             null subscribers = 1;
+//!             tryte pskid[27] = 2;
+//!         }
+//!         oneof {
+//!             absorb trytes public_value;
+//!             mask trytes subscribers_value;
+//!             {
+//!                 skip size n;
+//!                 fork {
+//!                     absorb external tryte psk[81];
+//!                     commit;
+//!                     mask tryte psk_value[n];
+//!                     commit;
+//!                     squeeze tryte mac[27];
+//!                 }
+//!             }
+//!         }
+//!     }
+//!     commit;
+//!     squeeze external tryte hash[78];
+//!     mssig(hash) sig;
+//! }
+//! ```
+//!
+//! # Fields
+//!
+//! * `msgid` -- link to the base message.
+//!
+//! * `name` -- field name.
+//!
+//! * `pskid` -- identifier of pre-shared key the field is masked with.
+//!
+//! * `public_value`, `subscribers_value`, `psk_value` -- field value.
+//!
+//! * `mac` -- authentication tag of the pre-shared key field.
+//!
+//! * `hash` -- hash value to be signed.
+//!
+//! * `sig` -- message signature generated with one of channel owner's private key.
+//!
+//! Pre-shared key fields are forked: readers without the key skip them, and the signature
+//! covers their size only, the value is authenticated with `mac`.
+
+use failure::{
+    bail,
+    Fallible,
+};
+use iota_streams_app::message::{
+    self,
+    HasLink,
+};
+use iota_streams_core::{
+    psk,
+    sponge::prp::PRP,
+    tbits::{
+        trinary,
+        word::{
+            BasicTbitWord,
+            IntTbitWord,
+            SpongosTbitWord,
+        },
+    },
+};
+use iota_streams_core_mss::signature::mss;
+use iota_streams_protobuf3::{
+    command::*,
+    io,
+    types::*,
+};
+
+/// Type of `StructuredPacket` message content.
+pub const TYPE: &str = "STREAMS9CHANNEL9STRUCTUREDPACKET";
+
+/// Size of pre-shared key field MAC in trits.
+pub const MAC_SIZE: usize = 81;
+
+/// Readers of a structured packet field.
+#[derive(Clone)]
+pub enum Audience<TW> {
+    /// Anyone, the value is not masked.
+    Public,
+    /// Those able to unwrap the message, ie. recipients of the linked keyload.
+    Subscribers,
+    /// Holders of the pre-shared key with the identifier.
+    Psk(psk::PskId<TW>),
+}
+
+impl<TW> Audience<TW> {
+    fn to_trint3(&self) -> Trint3 {
+        match self {
+            Audience::Public => Trint3(0),
+            Audience::Subscribers => Trint3(1),
+            Audience::Psk(_) => Trint3(2),
+        }
+    }
+}
+
+impl<TW> PartialEq for Audience<TW>
+where
+    TW: BasicTbitWord,
+{
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Audience::Public, Audience::Public) => true,
+            (Audience::Subscribers, Audience::Subscribers) => true,
+            (Audience::Psk(a), Audience::Psk(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl<TW> Eq for Audience<TW> where TW: BasicTbitWord {}
+
+/// Named field of a structured packet.
+#[derive(Clone)]
+pub struct Field<TW> {
+    pub name: Trytes<TW>,
+    pub audience: Audience<TW>,
+    /// Field value, or nothing in an unwrapped field not disclosed to the reader.
+    pub value: Option<Trytes<TW>>,
+}
+
+impl<TW> Field<TW> {
+    pub fn new(name: Trytes<TW>, audience: Audience<TW>, value: Trytes<TW>) -> Self {
+        Self {
+            name,
+            audience,
+            value: Some(value),
+        }
+    }
+}
+
+/// Field value, the field and the pre-shared key of its audience.
+pub(crate) type WrapField<'a, TW> = (&'a Trytes<TW>, &'a Field<TW>, Option<&'a psk::Psk<TW>>);
+
+pub struct ContentWrap<'a, TW, F, P, Link>
+where
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: 'a,
+{
+    pub(crate) link: &'a <Link as HasLink>::Rel,
+    pub(crate) fields: Vec<WrapField<'a, TW>>,
+    pub(crate) mss_sk: &'a mss::PrivateKey<TW, P>,
+    pub(crate) _phantom: std::marker::PhantomData<(F, Link)>,
+}
+
+impl<'a, TW, F, P, Link, Store> message::ContentWrap<TW, F, Store> for ContentWrap<'a, TW, F, P, Link>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW> + Clone,
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: 'a + Eq + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn sizeof<'c>(&self, ctx: &'c mut sizeof::Context<TW, F>) -> Fallible<&'c mut sizeof::Context<TW, F>> {
+        let store = EmptyLinkStore::<TW, F, <Link as HasLink>::Rel, ()>::default();
+        ctx.join(&store, self.link)?
+            .skip(Size(self.fields.len()))?
+            .repeated(self.fields.iter(), |ctx, (value, field, psk)| {
+                ctx.absorb(&field.name)?.absorb(field.audience.to_trint3())?;
+                match (&field.audience, psk) {
+                    (Audience::Public, _) => ctx.absorb(*value),
+                    (Audience::Subscribers, _) => ctx.mask(*value),
+                    (Audience::Psk(pskid), Some(psk)) => ctx
+                        .absorb(&NTrytes(pskid.clone()))?
+                        .skip(Size(value.0.size() / 3))?
+                        .fork(|ctx| {
+                            ctx.absorb(External(&NTrytes((*psk).clone())))?
+                                .commit()?
+                                .mask(&NTrytes(value.0.clone()))?
+                                .commit()?
+                                .squeeze(&Mac(MAC_SIZE))
+                        }),
+                    (Audience::Psk(_), None) => bail!("Pre-shared key of the field audience not found."),
+                }
+            })?
+            .mssig(self.mss_sk, MssHashSig)?;
+        Ok(ctx)
+    }
+
+    fn wrap<'c, OS: io::OStream<TW>>(
+        &self,
+        store: &Store,
+        ctx: &'c mut wrap::Context<TW, F, OS>,
+    ) -> Fallible<&'c mut wrap::Context<TW, F, OS>> {
+        ctx.join(store, self.link)?
+            .skip(Size(self.fields.len()))?
+            .repeated(self.fields.iter(), |ctx, (value, field, psk)| {
+                ctx.absorb(&field.name)?.absorb(&field.audience.to_trint3())?;
+                match (&field.audience, psk) {
+                    (Audience::Public, _) => ctx.absorb(*value),
+                    (Audience::Subscribers, _) => ctx.mask(*value),
+                    (Audience::Psk(pskid), Some(psk)) => ctx
+                        .absorb(&NTrytes(pskid.clone()))?
+                        .skip(Size(value.0.size() / 3))?
+                        .fork(|ctx| {
+                            ctx.absorb(External(&NTrytes((*psk).clone())))?
+                                .commit()?
+                                .mask(&NTrytes(value.0.clone()))?
+                                .commit()?
+                                .squeeze(&Mac(MAC_SIZE))
+                        }),
+                    (Audience::Psk(_), None) => bail!("Pre-shared key of the field audience not found."),
+                }
+            })?
+            .mssig(self.mss_sk, MssHashSig)?;
+        Ok(ctx)
+    }
+}
+
+pub struct ContentUnwrap<'a, TW, F, P, Link: HasLink> {
+    pub(crate) link: <Link as HasLink>::Rel,
+    pub(crate) fields: Vec<Field<TW>>,
+    /// Own pre-shared key used to unmask fields of its audience.
+    pub(crate) opt_psk: Option<psk::IPsk<'a, TW>>,
+    pub(crate) mss_pk: mss::PublicKey<TW, P>,
+    pub(crate) _phantom: std::marker::PhantomData<(F, Link)>,
+}
+
+impl<'a, TW, F, P, Link> ContentUnwrap<'a, TW, F, P, Link>
+where
+    TW: BasicTbitWord,
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+{
+    pub fn new(opt_psk: Option<psk::IPsk<'a, TW>>) -> Self {
+        Self {
+            link: <<Link as HasLink>::Rel as Default>::default(),
+            fields: Vec::new(),
+            opt_psk,
+            mss_pk: mss::PublicKey::<TW, P>::default(),
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<'a, TW, F, P, Link, Store> message::ContentUnwrap<TW, F, Store> for ContentUnwrap<'a, TW, F, P, Link>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW> + Clone,
+    P: mss::Parameters<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn unwrap<'c, IS: io::IStream<TW>>(
+        &mut self,
+        store: &Store,
+        ctx: &'c mut unwrap::Context<TW, F, IS>,
+    ) -> Fallible<&'c mut unwrap::Context<TW, F, IS>> {
+        let mut repeated = Size(0);
+        ctx.join(store, &mut self.link)?
+            .skip(&mut repeated)?
+            .repeated(repeated, |ctx| {
+                let mut name = Trytes::default();
+                let mut oneof = Trint3(-1);
+                ctx.absorb(&mut name)?.absorb(&mut oneof)?;
+                let mut value = Trytes::default();
+                let (audience, value) = match oneof {
+                    Trint3(0) => {
+                        ctx.absorb(&mut value)?;
+                        (Audience::Public, Some(value))
+                    }
+                    Trint3(1) => {
+                        ctx.mask(&mut value)?;
+                        (Audience::Subscribers, Some(value))
+                    }
+                    Trint3(2) => {
+                        let mut pskid = NTrytes::zero(psk::PSKID_SIZE);
+                        let mut n = Size(0);
+                        ctx.absorb(&mut pskid)?.skip(&mut n)?;
+                        match self.opt_psk {
+                            Some((own_pskid, psk)) if *own_pskid == pskid.0 => {
+                                ctx.check_size(n)?;
+                                let mut psk_value = NTrytes::zero(3 * n.0);
+                                ctx.fork(|ctx| {
+                                    ctx.absorb(External(&NTrytes(psk.clone())))?
+                                        .commit()?
+                                        .mask(&mut psk_value)?
+                                        .commit()?
+                                        .squeeze(&Mac(MAC_SIZE))
+                                })?;
+                                (Audience::Psk(pskid.0), Some(Trytes(psk_value.0)))
+                            }
+                            _ => {
+                                ctx.drop(Size(3 * n.0 + MAC_SIZE))?;
+                                (Audience::Psk(pskid.0), None)
+                            }
+                        }
+                    }
+                    _ => bail!("StructuredPacket: bad field audience: {}", oneof),
+                };
+                self.fields.push(Field {
+                    name,
+                    audience,
+                    value,
+                });
+                Ok(ctx)
+            })?
+            .mssig(&mut self.mss_pk, MssHashSig)?;
+        Ok(ctx)
+    }
+}
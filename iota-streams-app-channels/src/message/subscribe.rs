@@ -114,6 +114,8 @@ where
     }
 }
 
+impl<'a, TW, F, G, Link: HasLink> message::FixedSize for ContentWrap<'a, TW, F, G, Link> {}
+
 pub struct ContentUnwrap<'a, TW, F, Link: HasLink> {
     pub link: <Link as HasLink>::Rel,
     pub unsubscribe_key: NTrytes<TW>,
@@ -5,6 +5,7 @@
 //! ```pb3
 //! message TaggedPacket {
 //!     join link msgid;
+//!     absorb external trytes aad;
 //!     absorb trytes public_payload;
 //!     mask trytes masked_payload;
 //!     commit;
@@ -16,19 +17,35 @@
 //!
 //! * `msgid` -- link to the base message.
 //!
+//! * `aad` -- optional additional authenticated data, it is not transmitted and must
+//! be provided by recipients. When absent it is not absorbed.
+//!
 //! * `public_payload` -- public part of payload.
 //!
 //! * `masked_payload` -- masked part of payload.
 //!
 //! * `mac` -- MAC of the message.
 //!
+//! Padding packets used as cover traffic are tagged packets with `masked_payload`
+//! starting with `PADDING_MARKER` followed by random trytes. They can't be told from
+//! real packets without the session key, recipients drop them (see `is_padding`).
+//!
+//! Large masked payloads can be unwrapped with `StreamContentUnwrap`: chunks are passed
+//! to a callback as they are decrypted and the MAC is verified after the last chunk, so
+//! chunks must not be trusted before unwrapping succeeds.
+//!
 
-use failure::Fallible;
+use failure::{
+    ensure,
+    Fallible,
+};
+use std::str::FromStr;
 use iota_streams_app::message::{
     self,
     HasLink,
 };
 use iota_streams_core::{
+    prng,
     sponge::{
         prp::PRP,
         spongos,
@@ -37,8 +54,11 @@ use iota_streams_core::{
         trinary,
         word::{
             BasicTbitWord,
+            RngTbitWord,
             SpongosTbitWord,
+            StringTbitWord,
         },
+        Tbits,
     },
 };
 use iota_streams_protobuf3::{
@@ -50,6 +70,33 @@ use iota_streams_protobuf3::{
 /// Type of `TaggedPacket` message content.
 pub const TYPE: &str = "STREAMS9CHANNEL9TAGGEDPACKET";
 
+/// Prefix of masked payload of padding packets.
+pub const PADDING_MARKER: &str = "STREAMS9CHANNEL9PADDING";
+
+/// Masked payload of a padding packet of `size` trytes: the marker followed by random trytes.
+pub fn padding_payload<TW>(size: usize) -> Fallible<Trytes<TW>>
+where
+    TW: RngTbitWord + StringTbitWord + Copy,
+{
+    ensure!(
+        PADDING_MARKER.len() <= size,
+        "Padding payload must be at least {} trytes.",
+        PADDING_MARKER.len()
+    );
+    let marker = Tbits::<TW>::from_str(PADDING_MARKER).unwrap();
+    let random = prng::random_key::<TW>(3 * (size - PADDING_MARKER.len()));
+    Ok(Trytes(&marker + &random))
+}
+
+/// Check whether unwrapped masked payload is padding.
+pub fn is_padding<TW>(masked_payload: &Trytes<TW>) -> bool
+where
+    TW: StringTbitWord,
+{
+    let marker = Tbits::<TW>::from_str(PADDING_MARKER).unwrap();
+    masked_payload.0.size() >= marker.size() && masked_payload.0.slice().take(marker.size()) == marker.slice()
+}
+
 pub struct ContentWrap<'a, TW, F, Link>
 where
     Link: HasLink,
@@ -58,6 +105,7 @@ where
     pub(crate) link: &'a <Link as HasLink>::Rel,
     pub(crate) public_payload: &'a Trytes<TW>,
     pub(crate) masked_payload: &'a Trytes<TW>,
+    pub(crate) aad: Option<&'a Trytes<TW>>,
     pub(crate) _phantom: std::marker::PhantomData<(F, Link)>,
 }
 
@@ -72,8 +120,11 @@ where
     fn sizeof<'c>(&self, ctx: &'c mut sizeof::Context<TW, F>) -> Fallible<&'c mut sizeof::Context<TW, F>> {
         let store = EmptyLinkStore::<TW, F, <Link as HasLink>::Rel, ()>::default();
         let mac = Mac(spongos::Spongos::<TW, F>::MAC_SIZE);
-        ctx.join(&store, self.link)?
-            .absorb(self.public_payload)?
+        ctx.join(&store, self.link)?;
+        if let Some(aad) = self.aad {
+            ctx.absorb(External(aad))?;
+        }
+        ctx.absorb(self.public_payload)?
             .mask(self.masked_payload)?
             .commit()?
             .squeeze(&mac)?;
@@ -87,8 +138,11 @@ where
         ctx: &'c mut wrap::Context<TW, F, OS>,
     ) -> Fallible<&'c mut wrap::Context<TW, F, OS>> {
         let mac = Mac(spongos::Spongos::<TW, F>::MAC_SIZE);
-        ctx.join(store, self.link)?
-            .absorb(self.public_payload)?
+        ctx.join(store, self.link)?;
+        if let Some(aad) = self.aad {
+            ctx.absorb(External(aad))?;
+        }
+        ctx.absorb(self.public_payload)?
             .mask(self.masked_payload)?
             .commit()?
             .squeeze(&mac)?;
@@ -100,6 +154,7 @@ pub struct ContentUnwrap<TW, F, Link: HasLink> {
     pub(crate) link: <Link as HasLink>::Rel,
     pub(crate) public_payload: Trytes<TW>,
     pub(crate) masked_payload: Trytes<TW>,
+    pub(crate) aad: Option<Trytes<TW>>,
     pub(crate) _phantom: std::marker::PhantomData<(F, Link)>,
 }
 
@@ -114,9 +169,16 @@ where
             link: <<Link as HasLink>::Rel as Default>::default(),
             public_payload: Trytes::<TW>::default(),
             masked_payload: Trytes::<TW>::default(),
+            aad: None,
             _phantom: std::marker::PhantomData,
         }
     }
+
+    /// Additional authenticated data the message was wrapped with.
+    pub fn with_aad(mut self, aad: Trytes<TW>) -> Self {
+        self.aad = Some(aad);
+        self
+    }
 }
 
 impl<TW, F, Link, Store> message::ContentUnwrap<TW, F, Store> for ContentUnwrap<TW, F, Link>
@@ -133,11 +195,88 @@ where
         ctx: &'c mut unwrap::Context<TW, F, IS>,
     ) -> Fallible<&'c mut unwrap::Context<TW, F, IS>> {
         let mac = Mac(spongos::Spongos::<TW, F>::MAC_SIZE);
-        ctx.join(store, &mut self.link)?
-            .absorb(&mut self.public_payload)?
+        ctx.join(store, &mut self.link)?;
+        if let Some(aad) = &self.aad {
+            ctx.absorb(External(aad))?;
+        }
+        ctx.absorb(&mut self.public_payload)?
             .mask(&mut self.masked_payload)?
             .commit()?
             .squeeze(&mac)?;
         Ok(ctx)
     }
 }
+
+/// Unwrap of tagged packet passing masked payload to `on_chunk` in chunks of `chunk_size`
+/// trytes instead of returning it. The payload is not buffered, so its size is not limited.
+pub struct StreamContentUnwrap<'a, TW, F, Link: HasLink> {
+    pub(crate) link: <Link as HasLink>::Rel,
+    pub(crate) public_payload: Trytes<TW>,
+    /// Size of the masked payload in trytes.
+    pub(crate) masked_size: usize,
+    pub(crate) aad: Option<Trytes<TW>>,
+    pub(crate) chunk_size: usize,
+    pub(crate) on_chunk: &'a mut dyn FnMut(&Trytes<TW>) -> Fallible<()>,
+    pub(crate) _phantom: std::marker::PhantomData<(F, Link)>,
+}
+
+impl<'a, TW, F, Link> StreamContentUnwrap<'a, TW, F, Link>
+where
+    TW: BasicTbitWord,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+{
+    pub fn new(chunk_size: usize, on_chunk: &'a mut dyn FnMut(&Trytes<TW>) -> Fallible<()>) -> Self {
+        Self {
+            link: <<Link as HasLink>::Rel as Default>::default(),
+            public_payload: Trytes::<TW>::default(),
+            masked_size: 0,
+            aad: None,
+            chunk_size,
+            on_chunk,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+
+    /// Additional authenticated data the message was wrapped with.
+    pub fn with_aad(mut self, aad: Trytes<TW>) -> Self {
+        self.aad = Some(aad);
+        self
+    }
+}
+
+impl<'a, TW, F, Link, Store> message::ContentUnwrap<TW, F, Store> for StreamContentUnwrap<'a, TW, F, Link>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn unwrap<'c, IS: io::IStream<TW>>(
+        &mut self,
+        store: &Store,
+        ctx: &'c mut unwrap::Context<TW, F, IS>,
+    ) -> Fallible<&'c mut unwrap::Context<TW, F, IS>> {
+        ensure!(0 < self.chunk_size, "Chunk size must be positive.");
+        let mac = Mac(spongos::Spongos::<TW, F>::MAC_SIZE);
+        ctx.join(store, &mut self.link)?;
+        if let Some(aad) = &self.aad {
+            ctx.absorb(External(aad))?;
+        }
+        // Masked trytes are decrypted with the same spongos state whether in one go or in chunks.
+        let mut size = Size(0);
+        ctx.absorb(&mut self.public_payload)?.mask(&mut size)?;
+        self.masked_size = size.0;
+        let mut left = size.0;
+        while 0 < left {
+            let n = std::cmp::min(left, self.chunk_size);
+            let mut chunk = NTrytes(Tbits::zero(3 * n));
+            ctx.mask(&mut chunk)?;
+            (self.on_chunk)(&Trytes(chunk.0))?;
+            left -= n;
+        }
+        ctx.commit()?.squeeze(&mac)?;
+        Ok(ctx)
+    }
+}
@@ -0,0 +1,322 @@
+//! `ThresholdAnnounce` message content. This is the initial message of a channel governed by
+//! a set of Author MSS keys: announcements and keyloads of the channel must be signed with
+//! at least `threshold` keys of the set. The set is announced in the message which is itself
+//! signed by at least `threshold` keys.
+//!
+//! ```pb3
+//! message ThresholdAnnounce {
+//!     absorb size threshold;
+//!     absorb size n;
+//!     repeated n {
+//!         absorb tryte msspk[81];
+//!     }
+//!     absorb oneof {
+//!         null empty = 0;
+//!         tryte ntrupk[3072] = 1;
+//!     }
+//!     absorb tryte link_version;
+//!     absorb tryte lite_mac;
+//!     ThresholdSig sigs;
+//! }
+//!
+//! message ThresholdSig {
+//!     commit;
+//!     squeeze external tryte hash[78];
+//!     skip size k;
+//!     repeated k {
+//!         absorb size signer;
+//!         mssig(hash) sig;
+//!     }
+//! }
+//! ```
+//!
+//! # Fields
+//!
+//! * `threshold` -- the number of valid signatures required, `0 < threshold <= n`.
+//!
+//! * `msspk` -- MSS public keys of the Author set, the first one is the key the channel
+//! address is derived from.
+//!
+//! * `ntrupk`, `link_version`, `lite_mac` -- as in `Announce` message.
+//!
+//! * `signer` -- index of the signer's public key in the Author set.
+//!
+//! * `sig` -- signature of `hash` produced with the signer's MSS private key.
+//!
+//! Signatures are not absorbed, so invalid ones don't break the message: they are ignored
+//! and only valid signatures of distinct signers count towards the threshold.
+//!
+
+use failure::{
+    bail,
+    ensure,
+    Fallible,
+};
+
+use iota_streams_app::message;
+use iota_streams_core::{
+    heap::HeapSize,
+    sponge::prp::PRP,
+    tbits::{
+        trinary,
+        word::{
+            BasicTbitWord,
+            IntTbitWord,
+            SpongosTbitWord,
+        },
+    },
+};
+use iota_streams_core_mss::signature::mss;
+use iota_streams_core_ntru::key_encapsulation::ntru;
+use iota_streams_protobuf3::{
+    command::*,
+    io,
+    types::*,
+};
+
+/// Type of `ThresholdAnnounce` message content.
+pub const TYPE: &str = "STREAMS9CHANNEL9THRESHOLDANNOUNCE";
+
+/// Author MSS public keys governing a channel and the number of signatures required.
+pub struct AuthorSet<TW, P> {
+    pub threshold: usize,
+    pub mss_pks: Vec<mss::PublicKey<TW, P>>,
+}
+
+impl<TW, P> Clone for AuthorSet<TW, P>
+where
+    TW: BasicTbitWord,
+{
+    fn clone(&self) -> Self {
+        Self {
+            threshold: self.threshold,
+            mss_pks: self.mss_pks.clone(),
+        }
+    }
+}
+
+impl<TW, P> HeapSize for AuthorSet<TW, P> {
+    fn heap_size(&self) -> usize {
+        self.mss_pks.heap_size()
+    }
+}
+
+impl<TW, P> AuthorSet<TW, P>
+where
+    TW: BasicTbitWord,
+{
+    /// Check threshold and uniqueness of the keys.
+    pub fn new(threshold: usize, mss_pks: Vec<mss::PublicKey<TW, P>>) -> Fallible<Self> {
+        ensure!(
+            0 < threshold && threshold <= mss_pks.len(),
+            "Bad Author set threshold: {} of {}.",
+            threshold,
+            mss_pks.len()
+        );
+        for (i, mss_pk) in mss_pks.iter().enumerate() {
+            ensure!(!mss_pks[..i].contains(mss_pk), "Duplicate MSS public key in Author set.");
+        }
+        Ok(Self { threshold, mss_pks })
+    }
+
+    /// Index of `mss_pk` in the set.
+    pub fn index_of(&self, mss_pk: &mss::PublicKey<TW, P>) -> Option<usize> {
+        self.mss_pks.iter().position(|pk| pk == mss_pk)
+    }
+}
+
+/// Private keys signing a message with their indices in the Author set.
+pub type Signers<'a, TW, P> = Vec<(usize, &'a mss::PrivateKey<TW, P>)>;
+
+pub(crate) fn sizeof_sigs<'c, TW, F, P>(
+    ctx: &'c mut sizeof::Context<TW, F>,
+    signers: &Signers<TW, P>,
+) -> Fallible<&'c mut sizeof::Context<TW, F>>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    P: mss::Parameters<TW>,
+{
+    let hash = External(Mac(P::HASH_SIZE));
+    ctx.commit()?
+        .squeeze(&hash)?
+        .skip(Size(signers.len()))?
+        .repeated(signers.iter(), |ctx, (signer, mss_sk)| {
+            ctx.absorb(Size(*signer))?.mssig(*mss_sk, &hash)
+        })?;
+    Ok(ctx)
+}
+
+pub(crate) fn wrap_sigs<'c, TW, F, P, OS: io::OStream<TW>>(
+    ctx: &'c mut wrap::Context<TW, F, OS>,
+    signers: &Signers<TW, P>,
+) -> Fallible<&'c mut wrap::Context<TW, F, OS>>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    P: mss::Parameters<TW>,
+{
+    let mut hash = External(NTrytes::zero(P::HASH_SIZE));
+    ctx.commit()?
+        .squeeze(&mut hash)?
+        .skip(Size(signers.len()))?
+        .repeated(signers.iter(), |ctx, (signer, mss_sk)| {
+            ctx.absorb(Size(*signer))?.mssig(*mss_sk, &hash)
+        })?;
+    Ok(ctx)
+}
+
+/// Unwrap signatures and check that valid ones meet the threshold of `author_set`.
+/// Indices of the valid signers are returned.
+pub(crate) fn unwrap_sigs<'c, TW, F, P, IS: io::IStream<TW>>(
+    ctx: &'c mut unwrap::Context<TW, F, IS>,
+    author_set: &AuthorSet<TW, P>,
+) -> Fallible<(&'c mut unwrap::Context<TW, F, IS>, Vec<usize>)>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    P: mss::Parameters<TW>,
+{
+    let mut hash = External(NTrytes::zero(P::HASH_SIZE));
+    let mut count = Size(0);
+    ctx.commit()?.squeeze(&mut hash)?.skip(&mut count)?;
+    ensure!(
+        count.0 <= author_set.mss_pks.len(),
+        "More signatures than keys in Author set: {}.",
+        count.0
+    );
+    let mut signers = Vec::new();
+    ctx.repeated(count, |ctx| {
+        let mut signer = Size(0);
+        let mut apk = mss::PublicKey::<TW, P>::default();
+        ctx.absorb(&mut signer)?.mssig(&mut apk, &hash)?;
+        let valid = author_set.mss_pks.get(signer.0).map_or(false, |mss_pk| *mss_pk == apk);
+        if valid && !signers.contains(&signer.0) {
+            signers.push(signer.0);
+        }
+        Ok(ctx)
+    })?;
+    ensure!(
+        author_set.threshold <= signers.len(),
+        "Not enough valid Author signatures: {} of {} required.",
+        signers.len(),
+        author_set.threshold
+    );
+    Ok((ctx, signers))
+}
+
+pub struct ContentWrap<'a, TW, F, P: mss::Parameters<TW>> {
+    pub(crate) author_set: &'a AuthorSet<TW, P>,
+    pub(crate) signers: Signers<'a, TW, P>,
+    pub(crate) ntru_pk: Option<&'a ntru::PublicKey<TW, F>>,
+    pub(crate) link_version: Trint3,
+    pub(crate) lite_mac: Trint3,
+}
+
+impl<'a, TW, F, P, Store> message::ContentWrap<TW, F, Store> for ContentWrap<'a, TW, F, P>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    P: mss::Parameters<TW>,
+{
+    fn sizeof<'c>(&self, ctx: &'c mut sizeof::Context<TW, F>) -> Fallible<&'c mut sizeof::Context<TW, F>> {
+        ctx.absorb(Size(self.author_set.threshold))?
+            .absorb(Size(self.author_set.mss_pks.len()))?
+            .repeated(self.author_set.mss_pks.iter(), |ctx, mss_pk| ctx.absorb(mss_pk))?;
+        if let Some(ntru_pk) = self.ntru_pk {
+            ctx.absorb(Trint3(1))?.absorb(ntru_pk)?;
+        } else {
+            ctx.absorb(Trint3(0))?;
+        }
+        ctx.absorb(&self.link_version)?.absorb(&self.lite_mac)?;
+        sizeof_sigs(ctx, &self.signers)
+    }
+
+    fn wrap<'c, OS: io::OStream<TW>>(
+        &self,
+        _store: &Store,
+        ctx: &'c mut wrap::Context<TW, F, OS>,
+    ) -> Fallible<&'c mut wrap::Context<TW, F, OS>> {
+        ctx.absorb(Size(self.author_set.threshold))?
+            .absorb(Size(self.author_set.mss_pks.len()))?
+            .repeated(self.author_set.mss_pks.iter(), |ctx, mss_pk| ctx.absorb(mss_pk))?;
+        if let Some(ntru_pk) = self.ntru_pk {
+            ctx.absorb(Trint3(1))?.absorb(ntru_pk)?;
+        } else {
+            ctx.absorb(Trint3(0))?;
+        }
+        ctx.absorb(&self.link_version)?.absorb(&self.lite_mac)?;
+        wrap_sigs(ctx, &self.signers)
+    }
+}
+
+pub struct ContentUnwrap<TW, F, P> {
+    pub(crate) author_set: AuthorSet<TW, P>,
+    pub(crate) ntru_pk: Option<ntru::PublicKey<TW, F>>,
+    pub(crate) link_version: Trint3,
+    pub(crate) lite_mac: Trint3,
+    /// Indices of keys with valid signatures.
+    pub(crate) signers: Vec<usize>,
+}
+
+impl<TW, F, P> Default for ContentUnwrap<TW, F, P> {
+    fn default() -> Self {
+        Self {
+            author_set: AuthorSet {
+                threshold: 0,
+                mss_pks: Vec::new(),
+            },
+            ntru_pk: None,
+            link_version: Trint3(0),
+            lite_mac: Trint3(0),
+            signers: Vec::new(),
+        }
+    }
+}
+
+impl<TW, F, P, Store> message::ContentUnwrap<TW, F, Store> for ContentUnwrap<TW, F, P>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    P: mss::Parameters<TW>,
+{
+    fn unwrap<'c, IS: io::IStream<TW>>(
+        &mut self,
+        _store: &Store,
+        ctx: &'c mut unwrap::Context<TW, F, IS>,
+    ) -> Fallible<&'c mut unwrap::Context<TW, F, IS>> {
+        let mut threshold = Size(0);
+        let mut n = Size(0);
+        ctx.absorb(&mut threshold)?.absorb(&mut n)?;
+        ensure!(
+            0 < threshold.0 && threshold.0 <= n.0,
+            "ThresholdAnnounce: bad threshold: {} of {}.",
+            threshold.0,
+            n.0
+        );
+        let mut mss_pks = Vec::new();
+        ctx.repeated(n, |ctx| {
+            let mut mss_pk = mss::PublicKey::<TW, P>::default();
+            ctx.absorb(&mut mss_pk)?;
+            mss_pks.push(mss_pk);
+            Ok(ctx)
+        })?;
+        self.author_set = AuthorSet::new(threshold.0, mss_pks)?;
+
+        let mut oneof = Trint3(-1);
+        ctx.absorb(&mut oneof)?;
+        self.ntru_pk = match oneof {
+            Trint3(0) => None,
+            Trint3(1) => {
+                let mut ntru_pk = ntru::PublicKey::default();
+                ctx.absorb(&mut ntru_pk)?;
+                Some(ntru_pk)
+            }
+            _ => bail!("ThresholdAnnounce: bad oneof: {:?}", oneof),
+        };
+        ctx.absorb(&mut self.link_version)?.absorb(&mut self.lite_mac)?;
+        let (ctx, signers) = unwrap_sigs(ctx, &self.author_set)?;
+        self.signers = signers;
+        Ok(ctx)
+    }
+}
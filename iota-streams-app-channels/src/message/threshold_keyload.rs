@@ -0,0 +1,107 @@
+//! `ThresholdKeyload` message content. This is `Keyload` of a channel announced with
+//! `ThresholdAnnounce` message, it's signed by at least `threshold` keys of the Author set.
+//!
+//! ```pb3
+//! message ThresholdKeyload {
+//!     Keyload keyload;
+//!     ThresholdSig sigs;
+//! }
+//! ```
+//!
+//! # Fields
+//!
+//! * `keyload` -- keyload content, see `keyload` module.
+//!
+//! * `sigs` -- signatures of the Author set, see `threshold_announce` module.
+//!
+
+use failure::Fallible;
+
+use iota_streams_app::message;
+use iota_streams_core::{
+    sponge::prp::PRP,
+    tbits::{
+        trinary,
+        word::{
+            IntTbitWord,
+            SpongosTbitWord,
+        },
+    },
+};
+use iota_streams_core_mss::signature::mss;
+use iota_streams_protobuf3::{
+    command::*,
+    io,
+};
+
+use super::threshold_announce::{
+    self,
+    AuthorSet,
+    Signers,
+};
+
+/// Type of `ThresholdKeyload` message content.
+pub const TYPE: &str = "STREAMS9CHANNEL9THRESHOLDKEYLOAD";
+
+pub struct ContentWrap<'a, TW, P: mss::Parameters<TW>, Keyload> {
+    pub keyload: Keyload,
+    pub(crate) signers: Signers<'a, TW, P>,
+}
+
+impl<'a, TW, F, P, Keyload, Store> message::ContentWrap<TW, F, Store> for ContentWrap<'a, TW, P, Keyload>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    P: mss::Parameters<TW>,
+    Keyload: message::ContentWrap<TW, F, Store>,
+{
+    fn sizeof<'c>(&self, ctx: &'c mut sizeof::Context<TW, F>) -> Fallible<&'c mut sizeof::Context<TW, F>> {
+        let ctx = self.keyload.sizeof(ctx)?;
+        threshold_announce::sizeof_sigs(ctx, &self.signers)
+    }
+
+    fn wrap<'c, OS: io::OStream<TW>>(
+        &self,
+        store: &Store,
+        ctx: &'c mut wrap::Context<TW, F, OS>,
+    ) -> Fallible<&'c mut wrap::Context<TW, F, OS>> {
+        let ctx = self.keyload.wrap(store, ctx)?;
+        threshold_announce::wrap_sigs(ctx, &self.signers)
+    }
+}
+
+pub struct ContentUnwrap<'a, TW, P, Keyload> {
+    pub keyload: Keyload,
+    pub(crate) author_set: &'a AuthorSet<TW, P>,
+    /// Indices of keys with valid signatures.
+    pub(crate) signers: Vec<usize>,
+}
+
+impl<'a, TW, P, Keyload> ContentUnwrap<'a, TW, P, Keyload> {
+    pub fn new(keyload: Keyload, author_set: &'a AuthorSet<TW, P>) -> Self {
+        Self {
+            keyload,
+            author_set,
+            signers: Vec::new(),
+        }
+    }
+}
+
+impl<'a, TW, F, P, Keyload, Store> message::ContentUnwrap<TW, F, Store> for ContentUnwrap<'a, TW, P, Keyload>
+where
+    TW: IntTbitWord + SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    P: mss::Parameters<TW>,
+    Keyload: message::ContentUnwrap<TW, F, Store>,
+{
+    fn unwrap<'c, IS: io::IStream<TW>>(
+        &mut self,
+        store: &Store,
+        ctx: &'c mut unwrap::Context<TW, F, IS>,
+    ) -> Fallible<&'c mut unwrap::Context<TW, F, IS>> {
+        let ctx = self.keyload.unwrap(store, ctx)?;
+        let (ctx, signers) = threshold_announce::unwrap_sigs(ctx, self.author_set)?;
+        self.signers = signers;
+        Ok(ctx)
+    }
+}
@@ -70,6 +70,8 @@ where
     }
 }
 
+impl<'a, TW, F, Link: HasLink> message::FixedSize for ContentWrap<'a, TW, F, Link> {}
+
 pub struct ContentUnwrap<TW, F, Link: HasLink> {
     pub link: <Link as HasLink>::Rel,
     _phantom: std::marker::PhantomData<(TW, F, Link)>,
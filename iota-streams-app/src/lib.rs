@@ -28,6 +28,9 @@ pub mod message;
 /// Transport-related abstractions.
 pub mod transport;
 
+/// Clock of time-dependent features.
+pub mod time;
+
 /*
 pub trait TrustProvider<PublicKey> {
     fn check_trusted(pk: &PublicKey) -> Fallible<()>;
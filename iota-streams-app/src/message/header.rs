@@ -11,6 +11,28 @@
 //!     absorb external tryte appinst[81];
 //!     absorb external tryte msgid[27];
 //!     absorb trytes type;
+//!     // Only in version 5 header.
+//!     absorb tryte app_version;
+//!     absorb size flags;
+//!     // Only if `flags` has `HEADER_FLAG_TLV` bit set.
+//!     absorb size tlv_count;
+//!     repeated {
+//!         absorb size tlv_type;
+//!         absorb trytes tlv_value;
+//!     }
+//!     // Only in version 5 header, `ext` is empty when `ext_size` is 0.
+//!     absorb size ext_size;
+//!     fork {
+//!         HeaderExt ext;
+//!     }
+//!     absorb external tryte ext_block[ext_size];
+//! }
+//! message HeaderExt {
+//!     join link msgid;
+//!     mask trytes branch;
+//!     mask trytes schema;
+//!     commit;
+//!     squeeze tryte mac[27];
 //! }
 //! ```
 //!
@@ -28,6 +50,26 @@
 //! * `msgid` -- Streams application message identifier, externally stored
 //! in `tag` field of Transaction.
 //!
+//! * `app_version` -- application protocol version, non-zero. Applications check it with
+//! their own compatibility rules, eg. to handle messages from devices running different
+//! firmware. Headers without the field (version 1) have application version 0.
+//!
+//! * `flags` -- bits telling which optional header areas follow. Unknown bits are rejected
+//! as the layout of the areas they guard is unknown.
+//!
+//! * `tlv_type`, `tlv_value` -- TLV extensions, typed values in plain sorted by type, each
+//! type occurs once. Future header data is added as a new TLV type, see `TlvExtension`,
+//! parsers keep values of unknown types as they are.
+//!
+//! * `ext_size` -- size of the forked extension block in trits.
+//!
+//! * `ext` -- optional extension masked with the state of a keyload (or a message
+//! linked to keyload), so only keyload recipients can read it. It carries routing hints:
+//! `branch` label and `schema` id. The block is forked, others skip it.
+//!
+//! * `ext_block` -- trits of the forked extension block. They are absorbed along with its size,
+//! so all header trits are committed and the block can't be altered, stripped or inserted.
+//!
 //! # Alternative design
 //!
 //! ```pb3
@@ -50,10 +92,13 @@
 
 use failure::{
     ensure,
+    format_err,
     Fallible,
 };
 use std::str::FromStr;
 
+use iota_streams_core::sponge::spongos;
+
 use iota_streams_core::{
     sponge::prp::PRP,
     tbits::{
@@ -74,10 +119,147 @@ use protobuf3::{
 
 use super::*;
 
+/// Header extension with routing hints readable by keyload recipients only.
+pub struct HeaderExt<TW, Link> {
+    /// Link to the message the extension is masked with, usually keyload.
+    pub link: Link,
+    /// Branch label.
+    pub branch: Trytes<TW>,
+    /// Content schema id.
+    pub schema: Trytes<TW>,
+}
+
+impl<TW, Link> Clone for HeaderExt<TW, Link>
+where
+    TW: Clone,
+    Link: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            link: self.link.clone(),
+            branch: self.branch.clone(),
+            schema: self.schema.clone(),
+        }
+    }
+}
+
+impl<TW, Link> HeaderExt<TW, Link>
+where
+    TW: StringTbitWord,
+{
+    /// Extension with `branch` label and `schema` id given as tryte strings.
+    pub fn new(link: Link, branch: &str, schema: &str) -> Fallible<Self> {
+        let branch = Tbits::<TW>::from_str(branch).map_err(|_| format_err!("Bad branch trytes: '{}'.", branch))?;
+        let schema = Tbits::<TW>::from_str(schema).map_err(|_| format_err!("Bad schema trytes: '{}'.", schema))?;
+        Ok(Self {
+            link: link,
+            branch: Trytes(branch),
+            schema: Trytes(schema),
+        })
+    }
+}
+
+impl<TW, F, Link, Store> ContentWrap<TW, F, Store> for HeaderExt<TW, Link>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn sizeof<'c>(&self, ctx: &'c mut sizeof::Context<TW, F>) -> Fallible<&'c mut sizeof::Context<TW, F>> {
+        let store = EmptyLinkStore::<TW, F, <Link as HasLink>::Rel, ()>::default();
+        let mac = Mac(spongos::Spongos::<TW, F>::MAC_SIZE);
+        ctx.join(&store, self.link.rel())?
+            .mask(&self.branch)?
+            .mask(&self.schema)?
+            .commit()?
+            .squeeze(&mac)?;
+        Ok(ctx)
+    }
+
+    fn wrap<'c, OS: io::OStream<TW>>(
+        &self,
+        store: &Store,
+        ctx: &'c mut wrap::Context<TW, F, OS>,
+    ) -> Fallible<&'c mut wrap::Context<TW, F, OS>> {
+        let mac = Mac(spongos::Spongos::<TW, F>::MAC_SIZE);
+        ctx.join(store, self.link.rel())?
+            .mask(&self.branch)?
+            .mask(&self.schema)?
+            .commit()?
+            .squeeze(&mac)?;
+        Ok(ctx)
+    }
+}
+
+impl<TW, F, Link, Store> ContentUnwrap<TW, F, Store> for HeaderExt<TW, Link>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Eq + Default + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+{
+    fn unwrap<'c, IS: io::IStream<TW>>(
+        &mut self,
+        store: &Store,
+        ctx: &'c mut unwrap::Context<TW, F, IS>,
+    ) -> Fallible<&'c mut unwrap::Context<TW, F, IS>> {
+        let mac = Mac(spongos::Spongos::<TW, F>::MAC_SIZE);
+        let mut rel = <Link as HasLink>::Rel::default();
+        ctx.join(store, &mut rel)?
+            .mask(&mut self.branch)?
+            .mask(&mut self.schema)?
+            .commit()?
+            .squeeze(&mac)?;
+        self.link = Link::from_base_rel(self.link.base(), &rel);
+        Ok(ctx)
+    }
+}
+
+/// `flags` bit of the TLV extension area.
+pub const HEADER_FLAG_TLV: usize = 1;
+
+/// `flags` bits known to this implementation.
+const HEADER_FLAGS: usize = HEADER_FLAG_TLV;
+
+/// Header TLV extension, a typed value.
+pub struct Tlv<TW> {
+    pub tlv_type: usize,
+    pub value: Trytes<TW>,
+}
+
+impl<TW> Clone for Tlv<TW>
+where
+    TW: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            tlv_type: self.tlv_type,
+            value: self.value.clone(),
+        }
+    }
+}
+
+/// Header data carried as a TLV extension. TLV types are registered by implementing the trait,
+/// a type must be used by one extension only.
+pub trait TlvExtension<TW>: Sized {
+    const TLV_TYPE: usize;
+    fn to_value(&self) -> Trytes<TW>;
+    fn from_value(value: &Trytes<TW>) -> Fallible<Self>;
+}
+
 pub struct Header<TW, Link> {
     pub version: Trint3,
     pub link: Link,
     pub content_type: Trytes<TW>,
+    /// Application protocol version, 0 if not specified. It's wrapped in version 5 header only.
+    pub app_version: Trint3,
+    /// TLV extensions sorted by type, they are wrapped in version 5 header only.
+    pub tlvs: Vec<Tlv<TW>>,
+    /// Optional extension, it's wrapped in version 5 header only.
+    pub ext: Option<HeaderExt<TW, Link>>,
 }
 
 impl<TW, Link> Clone for Header<TW, Link>
@@ -90,7 +272,68 @@ where
             version: self.version,
             link: self.link.clone(),
             content_type: self.content_type.clone(),
+            app_version: self.app_version,
+            tlvs: self.tlvs.clone(),
+            ext: self.ext.clone(),
+        }
+    }
+}
+
+impl<TW, Link> Header<TW, Link> {
+    /// Attach extension, the header is wrapped with version 5.
+    pub fn with_ext(mut self, ext: HeaderExt<TW, Link>) -> Self {
+        self.ext = Some(ext);
+        self.update_version();
+        self
+    }
+
+    /// Set application protocol version, non-zero version is wrapped with header version 5.
+    pub fn with_app_version(mut self, app_version: Trint3) -> Self {
+        self.app_version = app_version;
+        self.update_version();
+        self
+    }
+
+    /// Attach TLV extension value, a value of the same type is replaced. The header is wrapped
+    /// with version 5.
+    pub fn with_tlv(mut self, tlv_type: usize, value: Trytes<TW>) -> Self {
+        match self.tlvs.binary_search_by_key(&tlv_type, |tlv| tlv.tlv_type) {
+            Ok(i) => self.tlvs[i].value = value,
+            Err(i) => self.tlvs.insert(i, Tlv { tlv_type, value }),
         }
+        self.update_version();
+        self
+    }
+
+    /// Attach TLV extension.
+    pub fn with_tlv_ext<E: TlvExtension<TW>>(self, ext: &E) -> Self {
+        self.with_tlv(E::TLV_TYPE, ext.to_value())
+    }
+
+    /// Value of TLV extension of type `tlv_type`.
+    pub fn tlv(&self, tlv_type: usize) -> Option<&Trytes<TW>> {
+        self.tlvs
+            .binary_search_by_key(&tlv_type, |tlv| tlv.tlv_type)
+            .ok()
+            .map(|i| &self.tlvs[i].value)
+    }
+
+    /// TLV extension, `None` if the header doesn't carry it.
+    pub fn tlv_ext<E: TlvExtension<TW>>(&self) -> Fallible<Option<E>> {
+        match self.tlv(E::TLV_TYPE) {
+            Some(value) => Ok(Some(E::from_value(value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Version 1 header if no optional field is set, otherwise version 5 header committing
+    /// all the fields.
+    fn update_version(&mut self) {
+        self.version = if !self.tlvs.is_empty() || self.app_version != Trint3(0) || self.ext.is_some() {
+            STREAMS_5_VER
+        } else {
+            STREAMS_1_VER
+        };
     }
 }
 
@@ -103,6 +346,9 @@ where
             version: STREAMS_1_VER,
             link: link,
             content_type: Trytes(Tbits::<TW>::from_str(content_type).unwrap()),
+            app_version: Trint3(0),
+            tlvs: Vec::new(),
+            ext: None,
         }
     }
 
@@ -111,31 +357,90 @@ where
             version: STREAMS_1_VER,
             link: link,
             content_type: Trytes(Tbits::zero(0)),
+            app_version: Trint3(0),
+            tlvs: Vec::new(),
+            ext: None,
         }
     }
 }
 
+/// Size of the extension block in trits.
+fn sizeof_ext<TW, F, Link, Store>(ext: &HeaderExt<TW, Link>) -> Fallible<Size>
+where
+    HeaderExt<TW, Link>: ContentWrap<TW, F, Store>,
+{
+    let mut ctx = sizeof::Context::<TW, F>::new();
+    ContentWrap::<TW, F, Store>::sizeof(ext, &mut ctx)?;
+    Ok(Size(ctx.get_size()))
+}
+
 impl<TW, F, Link, Store> ContentWrap<TW, F, Store> for Header<TW, Link>
 where
     TW: SpongosTbitWord + trinary::TritWord,
-    F: PRP<TW>,
-    Link: AbsorbExternalFallback<TW, F>,
+    F: PRP<TW> + Clone + Default,
+    Link: HasLink + AbsorbExternalFallback<TW, F>,
+    <Link as HasLink>::Rel: Eq + SkipFallback<TW, F>,
+    Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
 {
     fn sizeof<'c>(&self, ctx: &'c mut sizeof::Context<TW, F>) -> Fallible<&'c mut sizeof::Context<TW, F>> {
         ctx.absorb(&self.version)?
             .absorb(External(Fallback(&self.link)))?
             .absorb(&self.content_type)?;
+        if self.version == STREAMS_5_VER {
+            ctx.absorb(&self.app_version)?;
+            let flags = if self.tlvs.is_empty() { 0 } else { HEADER_FLAG_TLV };
+            ctx.absorb(Size(flags))?;
+            if flags & HEADER_FLAG_TLV != 0 {
+                ctx.absorb(Size(self.tlvs.len()))?
+                    .repeated(self.tlvs.iter(), |ctx, tlv| ctx.absorb(Size(tlv.tlv_type))?.absorb(&tlv.value))?;
+            }
+            match &self.ext {
+                Some(ext) => {
+                    ctx.absorb(sizeof_ext::<TW, F, Link, Store>(ext)?)?
+                        .fork(|ctx| ContentWrap::<TW, F, Store>::sizeof(ext, ctx))?;
+                }
+                None => {
+                    ctx.absorb(Size(0))?;
+                }
+            }
+        }
         Ok(ctx)
     }
 
     fn wrap<'c, OS: io::OStream<TW>>(
         &self,
-        _store: &Store,
+        store: &Store,
         ctx: &'c mut wrap::Context<TW, F, OS>,
     ) -> Fallible<&'c mut wrap::Context<TW, F, OS>> {
         ctx.absorb(&self.version)?
             .absorb(External(Fallback(&self.link)))?
             .absorb(&self.content_type)?;
+        if self.version == STREAMS_5_VER {
+            ctx.absorb(&self.app_version)?;
+            let flags = if self.tlvs.is_empty() { 0 } else { HEADER_FLAG_TLV };
+            ctx.absorb(Size(flags))?;
+            if flags & HEADER_FLAG_TLV != 0 {
+                ctx.absorb(Size(self.tlvs.len()))?
+                    .repeated(self.tlvs.iter(), |ctx, tlv| ctx.absorb(Size(tlv.tlv_type))?.absorb(&tlv.value))?;
+            }
+            match &self.ext {
+                Some(ext) => {
+                    let ext_size = sizeof_ext::<TW, F, Link, Store>(ext)?;
+                    ctx.absorb(ext_size)?;
+                    // Wrap the forked block aside, so that its trits can be absorbed.
+                    let mut ext_block = NTrytes(Tbits::zero(ext_size.0));
+                    {
+                        let mut ext_ctx = wrap::Context::new(ext_block.0.slice_mut());
+                        ext_ctx.spongos = ctx.spongos.fork();
+                        ext.wrap(store, &mut ext_ctx)?;
+                    }
+                    ctx.skip(&ext_block)?.absorb(External(&ext_block))?;
+                }
+                None => {
+                    ctx.absorb(Size(0))?;
+                }
+            }
+        }
         Ok(ctx)
     }
 }
@@ -151,14 +456,36 @@ where
         _store: &Store,
         ctx: &'c mut unwrap::Context<TW, F, IS>,
     ) -> Fallible<&'c mut unwrap::Context<TW, F, IS>> {
-        ctx.absorb(&mut self.version)?
-            .absorb(External(Fallback(&self.link)))?
+        ctx.absorb(&mut self.version)?;
+        check_version(self.version)?;
+        ctx.absorb(External(Fallback(&self.link)))?
             .absorb(&mut self.content_type)?;
-        ensure!(
-            self.version == STREAMS_1_VER,
-            "Message version not supported: {}.",
-            self.version
-        );
+        if self.version == STREAMS_5_VER {
+            ctx.absorb(&mut self.app_version)?;
+            let mut flags = Size(0);
+            ctx.absorb(&mut flags)?;
+            ensure!(flags.0 & !HEADER_FLAGS == 0, "Unknown header flags: {}.", flags);
+            if flags.0 & HEADER_FLAG_TLV != 0 {
+                let mut count = Size(0);
+                let tlvs = &mut self.tlvs;
+                ctx.absorb(&mut count)?.repeated(count, |ctx| {
+                    let mut tlv = Tlv {
+                        tlv_type: 0,
+                        value: Trytes::default(),
+                    };
+                    let mut tlv_type = Size(0);
+                    ctx.absorb(&mut tlv_type)?.absorb(&mut tlv.value)?;
+                    tlv.tlv_type = tlv_type.0;
+                    ensure!(
+                        tlvs.last().map_or(true, |last| last.tlv_type < tlv.tlv_type),
+                        "Header TLV extensions are not sorted by type."
+                    );
+                    tlvs.push(tlv);
+                    Ok(ctx)
+                })?;
+            }
+        }
+        // Extension block of version 5 header follows, it's handled by `TbinaryMessage::parse_header`.
         Ok(ctx)
     }
 }
@@ -0,0 +1,71 @@
+//! Inspection of the public part of wrapped messages.
+//!
+//! Header fields up to the extension block are absorbed in plain, so they can be read
+//! without channel state or keys, eg. by routers, explorers and debugging tools. Nothing
+//! is authenticated until the message is unwrapped by a channel participant.
+
+use failure::Fallible;
+use std::fmt;
+
+use super::*;
+use iota_streams_core::{
+    sponge::prp::PRP,
+    tbits::{
+        trinary,
+        word::{
+            SpongosTbitWord,
+            StringTbitWord,
+        },
+    },
+};
+use iota_streams_protobuf3::types::{
+    AbsorbExternalFallback,
+    Trint3,
+};
+
+/// Public header fields of a wrapped message.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct HeaderInfo<Link> {
+    pub link: Link,
+    pub version: Trint3,
+    pub content_type: String,
+    /// Application protocol version, 0 if not specified.
+    pub app_version: Trint3,
+    /// Size of the header extension block in trits, 0 if there's none.
+    pub ext_size: usize,
+    /// Size of the content in trits.
+    pub content_size: usize,
+}
+
+impl<Link: fmt::Display> fmt::Display for HeaderInfo<Link> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{{link: {}, version: {}, type: {}, app version: {}, ext size: {}, content size: {}}}",
+            self.link, self.version, self.content_type, self.app_version, self.ext_size, self.content_size
+        )
+    }
+}
+
+/// Parse public header fields of `msg` without unwrapping the content.
+pub fn inspect<TW, F, Link>(msg: &TbinaryMessage<TW, F, Link>) -> Fallible<HeaderInfo<Link>>
+where
+    TW: SpongosTbitWord + StringTbitWord + trinary::TritWord,
+    F: PRP<TW> + Clone + Default,
+    Link: Clone + AbsorbExternalFallback<TW, F>,
+{
+    let preparsed = msg.parse_header()?;
+    let content_size = preparsed.ctx.stream.size();
+    let ext_size = preparsed
+        .ext_ctx
+        .as_ref()
+        .map_or(0, |ext_ctx| ext_ctx.stream.size() - content_size);
+    Ok(HeaderInfo {
+        content_type: preparsed.content_type(),
+        link: preparsed.header.link,
+        version: preparsed.header.version,
+        app_version: preparsed.header.app_version,
+        ext_size,
+        content_size,
+    })
+}
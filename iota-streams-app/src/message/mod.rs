@@ -1,4 +1,7 @@
-use failure::Fallible;
+use failure::{
+    ensure,
+    Fallible,
+};
 
 use iota_streams_protobuf3::{
     command::{
@@ -7,6 +10,7 @@ use iota_streams_protobuf3::{
         wrap,
     },
     io,
+    types::Trint3,
 };
 
 /// Type of "absolute" links. For http it's the absolute URL.
@@ -56,10 +60,36 @@ pub trait ContentUnwrap<TW, F, Store> {
         store: &Store,
         ctx: &'c mut unwrap::Context<TW, F, IS>,
     ) -> Fallible<&'c mut unwrap::Context<TW, F, IS>>;
+
+    /// Unwrap content of a message with the given STREAMS version. Content supporting
+    /// older layouts dispatches on `version`, by default only the layout of `STREAMS_1_VER`
+    /// is supported; `STREAMS_5_VER` changes the header only.
+    fn unwrap_versioned<'c, IS: io::IStream<TW>>(
+        &mut self,
+        version: Trint3,
+        store: &Store,
+        ctx: &'c mut unwrap::Context<TW, F, IS>,
+    ) -> Fallible<&'c mut unwrap::Context<TW, F, IS>> {
+        ensure!(
+            SUPPORTED_VERSIONS.contains(&version),
+            "Content version not supported: {}.",
+            version
+        );
+        self.unwrap(store, ctx)
+    }
 }
 
+/// Marker for content whose wrapped size doesn't depend on field values, ie. content of
+/// fixed-size fields only. Its size is computed once per content type, see
+/// `PreparedMessage::wrap_fixed`.
+pub trait FixedSize {}
+
 pub mod header;
-use header::Header;
+use header::{
+    Header,
+    HeaderExt,
+    TlvExtension,
+};
 mod version;
 pub use version::*;
 
@@ -73,3 +103,7 @@ mod preparsed;
 pub use preparsed::*;
 mod unwrapped;
 pub use unwrapped::*;
+mod inspect;
+pub use inspect::*;
+mod tag;
+pub use tag::*;
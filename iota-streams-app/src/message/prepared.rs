@@ -2,7 +2,14 @@ use failure::{
     ensure,
     Fallible,
 };
-use std::cell::Ref;
+use std::{
+    any::type_name,
+    cell::{
+        Ref,
+        RefCell,
+    },
+    collections::HashMap,
+};
 
 use super::*;
 use iota_streams_core::{
@@ -14,6 +21,11 @@ use iota_streams_core::{
 };
 use iota_streams_protobuf3::types::*;
 
+thread_local! {
+    /// Sizes of `FixedSize` contents by content type name.
+    static FIXED_SIZES: RefCell<HashMap<&'static str, usize>> = RefCell::new(HashMap::new());
+}
+
 /// Message context prepared for wrapping.
 pub struct PreparedMessage<'a, TW, F, Link, Store: 'a, Content> {
     store: Ref<'a, Store>,
@@ -33,6 +45,20 @@ impl<'a, TW, F, Link, Store: 'a, Content> PreparedMessage<'a, TW, F, Link, Store
     }
 }
 
+impl<'a, TW, F, Link, Store, Content> PreparedMessage<'a, TW, F, Link, Store, Content> {
+    /// Attach header extension masked with the state of the message `ext.link` refers to.
+    pub fn with_header_ext(mut self, ext: HeaderExt<TW, Link>) -> Self {
+        self.header = self.header.with_ext(ext);
+        self
+    }
+
+    /// Attach header TLV extension.
+    pub fn with_tlv_ext<E: TlvExtension<TW>>(mut self, ext: &E) -> Self {
+        self.header = self.header.with_tlv_ext(ext);
+        self
+    }
+}
+
 impl<'a, TW, F, Link, Store, Content> PreparedMessage<'a, TW, F, Link, Store, Content>
 where
     TW: SpongosTbitWord,
@@ -52,13 +78,54 @@ where
             self.content.sizeof(&mut ctx)?;
             ctx.get_size()
         };
+        self.wrap_sized(buf_size)
+    }
+
+    /// Wrap message with `FixedSize` content, size of the content is computed on the first
+    /// call for the content type and reused afterwards.
+    pub fn wrap_fixed(&self) -> Fallible<WrappedMessage<TW, F, Link>>
+    where
+        Link: HasLink + AbsorbExternalFallback<TW, F> + Clone,
+        <Link as HasLink>::Rel: Eq + SkipFallback<TW, F>,
+        Store: 'a + LinkStore<TW, F, <Link as HasLink>::Rel>,
+        Header<TW, Link>: ContentWrap<TW, F, Store>,
+        Content: ContentWrap<TW, F, Store> + FixedSize,
+    {
+        let header_size = {
+            let mut ctx = sizeof::Context::<TW, F>::new();
+            self.header.sizeof(&mut ctx)?;
+            ctx.get_size()
+        };
+        // Lifetimes don't affect size, type name identifies the content type with its parameters.
+        let key = type_name::<Content>();
+        let content_size = match FIXED_SIZES.with(|sizes| sizes.borrow().get(key).copied()) {
+            Some(size) => size,
+            None => {
+                let mut ctx = sizeof::Context::<TW, F>::new();
+                self.content.sizeof(&mut ctx)?;
+                let size = ctx.get_size();
+                FIXED_SIZES.with(|sizes| sizes.borrow_mut().insert(key, size));
+                size
+            }
+        };
+        self.wrap_sized(header_size + content_size)
+    }
 
+    fn wrap_sized(&self, buf_size: usize) -> Fallible<WrappedMessage<TW, F, Link>>
+    where
+        Link: HasLink + AbsorbExternalFallback<TW, F> + Clone,
+        <Link as HasLink>::Rel: Eq + SkipFallback<TW, F>,
+        Store: 'a + LinkStore<TW, F, <Link as HasLink>::Rel>,
+        Header<TW, Link>: ContentWrap<TW, F, Store>,
+        Content: ContentWrap<TW, F, Store>,
+    {
         let mut buf = Tbits::<TW>::zero(buf_size);
 
         let spongos = {
-            let mut ctx = wrap::Context::new(buf.slice_mut());
+            let mut ctx = wrap::Context::strict(buf.slice_mut(), buf_size)?;
             self.header.wrap(&*self.store, &mut ctx)?;
             self.content.wrap(&*self.store, &mut ctx)?;
+            ctx.check_exhausted()?;
             ensure!(ctx.stream.is_empty(), "OStream has not been exhausted.");
 
             ctx.spongos
@@ -1,17 +1,38 @@
-use failure::Fallible;
+use failure::{
+    bail,
+    Fallible,
+};
 use std::string::ToString;
 
 use super::*;
-use iota_streams_core::tbits::{
-    word::StringTbitWord,
-    TbitSlice,
+use iota_streams_core::{
+    sponge::prp::PRP,
+    tbits::{
+        trinary,
+        word::{
+            BasicTbitWord,
+            SpongosTbitWord,
+            StringTbitWord,
+        },
+        TbitSlice,
+        Tbits,
+    },
+};
+use iota_streams_protobuf3::{
+    command::unwrap,
+    types::{
+        LinkStore,
+        SkipFallback,
+        Trytes,
+    },
 };
-use iota_streams_protobuf3::command::unwrap;
 
 /// Message context preparsed for unwrapping.
 pub struct PreparsedMessage<'a, TW, F, Link> {
     pub header: Header<TW, Link>,
     pub(crate) ctx: unwrap::Context<TW, F, TbitSlice<'a, TW>>,
+    /// Context of the header extension block, if any.
+    pub(crate) ext_ctx: Option<unwrap::Context<TW, F, TbitSlice<'a, TW>>>,
 }
 
 impl<'a, TW, F, Link> PreparsedMessage<'a, TW, F, Link>
@@ -26,6 +47,16 @@ where
         (self.header.content_type.0).to_string()
     }
 
+    /// Limit size of variable-length fields of the content and header extension,
+    /// `None` leaves them limited by the size of the rest of the message only.
+    pub fn with_max_size(mut self, max_size: Option<usize>) -> Self {
+        self.ctx.max_size = bound_max_size(max_size, self.ctx.stream.size());
+        if let Some(ext_ctx) = &mut self.ext_ctx {
+            ext_ctx.max_size = bound_max_size(max_size, ext_ctx.stream.size());
+        }
+        self
+    }
+
     pub fn unwrap<Store, Content>(
         mut self,
         store: &Store,
@@ -34,7 +65,7 @@ where
     where
         Content: ContentUnwrap<TW, F, Store>,
     {
-        content.unwrap(&store, &mut self.ctx)?;
+        content.unwrap_versioned(self.header.version, &store, &mut self.ctx)?;
         // Discard what's left of `self.ctx.stream`
         Ok(UnwrappedMessage {
             link: self.header.link,
@@ -44,6 +75,50 @@ where
     }
 }
 
+impl<'a, TW, F, Link> PreparsedMessage<'a, TW, F, Link>
+where
+    TW: BasicTbitWord,
+    F: Clone,
+    Link: HasLink,
+    <Link as HasLink>::Rel: Default + SkipFallback<TW, F>,
+{
+    /// Peek the link to the message the content is joined to without unwrapping the content.
+    /// The link is skipped (ie. not masked), it's the first field of all content types
+    /// starting with `join link`. The result is meaningless for other content types.
+    pub fn peek_link(&self) -> Fallible<<Link as HasLink>::Rel> {
+        let mut ctx = self.ctx.clone();
+        let mut link = <Link as HasLink>::Rel::default();
+        link.unwrap_skip(&mut ctx)?;
+        Ok(link)
+    }
+
+    /// Unwrap header extension masked with the state of a message in `store`.
+    /// It fails if the header has no extension or the linked message is not in `store`,
+    /// eg. the recipient was not given the keyload. The content can be unwrapped anyway.
+    pub fn unwrap_header_ext<Store>(&self, store: &Store) -> Fallible<HeaderExt<TW, Link>>
+    where
+        TW: SpongosTbitWord + StringTbitWord + trinary::TritWord,
+        F: PRP<TW>,
+        Link: Clone,
+        <Link as HasLink>::Rel: Eq,
+        Store: LinkStore<TW, F, <Link as HasLink>::Rel>,
+    {
+        match &self.ext_ctx {
+            Some(ext_ctx) => {
+                let mut ctx = ext_ctx.clone();
+                let mut ext = HeaderExt {
+                    link: self.header.link.clone(),
+                    branch: Trytes(Tbits::zero(0)),
+                    schema: Trytes(Tbits::zero(0)),
+                };
+                ext.unwrap(store, &mut ctx)?;
+                Ok(ext)
+            }
+            None => bail!("Message header has no extension."),
+        }
+    }
+}
+
 impl<'a, TW, F, Link> Clone for PreparsedMessage<'a, TW, F, Link>
 where
     TW: Clone,
@@ -54,6 +129,13 @@ where
         Self {
             header: self.header.clone(),
             ctx: self.ctx.clone(),
+            ext_ctx: self.ext_ctx.clone(),
         }
     }
 }
+
+/// Size limit of variable-length fields bounded by `stream_size` trits left in the message,
+/// so that tampered sizes can't cause oversized allocations.
+pub(crate) fn bound_max_size(max_size: Option<usize>, stream_size: usize) -> Option<usize> {
+    Some(max_size.map_or(stream_size, |max_size| max_size.min(stream_size)))
+}
@@ -0,0 +1,43 @@
+use std::str::FromStr;
+
+use iota_streams_core::{
+    sponge::{
+        prp::PRP,
+        spongos::Spongos,
+    },
+    tbits::{
+        word::{
+            SpongosTbitWord,
+            StringTbitWord,
+        },
+        Tbits,
+    },
+};
+use iota_streams_protobuf3::types::NTrytes;
+
+/// Commitment to a message, the same for the publisher and the recipients. It's squeezed
+/// from the message spongos state, so it commits to the message and the messages it's joined
+/// to, but not to the transport link.
+pub type MessageTag<TW> = NTrytes<TW>;
+
+/// Size of `MessageTag` in trits.
+pub const MESSAGE_TAG_SIZE: usize = 243;
+
+/// Absorbed before the tag is squeezed, so that the tag is unrelated to the state squeezed
+/// when other messages are joined to the message.
+const MESSAGE_TAG_DOMAIN: &str = "STREAMS9MESSAGE9TAG";
+
+/// Tag of a message with the state `spongos`, eg. looked up in a link store.
+pub fn message_tag<TW, F>(spongos: &Spongos<TW, F>) -> MessageTag<TW>
+where
+    TW: SpongosTbitWord + StringTbitWord,
+    F: PRP<TW> + Clone,
+{
+    let mut s = spongos.fork();
+    s.commit();
+    // Link stores keep the inner part of the state only, start from the same state.
+    let mut s = Spongos::<TW, F>::from_inner(s.to_inner());
+    s.absorb_tbits(&Tbits::from_str(MESSAGE_TAG_DOMAIN).unwrap());
+    s.commit();
+    NTrytes(s.squeeze_tbits(MESSAGE_TAG_SIZE))
+}
@@ -1,4 +1,7 @@
-use failure::Fallible;
+use failure::{
+    ensure,
+    Fallible,
+};
 use std::fmt;
 
 use super::*;
@@ -14,7 +17,11 @@ use iota_streams_core::{
     },
 };
 use iota_streams_protobuf3::{
-    command::unwrap,
+    command::{
+        unwrap,
+        Absorb,
+        Skip,
+    },
     types::*,
 };
 
@@ -69,18 +76,47 @@ impl<TW, F, AbsLink> TbinaryMessage<TW, F, AbsLink> {
 impl<TW, F, Link> TbinaryMessage<TW, F, Link>
 where
     TW: SpongosTbitWord + StringTbitWord + trinary::TritWord,
-    F: PRP<TW> + Default,
+    F: PRP<TW> + Clone + Default,
     Link: Clone + AbsorbExternalFallback<TW, F>,
 {
     pub fn parse_header<'a>(&'a self) -> Fallible<PreparsedMessage<'a, TW, F, Link>> {
-        let mut ctx = unwrap::Context::new(self.body.slice());
+        self.parse_header_with_max_size(None)
+    }
+
+    /// Parse header of an untrusted message, variable-length fields of the header and
+    /// the content larger than `max_size` trytes are rejected.
+    pub fn parse_header_with_max_size<'a>(
+        &'a self,
+        max_size: Option<usize>,
+    ) -> Fallible<PreparsedMessage<'a, TW, F, Link>> {
+        let mut ctx =
+            unwrap::Context::new(self.body.slice()).with_max_size(bound_max_size(max_size, self.body.size()));
         let mut header = Header::<TW, Link>::new(self.link().clone());
         let store = EmptyLinkStore::<TW, F, Link, ()>::default();
         header.unwrap(&store, &mut ctx)?;
 
+        // Extension block is forked, keep the context to unwrap it later and skip it for now.
+        // Version 5 header commits the block.
+        let ext_ctx = if header.version == STREAMS_5_VER {
+            let mut ext_size = Size(0);
+            ctx.absorb(&mut ext_size)?;
+            ensure!(ext_size.0 <= self.body.size(), "Bad header extension size: {}.", ext_size.0);
+            let ext_ctx = ctx.clone();
+            let mut ext_block = NTrytes(Tbits::zero(ext_size.0));
+            ctx.skip(&mut ext_block)?.absorb(External(&ext_block))?;
+            if ext_size.0 != 0 {
+                Some(ext_ctx)
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
         Ok(PreparsedMessage {
             header: header,
             ctx: ctx,
+            ext_ctx: ext_ctx,
         })
     }
 }
@@ -10,8 +10,131 @@
 //! STREAMS message syntax version is indicated as the first tryte in the trinary encoded message.
 //!
 //! Backwards compatibility of the STREAMS implementations is welcome and not mandatory.
+//! Versions listed in `SUPPORTED_VERSIONS` are accepted by `Header`, layouts of the header
+//! and of contents are dispatched on version in `Header::unwrap` and
+//! `ContentUnwrap::unwrap_versioned` respectively.
 
-use iota_streams_protobuf3::types::Trint3;
+use failure::{
+    ensure,
+    Fallible,
+};
+
+use iota_streams_protobuf3::types::{
+    LinkStore,
+    Trint3,
+};
 
 /// STREAMS version number.
 pub const STREAMS_1_VER: Trint3 = Trint3(1);
+
+/// STREAMS version of messages with application version, TLV extensions or `HeaderExt` in `Header`.
+/// All header trits are committed, including `HeaderExt` block and its size. The content layout
+/// is the same as in version 1.
+///
+/// Versions 2 to 4 didn't commit the extension block, so it could be altered; they are not supported.
+pub const STREAMS_5_VER: Trint3 = Trint3(5);
+
+/// STREAMS versions supported for unwrapping. New messages are wrapped with version 1
+/// unless the header carries an extension, application version or TLV extensions, then
+/// version 5 is used.
+pub const SUPPORTED_VERSIONS: [Trint3; 2] = [STREAMS_1_VER, STREAMS_5_VER];
+
+/// Check STREAMS version of a message is supported.
+pub fn check_version(version: Trint3) -> Fallible<()> {
+    ensure!(
+        SUPPORTED_VERSIONS.contains(&version),
+        "Message version not supported: {}.",
+        version
+    );
+    Ok(())
+}
+
+/// Copy spongos states and info of `links` from one link store into another.
+///
+/// Link stores don't version their state, so when the format of stored state changes
+/// (eg. a new store implementation is used) the state is migrated by reading it
+/// with the old store and updating the new one.
+pub fn migrate_links<'a, TW, F, Link, From, To, Links>(links: Links, from: &From, to: &mut To) -> Fallible<()>
+where
+    Link: 'a,
+    From: LinkStore<TW, F, Link>,
+    To: LinkStore<TW, F, Link, Info = From::Info>,
+    Links: IntoIterator<Item = &'a Link>,
+{
+    for link in links {
+        let (spongos, info) = from.lookup(link)?;
+        to.update(link, spongos, info)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::message::ContentUnwrap;
+    use iota_streams_core::{
+        sponge::prp::troika::Troika,
+        tbits::{
+            trinary::Trit,
+            TbitSlice,
+            TbitSliceMut,
+            Tbits,
+        },
+    };
+    use iota_streams_protobuf3::{
+        command::{
+            unwrap,
+            wrap,
+            Absorb,
+        },
+        io,
+        types::{
+            sizeof_sizet,
+            Size,
+        },
+    };
+
+    /// Content with a single `size` field.
+    struct Counter(Size);
+
+    impl ContentUnwrap<Trit, Troika, ()> for Counter {
+        fn unwrap<'c, IS: io::IStream<Trit>>(
+            &mut self,
+            _store: &(),
+            ctx: &'c mut unwrap::Context<Trit, Troika, IS>,
+        ) -> Fallible<&'c mut unwrap::Context<Trit, Troika, IS>> {
+            ctx.absorb(&mut self.0)?;
+            Ok(ctx)
+        }
+    }
+
+    #[test]
+    fn supported_versions() {
+        assert!(check_version(STREAMS_1_VER).is_ok());
+        assert!(check_version(STREAMS_5_VER).is_ok());
+        for version in &[0, 2, 3, 4, 6, -1] {
+            assert!(check_version(Trint3(*version)).is_err());
+        }
+    }
+
+    #[test]
+    fn unwrap_versioned_content() {
+        let mut buf = Tbits::<Trit>::zero(sizeof_sizet(7));
+        assert!(wrap::Context::<Trit, Troika, TbitSliceMut<Trit>>::new(buf.slice_mut())
+            .absorb(Size(7))
+            .is_ok());
+
+        for version in SUPPORTED_VERSIONS.iter() {
+            let mut counter = Counter(Size(0));
+            let mut ctx = unwrap::Context::<Trit, Troika, TbitSlice<Trit>>::new(buf.slice());
+            assert!(counter.unwrap_versioned(*version, &(), &mut ctx).is_ok());
+            assert_eq!(7, (counter.0).0);
+        }
+
+        // Unsupported version is rejected before the content is unwrapped.
+        let mut counter = Counter(Size(0));
+        let mut ctx = unwrap::Context::<Trit, Troika, TbitSlice<Trit>>::new(buf.slice());
+        assert!(counter.unwrap_versioned(Trint3(2), &(), &mut ctx).is_err());
+        assert_eq!(0, (counter.0).0);
+    }
+}
@@ -7,7 +7,10 @@ use iota_streams_core::{
         prp::PRP,
         spongos::Spongos,
     },
-    tbits::word::SpongosTbitWord,
+    tbits::word::{
+        SpongosTbitWord,
+        StringTbitWord,
+    },
 };
 use iota_streams_protobuf3::types::*;
 
@@ -37,3 +40,14 @@ where
         Ok(self.message)
     }
 }
+
+impl<TW, F, Link> WrappedMessage<TW, F, Link>
+where
+    TW: SpongosTbitWord + StringTbitWord,
+    F: PRP<TW> + Clone,
+{
+    /// Commitment to the message, see `MessageTag`.
+    pub fn tag(&self) -> MessageTag<TW> {
+        message_tag(&self.spongos)
+    }
+}
@@ -0,0 +1,80 @@
+//! Clock of time-dependent features: retries, TTLs, heartbeats and timestamps.
+//!
+//! Time is read from a `TimeProvider`, system time by default. Targets without a real-time
+//! clock supply their own provider, deterministic tests can use `ManualTimeProvider`.
+
+use std::{
+    sync::{
+        Arc,
+        Mutex,
+    },
+    thread,
+    time::{
+        Duration,
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+/// Source of the current time.
+pub trait TimeProvider: Send + Sync {
+    /// Current time since UNIX epoch.
+    fn now(&self) -> Duration;
+
+    /// Wait for `duration`, eg. before retrying.
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// Time provider shared by Author, Subscriber and transports.
+pub type SharedTimeProvider = Arc<dyn TimeProvider>;
+
+/// System time.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct SystemTimeProvider;
+
+impl TimeProvider for SystemTimeProvider {
+    fn now(&self) -> Duration {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()
+    }
+}
+
+/// Default time provider, system time.
+pub fn system_time_provider() -> SharedTimeProvider {
+    Arc::new(SystemTimeProvider)
+}
+
+/// Time provider advanced manually, sleeping advances it instead of blocking.
+/// Clones share the time.
+#[derive(Clone, Default, Debug)]
+pub struct ManualTimeProvider {
+    now: Arc<Mutex<Duration>>,
+}
+
+impl ManualTimeProvider {
+    /// Provider starting at `now` since UNIX epoch.
+    pub fn new(now: Duration) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    pub fn set(&self, now: Duration) {
+        *self.now.lock().unwrap() = now;
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl TimeProvider for ManualTimeProvider {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
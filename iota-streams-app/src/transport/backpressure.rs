@@ -0,0 +1,227 @@
+//! Congestion signals of the node, so that publishers can adapt their sending rate.
+//!
+//! A congested transport fails `send_message` with `Backpressure` error suggesting when to
+//! retry, the error can be recovered with `failure::Error::downcast_ref`. `Transport::health`
+//! reports congestion before sending.
+
+use failure::Fallible;
+use std::{
+    collections::VecDeque,
+    error,
+    fmt,
+    time::Duration,
+};
+
+use super::Transport;
+use crate::{
+    message::TbinaryMessage,
+    time::{
+        system_time_provider,
+        SharedTimeProvider,
+        SystemTimeProvider,
+        TimeProvider,
+    },
+};
+
+/// Congestion state of the node.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct NodeHealth {
+    /// Number of messages queued and not processed yet.
+    pub queue_depth: usize,
+    /// Number of messages accepted per time window, `None` if not limited.
+    pub rate_limit: Option<(usize, Duration)>,
+    /// Time to wait before messages are accepted again, `None` if they are accepted now.
+    pub retry_after: Option<Duration>,
+}
+
+impl NodeHealth {
+    /// Messages are not accepted now.
+    pub fn is_congested(&self) -> bool {
+        self.retry_after.is_some()
+    }
+}
+
+/// Error of sending a message to a congested node.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Backpressure {
+    /// Suggested time to wait before retrying.
+    pub retry_after: Duration,
+}
+
+impl fmt::Display for Backpressure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Node is congested, retry after {} ms.", self.retry_after.as_millis())
+    }
+}
+
+impl error::Error for Backpressure {}
+
+/// Send a message, waiting as suggested by `Backpressure` errors up to `max_retries` times.
+/// Other errors are returned immediately.
+pub fn send_with_backpressure<TW, F, Link, T>(
+    transport: &mut T,
+    msg: &TbinaryMessage<TW, F, Link>,
+    max_retries: usize,
+) -> Fallible<()>
+where
+    T: Transport<TW, F, Link>,
+    T::SendOptions: Default,
+{
+    send_with_backpressure_using(transport, msg, max_retries, &SystemTimeProvider)
+}
+
+/// Send a message with backpressure retries, waiting with `time_provider`.
+pub fn send_with_backpressure_using<TW, F, Link, T>(
+    transport: &mut T,
+    msg: &TbinaryMessage<TW, F, Link>,
+    max_retries: usize,
+    time_provider: &dyn TimeProvider,
+) -> Fallible<()>
+where
+    T: Transport<TW, F, Link>,
+    T::SendOptions: Default,
+{
+    let mut retries = 0;
+    loop {
+        match transport.send_message(msg) {
+            Err(err) if retries < max_retries => match err.downcast_ref::<Backpressure>() {
+                Some(backpressure) => time_provider.sleep(backpressure.retry_after),
+                None => return Err(err),
+            },
+            result => return result,
+        }
+        retries += 1;
+    }
+}
+
+/// Transport wrapper accepting at most `limit` messages per `window`, sending more fails
+/// with `Backpressure` error. It keeps publishers within the rate limit of the node.
+pub struct RateLimitedTransport<T> {
+    inner: T,
+    limit: usize,
+    window: Duration,
+    /// Send times within the last window, oldest first.
+    sent: VecDeque<Duration>,
+    time_provider: SharedTimeProvider,
+}
+
+impl<T> RateLimitedTransport<T> {
+    pub fn new(inner: T, limit: usize, window: Duration) -> Self {
+        Self {
+            inner,
+            limit,
+            window,
+            sent: VecDeque::new(),
+            time_provider: system_time_provider(),
+        }
+    }
+
+    /// Measure the window with `time_provider` instead of system time.
+    pub fn with_time_provider(mut self, time_provider: SharedTimeProvider) -> Self {
+        self.time_provider = time_provider;
+        self
+    }
+
+    /// Wrapped transport.
+    pub fn inner(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Return the wrapped transport.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Forget sends out of the window and return the time to wait before the next send.
+    fn retry_after(&mut self) -> Option<Duration> {
+        let now = self.time_provider.now();
+        while self.sent.front().map_or(false, |t| now.saturating_sub(*t) >= self.window) {
+            self.sent.pop_front();
+        }
+        if self.sent.len() < self.limit {
+            None
+        } else {
+            let oldest = self.sent[self.sent.len() - self.limit];
+            Some(self.window - now.saturating_sub(oldest))
+        }
+    }
+}
+
+impl<TW, F, Link, T> Transport<TW, F, Link> for RateLimitedTransport<T>
+where
+    T: Transport<TW, F, Link>,
+{
+    type SendOptions = T::SendOptions;
+
+    fn send_message_with_options(&mut self, msg: &TbinaryMessage<TW, F, Link>, opt: Self::SendOptions) -> Fallible<()> {
+        if let Some(retry_after) = self.retry_after() {
+            return Err(Backpressure { retry_after }.into());
+        }
+        self.inner.send_message_with_options(msg, opt)?;
+        self.sent.push_back(self.time_provider.now());
+        Ok(())
+    }
+
+    type RecvOptions = T::RecvOptions;
+
+    fn recv_messages_with_options(
+        &mut self,
+        link: &Link,
+        opt: Self::RecvOptions,
+    ) -> Fallible<Vec<TbinaryMessage<TW, F, Link>>> {
+        self.inner.recv_messages_with_options(link, opt)
+    }
+
+    /// Health of the wrapped transport limited by the rate of this one.
+    fn health(&mut self) -> Fallible<NodeHealth> {
+        let inner = self.inner.health()?;
+        let retry_after = self.retry_after();
+        Ok(NodeHealth {
+            queue_depth: inner.queue_depth,
+            rate_limit: inner.rate_limit.or(Some((self.limit, self.window))),
+            retry_after: inner.retry_after.max(retry_after),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transport::BucketTransport;
+    use iota_streams_core::{
+        sponge::prp::troika::Troika,
+        tbits::{
+            trinary::Trit,
+            Tbits,
+        },
+    };
+
+    type Msg = TbinaryMessage<Trit, Troika, usize>;
+
+    #[test]
+    fn rate_limited() {
+        let bucket = BucketTransport::<Trit, Troika, usize>::new();
+        let mut transport = RateLimitedTransport::new(bucket, 2, Duration::from_secs(600));
+        assert_eq!(transport.inner().health().unwrap(), NodeHealth::default());
+        assert!(!transport.health().unwrap().is_congested());
+
+        transport.send_message(&Msg::new(1, Tbits::zero(3))).unwrap();
+        transport.send_message(&Msg::new(2, Tbits::zero(3))).unwrap();
+        let health = transport.health().unwrap();
+        assert!(health.is_congested());
+        assert_eq!(health.rate_limit, Some((2, Duration::from_secs(600))));
+
+        let err = transport.send_message(&Msg::new(3, Tbits::zero(3))).unwrap_err();
+        let backpressure = err.downcast_ref::<Backpressure>().unwrap();
+        assert!(backpressure.retry_after > Duration::from_secs(590));
+        assert!(backpressure.retry_after <= health.retry_after.unwrap());
+        assert!(transport.recv_message(&3).is_err());
+
+        // Sends out of the window don't count.
+        let mut transport = RateLimitedTransport::new(transport.into_inner(), 1, Duration::from_millis(1));
+        for link in 3..6 {
+            send_with_backpressure(&mut transport, &Msg::new(link, Tbits::zero(3)), 1).unwrap();
+        }
+        assert_eq!(transport.recv_message(&5).unwrap().link, 5);
+    }
+}
@@ -0,0 +1,238 @@
+//! Transport wrapper caching received messages.
+
+use failure::Fallible;
+use std::{
+    collections::HashMap,
+    hash,
+    time::Duration,
+};
+
+use super::{
+    NodeHealth,
+    Transport,
+};
+use crate::{
+    message::TbinaryMessage,
+    time::{
+        system_time_provider,
+        SharedTimeProvider,
+    },
+};
+
+type Entry<TW, F, Link> = (Duration, Vec<TbinaryMessage<TW, F, Link>>);
+
+/// Transport memoizing received messages for `ttl` so that repeated lookups of the same link,
+/// eg. when resolving previous messages, don't hit the node. At most `capacity` links are
+/// cached, the oldest entry is evicted first.
+///
+/// Only found messages are cached, lookup errors are not. A cached lookup ignores receive
+/// options. Sending a message through the cache invalidates its link.
+pub struct CachedTransport<TW, F, Link, T> {
+    inner: T,
+    ttl: Duration,
+    capacity: usize,
+    cache: HashMap<Link, Entry<TW, F, Link>>,
+    time_provider: SharedTimeProvider,
+}
+
+impl<TW, F, Link, T> CachedTransport<TW, F, Link, T>
+where
+    Link: Eq + hash::Hash + Clone,
+{
+    pub fn new(inner: T, ttl: Duration, capacity: usize) -> Self {
+        Self {
+            inner,
+            ttl,
+            capacity,
+            cache: HashMap::new(),
+            time_provider: system_time_provider(),
+        }
+    }
+
+    /// Expire entries with `time_provider` instead of system time.
+    pub fn with_time_provider(mut self, time_provider: SharedTimeProvider) -> Self {
+        self.time_provider = time_provider;
+        self
+    }
+
+    /// Wrapped transport.
+    pub fn inner(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Drop the cache and return the wrapped transport.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Forget messages cached for `link`.
+    pub fn invalidate(&mut self, link: &Link) {
+        self.cache.remove(link);
+    }
+
+    /// Forget all cached messages.
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    /// Number of cached links, including expired ones not evicted yet.
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    fn insert(&mut self, link: &Link, msgs: Vec<TbinaryMessage<TW, F, Link>>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if !self.cache.contains_key(link) && self.cache.len() >= self.capacity {
+            let oldest = self.cache.iter().min_by_key(|(_, (t, _))| *t).map(|(l, _)| l.clone());
+            if let Some(oldest) = oldest {
+                self.cache.remove(&oldest);
+            }
+        }
+        let now = self.time_provider.now();
+        self.cache.insert(link.clone(), (now, msgs));
+    }
+}
+
+impl<TW, F, Link, T> Transport<TW, F, Link> for CachedTransport<TW, F, Link, T>
+where
+    TW: Clone,
+    Link: Eq + hash::Hash + Clone,
+    T: Transport<TW, F, Link>,
+{
+    type SendOptions = T::SendOptions;
+
+    fn send_message_with_options(&mut self, msg: &TbinaryMessage<TW, F, Link>, opt: Self::SendOptions) -> Fallible<()> {
+        self.invalidate(msg.link());
+        self.inner.send_message_with_options(msg, opt)
+    }
+
+    type RecvOptions = T::RecvOptions;
+
+    fn recv_messages_with_options(
+        &mut self,
+        link: &Link,
+        opt: Self::RecvOptions,
+    ) -> Fallible<Vec<TbinaryMessage<TW, F, Link>>> {
+        let now = self.time_provider.now();
+        match self.cache.get(link) {
+            Some((t, msgs)) if now.saturating_sub(*t) < self.ttl => return Ok(msgs.clone()),
+            Some(_) => self.invalidate(link),
+            None => {}
+        }
+        let msgs = self.inner.recv_messages_with_options(link, opt)?;
+        if !msgs.is_empty() {
+            self.insert(link, msgs.clone());
+        }
+        Ok(msgs)
+    }
+
+    fn health(&mut self) -> Fallible<NodeHealth> {
+        self.inner.health()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        time::ManualTimeProvider,
+        transport::BucketTransport,
+    };
+    use iota_streams_core::{
+        sponge::prp::troika::Troika,
+        tbits::{
+            trinary::Trit,
+            Tbits,
+        },
+    };
+    use std::sync::Arc;
+
+    type Msg = TbinaryMessage<Trit, Troika, usize>;
+
+    /// Bucket transport counting receive requests.
+    struct CountingTransport {
+        bucket: BucketTransport<Trit, Troika, usize>,
+        recvs: usize,
+    }
+
+    impl Transport<Trit, Troika, usize> for CountingTransport {
+        type SendOptions = ();
+
+        fn send_message_with_options(&mut self, msg: &Msg, opt: ()) -> Fallible<()> {
+            self.bucket.send_message_with_options(msg, opt)
+        }
+
+        type RecvOptions = ();
+
+        fn recv_messages_with_options(&mut self, link: &usize, opt: ()) -> Fallible<Vec<Msg>> {
+            self.recvs += 1;
+            self.bucket.recv_messages_with_options(link, opt)
+        }
+    }
+
+    #[test]
+    fn cached() {
+        let counting = CountingTransport {
+            bucket: BucketTransport::new(),
+            recvs: 0,
+        };
+        let mut transport = CachedTransport::new(counting, Duration::from_secs(600), 2);
+        for link in 1..4 {
+            transport.send_message(&Msg::new(link, Tbits::zero(3))).unwrap();
+        }
+
+        // Missing messages are not cached.
+        assert!(transport.recv_message(&0).is_err());
+        assert!(transport.recv_message(&0).is_err());
+        assert_eq!(transport.inner().recvs, 2);
+
+        transport.recv_message(&1).unwrap();
+        transport.recv_message(&1).unwrap();
+        assert_eq!(transport.inner().recvs, 3);
+
+        // Capacity is exceeded, link 1 is evicted.
+        transport.recv_message(&2).unwrap();
+        transport.recv_message(&3).unwrap();
+        assert_eq!(transport.len(), 2);
+        transport.recv_message(&1).unwrap();
+        assert_eq!(transport.inner().recvs, 6);
+
+        // Sending invalidates the link.
+        transport.send_message(&Msg::new(1, Tbits::zero(3))).unwrap();
+        assert_eq!(transport.recv_messages(&1).unwrap().len(), 2);
+        assert_eq!(transport.inner().recvs, 7);
+
+        // Expired entries are fetched again.
+        let mut transport = CachedTransport::new(transport.into_inner(), Duration::from_secs(0), 2);
+        transport.recv_message(&2).unwrap();
+        transport.recv_message(&2).unwrap();
+        assert_eq!(transport.inner().recvs, 9);
+    }
+
+    #[test]
+    fn cached_manual_time() {
+        let counting = CountingTransport {
+            bucket: BucketTransport::new(),
+            recvs: 0,
+        };
+        let clock = ManualTimeProvider::default();
+        let mut transport =
+            CachedTransport::new(counting, Duration::from_secs(60), 2).with_time_provider(Arc::new(clock.clone()));
+        transport.send_message(&Msg::new(1, Tbits::zero(3))).unwrap();
+
+        transport.recv_message(&1).unwrap();
+        clock.advance(Duration::from_secs(59));
+        transport.recv_message(&1).unwrap();
+        assert_eq!(transport.inner().recvs, 1);
+
+        clock.advance(Duration::from_secs(1));
+        transport.recv_message(&1).unwrap();
+        assert_eq!(transport.inner().recvs, 2);
+    }
+}
@@ -0,0 +1,236 @@
+//! Confirmation tracking of sent messages.
+//!
+//! Messages sent to the Tangle may never get confirmed, eg. when they are attached to
+//! lazy tips. Such messages are to be reattached: the same message is attached again,
+//! and the message is confirmed once any of its attachments is.
+
+use failure::{
+    ensure,
+    Fallible,
+};
+use std::{
+    hash,
+    time::Duration,
+};
+
+use super::{
+    BucketTransport,
+    Transport,
+};
+use crate::{
+    message::TbinaryMessage,
+    time::{
+        SystemTimeProvider,
+        TimeProvider,
+    },
+};
+
+/// Confirmation status of a sent message.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ConfirmationStatus {
+    /// Not confirmed yet.
+    Pending,
+    Confirmed,
+}
+
+/// Options of `ConfirmTransport::send_and_confirm_with_options`.
+#[derive(Copy, Clone, Debug)]
+pub struct ConfirmOptions {
+    /// Time to wait for confirmation before the message is reattached.
+    pub timeout: Duration,
+    /// Time between confirmation checks.
+    pub poll_interval: Duration,
+    /// Number of reattachments before giving up.
+    pub max_reattachments: usize,
+}
+
+impl Default for ConfirmOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(180),
+            poll_interval: Duration::from_secs(10),
+            max_reattachments: 3,
+        }
+    }
+}
+
+/// Transport able to check confirmation of sent messages and to reattach unconfirmed ones.
+pub trait ConfirmTransport<TW, F, Link>: Transport<TW, F, Link> {
+    /// Confirmation status of the message sent at `link`.
+    fn confirmation_status(&mut self, link: &Link) -> Fallible<ConfirmationStatus>;
+
+    /// Reattach the message sent at `link`.
+    fn reattach(&mut self, link: &Link, opt: Self::SendOptions) -> Fallible<()>;
+
+    /// Clock measuring confirmation timeouts and waiting between checks, system time by default.
+    fn time_provider(&self) -> &dyn TimeProvider {
+        &SystemTimeProvider
+    }
+
+    /// Send a message and wait until it's confirmed. The message is reattached each time
+    /// it's not confirmed within `confirm.timeout`, `Pending` is returned once
+    /// `confirm.max_reattachments` reattachments have timed out.
+    fn send_and_confirm_with_options(
+        &mut self,
+        msg: &TbinaryMessage<TW, F, Link>,
+        opt: Self::SendOptions,
+        confirm: ConfirmOptions,
+    ) -> Fallible<ConfirmationStatus>
+    where
+        Self::SendOptions: Clone,
+    {
+        self.send_message_with_options(msg, opt.clone())?;
+        let mut reattachments = 0;
+        let mut attached = self.time_provider().now();
+        loop {
+            if self.confirmation_status(msg.link())? == ConfirmationStatus::Confirmed {
+                return Ok(ConfirmationStatus::Confirmed);
+            }
+            if self.time_provider().now().saturating_sub(attached) >= confirm.timeout {
+                if reattachments == confirm.max_reattachments {
+                    return Ok(ConfirmationStatus::Pending);
+                }
+                self.reattach(msg.link(), opt.clone())?;
+                reattachments += 1;
+                attached = self.time_provider().now();
+            }
+            self.time_provider().sleep(confirm.poll_interval);
+        }
+    }
+
+    /// Send a message with default options and wait until it's confirmed, the message is
+    /// reattached each time it's not confirmed within `timeout`.
+    fn send_and_confirm(&mut self, msg: &TbinaryMessage<TW, F, Link>, timeout: Duration) -> Fallible<ConfirmationStatus>
+    where
+        Self::SendOptions: Clone + Default,
+    {
+        let confirm = ConfirmOptions {
+            timeout,
+            ..ConfirmOptions::default()
+        };
+        self.send_and_confirm_with_options(msg, Self::SendOptions::default(), confirm)
+    }
+}
+
+/// Messages in the bucket are confirmed as soon as they are sent.
+impl<TW, F, Link> ConfirmTransport<TW, F, Link> for BucketTransport<TW, F, Link>
+where
+    TW: Clone,
+    Link: Eq + hash::Hash + Clone,
+{
+    fn confirmation_status(&mut self, link: &Link) -> Fallible<ConfirmationStatus> {
+        ensure!(self.bucket.contains_key(link), "Link not found in the bucket.");
+        Ok(ConfirmationStatus::Confirmed)
+    }
+
+    fn reattach(&mut self, link: &Link, _opt: ()) -> Fallible<()> {
+        ensure!(self.bucket.contains_key(link), "Link not found in the bucket.");
+        Ok(())
+    }
+
+    fn time_provider(&self) -> &dyn TimeProvider {
+        &*self.time_provider
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::time::ManualTimeProvider;
+    use iota_streams_core::{
+        sponge::prp::troika::Troika,
+        tbits::{
+            trinary::Trit,
+            Tbits,
+        },
+    };
+    use std::sync::Arc;
+
+    type Msg = TbinaryMessage<Trit, Troika, usize>;
+
+    /// Bucket transport confirming messages after `confirm_after` reattachments.
+    struct LazyTransport {
+        bucket: BucketTransport<Trit, Troika, usize>,
+        confirm_after: usize,
+        reattachments: usize,
+    }
+
+    impl Transport<Trit, Troika, usize> for LazyTransport {
+        type SendOptions = ();
+
+        fn send_message_with_options(&mut self, msg: &Msg, opt: ()) -> Fallible<()> {
+            self.bucket.send_message_with_options(msg, opt)
+        }
+
+        type RecvOptions = ();
+
+        fn recv_messages_with_options(&mut self, link: &usize, opt: ()) -> Fallible<Vec<Msg>> {
+            self.bucket.recv_messages_with_options(link, opt)
+        }
+    }
+
+    impl ConfirmTransport<Trit, Troika, usize> for LazyTransport {
+        fn confirmation_status(&mut self, link: &usize) -> Fallible<ConfirmationStatus> {
+            self.bucket.confirmation_status(link)?;
+            if self.reattachments < self.confirm_after {
+                Ok(ConfirmationStatus::Pending)
+            } else {
+                Ok(ConfirmationStatus::Confirmed)
+            }
+        }
+
+        fn reattach(&mut self, link: &usize, opt: ()) -> Fallible<()> {
+            self.bucket.reattach(link, opt)?;
+            self.reattachments += 1;
+            Ok(())
+        }
+
+        fn time_provider(&self) -> &dyn TimeProvider {
+            self.bucket.time_provider()
+        }
+    }
+
+    #[test]
+    fn send_and_confirm() {
+        let mut transport = LazyTransport {
+            bucket: BucketTransport::new(),
+            confirm_after: 2,
+            reattachments: 0,
+        };
+        let confirm = ConfirmOptions {
+            timeout: Duration::from_secs(0),
+            poll_interval: Duration::from_secs(0),
+            max_reattachments: 1,
+        };
+        let status = transport.send_and_confirm_with_options(&Msg::new(1, Tbits::zero(3)), (), confirm);
+        assert_eq!(status.unwrap(), ConfirmationStatus::Pending);
+        assert_eq!(transport.reattachments, 1);
+
+        transport.reattachments = 0;
+        let confirm = ConfirmOptions {
+            max_reattachments: 3,
+            ..confirm
+        };
+        let status = transport.send_and_confirm_with_options(&Msg::new(2, Tbits::zero(3)), (), confirm);
+        assert_eq!(status.unwrap(), ConfirmationStatus::Confirmed);
+        assert_eq!(transport.reattachments, 2);
+
+        assert!(transport.confirmation_status(&3).is_err());
+    }
+
+    #[test]
+    fn send_and_confirm_manual_time() {
+        let clock = ManualTimeProvider::default();
+        let mut transport = LazyTransport {
+            bucket: BucketTransport::new().with_time_provider(Arc::new(clock.clone())),
+            confirm_after: 4,
+            reattachments: 0,
+        };
+        // Waiting advances the clock, default timeouts don't block.
+        let msg = Msg::new(1, Tbits::zero(3));
+        let status = transport.send_and_confirm_with_options(&msg, (), ConfirmOptions::default());
+        assert_eq!(status.unwrap(), ConfirmationStatus::Pending);
+        assert_eq!(transport.reattachments, 3);
+        assert_eq!(clock.now(), Duration::from_secs(4 * 180));
+    }
+}
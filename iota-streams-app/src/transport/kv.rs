@@ -0,0 +1,219 @@
+//! Transport over a key-value store, eg. for testing or permissioned deployments
+//! running on private infrastructure instead of the Tangle.
+//!
+//! Messages are stored under the key of their link (see `KvKey`). A link may hold
+//! several messages, the value is a sequence of records:
+//!
+//! ```text
+//! record = size:u32be body[packed_len(size)]
+//! ```
+//!
+//! where `size` is the number of trits in message body and body trits are packed 5 per byte.
+//!
+//! Reference stores: `HashMap<Vec<u8>, Vec<u8>>`, `sled::Tree` (`kv-sled` feature, eg. a tree
+//! opened with `db.open_tree("streams")`), `redis::Connection` (`kv-redis` feature).
+
+use failure::{
+    bail,
+    ensure,
+    Fallible,
+};
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+};
+
+use iota_streams_core::tbits::trinary::TritWord;
+
+use super::{
+    tangle::{
+        pack_trits,
+        packed_len,
+        unpack_trits,
+        TangleAddress,
+    },
+    Transport,
+};
+use crate::message::TbinaryMessage;
+
+/// Byte-oriented key-value store.
+pub trait KeyValueStore {
+    /// Value stored at `key`, if any.
+    fn get(&mut self, key: &[u8]) -> Fallible<Option<Vec<u8>>>;
+
+    /// Replace value stored at `key`.
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Fallible<()>;
+
+    /// Append `value` to the value stored at `key`. The default implementation is not atomic,
+    /// stores shared by several writers should override it.
+    fn append(&mut self, key: &[u8], value: &[u8]) -> Fallible<()> {
+        let mut old = self.get(key)?.unwrap_or_default();
+        old.extend_from_slice(value);
+        self.put(key, &old)
+    }
+}
+
+/// Link convertible into a store key.
+pub trait KvKey {
+    fn kv_key(&self) -> Vec<u8>;
+}
+
+impl<TW: TritWord> KvKey for TangleAddress<TW> {
+    fn kv_key(&self) -> Vec<u8> {
+        self.to_packed()
+    }
+}
+
+/// Transport storing messages in a key-value store.
+pub struct KvTransport<K> {
+    store: K,
+}
+
+impl<K> KvTransport<K> {
+    pub fn new(store: K) -> Self {
+        Self { store }
+    }
+
+    /// Wrapped store.
+    pub fn inner(&mut self) -> &mut K {
+        &mut self.store
+    }
+
+    /// Return the wrapped store.
+    pub fn into_inner(self) -> K {
+        self.store
+    }
+}
+
+impl<TW, F, Link, K> Transport<TW, F, Link> for KvTransport<K>
+where
+    TW: TritWord,
+    Link: KvKey + Clone,
+    K: KeyValueStore,
+{
+    type SendOptions = ();
+
+    fn send_message_with_options(&mut self, msg: &TbinaryMessage<TW, F, Link>, _opt: ()) -> Fallible<()> {
+        let size = msg.body.size();
+        ensure!(size <= u32::MAX as usize, "Message is too large: {}.", size);
+        let mut record = Vec::with_capacity(4 + packed_len(size));
+        record.extend_from_slice(&(size as u32).to_be_bytes());
+        pack_trits(&msg.body, &mut record);
+        self.store.append(&msg.link().kv_key(), &record)
+    }
+
+    type RecvOptions = ();
+
+    fn recv_messages_with_options(&mut self, link: &Link, _opt: ()) -> Fallible<Vec<TbinaryMessage<TW, F, Link>>> {
+        let value = match self.store.get(&link.kv_key())? {
+            Some(value) => value,
+            None => bail!("Link not found in the store."),
+        };
+        let mut msgs = Vec::new();
+        let mut rest = &value[..];
+        while !rest.is_empty() {
+            ensure!(rest.len() >= 4, "Truncated record size.");
+            let size = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let end = 4 + packed_len(size);
+            ensure!(rest.len() >= end, "Truncated record body.");
+            msgs.push(TbinaryMessage::new(link.clone(), unpack_trits(&rest[4..end], size)?));
+            rest = &rest[end..];
+        }
+        Ok(msgs)
+    }
+}
+
+impl KeyValueStore for HashMap<Vec<u8>, Vec<u8>> {
+    fn get(&mut self, key: &[u8]) -> Fallible<Option<Vec<u8>>> {
+        Ok(HashMap::get(self, key).cloned())
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Fallible<()> {
+        self.insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn append(&mut self, key: &[u8], value: &[u8]) -> Fallible<()> {
+        self.entry(key.to_vec()).or_default().extend_from_slice(value);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "kv-sled")]
+impl KeyValueStore for sled::Tree {
+    fn get(&mut self, key: &[u8]) -> Fallible<Option<Vec<u8>>> {
+        Ok(sled::Tree::get(self, key)?.map(|v| v.to_vec()))
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Fallible<()> {
+        self.insert(key, value)?;
+        Ok(())
+    }
+
+    fn append(&mut self, key: &[u8], value: &[u8]) -> Fallible<()> {
+        self.fetch_and_update(key, |old| {
+            let mut new = old.map_or_else(Vec::new, |old| old.to_vec());
+            new.extend_from_slice(value);
+            Some(new)
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "kv-redis")]
+impl KeyValueStore for redis::Connection {
+    fn get(&mut self, key: &[u8]) -> Fallible<Option<Vec<u8>>> {
+        Ok(redis::cmd("GET").arg(key).query(self)?)
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Fallible<()> {
+        redis::cmd("SET").arg(key).arg(value).query::<()>(self)?;
+        Ok(())
+    }
+
+    fn append(&mut self, key: &[u8], value: &[u8]) -> Fallible<()> {
+        redis::cmd("APPEND").arg(key).arg(value).query::<usize>(self)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use iota_streams_core::{
+        sponge::prp::troika::Troika,
+        tbits::{
+            trinary::Trit,
+            Tbits,
+        },
+    };
+    use std::str::FromStr;
+
+    type Msg = TbinaryMessage<Trit, Troika, TangleAddress<Trit>>;
+
+    #[test]
+    fn kv_transport() {
+        let mut transport = KvTransport::new(HashMap::new());
+        let link = TangleAddress::<Trit>::from_str(&"A".repeat(81), &"B".repeat(27)).unwrap();
+        let other = TangleAddress::<Trit>::from_str(&"A".repeat(81), &"C".repeat(27)).unwrap();
+        let msgs = vec![
+            Msg::new(link.clone(), Tbits::from_str("FIRST9MESSAGE").unwrap()),
+            Msg::new(link.clone(), Tbits::cycle_str(7, "A")),
+        ];
+        for msg in &msgs {
+            transport.send_message(msg).unwrap();
+        }
+
+        let received: Vec<Msg> = transport.recv_messages(&link).unwrap();
+        assert_eq!(received.len(), 2);
+        assert!(received.iter().zip(msgs.iter()).all(|(r, m)| r.body == m.body));
+        assert!(Transport::<Trit, Troika, _>::recv_message(&mut transport, &link).is_err());
+        assert!(Transport::<Trit, Troika, _>::recv_messages(&mut transport, &other).is_err());
+
+        // Truncated records are rejected.
+        let key = link.kv_key();
+        let value = transport.inner()[&key].clone();
+        transport.inner().insert(key, value[..value.len() - 1].to_vec());
+        assert!(Transport::<Trit, Troika, _>::recv_messages(&mut transport, &link).is_err());
+    }
+}
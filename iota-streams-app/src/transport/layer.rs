@@ -0,0 +1,364 @@
+//! Transport middleware for cross-cutting concerns.
+//!
+//! A `Layer` sees messages sent and received by the transport it wraps and may transform
+//! them, so logging, metrics or payload encryption can be added to any transport. Layers
+//! are stacked with `TransportExt::with`, the layer added last is the outermost one:
+//!
+//! ```ignore
+//! let transport = BucketTransport::new()
+//!     .with(EncryptionLayer::<_, Troika>::new(key)?)
+//!     .with(MetricsLayer::new());
+//! ```
+
+use failure::{
+    ensure,
+    Fallible,
+};
+use std::{
+    fmt,
+    io,
+    marker::PhantomData,
+    sync::{
+        atomic::{
+            AtomicUsize,
+            Ordering,
+        },
+        Arc,
+    },
+};
+
+use iota_streams_core::{
+    prng,
+    sponge::{
+        prp::PRP,
+        spongos::Spongos,
+    },
+    tbits::{
+        word::{
+            BasicTbitWord,
+            RngTbitWord,
+            SpongosTbitWord,
+        },
+        Tbits,
+    },
+};
+
+use super::{
+    NodeHealth,
+    Transport,
+};
+use crate::message::TbinaryMessage;
+
+/// Middleware of a transport, default methods pass messages through unchanged.
+pub trait Layer<TW, F, Link> {
+    /// Process a message before it is sent by the wrapped transport.
+    fn on_send(&mut self, msg: TbinaryMessage<TW, F, Link>) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        Ok(msg)
+    }
+
+    /// Process the result of sending the message at `link`.
+    fn on_sent(&mut self, _link: &Link, result: Fallible<()>) -> Fallible<()> {
+        result
+    }
+
+    /// Process the result of receiving messages at `link` by the wrapped transport.
+    fn on_recv(
+        &mut self,
+        _link: &Link,
+        msgs: Fallible<Vec<TbinaryMessage<TW, F, Link>>>,
+    ) -> Fallible<Vec<TbinaryMessage<TW, F, Link>>> {
+        msgs
+    }
+}
+
+/// Transport wrapped with a layer, see `TransportExt::with`.
+pub struct Layered<T, L> {
+    inner: T,
+    layer: L,
+}
+
+impl<T, L> Layered<T, L> {
+    /// Wrapped transport.
+    pub fn inner(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    pub fn layer(&mut self) -> &mut L {
+        &mut self.layer
+    }
+
+    /// Remove the layer and return the wrapped transport.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<TW, F, Link, T, L> Transport<TW, F, Link> for Layered<T, L>
+where
+    TW: Clone,
+    Link: Clone,
+    T: Transport<TW, F, Link>,
+    L: Layer<TW, F, Link>,
+{
+    type SendOptions = T::SendOptions;
+
+    fn send_message_with_options(&mut self, msg: &TbinaryMessage<TW, F, Link>, opt: Self::SendOptions) -> Fallible<()> {
+        let msg = self.layer.on_send(msg.clone())?;
+        let result = self.inner.send_message_with_options(&msg, opt);
+        self.layer.on_sent(&msg.link, result)
+    }
+
+    type RecvOptions = T::RecvOptions;
+
+    fn recv_messages_with_options(
+        &mut self,
+        link: &Link,
+        opt: Self::RecvOptions,
+    ) -> Fallible<Vec<TbinaryMessage<TW, F, Link>>> {
+        let msgs = self.inner.recv_messages_with_options(link, opt);
+        self.layer.on_recv(link, msgs)
+    }
+
+    fn health(&mut self) -> Fallible<NodeHealth> {
+        self.inner.health()
+    }
+}
+
+/// Extension of transports with middleware layers.
+pub trait TransportExt: Sized {
+    /// Wrap the transport with `layer`.
+    fn with<L>(self, layer: L) -> Layered<Self, L> {
+        Layered { inner: self, layer }
+    }
+}
+
+impl<T> TransportExt for T {}
+
+/// Layer writing a line per send and receive request, write errors are ignored.
+pub struct LoggingLayer<W> {
+    writer: W,
+}
+
+impl<W> LoggingLayer<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<TW, F, Link, W> Layer<TW, F, Link> for LoggingLayer<W>
+where
+    Link: fmt::Display,
+    W: io::Write,
+{
+    fn on_sent(&mut self, link: &Link, result: Fallible<()>) -> Fallible<()> {
+        let _ = match &result {
+            Ok(()) => writeln!(self.writer, "send {}", link),
+            Err(err) => writeln!(self.writer, "send {} failed: {}", link, err),
+        };
+        result
+    }
+
+    fn on_recv(
+        &mut self,
+        link: &Link,
+        msgs: Fallible<Vec<TbinaryMessage<TW, F, Link>>>,
+    ) -> Fallible<Vec<TbinaryMessage<TW, F, Link>>> {
+        let _ = match &msgs {
+            Ok(msgs) => writeln!(self.writer, "recv {}: {} messages", link, msgs.len()),
+            Err(err) => writeln!(self.writer, "recv {} failed: {}", link, err),
+        };
+        msgs
+    }
+}
+
+/// Counters of a `MetricsLayer`, message sizes are in tbits.
+#[derive(Default, Debug)]
+pub struct TransportMetrics {
+    pub sent: AtomicUsize,
+    pub sent_tbits: AtomicUsize,
+    pub send_errors: AtomicUsize,
+    pub received: AtomicUsize,
+    pub received_tbits: AtomicUsize,
+    pub recv_errors: AtomicUsize,
+}
+
+/// Layer counting messages, the counters can be read while the transport is in use.
+#[derive(Default)]
+pub struct MetricsLayer {
+    metrics: Arc<TransportMetrics>,
+    /// Size of the message being sent.
+    sending: usize,
+}
+
+impl MetricsLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shared counters.
+    pub fn metrics(&self) -> Arc<TransportMetrics> {
+        self.metrics.clone()
+    }
+}
+
+impl<TW, F, Link> Layer<TW, F, Link> for MetricsLayer
+where
+    TW: BasicTbitWord,
+{
+    fn on_send(&mut self, msg: TbinaryMessage<TW, F, Link>) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        self.sending = msg.body.size();
+        Ok(msg)
+    }
+
+    fn on_sent(&mut self, _link: &Link, result: Fallible<()>) -> Fallible<()> {
+        if result.is_ok() {
+            self.metrics.sent.fetch_add(1, Ordering::Relaxed);
+            self.metrics.sent_tbits.fetch_add(self.sending, Ordering::Relaxed);
+        } else {
+            self.metrics.send_errors.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+
+    fn on_recv(
+        &mut self,
+        _link: &Link,
+        msgs: Fallible<Vec<TbinaryMessage<TW, F, Link>>>,
+    ) -> Fallible<Vec<TbinaryMessage<TW, F, Link>>> {
+        match &msgs {
+            Ok(msgs) => {
+                let tbits: usize = msgs.iter().map(|msg| msg.body.size()).sum();
+                self.metrics.received.fetch_add(msgs.len(), Ordering::Relaxed);
+                self.metrics.received_tbits.fetch_add(tbits, Ordering::Relaxed);
+            }
+            Err(_) => {
+                self.metrics.recv_errors.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        msgs
+    }
+}
+
+/// Layer encrypting message bodies at rest, eg. in a bucket or key-value store transport.
+/// Links are not encrypted. Stored body layout is `nonce body' mac` where `body'` is
+/// encrypted with spongos keyed with `key` and `nonce`, the nonce is random per message.
+pub struct EncryptionLayer<TW, G> {
+    key: Tbits<TW>,
+    _phantom: PhantomData<G>,
+}
+
+impl<TW, G> EncryptionLayer<TW, G>
+where
+    TW: SpongosTbitWord,
+    G: PRP<TW> + Default,
+{
+    pub fn new(key: Tbits<TW>) -> Fallible<Self> {
+        ensure!(
+            key.size() == Spongos::<TW, G>::KEY_SIZE,
+            "Bad at-rest key size: {}.",
+            key.size()
+        );
+        Ok(Self {
+            key,
+            _phantom: PhantomData,
+        })
+    }
+
+    fn spongos(&self, nonce: &Tbits<TW>) -> Spongos<TW, G> {
+        let mut s = Spongos::<TW, G>::init();
+        s.absorb_tbits(&self.key);
+        s.absorb_tbits(nonce);
+        s.commit();
+        s
+    }
+
+    fn decrypt<F, Link>(&self, mut msg: TbinaryMessage<TW, F, Link>) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        let (nonce_size, mac_size) = (Spongos::<TW, G>::NONCE_SIZE, Spongos::<TW, G>::MAC_SIZE);
+        let size = msg.body.size();
+        ensure!(size >= nonce_size + mac_size, "Bad at-rest body size: {}.", size);
+        let nonce = Tbits::from_slice(msg.body.slice().take(nonce_size));
+        let encrypted = Tbits::from_slice(msg.body.slice().drop(nonce_size).take(size - nonce_size - mac_size));
+        let mac = Tbits::from_slice(msg.body.slice().drop(size - mac_size));
+        let mut s = self.spongos(&nonce);
+        msg.body = s.decrypt_tbits(&encrypted);
+        ensure!(s.squeeze_tag() == mac, "At-rest MAC check failed.");
+        Ok(msg)
+    }
+}
+
+impl<TW, F, Link, G> Layer<TW, F, Link> for EncryptionLayer<TW, G>
+where
+    TW: SpongosTbitWord + RngTbitWord,
+    G: PRP<TW> + Default,
+{
+    fn on_send(&mut self, mut msg: TbinaryMessage<TW, F, Link>) -> Fallible<TbinaryMessage<TW, F, Link>> {
+        let nonce = prng::random_nonce(Spongos::<TW, G>::NONCE_SIZE);
+        let mut s = self.spongos(&nonce);
+        let encrypted = s.encrypt_tbits(&msg.body);
+        let mac = s.squeeze_tag();
+        msg.body = Tbits::from_slices(&[nonce.slice(), encrypted.slice(), mac.slice()]);
+        Ok(msg)
+    }
+
+    fn on_recv(
+        &mut self,
+        _link: &Link,
+        msgs: Fallible<Vec<TbinaryMessage<TW, F, Link>>>,
+    ) -> Fallible<Vec<TbinaryMessage<TW, F, Link>>> {
+        msgs?.into_iter().map(|msg| self.decrypt(msg)).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transport::BucketTransport;
+    use iota_streams_core::{
+        sponge::prp::troika::Troika,
+        tbits::trinary::Trit,
+    };
+
+    type Msg = TbinaryMessage<Trit, Troika, usize>;
+
+    #[test]
+    fn layers() {
+        let key = prng::random_key(Spongos::<Trit, Troika>::KEY_SIZE);
+        let metrics = MetricsLayer::new();
+        let counters = metrics.metrics();
+        let mut transport = BucketTransport::<Trit, Troika, usize>::new()
+            .with(EncryptionLayer::<Trit, Troika>::new(key).unwrap())
+            .with(metrics)
+            .with(LoggingLayer::new(Vec::new()));
+
+        let body = Tbits::cycle_str(30, "BODY");
+        transport.send_message(&Msg::new(1, body.clone())).unwrap();
+        assert!(transport.recv_message(&1).unwrap().body == body);
+        assert!(transport.recv_message(&2).is_err());
+        assert_eq!(1, counters.sent.load(Ordering::Relaxed));
+        assert_eq!(30, counters.sent_tbits.load(Ordering::Relaxed));
+        assert_eq!(1, counters.received.load(Ordering::Relaxed));
+        assert_eq!(1, counters.recv_errors.load(Ordering::Relaxed));
+
+        // Stored body is encrypted, tampering is detected.
+        let bucket = transport.inner().inner().inner();
+        let mut stored = bucket.recv_message(&1).unwrap();
+        assert!(stored.body.size() > 30 && stored.body != body);
+        stored.link = 3;
+        let size = stored.body.size();
+        let t = stored.body.slice().drop(size / 2).take(1).get_isize();
+        stored.body.slice_mut().drop(size / 2).take(1).put_isize(if t == 1 { -1 } else { t + 1 });
+        bucket.send_message(&stored).unwrap();
+        assert!(transport.recv_message(&3).is_err());
+
+        let log = String::from_utf8(transport.layer().writer.clone()).unwrap();
+        let lines: Vec<_> = log.lines().collect();
+        assert_eq!(4, lines.len());
+        assert_eq!("send 1", lines[0]);
+        assert_eq!("recv 1: 1 messages", lines[1]);
+        assert!(lines[3].starts_with("recv 3 failed: At-rest MAC check failed."));
+    }
+}
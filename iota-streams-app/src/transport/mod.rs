@@ -8,7 +8,13 @@ use std::{
     hash,
 };
 
-use crate::message::TbinaryMessage;
+use crate::{
+    message::TbinaryMessage,
+    time::{
+        system_time_provider,
+        SharedTimeProvider,
+    },
+};
 
 /// Network transport abstraction.
 /// Parametrized by the type of message links.
@@ -66,10 +72,18 @@ pub trait Transport<TW, F, Link> /* where Link: HasLink */ {
     {
         self.recv_message_with_options(link, Self::RecvOptions::default())
     }
+
+    /// Congestion state of the node, transports not reporting congestion are always healthy.
+    fn health(&mut self) -> Fallible<NodeHealth> {
+        Ok(NodeHealth::default())
+    }
 }
 
 pub struct BucketTransport<TW, F, Link> {
-    bucket: HashMap<Link, Vec<TbinaryMessage<TW, F, Link>>>,
+    /// Sent messages and their attachment timestamps.
+    bucket: HashMap<Link, Vec<(TbinaryMessage<TW, F, Link>, i64)>>,
+    /// Clock of attachment timestamps.
+    time_provider: SharedTimeProvider,
 }
 
 impl<TW, F, Link> BucketTransport<TW, F, Link>
@@ -77,7 +91,38 @@ where
     Link: Eq + hash::Hash,
 {
     pub fn new() -> Self {
-        Self { bucket: HashMap::new() }
+        Self {
+            bucket: HashMap::new(),
+            time_provider: system_time_provider(),
+        }
+    }
+
+    /// Timestamp sent messages with `time_provider` instead of system time.
+    pub fn with_time_provider(mut self, time_provider: SharedTimeProvider) -> Self {
+        self.time_provider = time_provider;
+        self
+    }
+}
+
+impl<TW, F, Link> BucketTransport<TW, F, Link>
+where
+    TW: Clone,
+    Link: Eq + hash::Hash + Clone,
+{
+    /// Send a message attached at `timestamp` in milliseconds since UNIX epoch.
+    pub fn send_message_at(&mut self, msg: &TbinaryMessage<TW, F, Link>, timestamp: i64) {
+        self.bucket
+            .entry(msg.link().clone())
+            .or_insert_with(Vec::new)
+            .push((msg.clone(), timestamp));
+    }
+
+    fn recv_timestamped(&self, link: &Link) -> Fallible<Vec<(TbinaryMessage<TW, F, Link>, i64)>> {
+        if let Some(msgs) = self.bucket.get(link) {
+            Ok(msgs.clone())
+        } else {
+            bail!("Link not found in the bucket.")
+        }
     }
 }
 
@@ -89,24 +134,34 @@ where
     type SendOptions = ();
 
     fn send_message_with_options(&mut self, msg: &TbinaryMessage<TW, F, Link>, _opt: ()) -> Fallible<()> {
-        if let Some(msgs) = self.bucket.get_mut(msg.link()) {
-            msgs.push(msg.clone());
-            Ok(())
-        } else {
-            self.bucket.insert(msg.link().clone(), vec![msg.clone()]);
-            Ok(())
-        }
+        let timestamp = self.time_provider.now().as_millis() as i64;
+        self.send_message_at(msg, timestamp);
+        Ok(())
     }
 
     type RecvOptions = ();
 
     fn recv_messages_with_options(&mut self, link: &Link, _opt: ()) -> Fallible<Vec<TbinaryMessage<TW, F, Link>>> {
-        if let Some(msgs) = self.bucket.get(link) {
-            Ok(msgs.clone())
-        } else {
-            bail!("Link not found in the bucket.")
-        }
+        Ok(self.recv_timestamped(link)?.into_iter().map(|(msg, _)| msg).collect())
     }
 }
 
+mod backpressure;
+pub use backpressure::*;
+
+mod cached;
+pub use cached::*;
+
+mod confirm;
+pub use confirm::*;
+
+mod kv;
+pub use kv::*;
+
+mod layer;
+pub use layer::*;
+
+mod timestamp;
+pub use timestamp::*;
+
 pub mod tangle;
@@ -1,12 +1,13 @@
-use chrono::Utc;
 use failure::{
     bail,
+    ensure,
     Fallible,
 };
 use std::{
     convert::TryInto,
     str::FromStr,
     string::ToString,
+    time::Duration,
 };
 
 use iota_constants::HASH_TRINARY_SIZE as HASH_LENGTH;
@@ -20,14 +21,20 @@ use iota_lib_rs::prelude::{
 };
 
 use iota_streams_core::tbits::{
+    trinary,
     word::{
         BasicTbitWord,
+        SpongosTbitWord,
         StringTbitWord,
     },
     TbitSlice,
     Tbits,
 };
 
+use crate::time::{
+    system_time_provider,
+    SharedTimeProvider,
+};
 use crate::transport::{
     tangle::*,
     *,
@@ -208,6 +215,10 @@ pub fn bundles_from_trytes(trytes: &Vec<iota_conversion::Trytes>) -> Vec<iota_mo
             // reverse order of txs will be extracted from back with `pop`
             .then(x.current_index.cmp(&y.current_index).reverse())
     });
+    // Reattached transactions differ in attachment fields only, keep one of them.
+    txs.dedup_by(|x, y| {
+        x.address == y.address && x.tag == y.tag && x.bundle == y.bundle && x.current_index == y.current_index
+    });
 
     let mut bundles = Vec::new();
 
@@ -265,6 +276,25 @@ where
     bundle
 }
 
+/// Convert message sent at `now` since UNIX epoch to bundle indexed with `index_strategy`.
+pub fn msg_to_indexed_bundle<TW, F>(
+    msg: &TbinaryMessage<TW, F, TangleAddress<TW>>,
+    index_strategy: IndexStrategy,
+    now: Duration,
+) -> Fallible<iota_model::Bundle>
+where
+    TW: StringTbitWord + SpongosTbitWord + trinary::TritWord,
+{
+    let nonce = (now.as_secs() as usize)
+        .wrapping_mul(1_000_000_000)
+        .wrapping_add(now.subsec_nanos() as usize);
+    let (address, tag) = index_strategy.index(msg.link(), nonce)?;
+    let mut bundle = iota_model::Bundle::new(make_txs(&address, &tag, &msg.body, now.as_secs() as i64));
+    bundle.reset_indexes();
+    ensure!(bundle.finalize().is_ok(), "Bundle finalization failed.");
+    Ok(bundle)
+}
+
 /// Reconstruct STREAMS Message from bundle. The input bundle is not checked (for validity of
 /// the hash, consistency of indices, etc.). Checked bundles are returned by `bundles_from_trytes`.
 pub fn msg_from_bundle<TW, F>(bundle: &iota_model::Bundle) -> TbinaryMessage<TW, F, TangleAddress<TW>>
@@ -361,12 +391,16 @@ fn test_bundle_from_to_trytes() {
 }
 
 /// Stripped version of `iota_client::options::SendTrytesOptions<'a>` due to lifetime parameter.
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct SendTrytesOptions {
     pub depth: usize,
     pub min_weight_magnitude: usize,
     pub local_pow: bool,
     pub threads: usize,
+    /// Indexation of the message transactions, see `IndexStrategy`.
+    pub index_strategy: IndexStrategy,
+    /// Clock of transaction timestamps and index nonces, system time by default.
+    pub time_provider: SharedTimeProvider,
 }
 
 impl Default for SendTrytesOptions {
@@ -376,22 +410,26 @@ impl Default for SendTrytesOptions {
             min_weight_magnitude: 14,
             local_pow: true,
             threads: num_cpus::get(),
+            index_strategy: IndexStrategy::default(),
+            time_provider: system_time_provider(),
         }
     }
 }
 
 impl<'a, TW, F> Transport<TW, F, TangleAddress<TW>> for iota_client::Client<'a>
 where
-    TW: StringTbitWord,
+    TW: StringTbitWord + SpongosTbitWord + trinary::TritWord,
 {
     type SendOptions = SendTrytesOptions;
 
-    /// Send a Streams message over the Tangle with the current timestamp and default SendTrytesOptions.
+    /// Send a Streams message over the Tangle timestamped with `opt.time_provider`.
     fn send_message_with_options(
         &mut self,
         msg: &TbinaryMessage<TW, F, TangleAddress<TW>>,
         opt: Self::SendOptions,
     ) -> Fallible<()> {
+        let bundle = msg_to_indexed_bundle(msg, opt.index_strategy, opt.time_provider.now())?;
+        let trytes = bundle_to_trytes(&bundle);
         let opt = iota_client::options::SendTrytesOptions {
             depth: opt.depth,
             min_weight_magnitude: opt.min_weight_magnitude,
@@ -399,9 +437,6 @@ where
             threads: opt.threads,
             reference: None,
         };
-        let timestamp = Utc::now().timestamp();
-        let bundle = msg_to_bundle(msg, timestamp);
-        let trytes = bundle_to_trytes(&bundle);
         // Ignore PoWed transactions.
         let _txs = self.send_trytes(&trytes, opt)?;
         Ok(())
@@ -409,36 +444,120 @@ where
 
     type RecvOptions = ();
 
-    /// Receive a message.
+    /// Receive a message, messages sent with any `IndexStrategy` are found.
     fn recv_messages_with_options(
         &mut self,
         link: &TangleAddress<TW>,
         _opt: Self::RecvOptions,
     ) -> Fallible<Vec<TbinaryMessage<TW, F, TangleAddress<TW>>>> {
+        let hashes = find_hashes(self, link)?;
+        Ok(bundles_from_trytes(&get_trytes(self, &hashes)?)
+            .into_iter()
+            .map(|bundle| msg_at(link, &bundle))
+            .collect())
+    }
+}
+
+/// Message at `link` reconstructed from a bundle found by `find_hashes`.
+/// Privately indexed bundles don't carry the link.
+fn msg_at<TW, F>(link: &TangleAddress<TW>, bundle: &iota_model::Bundle) -> TbinaryMessage<TW, F, TangleAddress<TW>>
+where
+    TW: StringTbitWord,
+{
+    let mut msg = msg_from_bundle(bundle);
+    msg.link = link.clone();
+    msg
+}
+
+/// Hashes of transactions at `link` indexed with any `IndexStrategy`, including all attachments.
+fn find_hashes<TW>(client: &mut iota_client::Client<'_>, link: &TangleAddress<TW>) -> Fallible<Vec<String>>
+where
+    TW: StringTbitWord + SpongosTbitWord + trinary::TritWord,
+{
+    let mut hashes = Vec::new();
+    for index_strategy in &[IndexStrategy::Link, IndexStrategy::Private] {
+        let (address, tag) = index_strategy.query(link)?;
         let find_opt = iota_client::options::FindTransactionsOptions {
             bundles: Vec::new(),
-            addresses: vec![link.appinst.to_string()],
-            tags: vec![link.msgid.to_string()],
+            addresses: vec![address.to_string()],
+            tags: tag.map_or(Vec::new(), |tag| vec![tag.to_string()]),
             approvees: Vec::new(),
         };
 
-        let find_resp = self.find_transactions(find_opt)?;
+        let find_resp = client.find_transactions(find_opt)?;
         if let Some(e) = find_resp.error() {
             bail!("Find transactions failed with: {}.", e)
-        } else if let Some(hashes) = find_resp.take_hashes() {
-            let get_resp = self.get_trytes(&hashes)?;
-            if let Some(e) = get_resp.error() {
-                bail!("Get trytes failed with: {}.", e)
-            } else if let Some(trytes) = get_resp.take_trytes() {
-                return Ok(bundles_from_trytes(&trytes)
-                    .into_iter()
-                    .map(|bundle| msg_from_bundle(&bundle))
-                    .collect());
-            } else {
-                bail!("Get trytes contains no trytes.")
-            }
+        } else if let Some(found) = find_resp.take_hashes() {
+            hashes.extend(found);
         } else {
             bail!("Find transactions contains no hashes.")
         }
     }
+    Ok(hashes)
+}
+
+fn get_trytes(client: &mut iota_client::Client<'_>, hashes: &[String]) -> Fallible<Vec<iota_conversion::Trytes>> {
+    let get_resp = client.get_trytes(hashes)?;
+    if let Some(e) = get_resp.error() {
+        bail!("Get trytes failed with: {}.", e)
+    } else if let Some(trytes) = get_resp.take_trytes() {
+        Ok(trytes)
+    } else {
+        bail!("Get trytes contains no trytes.")
+    }
+}
+
+impl<'a, TW, F> ConfirmTransport<TW, F, TangleAddress<TW>> for iota_client::Client<'a>
+where
+    TW: StringTbitWord + SpongosTbitWord + trinary::TritWord,
+{
+    /// The message is confirmed once any transaction of any of its attachments is.
+    fn confirmation_status(&mut self, link: &TangleAddress<TW>) -> Fallible<ConfirmationStatus> {
+        let hashes = find_hashes(self, link)?;
+        if self.get_latest_inclusion(&hashes)?.into_iter().any(|included| included) {
+            Ok(ConfirmationStatus::Confirmed)
+        } else {
+            Ok(ConfirmationStatus::Pending)
+        }
+    }
+
+    /// Attach bundles found at `link` to new tips, bundle hashes are not changed.
+    fn reattach(&mut self, link: &TangleAddress<TW>, opt: Self::SendOptions) -> Fallible<()> {
+        let hashes = find_hashes(self, link)?;
+        let bundles = bundles_from_trytes(&get_trytes(self, &hashes)?);
+        ensure!(!bundles.is_empty(), "No valid bundle found at the link.");
+        for bundle in bundles {
+            let opt = iota_client::options::SendTrytesOptions {
+                depth: opt.depth,
+                min_weight_magnitude: opt.min_weight_magnitude,
+                local_pow: opt.local_pow,
+                threads: opt.threads,
+                reference: None,
+            };
+            // Ignore PoWed transactions.
+            let _txs = self.send_trytes(&bundle_to_trytes(&bundle), opt)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, TW, F> TimestampTransport<TW, F, TangleAddress<TW>> for iota_client::Client<'a>
+where
+    TW: StringTbitWord + SpongosTbitWord + trinary::TritWord,
+{
+    /// Messages are timestamped with attachment timestamp of the first transaction of the bundle.
+    fn recv_messages_with_timestamps(
+        &mut self,
+        link: &TangleAddress<TW>,
+        _opt: Self::RecvOptions,
+    ) -> Fallible<Vec<(TbinaryMessage<TW, F, TangleAddress<TW>>, i64)>> {
+        let hashes = find_hashes(self, link)?;
+        Ok(bundles_from_trytes(&get_trytes(self, &hashes)?)
+            .into_iter()
+            .map(|bundle| {
+                let timestamp = bundle.iter().next().map_or(0, |tx| tx.attachment_timestamp);
+                (msg_at(link, &bundle), timestamp)
+            })
+            .collect())
+    }
 }
@@ -0,0 +1,299 @@
+//! Text encodings of Tangle links matching conventions of other tooling.
+//!
+//! A codec encodes packed `TangleAddress` bytes (see `TangleAddress::to_packed`) into a
+//! string and back. `Base64Url` is the encoding of compact links, `Hex`, `Base58`
+//! (Bitcoin alphabet) and `Bech32` (BIP-173 checksum, without the 90 characters limit
+//! as a packed link is longer) are provided for interoperability. `Base45` (RFC 9285)
+//! fits the alphanumeric mode of QR codes.
+
+use failure::{
+    bail,
+    ensure,
+    Fallible,
+};
+
+use iota_streams_core::tbits::trinary::TritWord;
+
+use super::*;
+
+/// Link text encoding.
+pub trait LinkCodec {
+    /// Encode bytes into string.
+    fn encode(&self, bytes: &[u8]) -> String;
+
+    /// Decode string produced by `encode`.
+    fn decode(&self, s: &str) -> Fallible<Vec<u8>>;
+}
+
+/// Unpadded base64url encoding, the same as `TangleAddress::to_compact`.
+pub struct Base64Url;
+
+impl LinkCodec for Base64Url {
+    fn encode(&self, bytes: &[u8]) -> String {
+        encode_base64url(bytes)
+    }
+
+    fn decode(&self, s: &str) -> Fallible<Vec<u8>> {
+        decode_base64url(s)
+    }
+}
+
+/// Lowercase hex encoding, upper case is accepted on decoding.
+pub struct Hex;
+
+impl LinkCodec for Hex {
+    fn encode(&self, bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn decode(&self, s: &str) -> Fallible<Vec<u8>> {
+        ensure!(s.len() % 2 == 0, "Bad hex length: {}.", s.len());
+        s.as_bytes()
+            .chunks(2)
+            .map(|c| match (hex_digit(c[0]), hex_digit(c[1])) {
+                (Some(h), Some(l)) => Ok(h << 4 | l),
+                _ => bail!("Bad hex digits: '{}{}'.", c[0] as char, c[1] as char),
+            })
+            .collect()
+    }
+}
+
+fn hex_digit(c: u8) -> Option<u8> {
+    (c as char).to_digit(16).map(|d| d as u8)
+}
+
+const BASE58: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Base58 encoding with Bitcoin alphabet, leading zero bytes are encoded as `1`s.
+pub struct Base58;
+
+impl LinkCodec for Base58 {
+    fn encode(&self, bytes: &[u8]) -> String {
+        let zeros = bytes.iter().take_while(|b| **b == 0).count();
+        // Base58 digits, the least significant first.
+        let mut digits: Vec<u8> = Vec::with_capacity(bytes.len() * 138 / 100 + 1);
+        for b in &bytes[zeros..] {
+            let mut carry = *b as u32;
+            for d in digits.iter_mut() {
+                carry += (*d as u32) << 8;
+                *d = (carry % 58) as u8;
+                carry /= 58;
+            }
+            while carry > 0 {
+                digits.push((carry % 58) as u8);
+                carry /= 58;
+            }
+        }
+        let mut s = "1".repeat(zeros);
+        s.extend(digits.iter().rev().map(|d| BASE58[*d as usize] as char));
+        s
+    }
+
+    fn decode(&self, s: &str) -> Fallible<Vec<u8>> {
+        let zeros = s.bytes().take_while(|c| *c == b'1').count();
+        // Bytes, the least significant first.
+        let mut bytes: Vec<u8> = Vec::with_capacity(s.len() * 733 / 1000 + 1);
+        for c in s.bytes().skip(zeros) {
+            let mut carry = match BASE58.iter().position(|x| *x == c) {
+                Some(v) => v as u32,
+                None => bail!("Bad base58 character: '{}'.", c as char),
+            };
+            for b in bytes.iter_mut() {
+                carry += (*b as u32) * 58;
+                *b = carry as u8;
+                carry >>= 8;
+            }
+            while carry > 0 {
+                bytes.push(carry as u8);
+                carry >>= 8;
+            }
+        }
+        bytes.extend(std::iter::repeat(0).take(zeros));
+        bytes.reverse();
+        Ok(bytes)
+    }
+}
+
+const BECH32: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Bech32 encoding with human-readable part `hrp`.
+pub struct Bech32 {
+    pub hrp: String,
+}
+
+impl Bech32 {
+    pub fn new(hrp: &str) -> Self {
+        Self {
+            hrp: hrp.to_lowercase(),
+        }
+    }
+
+    fn polymod(values: impl Iterator<Item = u8>) -> u32 {
+        const GEN: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+        values.fold(1_u32, |chk, v| {
+            let b = chk >> 25;
+            let chk = (chk & 0x1ffffff) << 5 ^ v as u32;
+            (0..5).fold(chk, |chk, i| if (b >> i) & 1 == 1 { chk ^ GEN[i] } else { chk })
+        })
+    }
+
+    fn hrp_expand(&self) -> Vec<u8> {
+        let hrp = self.hrp.as_bytes();
+        let mut v: Vec<u8> = hrp.iter().map(|c| c >> 5).collect();
+        v.push(0);
+        v.extend(hrp.iter().map(|c| c & 31));
+        v
+    }
+}
+
+/// Regroup bits of `data` from `from`-bit to `to`-bit values.
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Fallible<Vec<u8>> {
+    let mut acc = 0_u32;
+    let mut bits = 0_u32;
+    let mut out = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    let max = (1_u32 << to) - 1;
+    for v in data {
+        acc = acc << from | *v as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            out.push((acc >> bits & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            out.push((acc << (to - bits) & max) as u8);
+        }
+    } else {
+        ensure!(bits < from && acc << (to - bits) & max == 0, "Non-zero padding in bech32 data.");
+    }
+    Ok(out)
+}
+
+impl LinkCodec for Bech32 {
+    fn encode(&self, bytes: &[u8]) -> String {
+        // Regrouping into 5-bit values with padding doesn't fail.
+        let data = convert_bits(bytes, 8, 5, true).unwrap();
+        let values = self.hrp_expand().into_iter().chain(data.iter().cloned()).chain([0; 6].iter().cloned());
+        let chk = Self::polymod(values) ^ 1;
+        let mut s = self.hrp.clone();
+        s.push('1');
+        s.extend(data.iter().map(|d| BECH32[*d as usize] as char));
+        s.extend((0..6).map(|i| BECH32[(chk >> (5 * (5 - i)) & 31) as usize] as char));
+        s
+    }
+
+    fn decode(&self, s: &str) -> Fallible<Vec<u8>> {
+        ensure!(
+            s.to_lowercase() == s || s.to_uppercase() == s,
+            "Mixed case bech32 string."
+        );
+        let s = s.to_lowercase();
+        let (hrp, data) = match s.rfind('1') {
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None => bail!("Bech32 separator not found."),
+        };
+        ensure!(hrp == self.hrp, "Bad bech32 human-readable part: '{}'.", hrp);
+        ensure!(data.len() >= 6, "Bech32 checksum is missing.");
+        let mut values = Vec::with_capacity(data.len());
+        for c in data.bytes() {
+            match BECH32.iter().position(|x| *x == c) {
+                Some(v) => values.push(v as u8),
+                None => bail!("Bad bech32 character: '{}'.", c as char),
+            }
+        }
+        ensure!(
+            Self::polymod(self.hrp_expand().into_iter().chain(values.iter().cloned())) == 1,
+            "Bad bech32 checksum."
+        );
+        convert_bits(&values[..values.len() - 6], 5, 8, false)
+    }
+}
+
+const BASE45: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// Base45 encoding (RFC 9285), its alphabet is the QR code alphanumeric one.
+pub struct Base45;
+
+impl LinkCodec for Base45 {
+    fn encode(&self, bytes: &[u8]) -> String {
+        let mut s = String::with_capacity((bytes.len() * 3 + 1) / 2);
+        for chunk in bytes.chunks(2) {
+            let mut n = chunk.iter().fold(0_u32, |n, b| n << 8 | *b as u32);
+            for _ in 0..chunk.len() + 1 {
+                s.push(BASE45[(n % 45) as usize] as char);
+                n /= 45;
+            }
+        }
+        s
+    }
+
+    fn decode(&self, s: &str) -> Fallible<Vec<u8>> {
+        ensure!(s.len() % 3 != 1, "Bad base45 length: {}.", s.len());
+        let mut bytes = Vec::with_capacity(s.len() * 2 / 3);
+        for chunk in s.as_bytes().chunks(3) {
+            let mut n = 0_u32;
+            for c in chunk.iter().rev() {
+                match BASE45.iter().position(|x| x == c) {
+                    Some(v) => n = n * 45 + v as u32,
+                    None => bail!("Bad base45 character: '{}'.", *c as char),
+                }
+            }
+            if chunk.len() == 3 {
+                ensure!(n <= 0xffff, "Bad base45 triple: '{}'.", String::from_utf8_lossy(chunk));
+                bytes.push((n >> 8) as u8);
+            } else {
+                ensure!(n <= 0xff, "Bad base45 pair: '{}'.", String::from_utf8_lossy(chunk));
+            }
+            bytes.push(n as u8);
+        }
+        Ok(bytes)
+    }
+}
+
+impl<TW> TangleAddress<TW>
+where
+    TW: TritWord,
+{
+    /// Encode link with `codec`.
+    pub fn encode<C: LinkCodec>(&self, codec: &C) -> String {
+        codec.encode(&self.to_packed())
+    }
+
+    /// Decode link encoded with `codec`.
+    pub fn decode<C: LinkCodec>(codec: &C, s: &str) -> Fallible<Self> {
+        Self::from_packed(&codec.decode(s)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use iota_streams_core::tbits::trinary::Trit;
+
+    fn check<C: LinkCodec>(codec: &C, bytes: &[u8], s: &str) {
+        assert_eq!(codec.encode(bytes), s);
+        assert_eq!(codec.decode(s).unwrap(), bytes);
+    }
+
+    #[test]
+    fn codecs() {
+        check(&Hex, b"\x00\x01\xfe", "0001fe");
+        assert_eq!(Hex.decode("0001FE").unwrap(), b"\x00\x01\xfe");
+        check(&Base58, b"Hello World!", "2NEpo7TZRRrLZSi2U");
+        check(&Base58, b"\x00\x00\x01", "112");
+        check(&Base64Url, b"\xfb\xff", "-_8");
+        check(&Base45, b"AB", "BB8");
+        check(&Base45, b"Hello!!", "%69 VD92EX0");
+        check(&Base45, b"base-45", "UJCLQE7W581");
+        assert!(Base45.decode("GGW").is_err());
+        check(&Bech32::new("a"), b"", "a12uel5l");
+        let bytes = Hex.decode("00443214c74254b635cf84653a56d7c675be77df").unwrap();
+        check(&Bech32::new("abcdef"), &bytes, "abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw");
+        assert!(Bech32::new("abcdef").decode("abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxx").is_err());
+
+        let link = TangleAddress::<Trit>::from_str(&"A".repeat(81), &"B".repeat(27)).unwrap();
+        let codec = Bech32::new("iota");
+        assert!(TangleAddress::decode(&codec, &link.encode(&codec)).unwrap() == link);
+    }
+}
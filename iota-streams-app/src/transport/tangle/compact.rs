@@ -0,0 +1,209 @@
+//! Compact representations of Tangle links for size-constrained media (QR codes, NFC tags).
+//!
+//! `TangleAddress` trits are packed 5 trits per byte (as in `B1T5` network encoding)
+//! and the resulting bytes are encoded with unpadded base64url. An 81-tryte appinst
+//! together with a 27-tryte msgid take 87 characters instead of 108 trytes.
+//!
+//! Short ids are the first `SHORT_ID_BYTES` bytes of the packed msgid. They are
+//! meant for display only and can't be decoded back into a link; a short id must be
+//! resolved against a set of known links (eg. the link store) which must not
+//! contain two msgids with the same short id.
+
+use failure::{
+    bail,
+    ensure,
+    Fallible,
+};
+
+use iota_streams_core::tbits::{
+    trinary::{
+        Trit,
+        TritWord,
+    },
+    Tbits,
+};
+use iota_streams_protobuf3::types::NTrytes;
+
+use super::*;
+
+/// Size of the truncated msgid used as a short id, in bytes.
+pub const SHORT_ID_BYTES: usize = 16;
+
+/// Size of packed `MsgId`, in bytes.
+pub const PACKED_MSGID_SIZE: usize = (MSGID_SIZE + 4) / 5;
+
+/// Size of packed `TangleAddress`, in bytes.
+pub const PACKED_LINK_SIZE: usize = (APPINST_SIZE + 4) / 5 + (MSGID_SIZE + 4) / 5;
+
+const BASE64URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Number of bytes taken by `trits` packed trits.
+pub fn packed_len(trits: usize) -> usize {
+    (trits + 4) / 5
+}
+
+/// Pack trits 5 per byte, the last byte is padded with zero trits.
+pub fn pack_trits<TW: TritWord>(tbits: &Tbits<TW>, bytes: &mut Vec<u8>) {
+    let mut trits = vec![Trit(0); tbits.size()];
+    tbits.slice().get_tbits(&mut trits);
+    for chunk in trits.chunks(5) {
+        let b = chunk.iter().rev().fold(0_u8, |b, t| b * 3 + t.0);
+        bytes.push(b);
+    }
+}
+
+/// Unpack `n` trits from bytes, padding trits must be zero.
+pub fn unpack_trits<TW: TritWord>(bytes: &[u8], n: usize) -> Fallible<Tbits<TW>> {
+    ensure!(bytes.len() == packed_len(n), "Bad packed trits length: {}.", bytes.len());
+    let mut trits = Vec::with_capacity(bytes.len() * 5);
+    for b in bytes {
+        ensure!(*b < 243, "Bad packed trits byte: {}.", b);
+        let mut u = *b;
+        for _ in 0..5 {
+            trits.push(Trit(u % 3));
+            u /= 3;
+        }
+    }
+    ensure!(trits[n..].iter().all(|t| t.0 == 0), "Non-zero padding in packed trits.");
+    Ok(Tbits::from_tbits(&trits[..n]))
+}
+
+/// Encode bytes with unpadded base64url.
+pub fn encode_base64url(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity((bytes.len() * 4 + 2) / 3);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | (b[2] as u32);
+        for i in 0..chunk.len() + 1 {
+            s.push(BASE64URL[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+        }
+    }
+    s
+}
+
+/// Decode unpadded base64url produced by `encode_base64url`.
+pub fn decode_base64url(s: &str) -> Fallible<Vec<u8>> {
+    ensure!(s.len() % 4 != 1, "Bad base64url length: {}.", s.len());
+    let mut bytes = Vec::with_capacity(s.len() * 3 / 4);
+    for chunk in s.as_bytes().chunks(4) {
+        let mut n = 0_u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let v = match BASE64URL.iter().position(|x| x == c) {
+                Some(v) => v as u32,
+                None => bail!("Bad base64url character: '{}'.", *c as char),
+            };
+            n |= v << (18 - 6 * i);
+        }
+        for i in 0..chunk.len() - 1 {
+            bytes.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+impl<TW> MsgId<TW>
+where
+    TW: TritWord,
+{
+    /// Pack msgid trits into bytes, 5 trits per byte.
+    pub fn to_packed(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(PACKED_MSGID_SIZE);
+        pack_trits(self.tbits(), &mut bytes);
+        bytes
+    }
+
+    /// Unpack bytes produced by `to_packed`.
+    pub fn from_packed(bytes: &[u8]) -> Fallible<Self> {
+        ensure!(bytes.len() == PACKED_MSGID_SIZE, "Bad packed msgid length: {}.", bytes.len());
+        Ok(Self {
+            id: NTrytes(unpack_trits(bytes, MSGID_SIZE)?),
+        })
+    }
+}
+
+impl<TW> TangleAddress<TW>
+where
+    TW: TritWord,
+{
+    /// Pack appinst and msgid trits into bytes, 5 trits per byte.
+    pub fn to_packed(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(PACKED_LINK_SIZE);
+        pack_trits(self.appinst.tbits(), &mut bytes);
+        pack_trits(self.msgid.tbits(), &mut bytes);
+        bytes
+    }
+
+    /// Unpack bytes produced by `to_packed`.
+    pub fn from_packed(bytes: &[u8]) -> Fallible<Self> {
+        ensure!(bytes.len() == PACKED_LINK_SIZE, "Bad packed link length: {}.", bytes.len());
+        let appinst_len = packed_len(APPINST_SIZE);
+        let appinst = AppInst {
+            id: NTrytes(unpack_trits(&bytes[..appinst_len], APPINST_SIZE)?),
+        };
+        let msgid = MsgId {
+            id: NTrytes(unpack_trits(&bytes[appinst_len..], MSGID_SIZE)?),
+        };
+        Ok(Self { appinst, msgid })
+    }
+
+    /// Encode appinst and msgid into a compact base64url string.
+    pub fn to_compact(&self) -> String {
+        encode_base64url(&self.to_packed())
+    }
+
+    /// Decode a string produced by `to_compact`.
+    pub fn from_compact(s: &str) -> Fallible<Self> {
+        let bytes = decode_base64url(s)?;
+        ensure!(bytes.len() == PACKED_LINK_SIZE, "Bad compact link length: {}.", s.len());
+        Self::from_packed(&bytes)
+    }
+}
+
+impl<TW> MsgId<TW>
+where
+    TW: TritWord,
+{
+    /// Truncated msgid for display purposes, it can't be decoded.
+    pub fn to_short_id(&self) -> String {
+        let mut bytes = Vec::with_capacity(packed_len(MSGID_SIZE));
+        pack_trits(self.tbits(), &mut bytes);
+        encode_base64url(&bytes[..SHORT_ID_BYTES])
+    }
+
+    /// Truncated msgid checked against `known` msgids: the short id must
+    /// identify `self` unambiguously.
+    pub fn to_short_id_checked<'a, I>(&self, known: I) -> Fallible<String>
+    where
+        I: IntoIterator<Item = &'a MsgId<TW>>,
+        TW: 'a,
+    {
+        let short_id = self.to_short_id();
+        for msgid in known {
+            ensure!(
+                msgid == self || msgid.to_short_id() != short_id,
+                "Short id collision: {}.",
+                short_id
+            );
+        }
+        Ok(short_id)
+    }
+
+    /// Find the only msgid in `known` with the given short id.
+    pub fn resolve_short_id<'a, I>(short_id: &str, known: I) -> Fallible<MsgId<TW>>
+    where
+        I: IntoIterator<Item = &'a MsgId<TW>>,
+        TW: 'a,
+    {
+        let mut found: Option<&MsgId<TW>> = None;
+        for msgid in known {
+            if msgid.to_short_id() == short_id {
+                ensure!(found.is_none(), "Short id collision: {}.", short_id);
+                found = Some(msgid);
+            }
+        }
+        match found {
+            Some(msgid) => Ok(msgid.clone()),
+            None => bail!("Short id not found: {}.", short_id),
+        }
+    }
+}
@@ -0,0 +1,252 @@
+//! Length-delimited framing of wrapped messages for byte streams (TCP, serial links).
+//!
+//! Binary layout of a frame, integers are big-endian:
+//!
+//! ```text
+//! frame = frame_size:u32 link[PACKED_LINK_SIZE] body_size:u32 body[(body_size + 4) / 5]
+//! ```
+//!
+//! `frame_size` is the number of bytes following it, `link` is packed with
+//! `TangleAddress::to_packed`, `body` trits are packed 5 per byte and `body_size` is in trits.
+
+use failure::{
+    ensure,
+    Fallible,
+};
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    io,
+};
+
+use iota_streams_core::tbits::trinary::TritWord;
+
+use super::*;
+use crate::transport::Transport;
+
+/// Default limit of the frame size, large enough for any message with Troika spongos.
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 1 << 20;
+
+/// Encoder and decoder of frames.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct FramedCodec {
+    /// Frames larger than this are rejected by the decoder.
+    pub max_frame_size: usize,
+}
+
+impl Default for FramedCodec {
+    fn default() -> Self {
+        Self {
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+}
+
+impl FramedCodec {
+    pub fn new(max_frame_size: usize) -> Self {
+        Self { max_frame_size }
+    }
+
+    /// Append the frame of `msg` to `buf`.
+    pub fn encode<TW: TritWord, F>(&self, msg: &TbinaryMessage<TW, F, TangleAddress<TW>>, buf: &mut Vec<u8>) {
+        let frame_size = PACKED_LINK_SIZE + 4 + packed_len(msg.body.size());
+        buf.reserve(4 + frame_size);
+        buf.extend(&(frame_size as u32).to_be_bytes());
+        buf.extend(msg.link.to_packed());
+        buf.extend(&(msg.body.size() as u32).to_be_bytes());
+        pack_trits(&msg.body, buf);
+    }
+
+    /// Decode the first frame in `buf` and remove it from `buf`.
+    /// Return `None` and leave `buf` untouched if the frame is incomplete.
+    pub fn decode<TW: TritWord, F>(
+        &self,
+        buf: &mut Vec<u8>,
+    ) -> Fallible<Option<TbinaryMessage<TW, F, TangleAddress<TW>>>> {
+        if buf.len() < 4 {
+            return Ok(None);
+        }
+        let frame_size = self.frame_size(buf[..4].try_into().unwrap())?;
+        if buf.len() < 4 + frame_size {
+            return Ok(None);
+        }
+        let msg = Self::decode_frame(&buf[4..4 + frame_size])?;
+        buf.drain(..4 + frame_size);
+        Ok(Some(msg))
+    }
+
+    /// Write the frame of `msg`.
+    pub fn write_message<TW: TritWord, F, W: io::Write>(
+        &self,
+        writer: &mut W,
+        msg: &TbinaryMessage<TW, F, TangleAddress<TW>>,
+    ) -> Fallible<()> {
+        let mut buf = Vec::new();
+        self.encode(msg, &mut buf);
+        writer.write_all(&buf)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Read the next frame, return `None` at the end of stream between frames.
+    pub fn read_message<TW: TritWord, F, R: io::Read>(
+        &self,
+        reader: &mut R,
+    ) -> Fallible<Option<TbinaryMessage<TW, F, TangleAddress<TW>>>> {
+        let mut size = [0_u8; 4];
+        let mut n = 0;
+        while n < 4 {
+            match reader.read(&mut size[n..]) {
+                Ok(0) if n == 0 => return Ok(None),
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                Ok(k) => n += k,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let mut frame = vec![0_u8; self.frame_size(size)?];
+        reader.read_exact(&mut frame)?;
+        Ok(Some(Self::decode_frame(&frame)?))
+    }
+
+    fn frame_size(&self, size: [u8; 4]) -> Fallible<usize> {
+        let frame_size = u32::from_be_bytes(size) as usize;
+        ensure!(
+            PACKED_LINK_SIZE + 4 <= frame_size && frame_size <= self.max_frame_size,
+            "Bad frame size: {}.",
+            frame_size
+        );
+        Ok(frame_size)
+    }
+
+    fn decode_frame<TW: TritWord, F>(frame: &[u8]) -> Fallible<TbinaryMessage<TW, F, TangleAddress<TW>>> {
+        let link = TangleAddress::from_packed(&frame[..PACKED_LINK_SIZE])?;
+        let body_size = u32::from_be_bytes(frame[PACKED_LINK_SIZE..PACKED_LINK_SIZE + 4].try_into().unwrap());
+        let body = unpack_trits(&frame[PACKED_LINK_SIZE + 4..], body_size as usize)?;
+        Ok(TbinaryMessage::new(link, body))
+    }
+}
+
+/// Transport over a byte stream, messages are sent and received as frames.
+///
+/// Frames are read until one with the requested link is found, frames with other links are
+/// kept for later requests. Receiving a message which is not in the stream reads it to the end,
+/// so `recv_messages` blocks on a connected socket until the peer closes it.
+pub struct Framed<TW, F, S> {
+    stream: S,
+    codec: FramedCodec,
+    received: HashMap<TangleAddress<TW>, Vec<TbinaryMessage<TW, F, TangleAddress<TW>>>>,
+}
+
+impl<TW, F, S> Framed<TW, F, S>
+where
+    TW: TritWord,
+{
+    pub fn new(stream: S) -> Self {
+        Self::with_codec(stream, FramedCodec::default())
+    }
+
+    pub fn with_codec(stream: S, codec: FramedCodec) -> Self {
+        Self {
+            stream,
+            codec,
+            received: HashMap::new(),
+        }
+    }
+
+    /// Underlying stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Return the underlying stream, frames read and not received yet are lost.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<TW, F, S> Transport<TW, F, TangleAddress<TW>> for Framed<TW, F, S>
+where
+    TW: TritWord + Clone,
+    S: io::Read + io::Write,
+{
+    type SendOptions = ();
+
+    fn send_message_with_options(
+        &mut self,
+        msg: &TbinaryMessage<TW, F, TangleAddress<TW>>,
+        _opt: (),
+    ) -> Fallible<()> {
+        self.codec.write_message(&mut self.stream, msg)
+    }
+
+    type RecvOptions = ();
+
+    fn recv_messages_with_options(
+        &mut self,
+        link: &TangleAddress<TW>,
+        _opt: (),
+    ) -> Fallible<Vec<TbinaryMessage<TW, F, TangleAddress<TW>>>> {
+        if let Some(msgs) = self.received.remove(link) {
+            return Ok(msgs);
+        }
+        while let Some(msg) = self.codec.read_message(&mut self.stream)? {
+            if msg.link == *link {
+                return Ok(vec![msg]);
+            }
+            self.received.entry(msg.link.clone()).or_insert_with(Vec::new).push(msg);
+        }
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use iota_streams_core::tbits::trinary::Trit;
+    use std::io::Cursor;
+
+    type Msg = TbinaryMessage<Trit, Troika, TangleAddress<Trit>>;
+
+    fn msg(msgid: &str, body_size: usize) -> Msg {
+        let link = TangleAddress::from_str(&format!("{:9<81}", "CHANNEL"), &format!("{:9<27}", msgid)).unwrap();
+        let mut body = Tbits::zero(body_size);
+        body.slice_mut().advance(body_size / 2).put_trit(Trit(2));
+        Msg::new(link, body)
+    }
+
+    fn same(a: &Msg, b: &Msg) -> bool {
+        a.link == b.link && a.body == b.body
+    }
+
+    #[test]
+    fn framed() {
+        let codec = FramedCodec::default();
+        let msgs = vec![msg("FIRST", 1000), msg("SECOND", 7), msg("THIRD", 3)];
+        let mut buf = Vec::new();
+        for m in &msgs {
+            codec.encode(m, &mut buf);
+        }
+
+        // Incomplete frames are not decoded.
+        let mut partial = buf[..buf.len() - 1].to_vec();
+        for m in &msgs[..2] {
+            assert!(same(&codec.decode(&mut partial).unwrap().unwrap(), m));
+        }
+        assert!(codec.decode::<Trit, Troika>(&mut partial).unwrap().is_none());
+        assert!(FramedCodec::new(100).decode::<Trit, Troika>(&mut buf.clone()).is_err());
+
+        let mut transport = Framed::<Trit, Troika, _>::new(Cursor::new(buf));
+        assert!(same(&transport.recv_message(&msgs[1].link).unwrap(), &msgs[1]));
+        let received = transport.recv_messages(&msgs[0].link).unwrap();
+        assert!(received.len() == 1 && same(&received[0], &msgs[0]));
+        assert!(same(&transport.recv_message(&msgs[2].link).unwrap(), &msgs[2]));
+        assert!(transport.recv_message(&msgs[1].link).is_err());
+
+        let mut transport = Framed::<Trit, Troika, _>::new(Cursor::new(Vec::new()));
+        transport.send_message(&msgs[1]).unwrap();
+        let mut sent = transport.into_inner().into_inner();
+        assert!(same(&codec.decode(&mut sent).unwrap().unwrap(), &msgs[1]));
+        assert!(sent.is_empty());
+    }
+}
@@ -0,0 +1,108 @@
+//! Indexation of messages by address and tag fields of their transactions.
+//!
+//! By default the transaction address is the channel appinst and the tag is the msgid, so
+//! all messages of a channel can be enumerated by scanning the address. Private indexation
+//! derives the address from the whole link and randomizes the tag per attachment: messages
+//! can only be found by those who know their links, and messages of the same channel can't
+//! be related by their indexes.
+
+use failure::Fallible;
+
+use super::*;
+
+/// How transactions of sent messages are indexed.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum IndexStrategy {
+    /// Address is the appinst, tag is the msgid.
+    Link,
+    /// Address is a hash of the link, tag is a hash of the link and a per-attachment nonce.
+    Private,
+}
+
+impl Default for IndexStrategy {
+    fn default() -> Self {
+        IndexStrategy::Link
+    }
+}
+
+impl IndexStrategy {
+    /// Transaction address and tag of the message at `link`.
+    /// The nonce (eg. the attachment time in nanoseconds) must differ between attachments.
+    pub fn index<TW>(&self, link: &TangleAddress<TW>, nonce: usize) -> Fallible<(Tbits<TW>, Tbits<TW>)>
+    where
+        TW: SpongosTbitWord + trinary::TritWord,
+    {
+        match self {
+            IndexStrategy::Link => Ok((link.appinst.tbits().clone(), link.msgid.tbits().clone())),
+            IndexStrategy::Private => Ok((private_address(link)?, private_tag(link, nonce)?)),
+        }
+    }
+
+    /// Transaction address and tag to search for the message at `link`, the tag is `None`
+    /// if it is not known in advance.
+    pub fn query<TW>(&self, link: &TangleAddress<TW>) -> Fallible<(Tbits<TW>, Option<Tbits<TW>>)>
+    where
+        TW: SpongosTbitWord + trinary::TritWord,
+    {
+        match self {
+            IndexStrategy::Link => Ok((link.appinst.tbits().clone(), Some(link.msgid.tbits().clone()))),
+            IndexStrategy::Private => Ok((private_address(link)?, None)),
+        }
+    }
+}
+
+fn private_address<TW>(link: &TangleAddress<TW>) -> Fallible<Tbits<TW>>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+{
+    let mut address = NTrytes(Tbits::zero(APPINST_SIZE));
+    wrap::Context::<TW, Troika, io::NoOStream>::new(io::NoOStream)
+        .absorb(External(&link.appinst.id))?
+        .absorb(External(&link.msgid.id))?
+        .commit()?
+        .squeeze(External(&mut address))?;
+    Ok(address.0)
+}
+
+fn private_tag<TW>(link: &TangleAddress<TW>, nonce: usize) -> Fallible<Tbits<TW>>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+{
+    let mut tag = NTrytes(Tbits::zero(MSGID_SIZE));
+    wrap::Context::<TW, Troika, io::NoOStream>::new(io::NoOStream)
+        .absorb(External(&link.appinst.id))?
+        .absorb(External(&link.msgid.id))?
+        .absorb(External(Size(nonce)))?
+        .commit()?
+        .squeeze(External(&mut tag))?;
+    Ok(tag.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use iota_streams_core::tbits::trinary::Trit;
+
+    #[test]
+    fn private_index() {
+        let appinst = AppInst::<Trit>::from_str(&format!("{:9<81}", "CHANNEL")).unwrap();
+        let link = |msgid: &str| TangleAddress {
+            appinst: appinst.clone(),
+            msgid: MsgId::from_str(&format!("{:9<27}", msgid)).unwrap(),
+        };
+        let (first, second) = (link("FIRST"), link("SECOND"));
+
+        let (address, tag) = IndexStrategy::Link.index(&first, 1).unwrap();
+        assert!(address == *first.appinst.tbits() && tag == *first.msgid.tbits());
+        assert!(IndexStrategy::Link.query(&first).unwrap() == (address, Some(tag)));
+
+        let (address, tag) = IndexStrategy::Private.index(&first, 1).unwrap();
+        assert!(address.size() == APPINST_SIZE && tag.size() == MSGID_SIZE);
+        assert!(address != *first.appinst.tbits() && tag != *first.msgid.tbits());
+        assert!(IndexStrategy::Private.query(&first).unwrap() == (address.clone(), None));
+        // Reattachments get new tags, other messages of the channel get other addresses.
+        let (address2, tag2) = IndexStrategy::Private.index(&first, 2).unwrap();
+        assert!(address2 == address && tag2 != tag);
+        assert!(IndexStrategy::Private.index(&second, 1).unwrap().0 != address);
+    }
+}
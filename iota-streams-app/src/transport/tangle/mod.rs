@@ -1,16 +1,24 @@
 //! Tangle-specific transport definitions.
 
-use chrono::Utc;
-use failure::Fallible;
+use failure::{
+    bail,
+    ensure,
+    Fallible,
+};
 use std::{
     convert::AsRef,
     fmt,
     hash,
     str::FromStr,
+    sync::Arc,
 };
 
 use iota_streams_core::{
-    sponge::prp::PRP,
+    heap::HeapSize,
+    sponge::prp::{
+        troika::Troika,
+        PRP,
+    },
     tbits::{
         trinary,
         word::{
@@ -28,7 +36,13 @@ use iota_streams_protobuf3::{
     types::*,
 };
 
-use crate::message::*;
+use crate::{
+    message::*,
+    time::{
+        SystemTimeProvider,
+        TimeProvider,
+    },
+};
 
 pub struct TangleMessage<TW, F> {
     /// Encapsulated tbinary encoded message.
@@ -40,11 +54,11 @@ pub struct TangleMessage<TW, F> {
 }
 
 impl<TW, F> TangleMessage<TW, F> {
-    /// Create TangleMessage from TbinaryMessage and add the current timestamp.
+    /// Create TangleMessage from TbinaryMessage and add the current system time as timestamp.
     pub fn new(msg: TbinaryMessage<TW, F, TangleAddress<TW>>) -> Self {
         Self {
             tbinary_message: msg,
-            timestamp: Utc::now().timestamp_millis(),
+            timestamp: SystemTimeProvider.now().as_millis() as i64,
         }
     }
     /// Create TangleMessage from TbinaryMessage and an explicit timestamp.
@@ -62,6 +76,12 @@ pub struct TangleAddress<TW> {
     pub msgid: MsgId<TW>,
 }
 
+impl<TW> HeapSize for TangleAddress<TW> {
+    fn heap_size(&self) -> usize {
+        self.appinst.heap_size() + self.msgid.heap_size()
+    }
+}
+
 impl<TW> TangleAddress<TW>
 where
     TW: StringTbitWord,
@@ -153,10 +173,58 @@ where
     }
 }
 
+/// Algorithm of msgid derivation, it's announced by the channel owner.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum MsgIdDerivation {
+    /// Msgids are derived with spongos over the channel PRP; changing PRP breaks links.
+    ChannelPrp,
+    /// Msgids are derived with spongos over Troika independently of the channel PRP.
+    Troika,
+    /// Msgids are derived with a custom `MsgIdDeriver`, Subscribers must be set up with the same one.
+    Custom,
+}
+
+impl Default for MsgIdDerivation {
+    fn default() -> Self {
+        MsgIdDerivation::ChannelPrp
+    }
+}
+
+impl MsgIdDerivation {
+    /// Encoding of the derivation in Announce message.
+    pub fn to_trint3(self) -> Trint3 {
+        match self {
+            MsgIdDerivation::ChannelPrp => Trint3(0),
+            MsgIdDerivation::Troika => Trint3(1),
+            MsgIdDerivation::Custom => Trint3(2),
+        }
+    }
+
+    /// Decode the derivation from Announce message.
+    pub fn from_trint3(t: Trint3) -> Fallible<Self> {
+        match t {
+            Trint3(0) => Ok(MsgIdDerivation::ChannelPrp),
+            Trint3(1) => Ok(MsgIdDerivation::Troika),
+            Trint3(2) => Ok(MsgIdDerivation::Custom),
+            _ => bail!("Unsupported msgid derivation: {}.", t),
+        }
+    }
+}
+
+/// Custom msgid derivation, eg. incorporating tenant identifiers or following an internal
+/// addressing scheme, see `DefaultTangleLinkGenerator::set_msgid_deriver`.
+pub trait MsgIdDeriver<TW>: Send + Sync {
+    /// Msgid trytes of the message joined to `msgid` in channel `appinst`, `counter` is the number
+    /// of links generated so far. Msgids must be distinct for distinct `msgid` and `counter`.
+    fn derive_msgid(&self, appinst: &AppInst<TW>, msgid: &MsgId<TW>, counter: usize) -> Fallible<NTrytes<TW>>;
+}
+
 #[derive(Clone)]
 pub struct DefaultTangleLinkGenerator<TW, F> {
     appinst: AppInst<TW>,
     counter: usize,
+    msgid_derivation: MsgIdDerivation,
+    msgid_deriver: Option<Arc<dyn MsgIdDeriver<TW>>>,
     _phantom: std::marker::PhantomData<F>,
 }
 
@@ -168,6 +236,8 @@ where
         Self {
             appinst: AppInst::<TW>::default(),
             counter: 0,
+            msgid_derivation: MsgIdDerivation::default(),
+            msgid_deriver: None,
             _phantom: std::marker::PhantomData,
         }
     }
@@ -177,6 +247,34 @@ impl<TW, F> DefaultTangleLinkGenerator<TW, F> {
     pub fn reset_appinst(&mut self, appinst: AppInst<TW>) {
         self.appinst = appinst;
     }
+
+    pub fn msgid_derivation(&self) -> MsgIdDerivation {
+        self.msgid_derivation
+    }
+
+    /// Msgid derivation must be set before the first link is generated.
+    pub fn set_msgid_derivation(&mut self, msgid_derivation: MsgIdDerivation) {
+        self.msgid_derivation = msgid_derivation;
+    }
+
+    pub fn msgid_deriver(&self) -> Option<&Arc<dyn MsgIdDeriver<TW>>> {
+        self.msgid_deriver.as_ref()
+    }
+
+    /// Deriver of msgids used with `MsgIdDerivation::Custom`.
+    pub fn set_msgid_deriver(&mut self, msgid_deriver: Option<Arc<dyn MsgIdDeriver<TW>>>) {
+        self.msgid_deriver = msgid_deriver;
+    }
+
+    /// Number of links generated so far, the counter is absorbed into msgids.
+    pub fn counter(&self) -> usize {
+        self.counter
+    }
+
+    /// Continue generating links after `counter`, eg. once lost state has been recovered.
+    pub fn set_counter(&mut self, counter: usize) {
+        self.counter = counter;
+    }
 }
 
 impl<TW, F> DefaultTangleLinkGenerator<TW, F>
@@ -184,18 +282,44 @@ where
     TW: SpongosTbitWord + trinary::TritWord,
     F: PRP<TW> + Default,
 {
-    fn try_gen_msgid(&self, msgid: &MsgId<TW>) -> Fallible<MsgId<TW>> {
+    fn try_gen_msgid_with<G>(&self, msgid: &MsgId<TW>, counter: usize) -> Fallible<MsgId<TW>>
+    where
+        G: PRP<TW> + Default,
+    {
         let mut new = MsgId::default();
-        wrap::Context::<TW, F, io::NoOStream>::new(io::NoOStream)
+        wrap::Context::<TW, G, io::NoOStream>::new(io::NoOStream)
             .absorb(External(&self.appinst.id))?
             .absorb(External(&msgid.id))?
-            .absorb(External(Size(self.counter)))?
+            .absorb(External(Size(counter)))?
             .commit()?
             .squeeze(External(&mut new.id))?;
         Ok(new)
     }
+    fn try_gen_msgid(&self, msgid: &MsgId<TW>, counter: usize) -> Fallible<MsgId<TW>> {
+        match self.msgid_derivation {
+            MsgIdDerivation::ChannelPrp => self.try_gen_msgid_with::<F>(msgid, counter),
+            MsgIdDerivation::Troika => self.try_gen_msgid_with::<Troika>(msgid, counter),
+            MsgIdDerivation::Custom => match &self.msgid_deriver {
+                Some(msgid_deriver) => {
+                    let id = msgid_deriver.derive_msgid(&self.appinst, msgid, counter)?;
+                    ensure!((id.0).size() == MSGID_SIZE, "Bad custom msgid size: {}.", (id.0).size());
+                    Ok(MsgId { id })
+                }
+                None => bail!("Custom msgid derivation is not set."),
+            },
+        }
+    }
     fn gen_msgid(&self, msgid: &MsgId<TW>) -> MsgId<TW> {
-        self.try_gen_msgid(msgid).map_or(MsgId::<TW>::default(), |x| x)
+        self.try_gen_msgid(msgid, self.counter).map_or(MsgId::<TW>::default(), |x| x)
+    }
+
+    /// Link generated for a message joined to `msgid` when the counter is `counter`.
+    /// The generator state is not changed.
+    pub fn link_at(&self, msgid: &MsgId<TW>, counter: usize) -> TangleAddress<TW> {
+        TangleAddress {
+            appinst: self.appinst.clone(),
+            msgid: self.try_gen_msgid(msgid, counter).map_or(MsgId::<TW>::default(), |x| x),
+        }
     }
 }
 
@@ -251,6 +375,12 @@ pub struct AppInst<TW> {
     pub(crate) id: NTrytes<TW>,
 }
 
+impl<TW> HeapSize for AppInst<TW> {
+    fn heap_size(&self) -> usize {
+        self.id.heap_size()
+    }
+}
+
 impl<TW> FromStr for AppInst<TW>
 where
     TW: StringTbitWord,
@@ -372,6 +502,12 @@ pub struct MsgId<TW> {
     pub(crate) id: NTrytes<TW>,
 }
 
+impl<TW> HeapSize for MsgId<TW> {
+    fn heap_size(&self) -> usize {
+        self.id.heap_size()
+    }
+}
+
 impl<TW> FromStr for MsgId<TW>
 where
     TW: StringTbitWord,
@@ -427,6 +563,15 @@ impl<TW> AsRef<Tbits<TW>> for MsgId<TW> {
     }
 }
 
+impl<TW> SealBinding<TW> for MsgId<TW>
+where
+    TW: BasicTbitWord,
+{
+    fn binding(&self) -> Tbits<TW> {
+        self.id.0.clone()
+    }
+}
+
 impl<TW> Default for MsgId<TW>
 where
     TW: BasicTbitWord,
@@ -479,5 +624,57 @@ where
     }
 }
 
+/// Msgid can be a confidential field of message content, then it's `mask`ed.
+impl<TW, F> MaskFallback<TW, F> for MsgId<TW>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+{
+    fn sizeof_mask(&self, ctx: &mut sizeof::Context<TW, F>) -> Fallible<()> {
+        ctx.mask(&self.id)?;
+        Ok(())
+    }
+    fn wrap_mask<OS: io::OStream<TW>>(&self, ctx: &mut wrap::Context<TW, F, OS>) -> Fallible<()> {
+        ctx.mask(&self.id)?;
+        Ok(())
+    }
+    fn unwrap_mask<IS: io::IStream<TW>>(&mut self, ctx: &mut unwrap::Context<TW, F, IS>) -> Fallible<()> {
+        ctx.mask(&mut self.id)?;
+        Ok(())
+    }
+}
+
+/// Link to a message in another channel can be a confidential field of message content.
+impl<TW, F> MaskFallback<TW, F> for TangleAddress<TW>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+{
+    fn sizeof_mask(&self, ctx: &mut sizeof::Context<TW, F>) -> Fallible<()> {
+        ctx.mask(&self.appinst.id)?.mask(&self.msgid.id)?;
+        Ok(())
+    }
+    fn wrap_mask<OS: io::OStream<TW>>(&self, ctx: &mut wrap::Context<TW, F, OS>) -> Fallible<()> {
+        ctx.mask(&self.appinst.id)?.mask(&self.msgid.id)?;
+        Ok(())
+    }
+    fn unwrap_mask<IS: io::IStream<TW>>(&mut self, ctx: &mut unwrap::Context<TW, F, IS>) -> Fallible<()> {
+        ctx.mask(&mut self.appinst.id)?.mask(&mut self.msgid.id)?;
+        Ok(())
+    }
+}
+
+mod codec;
+pub use codec::*;
+
+mod compact;
+pub use compact::*;
+
+mod framed;
+pub use framed::*;
+
+mod index;
+pub use index::*;
+
 //#[cfg(feature = "tangle")]
 pub mod client;
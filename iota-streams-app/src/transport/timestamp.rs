@@ -0,0 +1,61 @@
+//! Attachment time of messages, so that consumers can skip old messages.
+//!
+//! Timestamps are in milliseconds since UNIX epoch, as reported by the node. They are
+//! set by the publisher and are not authenticated by Streams.
+
+use failure::Fallible;
+use std::hash;
+
+use super::{
+    BucketTransport,
+    Transport,
+};
+use crate::message::TbinaryMessage;
+
+/// Transport reporting when messages were attached.
+pub trait TimestampTransport<TW, F, Link>: Transport<TW, F, Link> {
+    /// Receive messages together with their attachment timestamps.
+    fn recv_messages_with_timestamps(
+        &mut self,
+        link: &Link,
+        opt: Self::RecvOptions,
+    ) -> Fallible<Vec<(TbinaryMessage<TW, F, Link>, i64)>>;
+
+    /// Attachment timestamps of messages at `link`. Transports able to query message metadata
+    /// without message bodies should override it.
+    fn attachment_timestamps(&mut self, link: &Link) -> Fallible<Vec<i64>>
+    where
+        Self::RecvOptions: Default,
+    {
+        let msgs = self.recv_messages_with_timestamps(link, Self::RecvOptions::default())?;
+        Ok(msgs.into_iter().map(|(_, timestamp)| timestamp).collect())
+    }
+
+    /// Receive messages at `link` attached at or after `since`.
+    fn recv_messages_since(&mut self, link: &Link, since: i64) -> Fallible<Vec<TbinaryMessage<TW, F, Link>>>
+    where
+        Self::RecvOptions: Default,
+    {
+        let msgs = self.recv_messages_with_timestamps(link, Self::RecvOptions::default())?;
+        Ok(msgs
+            .into_iter()
+            .filter(|(_, timestamp)| since <= *timestamp)
+            .map(|(msg, _)| msg)
+            .collect())
+    }
+}
+
+/// Messages in the bucket are attached when they are sent.
+impl<TW, F, Link> TimestampTransport<TW, F, Link> for BucketTransport<TW, F, Link>
+where
+    TW: Clone,
+    Link: Eq + hash::Hash + Clone,
+{
+    fn recv_messages_with_timestamps(
+        &mut self,
+        link: &Link,
+        _opt: (),
+    ) -> Fallible<Vec<(TbinaryMessage<TW, F, Link>, i64)>> {
+        self.recv_timestamped(link)
+    }
+}
@@ -0,0 +1,101 @@
+//! `streams-cli` -- command line tool for Channels Application.
+//!
+//! ```text
+//! streams-cli [--node URL | --bucket] author SEED [MSS_HEIGHT]
+//! streams-cli [--node URL | --bucket] subscriber SEED
+//! ```
+//!
+//! Commands are read from standard input one per line (see `session` module),
+//! the output of each command is printed to standard output, eg. links of
+//! published messages as `APPINST:MSGID`. Errors are printed to standard error
+//! and the tool exits with non-zero status at the first failed command.
+//!
+//! Messages are sent to and received from IOTA node at `URL`. With `--bucket`
+//! messages are kept in memory for the duration of the session, which is only
+//! useful for debugging.
+
+use failure::{
+    bail,
+    Fallible,
+};
+use iota_lib_rs::prelude::iota_client;
+use std::{
+    env,
+    io::{
+        self,
+        BufRead,
+    },
+    process,
+};
+
+use iota_streams_app_channels::api::tangle::{
+    BucketTransport,
+    Transport,
+};
+
+mod session;
+use session::Session;
+
+const DEFAULT_NODE: &str = "https://nodes.devnet.iota.org:443";
+const DEFAULT_MSS_HEIGHT: usize = 3;
+const USAGE: &str = "Usage:
+    streams-cli [--node URL | --bucket] author SEED [MSS_HEIGHT]
+    streams-cli [--node URL | --bucket] subscriber SEED";
+
+fn run<T: Transport>(transport: &mut T, session: &mut Session) -> Fallible<()>
+where
+    T::SendOptions: Default,
+    T::RecvOptions: Default,
+{
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        for out in session.exec(transport, &line?)? {
+            println!("{}", out);
+        }
+    }
+    Ok(())
+}
+
+fn main_() -> Fallible<()> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let mut node = Some(DEFAULT_NODE.to_string());
+    match args.first().map(|a| a.as_str()) {
+        Some("--node") if args.len() > 1 => {
+            node = Some(args[1].clone());
+            args.drain(..2);
+        }
+        Some("--bucket") => {
+            node = None;
+            args.remove(0);
+        }
+        _ => {}
+    }
+
+    let mut session = match args.iter().map(|a| a.as_str()).collect::<Vec<_>>().as_slice() {
+        ["author", seed] => Session::new_author(seed, DEFAULT_MSS_HEIGHT),
+        ["author", seed, mss_height] => match mss_height.parse() {
+            Ok(mss_height) => Session::new_author(seed, mss_height),
+            Err(_) => bail!("Bad MSS height: '{}'.", mss_height),
+        },
+        ["subscriber", seed] => Session::new_subscriber(seed),
+        _ => bail!("{}", USAGE),
+    };
+
+    match node {
+        Some(url) => {
+            let mut client = iota_client::Client::new(&url);
+            run(&mut client, &mut session)
+        }
+        None => {
+            let mut transport = BucketTransport::new();
+            run(&mut transport, &mut session)
+        }
+    }
+}
+
+fn main() {
+    if let Err(e) = main_() {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,344 @@
+//! Channel session driven by text commands.
+//!
+//! Each command is a line of whitespace separated words, empty lines and lines
+//! starting with `#` are ignored. Links are given either as `APPINST:MSGID` trytes
+//! or in compact form (see `TangleAddress::to_compact`).
+//!
+//! Author commands:
+//!
+//! * `announce` -- publish channel announcement.
+//! * `keyload LINK` -- publish keyload for all subscribers.
+//! * `sign LINK PUBLIC MASKED` -- publish signed packet.
+//! * `tag LINK PUBLIC MASKED` -- publish tagged packet.
+//! * `heartbeat LINK` -- publish heartbeat.
+//! * `fetch LINK` -- handle subscribe, unsubscribe, keyload request, lite, framed or tagged packet.
+//! * `inspect LINK` -- print public header fields of message at `LINK` without handling it.
+//!
+//! Subscriber commands:
+//!
+//! * `subscribe LINK` -- handle announcement at `LINK` (if not yet) and publish subscribe message.
+//! * `tag LINK PUBLIC MASKED` -- publish tagged packet.
+//! * `fetch LINK` -- handle announcement, change key, heartbeat, keyload, participants, reveal key, signed,
+//!   structured, lite, framed or tagged packet. Undisclosed structured packet fields are printed as `NAME=?`.
+//! * `inspect LINK` -- same as for Author.
+//! * `export FILE` -- save session state.
+//! * `import FILE` -- restore session state saved with `export`.
+//!
+//! Subscriber state is saved as a journal: the seed and the links of handled messages.
+//! Import replays the journal, ie. fetches and handles the messages again. Own messages
+//! (eg. subscribe) are not replayed. Author state can't be saved this way: keyload session
+//! keys are random and MSS private keys must not be reused, so `export` fails for Author.
+//! Note, the state file contains the seed and must be kept secret.
+
+use failure::{
+    bail,
+    ensure,
+    format_err,
+    Fallible,
+};
+use std::{
+    fs,
+    str::FromStr,
+};
+
+use iota_streams_app::message::inspect;
+use iota_streams_app_channels::{
+    api::tangle::{
+        Address,
+        Author,
+        AuthorBuilder,
+        Message,
+        Subscriber,
+        SubscriberBuilder,
+        Transport,
+        Trytes,
+    },
+    message,
+};
+use iota_streams_core::tbits::Tbits;
+use iota_streams_protobuf3::types as pb3_types;
+
+/// State file header.
+const STATE_MAGIC: &str = "streams-cli 1";
+
+pub enum User {
+    Author(Author),
+    Subscriber(Subscriber),
+}
+
+pub struct Session {
+    seed: String,
+    user: User,
+    /// Links of handled messages, in order.
+    journal: Vec<String>,
+}
+
+/// Parse link given as `APPINST:MSGID` or in compact form.
+pub fn parse_link(s: &str) -> Fallible<Address> {
+    match s.find(':') {
+        Some(i) => Address::from_str(&s[..i], &s[i + 1..]).map_err(|_| format_err!("Bad link: '{}'.", s)),
+        None => Address::from_compact(s),
+    }
+}
+
+/// Format link as `APPINST:MSGID`.
+pub fn format_link(link: &Address) -> String {
+    format!("{}:{}", link.appinst, link.msgid)
+}
+
+fn parse_trytes(s: &str) -> Fallible<Trytes> {
+    Tbits::from_str(s)
+        .map(pb3_types::Trytes)
+        .map_err(|_| format_err!("Bad trytes: '{}'.", s))
+}
+
+fn args<'a>(words: &[&'a str], n: usize) -> Fallible<Vec<&'a str>> {
+    ensure!(
+        words.len() == n + 1,
+        "Command '{}' expects {} argument(s), {} given.",
+        words[0],
+        n,
+        words.len() - 1
+    );
+    Ok(words[1..].to_vec())
+}
+
+impl Session {
+    pub fn new_author(seed: &str, mss_height: usize) -> Self {
+        Self {
+            seed: seed.to_string(),
+            user: User::Author(AuthorBuilder::new(seed).mss_height(mss_height).build()),
+            journal: Vec::new(),
+        }
+    }
+
+    pub fn new_subscriber(seed: &str) -> Self {
+        Self {
+            seed: seed.to_string(),
+            user: User::Subscriber(SubscriberBuilder::new(seed).build()),
+            journal: Vec::new(),
+        }
+    }
+
+    /// Execute command line, the output is returned as a list of lines.
+    pub fn exec<T: Transport>(&mut self, transport: &mut T, line: &str) -> Fallible<Vec<String>>
+    where
+        T::SendOptions: Default,
+        T::RecvOptions: Default,
+    {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() || words[0].starts_with('#') {
+            return Ok(Vec::new());
+        }
+        match (words[0], &mut self.user) {
+            ("announce", User::Author(author)) => {
+                args(&words, 0)?;
+                let msg = author.announce()?;
+                send(transport, &msg)
+            }
+            ("keyload", User::Author(author)) => {
+                let a = args(&words, 1)?;
+                let msg = author.share_keyload_for_everyone(&parse_link(a[0])?)?;
+                send(transport, &msg)
+            }
+            ("sign", User::Author(author)) => {
+                let a = args(&words, 3)?;
+                let msg = author.sign_packet(&parse_link(a[0])?, &parse_trytes(a[1])?, &parse_trytes(a[2])?)?;
+                send(transport, &msg)
+            }
+            ("tag", User::Author(author)) => {
+                let a = args(&words, 3)?;
+                let msg = author.tag_packet(&parse_link(a[0])?, &parse_trytes(a[1])?, &parse_trytes(a[2])?)?;
+                send(transport, &msg)
+            }
+            ("heartbeat", User::Author(author)) => {
+                let a = args(&words, 1)?;
+                let msg = author.heartbeat(&parse_link(a[0])?)?;
+                send(transport, &msg)
+            }
+            ("subscribe", User::Subscriber(subscriber)) => {
+                let a = args(&words, 1)?;
+                let link = parse_link(a[0])?;
+                let mut out = Vec::new();
+                if !subscriber.is_registered() {
+                    out = self.fetch(transport, &link)?;
+                }
+                if let User::Subscriber(subscriber) = &mut self.user {
+                    let msg = subscriber.subscribe(&link)?;
+                    out.extend(send(transport, &msg)?);
+                }
+                Ok(out)
+            }
+            ("tag", User::Subscriber(subscriber)) => {
+                let a = args(&words, 3)?;
+                let msg = subscriber.tag_packet(&parse_link(a[0])?, &parse_trytes(a[1])?, &parse_trytes(a[2])?)?;
+                send(transport, &msg)
+            }
+            ("fetch", _) => {
+                let a = args(&words, 1)?;
+                self.fetch(transport, &parse_link(a[0])?)
+            }
+            ("inspect", _) => {
+                let a = args(&words, 1)?;
+                let msg = transport.recv_message(&parse_link(a[0])?)?;
+                let info = inspect(&msg)?;
+                Ok(vec![format!(
+                    "{} version {} app version {} ext size {} content size {}",
+                    info.content_type, info.version, info.app_version, info.ext_size, info.content_size
+                )])
+            }
+            ("export", _) => {
+                let a = args(&words, 1)?;
+                self.export(a[0])?;
+                Ok(Vec::new())
+            }
+            ("import", _) => {
+                let a = args(&words, 1)?;
+                self.import(transport, a[0])
+            }
+            (cmd, User::Author(_)) => bail!("Unknown Author command: '{}'.", cmd),
+            (cmd, User::Subscriber(_)) => bail!("Unknown Subscriber command: '{}'.", cmd),
+        }
+    }
+
+    /// Fetch message at `link` and handle it according to its content type.
+    fn fetch<T: Transport>(&mut self, transport: &mut T, link: &Address) -> Fallible<Vec<String>>
+    where
+        T::RecvOptions: Default,
+    {
+        let msg = transport.recv_message(link)?;
+        let preparsed = msg.parse_header()?;
+        let mut out = Vec::new();
+        match &mut self.user {
+            User::Author(author) => {
+                if preparsed.check_content_type(message::subscribe::TYPE) {
+                    author.unwrap_subscribe(preparsed)?;
+                    out.push("subscribe".to_string());
+                } else if preparsed.check_content_type(message::unsubscribe::TYPE) {
+                    author.unwrap_unsubscribe(preparsed)?;
+                    out.push("unsubscribe".to_string());
+                } else if preparsed.check_content_type(message::keyload_request::TYPE) {
+                    let ntru_pkid = author.unwrap_keyload_request(preparsed)?;
+                    out.push(format!("keyload request {}", ntru_pkid.0));
+                } else if preparsed.check_content_type(message::lite_packet::TYPE) {
+                    let payload = author.unwrap_lite_packet(preparsed)?;
+                    out.push(format!("lite packet {}", payload.0));
+                } else if preparsed.check_content_type(message::tagged_packet::TYPE) {
+                    let (public_payload, masked_payload) = author.unwrap_tagged_packet(preparsed)?;
+                    out.push(format!("tagged packet {} {}", public_payload.0, masked_payload.0));
+                } else if preparsed.check_content_type(message::framed_packet::TYPE) {
+                    let (public_payload, masked_payload) = author.unwrap_framed_packet(preparsed)?;
+                    out.push(format!("framed packet {} {}", public_payload.0, masked_payload.0));
+                } else {
+                    bail!("Author can't handle message of type '{}'.", preparsed.header.content_type.0);
+                }
+            }
+            User::Subscriber(subscriber) => {
+                if preparsed.check_content_type(message::announce::TYPE) {
+                    subscriber.unwrap_announcement(preparsed)?;
+                    out.push("announce".to_string());
+                } else if preparsed.check_content_type(message::change_key::TYPE) {
+                    subscriber.unwrap_change_key(preparsed)?;
+                    out.push("change key".to_string());
+                } else if preparsed.check_content_type(message::heartbeat::TYPE) {
+                    let heartbeat = subscriber.unwrap_heartbeat(preparsed)?;
+                    out.push(format!("heartbeat {} {}", heartbeat.counter, heartbeat.timestamp));
+                } else if preparsed.check_content_type(message::keyload::TYPE)
+                    || preparsed.check_content_type(message::keyload::EMBARGOED_TYPE)
+                {
+                    subscriber.unwrap_keyload(preparsed)?;
+                    out.push("keyload".to_string());
+                } else if preparsed.check_content_type(message::participants::TYPE) {
+                    subscriber.unwrap_participants(preparsed)?;
+                    out.push(format!("participants {}", subscriber.participants().len()));
+                } else if preparsed.check_content_type(message::reveal_key::TYPE) {
+                    let reveal_key_id = subscriber.unwrap_reveal_key(preparsed)?;
+                    out.push(format!("reveal key {}", reveal_key_id));
+                } else if preparsed.check_content_type(message::signed_packet::TYPE) {
+                    let (public_payload, masked_payload) = subscriber.unwrap_signed_packet(preparsed)?;
+                    out.push(format!("signed packet {} {}", public_payload.0, masked_payload.0));
+                } else if preparsed.check_content_type(message::structured_packet::TYPE) {
+                    let fields: Vec<String> = subscriber
+                        .unwrap_structured_packet(preparsed)?
+                        .iter()
+                        .map(|field| match &field.value {
+                            Some(value) => format!("{}={}", field.name.0, value.0),
+                            None => format!("{}=?", field.name.0),
+                        })
+                        .collect();
+                    out.push(format!("structured packet {}", fields.join(" ")));
+                } else if preparsed.check_content_type(message::lite_packet::TYPE) {
+                    let payload = subscriber.unwrap_lite_packet(preparsed)?;
+                    out.push(format!("lite packet {}", payload.0));
+                } else if preparsed.check_content_type(message::tagged_packet::TYPE) {
+                    let (public_payload, masked_payload) = subscriber.unwrap_tagged_packet(preparsed)?;
+                    out.push(format!("tagged packet {} {}", public_payload.0, masked_payload.0));
+                } else if preparsed.check_content_type(message::framed_packet::TYPE) {
+                    let (public_payload, masked_payload) = subscriber.unwrap_framed_packet(preparsed)?;
+                    out.push(format!("framed packet {} {}", public_payload.0, masked_payload.0));
+                } else {
+                    bail!(
+                        "Subscriber can't handle message of type '{}'.",
+                        preparsed.header.content_type.0
+                    );
+                }
+            }
+        }
+        self.journal.push(format_link(link));
+        Ok(out)
+    }
+
+    fn export(&self, path: &str) -> Fallible<()> {
+        ensure!(
+            !self.is_author(),
+            "Author state can't be exported: keyload keys are random and MSS keys must not be reused."
+        );
+        let mut state = format!("{}\nsubscriber {}\n", STATE_MAGIC, self.seed);
+        for link in &self.journal {
+            state.push_str(&format!("fetch {}\n", link));
+        }
+        fs::write(path, state)?;
+        Ok(())
+    }
+
+    fn import<T: Transport>(&mut self, transport: &mut T, path: &str) -> Fallible<Vec<String>>
+    where
+        T::RecvOptions: Default,
+    {
+        ensure!(!self.is_author(), "Author state can't be imported.");
+        let state = fs::read_to_string(path)?;
+        let mut lines = state.lines();
+        ensure!(lines.next() == Some(STATE_MAGIC), "Bad state file header.");
+        let seed = match lines.next().map(|l| l.split_whitespace().collect::<Vec<_>>()) {
+            Some(ref words) if words.len() == 2 && words[0] == "subscriber" => words[1].to_string(),
+            _ => bail!("Bad state file role."),
+        };
+        *self = Self::new_subscriber(&seed);
+        let mut out = Vec::new();
+        for line in lines {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            ensure!(
+                words.len() == 2 && words[0] == "fetch",
+                "Bad state file line: '{}'.",
+                line
+            );
+            out.extend(self.fetch(transport, &parse_link(words[1])?)?);
+        }
+        Ok(out)
+    }
+
+    fn is_author(&self) -> bool {
+        match self.user {
+            User::Author(_) => true,
+            User::Subscriber(_) => false,
+        }
+    }
+}
+
+fn send<T: Transport>(transport: &mut T, msg: &Message) -> Fallible<Vec<String>>
+where
+    T::SendOptions: Default,
+{
+    transport.send_message(msg)?;
+    Ok(vec![format_link(&msg.link)])
+}
@@ -2,7 +2,10 @@
 extern crate criterion;
 
 use criterion::Criterion;
-use iota_streams_core_keccak::sponge::prp::keccak::KeccakF1600B;
+use iota_streams_core_keccak::sponge::prp::keccak::{
+    is_accelerated,
+    KeccakF1600B,
+};
 
 fn keccakf1600_benchmark(c: &mut Criterion) {
     let mut keccak = KeccakF1600B::default();
@@ -11,6 +14,16 @@ fn keccakf1600_benchmark(c: &mut Criterion) {
             keccak.permutation();
         })
     });
+
+    // `p1600` always runs the portable implementation, compare it with the transform above
+    // to see the speedup of `asm` feature.
+    let mut state = [0u64; 25];
+    c.bench_function("Run KeccakF1600 transform/portable", move |b| {
+        b.iter(|| {
+            keccak::p1600(&mut state, 24);
+        })
+    });
+    println!("Keccak-f[1600] accelerated: {}", is_accelerated());
 }
 
 criterion_group!(benches, keccakf1600_benchmark);
@@ -15,6 +15,22 @@ use iota_streams_core::{
 };
 use keccak;
 
+#[cfg(all(target_arch = "aarch64", feature = "asm"))]
+cpufeatures::new!(armv8_sha3, "sha3");
+
+/// Whether Keccak-f[1600] permutation runs on CPU instructions rather than the portable
+/// implementation: `asm` feature is enabled and the CPU supports ARMv8 SHA3 extension.
+pub fn is_accelerated() -> bool {
+    #[cfg(all(target_arch = "aarch64", feature = "asm"))]
+    {
+        armv8_sha3::get()
+    }
+    #[cfg(not(all(target_arch = "aarch64", feature = "asm")))]
+    {
+        false
+    }
+}
+
 #[derive(Clone)]
 pub struct KeccakF1600B {
     state: [u64; 25],
@@ -161,6 +177,15 @@ where
     type Inner = inner::Inner<Byte, KeccakF1600T>;
 }
 
+#[test]
+fn test_keccakf1600_permutation() {
+    // Keccak-f[1600] of the zero state, the first lanes.
+    let mut s = KeccakF1600B::default();
+    s.permutation();
+    assert_eq!(0xF1258F7940E1DDE7, s.state[0]);
+    assert_eq!(0x84D5CCF933C0478A, s.state[1]);
+}
+
 #[test]
 fn test_keccakf1600t_transform() {
     use iota_streams_core::tbits::{
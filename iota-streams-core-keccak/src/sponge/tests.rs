@@ -33,6 +33,13 @@ fn encrypt_decrypt_keccak_byte() {
     encrypt_decrypt_n::<Byte, KeccakF1600B>(2 * RATE);
 }
 
+#[test]
+fn absorb_chunks_keccak_byte() {
+    const RATE: usize = <KeccakF1600B as PRP<Byte>>::RATE;
+    absorb_chunks_n::<Byte, KeccakF1600B>(27);
+    absorb_chunks_n::<Byte, KeccakF1600B>(3 * RATE + 28);
+}
+
 #[test]
 fn tbits_with_size_boundary_cases_keccak_trit() {
     tbits_with_size_boundary_cases::<Trit, KeccakF1600T>();
@@ -53,3 +60,10 @@ fn encrypt_decrypt_keccak_trit() {
     encrypt_decrypt_n::<Trit, KeccakF1600T>(RATE + 28);
     encrypt_decrypt_n::<Trit, KeccakF1600T>(2 * RATE);
 }
+
+#[test]
+fn absorb_chunks_keccak_trit() {
+    const RATE: usize = <KeccakF1600T as PRP<Trit>>::RATE;
+    absorb_chunks_n::<Trit, KeccakF1600T>(27);
+    absorb_chunks_n::<Trit, KeccakF1600T>(3 * RATE + 28);
+}
@@ -1,5 +1,6 @@
 use iota_streams_core::{
     hash::Hash,
+    heap::HeapSize,
     prng::Prng,
     sponge::prp::PRP,
     tbits::{
@@ -250,6 +251,17 @@ where
     }
 }
 
+impl<TW, P> HeapSize for PrivateKey<TW, P>
+where
+    P: Parameters<TW>,
+{
+    /// Merkle tree nodes are copied to be accounted.
+    fn heap_size(&self) -> usize {
+        let (_, _, nodes) = self.mt.store();
+        self.sk.prng.heap_size() + self.sk.nonce.heap_size() + nodes.heap_size()
+    }
+}
+
 //TODO: Debug
 pub struct PublicKey<TW, P> {
     pub(crate) pk: Tbits<TW>,
@@ -278,6 +290,12 @@ where
 }
 impl<TW, P> Eq for PublicKey<TW, P> where TW: BasicTbitWord {}
 
+impl<TW, P> HeapSize for PublicKey<TW, P> {
+    fn heap_size(&self) -> usize {
+        self.pk.heap_size()
+    }
+}
+
 impl<TW, P> PublicKey<TW, P> {
     pub fn tbits(&self) -> &Tbits<TW> {
         &self.pk
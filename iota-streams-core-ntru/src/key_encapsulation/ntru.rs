@@ -1,11 +1,12 @@
 use std::{
     borrow::Borrow,
-    collections::HashSet,
     fmt,
     hash,
 };
 
 use iota_streams_core::{
+    heap::HeapSize,
+    prelude::HashSet,
     prng::Prng,
     sponge::{
         prp::PRP,
@@ -444,6 +445,12 @@ where
 /// For types implementing `Borrow` the following statement must be true:
 /// "x.borrow() == y.borrow() should give the same result as x == y".
 /// For `PublicKey` this doesn't hold but with neglegible probability.
+impl<TW, F> HeapSize for PublicKey<TW, F> {
+    fn heap_size(&self) -> usize {
+        self.pk.heap_size()
+    }
+}
+
 impl<TW, F> Borrow<Pkid<TW>> for PublicKey<TW, F> {
     fn borrow(&self) -> &Pkid<TW> {
         unsafe { std::mem::transmute(self.tbits()) }
@@ -451,6 +458,7 @@ impl<TW, F> Borrow<Pkid<TW>> for PublicKey<TW, F> {
 }
 
 /// Thin wrapper around Tbits which contains either a full public key or the first `PKID_SIZE` of the public key.
+#[derive(Clone)]
 pub struct Pkid<TW>(pub Tbits<TW>);
 
 impl<TW> Pkid<TW> {
@@ -462,6 +470,12 @@ impl<TW> Pkid<TW> {
     }
 }
 
+impl<TW> HeapSize for Pkid<TW> {
+    fn heap_size(&self) -> usize {
+        self.0.heap_size()
+    }
+}
+
 impl<TW> AsRef<Tbits<TW>> for Pkid<TW> {
     fn as_ref(&self) -> &Tbits<TW> {
         &self.0
@@ -540,6 +554,12 @@ where
     (sk, pk)
 }
 
+impl<TW, F> HeapSize for PrivateKey<TW, F> {
+    fn heap_size(&self) -> usize {
+        self.sk.heap_size()
+    }
+}
+
 impl<TW, F> PrivateKey<TW, F>
 where
     TW: TritWord + SpongosTbitWord,
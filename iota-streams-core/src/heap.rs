@@ -0,0 +1,297 @@
+//! Heap accounting, eg. for sizing heaps of embedded targets.
+//!
+//! `HeapSize` estimates heap memory owned by a value such as Author or Subscriber state.
+//! Hash map entries are accounted by capacity, allocator overhead is not included.
+//!
+//! With `alloc-trace` feature `TracingAlloc` wraps a global allocator, either the system
+//! one or a user-supplied one (eg. a firmware heap), tracks the current and peak heap usage
+//! and optionally fails allocations exceeding a budget:
+//!
+//! ```ignore
+//! #[global_allocator]
+//! static ALLOC: TracingAlloc<System> = TracingAlloc::new(System);
+//!
+//! ALLOC.reset_peak();
+//! let msg = author.share_keyload_for_everyone(&link)?;
+//! println!("keyload took up to {} bytes", ALLOC.peak() - ALLOC.current());
+//! ```
+
+use std::{
+    collections::{
+        HashMap,
+        HashSet,
+    },
+    mem,
+};
+
+/// Estimate of heap memory owned by a value, in bytes.
+pub trait HeapSize {
+    fn heap_size(&self) -> usize;
+}
+
+macro_rules! heap_size_zero {
+    ($($t:ty),*) => {
+        $(impl HeapSize for $t {
+            fn heap_size(&self) -> usize {
+                0
+            }
+        })*
+    };
+}
+
+heap_size_zero!((), bool, u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl HeapSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Option<T> {
+    fn heap_size(&self) -> usize {
+        self.as_ref().map_or(0, |t| t.heap_size())
+    }
+}
+
+macro_rules! heap_size_tuple {
+    ($($t:ident $i:tt),*) => {
+        impl<$($t: HeapSize),*> HeapSize for ($($t,)*) {
+            fn heap_size(&self) -> usize {
+                0 $(+ self.$i.heap_size())*
+            }
+        }
+    };
+}
+
+heap_size_tuple!(A 0, B 1);
+heap_size_tuple!(A 0, B 1, C 2);
+heap_size_tuple!(A 0, B 1, C 2, D 3);
+heap_size_tuple!(A 0, B 1, C 2, D 3, E 4);
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * mem::size_of::<T>() + self.iter().map(|t| t.heap_size()).sum::<usize>()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize, S> HeapSize for HashMap<K, V, S> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * mem::size_of::<(K, V)>()
+            + self.iter().map(|(k, v)| k.heap_size() + v.heap_size()).sum::<usize>()
+    }
+}
+
+impl<T: HeapSize, S> HeapSize for HashSet<T, S> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * mem::size_of::<T>() + self.iter().map(|t| t.heap_size()).sum::<usize>()
+    }
+}
+
+#[cfg(feature = "hashbrown-maps")]
+impl<K: HeapSize, V: HeapSize, S> HeapSize for hashbrown::HashMap<K, V, S> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * mem::size_of::<(K, V)>()
+            + self.iter().map(|(k, v)| k.heap_size() + v.heap_size()).sum::<usize>()
+    }
+}
+
+#[cfg(feature = "hashbrown-maps")]
+impl<T: HeapSize, S> HeapSize for hashbrown::HashSet<T, S> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * mem::size_of::<T>() + self.iter().map(|t| t.heap_size()).sum::<usize>()
+    }
+}
+
+/// Ordered maps keep a hash table of indices besides entries.
+#[cfg(feature = "ordered-maps")]
+impl<K: HeapSize, V: HeapSize, S> HeapSize for indexmap::IndexMap<K, V, S> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * (mem::size_of::<(K, V)>() + 2 * mem::size_of::<usize>())
+            + self.iter().map(|(k, v)| k.heap_size() + v.heap_size()).sum::<usize>()
+    }
+}
+
+#[cfg(feature = "ordered-maps")]
+impl<T: HeapSize, S> HeapSize for indexmap::IndexSet<T, S> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * (mem::size_of::<T>() + 2 * mem::size_of::<usize>())
+            + self.iter().map(|t| t.heap_size()).sum::<usize>()
+    }
+}
+
+#[cfg(feature = "alloc-trace")]
+pub use self::trace::TracingAlloc;
+
+#[cfg(feature = "alloc-trace")]
+mod trace {
+    use std::{
+        alloc::{
+            GlobalAlloc,
+            Layout,
+        },
+        ptr,
+        sync::atomic::{
+            AtomicUsize,
+            Ordering,
+        },
+    };
+
+    /// Global allocator wrapper tracking heap usage.
+    pub struct TracingAlloc<A> {
+        inner: A,
+        current: AtomicUsize,
+        peak: AtomicUsize,
+        budget: AtomicUsize,
+    }
+
+    impl<A> TracingAlloc<A> {
+        pub const fn new(inner: A) -> Self {
+            Self {
+                inner,
+                current: AtomicUsize::new(0),
+                peak: AtomicUsize::new(0),
+                budget: AtomicUsize::new(usize::MAX),
+            }
+        }
+
+        /// Bytes currently allocated.
+        pub fn current(&self) -> usize {
+            self.current.load(Ordering::Relaxed)
+        }
+
+        /// Maximum of bytes allocated at once since creation or the last `reset_peak`.
+        pub fn peak(&self) -> usize {
+            self.peak.load(Ordering::Relaxed)
+        }
+
+        /// Start tracking peak usage from the current one, eg. before a channel operation.
+        pub fn reset_peak(&self) {
+            self.peak.store(self.current(), Ordering::Relaxed);
+        }
+
+        /// Fail allocations which would exceed `budget` bytes in total, `None` removes the limit.
+        pub fn set_budget(&self, budget: Option<usize>) {
+            self.budget.store(budget.unwrap_or(usize::MAX), Ordering::Relaxed);
+        }
+
+        fn reserve(&self, size: usize) -> bool {
+            let budget = self.budget.load(Ordering::Relaxed);
+            let mut current = self.current();
+            loop {
+                let new = match current.checked_add(size) {
+                    Some(new) if new <= budget => new,
+                    _ => return false,
+                };
+                match self
+                    .current
+                    .compare_exchange_weak(current, new, Ordering::Relaxed, Ordering::Relaxed)
+                {
+                    Ok(_) => {
+                        self.peak.fetch_max(new, Ordering::Relaxed);
+                        return true;
+                    }
+                    Err(c) => current = c,
+                }
+            }
+        }
+
+        fn release(&self, size: usize) {
+            self.current.fetch_sub(size, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl<A: GlobalAlloc> GlobalAlloc for TracingAlloc<A> {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            if !self.reserve(layout.size()) {
+                return ptr::null_mut();
+            }
+            let p = self.inner.alloc(layout);
+            if p.is_null() {
+                self.release(layout.size());
+            }
+            p
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            if !self.reserve(layout.size()) {
+                return ptr::null_mut();
+            }
+            let p = self.inner.alloc_zeroed(layout);
+            if p.is_null() {
+                self.release(layout.size());
+            }
+            p
+        }
+
+        unsafe fn dealloc(&self, p: *mut u8, layout: Layout) {
+            self.inner.dealloc(p, layout);
+            self.release(layout.size());
+        }
+
+        unsafe fn realloc(&self, p: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let old_size = layout.size();
+            if new_size > old_size && !self.reserve(new_size - old_size) {
+                return ptr::null_mut();
+            }
+            let q = self.inner.realloc(p, layout, new_size);
+            if q.is_null() {
+                if new_size > old_size {
+                    self.release(new_size - old_size);
+                }
+            } else if new_size < old_size {
+                self.release(old_size - new_size);
+            }
+            q
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tbits::{
+        trinary::Trit,
+        Tbits,
+    };
+
+    #[test]
+    fn heap_size() {
+        let t = Tbits::<Trit>::zero(81);
+        assert!(t.heap_size() >= 81 * mem::size_of::<Trit>());
+        let v = vec![t.clone(), t.clone()];
+        assert!(v.heap_size() >= 2 * t.heap_size() + 2 * mem::size_of::<Tbits<Trit>>());
+        assert_eq!(0, None::<Tbits<Trit>>.heap_size());
+    }
+
+    #[cfg(feature = "alloc-trace")]
+    #[test]
+    fn tracing_alloc() {
+        use std::alloc::{
+            GlobalAlloc,
+            Layout,
+            System,
+        };
+
+        let alloc = TracingAlloc::new(System);
+        let layout = Layout::from_size_align(100, 8).unwrap();
+        unsafe {
+            let p = alloc.alloc(layout);
+            assert!(!p.is_null());
+            let p = alloc.realloc(p, layout, 300);
+            assert!(!p.is_null());
+            assert_eq!(300, alloc.current());
+            let p = alloc.realloc(p, Layout::from_size_align(300, 8).unwrap(), 50);
+            assert_eq!(50, alloc.current());
+            assert_eq!(300, alloc.peak());
+
+            alloc.set_budget(Some(100));
+            assert!(alloc.alloc(layout).is_null());
+            assert_eq!(50, alloc.current());
+
+            alloc.dealloc(p, Layout::from_size_align(50, 8).unwrap());
+            assert_eq!(0, alloc.current());
+            alloc.reset_peak();
+            assert_eq!(0, alloc.peak());
+        }
+    }
+}
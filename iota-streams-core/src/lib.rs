@@ -1,5 +1,8 @@
 pub mod hash;
+pub mod heap;
+pub mod prelude;
 pub mod prng;
 pub mod psk;
+pub mod seal;
 pub mod sponge;
 pub mod tbits;
@@ -0,0 +1,47 @@
+//! Maps and sets for user state: link stores, pre-shared keys, NTRU public keys.
+//!
+//! By default they are `std` hash maps and sets, `hashbrown` ones with `hashbrown-maps` feature.
+//! Iteration order of both differs across runs. With `ordered-maps` feature they are `indexmap`
+//! maps and sets iterating in insertion order, so that exported state is reproducible.
+//! Note, removing an entry from an ordered map moves the last entry into its place.
+//!
+//! `hash_map` and `hash_set` modules contain iterator types of the selected implementation.
+
+#[cfg(feature = "ordered-maps")]
+pub use indexmap::{
+    map as hash_map,
+    set as hash_set,
+    IndexMap as HashMap,
+    IndexSet as HashSet,
+};
+
+#[cfg(all(feature = "hashbrown-maps", not(feature = "ordered-maps")))]
+pub use hashbrown::{
+    hash_map,
+    hash_set,
+    HashMap,
+    HashSet,
+};
+
+#[cfg(not(any(feature = "hashbrown-maps", feature = "ordered-maps")))]
+pub use std::collections::{
+    hash_map,
+    hash_set,
+    HashMap,
+    HashSet,
+};
+
+#[cfg(all(test, feature = "ordered-maps"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn insertion_order() {
+        let keys = [5_usize, 3, 8, 1, 9, 2];
+        let mut map = HashMap::new();
+        for k in keys.iter() {
+            map.insert(*k, ());
+        }
+        assert!(map.keys().eq(keys.iter()));
+    }
+}
@@ -1,6 +1,7 @@
 //! Spongos-base pseudo-random trinary number generator.
 
 use crate::{
+    heap::HeapSize,
     sponge::{
         prp::PRP,
         spongos::Spongos,
@@ -27,6 +28,12 @@ pub struct Prng<TW, G> {
     _phantom: std::marker::PhantomData<G>,
 }
 
+impl<TW, G> HeapSize for Prng<TW, G> {
+    fn heap_size(&self) -> usize {
+        self.secret_key.heap_size()
+    }
+}
+
 fn random_tbits<TW, R>(n: usize, rng: &mut R) -> Tbits<TW>
 where
     //TW: BasicTbitWord,
@@ -1,13 +1,24 @@
 //! Pre-shared key is a secret symmetric key shared between two parties and is used for
 //! (session) key exchange.
 
-use crate::tbits::{
-    word::BasicTbitWord,
-    Tbits,
+use crate::{
+    prelude::HashMap,
+    sponge::{
+        prp::PRP,
+        spongos::Spongos,
+    },
+    tbits::{
+        word::{
+            BasicTbitWord,
+            SpongosTbitWord,
+            StringTbitWord,
+        },
+        Tbits,
+    },
 };
 use std::{
-    collections::HashMap,
     hash,
+    str::FromStr,
 };
 
 /// Size of pre-shared key identifier.
@@ -32,6 +43,106 @@ pub type IPsk<'a, TW> = (&'a PskId<TW>, &'a Psk<TW>);
 /// Container (set) of pre-shared key identifiers.
 pub type PskIds<TW> = Vec<PskId<TW>>;
 
+/// Derive the identifier of a pre-shared key: `pskid = H(psk)`.
+pub fn pskid_from_psk<TW, F>(psk: &Psk<TW>) -> PskId<TW>
+where
+    TW: SpongosTbitWord,
+    F: PRP<TW> + Default,
+{
+    let mut s = Spongos::<TW, F>::init();
+    s.absorb_tbits(psk);
+    s.commit();
+    s.squeeze_tbits(PSKID_SIZE)
+}
+
+/// Derive a pre-shared key from a binary seed, eg. a passphrase.
+/// Each byte of the seed is absorbed as two trytes.
+pub fn psk_from_seed<TW, F>(seed: &[u8]) -> Psk<TW>
+where
+    TW: SpongosTbitWord + StringTbitWord,
+    F: PRP<TW> + Default,
+{
+    const TRYTES: &[u8; 27] = b"9ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    let trytes: String = seed
+        .iter()
+        .flat_map(|b| vec![TRYTES[*b as usize / 27] as char, TRYTES[*b as usize % 27] as char])
+        .collect();
+    let mut s = Spongos::<TW, F>::init();
+    s.absorb_tbits(&Tbits::from_str(&trytes).unwrap());
+    s.commit();
+    s.squeeze_tbits(PSK_SIZE)
+}
+
+/// Derive a pre-shared key from a hex-encoded binary seed, see `psk_from_seed`.
+pub fn psk_from_hex<TW, F>(hex: &str) -> Result<Psk<TW>, ()>
+where
+    TW: SpongosTbitWord + StringTbitWord,
+    F: PRP<TW> + Default,
+{
+    // `from_str_radix` accepts a leading sign, so digits are checked first.
+    if hex.len() % 2 != 0 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(());
+    }
+    let seed = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| ()))
+        .collect::<Result<Vec<u8>, ()>>()?;
+    Ok(psk_from_seed::<TW, F>(&seed))
+}
+
+/// Parse a pre-shared key identifier from a tryte string.
+pub fn pskid_from_str<TW>(s: &str) -> Result<PskId<TW>, ()>
+where
+    TW: StringTbitWord,
+{
+    let pskid = PskId::from_str(s)?;
+    if pskid.size() == PSKID_SIZE {
+        Ok(pskid)
+    } else {
+        Err(())
+    }
+}
+
+/// Parse a pre-shared key from a tryte string.
+pub fn psk_from_str<TW>(s: &str) -> Result<Psk<TW>, ()>
+where
+    TW: StringTbitWord,
+{
+    let psk = Psk::from_str(s)?;
+    if psk.size() == PSK_SIZE {
+        Ok(psk)
+    } else {
+        Err(())
+    }
+}
+
+/// Insert a pre-shared key with its identifier derived with `pskid_from_psk`.
+pub fn add_psk<TW, F>(psks: &mut Psks<TW>, psk: Psk<TW>) -> PskId<TW>
+where
+    TW: SpongosTbitWord,
+    TW::Tbit: hash::Hash,
+    F: PRP<TW> + Default,
+{
+    let pskid = pskid_from_psk::<TW, F>(&psk);
+    psks.insert(pskid.clone(), psk);
+    pskid
+}
+
+/// Insert a pre-shared key, fail if `pskid` is not derived from `psk` with `pskid_from_psk`.
+pub fn insert_psk<TW, F>(psks: &mut Psks<TW>, pskid: PskId<TW>, psk: Psk<TW>) -> Result<(), ()>
+where
+    TW: SpongosTbitWord,
+    TW::Tbit: hash::Hash,
+    F: PRP<TW> + Default,
+{
+    if pskid == pskid_from_psk::<TW, F>(&psk) {
+        psks.insert(pskid, psk);
+        Ok(())
+    } else {
+        Err(())
+    }
+}
+
 /// Select only pre-shared keys with given identifiers.
 pub fn filter_psks<'a, TW>(psks: &'a Psks<TW>, pskids: &'_ PskIds<TW>) -> Vec<IPsk<'a, TW>>
 where
@@ -43,3 +154,34 @@ where
         .filter_map(|pskid| psks.get_key_value(pskid))
         .collect::<Vec<(&PskId<TW>, &Psk<TW>)>>()
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        sponge::prp::troika::Troika,
+        tbits::trinary::Trit,
+    };
+
+    #[test]
+    fn derive_pskid() {
+        let psk = psk_from_seed::<Trit, Troika>(b"passphrase");
+        assert_eq!(PSK_SIZE, psk.size());
+        assert!(psk_from_hex::<Trit, Troika>("70617373706872617365") == Ok(psk.clone()));
+        assert!(psk_from_hex::<Trit, Troika>("7061737").is_err());
+        assert!(psk_from_hex::<Trit, Troika>("+0617373706872617365").is_err());
+        assert!(psk_from_hex::<Trit, Troika>("70 17373706872617365").is_err());
+        assert!(psk_from_str::<Trit>(&psk.to_string()) == Ok(psk.clone()));
+        assert!(psk_from_seed::<Trit, Troika>(b"passphrasE") != psk);
+
+        let pskid = pskid_from_psk::<Trit, Troika>(&psk);
+        assert!(pskid_from_str::<Trit>(&pskid.to_string()) == Ok(pskid.clone()));
+        assert!(pskid_from_str::<Trit>(&psk.to_string()).is_err());
+
+        let mut psks = Psks::new();
+        assert!(insert_psk::<Trit, Troika>(&mut psks, Tbits::cycle_str(PSKID_SIZE, "PSKID"), psk.clone()).is_err());
+        assert!(insert_psk::<Trit, Troika>(&mut psks, pskid.clone(), psk.clone()).is_ok());
+        assert!(add_psk::<Trit, Troika>(&mut psks, psk.clone()) == pskid);
+        assert_eq!(1, psks.len());
+    }
+}
@@ -0,0 +1,104 @@
+//! Authenticated encryption of side-band data with a pre-shared key.
+//!
+//! Data is encrypted with the same Spongos primitives as Streams messages:
+//!
+//! ```pb3
+//! absorb tryte psk[81];
+//! absorb trytes nonce;
+//! commit;
+//! mask trytes data;
+//! commit;
+//! squeeze tryte mac[81];
+//! ```
+//!
+//! Sealed data is the encrypted data followed by MAC. Nonce must be unique per key, eg. a
+//! counter or a random value, it is not included in sealed data.
+
+use crate::{
+    psk::Psk,
+    sponge::{
+        prp::PRP,
+        spongos::Spongos,
+    },
+    tbits::{
+        word::SpongosTbitWord,
+        Tbits,
+    },
+};
+
+fn init<TW, F>(psk: &Psk<TW>, nonce: &Tbits<TW>) -> Spongos<TW, F>
+where
+    TW: SpongosTbitWord,
+    F: PRP<TW> + Default,
+{
+    let mut s = Spongos::<TW, F>::init();
+    s.absorb_tbits(psk);
+    s.absorb_tbits(nonce);
+    s.commit();
+    s
+}
+
+/// Encrypt and authenticate `data`, sealed data is `Spongos::MAC_SIZE` trits longer.
+pub fn seal<TW, F>(psk: &Psk<TW>, nonce: &Tbits<TW>, data: &Tbits<TW>) -> Tbits<TW>
+where
+    TW: SpongosTbitWord,
+    F: PRP<TW> + Default,
+{
+    let mut s = init::<TW, F>(psk, nonce);
+    let mut sealed = s.encrypt_tbits(data);
+    s.commit();
+    sealed += &s.squeeze_tbits(Spongos::<TW, F>::MAC_SIZE);
+    sealed
+}
+
+/// Decrypt sealed data, `None` is returned if it's too short or MAC doesn't match.
+pub fn open<TW, F>(psk: &Psk<TW>, nonce: &Tbits<TW>, sealed: &Tbits<TW>) -> Option<Tbits<TW>>
+where
+    TW: SpongosTbitWord,
+    F: PRP<TW> + Default,
+{
+    let n = sealed.size().checked_sub(Spongos::<TW, F>::MAC_SIZE)?;
+    let mut s = init::<TW, F>(psk, nonce);
+    let mut data = Tbits::zero(n);
+    s.decrypt(sealed.slice().take(n), &mut data.slice_mut());
+    s.commit();
+    if s.squeeze_eq(sealed.slice().drop(n)) {
+        Some(data)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        psk::PSK_SIZE,
+        sponge::prp::troika::Troika,
+        tbits::trinary::Trit,
+    };
+    use std::str::FromStr;
+
+    #[test]
+    fn seal_open() {
+        let psk = Tbits::<Trit>::cycle_str(PSK_SIZE, "PSK");
+        let nonce = Tbits::<Trit>::from_str("NONCE").unwrap();
+        let data = Tbits::<Trit>::from_str("SIDE9BAND9DATA").unwrap();
+
+        let sealed = seal::<Trit, Troika>(&psk, &nonce, &data);
+        // Test vector, other implementations must produce the same sealed data.
+        assert_eq!(
+            sealed.to_string(),
+            "GOOEVBNTAUMHPX\
+             LVXZCNUJYTHTUSLHHXQEWNQQQIVZXRYCUSMGZLBOZUJSRYZHGXJPFXAXXNSKZZDRH9ZRLRFFHKGTUNEKD"
+        );
+        assert!(open::<Trit, Troika>(&psk, &nonce, &sealed) == Some(data.clone()));
+
+        let other_nonce = Tbits::<Trit>::from_str("NONCF").unwrap();
+        assert!(open::<Trit, Troika>(&psk, &other_nonce, &sealed).is_none());
+        let sealed = sealed.to_string();
+        let tampered = if sealed.starts_with('A') { "B" } else { "A" }.to_string() + &sealed[1..];
+        assert!(open::<Trit, Troika>(&psk, &nonce, &Tbits::from_str(&tampered).unwrap()).is_none());
+        assert!(open::<Trit, Troika>(&psk, &nonce, &Tbits::from_str(&sealed[1..]).unwrap()).is_none());
+    }
+}
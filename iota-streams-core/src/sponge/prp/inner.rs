@@ -1,6 +1,7 @@
 use std::hash;
 
 use crate::{
+    heap::HeapSize,
     sponge::prp::PRP,
     tbits::{
         word::BasicTbitWord,
@@ -48,6 +49,12 @@ where
     }
 }
 
+impl<TW, F> HeapSize for Inner<TW, F> {
+    fn heap_size(&self) -> usize {
+        self.inner.heap_size()
+    }
+}
+
 impl<TW, F> AsRef<Tbits<TW>> for Inner<TW, F> {
     fn as_ref(&self) -> &Tbits<TW> {
         &self.inner
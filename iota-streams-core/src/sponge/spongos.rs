@@ -258,6 +258,25 @@ where
         self.squeeze_eq(y.slice())
     }
 
+    /// Absorb data split into a sequence of chunks, eg. a large payload streamed from disk.
+    /// The resulting state is the same as after absorbing concatenation of the chunks.
+    pub fn absorb_chunks<'a, I>(&mut self, chunks: I)
+    where
+        TW: 'a,
+        I: IntoIterator<Item = TbitSlice<'a, TW>>,
+    {
+        for chunk in chunks {
+            self.absorb(chunk);
+        }
+    }
+
+    /// Commit and squeeze a tag of `MAC_SIZE` tbits.
+    /// It corresponds to `commit; squeeze tryte mac[MAC_SIZE/3];` PB3 commands.
+    pub fn squeeze_tag(&mut self) -> Tbits<TW> {
+        self.commit();
+        self.squeeze_tbits(Self::MAC_SIZE)
+    }
+
     /// Encrypt a trit slice with Spongos object.
     /// Input and output slices must be non-overlapping.
     pub fn encrypt(&mut self, mut x: TbitSlice<TW>, y: &mut TbitSliceMut<TW>) {
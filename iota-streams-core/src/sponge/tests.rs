@@ -193,6 +193,27 @@ where
     }
 }
 
+pub fn absorb_chunks_n<TW, F>(n: usize)
+where
+    TW: SpongosTbitWord,
+    F: PRP<TW> + Default,
+{
+    let mut rng = Spongos::<TW, F>::init();
+    rng.absorb_tbits(&Tbits::zero(n));
+    rng.commit();
+    let x = rng.squeeze_tbits(n);
+
+    let mut s = Spongos::<TW, F>::init();
+    s.absorb_tbits(&x);
+    let tag = s.squeeze_tag();
+
+    for chunk_size in &[1, 5, F::RATE - 1, F::RATE, F::RATE + 1] {
+        let mut s = Spongos::<TW, F>::init();
+        s.absorb_chunks(x.slice().chunks(*chunk_size));
+        assert!(tag == s.squeeze_tag(), "{}/{}: chunked tag mismatch", n, chunk_size);
+    }
+}
+
 #[test]
 fn tbits_with_size_boundary_cases_troika() {
     tbits_with_size_boundary_cases::<Trit, Troika>();
@@ -213,6 +234,14 @@ fn encrypt_decrypt_troika() {
     encrypt_decrypt_n::<Trit, Troika>(2 * RATE);
 }
 
+#[test]
+fn absorb_chunks_troika() {
+    const RATE: usize = <Troika as PRP<Trit>>::RATE;
+    absorb_chunks_n::<Trit, Troika>(27);
+    absorb_chunks_n::<Trit, Troika>(RATE);
+    absorb_chunks_n::<Trit, Troika>(3 * RATE + 28);
+}
+
 /*
 #[test]
 fn inner() {
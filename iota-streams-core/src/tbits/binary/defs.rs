@@ -2,6 +2,10 @@ use std::{
     fmt,
     hash,
 };
+use subtle::{
+    Choice,
+    ConstantTimeEq,
+};
 
 /// Bit type with values in range 0..1.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug, Default)]
@@ -16,6 +20,12 @@ impl fmt::Display for Bit {
     }
 }
 
+impl ConstantTimeEq for Bit {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
 impl hash::Hash for Bit {
     fn hash<H: hash::Hasher>(&self, state: &mut H) {
         self.0.hash(state);
@@ -28,6 +38,12 @@ pub struct Byte(pub u8); //0..255
 pub const MAX_BYTE: Byte = Byte(255);
 pub const MIN_BYTE: Byte = Byte(0);
 
+impl ConstantTimeEq for Byte {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
 impl fmt::Display for Byte {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{:02x}", self.0)
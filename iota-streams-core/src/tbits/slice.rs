@@ -427,6 +427,12 @@ where
         unsafe { TW::equals(self.size(), self.r.d, self.p, other.r.d, other.p) }
     }
 
+    /// Compare two slices of equal size in constant time, see `BasicTbitWord::ct_equals`.
+    pub fn ct_equals(&self, other: &Self) -> bool {
+        assert_eq!(self.size(), other.size());
+        unsafe { TW::ct_equals(self.size(), self.r.d, self.p, other.r.d, other.p) }
+    }
+
     /// Compare two slices.
     pub fn equals_min(&self, other: &Self) -> (bool, usize) {
         let n = self.size_min(other.size());
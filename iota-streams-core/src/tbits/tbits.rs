@@ -1,6 +1,7 @@
 use std::{
     fmt,
     hash,
+    mem,
     ops,
     str::FromStr,
 };
@@ -9,6 +10,7 @@ use super::{
     slice::*,
     word::*,
 };
+use crate::heap::HeapSize;
 
 /// Container for tbits using a certain tbit encoding.
 /// Access to the individual tbits should be performed via `TbitConstSliceT` and `TbitMutSliceT` types.
@@ -96,6 +98,11 @@ where
     pub fn is_empty(&self) -> bool {
         0 == self.n
     }
+
+    /// Compare containers in constant time if sizes are equal, use it for secrets instead of `==`.
+    pub fn ct_eq(&self, other: &Self) -> bool {
+        self.size() == other.size() && self.slice().ct_equals(&other.slice())
+    }
 }
 
 impl<TW> Tbits<TW>
@@ -239,6 +246,13 @@ where
         v
     }
 }
+
+impl<TW> HeapSize for Tbits<TW> {
+    fn heap_size(&self) -> usize {
+        self.buf.capacity() * mem::size_of::<TW>()
+    }
+}
+
 /*
 pub type DefaultTbitWord = Tbit;
 pub type Tbits = Tbits<DefaultTbitWord>;
@@ -48,6 +48,15 @@ where
     assert_eq!(ts[..m], to_tbits(x2.slice().take(m))[..]);
     assert_eq!(ts[m..n], to_tbits(x2.slice().drop(m).take(n - m))[..]);
     assert_eq!(ts[n..], to_tbits(x2.slice().drop(n))[..]);
+    assert!(t0.slice().ct_equals(&x2.slice().take(m)));
+    assert!(t1.slice().ct_equals(&x2.slice().drop(m).take(n - m)));
+    assert!(t2.slice().ct_equals(&x2.slice().drop(n)));
+    assert!(t012.ct_eq(&x2));
+    if ts[m..n].iter().any(|t| *t != TW::ZERO_TBIT) {
+        let z = Tbits::<TW>::zero(ts.len());
+        assert!(!t1.slice().ct_equals(&z.slice().drop(m).take(n - m)));
+        assert!(!x2.slice().drop(m).take(n - m).ct_equals(&z.slice().drop(m).take(n - m)));
+    }
     x2.slice_mut().set_zero();
     x2.slice_mut().drop(m).take(n - m).put_tbits(&ts[m..n]);
     x2.slice_mut().drop(n).put_tbits(&ts[n..]);
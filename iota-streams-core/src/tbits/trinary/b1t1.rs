@@ -162,6 +162,14 @@ mod tests {
         crate::tbits::tests::add(&a, &b, &ab);
     }
 
+    #[test]
+    fn ct_eq() {
+        let a = Tbits::<Trit>::from_str("ABC").unwrap();
+        assert!(a.ct_eq(&Tbits::<Trit>::from_str("ABC").unwrap()));
+        assert!(!a.ct_eq(&Tbits::<Trit>::from_str("ABD").unwrap()));
+        assert!(!a.ct_eq(&Tbits::<Trit>::from_str("AB").unwrap()));
+    }
+
     #[test]
     fn get_put_char() {
         let alphabet = "9ABCDEFGHIJKLMNOPQRSTUVWXYZ";
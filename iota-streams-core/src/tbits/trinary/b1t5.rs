@@ -1,5 +1,10 @@
 //! 5 trits per 1 byte.
 
+use subtle::{
+    Choice,
+    ConstantTimeEq,
+};
+
 use super::defs::*;
 use crate::tbits::word::BasicTbitWord;
 
@@ -23,6 +28,13 @@ impl B1T5 {
     }
 }
 
+/// Representations are compared, like `PartialEq`.
+impl ConstantTimeEq for B1T5 {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
 impl BasicTbitWord for B1T5 {
     type Tbit = Trit;
     const SIZE: usize = 5;
@@ -1,4 +1,8 @@
 use std::fmt;
+use subtle::{
+    Choice,
+    ConstantTimeEq,
+};
 
 /// Unsigned trit type with values in range 0..2. Used by Troika implementation.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug, Default, Hash)]
@@ -12,6 +16,12 @@ impl fmt::Display for Trit {
     }
 }
 
+impl ConstantTimeEq for Trit {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
 /// Unsigned tryte type.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug, Default)]
 pub struct Tryte(pub u8); //0..26
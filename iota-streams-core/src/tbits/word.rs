@@ -1,14 +1,21 @@
 use std::fmt;
+use subtle::{
+    Choice,
+    ConstantTimeEq,
+};
+
+/// Maximum number of tbits per word, size of word buffers on stack.
+const MAX_WORD_SIZE: usize = 8;
 
 /// Abstraction for a binary/trinary word containing one or several tbits (bits/trits).
 /// The size and encoding of the word is defined by the implementation.
 /// Many functions take a pair `(d,p)` encoding a slice of tbits as input where
 /// `d` is the current tbit offset, `p` is the raw pointer to the first word in a slice.
-pub trait BasicTbitWord: Sized + Copy + PartialEq {
-    /// The number of tbits per word.
+pub trait BasicTbitWord: Sized + Copy + PartialEq + ConstantTimeEq {
+    /// The number of tbits per word, at most 8.
     const SIZE: usize;
     /// Trit or bit.
-    type Tbit: Sized + Copy + PartialEq + fmt::Display;
+    type Tbit: Sized + Copy + PartialEq + ConstantTimeEq + fmt::Display;
 
     /// Zero tbit.
     const ZERO_TBIT: Self::Tbit;
@@ -296,6 +303,58 @@ pub trait BasicTbitWord: Sized + Copy + PartialEq {
             xs == ys
         }
     }
+
+    /// Compare `n` tbits from `(dx,x)` slice into `(dy,y)` in constant time, ie. all tbits
+    /// are compared even after a mismatch. Use it for secrets such as MACs and keys.
+    unsafe fn ct_equals(mut s: usize, mut dx: usize, mut x: *const Self, dy: usize, mut y: *const Self) -> bool {
+        debug_assert!(Self::SIZE <= MAX_WORD_SIZE);
+        let mut xs = [Self::ZERO_TBIT; MAX_WORD_SIZE];
+        let mut ys = [Self::ZERO_TBIT; MAX_WORD_SIZE];
+        let mut eq = Choice::from(1);
+
+        if dx % Self::SIZE == dy % Self::SIZE {
+            x = x.add(dx / Self::SIZE);
+            dx = dx % Self::SIZE;
+            y = y.add(dy / Self::SIZE);
+
+            if dx != 0 && s != 0 {
+                Self::word_to_tbits(*x, xs.as_mut_ptr());
+                Self::word_to_tbits(*y, ys.as_mut_ptr());
+                let d = std::cmp::min(s, Self::SIZE - dx);
+                for i in dx..dx + d {
+                    eq &= xs[i].ct_eq(&ys[i]);
+                }
+                s -= d;
+                x = x.add(1);
+                y = y.add(1);
+            }
+
+            // Whole words are compared packed.
+            while s >= Self::SIZE {
+                eq &= (*x).ct_eq(&*y);
+                s -= Self::SIZE;
+                x = x.add(1);
+                y = y.add(1);
+            }
+
+            if s != 0 {
+                Self::word_to_tbits(*x, xs.as_mut_ptr());
+                Self::word_to_tbits(*y, ys.as_mut_ptr());
+                for i in 0..s {
+                    eq &= xs[i].ct_eq(&ys[i]);
+                }
+            }
+        } else {
+            // Rare case, compare tbit by tbit.
+            for i in 0..s {
+                Self::word_to_tbits(*x.add((dx + i) / Self::SIZE), xs.as_mut_ptr());
+                Self::word_to_tbits(*y.add((dy + i) / Self::SIZE), ys.as_mut_ptr());
+                eq &= xs[(dx + i) % Self::SIZE].ct_eq(&ys[(dy + i) % Self::SIZE]);
+            }
+        }
+
+        eq.into()
+    }
 }
 
 pub trait StringTbitWord: BasicTbitWord {
@@ -486,13 +545,13 @@ pub trait SpongosTbitWord: BasicTbitWord {
 
     /// Squeeze tbits `y` from state `s`, OVERWRITE mode.
     unsafe fn squeeze_eq_overwrite(ds: usize, s: *mut Self, n: usize, dy: usize, y: *const Self) -> bool {
-        let r = Self::equals(n, ds, s as *const Self, dy, y);
+        let r = Self::ct_equals(n, ds, s as *const Self, dy, y);
         Self::set_zero(n, ds, s);
         r
     }
     /// Squeeze tbits `y` from state `s`, ADD/XOR mode.
     unsafe fn squeeze_eq_xor(ds: usize, s: *mut Self, n: usize, dy: usize, y: *const Self) -> bool {
-        Self::equals(n, ds, s as *const Self, dy, y)
+        Self::ct_equals(n, ds, s as *const Self, dy, y)
     }
 
     /// Encrypt tbits `x` into `y` with state `s`, OVERWRITE mode.
@@ -0,0 +1,247 @@
+//! Derive `ContentWrap` and `ContentUnwrap` for message contents described by field attributes.
+//!
+//! Fields are processed in declaration order, each annotated field is encoded with
+//! the corresponding Protobuf3 command; fields without attributes are not encoded.
+//!
+//! ```ignore
+//! #[derive(Pb3Content)]
+//! struct Content<TW, Link: HasLink> {
+//!     #[join]
+//!     link: <Link as HasLink>::Rel,
+//!     #[absorb]
+//!     nonce: NTrytes<TW>,
+//!     #[skip]
+//!     #[guard(self.count.0 <= 10, "Too many items")]
+//!     count: Size,
+//!     #[mask]
+//!     payload: Trytes<TW>,
+//!     _phantom: std::marker::PhantomData<Link>,
+//! }
+//! ```
+//!
+//! * `#[join]` -- `join link` with the link store, the field has the link type.
+//!
+//! * `#[absorb]`, `#[mask]`, `#[skip]` -- the respective commands.
+//!
+//! * `#[guard(cond, "msg")]` -- unwrapping fails with `msg` unless `cond` holds,
+//! the condition is checked after the field is unwrapped and may refer to `self`.
+//!
+//! The content type must have `TW` type parameter, `F` type parameter is optional.
+//! Generated impls require `TW: IntTbitWord + SpongosTbitWord + TritWord` and `F: PRP<TW>`,
+//! the where clause of the content type is kept.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{
+    Span,
+    TokenStream as TokenStream2,
+};
+use quote::quote;
+use syn::{
+    parse::ParseStream,
+    parse_macro_input,
+    parse_quote,
+    spanned::Spanned,
+    Data,
+    DeriveInput,
+    Error,
+    Expr,
+    Fields,
+    GenericParam,
+    Ident,
+    LitStr,
+    Result,
+    Token,
+    Type,
+};
+
+/// Protobuf3 command applied to a field.
+enum Command {
+    Join(Type),
+    Absorb,
+    Mask,
+    Skip,
+    Guard(Expr, LitStr),
+}
+
+struct Field {
+    ident: Ident,
+    commands: Vec<Command>,
+}
+
+fn parse_guard(input: ParseStream) -> Result<(Expr, LitStr)> {
+    let cond: Expr = input.parse()?;
+    input.parse::<Token![,]>()?;
+    let msg: LitStr = input.parse()?;
+    Ok((cond, msg))
+}
+
+fn parse_fields(input: &DeriveInput) -> Result<Vec<Field>> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(Error::new(input.span(), "Pb3Content requires named fields")),
+        },
+        _ => return Err(Error::new(input.span(), "Pb3Content can only be derived for structs")),
+    };
+    let mut parsed = Vec::new();
+    for field in fields {
+        let mut commands = Vec::new();
+        for attr in &field.attrs {
+            let command = if attr.path.is_ident("join") {
+                Command::Join(field.ty.clone())
+            } else if attr.path.is_ident("absorb") {
+                Command::Absorb
+            } else if attr.path.is_ident("mask") {
+                Command::Mask
+            } else if attr.path.is_ident("skip") {
+                Command::Skip
+            } else if attr.path.is_ident("guard") {
+                let (cond, msg) = attr.parse_args_with(parse_guard)?;
+                Command::Guard(cond, msg)
+            } else {
+                continue;
+            };
+            commands.push(command);
+        }
+        parsed.push(Field {
+            ident: field.ident.clone().unwrap(),
+            commands,
+        });
+    }
+    Ok(parsed)
+}
+
+/// Commands encoding fields, `unwrap` selects mutable references to fields.
+fn encode(fields: &[Field], store: TokenStream2, unwrap: bool) -> TokenStream2 {
+    let mut body = TokenStream2::new();
+    for field in fields {
+        let ident = &field.ident;
+        let value = if unwrap {
+            quote!(&mut self.#ident)
+        } else {
+            quote!(&self.#ident)
+        };
+        for command in &field.commands {
+            body.extend(match command {
+                Command::Join(_) => quote!(ctx.join(#store, #value)?;),
+                Command::Absorb => quote!(ctx.absorb(#value)?;),
+                Command::Mask => quote!(ctx.mask(#value)?;),
+                Command::Skip => quote!(ctx.skip(#value)?;),
+                Command::Guard(cond, msg) if unwrap => quote!(ctx.guard(#cond, #msg)?;),
+                Command::Guard(_, _) => quote!(),
+            });
+        }
+    }
+    body
+}
+
+fn derive(input: DeriveInput) -> Result<TokenStream2> {
+    let fields = parse_fields(&input)?;
+    let name = &input.ident;
+    let type_param = |name: &str| {
+        input.generics.params.iter().any(|p| match p {
+            GenericParam::Type(t) => t.ident == name,
+            _ => false,
+        })
+    };
+    if !type_param("TW") {
+        return Err(Error::new(input.ident.span(), "Pb3Content requires `TW` type parameter"));
+    }
+
+    let joins: Vec<&Type> = fields
+        .iter()
+        .flat_map(|field| {
+            field.commands.iter().filter_map(|command| match command {
+                Command::Join(link) => Some(link),
+                _ => None,
+            })
+        })
+        .collect();
+    if joins.len() > 1 {
+        return Err(Error::new(input.ident.span(), "Pb3Content supports a single `join` field"));
+    }
+
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    let mut generics = input.generics.clone();
+    if !type_param("F") {
+        generics.params.push(parse_quote!(F));
+    }
+    generics.params.push(parse_quote!(Store));
+    {
+        let where_clause = generics.make_where_clause();
+        where_clause.predicates.push(parse_quote!(
+            TW: ::iota_streams_core::tbits::word::IntTbitWord
+                + ::iota_streams_core::tbits::word::SpongosTbitWord
+                + ::iota_streams_core::tbits::trinary::TritWord
+        ));
+        where_clause
+            .predicates
+            .push(parse_quote!(F: ::iota_streams_core::sponge::prp::PRP<TW>));
+        for link in &joins {
+            where_clause.predicates.push(parse_quote!(
+                #link: Eq + ::iota_streams_protobuf3::types::SkipFallback<TW, F>
+            ));
+            where_clause.predicates.push(parse_quote!(
+                Store: ::iota_streams_protobuf3::types::LinkStore<TW, F, #link>
+            ));
+        }
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    // Link store is not used by `sizeof`, an empty store of the respective link type is joined.
+    let sizeof_store = Ident::new("empty_store", Span::call_site());
+    let sizeof_store_decl = joins.first().map(|link| {
+        quote!(let #sizeof_store = ::iota_streams_protobuf3::types::EmptyLinkStore::<TW, F, #link, ()>::default();)
+    });
+    let sizeof_body = encode(&fields, quote!(&#sizeof_store), false);
+    let wrap_body = encode(&fields, quote!(store), false);
+    let unwrap_body = encode(&fields, quote!(store), true);
+
+    Ok(quote! {
+        impl #impl_generics ::iota_streams_app::message::ContentWrap<TW, F, Store> for #name #ty_generics #where_clause {
+            #[allow(unused_imports)]
+            fn sizeof<'c>(
+                &self,
+                ctx: &'c mut ::iota_streams_protobuf3::command::sizeof::Context<TW, F>,
+            ) -> ::failure::Fallible<&'c mut ::iota_streams_protobuf3::command::sizeof::Context<TW, F>> {
+                use ::iota_streams_protobuf3::command::*;
+                #sizeof_store_decl
+                #sizeof_body
+                Ok(ctx)
+            }
+
+            #[allow(unused_imports, unused_variables)]
+            fn wrap<'c, OS: ::iota_streams_protobuf3::io::OStream<TW>>(
+                &self,
+                store: &Store,
+                ctx: &'c mut ::iota_streams_protobuf3::command::wrap::Context<TW, F, OS>,
+            ) -> ::failure::Fallible<&'c mut ::iota_streams_protobuf3::command::wrap::Context<TW, F, OS>> {
+                use ::iota_streams_protobuf3::command::*;
+                #wrap_body
+                Ok(ctx)
+            }
+        }
+
+        impl #impl_generics ::iota_streams_app::message::ContentUnwrap<TW, F, Store> for #name #ty_generics #where_clause {
+            #[allow(unused_imports, unused_variables)]
+            fn unwrap<'c, IS: ::iota_streams_protobuf3::io::IStream<TW>>(
+                &mut self,
+                store: &Store,
+                ctx: &'c mut ::iota_streams_protobuf3::command::unwrap::Context<TW, F, IS>,
+            ) -> ::failure::Fallible<&'c mut ::iota_streams_protobuf3::command::unwrap::Context<TW, F, IS>> {
+                use ::iota_streams_protobuf3::command::*;
+                #unwrap_body
+                Ok(ctx)
+            }
+        }
+    })
+}
+
+/// Derive `ContentWrap` and `ContentUnwrap` from field attributes, see the crate docs.
+#[proc_macro_derive(Pb3Content, attributes(join, absorb, mask, skip, guard))]
+pub fn derive_pb3_content(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive(input).unwrap_or_else(|e| e.to_compile_error()).into()
+}
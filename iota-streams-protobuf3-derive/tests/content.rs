@@ -0,0 +1,99 @@
+use failure::{
+    ensure,
+    Fallible,
+};
+use std::str::FromStr;
+
+use iota_streams_app::{
+    message::{
+        ContentUnwrap,
+        ContentWrap,
+    },
+    transport::tangle::MsgId,
+};
+use iota_streams_core::{
+    sponge::{
+        prp::troika::Troika,
+        spongos::Spongos,
+    },
+    tbits::{
+        trinary::Trit,
+        TbitSlice,
+        TbitSliceMut,
+        Tbits,
+    },
+};
+use iota_streams_protobuf3::{
+    command::{
+        sizeof,
+        unwrap,
+        wrap,
+    },
+    types::*,
+};
+use iota_streams_protobuf3_derive::Pb3Content;
+
+#[derive(Pb3Content)]
+struct Content<TW> {
+    #[join]
+    link: MsgId<TW>,
+    #[absorb]
+    nonce: NTrytes<TW>,
+    #[skip]
+    #[guard(self.count.0 <= 3, "Too many items")]
+    count: Size,
+    #[mask]
+    payload: Trytes<TW>,
+    not_encoded: usize,
+}
+
+type Store = DefaultLinkStore<Trit, Troika, MsgId<Trit>, ()>;
+
+fn content(count: usize) -> Content<Trit> {
+    Content {
+        link: MsgId::from_str(&"L".repeat(27)).unwrap(),
+        nonce: NTrytes(Tbits::from_str("NONCE").unwrap()),
+        count: Size(count),
+        payload: Trytes(Tbits::from_str("PAYLOAD").unwrap()),
+        not_encoded: 1,
+    }
+}
+
+fn wrap_unwrap(wrapped: &Content<Trit>) -> Fallible<Content<Trit>> {
+    let mut store = Store::default();
+    store.update(&wrapped.link, Spongos::init(), ())?;
+
+    let mut ctx = sizeof::Context::<Trit, Troika>::new();
+    ContentWrap::<Trit, Troika, Store>::sizeof(wrapped, &mut ctx)?;
+    let mut buf = Tbits::<Trit>::zero(ctx.get_size());
+    {
+        let mut ctx = wrap::Context::<Trit, Troika, TbitSliceMut<Trit>>::new(buf.slice_mut());
+        wrapped.wrap(&store, &mut ctx)?;
+        ensure!(ctx.stream.is_empty(), "Output stream is not exhausted.");
+    }
+
+    let mut unwrapped = Content {
+        link: MsgId::default(),
+        nonce: NTrytes::zero(15),
+        count: Size(0),
+        payload: Trytes::default(),
+        not_encoded: 0,
+    };
+    let mut ctx = unwrap::Context::<Trit, Troika, TbitSlice<Trit>>::new(buf.slice());
+    unwrapped.unwrap(&store, &mut ctx)?;
+    ensure!(ctx.stream.is_empty(), "Input stream is not exhausted.");
+    Ok(unwrapped)
+}
+
+#[test]
+fn derived_content() {
+    let wrapped = content(3);
+    let unwrapped = wrap_unwrap(&wrapped).unwrap();
+    assert!(unwrapped.link == wrapped.link);
+    assert!(unwrapped.nonce == wrapped.nonce);
+    assert!(unwrapped.count == wrapped.count);
+    assert!(unwrapped.payload == wrapped.payload);
+    assert!(unwrapped.not_encoded == 0);
+
+    assert!(wrap_unwrap(&content(4)).is_err());
+}
@@ -74,6 +74,14 @@ pub trait Ntrukem<Key, Secret> {
     fn ntrukem(&mut self, key: Key, secret: Secret) -> Fallible<&mut Self>;
 }
 
+/// Merkle command. Merkle inclusion proof is encoded and checked against the tree root.
+/// The root is an external field absorbed into Spongos state, so the message is bound to the tree.
+/// Wrap operation refuses a proof not matching the root, Unwrap operation fails with
+/// bad Merkle proof error.
+pub trait Merkle<Root, Proof> {
+    fn merkle(&mut self, root: Root, proof: Proof) -> Fallible<&mut Self>;
+}
+
 /// Fork command. Fork Spongos state and continue processing `cont` commands.
 /// After the fork is finished the resulting Spongos state is discarded and
 /// field processing continues using the saved current Spongos state.
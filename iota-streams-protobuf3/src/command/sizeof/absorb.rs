@@ -10,10 +10,13 @@ use crate::{
         sizeof_sizet,
         AbsorbFallback,
         Fallback,
+        Float64,
+        Int64,
         NTrytes,
         Size,
         Trint3,
         Trytes,
+        INT64_TRYTES,
     },
 };
 use iota_streams_core::tbits::word::BasicTbitWord;
@@ -50,6 +53,36 @@ impl<TW, F> Absorb<Size> for Context<TW, F> {
     }
 }
 
+/// `int64` and `float64` values are encoded with `INT64_TRYTES` trytes.
+impl<TW, F> Absorb<&Int64> for Context<TW, F> {
+    fn absorb(&mut self, _n: &Int64) -> Fallible<&mut Self> {
+        self.size += 3 * INT64_TRYTES;
+        Ok(self)
+    }
+}
+
+/// `int64` and `float64` values are encoded with `INT64_TRYTES` trytes.
+impl<TW, F> Absorb<Int64> for Context<TW, F> {
+    fn absorb(&mut self, n: Int64) -> Fallible<&mut Self> {
+        self.absorb(&n)
+    }
+}
+
+/// `int64` and `float64` values are encoded with `INT64_TRYTES` trytes.
+impl<TW, F> Absorb<&Float64> for Context<TW, F> {
+    fn absorb(&mut self, _x: &Float64) -> Fallible<&mut Self> {
+        self.size += 3 * INT64_TRYTES;
+        Ok(self)
+    }
+}
+
+/// `int64` and `float64` values are encoded with `INT64_TRYTES` trytes.
+impl<TW, F> Absorb<Float64> for Context<TW, F> {
+    fn absorb(&mut self, x: Float64) -> Fallible<&mut Self> {
+        self.absorb(&x)
+    }
+}
+
 /// `trytes` has variable size thus the size is encoded before the content trytes.
 impl<'a, TW, F> Absorb<&'a Trytes<TW>> for Context<TW, F>
 where
@@ -8,6 +8,7 @@ use crate::{
         External,
         Fallback,
         NTrytes,
+        Trytes,
     },
 };
 
@@ -43,6 +44,13 @@ impl<'a, TW, F> Absorb<External<&'a NTrytes<TW>>> for Context<TW, F>
     }
 }
 
+/// External values are not encoded in the trinary stream.
+impl<'a, TW, F> Absorb<External<&'a Trytes<TW>>> for Context<TW, F> {
+    fn absorb(&mut self, _external: External<&'a Trytes<TW>>) -> Fallible<&mut Self> {
+        Ok(self)
+    }
+}
+
 impl<'a, TW, F, T: 'a + AbsorbExternalFallback<TW, F>> Absorb<External<Fallback<&'a T>>> for Context<TW, F> {
     fn absorb(&mut self, val: External<Fallback<&'a T>>) -> Fallible<&mut Self> {
         ((val.0).0).sizeof_absorb_external(self)?;
@@ -8,10 +8,15 @@ use crate::{
     command::Mask,
     types::{
         sizeof_sizet,
+        Fallback,
+        Float64,
+        Int64,
+        MaskFallback,
         NTrytes,
         Size,
         Trint3,
         Trytes,
+        INT64_TRYTES,
     },
 };
 use iota_streams_core::tbits::word::BasicTbitWord;
@@ -48,6 +53,36 @@ impl<TW, F> Mask<Size> for Context<TW, F> {
     }
 }
 
+/// Mask `int64` and `float64`, they are encoded with `INT64_TRYTES` trytes.
+impl<TW, F> Mask<&Int64> for Context<TW, F> {
+    fn mask(&mut self, _n: &Int64) -> Fallible<&mut Self> {
+        self.size += 3 * INT64_TRYTES;
+        Ok(self)
+    }
+}
+
+/// Mask `int64` and `float64`, they are encoded with `INT64_TRYTES` trytes.
+impl<TW, F> Mask<Int64> for Context<TW, F> {
+    fn mask(&mut self, n: Int64) -> Fallible<&mut Self> {
+        self.mask(&n)
+    }
+}
+
+/// Mask `int64` and `float64`, they are encoded with `INT64_TRYTES` trytes.
+impl<TW, F> Mask<&Float64> for Context<TW, F> {
+    fn mask(&mut self, _x: &Float64) -> Fallible<&mut Self> {
+        self.size += 3 * INT64_TRYTES;
+        Ok(self)
+    }
+}
+
+/// Mask `int64` and `float64`, they are encoded with `INT64_TRYTES` trytes.
+impl<TW, F> Mask<Float64> for Context<TW, F> {
+    fn mask(&mut self, x: Float64) -> Fallible<&mut Self> {
+        self.mask(&x)
+    }
+}
+
 /// Mask `n` trytes.
 impl<TW, F> Mask<&NTrytes<TW>> for Context<TW, F>
 where
@@ -99,3 +134,10 @@ where
         Ok(self)
     }
 }
+
+impl<'a, TW, F, T: 'a + MaskFallback<TW, F>> Mask<&'a Fallback<T>> for Context<TW, F> {
+    fn mask(&mut self, val: &'a Fallback<T>) -> Fallible<&mut Self> {
+        (val.0).sizeof_mask(self)?;
+        Ok(self)
+    }
+}
@@ -0,0 +1,34 @@
+use failure::Fallible;
+
+use super::Context;
+use crate::{
+    command::{
+        Absorb,
+        Merkle,
+    },
+    types::{
+        External,
+        MerkleProof,
+        NTrytes,
+        Size,
+    },
+};
+use iota_streams_core::tbits::word::SpongosTbitWord;
+
+/// Proof size depends on the depth of the tree.
+impl<'a, TW, F> Merkle<External<&'a NTrytes<TW>>, &'a MerkleProof<TW>> for Context<TW, F>
+where
+    TW: SpongosTbitWord,
+{
+    fn merkle(&mut self, root: External<&'a NTrytes<TW>>, proof: &'a MerkleProof<TW>) -> Fallible<&mut Self> {
+        proof.check()?;
+        self.absorb(root)?
+            .absorb(&proof.leaf)?
+            .absorb(Size(proof.index))?
+            .absorb(Size(proof.path.len()))?;
+        for sibling in &proof.path {
+            self.absorb(sibling)?;
+        }
+        Ok(self)
+    }
+}
@@ -34,6 +34,7 @@ mod skip;
 mod squeeze;
 mod squeeze_external;
 
+mod merkle;
 mod mssig;
 mod ntrukem;
 
@@ -9,11 +9,14 @@ use crate::{
     types::{
         sizeof_sizet,
         Fallback,
+        Float64,
+        Int64,
         NTrytes,
         Size,
         SkipFallback,
         Trint3,
         Trytes,
+        INT64_TRYTES,
     },
 };
 use iota_streams_core::tbits::word::BasicTbitWord;
@@ -49,6 +52,36 @@ impl<TW, F> Skip<Size> for Context<TW, F> {
     }
 }
 
+/// `int64` and `float64` values are encoded with `INT64_TRYTES` trytes.
+impl<TW, F> Skip<&Int64> for Context<TW, F> {
+    fn skip(&mut self, _n: &Int64) -> Fallible<&mut Self> {
+        self.size += 3 * INT64_TRYTES;
+        Ok(self)
+    }
+}
+
+/// `int64` and `float64` values are encoded with `INT64_TRYTES` trytes.
+impl<TW, F> Skip<Int64> for Context<TW, F> {
+    fn skip(&mut self, n: Int64) -> Fallible<&mut Self> {
+        self.skip(&n)
+    }
+}
+
+/// `int64` and `float64` values are encoded with `INT64_TRYTES` trytes.
+impl<TW, F> Skip<&Float64> for Context<TW, F> {
+    fn skip(&mut self, _x: &Float64) -> Fallible<&mut Self> {
+        self.size += 3 * INT64_TRYTES;
+        Ok(self)
+    }
+}
+
+/// `int64` and `float64` values are encoded with `INT64_TRYTES` trytes.
+impl<TW, F> Skip<Float64> for Context<TW, F> {
+    fn skip(&mut self, x: Float64) -> Fallible<&mut Self> {
+        self.skip(&x)
+    }
+}
+
 /// `trytes` is encoded with `sizeof_sizet(n) + 3 * n` trits.
 impl<'a, TW, F> Skip<&'a Trytes<TW>> for Context<TW, F>
 where
@@ -22,6 +22,7 @@ use iota_streams_core::{
         },
         word::{
             IntTbitWord,
+            RngTbitWord,
             SpongosTbitWord,
             StringTbitWord,
         },
@@ -155,6 +156,86 @@ fn size() {
     assert!(dbg!(absorb_mask_size::<Trit, Troika>()).is_ok());
 }
 
+fn absorb_mask_skip_int64_float64<TW, F>() -> Fallible<()>
+where
+    TW: SpongosTbitWord + StringTbitWord + TritWord,
+    F: PRP<TW> + Default,
+{
+    let ns = [0, 1, -1, 13, 14, -14, 1 << 40, i64::MAX, i64::MIN, i64::MIN + 1];
+    let xs = [0.0, -0.0, 1.5, -273.15, f64::MIN_POSITIVE, f64::MAX, f64::INFINITY, f64::NEG_INFINITY];
+
+    for (n, x) in ns.iter().zip(xs.iter().cycle()) {
+        let (n, x) = (Int64(*n), Float64(*x));
+        let buf_size = sizeof::Context::<TW, F>::new()
+            .absorb(n)?
+            .mask(n)?
+            .skip(n)?
+            .absorb(x)?
+            .mask(x)?
+            .skip(x)?
+            .get_size();
+        ensure!(buf_size == 6 * 3 * INT64_TRYTES, "Unexpected buf size.");
+
+        let mut buf = Tbits::<TW>::zero(buf_size);
+        {
+            let mut ctx = wrap::Context::<TW, F, TbitSliceMut<TW>>::new(buf.slice_mut());
+            ctx.absorb(&n)?.mask(&n)?.skip(&n)?.absorb(&x)?.mask(&x)?.skip(&x)?;
+            ensure!(ctx.stream.is_empty(), "Output stream is not exhausted.");
+        }
+
+        let mut ns2 = [Int64::default(); 3];
+        let mut xs2 = [Float64::default(); 3];
+        {
+            let mut ctx = unwrap::Context::<TW, F, TbitSlice<TW>>::new(buf.slice());
+            ctx.absorb(&mut ns2[0])?
+                .mask(&mut ns2[1])?
+                .skip(&mut ns2[2])?
+                .absorb(&mut xs2[0])?
+                .mask(&mut xs2[1])?
+                .skip(&mut xs2[2])?;
+            ensure!(ctx.stream.is_empty(), "Input stream is not exhausted.");
+        }
+        ensure!(ns2.iter().all(|n2| *n2 == n), "Bad int64: {:?} != {:?}.", ns2, n);
+        ensure!(
+            xs2.iter().all(|x2| x2.0.to_bits() == x.0.to_bits()),
+            "Bad float64: {:?} != {:?}.",
+            xs2,
+            x
+        );
+    }
+
+    // Encoding is big-endian and canonical.
+    let encode = |n: Int64| -> Fallible<Tbits<TW>> {
+        let mut buf = Tbits::<TW>::zero(3 * INT64_TRYTES);
+        wrap::Context::<TW, F, TbitSliceMut<TW>>::new(buf.slice_mut()).skip(n)?;
+        Ok(buf)
+    };
+    ensure!(encode(Int64(1))? == Tbits::from_str("9999999999999A").unwrap());
+    ensure!(encode(Int64(-28))? == Tbits::from_str("999999999999ZZ").unwrap());
+    let mut n = Int64::default();
+    let overflown = Tbits::<TW>::from_str("MMMMMMMMMMMMMM").unwrap();
+    ensure!(unwrap::Context::<TW, F, TbitSlice<TW>>::new(overflown.slice())
+        .skip(&mut n)
+        .is_err());
+
+    // NaNs are canonicalized.
+    let mut x = Float64::default();
+    let nan = encode(Float64(-f64::NAN).to_int64())?;
+    ensure!(nan == encode(Float64(f64::NAN).to_int64())?);
+    unwrap::Context::<TW, F, TbitSlice<TW>>::new(nan.slice()).skip(&mut x)?;
+    ensure!(x.0.is_nan());
+    let signaling_nan = encode(Int64(0x7ff0_0000_0000_0001))?;
+    ensure!(unwrap::Context::<TW, F, TbitSlice<TW>>::new(signaling_nan.slice())
+        .skip(&mut x)
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn int64_float64() {
+    assert!(dbg!(absorb_mask_skip_int64_float64::<Trit, Troika>()).is_ok());
+}
+
 fn absorb_mask_squeeze_trytes_mac<TW, F>() -> Fallible<()>
 where
     TW: StringTbitWord + IntTbitWord + SpongosTbitWord + TritWord,
@@ -262,6 +343,36 @@ fn trytes() {
     assert!(dbg!(absorb_mask_squeeze_trytes_mac::<Trit, Troika>()).is_ok());
 }
 
+fn unwrap_trytes_max_size<TW, F>() -> Fallible<()>
+where
+    TW: StringTbitWord + SpongosTbitWord + TritWord,
+    F: PRP<TW> + Default,
+{
+    let ta = Trytes::<TW>(Tbits::cycle_str(300, "ABSORBED"));
+    let tm = Trytes::<TW>(Tbits::cycle_str(30, "MASKED"));
+    let buf_size = sizeof::Context::<TW, F>::new().absorb(&ta)?.mask(&tm)?.get_size();
+    let mut buf = Tbits::zero(buf_size);
+    wrap::Context::<TW, F, TbitSliceMut<TW>>::new(buf.slice_mut())
+        .absorb(&ta)?
+        .mask(&tm)?;
+
+    let unwrap = |max_size| -> Fallible<()> {
+        let mut ctx = unwrap::Context::<TW, F, TbitSlice<TW>>::new(buf.slice()).with_max_size(max_size);
+        ctx.absorb(&mut Trytes::default())?.mask(&mut Trytes::default())?;
+        Ok(())
+    };
+    unwrap(None)?;
+    unwrap(Some(100))?;
+    let err = unwrap(Some(99)).unwrap_err();
+    ensure!(err.downcast_ref() == Some(&unwrap::SizeLimitExceeded { size: 100, max_size: 99 }));
+    Ok(())
+}
+
+#[test]
+fn trytes_max_size() {
+    assert!(dbg!(unwrap_trytes_max_size::<Trit, Troika>()).is_ok());
+}
+
 fn mssig_traverse<TW, F, P>() -> Fallible<()>
 where
     TW: StringTbitWord + IntTbitWord + SpongosTbitWord + TritWord,
@@ -367,6 +478,281 @@ fn ntrukem() {
     assert!(dbg!(ntrukem_caps::<Trit, Troika>()).is_ok());
 }
 
+fn encrypted_link_store_lookup<TW, F>() -> Fallible<()>
+where
+    TW: StringTbitWord + SpongosTbitWord + RngTbitWord + IntTbitWord,
+    F: PRP<TW> + Clone + Default,
+    F::Inner: Default + AsRef<Tbits<TW>> + AsMut<Tbits<TW>>,
+{
+    let key = prng::random_key(Spongos::<TW, F>::KEY_SIZE);
+    let nonce = prng::random_nonce(Spongos::<TW, F>::NONCE_SIZE);
+    let mut store = EncryptedLinkStore::<TW, F, usize, usize>::new(key.clone(), nonce)?;
+
+    let mut s = Spongos::<TW, F>::init();
+    s.absorb_tbits(&Tbits::from_str("LINKSTORE").unwrap());
+    s.commit();
+    store.update(&1, s.clone(), 1)?;
+    store.update(&2, s.clone(), 2)?;
+
+    let (mut s1, info1) = store.lookup(&1)?;
+    let (mut s2, info2) = store.lookup(&2)?;
+    ensure!(info1 == 1 && info2 == 2, "Bad link info.");
+    // Only inner part of the state is stored.
+    let h = Spongos::<TW, F>::from_inner(s.to_inner()).squeeze_tbits(Spongos::<TW, F>::HASH_SIZE);
+    ensure!(s1.squeeze_eq_tbits(&h), "Unsealed spongos state differs.");
+    ensure!(s2.squeeze_eq_tbits(&h), "Unsealed spongos state differs.");
+
+    // Sealed states are bound to their links and info.
+    let mut states: Vec<_> = store.sealed_states().map(|(link, state)| (*link, state.clone())).collect();
+    states.sort_by_key(|(link, _)| *link);
+    let mut tampered = EncryptedLinkStore::<TW, F, usize, usize>::new(key.clone(), store.nonce().clone())?;
+    ensure!(tampered.insert_sealed(2, states[0].1.clone()).is_err(), "Swapped link not detected.");
+    let mut state = states[0].1.clone();
+    state.info = 2;
+    ensure!(tampered.insert_sealed(1, state).is_err(), "Changed info not detected.");
+
+    // Saved states load into a store with the same key.
+    let mut loaded = EncryptedLinkStore::<TW, F, usize, usize>::new(key, store.nonce().clone())?;
+    for (link, state) in states {
+        loaded.insert_sealed(link, state)?;
+    }
+    let (mut s1, info1) = loaded.lookup(&1)?;
+    ensure!(info1 == 1 && s1.squeeze_eq_tbits(&h), "Loaded spongos state differs.");
+
+    store.erase(&1);
+    ensure!(store.lookup(&1).is_err(), "Erased link found.");
+    Ok(())
+}
+
+#[test]
+fn encrypted_link_store() {
+    assert!(dbg!(encrypted_link_store_lookup::<Trit, Troika>()).is_ok());
+}
+
+fn latest_link_store_lookup<TW, F>() -> Fallible<()>
+where
+    TW: StringTbitWord + SpongosTbitWord,
+    F: PRP<TW> + Clone + Default,
+    F::Inner: Clone,
+{
+    let mut store = LatestLinkStore::<TW, F, usize, char>::default();
+    let mut s = Spongos::<TW, F>::init();
+    s.absorb_tbits(&Tbits::from_str("LINKSTORE").unwrap());
+    s.commit();
+    store.update(&1, s.clone(), 'a')?;
+    store.update(&2, s.clone(), 'b')?;
+    store.update(&3, s.clone(), 'a')?;
+
+    ensure!(store.lookup(&1).is_err(), "Evicted link found.");
+    let (_, info2) = store.lookup(&2)?;
+    let (_, info3) = store.lookup(&3)?;
+    ensure!(info2 == 'b' && info3 == 'a', "Bad link info.");
+    ensure!(store.links().count() == 2, "Store is not bounded.");
+
+    store.erase(&3);
+    ensure!(store.lookup(&3).is_err(), "Erased link found.");
+    Ok(())
+}
+
+#[test]
+fn latest_link_store() {
+    assert!(dbg!(latest_link_store_lookup::<Trit, Troika>()).is_ok());
+}
+
+fn default_link_store_epochs<TW, F>() -> Fallible<()>
+where
+    TW: StringTbitWord + SpongosTbitWord,
+    F: PRP<TW> + Clone + Default,
+    F::Inner: Clone,
+{
+    let mut store = DefaultLinkStore::<TW, F, usize, ()>::default();
+    let s = Spongos::<TW, F>::init();
+    store.update(&0, s.clone(), ())?;
+    store.start_epoch(2);
+    store.update(&1, s.clone(), ())?;
+    store.start_epoch(2);
+    store.update(&2, s.clone(), ())?;
+    ensure!(store.lookup(&1).is_ok(), "Link from the previous epoch forgotten.");
+    store.start_epoch(2);
+    store.update(&3, s.clone(), ())?;
+    ensure!(store.lookup(&1).is_err(), "Link from an old epoch kept.");
+    ensure!(store.lookup(&0).is_ok(), "Link from before the first epoch forgotten.");
+    ensure!(store.lookup(&2).is_ok() && store.lookup(&3).is_ok(), "Link from the last epochs forgotten.");
+    Ok(())
+}
+
+#[test]
+fn link_store_epochs() {
+    assert!(dbg!(default_link_store_epochs::<Trit, Troika>()).is_ok());
+}
+
+fn default_link_store_labels<TW, F>() -> Fallible<()>
+where
+    TW: StringTbitWord + SpongosTbitWord,
+    F: PRP<TW> + Clone + Default,
+    F::Inner: Clone,
+{
+    let mut store = DefaultLinkStore::<TW, F, usize, ()>::default();
+    let s = Spongos::<TW, F>::init();
+    ensure!(store.tag_link(&0, "genesis").is_err(), "Unknown link tagged.");
+    store.update(&0, s.clone(), ())?;
+    store.update(&1, s.clone(), ())?;
+    store.tag_link(&0, "genesis")?;
+    store.tag_link(&0, "latest-config")?;
+    store.tag_link(&1, "latest-config")?;
+    ensure!(store.find_by_label("genesis") == Some(0));
+    ensure!(store.find_by_label("latest-config") == Some(1), "Label not moved.");
+    ensure!(store.labels().len() == 2);
+    store.erase(&1);
+    ensure!(store.find_by_label("latest-config").is_none(), "Label of erased link kept.");
+    store.start_epoch(1);
+    store.update(&2, s.clone(), ())?;
+    store.tag_link(&2, "latest-config")?;
+    store.start_epoch(1);
+    ensure!(store.find_by_label("latest-config").is_none(), "Label of forgotten link kept.");
+    ensure!(store.find_by_label("genesis") == Some(0));
+    Ok(())
+}
+
+#[test]
+fn link_store_labels() {
+    assert!(dbg!(default_link_store_labels::<Trit, Troika>()).is_ok());
+}
+
+struct TestLink<TW>(NTrytes<TW>);
+
+impl<TW, F> MaskFallback<TW, F> for TestLink<TW>
+where
+    TW: SpongosTbitWord + TritWord,
+    F: PRP<TW>,
+{
+    fn sizeof_mask(&self, ctx: &mut sizeof::Context<TW, F>) -> Fallible<()> {
+        ctx.mask(&self.0)?;
+        Ok(())
+    }
+    fn wrap_mask<OS: crate::io::OStream<TW>>(&self, ctx: &mut wrap::Context<TW, F, OS>) -> Fallible<()> {
+        ctx.mask(&self.0)?;
+        Ok(())
+    }
+    fn unwrap_mask<IS: crate::io::IStream<TW>>(&mut self, ctx: &mut unwrap::Context<TW, F, IS>) -> Fallible<()> {
+        ctx.mask(&mut self.0)?;
+        Ok(())
+    }
+}
+
+fn mask_fallback_link<TW, F>() -> Fallible<()>
+where
+    TW: StringTbitWord + SpongosTbitWord + TritWord,
+    F: PRP<TW> + Default,
+{
+    let key = External(NTrytes(Tbits::<TW>::from_str("KEY").unwrap()));
+    let id = Tbits::<TW>::from_str("MASKEDLINK").unwrap();
+    let link = Fallback(TestLink(NTrytes(id.clone())));
+    let buf_size = sizeof::Context::<TW, F>::new().mask(&link)?.get_size();
+    ensure!(buf_size == id.size(), "Unexpected buf size.");
+
+    let mut buf = Tbits::<TW>::zero(buf_size);
+    {
+        let mut ctx = wrap::Context::<TW, F, TbitSliceMut<TW>>::new(buf.slice_mut());
+        ctx.absorb(&key)?.commit()?.mask(&link)?;
+        ensure!(ctx.stream.is_empty(), "Output stream is not exhausted.");
+    }
+    ensure!(buf != id, "Link is not masked.");
+
+    let mut link2 = Fallback(TestLink(NTrytes(Tbits::<TW>::zero(buf_size))));
+    {
+        let mut ctx = unwrap::Context::<TW, F, TbitSlice<TW>>::new(buf.slice());
+        ctx.absorb(&key)?.commit()?.mask(&mut link2)?;
+        ensure!(ctx.stream.is_empty(), "Input stream is not exhausted.");
+    }
+    ensure!(id == ((link2.0).0).0, "Unmasked link doesn't match.");
+    Ok(())
+}
+
+#[test]
+fn mask_fallback() {
+    assert!(dbg!(mask_fallback_link::<Trit, Troika>()).is_ok());
+}
+
+fn merkle_proofs<TW, F>() -> Fallible<()>
+where
+    TW: StringTbitWord + SpongosTbitWord + TritWord,
+    F: PRP<TW> + Default,
+{
+    let leaves: Vec<_> = ["LEAFA", "LEAFB", "LEAFC", "LEAFD", "LEAFE"]
+        .iter()
+        .map(|s| NTrytes::<TW>(Tbits::cycle_str(MERKLE_HASH_SIZE, s)))
+        .collect();
+    let root = MerkleProof::<TW>::gen::<F>(&leaves, 0)?.root::<F>();
+    let other_root = NTrytes::<TW>(Tbits::cycle_str(MERKLE_HASH_SIZE, "OTHERROOT"));
+
+    for index in 0..leaves.len() {
+        let proof = MerkleProof::<TW>::gen::<F>(&leaves, index)?;
+        ensure!(proof.path.len() == 3);
+        ensure!(proof.root::<F>() == root, "Proof of leaf {} doesn't match the root.", index);
+
+        let buf_size = sizeof::Context::<TW, F>::new().merkle(External(&root), &proof)?.get_size();
+        let mut buf = Tbits::<TW>::zero(buf_size);
+        wrap::Context::<TW, F, TbitSliceMut<TW>>::new(buf.slice_mut()).merkle(External(&root), &proof)?;
+        ensure!(wrap::Context::<TW, F, TbitSliceMut<TW>>::new(buf.clone().slice_mut())
+            .merkle(External(&other_root), &proof)
+            .is_err());
+
+        let mut unwrapped = MerkleProof::default();
+        unwrap::Context::<TW, F, TbitSlice<TW>>::new(buf.slice()).merkle(External(&root), &mut unwrapped)?;
+        ensure!(unwrapped == proof);
+        ensure!(unwrap::Context::<TW, F, TbitSlice<TW>>::new(buf.slice())
+            .merkle(External(&other_root), &mut MerkleProof::default())
+            .is_err());
+    }
+
+    ensure!(MerkleProof::<TW>::gen::<F>(&leaves, 5).is_err());
+    Ok(())
+}
+
+#[test]
+fn merkle() {
+    assert!(dbg!(merkle_proofs::<Trit, Troika>()).is_ok());
+}
+
+fn strict_wrap_sizes<TW, F>() -> Fallible<()>
+where
+    TW: StringTbitWord + SpongosTbitWord + TritWord,
+    F: PRP<TW> + Default,
+{
+    let trytes = Trytes::<TW>(Tbits::from_str("STRICT").unwrap());
+    let mac = Mac(81);
+    let size = sizeof::Context::<TW, F>::new()
+        .absorb(&trytes)?
+        .squeeze(&mac)?
+        .get_size();
+
+    let mut buf = Tbits::<TW>::zero(size - 1);
+    ensure!(wrap::Context::<TW, F, TbitSliceMut<TW>>::strict(buf.slice_mut(), size).is_err());
+
+    let mut buf = Tbits::<TW>::zero(size + 3);
+    let mut ctx = wrap::Context::<TW, F, TbitSliceMut<TW>>::strict(buf.slice_mut(), size)?;
+    ctx.absorb(&trytes)?.squeeze(&mac)?.check_exhausted()?;
+    let err = ctx.absorb(Trint3(1)).err().map(|e| e.to_string()).unwrap_or_default();
+    ensure!(err.contains("absorb"), "Offending command is not named: {}", err);
+
+    let mut ctx = wrap::Context::<TW, F, TbitSliceMut<TW>>::strict(buf.slice_mut(), size + 3)?;
+    let err = ctx
+        .absorb(&trytes)?
+        .squeeze(&mac)?
+        .check_exhausted()
+        .err()
+        .map(|e| e.to_string())
+        .unwrap_or_default();
+    ensure!(err.contains("squeeze"), "Last command is not named: {}", err);
+    Ok(())
+}
+
+#[test]
+fn strict_wrap() {
+    assert!(dbg!(strict_wrap_sizes::<Trit, Troika>()).is_ok());
+}
+
 /*
 use crate::io;
 use iota_streams_core::sponge::spongos::{self, Spongos};
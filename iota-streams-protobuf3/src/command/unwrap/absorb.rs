@@ -14,6 +14,8 @@ use crate::{
     types::{
         AbsorbFallback,
         Fallback,
+        Float64,
+        Int64,
         NTrytes,
         Size,
         Trint3,
@@ -85,6 +87,26 @@ where
 {
     unwrap_size(ctx, size)
 }
+fn unwrap_absorb_int64<'a, TW, F, IS: io::IStream<TW>>(
+    ctx: &'a mut AbsorbContext<TW, F, IS>,
+    n: &mut Int64,
+) -> Fallible<&'a mut AbsorbContext<TW, F, IS>>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+{
+    unwrap_int64(ctx, n)
+}
+fn unwrap_absorb_float64<'a, TW, F, IS: io::IStream<TW>>(
+    ctx: &'a mut AbsorbContext<TW, F, IS>,
+    x: &mut Float64,
+) -> Fallible<&'a mut AbsorbContext<TW, F, IS>>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+{
+    unwrap_float64(ctx, x)
+}
 fn unwrap_absorb_trits<'a, TW, F, IS: io::IStream<TW>>(
     ctx: &'a mut AbsorbContext<TW, F, IS>,
     trits: TbitSliceMut<TW>,
@@ -116,6 +138,26 @@ where
     }
 }
 
+impl<'a, TW, F, IS: io::IStream<TW>> Absorb<&'a mut Int64> for Context<TW, F, IS>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+{
+    fn absorb(&mut self, n: &'a mut Int64) -> Fallible<&mut Self> {
+        Ok(unwrap_absorb_int64(self.as_mut(), n)?.as_mut())
+    }
+}
+
+impl<'a, TW, F, IS: io::IStream<TW>> Absorb<&'a mut Float64> for Context<TW, F, IS>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+{
+    fn absorb(&mut self, x: &'a mut Float64) -> Fallible<&mut Self> {
+        Ok(unwrap_absorb_float64(self.as_mut(), x)?.as_mut())
+    }
+}
+
 impl<'a, TW, F, IS: io::IStream<TW>> Absorb<&'a mut NTrytes<TW>> for Context<TW, F, IS>
 where
     TW: SpongosTbitWord + trinary::TritWord,
@@ -134,6 +176,7 @@ where
     fn absorb(&mut self, trytes: &'a mut Trytes<TW>) -> Fallible<&mut Self> {
         let mut size = Size(0);
         self.absorb(&mut size)?;
+        self.check_size(size)?;
         trytes.0 = Tbits::<TW>::zero(3 * size.0);
         Ok(unwrap_absorb_trits(self.as_mut(), (trytes.0).slice_mut())?.as_mut())
     }
@@ -21,6 +21,7 @@ use crate::{
         NTrytes,
         Size,
         Trint3,
+        Trytes,
     },
 };
 use iota_streams_core::{
@@ -156,6 +157,20 @@ where
     }
 }
 
+/// External `trytes` absorb their size and trits, nothing is encoded.
+impl<'a, TW, F, IS: io::IStream<TW>> Absorb<External<&'a Trytes<TW>>> for Context<TW, F, IS>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+{
+    fn absorb(&mut self, external_trytes: External<&'a Trytes<TW>>) -> Fallible<&mut Self> {
+        let trits = &(external_trytes.0).0;
+        ensure!(trits.size() % 3 == 0, "Trit size of `trytes` must be a multiple of 3.");
+        let ctx = wrap_absorb_external_size(self.as_mut(), Size(trits.size() / 3))?;
+        Ok(wrap_absorb_external_trits(ctx, trits.slice())?.as_mut())
+    }
+}
+
 impl<'a, TW, F, IS: io::IStream<TW>, P> Absorb<External<&'a mss::PublicKey<TW, P>>> for Context<TW, F, IS>
 where
     TW: SpongosTbitWord + trinary::TritWord,
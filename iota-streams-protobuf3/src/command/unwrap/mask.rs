@@ -12,6 +12,10 @@ use crate::{
     command::Mask,
     io,
     types::{
+        Fallback,
+        Float64,
+        Int64,
+        MaskFallback,
         NTrytes,
         Size,
         Trint3,
@@ -95,6 +99,26 @@ where
 {
     unwrap_size(ctx, size)
 }
+fn unwrap_mask_int64<'a, TW, F, IS: io::IStream<TW>>(
+    ctx: &'a mut MaskContext<TW, F, IS>,
+    n: &mut Int64,
+) -> Fallible<&'a mut MaskContext<TW, F, IS>>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+{
+    unwrap_int64(ctx, n)
+}
+fn unwrap_mask_float64<'a, TW, F, IS: io::IStream<TW>>(
+    ctx: &'a mut MaskContext<TW, F, IS>,
+    x: &mut Float64,
+) -> Fallible<&'a mut MaskContext<TW, F, IS>>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+{
+    unwrap_float64(ctx, x)
+}
 fn unwrap_mask_trits<'a, TW, F, IS: io::IStream<TW>>(
     ctx: &'a mut MaskContext<TW, F, IS>,
     trits: TbitSliceMut<TW>,
@@ -126,6 +150,26 @@ where
     }
 }
 
+impl<'a, TW, F, IS: io::IStream<TW>> Mask<&'a mut Int64> for Context<TW, F, IS>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+{
+    fn mask(&mut self, n: &'a mut Int64) -> Fallible<&mut Self> {
+        Ok(unwrap_mask_int64(self.as_mut(), n)?.as_mut())
+    }
+}
+
+impl<'a, TW, F, IS: io::IStream<TW>> Mask<&'a mut Float64> for Context<TW, F, IS>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+{
+    fn mask(&mut self, x: &'a mut Float64) -> Fallible<&mut Self> {
+        Ok(unwrap_mask_float64(self.as_mut(), x)?.as_mut())
+    }
+}
+
 impl<'a, TW, F, IS: io::IStream<TW>> Mask<&'a mut NTrytes<TW>> for Context<TW, F, IS>
 where
     TW: SpongosTbitWord + trinary::TritWord,
@@ -144,6 +188,7 @@ where
     fn mask(&mut self, trytes: &'a mut Trytes<TW>) -> Fallible<&mut Self> {
         let mut size = Size(0);
         self.mask(&mut size)?;
+        self.check_size(size)?;
         trytes.0 = Tbits::<TW>::zero(size.0 * 3);
         Ok(unwrap_mask_trits(self.as_mut(), (trytes.0).slice_mut())?.as_mut())
     }
@@ -173,3 +218,10 @@ where
         Ok(unwrap_mask_trits(self.as_mut(), mss_pk.tbits_mut().slice_mut())?.as_mut())
     }
 }
+
+impl<'a, TW, F, T: 'a + MaskFallback<TW, F>, IS: io::IStream<TW>> Mask<&'a mut Fallback<T>> for Context<TW, F, IS> {
+    fn mask(&mut self, val: &'a mut Fallback<T>) -> Fallible<&mut Self> {
+        (val.0).unwrap_mask(self)?;
+        Ok(self)
+    }
+}
@@ -0,0 +1,61 @@
+use failure::{
+    ensure,
+    Fallible,
+};
+
+use super::Context;
+use crate::{
+    command::{
+        Absorb,
+        Merkle,
+    },
+    io,
+    types::{
+        External,
+        MerkleProof,
+        NTrytes,
+        Size,
+        MERKLE_HASH_SIZE,
+        MERKLE_MAX_DEPTH,
+    },
+};
+use iota_streams_core::{
+    sponge::prp::PRP,
+    tbits::{
+        trinary,
+        word::SpongosTbitWord,
+        Tbits,
+    },
+};
+
+impl<'a, TW, F, IS: io::IStream<TW>> Merkle<External<&'a NTrytes<TW>>, &'a mut MerkleProof<TW>>
+    for Context<TW, F, IS>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW> + Default,
+{
+    fn merkle(&mut self, root: External<&'a NTrytes<TW>>, proof: &'a mut MerkleProof<TW>) -> Fallible<&mut Self> {
+        let mut index = Size(0);
+        let mut depth = Size(0);
+        proof.leaf = NTrytes(Tbits::zero(MERKLE_HASH_SIZE));
+        self.absorb(root)?
+            .absorb(&mut proof.leaf)?
+            .absorb(&mut index)?
+            .absorb(&mut depth)?;
+        ensure!(
+            depth.0 <= MERKLE_MAX_DEPTH,
+            "Merkle proof depth must not exceed {}.",
+            MERKLE_MAX_DEPTH
+        );
+        proof.index = index.0;
+        proof.path = Vec::with_capacity(depth.0);
+        for _ in 0..depth.0 {
+            let mut sibling = NTrytes(Tbits::zero(MERKLE_HASH_SIZE));
+            self.absorb(&mut sibling)?;
+            proof.path.push(sibling);
+        }
+        proof.check()?;
+        ensure!(proof.root::<F>() == *root.0, "Integrity is violated, bad Merkle proof.");
+        Ok(self)
+    }
+}
@@ -1,6 +1,10 @@
 //! Implementation of command traits for unwrapping.
 
 use failure::Fallible;
+use std::{
+    error,
+    fmt,
+};
 
 use crate::{
     io,
@@ -14,10 +18,30 @@ use iota_streams_core::{
     tbits::word::SpongosTbitWord,
 };
 
+/// Error of unwrapping a variable-length field larger than allowed by the context.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct SizeLimitExceeded {
+    /// Size of the field in trytes.
+    pub size: usize,
+    pub max_size: usize,
+}
+
+impl fmt::Display for SizeLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Field size {} exceeds the limit of {} trytes.", self.size, self.max_size)
+    }
+}
+
+impl error::Error for SizeLimitExceeded {}
+
 //#[derive(Debug)]
 pub struct Context<TW, F, IS> {
     pub spongos: Spongos<TW, F>,
     pub stream: IS,
+    /// Maximum size of variable-length fields (eg. `Trytes`) in trytes. Field buffers are
+    /// allocated before the field is read, the limit guards against oversized length prefixes
+    /// of untrusted messages. Unlimited if `None`.
+    pub max_size: Option<usize>,
 }
 
 impl<TW, F, IS> Context<TW, F, IS>
@@ -29,8 +53,14 @@ where
         Self {
             spongos: Spongos::<TW, F>::init(),
             stream: stream,
+            max_size: None,
         }
     }
+
+    pub fn with_max_size(mut self, max_size: Option<usize>) -> Self {
+        self.max_size = max_size;
+        self
+    }
 }
 
 impl<TW, F, IS: io::IStream<TW>> Context<TW, F, IS> {
@@ -41,6 +71,21 @@ impl<TW, F, IS: io::IStream<TW>> Context<TW, F, IS> {
     }
 }
 
+impl<TW, F, IS> Context<TW, F, IS> {
+    /// Check size of a variable-length field read from the stream against `max_size`,
+    /// it fails with `SizeLimitExceeded` error.
+    pub fn check_size(&self, size: Size) -> Fallible<()> {
+        match self.max_size {
+            Some(max_size) if max_size < size.0 => Err(SizeLimitExceeded {
+                size: size.0,
+                max_size,
+            }
+            .into()),
+            _ => Ok(()),
+        }
+    }
+}
+
 impl<TW, F, IS> Clone for Context<TW, F, IS>
 where
     TW: Clone,
@@ -51,6 +96,7 @@ where
         Self {
             spongos: self.spongos.clone(),
             stream: self.stream.clone(),
+            max_size: self.max_size,
         }
     }
 }
@@ -70,6 +116,7 @@ mod skip;
 mod squeeze;
 mod squeeze_external;
 
+mod merkle;
 mod mssig;
 mod ntrukem;
 
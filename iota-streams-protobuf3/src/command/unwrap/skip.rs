@@ -10,6 +10,8 @@ use crate::{
     io,
     types::{
         Fallback,
+        Float64,
+        Int64,
         NTrytes,
         Size,
         SkipFallback,
@@ -72,6 +74,24 @@ where
 {
     unwrap_size(ctx, size)
 }
+fn unwrap_skip_int64<'a, TW, F, IS: io::IStream<TW>>(
+    ctx: &'a mut SkipContext<TW, F, IS>,
+    n: &mut Int64,
+) -> Fallible<&'a mut SkipContext<TW, F, IS>>
+where
+    TW: BasicTbitWord + trinary::TritWord,
+{
+    unwrap_int64(ctx, n)
+}
+fn unwrap_skip_float64<'a, TW, F, IS: io::IStream<TW>>(
+    ctx: &'a mut SkipContext<TW, F, IS>,
+    x: &mut Float64,
+) -> Fallible<&'a mut SkipContext<TW, F, IS>>
+where
+    TW: BasicTbitWord + trinary::TritWord,
+{
+    unwrap_float64(ctx, x)
+}
 fn unwrap_skip_trits<'a, TW, F, IS: io::IStream<TW>>(
     ctx: &'a mut SkipContext<TW, F, IS>,
     trits: TbitSliceMut<TW>,
@@ -100,6 +120,24 @@ where
     }
 }
 
+impl<'a, TW, F, IS: io::IStream<TW>> Skip<&'a mut Int64> for Context<TW, F, IS>
+where
+    TW: BasicTbitWord + trinary::TritWord,
+{
+    fn skip(&mut self, n: &'a mut Int64) -> Fallible<&mut Self> {
+        Ok(unwrap_skip_int64(self.as_mut(), n)?.as_mut())
+    }
+}
+
+impl<'a, TW, F, IS: io::IStream<TW>> Skip<&'a mut Float64> for Context<TW, F, IS>
+where
+    TW: BasicTbitWord + trinary::TritWord,
+{
+    fn skip(&mut self, x: &'a mut Float64) -> Fallible<&mut Self> {
+        Ok(unwrap_skip_float64(self.as_mut(), x)?.as_mut())
+    }
+}
+
 impl<'a, TW, F, IS: io::IStream<TW>> Skip<&'a mut NTrytes<TW>> for Context<TW, F, IS>
 where
     TW: BasicTbitWord + trinary::TritWord,
@@ -116,6 +154,7 @@ where
     fn skip(&mut self, trytes: &'a mut Trytes<TW>) -> Fallible<&mut Self> {
         let mut size = Size(0);
         self.skip(&mut size)?;
+        self.check_size(size)?;
         trytes.0 = Tbits::<TW>::zero(size.0 * 3);
         Ok(unwrap_skip_trits(self.as_mut(), (trytes.0).slice_mut())?.as_mut())
     }
@@ -4,8 +4,11 @@ use failure::{
 };
 
 use crate::types::{
+    Float64,
+    Int64,
     Size,
     Trint3,
+    INT64_TRYTES,
     SIZE_MAX,
 };
 use iota_streams_core::tbits::TbitSliceMut;
@@ -50,3 +53,28 @@ pub(crate) fn unwrap_size<'a, TW, Ctx: Unwrap<TW>>(ctx: &'a mut Ctx, size: &mut
     size.0 = m as usize;
     Ok(ctx)
 }
+
+/// Helper function for unwrapping `int64` values, the most significant tryte first.
+pub(crate) fn unwrap_int64<'a, TW, Ctx: Unwrap<TW>>(ctx: &'a mut Ctx, n: &mut Int64) -> Fallible<&'a mut Ctx> {
+    let mut m: i128 = 0;
+    let mut t = Trint3(0);
+    for _ in 0..INT64_TRYTES {
+        ctx.unwrap3(&mut t)?;
+        m = m * 27 + t.0 as i128;
+    }
+    ensure!(
+        i64::MIN as i128 <= m && m <= i64::MAX as i128,
+        "`int64` value is overflown: {}.",
+        m
+    );
+    n.0 = m as i64;
+    Ok(ctx)
+}
+
+/// Helper function for unwrapping `float64` values.
+pub(crate) fn unwrap_float64<'a, TW, Ctx: Unwrap<TW>>(ctx: &'a mut Ctx, x: &mut Float64) -> Fallible<&'a mut Ctx> {
+    let mut n = Int64(0);
+    unwrap_int64(ctx, &mut n)?;
+    *x = Float64::from_int64(n)?;
+    Ok(ctx)
+}
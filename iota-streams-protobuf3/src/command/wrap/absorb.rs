@@ -14,6 +14,8 @@ use crate::{
     types::{
         AbsorbFallback,
         Fallback,
+        Float64,
+        Int64,
         NTrytes,
         Size,
         Trint3,
@@ -51,12 +53,14 @@ where
     F: PRP<TW>,
 {
     fn wrap3(&mut self, trint3: Trint3) -> Fallible<&mut Self> {
+        self.ctx.reserve("absorb", 3)?;
         let mut slice = self.ctx.stream.try_advance(3)?;
         slice.put3(trint3);
         self.ctx.spongos.absorb(unsafe { slice.as_const() });
         Ok(self)
     }
     fn wrapn(&mut self, trits: TbitSlice<TW>) -> Fallible<&mut Self> {
+        self.ctx.reserve("absorb", trits.size())?;
         self.ctx.spongos.absorb(trits);
         let slice = self.ctx.stream.try_advance(trits.size())?;
         trits.copy(&slice);
@@ -84,6 +88,16 @@ where
 {
     wrap_size(ctx, size)
 }
+fn wrap_absorb_int64<'a, TW, F, OS: io::OStream<TW>>(
+    ctx: &'a mut AbsorbContext<TW, F, OS>,
+    n: Int64,
+) -> Fallible<&'a mut AbsorbContext<TW, F, OS>>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+{
+    wrap_int64(ctx, n)
+}
 fn wrap_absorb_trits<'a, TW, F, OS: io::OStream<TW>>(
     ctx: &'a mut AbsorbContext<TW, F, OS>,
     trits: TbitSlice<TW>,
@@ -135,6 +149,46 @@ where
     }
 }
 
+impl<'a, TW, F, OS: io::OStream<TW>> Absorb<&'a Int64> for Context<TW, F, OS>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+{
+    fn absorb(&mut self, n: &'a Int64) -> Fallible<&mut Self> {
+        Ok(wrap_absorb_int64(self.as_mut(), *n)?.as_mut())
+    }
+}
+
+impl<TW, F, OS: io::OStream<TW>> Absorb<Int64> for Context<TW, F, OS>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+{
+    fn absorb(&mut self, n: Int64) -> Fallible<&mut Self> {
+        self.absorb(&n)
+    }
+}
+
+impl<'a, TW, F, OS: io::OStream<TW>> Absorb<&'a Float64> for Context<TW, F, OS>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+{
+    fn absorb(&mut self, x: &'a Float64) -> Fallible<&mut Self> {
+        Ok(wrap_absorb_int64(self.as_mut(), x.to_int64())?.as_mut())
+    }
+}
+
+impl<TW, F, OS: io::OStream<TW>> Absorb<Float64> for Context<TW, F, OS>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+{
+    fn absorb(&mut self, x: Float64) -> Fallible<&mut Self> {
+        self.absorb(&x)
+    }
+}
+
 impl<'a, TW, F, OS: io::OStream<TW>> Absorb<&'a NTrytes<TW>> for Context<TW, F, OS>
 where
     TW: SpongosTbitWord + trinary::TritWord,
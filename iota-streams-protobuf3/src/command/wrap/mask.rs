@@ -12,6 +12,10 @@ use crate::{
     command::Mask,
     io,
     types::{
+        Fallback,
+        Float64,
+        Int64,
+        MaskFallback,
         NTrytes,
         Size,
         Trint3,
@@ -49,12 +53,14 @@ where
     F: PRP<TW>,
 {
     fn wrap3(&mut self, trint3: Trint3) -> Fallible<&mut Self> {
+        self.ctx.reserve("mask", 3)?;
         let mut slice = self.ctx.stream.try_advance(3)?;
         slice.put3(trint3);
         self.ctx.spongos.encrypt_mut(&mut slice);
         Ok(self)
     }
     fn wrapn(&mut self, trits: TbitSlice<TW>) -> Fallible<&mut Self> {
+        self.ctx.reserve("mask", trits.size())?;
         let mut slice = self.ctx.stream.try_advance(trits.size())?;
         self.ctx.spongos.encrypt(trits, &mut slice);
         Ok(self)
@@ -81,6 +87,16 @@ where
 {
     wrap_size(ctx, size)
 }
+fn wrap_mask_int64<'a, TW, F, OS: io::OStream<TW>>(
+    ctx: &'a mut MaskContext<TW, F, OS>,
+    n: Int64,
+) -> Fallible<&'a mut MaskContext<TW, F, OS>>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+{
+    wrap_int64(ctx, n)
+}
 fn wrap_mask_trits<'a, TW, F, OS: io::OStream<TW>>(
     ctx: &'a mut MaskContext<TW, F, OS>,
     trits: TbitSlice<TW>,
@@ -112,6 +128,46 @@ where
     }
 }
 
+impl<'a, TW, F, OS: io::OStream<TW>> Mask<&'a Int64> for Context<TW, F, OS>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+{
+    fn mask(&mut self, n: &'a Int64) -> Fallible<&mut Self> {
+        Ok(wrap_mask_int64(self.as_mut(), *n)?.as_mut())
+    }
+}
+
+impl<TW, F, OS: io::OStream<TW>> Mask<Int64> for Context<TW, F, OS>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+{
+    fn mask(&mut self, n: Int64) -> Fallible<&mut Self> {
+        self.mask(&n)
+    }
+}
+
+impl<'a, TW, F, OS: io::OStream<TW>> Mask<&'a Float64> for Context<TW, F, OS>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+{
+    fn mask(&mut self, x: &'a Float64) -> Fallible<&mut Self> {
+        Ok(wrap_mask_int64(self.as_mut(), x.to_int64())?.as_mut())
+    }
+}
+
+impl<TW, F, OS: io::OStream<TW>> Mask<Float64> for Context<TW, F, OS>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW>,
+{
+    fn mask(&mut self, x: Float64) -> Fallible<&mut Self> {
+        self.mask(&x)
+    }
+}
+
 impl<'a, TW, F, OS: io::OStream<TW>> Mask<&'a NTrytes<TW>> for Context<TW, F, OS>
 where
     TW: SpongosTbitWord + trinary::TritWord,
@@ -159,3 +215,10 @@ where
         Ok(wrap_mask_trits(self.as_mut(), mss_pk.tbits().slice())?.as_mut())
     }
 }
+
+impl<'a, TW, F, T: 'a + MaskFallback<TW, F>, OS: io::OStream<TW>> Mask<&'a Fallback<T>> for Context<TW, F, OS> {
+    fn mask(&mut self, val: &'a Fallback<T>) -> Fallible<&mut Self> {
+        (val.0).wrap_mask(self)?;
+        Ok(self)
+    }
+}
@@ -0,0 +1,45 @@
+use failure::{
+    ensure,
+    Fallible,
+};
+
+use super::Context;
+use crate::{
+    command::{
+        Absorb,
+        Merkle,
+    },
+    io,
+    types::{
+        External,
+        MerkleProof,
+        NTrytes,
+        Size,
+    },
+};
+use iota_streams_core::{
+    sponge::prp::PRP,
+    tbits::{
+        trinary,
+        word::SpongosTbitWord,
+    },
+};
+
+impl<'a, TW, F, OS: io::OStream<TW>> Merkle<External<&'a NTrytes<TW>>, &'a MerkleProof<TW>> for Context<TW, F, OS>
+where
+    TW: SpongosTbitWord + trinary::TritWord,
+    F: PRP<TW> + Default,
+{
+    fn merkle(&mut self, root: External<&'a NTrytes<TW>>, proof: &'a MerkleProof<TW>) -> Fallible<&mut Self> {
+        proof.check()?;
+        ensure!(proof.root::<F>() == *root.0, "Merkle proof doesn't match the root.");
+        self.absorb(root)?
+            .absorb(&proof.leaf)?
+            .absorb(Size(proof.index))?
+            .absorb(Size(proof.path.len()))?;
+        for sibling in &proof.path {
+            self.absorb(sibling)?;
+        }
+        Ok(self)
+    }
+}
@@ -1,5 +1,11 @@
 //! Implementation of command traits for wrapping.
 
+use failure::{
+    ensure,
+    Fallible,
+};
+
+use crate::io;
 use iota_streams_core::{
     sponge::{
         prp::PRP,
@@ -12,6 +18,10 @@ use iota_streams_core::{
 pub struct Context<TW, F, OS> {
     pub spongos: Spongos<TW, F>,
     pub stream: OS,
+    /// Trits left to wrap as computed by `sizeof`, strict contexts only.
+    budget: Option<usize>,
+    /// The last command which wrote to the stream.
+    last: &'static str,
 }
 
 impl<TW, F, OS> Context<TW, F, OS>
@@ -23,7 +33,71 @@ where
         Self {
             spongos: Spongos::<TW, F>::init(),
             stream: stream,
+            budget: None,
+            last: "",
+        }
+    }
+
+    /// Create a context wrapping exactly `size` trits, usually computed with `sizeof::Context`.
+    /// It fails upfront if `stream` is too short, so that nothing is wrapped and spongos
+    /// is not touched. A command writing more than `size` trits fails before it touches
+    /// spongos, use `check_exhausted` after the last command.
+    pub fn strict(stream: OS, size: usize) -> Fallible<Self>
+    where
+        OS: io::OStream<TW>,
+    {
+        if let Some(remaining) = stream.remaining() {
+            ensure!(
+                size <= remaining,
+                "Output stream too short: {} trits required, {} available.",
+                size,
+                remaining
+            );
+        }
+        let mut ctx = Self::new(stream);
+        ctx.budget = Some(size);
+        Ok(ctx)
+    }
+}
+
+impl<TW, F, OS: io::OStream<TW>> Context<TW, F, OS> {
+    /// Check that `cmd` can write `n` trits, it must be called before the command touches
+    /// spongos or stream.
+    pub(crate) fn reserve(&mut self, cmd: &'static str, n: usize) -> Fallible<()> {
+        if let Some(budget) = self.budget {
+            ensure!(
+                n <= budget,
+                "Wrap {}: {} trits exceed the size computed by sizeof, {} trits left.",
+                cmd,
+                n,
+                budget
+            );
+            self.budget = Some(budget - n);
+        }
+        if let Some(remaining) = self.stream.remaining() {
+            ensure!(
+                n <= remaining,
+                "Wrap {}: output stream too short, {} trits required, {} available.",
+                cmd,
+                n,
+                remaining
+            );
+        }
+        self.last = cmd;
+        Ok(())
+    }
+
+    /// Check that a strict context has wrapped exactly the size computed by `sizeof`.
+    pub fn check_exhausted(&self) -> Fallible<()> {
+        if let Some(budget) = self.budget {
+            ensure!(
+                budget == 0,
+                "Wrap: {} trits computed by sizeof left after the last command `{}`.",
+                budget,
+                self.last
+            );
         }
+        Ok(())
     }
 }
 
@@ -43,6 +117,7 @@ mod skip;
 mod squeeze;
 mod squeeze_external;
 
+mod merkle;
 mod mssig;
 mod ntrukem;
 
@@ -47,6 +47,7 @@ where
             sk.private_keys_left() > 0,
             "All WOTS private keys in MSS Merkle tree have been exhausted, nothing to sign hash with."
         );
+        self.reserve("mssig", P::signature_size(sk.height()))?;
         let sig_slice = self.stream.try_advance(P::signature_size(sk.height()))?;
         sk.sign(((hash.0).0).slice(), sig_slice);
         Ok(self)
@@ -31,6 +31,7 @@ where
     ) -> Fallible<&mut Self> {
         //TODO: ensure!(ntru::KEY_SIZE == (secret.0).size(), "Trit size of `external tryte secret[n]` to be encapsulated with NTRU must be equal {} trits.", ntru::KEY_SIZE);
 
+        self.reserve("ntrukem", ntru::EKEY_SIZE)?;
         let ekey_slice = self.stream.try_advance(ntru::EKEY_SIZE)?;
         (key.0).encrypt_with_spongos(
             &mut self.spongos,
@@ -10,6 +10,8 @@ use crate::{
     io,
     types::{
         Fallback,
+        Float64,
+        Int64,
         NTrytes,
         Size,
         SkipFallback,
@@ -42,11 +44,13 @@ where
     TW: BasicTbitWord + trinary::TritWord,
 {
     fn wrap3(&mut self, trint3: Trint3) -> Fallible<&mut Self> {
+        self.ctx.reserve("skip", 3)?;
         let mut slice = self.ctx.stream.try_advance(3)?;
         slice.put3(trint3);
         Ok(self)
     }
     fn wrapn(&mut self, trits: TbitSlice<TW>) -> Fallible<&mut Self> {
+        self.ctx.reserve("skip", trits.size())?;
         let slice = self.ctx.stream.try_advance(trits.size())?;
         trits.copy(&slice);
         Ok(self)
@@ -71,6 +75,15 @@ where
 {
     wrap_size(ctx, size)
 }
+fn wrap_skip_int64<'a, TW, F, OS: io::OStream<TW>>(
+    ctx: &'a mut SkipContext<TW, F, OS>,
+    n: Int64,
+) -> Fallible<&'a mut SkipContext<TW, F, OS>>
+where
+    TW: BasicTbitWord + trinary::TritWord,
+{
+    wrap_int64(ctx, n)
+}
 fn wrap_skip_trits<'a, TW, F, OS: io::OStream<TW>>(
     ctx: &'a mut SkipContext<TW, F, OS>,
     trits: TbitSlice<TW>,
@@ -117,6 +130,42 @@ where
     }
 }
 
+impl<'a, TW, F, OS: io::OStream<TW>> Skip<&'a Int64> for Context<TW, F, OS>
+where
+    TW: BasicTbitWord + trinary::TritWord,
+{
+    fn skip(&mut self, n: &'a Int64) -> Fallible<&mut Self> {
+        Ok(wrap_skip_int64(self.as_mut(), *n)?.as_mut())
+    }
+}
+
+impl<TW, F, OS: io::OStream<TW>> Skip<Int64> for Context<TW, F, OS>
+where
+    TW: BasicTbitWord + trinary::TritWord,
+{
+    fn skip(&mut self, n: Int64) -> Fallible<&mut Self> {
+        self.skip(&n)
+    }
+}
+
+impl<'a, TW, F, OS: io::OStream<TW>> Skip<&'a Float64> for Context<TW, F, OS>
+where
+    TW: BasicTbitWord + trinary::TritWord,
+{
+    fn skip(&mut self, x: &'a Float64) -> Fallible<&mut Self> {
+        Ok(wrap_skip_int64(self.as_mut(), x.to_int64())?.as_mut())
+    }
+}
+
+impl<TW, F, OS: io::OStream<TW>> Skip<Float64> for Context<TW, F, OS>
+where
+    TW: BasicTbitWord + trinary::TritWord,
+{
+    fn skip(&mut self, x: Float64) -> Fallible<&mut Self> {
+        self.skip(&x)
+    }
+}
+
 impl<'a, TW, F, OS: io::OStream<TW>> Skip<&'a NTrytes<TW>> for Context<TW, F, OS>
 where
     TW: BasicTbitWord + trinary::TritWord,
@@ -21,6 +21,7 @@ where
     F: PRP<TW>,
 {
     fn squeeze(&mut self, mac: &'a Mac) -> Fallible<&mut Self> {
+        self.reserve("squeeze", mac.0)?;
         self.spongos.squeeze(&mut self.stream.try_advance(mac.0)?);
         Ok(self)
     }
@@ -2,8 +2,10 @@ use failure::Fallible;
 
 use crate::types::{
     size_trytes,
+    Int64,
     Size,
     Trint3,
+    INT64_TRYTES,
 };
 use iota_streams_core::tbits::{
     trinary,
@@ -30,3 +32,22 @@ pub(crate) fn wrap_size<'a, TW, Ctx: Wrap<TW>>(ctx: &'a mut Ctx, size: Size) ->
     }
     Ok(ctx)
 }
+
+/// Helper function for wrapping `int64` values, the most significant tryte first.
+pub(crate) fn wrap_int64<'a, TW, Ctx: Wrap<TW>>(ctx: &'a mut Ctx, n: Int64) -> Fallible<&'a mut Ctx> {
+    let mut ts = [Trint3(0); INT64_TRYTES];
+    // Wide enough not to overflow on `i64::MIN`.
+    let mut n = n.0 as i128;
+    for t in ts.iter_mut().rev() {
+        let mut r = n.rem_euclid(27);
+        if 13 < r {
+            r -= 27;
+        }
+        *t = Trint3(r as i8);
+        n = (n - r) / 27;
+    }
+    for t in ts.iter() {
+        ctx.wrap3(*t)?;
+    }
+    Ok(ctx)
+}
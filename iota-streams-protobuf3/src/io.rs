@@ -26,6 +26,11 @@ pub trait OStream<TW> {
     /// Commit advanced buffers to the internal sink.
     fn commit(&mut self);
 
+    /// Number of tbits the stream can still take, `None` if it's not known in advance.
+    fn remaining(&self) -> Option<usize> {
+        None
+    }
+
     /// Dump stream debug info.
     fn dump(&self) -> String {
         String::new()
@@ -62,6 +67,9 @@ where
         Ok(self.advance(n))
     }
     fn commit(&mut self) {}
+    fn remaining(&self) -> Option<usize> {
+        Some(self.size())
+    }
     fn dump(&self) -> String {
         format!("{:?}", self)
     }
@@ -92,6 +100,9 @@ impl<TW> OStream<TW> for NoOStream {
         bail!("Advance can't be implemented for NoOStream")
     }
     fn commit(&mut self) {}
+    fn remaining(&self) -> Option<usize> {
+        Some(0)
+    }
 }
 
 pub struct NoIStream;
@@ -1,5 +1,6 @@
 use failure::{
     bail,
+    ensure,
     Fallible,
 };
 use std::{
@@ -12,13 +13,18 @@ use std::{
 };
 
 use iota_streams_core::{
+    heap::HeapSize,
     sponge::{
         prp::PRP,
-        spongos::Spongos,
+        spongos::{
+            rehash_tbits,
+            Spongos,
+        },
     },
     tbits::{
         word::{
             BasicTbitWord,
+            IntTbitWord,
             SpongosTbitWord,
             StringTbitWord,
         },
@@ -79,6 +85,17 @@ where
     pub fn zero(n: usize) -> Self {
         Self(Tbits::<TW>::zero(n))
     }
+
+    /// Compare in constant time, see `Tbits::ct_eq`.
+    pub fn ct_eq(&self, other: &Self) -> bool {
+        self.0.ct_eq(&other.0)
+    }
+}
+
+impl<TW> HeapSize for NTrytes<TW> {
+    fn heap_size(&self) -> usize {
+        self.0.heap_size()
+    }
 }
 
 /*
@@ -127,6 +144,12 @@ where
 }
 impl<TW> Eq for Trytes<TW> where TW: BasicTbitWord {}
 
+impl<TW> HeapSize for Trytes<TW> {
+    fn heap_size(&self) -> usize {
+        self.0.heap_size()
+    }
+}
+
 /*
 impl<TW> ToString for Trytes<TW>
 where
@@ -228,11 +251,162 @@ impl fmt::Display for Size {
     }
 }
 
+/// PB3 `int64` type, signed, encoded with `INT64_TRYTES` balanced trytes, the most significant first.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone, Debug, Default)]
+pub struct Int64(pub i64);
+
+/// PB3 `float64` type, IEEE 754 double encoded as `int64` value of its bits.
+/// All NaNs are encoded as the canonical quiet NaN, other NaN bits are rejected on unwrap.
+#[derive(PartialEq, PartialOrd, Copy, Clone, Debug, Default)]
+pub struct Float64(pub f64);
+
+/// Number of trytes encoding `int64` and `float64` values.
+pub const INT64_TRYTES: usize = 14;
+
+/// Bits of the canonical quiet NaN.
+const CANONICAL_NAN: u64 = 0x7ff8_0000_0000_0000;
+
+impl Float64 {
+    pub(crate) fn to_int64(self) -> Int64 {
+        if self.0.is_nan() {
+            Int64(CANONICAL_NAN as i64)
+        } else {
+            Int64(self.0.to_bits() as i64)
+        }
+    }
+
+    pub(crate) fn from_int64(n: Int64) -> Fallible<Self> {
+        let x = f64::from_bits(n.0 as u64);
+        ensure!(!x.is_nan() || n.0 as u64 == CANONICAL_NAN, "Non-canonical NaN: {:x}.", n.0);
+        Ok(Self(x))
+    }
+}
+
 /// PB3 `external` modifier, it changes behaviour of commands in the following way.
 /// The external field is not encoded in trinary representation and the value is stored in the environment implicitly.
 #[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
 pub struct External<T>(pub T);
 
+/// Size of Merkle tree hashes in trits.
+pub const MERKLE_HASH_SIZE: usize = 243;
+
+/// Max depth of Merkle proofs, leaf index must fit `usize`.
+pub const MERKLE_MAX_DEPTH: usize = 8 * std::mem::size_of::<usize>();
+
+/// Merkle inclusion proof of `leaf` with index `index`, `path` contains sibling hashes
+/// from the leaf up to the root. Inner nodes are hashes of their children, a node
+/// without a sibling is paired with itself.
+#[derive(Clone)]
+pub struct MerkleProof<TW> {
+    pub leaf: NTrytes<TW>,
+    pub index: usize,
+    pub path: Vec<NTrytes<TW>>,
+}
+
+impl<TW> PartialEq for MerkleProof<TW>
+where
+    TW: BasicTbitWord,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.leaf == other.leaf && self.index == other.index && self.path == other.path
+    }
+}
+
+impl<TW> Eq for MerkleProof<TW> where TW: BasicTbitWord {}
+
+impl<TW> Default for MerkleProof<TW>
+where
+    TW: BasicTbitWord,
+{
+    fn default() -> Self {
+        Self {
+            leaf: NTrytes(Tbits::zero(MERKLE_HASH_SIZE)),
+            index: 0,
+            path: Vec::new(),
+        }
+    }
+}
+
+fn merkle_node<TW, F>(left: &NTrytes<TW>, right: &NTrytes<TW>) -> NTrytes<TW>
+where
+    TW: SpongosTbitWord,
+    F: PRP<TW> + Default,
+{
+    let mut s = Spongos::<TW, F>::init();
+    s.absorb(left.0.slice());
+    s.absorb(right.0.slice());
+    s.commit();
+    NTrytes(s.squeeze_tbits(MERKLE_HASH_SIZE))
+}
+
+impl<TW> MerkleProof<TW>
+where
+    TW: SpongosTbitWord,
+{
+    /// Proof of leaf `index` in the tree of `leaves`.
+    pub fn gen<F>(leaves: &[NTrytes<TW>], index: usize) -> Fallible<Self>
+    where
+        F: PRP<TW> + Default,
+    {
+        ensure!(index < leaves.len(), "Merkle leaf index {} is out of range.", index);
+        let mut path = Vec::new();
+        let mut level = leaves.to_vec();
+        let mut i = index;
+        while level.len() > 1 {
+            path.push(level.get(i ^ 1).unwrap_or(&level[i]).clone());
+            level = level
+                .chunks(2)
+                .map(|pair| merkle_node::<TW, F>(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+                .collect();
+            i /= 2;
+        }
+        let proof = Self {
+            leaf: leaves[index].clone(),
+            index,
+            path,
+        };
+        proof.check()?;
+        Ok(proof)
+    }
+
+    /// Check sizes of hashes and that the index fits the depth of the proof.
+    pub fn check(&self) -> Fallible<()> {
+        ensure!(
+            self.path.len() <= MERKLE_MAX_DEPTH,
+            "Merkle proof depth must not exceed {}.",
+            MERKLE_MAX_DEPTH
+        );
+        ensure!(
+            self.path.len() == MERKLE_MAX_DEPTH || self.index >> self.path.len() == 0,
+            "Merkle leaf index {} doesn't fit proof depth {}.",
+            self.index,
+            self.path.len()
+        );
+        ensure!(
+            self.leaf.0.size() == MERKLE_HASH_SIZE && self.path.iter().all(|h| h.0.size() == MERKLE_HASH_SIZE),
+            "Trit size of Merkle hashes must be equal {} trits.",
+            MERKLE_HASH_SIZE
+        );
+        Ok(())
+    }
+
+    /// Root of the tree computed from the proof.
+    pub fn root<F>(&self) -> NTrytes<TW>
+    where
+        F: PRP<TW> + Default,
+    {
+        let mut node = self.leaf.clone();
+        for (level, sibling) in self.path.iter().enumerate() {
+            node = if (self.index >> level) & 1 == 0 {
+                merkle_node::<TW, F>(&node, sibling)
+            } else {
+                merkle_node::<TW, F>(sibling, &node)
+            };
+        }
+        node
+    }
+}
+
 /// The `link` type is generic and transport-specific. Links can be address+tag pair
 /// when messages are published in the Tangle. Or links can be a URL when HTTP is used.
 /// Or links can be a message sequence number in a stream/socket.
@@ -260,6 +434,45 @@ pub trait LinkStore<TW, F, Link> {
 
     /// Remove link and associated info from the store.
     fn erase(&mut self, _link: &Link) {}
+
+    /// Start a new epoch, eg. when session key is changed with a keyload, and forget links
+    /// put into the store in epochs older than the last `keep` ones (at least the new epoch
+    /// is kept). Links put before the first epoch started, eg. announcement, are never forgotten.
+    ///
+    /// Stores not tracking epochs keep all links.
+    fn start_epoch(&mut self, _keep: usize) {}
+
+    /// All links in the store together with spongos states and associated info, eg. to copy
+    /// the store into another one backed by external storage.
+    ///
+    /// Stores unable to enumerate links return nothing.
+    fn entries(&self) -> Fallible<Vec<(Link, Spongos<TW, F>, Self::Info)>> {
+        Ok(Vec::new())
+    }
+
+    /// Tag a link in the store with application `label`, eg. "latest-config". A label refers
+    /// to a single link, tagging another link moves it. Labels are dropped with their links.
+    ///
+    /// Stores not supporting labels fail.
+    fn tag_link(&mut self, _link: &Link, _label: &str) -> Fallible<()> {
+        bail!("Link store doesn't support labels.");
+    }
+
+    /// Link tagged with `label`.
+    fn find_by_label(&self, _label: &str) -> Option<Link> {
+        None
+    }
+
+    /// All labels with their links, eg. to copy them together with `entries`.
+    fn labels(&self) -> Vec<(String, Link)> {
+        Vec::new()
+    }
+}
+
+/// Whether a link put into the store in epoch `link_epoch` is still kept in epoch `epoch`
+/// when only the last `keep` epochs are kept.
+fn is_epoch_kept(link_epoch: usize, epoch: usize, keep: usize) -> bool {
+    link_epoch == 0 || link_epoch + keep.max(1) > epoch
 }
 
 /// Empty "dummy" link store that stores no links.
@@ -297,6 +510,18 @@ where
     info: Info,
 }
 
+impl<TW, F, Link, Info> HeapSize for SingleLinkStore<TW, F, Link, Info>
+where
+    F: PRP<TW>,
+    F::Inner: HeapSize,
+    Link: HeapSize,
+    Info: HeapSize,
+{
+    fn heap_size(&self) -> usize {
+        self.link.heap_size() + self.spongos.heap_size() + self.info.heap_size()
+    }
+}
+
 impl<TW, F, Link, Info> LinkStore<TW, F, Link> for SingleLinkStore<TW, F, Link, Info>
 where
     TW: BasicTbitWord + SpongosTbitWord,
@@ -323,15 +548,24 @@ where
     fn erase(&mut self, _link: &Link) {
         // Can't really erase link.
     }
+    fn entries(&self) -> Fallible<Vec<(Link, Spongos<TW, F>, Self::Info)>> {
+        let spongos = Spongos::<TW, F>::from_inner(self.spongos.clone());
+        Ok(vec![(self.link.clone(), spongos, self.info.clone())])
+    }
 }
 
-use std::collections::HashMap;
+use iota_streams_core::prelude::{
+    hash_map,
+    HashMap,
+};
 
 pub struct DefaultLinkStore<TW, F, Link, Info>
 where
     F: PRP<TW>,
 {
-    map: HashMap<Link, (F::Inner, Info)>,
+    map: HashMap<Link, (F::Inner, Info, usize)>,
+    labels: HashMap<String, Link>,
+    epoch: usize,
 }
 
 impl<TW, F, Link, Info> Default for DefaultLinkStore<TW, F, Link, Info>
@@ -340,7 +574,33 @@ where
     Link: Eq + hash::Hash,
 {
     fn default() -> Self {
-        Self { map: HashMap::new() }
+        Self {
+            map: HashMap::new(),
+            labels: HashMap::new(),
+            epoch: 0,
+        }
+    }
+}
+
+impl<TW, F, Link, Info> DefaultLinkStore<TW, F, Link, Info>
+where
+    F: PRP<TW>,
+{
+    /// Iterate over all links in the store.
+    pub fn links(&self) -> hash_map::Keys<Link, (F::Inner, Info, usize)> {
+        self.map.keys()
+    }
+}
+
+impl<TW, F, Link, Info> HeapSize for DefaultLinkStore<TW, F, Link, Info>
+where
+    F: PRP<TW>,
+    F::Inner: HeapSize,
+    Link: HeapSize,
+    Info: HeapSize,
+{
+    fn heap_size(&self) -> usize {
+        self.map.heap_size() + self.labels.heap_size()
     }
 }
 
@@ -356,7 +616,7 @@ where
 
     /// Add info for the link.
     fn lookup(&self, link: &Link) -> Fallible<(Spongos<TW, F>, Info)> {
-        if let Some((inner, info)) = self.map.get(link).cloned() {
+        if let Some((inner, info, _)) = self.map.get(link).cloned() {
             Ok((Spongos::from_inner(inner), info))
         } else {
             bail!("Link not found")
@@ -366,13 +626,396 @@ where
     /// Try to retrieve info for the link.
     fn update(&mut self, link: &Link, spongos: Spongos<TW, F>, info: Info) -> Fallible<()> {
         let inner = spongos.to_inner();
-        self.map.insert(link.clone(), (inner, info));
+        self.map.insert(link.clone(), (inner, info, self.epoch));
         Ok(())
     }
 
-    /// Remove info for the link.
+    /// Remove info and labels of the link.
     fn erase(&mut self, link: &Link) {
         self.map.remove(link);
+        self.labels.retain(|_, l| l != link);
+    }
+
+    /// Forget links from old epochs.
+    fn start_epoch(&mut self, keep: usize) {
+        self.epoch += 1;
+        let epoch = self.epoch;
+        self.map.retain(|_, (_, _, e)| is_epoch_kept(*e, epoch, keep));
+        let map = &self.map;
+        self.labels.retain(|_, link| map.contains_key(link));
+    }
+
+    fn entries(&self) -> Fallible<Vec<(Link, Spongos<TW, F>, Info)>> {
+        Ok(self
+            .map
+            .iter()
+            .map(|(link, (inner, info, _))| (link.clone(), Spongos::from_inner(inner.clone()), info.clone()))
+            .collect())
+    }
+
+    fn tag_link(&mut self, link: &Link, label: &str) -> Fallible<()> {
+        ensure!(self.map.contains_key(link), "Link not found.");
+        self.labels.insert(label.to_string(), link.clone());
+        Ok(())
+    }
+
+    fn find_by_label(&self, label: &str) -> Option<Link> {
+        self.labels.get(label).cloned()
+    }
+
+    fn labels(&self) -> Vec<(String, Link)> {
+        self.labels
+            .iter()
+            .map(|(label, link)| (label.clone(), link.clone()))
+            .collect()
+    }
+}
+
+/// Link store keeping only the latest spongos state per associated info, eg. per message
+/// type or per publisher.
+///
+/// Memory usage is bounded by the number of distinct info values regardless of the number
+/// of messages handled. Updating a link evicts the previous link with the same info,
+/// messages linked to evicted links can't be unwrapped, ie. random access is not supported.
+pub struct LatestLinkStore<TW, F, Link, Info>
+where
+    F: PRP<TW>,
+{
+    map: HashMap<Info, (Link, F::Inner)>,
+}
+
+impl<TW, F, Link, Info> Default for LatestLinkStore<TW, F, Link, Info>
+where
+    F: PRP<TW>,
+    Info: Eq + hash::Hash,
+{
+    fn default() -> Self {
+        Self { map: HashMap::new() }
+    }
+}
+
+impl<TW, F, Link, Info> LatestLinkStore<TW, F, Link, Info>
+where
+    F: PRP<TW>,
+{
+    /// Iterate over all links in the store.
+    pub fn links(&self) -> impl Iterator<Item = &Link> {
+        self.map.values().map(|(link, _)| link)
+    }
+}
+
+impl<TW, F, Link, Info> HeapSize for LatestLinkStore<TW, F, Link, Info>
+where
+    F: PRP<TW>,
+    F::Inner: HeapSize,
+    Link: HeapSize,
+    Info: HeapSize,
+{
+    fn heap_size(&self) -> usize {
+        self.map.heap_size()
+    }
+}
+
+impl<TW, F, Link, Info> LinkStore<TW, F, Link> for LatestLinkStore<TW, F, Link, Info>
+where
+    TW: SpongosTbitWord,
+    F: PRP<TW> + Clone,
+    F::Inner: Clone,
+    Link: Eq + Clone,
+    Info: Eq + hash::Hash + Clone,
+{
+    type Info = Info;
+
+    /// Lookup link among the latest ones.
+    fn lookup(&self, link: &Link) -> Fallible<(Spongos<TW, F>, Info)> {
+        for (info, (l, inner)) in self.map.iter() {
+            if l == link {
+                return Ok((Spongos::from_inner(inner.clone()), info.clone()));
+            }
+        }
+        bail!("Link not found, only the latest links are kept.")
+    }
+
+    /// Replace the link with the same info.
+    fn update(&mut self, link: &Link, spongos: Spongos<TW, F>, info: Info) -> Fallible<()> {
+        let inner = spongos.to_inner();
+        self.map.insert(info, (link.clone(), inner));
+        Ok(())
+    }
+
+    /// Remove the link if it's among the latest ones.
+    fn erase(&mut self, link: &Link) {
+        self.map.retain(|_, (l, _)| l != link);
+    }
+
+    fn entries(&self) -> Fallible<Vec<(Link, Spongos<TW, F>, Info)>> {
+        Ok(self
+            .map
+            .iter()
+            .map(|(info, (link, inner))| (link.clone(), Spongos::from_inner(inner.clone()), info.clone()))
+            .collect())
+    }
+}
+
+/// Value bound to sealed spongos states of `EncryptedLinkStore`, links and info are
+/// authenticated together with the state.
+pub trait SealBinding<TW> {
+    /// Tbits absorbed into the MAC, distinct values must have distinct tbits.
+    fn binding(&self) -> Tbits<TW>;
+}
+
+impl<TW> SealBinding<TW> for usize
+where
+    TW: IntTbitWord,
+{
+    fn binding(&self) -> Tbits<TW> {
+        // 81 tbits fit any usize both in trits and bits.
+        let mut t = Tbits::zero(81);
+        t.slice_mut().put_usize(*self);
+        t
+    }
+}
+
+/// Spongos state sealed by `EncryptedLinkStore`.
+#[derive(Clone)]
+pub struct SealedState<TW, Info> {
+    pub nonce: Tbits<TW>,
+    pub sealed: Tbits<TW>,
+    pub mac: Tbits<TW>,
+    pub info: Info,
+    pub epoch: usize,
+}
+
+impl<TW, Info> HeapSize for SealedState<TW, Info>
+where
+    Info: HeapSize,
+{
+    fn heap_size(&self) -> usize {
+        self.nonce.heap_size() + self.sealed.heap_size() + self.mac.heap_size() + self.info.heap_size()
+    }
+}
+
+/// Link store keeping spongos states sealed with a secret key.
+///
+/// Spongos states of messages linked to keyloads contain session key material. Each state
+/// is encrypted with spongos keyed with `key` and a fresh nonce and is authenticated together
+/// with its link and info with a MAC on lookup. Links and associated info are kept in plaintext.
+///
+/// Sealed states can be saved with `sealed_states` and loaded back with `insert_sealed` into
+/// a store with the same key and a nonce not used with the key before, eg. the saved `nonce`.
+pub struct EncryptedLinkStore<TW, F, Link, Info> {
+    key: Tbits<TW>,
+    nonce: Tbits<TW>,
+    map: HashMap<Link, SealedState<TW, Info>>,
+    labels: HashMap<String, Link>,
+    epoch: usize,
+    _phantom: std::marker::PhantomData<F>,
+}
+
+impl<TW, F, Link, Info> EncryptedLinkStore<TW, F, Link, Info>
+where
+    TW: SpongosTbitWord,
+    F: PRP<TW> + Clone + Default,
+    Link: Eq + hash::Hash,
+{
+    /// Create an empty store sealing spongos states with `key` of `Spongos::KEY_SIZE` tbits.
+    /// Nonces are derived from the initial `nonce` which must be unique for the `key`.
+    pub fn new(key: Tbits<TW>, nonce: Tbits<TW>) -> Fallible<Self> {
+        ensure!(
+            key.size() == Spongos::<TW, F>::KEY_SIZE,
+            "Bad link store key size: {}.",
+            key.size()
+        );
+        ensure!(
+            nonce.size() == Spongos::<TW, F>::NONCE_SIZE,
+            "Bad link store nonce size: {}.",
+            nonce.size()
+        );
+        Ok(Self {
+            key: key,
+            nonce: nonce,
+            map: HashMap::new(),
+            labels: HashMap::new(),
+            epoch: 0,
+            _phantom: std::marker::PhantomData,
+        })
+    }
+
+    /// Iterate over all links in the store.
+    pub fn links(&self) -> hash_map::Keys<Link, SealedState<TW, Info>> {
+        self.map.keys()
+    }
+
+    /// The last used nonce.
+    pub fn nonce(&self) -> &Tbits<TW> {
+        &self.nonce
+    }
+
+    /// Forget all links, the key and the nonce are kept.
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.labels.clear();
+        self.epoch = 0;
+    }
+
+    /// Iterate over sealed states of all links.
+    pub fn sealed_states(&self) -> impl Iterator<Item = (&Link, &SealedState<TW, Info>)> {
+        self.map.iter()
+    }
+
+    fn spongos(&self, nonce: &Tbits<TW>) -> Spongos<TW, F> {
+        let mut s = Spongos::<TW, F>::init();
+        s.absorb_tbits(&self.key);
+        s.absorb_tbits(nonce);
+        s.commit();
+        s
+    }
+}
+
+impl<TW, F, Link, Info> EncryptedLinkStore<TW, F, Link, Info>
+where
+    TW: SpongosTbitWord,
+    F: PRP<TW> + Clone + Default,
+    F::Inner: Default + AsRef<Tbits<TW>> + AsMut<Tbits<TW>>,
+    Link: Eq + hash::Hash + Clone + SealBinding<TW>,
+    Info: Clone + SealBinding<TW>,
+{
+    /// Seal spongos state for the link with a fresh nonce, the store is not updated.
+    pub fn seal(&mut self, link: &Link, spongos: Spongos<TW, F>, info: Info) -> SealedState<TW, Info> {
+        rehash_tbits::<TW, F>(&mut self.nonce);
+        let mut s = self.spongos(&self.nonce);
+        let sealed = s.encrypt_tbits(spongos.to_inner().as_ref());
+        s.absorb_tbits(&link.binding());
+        s.absorb_tbits(&info.binding());
+        s.commit();
+        let mac = s.squeeze_tbits(Spongos::<TW, F>::MAC_SIZE);
+        SealedState {
+            nonce: self.nonce.clone(),
+            sealed,
+            mac,
+            info,
+            epoch: self.epoch,
+        }
+    }
+
+    /// Insert a sealed state saved from a store with the same key, it is rejected if it
+    /// doesn't unseal.
+    pub fn insert_sealed(&mut self, link: Link, state: SealedState<TW, Info>) -> Fallible<()> {
+        self.unseal(&link, &state)?;
+        self.epoch = std::cmp::max(self.epoch, state.epoch);
+        self.map.insert(link, state);
+        Ok(())
+    }
+
+    fn unseal(&self, link: &Link, state: &SealedState<TW, Info>) -> Fallible<Spongos<TW, F>> {
+        let mut s = self.spongos(&state.nonce);
+        let mut inner = F::Inner::default();
+        *inner.as_mut() = s.decrypt_tbits(&state.sealed);
+        s.absorb_tbits(&link.binding());
+        s.absorb_tbits(&state.info.binding());
+        s.commit();
+        ensure!(s.squeeze_eq_tbits(&state.mac), "Link store integrity violated.");
+        Ok(Spongos::from_inner(inner))
+    }
+}
+
+impl<TW, F, Link, Info> Clone for EncryptedLinkStore<TW, F, Link, Info>
+where
+    TW: Clone,
+    Link: Clone,
+    Info: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            key: self.key.clone(),
+            nonce: self.nonce.clone(),
+            map: self.map.clone(),
+            labels: self.labels.clone(),
+            epoch: self.epoch,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<TW, F, Link, Info> HeapSize for EncryptedLinkStore<TW, F, Link, Info>
+where
+    Link: HeapSize,
+    Info: HeapSize,
+{
+    fn heap_size(&self) -> usize {
+        self.key.heap_size() + self.nonce.heap_size() + self.map.heap_size() + self.labels.heap_size()
+    }
+}
+
+impl<TW, F, Link, Info> LinkStore<TW, F, Link> for EncryptedLinkStore<TW, F, Link, Info>
+where
+    TW: SpongosTbitWord,
+    F: PRP<TW> + Clone + Default,
+    F::Inner: Default + AsRef<Tbits<TW>> + AsMut<Tbits<TW>>,
+    Link: Eq + hash::Hash + Clone + SealBinding<TW>,
+    Info: Clone + SealBinding<TW>,
+{
+    type Info = Info;
+
+    /// Unseal spongos state for the link.
+    fn lookup(&self, link: &Link) -> Fallible<(Spongos<TW, F>, Info)> {
+        if let Some(state) = self.map.get(link) {
+            let spongos = self.unseal(link, state)?;
+            Ok((spongos, state.info.clone()))
+        } else {
+            bail!("Link not found")
+        }
+    }
+
+    /// Seal spongos state for the link with a fresh nonce.
+    fn update(&mut self, link: &Link, spongos: Spongos<TW, F>, info: Info) -> Fallible<()> {
+        let state = self.seal(link, spongos, info);
+        self.map.insert(link.clone(), state);
+        Ok(())
+    }
+
+    /// Remove info and labels of the link.
+    fn erase(&mut self, link: &Link) {
+        self.map.remove(link);
+        self.labels.retain(|_, l| l != link);
+    }
+
+    /// Forget links from old epochs.
+    fn start_epoch(&mut self, keep: usize) {
+        self.epoch += 1;
+        let epoch = self.epoch;
+        self.map.retain(|_, state| is_epoch_kept(state.epoch, epoch, keep));
+        let map = &self.map;
+        self.labels.retain(|_, link| map.contains_key(link));
+    }
+
+    /// Unseal spongos states of all links.
+    fn entries(&self) -> Fallible<Vec<(Link, Spongos<TW, F>, Info)>> {
+        self.map
+            .keys()
+            .map(|link| {
+                let (spongos, info) = self.lookup(link)?;
+                Ok((link.clone(), spongos, info))
+            })
+            .collect()
+    }
+
+    /// Labels are kept in plaintext like links.
+    fn tag_link(&mut self, link: &Link, label: &str) -> Fallible<()> {
+        ensure!(self.map.contains_key(link), "Link not found.");
+        self.labels.insert(label.to_string(), link.clone());
+        Ok(())
+    }
+
+    fn find_by_label(&self, label: &str) -> Option<Link> {
+        self.labels.get(label).cloned()
+    }
+
+    fn labels(&self) -> Vec<(String, Link)> {
+        self.labels
+            .iter()
+            .map(|(label, link)| (label.clone(), link.clone()))
+            .collect()
     }
 }
 
@@ -440,6 +1083,15 @@ pub trait AbsorbExternalFallback<TW, F> {
     fn unwrap_absorb_external<IS: io::IStream<TW>>(&self, ctx: &mut unwrap::Context<TW, F, IS>) -> Fallible<()>;
 }
 
+/// Trait allows for custom (non-standard Protobuf3) types to be Mask.
+/// It is usually implemented for link types that are confidential fields of message
+/// content, ie. message doesn't reveal which message it refers to.
+pub trait MaskFallback<TW, F> {
+    fn sizeof_mask(&self, ctx: &mut sizeof::Context<TW, F>) -> Fallible<()>;
+    fn wrap_mask<OS: io::OStream<TW>>(&self, ctx: &mut wrap::Context<TW, F, OS>) -> Fallible<()>;
+    fn unwrap_mask<IS: io::IStream<TW>>(&mut self, ctx: &mut unwrap::Context<TW, F, IS>) -> Fallible<()>;
+}
+
 /// Trait allows for custom (non-standard Protobuf3) types to be Absorb.
 /// It is usually implemented for "relative" link types that are not specified
 /// in Protobuf3 and domain specific.
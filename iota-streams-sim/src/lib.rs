@@ -0,0 +1,30 @@
+//! Deterministic simulation of multi-party Channels scenarios.
+//!
+//! A `Script` lists steps of an Author and Subscribers: announcement, subscriptions,
+//! keyloads, packets, syncs and Subscriber restarts. Steps are run over `SimNetwork`
+//! delaying and dropping messages in virtual time. Delays, drops and party seeds are drawn
+//! from `SimRng` seeded with the script seed, so a run is reproducible and a failing
+//! scenario can be replayed and minimised step by step.
+//!
+//! The `Outcome` of a run holds sent and received payloads and unwrap errors of each
+//! Subscriber, checks on the final state are done on it.
+
+/// Seeded pseudo-random generator.
+pub mod rng;
+
+/// Simulated network with virtual time.
+pub mod network;
+
+/// Scenario scripts and their outcome.
+pub mod script;
+
+pub use network::SimNetwork;
+pub use rng::SimRng;
+pub use script::{
+    Outcome,
+    Script,
+    Step,
+};
+
+#[cfg(test)]
+mod test;
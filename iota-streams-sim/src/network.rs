@@ -0,0 +1,109 @@
+//! Sent messages are delivered after a random delay of `0..=max_delay` ticks or dropped with
+//! probability `drop_rate / 1000`. Only delivered messages can be received. Time advances
+//! with `tick` only, so delivery doesn't depend on the wall clock.
+
+use failure::Fallible;
+
+use iota_streams_app::transport;
+use iota_streams_app_channels::api::tangle::{
+    Address,
+    BucketTransport,
+    DefaultF,
+    DefaultTW,
+    Message,
+};
+
+use super::SimRng;
+
+/// Network delaying and dropping messages in virtual time.
+pub struct SimNetwork {
+    rng: SimRng,
+    now: u64,
+    max_delay: u64,
+    drop_rate: u32,
+    drop_next: usize,
+    /// Messages in flight with their delivery time, in order of sending.
+    in_flight: Vec<(u64, Message)>,
+    delivered: BucketTransport,
+    dropped: Vec<Address>,
+}
+
+impl SimNetwork {
+    pub fn new(rng: SimRng, max_delay: u64, drop_rate: u32) -> Self {
+        Self {
+            rng,
+            now: 0,
+            max_delay,
+            drop_rate,
+            drop_next: 0,
+            in_flight: Vec::new(),
+            delivered: BucketTransport::new(),
+            dropped: Vec::new(),
+        }
+    }
+
+    /// Virtual time in ticks.
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    /// Advance time by `ticks` and deliver messages due.
+    pub fn tick(&mut self, ticks: u64) {
+        self.now += ticks;
+        let now = self.now;
+        let (due, in_flight): (Vec<_>, Vec<_>) = self.in_flight.drain(..).partition(|(at, _)| *at <= now);
+        self.in_flight = in_flight;
+        for (at, msg) in due {
+            self.delivered.send_message_at(&msg, at as i64);
+        }
+    }
+
+    /// Drop the next `n` sent messages regardless of the drop rate.
+    pub fn drop_next(&mut self, n: usize) {
+        self.drop_next += n;
+    }
+
+    /// Links of dropped messages.
+    pub fn dropped(&self) -> &[Address] {
+        &self.dropped
+    }
+
+    /// Number of messages sent, but not delivered yet.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+}
+
+impl transport::Transport<DefaultTW, DefaultF, Address> for SimNetwork {
+    type SendOptions = ();
+
+    fn send_message_with_options(&mut self, msg: &Message, _opt: ()) -> Fallible<()> {
+        // The RNG is advanced the same way for every message, so that forced drops
+        // don't shift later decisions.
+        let dropped = self.rng.chance(self.drop_rate);
+        let delay = self.rng.below(self.max_delay + 1);
+        if self.drop_next > 0 || dropped {
+            self.drop_next = self.drop_next.saturating_sub(1);
+            self.dropped.push(msg.link.clone());
+        } else if delay == 0 {
+            self.delivered.send_message_at(msg, self.now as i64);
+        } else {
+            self.in_flight.push((self.now + delay, msg.clone()));
+        }
+        Ok(())
+    }
+
+    type RecvOptions = ();
+
+    fn recv_messages_with_options(&mut self, link: &Address, _opt: ()) -> Fallible<Vec<Message>> {
+        self.delivered.recv_messages_with_options(link, ())
+    }
+
+    /// Messages in flight are queued.
+    fn health(&mut self) -> Fallible<transport::NodeHealth> {
+        Ok(transport::NodeHealth {
+            queue_depth: self.in_flight.len(),
+            ..transport::NodeHealth::default()
+        })
+    }
+}
@@ -0,0 +1,388 @@
+//! Scripts are run step by step. Failures of Subscribers, eg. a message that can't be
+//! unwrapped, are recorded in the outcome, so that the final state can be checked. Failures
+//! of the Author and steps that don't apply to the script, eg. a Subscriber index out of range,
+//! stop the run.
+
+use failure::{
+    bail,
+    ensure,
+    format_err,
+    Fallible,
+};
+use std::str::FromStr;
+
+use iota_streams_app::transport::Transport as _;
+use iota_streams_app_channels::api::tangle::{
+    Address,
+    Author,
+    AuthorBuilder,
+    DefaultTW,
+    Received,
+    Subscriber,
+    SubscriberBuilder,
+    DEFAULT_MSS_HEIGHT,
+};
+use iota_streams_core::tbits::Tbits;
+use iota_streams_protobuf3::types::Trytes;
+
+use super::{
+    SimNetwork,
+    SimRng,
+};
+
+/// Step of a scenario.
+#[derive(Clone, Debug)]
+pub enum Step {
+    /// Author announces the channel.
+    Announce,
+    /// Subscriber `i` handles the announcement and subscribes.
+    Subscribe(usize),
+    /// Author handles delivered subscriptions and shares a keyload for everyone.
+    Keyload,
+    /// Author signs a packet with masked payload linked to the last keyload.
+    SignedPacket(String),
+    /// Subscriber `i` tags a packet with masked payload linked to the last keyload it handled.
+    TaggedPacket(usize, String),
+    /// Subscriber `i` fetches and handles new messages.
+    Sync(usize),
+    /// All Subscribers sync.
+    SyncAll,
+    /// Subscriber `i` loses its state and is recreated from its seed.
+    Restart(usize),
+    /// The next `n` sent messages are dropped.
+    Drop(usize),
+    /// Advance virtual time by the number of ticks.
+    Tick(u64),
+}
+
+/// Scenario of an Author and Subscribers.
+#[derive(Clone, Debug)]
+pub struct Script {
+    seed: u64,
+    subscribers: usize,
+    mss_height: usize,
+    max_delay: u64,
+    drop_rate: u32,
+    max_gap: usize,
+    steps: Vec<Step>,
+}
+
+impl Script {
+    /// Script with one Subscriber, instant delivery and no drops.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            subscribers: 1,
+            mss_height: DEFAULT_MSS_HEIGHT,
+            max_delay: 0,
+            drop_rate: 0,
+            max_gap: 5,
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn subscribers(mut self, n: usize) -> Self {
+        self.subscribers = n;
+        self
+    }
+
+    /// Height of Author's MSS tree, it limits the number of signed messages.
+    pub fn mss_height(mut self, mss_height: usize) -> Self {
+        self.mss_height = mss_height;
+        self
+    }
+
+    /// Messages are delivered after `0..=max_delay` ticks.
+    pub fn max_delay(mut self, max_delay: u64) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Messages are dropped with probability `permille / 1000`.
+    pub fn drop_rate(mut self, permille: u32) -> Self {
+        self.drop_rate = permille;
+        self
+    }
+
+    /// `max_gap` passed to `Subscriber::messages` on sync.
+    pub fn max_gap(mut self, max_gap: usize) -> Self {
+        self.max_gap = max_gap;
+        self
+    }
+
+    pub fn step(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    pub fn steps(mut self, steps: Vec<Step>) -> Self {
+        self.steps.extend(steps);
+        self
+    }
+
+    /// Run the script, the same script always has the same outcome.
+    pub fn run(&self) -> Fallible<Outcome> {
+        let mut sim = Simulation::new(self);
+        for (i, step) in self.steps.iter().enumerate() {
+            sim.run_step(step).map_err(|err| format_err!("Step {} {:?} failed: {}", i, step, err))?;
+        }
+        Ok(Outcome {
+            sent: sim.sent,
+            received: sim.subscribers.iter().map(|s| s.received.clone()).collect(),
+            errors: sim.subscribers.iter().map(|s| s.errors.clone()).collect(),
+            dropped: sim.network.dropped().len(),
+            in_flight: sim.network.in_flight(),
+            now: sim.network.now(),
+        })
+    }
+}
+
+/// Final state of a scenario.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Outcome {
+    /// Masked payloads of sent packets in order of sending with their sender,
+    /// `None` for the Author.
+    pub sent: Vec<(Option<usize>, String)>,
+    /// Masked payloads received by each Subscriber in order of handling.
+    pub received: Vec<Vec<String>>,
+    /// Errors of each Subscriber, eg. messages that couldn't be unwrapped.
+    pub errors: Vec<Vec<String>>,
+    /// Number of dropped messages.
+    pub dropped: usize,
+    /// Number of messages not delivered by the end of the run.
+    pub in_flight: usize,
+    /// Virtual time at the end of the run.
+    pub now: u64,
+}
+
+impl Outcome {
+    /// Payloads of packets sent by others than Subscriber `i` in order of sending.
+    pub fn expected(&self, i: usize) -> Vec<String> {
+        self.sent
+            .iter()
+            .filter(|(sender, _)| *sender != Some(i))
+            .map(|(_, payload)| payload.clone())
+            .collect()
+    }
+
+    /// Every Subscriber received packets of others exactly once, in any order.
+    pub fn ensure_all_received(&self) -> Fallible<()> {
+        for (i, received) in self.received.iter().enumerate() {
+            let mut expected = self.expected(i);
+            let mut received = received.clone();
+            expected.sort();
+            received.sort();
+            ensure!(
+                received == expected,
+                "Subscriber {} received {:?}, expected {:?}.",
+                i,
+                received,
+                expected
+            );
+        }
+        Ok(())
+    }
+
+    /// No Subscriber failed.
+    pub fn ensure_no_errors(&self) -> Fallible<()> {
+        for (i, errors) in self.errors.iter().enumerate() {
+            ensure!(errors.is_empty(), "Subscriber {} failed: {:?}.", i, errors);
+        }
+        Ok(())
+    }
+}
+
+struct SimSubscriber {
+    seed: String,
+    subscriber: Subscriber,
+    /// The last keyload handled, new packets are linked to it.
+    keyload: Option<Address>,
+    received: Vec<String>,
+    errors: Vec<String>,
+}
+
+impl SimSubscriber {
+    fn new(seed: String) -> Self {
+        Self {
+            subscriber: SubscriberBuilder::new(&seed).build(),
+            seed,
+            keyload: None,
+            received: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+}
+
+struct Simulation {
+    network: SimNetwork,
+    author: Author,
+    announcement: Option<Address>,
+    keyload: Option<Address>,
+    /// Links of subscribe messages not handled by the Author yet with the number of messages
+    /// sent, subscribe messages of different Subscribers may share a link.
+    subscriptions: Vec<(Address, usize)>,
+    subscribers: Vec<SimSubscriber>,
+    sent: Vec<(Option<usize>, String)>,
+    max_gap: usize,
+}
+
+impl Simulation {
+    fn new(script: &Script) -> Self {
+        let mut rng = SimRng::new(script.seed);
+        let author = AuthorBuilder::new(&rng.trytes(27))
+            .mss_height(script.mss_height)
+            .build();
+        let subscribers = (0..script.subscribers)
+            .map(|_| SimSubscriber::new(rng.trytes(27)))
+            .collect();
+        Self {
+            network: SimNetwork::new(SimRng::new(rng.next_u64()), script.max_delay, script.drop_rate),
+            author,
+            announcement: None,
+            keyload: None,
+            subscriptions: Vec::new(),
+            subscribers,
+            sent: Vec::new(),
+            max_gap: script.max_gap,
+        }
+    }
+
+    fn run_step(&mut self, step: &Step) -> Fallible<()> {
+        match step {
+            Step::Announce => {
+                let msg = self.author.announce()?;
+                self.network.send_message(&msg)?;
+                self.announcement = Some(msg.link);
+            }
+            Step::Subscribe(i) => {
+                self.check_index(*i)?;
+                if let Err(err) = self.subscribe(*i) {
+                    self.subscribers[*i].errors.push(err.to_string());
+                }
+            }
+            Step::Keyload => {
+                let announcement = self.announcement()?;
+                let network = &mut self.network;
+                let author = &mut self.author;
+                // Subscriptions not delivered yet are handled by a later keyload.
+                self.subscriptions.retain(|(link, sent)| {
+                    let msgs = network.recv_messages(link).unwrap_or_default();
+                    for msg in &msgs {
+                        let _ = msg.parse_header().and_then(|preparsed| author.unwrap_subscribe(preparsed));
+                    }
+                    msgs.len() < *sent
+                });
+                let msg = self.author.share_keyload_for_everyone(&announcement)?;
+                self.network.send_message(&msg)?;
+                self.keyload = Some(msg.link);
+            }
+            Step::SignedPacket(payload) => {
+                let link_to = match &self.keyload {
+                    Some(keyload) => keyload.clone(),
+                    None => self.announcement()?,
+                };
+                let msg = self.author.sign_packet(&link_to, &Trytes::default(), &trytes(payload)?)?;
+                self.network.send_message(&msg)?;
+                self.sent.push((None, payload.clone()));
+            }
+            Step::TaggedPacket(i, payload) => {
+                self.check_index(*i)?;
+                match self.tag_packet(*i, payload) {
+                    Ok(()) => self.sent.push((Some(*i), payload.clone())),
+                    Err(err) => self.subscribers[*i].errors.push(err.to_string()),
+                }
+            }
+            Step::Sync(i) => {
+                self.check_index(*i)?;
+                self.sync(*i);
+            }
+            Step::SyncAll => {
+                for i in 0..self.subscribers.len() {
+                    self.sync(i);
+                }
+            }
+            Step::Restart(i) => {
+                self.check_index(*i)?;
+                let sub = &mut self.subscribers[*i];
+                sub.subscriber = SubscriberBuilder::new(&sub.seed).build();
+                sub.keyload = None;
+            }
+            Step::Drop(n) => {
+                self.network.drop_next(*n);
+            }
+            Step::Tick(ticks) => {
+                self.network.tick(*ticks);
+            }
+        }
+        Ok(())
+    }
+
+    fn check_index(&self, i: usize) -> Fallible<()> {
+        ensure!(i < self.subscribers.len(), "No Subscriber {}.", i);
+        Ok(())
+    }
+
+    fn announcement(&self) -> Fallible<Address> {
+        match &self.announcement {
+            Some(link) => Ok(link.clone()),
+            None => bail!("Channel is not announced."),
+        }
+    }
+
+    /// Handle the announcement unless it has been handled already.
+    fn register(&mut self, i: usize) -> Fallible<()> {
+        if !self.subscribers[i].subscriber.is_registered() {
+            let announcement = self.announcement()?;
+            let msg = self.network.recv_message(&announcement)?;
+            self.subscribers[i].subscriber.unwrap_announcement(msg.parse_header()?)?;
+        }
+        Ok(())
+    }
+
+    fn subscribe(&mut self, i: usize) -> Fallible<()> {
+        self.register(i)?;
+        let announcement = self.announcement()?;
+        let msg = self.subscribers[i].subscriber.subscribe(&announcement)?;
+        self.network.send_message(&msg)?;
+        match self.subscriptions.iter_mut().find(|(link, _)| *link == msg.link) {
+            Some((_, sent)) => *sent += 1,
+            None => self.subscriptions.push((msg.link, 1)),
+        }
+        Ok(())
+    }
+
+    fn tag_packet(&mut self, i: usize, payload: &str) -> Fallible<()> {
+        let sub = &mut self.subscribers[i];
+        let keyload = match &sub.keyload {
+            Some(keyload) => keyload.clone(),
+            None => bail!("No keyload handled."),
+        };
+        let msg = sub.subscriber.tag_packet(&keyload, &Trytes::default(), &trytes(payload)?)?;
+        self.network.send_message(&msg)?;
+        Ok(())
+    }
+
+    fn sync(&mut self, i: usize) {
+        if let Err(err) = self.register(i) {
+            self.subscribers[i].errors.push(err.to_string());
+            return;
+        }
+        let sub = &mut self.subscribers[i];
+        for msg in sub.subscriber.messages(&mut self.network, self.max_gap) {
+            match msg {
+                Ok((link, Received::Keyload)) => sub.keyload = Some(link),
+                Ok((_, Received::SignedPacket(_, masked))) | Ok((_, Received::TaggedPacket(_, masked))) => {
+                    sub.received.push((masked.0).to_string())
+                }
+                Ok(_) => {}
+                Err(err) => sub.errors.push(err.to_string()),
+            }
+        }
+    }
+}
+
+fn trytes(payload: &str) -> Fallible<Trytes<DefaultTW>> {
+    Ok(Trytes(
+        Tbits::from_str(payload).map_err(|_| format_err!("Bad payload trytes: {}.", payload))?,
+    ))
+}
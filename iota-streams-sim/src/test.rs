@@ -0,0 +1,96 @@
+use failure::{
+    ensure,
+    Fallible,
+};
+
+use super::*;
+
+fn payload(s: &str) -> Step {
+    Step::SignedPacket(s.to_string())
+}
+
+fn reliable_network() -> Fallible<()> {
+    let script = Script::new(1).subscribers(2).steps(vec![
+        Step::Announce,
+        Step::Subscribe(0),
+        Step::Subscribe(1),
+        Step::Keyload,
+        payload("FIRST"),
+        Step::Sync(0),
+        payload("SECOND"),
+        Step::SyncAll,
+    ]);
+    let outcome = script.run()?;
+    outcome.ensure_no_errors()?;
+    outcome.ensure_all_received()?;
+    ensure!(outcome.received[1] == vec!["FIRST", "SECOND"]);
+    ensure!(script.run()? == outcome, "Run is not deterministic.");
+    Ok(())
+}
+
+#[test]
+fn run_reliable_network() {
+    assert!(dbg!(reliable_network()).is_ok());
+}
+
+fn delays_and_drops() -> Fallible<()> {
+    let outcome = Script::new(2)
+        .subscribers(2)
+        .max_delay(3)
+        .steps(vec![
+            Step::Announce,
+            Step::Tick(3),
+            Step::Subscribe(0),
+            Step::Drop(1),
+            Step::Subscribe(1),
+            Step::Tick(3),
+            Step::Keyload,
+            payload("SECRET"),
+            Step::Drop(1),
+            payload("LOST"),
+            payload("LATER"),
+            Step::Tick(3),
+            Step::SyncAll,
+        ])
+        .run()?;
+    ensure!(outcome.dropped == 2);
+    ensure!(outcome.in_flight == 0);
+    ensure!(outcome.now == 9);
+    // Lost packet is skipped.
+    ensure!(outcome.received[0] == vec!["SECRET", "LATER"]);
+    ensure!(outcome.errors[0].is_empty());
+    // Subscription of Subscriber 1 is lost, it's not a keyload recipient.
+    ensure!(outcome.received[1].is_empty());
+    ensure!(!outcome.errors[1].is_empty());
+    Ok(())
+}
+
+#[test]
+fn run_delays_and_drops() {
+    assert!(dbg!(delays_and_drops()).is_ok());
+}
+
+fn restart() -> Fallible<()> {
+    let outcome = Script::new(3)
+        .subscribers(1)
+        .steps(vec![
+            Step::Announce,
+            Step::Subscribe(0),
+            Step::Keyload,
+            payload("BEFORE"),
+            Step::Sync(0),
+            Step::Restart(0),
+            payload("AFTER"),
+            Step::Sync(0),
+        ])
+        .run()?;
+    outcome.ensure_no_errors()?;
+    // Restarted Subscriber handles the channel from the announcement again.
+    ensure!(outcome.received[0] == vec!["BEFORE", "BEFORE", "AFTER"]);
+    Ok(())
+}
+
+#[test]
+fn run_restart() {
+    assert!(dbg!(restart()).is_ok());
+}
@@ -1,3 +1,16 @@
+//! IOTA Streams: all crates in one namespace.
+//!
+//! Most applications need the Channels over the Tangle only, see `channels` and `prelude`:
+//!
+//! ```ignore
+//! use iota_streams::prelude::*;
+//!
+//! let mut author = AuthorBuilder::new("AUTHOR9SEED").build();
+//! let announcement = author.announce()?;
+//! ```
+//!
+//! Features `kv-sled` and `kv-redis` are forwarded to `iota-streams-app`.
+
 /// Streams Application layer definitions.
 pub use iota_streams_app as app;
 /// Streams Channel Application implementation.
@@ -14,3 +27,59 @@ pub use iota_streams_core_mss as core_mss;
 pub use iota_streams_core_ntru as core_ntru;
 /// Protobuf3 EDSL for Streams Messages.
 pub use iota_streams_protobuf3 as protobuf3;
+
+/// Channel Application over the Tangle with default parameters.
+pub use iota_streams_app_channels::api::tangle as channels;
+/// Message transports.
+pub use iota_streams_app::transport;
+
+/// Types and traits used by most applications.
+pub mod prelude {
+    pub use failure::Fallible;
+    pub use iota_streams_app::transport::Transport as _;
+    pub use iota_streams_app_channels::api::tangle::{
+        Address,
+        Author,
+        AuthorBuilder,
+        BucketTransport,
+        ChannelAddress,
+        Message,
+        MsgInfo,
+        NtruPkid,
+        NtruPkids,
+        Preparsed,
+        Psk,
+        PskId,
+        PskIds,
+        Received,
+        Subscriber,
+        SubscriberBuilder,
+        Transport,
+    };
+    pub use iota_streams_core::tbits::Tbits;
+    pub use iota_streams_protobuf3::types::Trytes;
+}
+
+#[cfg(test)]
+mod test {
+    use super::prelude::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn prelude() -> Fallible<()> {
+        let mut transport = BucketTransport::new();
+        let mut author = AuthorBuilder::new("AUTHOR9SEED").mss_height(1).build();
+        let mut subscriber = SubscriberBuilder::new("SUBSCRIBER9SEED").build();
+
+        let announcement = author.announce()?;
+        transport.send_message(&announcement)?;
+        let msg = transport.recv_message(&announcement.link)?;
+        subscriber.unwrap_announcement(msg.parse_header()?)?;
+
+        let payload = Trytes(Tbits::from_str("PAYLOAD").unwrap());
+        let msg = author.tag_packet(&announcement.link, &payload, &Trytes::default())?;
+        let (public, _) = subscriber.unwrap_tagged_packet(msg.parse_header()?)?;
+        assert!(public == payload);
+        Ok(())
+    }
+}